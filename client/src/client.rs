@@ -0,0 +1,184 @@
+// filepath: client/src/client.rs
+//! Blocking client sending one request per connection.
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    num::NonZeroUsize,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::protocol::{Action, PROTOCOL_VERSION, Request, Response, Snapshot, socket_path};
+
+/// Longest wait for the timer to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("no timer listening on {}: {source}", path.display())]
+    Connect { path: PathBuf, source: io::Error },
+    #[error("talking to the timer failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid response from the timer: {0}")]
+    Protocol(#[from] serde_json::Error),
+    #[error("the timer speaks protocol version {server}, this client version {PROTOCOL_VERSION}")]
+    Version { server: u32 },
+    #[error("the timer refused the request: {0}")]
+    Rejected(String),
+}
+
+/// Client of the timer listening on a control socket.
+#[derive(Debug, Clone)]
+pub struct Client {
+    path: PathBuf,
+}
+
+impl Client {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Client { path: path.into() }
+    }
+
+    /// Client of the timer started with `--name <name>`.
+    pub fn named(name: &str) -> Self {
+        Client::new(socket_path(Some(name)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn status(&self) -> Result<Snapshot, ClientError> {
+        let response = self.request(Action::Status)?;
+        response
+            .status
+            .ok_or_else(|| ClientError::Rejected("no status in the response".to_string()))
+    }
+
+    pub fn pause(&self) -> Result<(), ClientError> {
+        self.command(Action::Pause)
+    }
+
+    pub fn resume(&self) -> Result<(), ClientError> {
+        self.command(Action::Resume)
+    }
+
+    pub fn toggle(&self) -> Result<(), ClientError> {
+        self.command(Action::Toggle)
+    }
+
+    pub fn skip(&self) -> Result<(), ClientError> {
+        self.command(Action::Skip)
+    }
+
+    pub fn reset(&self) -> Result<(), ClientError> {
+        self.command(Action::Reset)
+    }
+
+    /// Lengthens the running session by `by`, in whole seconds.
+    pub fn extend(&self, by: Duration) -> Result<(), ClientError> {
+        self.command(Action::Extend(by.as_secs()))
+    }
+
+    pub fn set_task(&self, task: &str) -> Result<(), ClientError> {
+        self.command(Action::SetTask(task.to_string()))
+    }
+
+    /// Leaves the running session for the session of the set at `position`, from 1.
+    pub fn jump(&self, position: NonZeroUsize) -> Result<(), ClientError> {
+        self.command(Action::Jump(position))
+    }
+
+    fn command(&self, action: Action) -> Result<(), ClientError> {
+        self.request(action).map(|_| ())
+    }
+
+    /// Follows the timer: the snapshots it sends, the first one right away and then one each
+    /// time the session changes, until it stops.
+    pub fn watch(&self) -> Result<Watch, ClientError> {
+        let mut reader = self.send(Action::Watch)?;
+        let first = read(&mut reader)?.ok_or_else(closed)?;
+        // The timer only writes when something changes.
+        reader.get_ref().set_read_timeout(None)?;
+        Ok(Watch {
+            reader,
+            first: Some(first),
+        })
+    }
+
+    /// Sends `action` and returns the response, failing on an error response.
+    pub fn request(&self, action: Action) -> Result<Response, ClientError> {
+        let mut reader = self.send(action)?;
+        read(&mut reader)?.ok_or_else(closed)
+    }
+
+    fn send(&self, action: Action) -> Result<BufReader<UnixStream>, ClientError> {
+        let mut stream =
+            UnixStream::connect(&self.path).map_err(|source| ClientError::Connect {
+                path: self.path.clone(),
+                source,
+            })?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut line = serde_json::to_string(&Request::new(action))?;
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+        Ok(BufReader::new(stream))
+    }
+}
+
+fn closed() -> ClientError {
+    ClientError::Io(io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Reads the next response, none when the timer closed the connection.
+fn read(reader: &mut BufReader<UnixStream>) -> Result<Option<Response>, ClientError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let response: Response = serde_json::from_str(&line)?;
+    if response.version != PROTOCOL_VERSION {
+        return Err(ClientError::Version {
+            server: response.version,
+        });
+    }
+    match response.error {
+        Some(message) => Err(ClientError::Rejected(message)),
+        None => Ok(Some(response)),
+    }
+}
+
+/// The snapshots sent by a watched timer, ending when the timer stops.
+#[derive(Debug)]
+pub struct Watch {
+    reader: BufReader<UnixStream>,
+    first: Option<Response>,
+}
+
+impl Watch {
+    /// Longest wait for the next snapshot, none to wait as long as the timer runs.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<(), ClientError> {
+        Ok(self.reader.get_ref().set_read_timeout(timeout)?)
+    }
+}
+
+impl Iterator for Watch {
+    type Item = Result<Snapshot, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let response = match self.first.take() {
+            Some(response) => response,
+            None => match read(&mut self.reader) {
+                Ok(Some(response)) => response,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        Some(
+            response
+                .status
+                .ok_or_else(|| ClientError::Rejected("no status in the response".to_string())),
+        )
+    }
+}
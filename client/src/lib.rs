@@ -0,0 +1,24 @@
+// filepath: client/src/lib.rs
+//! Typed client for the control socket of a running rustodoro timer, for the tools and bots
+//! driving it from another process.
+//!
+//! ```no_run
+//! use rustodoro_client::Client;
+//!
+//! let client = Client::new(rustodoro_client::default_socket_path());
+//! if let Some(session) = client.status()?.session {
+//!     println!("{:?}: {}s left", session.kind, session.remaining_secs);
+//! }
+//! client.skip()?;
+//! # Ok::<(), rustodoro_client::ClientError>(())
+//! ```
+#[cfg(unix)]
+mod client;
+pub mod protocol;
+
+#[cfg(unix)]
+pub use client::{Client, ClientError, Watch};
+pub use protocol::{
+    FocusState, PROTOCOL_VERSION, Session, SessionKind, Snapshot, Totals, default_socket_path,
+    socket_path,
+};
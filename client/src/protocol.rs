@@ -0,0 +1,309 @@
+// filepath: client/src/protocol.rs
+//! Messages exchanged with a running timer over its control socket.
+//!
+//! Every message is a JSON object on a single line. The client sends a [`Request`] and the timer
+//! answers with a [`Response`], both carrying the protocol version. A timer rejects requests of
+//! another version with an error response, fields added within a version must be optional.
+use std::{env, num::NonZeroUsize, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the protocol spoken by this crate.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Environment variable overriding the location of the control socket.
+pub const SOCKET_ENV: &str = "RUSTODORO_SOCKET";
+
+/// What a request asks the timer. Actions without payload are their names, e.g. `"skip"`, and
+/// the others objects, e.g. `{"extend":300}`, as the timer itself names its commands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Describe the running session.
+    Status,
+    Pause,
+    Resume,
+    /// Pause a running session, resume a paused one.
+    Toggle,
+    /// Skip the running break, or the next one at the very end of a work session.
+    Skip,
+    /// Start the running session over.
+    Reset,
+    /// Skip as many breaks, from the running one or else the upcoming one.
+    SkipBreaks(u32),
+    /// Lengthen the running session by as many seconds.
+    Extend(u64),
+    /// Label the work sessions with another task, from the running one on.
+    SetTask(String),
+    /// Leave the running session for the session of the set at this position, from 1.
+    Jump(NonZeroUsize),
+    /// Describe the running session, then again each time it changes, on the same connection.
+    Watch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub version: u32,
+    pub command: Action,
+}
+
+impl Request {
+    pub fn new(command: Action) -> Self {
+        Request {
+            version: PROTOCOL_VERSION,
+            command,
+        }
+    }
+}
+
+/// Answer of the timer. A response without status nor error acknowledges a command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Snapshot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub fn accepted() -> Self {
+        Response {
+            version: PROTOCOL_VERSION,
+            status: None,
+            error: None,
+        }
+    }
+
+    pub fn status(status: Snapshot) -> Self {
+        Response {
+            status: Some(status),
+            ..Response::accepted()
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Response {
+            error: Some(message.into()),
+            ..Response::accepted()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+/// State of the timer at a given time. There is no session while a session is being announced.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Name of the timer, none for the unnamed one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub session: Option<Session>,
+    #[serde(default)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub kind: SessionKind,
+    /// Work session of the cycle, from 1 to `cycles`.
+    pub cycle: u64,
+    pub cycles: u64,
+    pub duration_secs: u64,
+    pub remaining_secs: u64,
+    pub paused: bool,
+    /// Time the sessions of the day plan after this one take, up to the daily goal, none once it
+    /// is reached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan_secs: Option<u64>,
+}
+
+/// What the timer achieved since it started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Totals {
+    /// Work sessions completed.
+    pub pomodoros: u64,
+    /// Time counted by the work sessions which ended, completed or not.
+    pub work_secs: u64,
+    /// Work sessions completed today, those of the previous runs included. Starts over at local
+    /// midnight.
+    #[serde(default)]
+    pub today: u64,
+}
+
+/// Version of the protocol of the HTTP endpoints, for browsers and editors, part of their paths.
+pub const FOCUS_PROTOCOL_VERSION: u32 = 1;
+
+/// What `GET /v1/focus` on the browser endpoint returns, for the extensions blocking sites during
+/// the work sessions. Fields are only ever added to a version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusState {
+    pub version: u32,
+    /// Whether a work session is running, paused or not: sites are to be blocked.
+    pub active: bool,
+    /// The running session, none while a session is being announced.
+    pub session: Option<SessionKind>,
+    pub remaining_secs: Option<u64>,
+    pub paused: bool,
+}
+
+impl From<&Snapshot> for FocusState {
+    fn from(status: &Snapshot) -> Self {
+        let session = status.session.as_ref();
+        FocusState {
+            version: FOCUS_PROTOCOL_VERSION,
+            active: session.is_some_and(|session| session.kind == SessionKind::Work),
+            session: session.map(|session| session.kind),
+            remaining_secs: session.map(|session| session.remaining_secs),
+            paused: session.is_some_and(|session| session.paused),
+        }
+    }
+}
+
+/// What `GET /v1/status` on the HTTP endpoint returns, for the editors showing the countdown in
+/// their status line. Flat, so that a plugin reads it in a few lines. Fields are only ever added to
+/// a version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorStatus {
+    pub version: u32,
+    /// The running session, none while a session is being announced.
+    pub session: Option<SessionKind>,
+    /// Work session of the cycle, from 1 to `cycles`.
+    pub cycle: Option<u64>,
+    pub cycles: Option<u64>,
+    pub duration_secs: Option<u64>,
+    pub remaining_secs: Option<u64>,
+    pub paused: bool,
+    /// Work sessions completed today.
+    pub today: u64,
+    /// The session and its countdown in a few characters, e.g. `🍅 12:34`, empty between two
+    /// sessions.
+    pub label: String,
+}
+
+impl From<&Snapshot> for EditorStatus {
+    fn from(status: &Snapshot) -> Self {
+        let session = status.session.as_ref();
+        let label = session.map_or_else(String::new, |session| {
+            let icon = match session.kind {
+                SessionKind::Work => "🍅",
+                SessionKind::ShortBreak | SessionKind::LongBreak => "☕",
+            };
+            format!(
+                "{} {:02}:{:02}{}",
+                icon,
+                session.remaining_secs / 60,
+                session.remaining_secs % 60,
+                if session.paused { " ⏸" } else { "" }
+            )
+        });
+        EditorStatus {
+            version: FOCUS_PROTOCOL_VERSION,
+            session: session.map(|session| session.kind),
+            cycle: session.map(|session| session.cycle),
+            cycles: session.map(|session| session.cycles),
+            duration_secs: session.map(|session| session.duration_secs),
+            remaining_secs: session.map(|session| session.remaining_secs),
+            paused: session.is_some_and(|session| session.paused),
+            today: status.totals.today,
+            label,
+        }
+    }
+}
+
+/// Where the unnamed timer listens: `$RUSTODORO_SOCKET`, else `rustodoro.sock` in
+/// `$XDG_RUNTIME_DIR`, else a per-user socket in the temporary directory.
+pub fn default_socket_path() -> PathBuf {
+    socket_path(None)
+}
+
+/// Where the timer called `name` listens, the unnamed one when none. A named timer listens on
+/// `rustodoro-<name>.sock` next to the default socket, `$RUSTODORO_SOCKET` only moving the socket of
+/// the unnamed timer.
+pub fn socket_path(name: Option<&str>) -> PathBuf {
+    if name.is_none()
+        && let Some(path) = env::var_os(SOCKET_ENV).filter(|path| !path.is_empty())
+    {
+        return PathBuf::from(path);
+    }
+    let suffix = name.map(|name| format!("-{}", name)).unwrap_or_default();
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir).join(format!("rustodoro{}.sock", suffix));
+    }
+    let user = env::var("USER").unwrap_or_default();
+    env::temp_dir().join(format!("rustodoro-{}{}.sock", user, suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_single_json_lines() {
+        let line = serde_json::to_string(&Request::new(Action::Skip)).unwrap();
+        assert_eq!(line, r#"{"version":1,"command":"skip"}"#);
+        let line = serde_json::to_string(&Request::new(Action::Extend(300))).unwrap();
+        assert_eq!(line, r#"{"version":1,"command":{"extend":300}}"#);
+        let request: Request =
+            serde_json::from_str(r#"{"version":1,"command":{"jump":3}}"#).unwrap();
+        assert_eq!(request.command, Action::Jump(NonZeroUsize::new(3).unwrap()));
+        assert!(serde_json::from_str::<Request>(r#"{"version":1,"command":{"jump":0}}"#).is_err());
+    }
+
+    #[test]
+    fn responses_carry_only_what_they_need() {
+        assert_eq!(
+            serde_json::to_string(&Response::accepted()).unwrap(),
+            r#"{"version":1}"#
+        );
+        let status = Snapshot {
+            name: None,
+            session: Some(Session {
+                kind: SessionKind::ShortBreak,
+                cycle: 2,
+                cycles: 4,
+                duration_secs: 300,
+                remaining_secs: 120,
+                paused: false,
+                plan_secs: None,
+            }),
+            totals: Totals {
+                pomodoros: 1,
+                work_secs: 1500,
+                today: 3,
+            },
+        };
+        let line = serde_json::to_string(&Response::status(status.clone())).unwrap();
+        assert_eq!(
+            line,
+            r#"{"version":1,"status":{"session":{"kind":"short_break","cycle":2,"cycles":4,"duration_secs":300,"remaining_secs":120,"paused":false},"totals":{"pomodoros":1,"work_secs":1500,"today":3}}}"#
+        );
+        let response: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(response.status, Some(status.clone()));
+        // Timers which do not send the totals yet.
+        let older: Snapshot = serde_json::from_str(r#"{"session":null}"#).unwrap();
+        assert_eq!(older, Snapshot::default());
+        let named: Snapshot = serde_json::from_str(r#"{"name":"writing","session":null}"#).unwrap();
+        assert_eq!(named.name.as_deref(), Some("writing"));
+
+        assert_eq!(
+            serde_json::to_string(&FocusState::from(&status)).unwrap(),
+            r#"{"version":1,"active":false,"session":"short_break","remaining_secs":120,"paused":false}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&FocusState::from(&Snapshot::default())).unwrap(),
+            r#"{"version":1,"active":false,"session":null,"remaining_secs":null,"paused":false}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&EditorStatus::from(&status)).unwrap(),
+            r#"{"version":1,"session":"short_break","cycle":2,"cycles":4,"duration_secs":300,"remaining_secs":120,"paused":false,"today":3,"label":"☕ 02:00"}"#
+        );
+        assert_eq!(EditorStatus::from(&Snapshot::default()).label, "");
+    }
+}
@@ -0,0 +1,419 @@
+// filepath: src/achievements.rs
+//! Module awarding achievements to the user of a Pomodoro timer application. Each achievement is
+//! a rule over the session history, such as a hundred pomodoros or seven days in a row, checked
+//! whenever a session ends: the ones unlocked are announced with a notification and listed by
+//! `rustodoro achievements`.
+//!
+//! The achievements unlocked are kept in a file next to the history with the time they were
+//! reached, so that pruning the history never takes one back.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc::Receiver},
+};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Cli,
+    desktop::Notification,
+    events::Event,
+    history::{
+        HISTORY_ENV, History, HistoryError, Recorder, SessionKind, SessionRecord, history_path,
+    },
+    quiet::Quiet,
+    settings::SharedSettings,
+};
+
+/// Pomodoros to complete for the centurion.
+const CENTURY: usize = 100;
+/// Days in a row with a pomodoro for the streak.
+const STREAK_DAYS: u64 = 7;
+/// Pomodoros without an interruption to complete in a day for the flawless day.
+const FLAWLESS_POMODOROS: usize = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AchievementError {
+    #[error("cannot access the achievements {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("invalid achievements {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Achievement {
+    FirstPomodoro,
+    Century,
+    WeekStreak,
+    FlawlessDay,
+}
+
+impl Achievement {
+    const ALL: [Achievement; 4] = [
+        Achievement::FirstPomodoro,
+        Achievement::Century,
+        Achievement::WeekStreak,
+        Achievement::FlawlessDay,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Achievement::FirstPomodoro => "First Pomodoro",
+            Achievement::Century => "Centurion",
+            Achievement::WeekStreak => "Seven in a Row",
+            Achievement::FlawlessDay => "Flawless Day",
+        }
+    }
+
+    /// What the achievement is awarded for.
+    fn description(self) -> String {
+        match self {
+            Achievement::FirstPomodoro => "a first pomodoro".to_string(),
+            Achievement::Century => format!("{} pomodoros", CENTURY),
+            Achievement::WeekStreak => format!("pomodoros on {} days in a row", STREAK_DAYS),
+            Achievement::FlawlessDay => format!(
+                "{} pomodoros in a day without a pause, a reset or an abandoned session",
+                FLAWLESS_POMODOROS
+            ),
+        }
+    }
+
+    /// When `records`, oldest first, first met the rule of the achievement, if ever.
+    fn reached(self, records: &[SessionRecord]) -> Option<DateTime<Local>> {
+        let work = records
+            .iter()
+            .filter(|record| record.kind == SessionKind::Work);
+        let mut pomodoros = work.clone().filter(|record| record.outcome.is_done());
+        match self {
+            Achievement::FirstPomodoro => pomodoros.next().map(|record| record.started_at),
+            Achievement::Century => pomodoros.nth(CENTURY - 1).map(|record| record.started_at),
+            Achievement::WeekStreak => {
+                let mut last: Option<NaiveDate> = None;
+                let mut streak = 0;
+                for record in pomodoros {
+                    let day = record.started_at.date_naive();
+                    if last == Some(day) {
+                        continue;
+                    }
+                    streak = match last {
+                        Some(last) if last.succ_opt() == Some(day) => streak + 1,
+                        _ => 1,
+                    };
+                    last = Some(day);
+                    if streak == STREAK_DAYS {
+                        return Some(record.started_at);
+                    }
+                }
+                None
+            }
+            Achievement::FlawlessDay => {
+                let mut day = None;
+                let mut flawless = 0;
+                let mut spoilt = false;
+                for record in work {
+                    if day != Some(record.started_at.date_naive()) {
+                        day = Some(record.started_at.date_naive());
+                        flawless = 0;
+                        spoilt = false;
+                    }
+                    if !record.outcome.is_done() || record.pauses > 0 || record.resets > 0 {
+                        spoilt = true;
+                        continue;
+                    }
+                    flawless += 1;
+                    if !spoilt && flawless == FLAWLESS_POMODOROS {
+                        return Some(record.started_at);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+/// An achievement with when it was reached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unlocked {
+    pub achievement: Achievement,
+    pub at: DateTime<Local>,
+}
+
+/// Adds the achievements `records`, oldest first, unlock to `unlocked`, and returns them.
+pub fn check(unlocked: &mut Vec<Unlocked>, records: &[SessionRecord]) -> Vec<Achievement> {
+    let mut new = Vec::new();
+    for achievement in Achievement::ALL {
+        if unlocked
+            .iter()
+            .any(|unlocked| unlocked.achievement == achievement)
+        {
+            continue;
+        }
+        if let Some(at) = achievement.reached(records) {
+            unlocked.push(Unlocked { achievement, at });
+            new.push(achievement);
+        }
+    }
+    new
+}
+
+/// The file of the achievements unlocked, next to the history.
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    /// The store of the achievements of `history`, a named timer keeping its own.
+    pub fn of(history: &Path) -> Self {
+        Store {
+            path: history.with_extension("achievements.json"),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn io_error(&self, source: io::Error) -> AchievementError {
+        AchievementError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// The achievements unlocked, a missing file meaning there is none.
+    pub fn load(&self) -> Result<Vec<Unlocked>, AchievementError> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => {
+                serde_json::from_str(&content).map_err(|source| AchievementError::Parse {
+                    path: self.path.clone(),
+                    source,
+                })
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(self.io_error(e)),
+        }
+    }
+
+    pub fn save(&self, unlocked: &[Unlocked]) -> Result<(), AchievementError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| self.io_error(e))?;
+        }
+        let content =
+            serde_json::to_string_pretty(unlocked).expect("achievements always serialize");
+        fs::write(&self.path, content + "\n").map_err(|e| self.io_error(e))
+    }
+}
+
+/// Checks the rules as the sessions of a run end, announcing the achievements they unlock.
+pub struct Tracker {
+    store: Store,
+    unlocked: Vec<Unlocked>,
+    records: Vec<SessionRecord>,
+    recorder: Recorder,
+    settings: SharedSettings,
+    quiet: Option<Arc<Quiet>>,
+}
+
+impl Tracker {
+    /// A tracker starting from `history`. The achievements reached before the run, e.g. in an
+    /// imported history, are kept without being announced.
+    pub fn new(
+        history: &History,
+        store: Store,
+        settings: SharedSettings,
+    ) -> Result<Self, AchievementError> {
+        let records = history.sessions()?;
+        let mut unlocked = store.load()?;
+        if !check(&mut unlocked, &records).is_empty() {
+            store.save(&unlocked)?;
+        }
+        Ok(Tracker {
+            store,
+            unlocked,
+            records,
+            // The records of the run are only needed in memory, the recorder of the run writes
+            // the history.
+            recorder: Recorder::new(None, None),
+            settings,
+            quiet: None,
+        })
+    }
+
+    /// Holds the announcements back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    /// Handles the events until the event bus is dropped, and returns the first failure to save
+    /// the achievements, if any.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<AchievementError> {
+        let mut failure = None;
+        for event in events {
+            let unlocked = match self.handle(&event) {
+                Ok(unlocked) => unlocked,
+                Err(e) => {
+                    failure.get_or_insert(e);
+                    continue;
+                }
+            };
+            if self.settings.lock().unwrap().no_notify
+                || self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+            {
+                continue;
+            }
+            for achievement in unlocked {
+                // The timer already reports notification failures.
+                let _ = Notification::new()
+                    .summary("Pomodoro Timer")
+                    .body(&format!(
+                        "Achievement unlocked: {}, for {}!",
+                        achievement.title(),
+                        achievement.description()
+                    ))
+                    .icon("starred")
+                    .show();
+            }
+        }
+        failure
+    }
+
+    /// Returns the achievements unlocked by the session `event` ends, if any.
+    pub fn handle(&mut self, event: &Event) -> Result<Vec<Achievement>, AchievementError> {
+        // Without a history, the recorder cannot fail.
+        let _ = self.recorder.handle(event);
+        if !matches!(event, Event::SessionEnded { .. }) {
+            return Ok(Vec::new());
+        }
+        self.records.extend(self.recorder.take());
+        let unlocked = check(&mut self.unlocked, &self.records);
+        if !unlocked.is_empty() {
+            self.store.save(&self.unlocked)?;
+        }
+        Ok(unlocked)
+    }
+}
+
+/// Lists the achievements, the ones unlocked with the day they were reached.
+fn render(unlocked: &[Unlocked]) -> String {
+    let width = Achievement::ALL
+        .iter()
+        .map(|achievement| achievement.title().len())
+        .max()
+        .unwrap_or(0);
+    let mut rendered = format!(
+        "Achievements: {}/{} unlocked\n",
+        unlocked.len(),
+        Achievement::ALL.len()
+    );
+    for achievement in Achievement::ALL {
+        let at = unlocked
+            .iter()
+            .find(|unlocked| unlocked.achievement == achievement)
+            .map(|unlocked| unlocked.at);
+        let line = format!(
+            "  {} {:<width$}  {}{}",
+            if at.is_some() { "✓" } else { "·" },
+            achievement.title(),
+            achievement.description(),
+            at.map(|at| format!(", on {}", at.format("%Y-%m-%d")))
+                .unwrap_or_default(),
+            width = width
+        );
+        rendered.push_str(&line);
+        rendered.push('\n');
+    }
+    rendered
+}
+
+fn run_action(cli: &Cli) -> Result<String, AchievementError> {
+    let path = history_path(cli.history.as_deref(), cli.name.as_deref())
+        .ok_or(AchievementError::NoHistory)?;
+    let store = Store::of(&path);
+    let mut unlocked = store.load()?;
+    if !check(&mut unlocked, &History::new(path).sessions()?).is_empty() {
+        store.save(&unlocked)?;
+    }
+    Ok(render(&unlocked))
+}
+
+/// Runs the `achievements` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli) -> i32 {
+    match run_action(cli) {
+        Ok(rendered) => {
+            print!("{}", rendered);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, TimeZone};
+
+    use super::*;
+    use crate::events::SessionOutcome;
+
+    fn work(day: u64, hour: u32, outcome: SessionOutcome, pauses: u32) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local.with_ymd_and_hms(2026, 10, 1, hour, 0, 0).unwrap() + Days::new(day),
+            kind: SessionKind::Work,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome,
+            pauses,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: None,
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn follows_the_rules_over_the_history() {
+        // A pomodoro a day for a week, the one of the fourth day paused.
+        let mut records: Vec<SessionRecord> = (0..7)
+            .map(|day| work(day, 9, SessionOutcome::Completed, u32::from(day == 3)))
+            .collect();
+        records.insert(2, work(1, 10, SessionOutcome::Interrupted, 0));
+        let mut unlocked = Vec::new();
+        assert_eq!(
+            check(&mut unlocked, &records),
+            [Achievement::FirstPomodoro, Achievement::WeekStreak]
+        );
+        assert_eq!(unlocked[1].at, records[7].started_at);
+        assert_eq!(check(&mut unlocked, &records), []);
+
+        // Four pomodoros after an abandoned one on a day do not count, four on the next day do.
+        records.push(work(8, 9, SessionOutcome::Interrupted, 0));
+        records.extend((10..14).map(|hour| work(8, hour, SessionOutcome::Completed, 0)));
+        assert_eq!(check(&mut unlocked, &records), []);
+        records.extend((10..14).map(|hour| work(9, hour, SessionOutcome::FinishedEarly, 0)));
+        assert_eq!(check(&mut unlocked, &records), [Achievement::FlawlessDay]);
+        assert_eq!(unlocked[2].at, records.last().unwrap().started_at);
+        let rendered = render(&unlocked);
+        assert!(rendered.starts_with("Achievements: 3/4 unlocked\n"));
+        assert!(rendered.contains("  · Centurion       100 pomodoros\n"));
+        assert!(rendered.contains("  ✓ First Pomodoro  a first pomodoro, on 2026-10-01\n"));
+    }
+}
@@ -0,0 +1,289 @@
+// filepath: src/aggregate.rs
+//! Module aggregating the session history of a Pomodoro timer application per day, hour and task,
+//! for the reports.
+use std::{cmp::Reverse, collections::BTreeMap, time::Duration};
+
+use chrono::{Datelike, NaiveDate, Timelike};
+
+use crate::history::{PauseReason, Review, SessionKind, SessionRecord};
+
+/// Label of the work sessions started without `--task`.
+pub const NO_TASK: &str = "(no task)";
+
+/// The work sessions of the history, the breaks being left out of every aggregate.
+fn work(records: &[SessionRecord]) -> impl Iterator<Item = &SessionRecord> {
+    records
+        .iter()
+        .filter(|record| record.kind == SessionKind::Work)
+}
+
+/// Completed pomodoros per day from `first` to `last` included, days without any being counted as
+/// zero.
+pub fn pomodoros_per_day(
+    records: &[SessionRecord],
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Vec<(NaiveDate, u64)> {
+    let mut days: BTreeMap<NaiveDate, u64> = first
+        .iter_days()
+        .take_while(|day| *day <= last)
+        .map(|day| (day, 0))
+        .collect();
+    for record in work(records).filter(|record| record.outcome.is_done()) {
+        if let Some(count) = days.get_mut(&record.started_at.date_naive()) {
+            *count += 1;
+        }
+    }
+    days.into_iter().collect()
+}
+
+/// Focused time per weekday (Monday first) and hour of the day the sessions started at, for the
+/// sessions started from `first` on.
+pub fn focus_per_hour(records: &[SessionRecord], first: NaiveDate) -> [[Duration; 24]; 7] {
+    let mut grid = [[Duration::ZERO; 24]; 7];
+    for record in work(records).filter(|record| record.started_at.date_naive() >= first) {
+        let weekday = record.started_at.weekday().num_days_from_monday() as usize;
+        let hour = record.started_at.hour() as usize;
+        grid[weekday][hour] += record.elapsed();
+    }
+    grid
+}
+
+/// Average focus score of the work sessions started from `first` on, if any was scored.
+pub fn average_focus(records: &[SessionRecord], first: NaiveDate) -> Option<u64> {
+    let scores: Vec<u64> = work(records)
+        .filter(|record| record.started_at.date_naive() >= first)
+        .filter_map(|record| record.focus)
+        .collect();
+    (!scores.is_empty())
+        .then(|| (scores.iter().sum::<u64>() as f64 / scores.len() as f64).round() as u64)
+}
+
+/// Focused time per task for the sessions started from `first` on, the longest first.
+pub fn focus_per_task(records: &[SessionRecord], first: NaiveDate) -> Vec<(String, Duration)> {
+    let mut tasks: BTreeMap<&str, Duration> = BTreeMap::new();
+    for record in work(records).filter(|record| record.started_at.date_naive() >= first) {
+        *tasks
+            .entry(record.task.as_deref().unwrap_or(NO_TASK))
+            .or_default() += record.elapsed();
+    }
+    let mut tasks: Vec<(String, Duration)> = tasks
+        .into_iter()
+        .map(|(task, focused)| (task.to_string(), focused))
+        .collect();
+    tasks.sort_by_key(|(_, focused)| Reverse(*focused));
+    tasks
+}
+
+/// How the intents of the work sessions of a task went.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Reviews {
+    pub done: usize,
+    pub partial: usize,
+    pub missed: usize,
+}
+
+impl Reviews {
+    pub fn count(&self) -> usize {
+        self.done + self.partial + self.missed
+    }
+
+    /// Share of the intents carried out, in percent, a partial one counting half.
+    pub fn completion(&self) -> u64 {
+        let done = self.done as f64 + self.partial as f64 / 2.0;
+        (done * 100.0 / self.count().max(1) as f64).round() as u64
+    }
+}
+
+/// Reviews of the work sessions started from `first` on per task, the most reviewed first.
+pub fn reviews_per_task(records: &[SessionRecord], first: NaiveDate) -> Vec<(String, Reviews)> {
+    let mut tasks: BTreeMap<&str, Reviews> = BTreeMap::new();
+    for record in work(records).filter(|record| record.started_at.date_naive() >= first) {
+        let Some(review) = record.review else {
+            continue;
+        };
+        let reviews = tasks
+            .entry(record.task.as_deref().unwrap_or(NO_TASK))
+            .or_default();
+        match review {
+            Review::Done => reviews.done += 1,
+            Review::Partial => reviews.partial += 1,
+            Review::Missed => reviews.missed += 1,
+        }
+    }
+    let mut tasks: Vec<(String, Reviews)> = tasks
+        .into_iter()
+        .map(|(task, reviews)| (task.to_string(), reviews))
+        .collect();
+    tasks.sort_by_key(|(_, reviews)| Reverse(reviews.count()));
+    tasks
+}
+
+/// Pauses of the work sessions started from `first` on, with how many were for each reason, the
+/// most frequent first.
+pub fn pause_reasons(
+    records: &[SessionRecord],
+    first: NaiveDate,
+) -> (u64, Vec<(PauseReason, usize)>) {
+    let mut pauses = 0;
+    let mut reasons: Vec<(PauseReason, usize)> =
+        PauseReason::ALL.iter().map(|reason| (*reason, 0)).collect();
+    for record in work(records).filter(|record| record.started_at.date_naive() >= first) {
+        pauses += u64::from(record.pauses);
+        for reason in &record.pause_reasons {
+            if let Some((_, count)) = reasons.iter_mut().find(|(known, _)| known == reason) {
+                *count += 1;
+            }
+        }
+    }
+    reasons.retain(|(_, count)| *count > 0);
+    reasons.sort_by_key(|(_, count)| Reverse(*count));
+    (pauses, reasons)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+    use crate::events::SessionOutcome;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn record(day: u32, hour: u32, kind: SessionKind, task: Option<&str>) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap(),
+            kind,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: task.map(str::to_string),
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    #[test]
+    fn counts_pomodoros_on_every_day_of_the_range() {
+        let records = [
+            record(10, 9, SessionKind::Work, None),
+            record(10, 10, SessionKind::ShortBreak, None),
+            record(12, 9, SessionKind::Work, None),
+            record(20, 9, SessionKind::Work, None),
+        ];
+        assert_eq!(
+            pomodoros_per_day(&records, date(10), date(12)),
+            vec![(date(10), 1), (date(11), 0), (date(12), 1)]
+        );
+    }
+
+    #[test]
+    fn spreads_focus_over_weekdays_and_hours() {
+        // 2026-03-16 is a Monday.
+        let records = [
+            record(16, 9, SessionKind::Work, None),
+            record(16, 9, SessionKind::Work, None),
+            record(17, 14, SessionKind::Work, None),
+            record(2, 9, SessionKind::Work, None),
+        ];
+        let grid = focus_per_hour(&records, date(10));
+        assert_eq!(grid[0][9], MINUTE * 50);
+        assert_eq!(grid[1][14], MINUTE * 25);
+        assert_eq!(grid.iter().flatten().sum::<Duration>(), MINUTE * 75);
+    }
+
+    #[test]
+    fn sorts_tasks_by_focused_time() {
+        let records = [
+            record(10, 9, SessionKind::Work, Some("docs")),
+            record(10, 10, SessionKind::Work, Some("review")),
+            record(10, 11, SessionKind::Work, Some("review")),
+            record(10, 12, SessionKind::Work, None),
+        ];
+        assert_eq!(
+            focus_per_task(&records, date(1)),
+            vec![
+                ("review".to_string(), MINUTE * 50),
+                (NO_TASK.to_string(), MINUTE * 25),
+                ("docs".to_string(), MINUTE * 25),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_the_pause_reasons() {
+        let paused = |reasons: &[PauseReason]| SessionRecord {
+            pauses: reasons.len() as u32 + 1,
+            pause_reasons: reasons.to_vec(),
+            ..record(10, 9, SessionKind::Work, None)
+        };
+        let records = [
+            paused(&[PauseReason::Bio]),
+            paused(&[PauseReason::Interruption, PauseReason::Interruption]),
+            SessionRecord {
+                kind: SessionKind::ShortBreak,
+                ..paused(&[PauseReason::Call])
+            },
+        ];
+        assert_eq!(
+            pause_reasons(&records, date(1)),
+            (
+                5,
+                vec![(PauseReason::Interruption, 2), (PauseReason::Bio, 1)]
+            )
+        );
+        assert_eq!(pause_reasons(&records, date(11)), (0, vec![]));
+    }
+
+    #[test]
+    fn rates_the_intents_per_task() {
+        let reviewed = |task, review| SessionRecord {
+            review: Some(review),
+            ..record(10, 9, SessionKind::Work, task)
+        };
+        let records = [
+            reviewed(Some("Report"), Review::Done),
+            reviewed(Some("Report"), Review::Partial),
+            reviewed(Some("Report"), Review::Missed),
+            reviewed(None, Review::Done),
+            record(10, 10, SessionKind::Work, Some("Inbox")),
+        ];
+        let tasks = reviews_per_task(&records, date(1));
+        assert_eq!(
+            tasks,
+            [
+                (
+                    "Report".to_string(),
+                    Reviews {
+                        done: 1,
+                        partial: 1,
+                        missed: 1
+                    }
+                ),
+                (
+                    NO_TASK.to_string(),
+                    Reviews {
+                        done: 1,
+                        ..Reviews::default()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(tasks[0].1.completion(), 50);
+        assert_eq!(tasks[1].1.completion(), 100);
+        assert!(reviews_per_task(&records, date(11)).is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+// filepath: src/alerts.rs
+//! Module handling the sounds and desktop notifications of a Pomodoro timer application.
+use std::{process::Child, sync::Arc};
+
+use tokio::time::Instant;
+
+use crate::AppError;
+use crate::audio::{Output, Switch};
+use crate::desktop::Notification;
+use crate::haptics::Haptics;
+use crate::platform;
+use crate::quiet::Quiet;
+use crate::settings::Settings;
+use crate::sounds::{Cue, Repeats, Sound, SoundsConfig};
+
+/// Audio output and notification state, created once for the whole run.
+pub struct Alerts {
+    audio: Option<Output>,
+    /// The output device chosen, the default one when `None`.
+    device: Option<String>,
+    /// Whether the sounds play through the programs of the platform, under WSL or Termux.
+    host: bool,
+    /// The player of the last sound played through them.
+    player: Option<Child>,
+    sounds: SoundsConfig,
+    haptics: Option<Haptics>,
+    /// The sound ringing again until acknowledged.
+    repeats: Option<Repeats>,
+    notification_failed: bool,
+    quiet: Option<Arc<Quiet>>,
+}
+
+impl Alerts {
+    /// Opens the audio output right away if the sound is enabled, so a missing device is reported
+    /// before the first session starts.
+    pub fn new(settings: &Settings) -> Result<Self, AppError> {
+        let mut alerts = Alerts {
+            audio: None,
+            device: settings.audio_device.clone(),
+            host: platform::current() != platform::Platform::Native,
+            player: None,
+            sounds: settings.sounds.clone(),
+            haptics: settings.vibrations.as_ref().and_then(Haptics::detect),
+            repeats: None,
+            notification_failed: false,
+            quiet: None,
+        };
+        if !settings.no_sound && !alerts.host {
+            alerts.audio()?;
+        }
+        Ok(alerts)
+    }
+
+    /// Holds the sounds and notifications back whenever `quiet` says so.
+    pub fn set_quiet(&mut self, quiet: Arc<Quiet>) {
+        self.quiet = Some(quiet);
+    }
+
+    /// Whether the sounds and notifications are held back right now.
+    pub fn is_quiet(&self) -> bool {
+        self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+    }
+
+    /// Returns the audio output, opening it the first time the sound is needed, e.g. after being
+    /// enabled from the settings menu.
+    fn audio(&mut self) -> Result<&mut Output, AppError> {
+        if self.audio.is_none() {
+            self.audio = Some(Output::open(self.device.as_deref())?);
+        }
+        Ok(self.audio.as_mut().unwrap())
+    }
+
+    /// Starts the sound of `cue`, returning the change of device if its device went away or came
+    /// back since the last sound. The sink plays it on its own thread, so the timer doesn't wait
+    /// for it to finish. The end of the breaks then rings again until acknowledged, if configured
+    /// to.
+    pub fn play(&mut self, cue: Cue) -> Result<Option<Switch>, AppError> {
+        let switch = self.play_at(cue, 1.0)?;
+        if let Some(repeats) = self.sounds.repeats(cue, Instant::now())
+            && self.sounds.sound(cue) != &Sound::None
+            && !self.is_quiet()
+        {
+            self.repeats = Some(repeats);
+        }
+        Ok(switch)
+    }
+
+    /// Starts the vibration of `cue`, if there are vibrations.
+    pub fn vibrate(&self, cue: Cue) {
+        if let Some(haptics) = &self.haptics
+            && !self.is_quiet()
+        {
+            haptics.play(cue);
+        }
+    }
+
+    fn play_at(&mut self, cue: Cue, volume: f32) -> Result<Option<Switch>, AppError> {
+        let sound = self.sounds.sound(cue).clone();
+        if sound == Sound::None || self.is_quiet() {
+            return Ok(None);
+        }
+        if self.host {
+            // The platform plays the sound as it is, without fading in nor getting louder.
+            self.player = platform::play(&sound);
+            return Ok(None);
+        }
+        let fade_in = self.sounds.fade_in();
+        let audio = self.audio()?;
+        let switch = audio.refresh()?;
+        audio.play(&sound, fade_in, volume)?;
+        Ok(switch)
+    }
+
+    /// When the sound ringing until acknowledged rings next, if there is one.
+    pub fn next_repeat(&self) -> Option<Instant> {
+        self.repeats.as_ref().map(Repeats::next)
+    }
+
+    /// Plays the repeat of the sound due by now, if any, louder than the previous one.
+    pub fn repeat(&mut self) -> Result<Option<Switch>, AppError> {
+        let Some(repeats) = &mut self.repeats else {
+            return Ok(None);
+        };
+        let cue = repeats.cue;
+        let volume = repeats.due(Instant::now());
+        if repeats.is_over() {
+            self.repeats = None;
+        }
+        match volume {
+            Some(volume) => self.play_at(cue, volume),
+            None => Ok(None),
+        }
+    }
+
+    /// Stops the sound ringing until acknowledged, returning whether there was one.
+    pub fn acknowledge(&mut self) -> bool {
+        self.repeats.take().is_some()
+    }
+
+    /// Waits for the sounds started to finish playing.
+    pub fn wait_for_sound(&mut self) {
+        if let Some(audio) = &self.audio {
+            audio.wait();
+        }
+        if let Some(player) = &mut self.player {
+            let _ = player.wait();
+        }
+    }
+
+    /// Shows a desktop notification. Only the first failure is returned, as reporting it once per
+    /// run is enough.
+    pub async fn notify(&mut self, message: &str) -> Result<(), AppError> {
+        if self.is_quiet() {
+            return Ok(());
+        }
+        // Talking to the notification daemon blocks, so it must not happen on the runtime thread.
+        let message = message.to_string();
+        let result = tokio::task::spawn_blocking(move || {
+            Notification::new()
+                .summary("Pomodoro Timer")
+                .body(&message)
+                .icon("dialog-information")
+                .show()
+                .map(|_| ())
+        })
+        .await
+        .map_err(|_| AppError::ThreadPanic)?;
+        match result {
+            Ok(()) => Ok(()),
+            Err(_) if self.notification_failed => Ok(()),
+            Err(e) => {
+                self.notification_failed = true;
+                Err(e.into())
+            }
+        }
+    }
+}
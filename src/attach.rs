@@ -0,0 +1,137 @@
+// filepath: src/attach.rs
+//! Module showing the progress of a Pomodoro timer running in another process, following the
+//! snapshots it sends over its control socket. The viewer only reads: quitting it, with Ctrl+C,
+//! leaves the timer running.
+use indicatif::ProgressBar;
+use rustodoro_client::{Client, ClientError, Session, SessionKind, Snapshot};
+
+use crate::{
+    SessionType,
+    config::{self, Cli, ConfigError},
+    control,
+    render::{self, Color, Renderer},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttachError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("invalid progress bar template: {0}")]
+    Template(#[from] indicatif::style::TemplateError),
+}
+
+/// The session shown, and its progress bar.
+struct View {
+    renderer: Renderer,
+    shown: Option<(SessionKind, u64, ProgressBar)>,
+}
+
+impl View {
+    fn show(&mut self, session: &Session) -> Result<(), AttachError> {
+        let current = self
+            .shown
+            .as_ref()
+            .is_some_and(|(kind, cycle, _)| (*kind, *cycle) == (session.kind, session.cycle));
+        if !current {
+            self.clear();
+            print!(
+                "{}",
+                self.renderer.transition(
+                    &SessionType::from(session.kind),
+                    session.cycle,
+                    session.cycles,
+                    None
+                )
+            );
+            let progress_bar = ProgressBar::new(session.duration_secs);
+            progress_bar.set_style(render::progress_style(render::columns(), Color::default())?);
+            self.shown = Some((session.kind, session.cycle, progress_bar));
+        }
+        if let Some((_, _, progress_bar)) = &self.shown {
+            progress_bar.set_message(message(session));
+            progress_bar.set_position(session.duration_secs.saturating_sub(session.remaining_secs));
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        if let Some((_, _, progress_bar)) = self.shown.take() {
+            progress_bar.finish_and_clear();
+        }
+    }
+}
+
+/// What the progress bar of `session` says.
+fn message(session: &Session) -> String {
+    format!(
+        "{} (#{}/{}){}",
+        SessionType::from(session.kind),
+        session.cycle,
+        session.cycles,
+        if session.paused { ", paused" } else { "" }
+    )
+}
+
+fn attach(cli: &Cli) -> Result<Snapshot, AttachError> {
+    let theme = config::resolve(cli)?.theme.value;
+    let client = Client::new(control::socket_path(cli));
+    let mut watch = client.watch()?;
+    println!(
+        "Attached to the timer on {}, Ctrl+C detaches.",
+        client.path().display()
+    );
+    let mut view = View {
+        renderer: Renderer::new(theme),
+        shown: None,
+    };
+    let mut last = Snapshot::default();
+    for snapshot in &mut watch {
+        last = snapshot?;
+        match &last.session {
+            Some(session) => view.show(session)?,
+            None => view.clear(),
+        }
+    }
+    view.clear();
+    Ok(last)
+}
+
+/// Runs the `attach` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli) -> i32 {
+    match attach(cli) {
+        Ok(last) => {
+            println!(
+                "\nThe timer stopped, {} pomodoros done.",
+                last.totals.pomodoros
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_progress_bar_tells_a_pause() {
+        let mut session = Session {
+            kind: SessionKind::Work,
+            cycle: 2,
+            cycles: 4,
+            duration_secs: 1500,
+            remaining_secs: 600,
+            paused: false,
+            plan_secs: None,
+        };
+        assert_eq!(message(&session), "Work session (#2/4)");
+        session.paused = true;
+        assert_eq!(message(&session), "Work session (#2/4), paused");
+    }
+}
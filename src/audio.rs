@@ -0,0 +1,315 @@
+// filepath: src/audio.rs
+//! Module choosing the audio output of a Pomodoro timer application, and keeping the sound working
+//! when its device goes away mid-run, e.g. when undocking: the sound then plays on the default
+//! device, and goes back to the chosen one when it comes back. Built without the audio feature,
+//! e.g. for a server or WSL without ALSA, there is no audio output to open.
+#[cfg(feature = "audio")]
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::{fmt, path::PathBuf, time::Duration};
+
+#[cfg(feature = "audio")]
+use rodio::{
+    Device, OutputStream, OutputStreamBuilder, Sink,
+    cpal::traits::{DeviceTrait, HostTrait},
+};
+
+use crate::{
+    config::{self, Cli},
+    sounds::Sound,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+pub enum AudioError {
+    #[error(transparent)]
+    Config(#[from] config::ConfigError),
+    #[cfg(feature = "audio")]
+    #[error("cannot list the audio output devices: {0}")]
+    Devices(#[from] rodio::DevicesError),
+    #[error("no audio output device is called {0}")]
+    NotFound(String),
+    #[error("no default audio output device")]
+    NoDefault,
+    #[error("cannot play {}: {message}", path.display())]
+    File { path: PathBuf, message: String },
+    #[cfg(feature = "audio")]
+    #[error(transparent)]
+    Stream(#[from] rodio::StreamError),
+    #[cfg(not(feature = "audio"))]
+    #[error("rustodoro was built without the audio feature")]
+    Unsupported,
+}
+
+/// A change of the device the sound plays on.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+pub enum Switch {
+    /// The chosen device is gone.
+    Fallback { lost: String },
+    /// The chosen device is back.
+    Back { name: String },
+}
+
+impl fmt::Display for Switch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Switch::Fallback { lost } => write!(
+                f,
+                "the audio device {} is gone, the sound plays on the default one",
+                lost
+            ),
+            Switch::Back { name } => write!(f, "the audio device {} is back", name),
+        }
+    }
+}
+
+/// The index of the device called `wanted` among `names`, ignoring the case, or else of the only
+/// one whose name contains it.
+fn pick(names: &[String], wanted: &str) -> Option<usize> {
+    let wanted = wanted.trim().to_lowercase();
+    let names: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    if let Some(index) = names.iter().position(|name| *name == wanted) {
+        return Some(index);
+    }
+    let mut containing = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.contains(&wanted));
+    match (containing.next(), containing.next()) {
+        (Some((index, _)), None) => Some(index),
+        _ => None,
+    }
+}
+
+/// The output devices with their names.
+#[cfg(feature = "audio")]
+fn devices() -> Result<Vec<(String, Device)>, AudioError> {
+    Ok(rodio::cpal::default_host()
+        .output_devices()?
+        .filter_map(|device| Some((device.name().ok()?, device)))
+        .collect())
+}
+
+/// The output device called `wanted`.
+#[cfg(feature = "audio")]
+fn find(wanted: &str) -> Result<Device, AudioError> {
+    let mut devices = devices()?;
+    let names: Vec<String> = devices.iter().map(|(name, _)| name.clone()).collect();
+    let index = pick(&names, wanted).ok_or_else(|| AudioError::NotFound(wanted.to_string()))?;
+    Ok(devices.swap_remove(index).1)
+}
+
+#[cfg(feature = "audio")]
+fn default_device() -> Result<Device, AudioError> {
+    rodio::cpal::default_host()
+        .default_output_device()
+        .ok_or(AudioError::NoDefault)
+}
+
+/// The names of the output devices, and the one of the default device.
+#[cfg(feature = "audio")]
+fn names() -> Result<(Vec<String>, Option<String>), AudioError> {
+    let names = devices()?.into_iter().map(|(name, _)| name).collect();
+    let default = default_device().ok().and_then(|device| device.name().ok());
+    Ok((names, default))
+}
+
+#[cfg(not(feature = "audio"))]
+fn names() -> Result<(Vec<String>, Option<String>), AudioError> {
+    Err(AudioError::Unsupported)
+}
+
+/// The device list of `rustodoro audio list`, marking the default and the chosen devices.
+pub fn render(names: &[String], default: Option<&str>, chosen: Option<&str>) -> String {
+    if names.is_empty() {
+        return "No audio output device found.\n".to_string();
+    }
+    let chosen = chosen.and_then(|wanted| pick(names, wanted));
+    let mut rendered = String::from("Audio output devices:\n");
+    for (index, name) in names.iter().enumerate() {
+        let mut line = format!("  {}", name);
+        if Some(name.as_str()) == default {
+            line.push_str(" (default)");
+        }
+        if Some(index) == chosen {
+            line.push_str(" (chosen)");
+        }
+        rendered.push_str(&line);
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// Runs the `audio list` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli) -> i32 {
+    let result = config::resolve(cli)
+        .map_err(AudioError::from)
+        .and_then(|effective| {
+            let (names, default) = names()?;
+            Ok(render(
+                &names,
+                default.as_deref(),
+                effective.audio_device.value.as_deref(),
+            ))
+        });
+    match result {
+        Ok(rendered) => {
+            print!("{}", rendered);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// The audio output, on the chosen device or the default one.
+#[cfg(feature = "audio")]
+pub struct Output {
+    /// The chosen device, the default one when `None`.
+    chosen: Option<String>,
+    /// Whether the default device stands in for the chosen one.
+    fallback: bool,
+    /// Set by the stream when its device fails.
+    failed: Arc<AtomicBool>,
+    sink: Sink,
+    _stream: OutputStream, // Keep the stream alive
+}
+
+#[cfg(feature = "audio")]
+impl Output {
+    /// Opens the chosen device, failing when it is not there.
+    pub fn open(chosen: Option<&str>) -> Result<Self, AudioError> {
+        let device = match chosen {
+            Some(name) => find(name)?,
+            None => default_device()?,
+        };
+        Output::on(device, chosen.map(str::to_string), false)
+    }
+
+    fn on(device: Device, chosen: Option<String>, fallback: bool) -> Result<Self, AudioError> {
+        let failed = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&failed);
+        let mut stream = OutputStreamBuilder::from_device(device)?
+            .with_error_callback(move |_| flag.store(true, Ordering::Relaxed))
+            .open_stream_or_fallback()?;
+        stream.log_on_drop(false);
+        Ok(Output {
+            chosen,
+            fallback,
+            failed,
+            sink: Sink::connect_new(stream.mixer()),
+            _stream: stream,
+        })
+    }
+
+    /// Opens the output again when its device failed or went away, on the default device while
+    /// the chosen one is gone, and back on the chosen one once it is there again.
+    pub fn refresh(&mut self) -> Result<Option<Switch>, AudioError> {
+        let failed = self.failed.load(Ordering::Relaxed);
+        let Some(name) = self.chosen.clone() else {
+            if failed {
+                *self = Output::on(default_device()?, None, false)?;
+            }
+            return Ok(None);
+        };
+        match find(&name) {
+            Ok(device) if failed || self.fallback => {
+                let was_fallback = self.fallback;
+                *self = Output::on(device, Some(name.clone()), false)?;
+                Ok(was_fallback.then_some(Switch::Back { name }))
+            }
+            Ok(_) => Ok(None),
+            Err(AudioError::NotFound(_)) if failed || !self.fallback => {
+                let was_fallback = self.fallback;
+                *self = Output::on(default_device()?, Some(name.clone()), true)?;
+                Ok((!was_fallback).then_some(Switch::Fallback { lost: name }))
+            }
+            Err(AudioError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Starts playing `sound`, on its own thread, fading in over `fade_in` and at `volume` times
+    /// its own volume.
+    pub fn play(&self, sound: &Sound, fade_in: Duration, volume: f32) -> Result<(), AudioError> {
+        sound.play(&self.sink, fade_in, volume)
+    }
+
+    /// Waits for the sounds started to finish playing.
+    pub fn wait(&self) {
+        self.sink.sleep_until_end();
+    }
+}
+
+/// No audio output, none being ever opened.
+#[cfg(not(feature = "audio"))]
+pub enum Output {}
+
+#[cfg(not(feature = "audio"))]
+impl Output {
+    pub fn open(_chosen: Option<&str>) -> Result<Self, AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    pub fn refresh(&mut self) -> Result<Option<Switch>, AudioError> {
+        match *self {}
+    }
+
+    pub fn play(&self, _sound: &Sound, _fade_in: Duration, _volume: f32) -> Result<(), AudioError> {
+        match *self {}
+    }
+
+    pub fn wait(&self) {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["Built-in Audio", "USB Speakers", "HDMI 1", "HDMI 2"]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_device_by_name() {
+        let names = names();
+        assert_eq!(pick(&names, "USb Speakers"), Some(1));
+        assert_eq!(pick(&names, "usb"), Some(1));
+        // The exact name wins over the names containing it, several of them being ambiguous.
+        assert_eq!(pick(&names, "hdmi 1"), Some(2));
+        assert_eq!(pick(&names, "HDMI"), None);
+        assert_eq!(pick(&names, "Bluetooth"), None);
+    }
+
+    #[test]
+    fn lists_the_devices() {
+        assert_eq!(
+            render(&names(), Some("Built-in Audio"), Some("usb speakers")),
+            "\
+Audio output devices:
+  Built-in Audio (default)
+  USB Speakers (chosen)
+  HDMI 1
+  HDMI 2
+"
+        );
+        assert_eq!(render(&[], None, None), "No audio output device found.\n");
+        assert_eq!(
+            Switch::Fallback {
+                lost: "USB Speakers".to_string()
+            }
+            .to_string(),
+            "the audio device USB Speakers is gone, the sound plays on the default one"
+        );
+    }
+}
@@ -0,0 +1,434 @@
+// filepath: src/backup.rs
+//! Module backing up the data of a Pomodoro timer application, the configuration file, the session
+//! history, the achievements unlocked, the run queue and the outbox, into a single JSON file, and
+//! restoring it. The backup records the version of the history records and a checksum of each
+//! file, checked before anything is restored. It is not compressed, neither tar nor zstd being at
+//! hand.
+//!
+//! The key of a history encrypted with a key in the keychain stays in the keychain, out of the
+//! backup, which warns about it.
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements,
+    config::{self, Cli},
+    crypt::Encryption,
+    history::{self, HISTORY_ENV, History},
+    outbox, run_queue,
+};
+
+/// Version of the backup format, increased when a backup cannot be read as before: the second
+/// holds the achievements, the run queue and the outbox too.
+const FORMAT: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("cannot locate the configuration directory, set HOME or XDG_CONFIG_HOME")]
+    NoConfigDir,
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+    #[error("nothing to back up, there is neither a configuration file nor a history yet")]
+    Empty,
+    #[error("cannot access {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{} is not a backup of rustodoro: {message}", path.display())]
+    Invalid { path: PathBuf, message: String },
+    #[error("{} is corrupted: the checksum of its {part} does not match", path.display())]
+    Corrupted { path: PathBuf, part: Part },
+    #[error("{} comes from a newer rustodoro, update it to restore this backup", path.display())]
+    Newer { path: PathBuf },
+    #[error("{} already exists, restore with --force to replace it", path.display())]
+    Exists { path: PathBuf },
+}
+
+/// A file of the backup.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Part {
+    Config,
+    History,
+    Achievements,
+    RunQueue,
+    Outbox,
+}
+
+impl Part {
+    const ALL: [Part; 5] = [
+        Part::Config,
+        Part::History,
+        Part::Achievements,
+        Part::RunQueue,
+        Part::Outbox,
+    ];
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Part::Config => write!(f, "configuration file"),
+            Part::History => write!(f, "history"),
+            Part::Achievements => write!(f, "achievements"),
+            Part::RunQueue => write!(f, "run queue"),
+            Part::Outbox => write!(f, "outbox"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    part: Part,
+    content: String,
+    /// CRC-32 of the content.
+    checksum: u32,
+}
+
+/// What a backup file holds, written as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    format: u32,
+    /// Version of rustodoro which made the backup.
+    version: String,
+    /// Version of the history records, as they were backed up.
+    history_schema: u32,
+    created_at: DateTime<Local>,
+    entries: Vec<Entry>,
+}
+
+impl Backup {
+    pub fn new(parts: Vec<(Part, String)>, created_at: DateTime<Local>) -> Self {
+        let history_schema = parts
+            .iter()
+            .find(|(part, _)| *part == Part::History)
+            .map_or(history::SCHEMA_VERSION, |(_, content)| {
+                history::parse(content).version
+            });
+        Backup {
+            format: FORMAT,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            history_schema,
+            created_at,
+            entries: parts
+                .into_iter()
+                .map(|(part, content)| Entry {
+                    part,
+                    checksum: crc32fast::hash(content.as_bytes()),
+                    content,
+                })
+                .collect(),
+        }
+    }
+
+    /// Reads the backup file at `path`, checking that this version can restore it and that it
+    /// is intact.
+    pub fn read(path: &Path, content: &str) -> Result<Self, BackupError> {
+        let invalid = |message: String| BackupError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        };
+        let value: serde_json::Value =
+            serde_json::from_str(content).map_err(|e| invalid(e.to_string()))?;
+        let format = value.get("format").and_then(serde_json::Value::as_u64);
+        let schema = value
+            .get("history_schema")
+            .and_then(serde_json::Value::as_u64);
+        match (format, schema) {
+            (None, _) | (_, None) => return Err(invalid("no format version".to_string())),
+            (Some(format), Some(schema))
+                if format > u64::from(FORMAT) || schema > u64::from(history::SCHEMA_VERSION) =>
+            {
+                return Err(BackupError::Newer {
+                    path: path.to_path_buf(),
+                });
+            }
+            _ => {}
+        }
+        let backup: Backup = serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+        if let Some(entry) = backup
+            .entries
+            .iter()
+            .find(|entry| crc32fast::hash(entry.content.as_bytes()) != entry.checksum)
+        {
+            return Err(BackupError::Corrupted {
+                path: path.to_path_buf(),
+                part: entry.part,
+            });
+        }
+        Ok(backup)
+    }
+
+    pub fn parts(&self) -> impl Iterator<Item = (Part, &str)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.part, entry.content.as_str()))
+    }
+}
+
+/// Where the parts are read from and restored to.
+struct Paths {
+    config: PathBuf,
+    history: PathBuf,
+    achievements: PathBuf,
+    run_queue: PathBuf,
+    outbox: PathBuf,
+}
+
+impl Paths {
+    fn of(cli: &Cli) -> Result<Self, BackupError> {
+        let history = history::history_path(cli.history.as_deref(), cli.name.as_deref())
+            .ok_or(BackupError::NoHistory)?;
+        Ok(Paths {
+            config: config::config_path(cli).ok_or(BackupError::NoConfigDir)?.0,
+            achievements: achievements::Store::of(&history).path().to_path_buf(),
+            // Both are kept next to the default history.
+            run_queue: run_queue::default_run_queue_path()
+                .unwrap_or_else(|| history.with_file_name("runs.jsonl")),
+            outbox: outbox::default_outbox_path()
+                .unwrap_or_else(|| history.with_file_name("outbox.jsonl")),
+            history,
+        })
+    }
+
+    fn path(&self, part: Part) -> &Path {
+        match part {
+            Part::Config => &self.config,
+            Part::History => &self.history,
+            Part::Achievements => &self.achievements,
+            Part::RunQueue => &self.run_queue,
+            Part::Outbox => &self.outbox,
+        }
+    }
+}
+
+/// The keychain account of the key of the history at `paths`, which the backup leaves out, if it
+/// is encrypted with one.
+fn key_left_out(paths: &Paths) -> Option<String> {
+    match History::new(paths.history.clone()).encryption() {
+        Ok(Some(Encryption::Keychain { account, .. })) => Some(account),
+        _ => None,
+    }
+}
+
+/// Writes the backup of the files at `paths` which exist to `file`.
+fn back_up(paths: &Paths, file: &Path) -> Result<Vec<Part>, BackupError> {
+    let mut parts = Vec::new();
+    for part in Part::ALL {
+        let path = paths.path(part);
+        match fs::read_to_string(path) {
+            Ok(content) => parts.push((part, content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(source) => {
+                return Err(BackupError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Err(BackupError::Empty);
+    }
+    let saved = parts.iter().map(|(part, _)| *part).collect();
+    let backup = Backup::new(parts, Local::now());
+    let content = serde_json::to_string_pretty(&backup).expect("backups always serialize");
+    fs::write(file, content).map_err(|source| BackupError::Io {
+        path: file.to_path_buf(),
+        source,
+    })?;
+    Ok(saved)
+}
+
+/// Restores the backup in `file` to `paths`, replacing the files there only when `force` is set.
+/// The whole backup is checked before anything is written.
+fn restore(paths: &Paths, file: &Path, force: bool) -> Result<Vec<Part>, BackupError> {
+    let content = fs::read_to_string(file).map_err(|source| BackupError::Io {
+        path: file.to_path_buf(),
+        source,
+    })?;
+    let backup = Backup::read(file, &content)?;
+    if !force && let Some((part, _)) = backup.parts().find(|(part, _)| paths.path(*part).exists()) {
+        return Err(BackupError::Exists {
+            path: paths.path(part).to_path_buf(),
+        });
+    }
+    for (part, content) in backup.parts() {
+        let path = paths.path(part);
+        let io_error = |source| BackupError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        // Renamed over the file, which is then either restored or left as it was.
+        let temporary = path.with_extension("restore.tmp");
+        fs::write(&temporary, content)
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(io_error)?;
+    }
+    Ok(backup.parts().map(|(part, _)| part).collect())
+}
+
+fn describe(parts: &[Part]) -> String {
+    let mut parts: Vec<String> = parts.iter().map(Part::to_string).collect();
+    match parts.pop() {
+        Some(last) if !parts.is_empty() => format!("{} and {}", parts.join(", "), last),
+        last => last.unwrap_or_default(),
+    }
+}
+
+/// Runs the `backup` subcommand and returns the process exit code.
+pub fn run_backup(cli: &Cli, file: &Path) -> i32 {
+    let backed_up = Paths::of(cli).and_then(|paths| {
+        let parts = back_up(&paths, file)?;
+        Ok((parts, key_left_out(&paths)))
+    });
+    match backed_up {
+        Ok((parts, key)) => {
+            println!(
+                "Backup of the {} written to {}.",
+                describe(&parts),
+                file.display()
+            );
+            if let Some(account) = key {
+                eprintln!(
+                    "warning: the key of the history is in the keychain, under the {} account, \
+                     not in the backup: the history cannot be read without it",
+                    account
+                );
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// Runs the `restore` subcommand and returns the process exit code.
+pub fn run_restore(cli: &Cli, file: &Path, force: bool) -> i32 {
+    match Paths::of(cli).and_then(|paths| restore(&paths, file, force)) {
+        Ok(parts) => {
+            println!("The {} restored from {}.", describe(&parts), file.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn paths(dir: &Path) -> Paths {
+        Paths {
+            config: dir.join("config.toml"),
+            history: dir.join("history.jsonl"),
+            achievements: dir.join("history.achievements.json"),
+            run_queue: dir.join("runs.jsonl"),
+            outbox: dir.join("outbox.jsonl"),
+        }
+    }
+
+    #[test]
+    fn restores_what_was_backed_up() {
+        let dir = temp_dir("backup");
+        let (from, to) = (paths(&dir.join("from")), paths(&dir.join("to")));
+        let file = dir.join("backup.json");
+        fs::create_dir_all(dir.join("from")).unwrap();
+        assert!(matches!(back_up(&from, &file), Err(BackupError::Empty)));
+
+        fs::write(&from.history, "{\"kind\": \"work\"}\n").unwrap();
+        assert_eq!(back_up(&from, &file).unwrap(), [Part::History]);
+        assert_eq!(restore(&to, &file, false).unwrap(), [Part::History]);
+        assert_eq!(
+            fs::read_to_string(&to.history).unwrap(),
+            "{\"kind\": \"work\"}\n"
+        );
+        assert!(!to.config.exists());
+        // The files there are only replaced when asked to.
+        assert!(matches!(
+            restore(&to, &file, false),
+            Err(BackupError::Exists { .. })
+        ));
+        assert!(restore(&to, &file, true).is_ok());
+
+        fs::write(&from.achievements, "[]\n").unwrap();
+        fs::write(&from.run_queue, "{\"work\": 50}\n").unwrap();
+        fs::write(&from.outbox, "{\"topic\": \"done\"}\n").unwrap();
+        let parts = back_up(&from, &file).unwrap();
+        assert_eq!(
+            describe(&parts),
+            "history, achievements, run queue and outbox"
+        );
+        assert_eq!(restore(&to, &file, true).unwrap(), parts);
+        assert_eq!(
+            fs::read_to_string(&to.outbox).unwrap(),
+            "{\"topic\": \"done\"}\n"
+        );
+        assert_eq!(key_left_out(&from), None);
+    }
+
+    #[test]
+    fn warns_of_the_key_left_in_the_keychain() {
+        let dir = temp_dir("backup-keychain");
+        let paths = paths(&dir);
+        fs::write(
+            &paths.history,
+            "{\"schema\":4,\"encryption\":\
+             {\"key\":\"keychain\",\"account\":\"history-0a1b2c3d\"}}\n",
+        )
+        .unwrap();
+        assert_eq!(key_left_out(&paths).as_deref(), Some("history-0a1b2c3d"));
+    }
+
+    #[test]
+    fn checks_the_backup_before_restoring_it() {
+        let path = Path::new("backup.json");
+        let backup = Backup::new(
+            vec![
+                (Part::Config, "work = 50\n".to_string()),
+                (Part::History, String::new()),
+            ],
+            Local::now(),
+        );
+        let content = serde_json::to_string(&backup).unwrap();
+        assert_eq!(Backup::read(path, &content).unwrap(), backup);
+        assert!(matches!(
+            Backup::read(path, &content.replace("work = 50", "work = 60")),
+            Err(BackupError::Corrupted {
+                part: Part::Config,
+                ..
+            })
+        ));
+        let newer = serde_json::json!({ "format": FORMAT + 1, "history_schema": 1 });
+        assert!(matches!(
+            Backup::read(path, &newer.to_string()),
+            Err(BackupError::Newer { .. })
+        ));
+        assert!(matches!(
+            Backup::read(path, "{}"),
+            Err(BackupError::Invalid { .. })
+        ));
+    }
+}
@@ -0,0 +1,604 @@
+// filepath: src/calendar.rs
+//! Module blocking the focus time of a Pomodoro timer application in a calendar, CalDAV or Google
+//! Calendar, so that colleagues see the user as busy: a work session starting writes a focus event
+//! covering it, or extends the one of the previous work session when it ended moments ago.
+//! Quitting early truncates the event to the time spent, or deletes it when hardly anything was.
+//!
+//! An event is only changed while it is as the timer last wrote it: one moved or deleted in the
+//! calendar meanwhile is left alone, the server checking the ETag of each write.
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeDelta, Utc};
+#[cfg(feature = "caldav")]
+use ureq::Agent;
+
+#[cfg(feature = "caldav")]
+use rustodoro_client::protocol::SessionKind;
+
+use crate::config::CalendarConfig;
+use crate::desktop::Notification;
+use crate::quiet::Quiet;
+use crate::settings::SharedSettings;
+use crate::{SessionType, events::Event, events::SessionOutcome};
+
+/// Longest wait for the calendar server, events being written in the background.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+/// Shortest focus block kept when quitting early, a shorter one is deleted.
+const MIN_BLOCK: TimeDelta = TimeDelta::minutes(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalendarError {
+    #[error("cannot write the focus block to the calendar: {0}")]
+    Http(String),
+    #[error("the focus block was changed in the calendar meanwhile, it is left as it is")]
+    Edited,
+    #[cfg(feature = "gcal")]
+    #[error("rustodoro is not authorized to use Google Calendar, run `rustodoro auth google`")]
+    Unauthorized,
+    #[cfg(feature = "gcal")]
+    #[error("the access to Google Calendar was denied")]
+    Denied,
+    #[cfg(feature = "gcal")]
+    #[error("the code was not entered in time, run `rustodoro auth google` again")]
+    Expired,
+    #[cfg(feature = "gcal")]
+    #[error(transparent)]
+    Keychain(#[from] crate::keychain::KeychainError),
+}
+
+/// What the event must be for a write to go through.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    /// The event does not exist yet.
+    Absent,
+    /// The event still has this ETag.
+    Tag(String),
+    /// The server gave no ETag to check.
+    Unknown,
+}
+
+/// How a write went.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Written {
+    /// The write went through, giving the new ETag of the event if the server tells it.
+    Done(Option<String>),
+    /// The event is not as expected anymore.
+    Conflict,
+}
+
+/// A focus event, busy from its start to its end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusEvent {
+    pub uid: String,
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// An event of the calendar the user is busy with, other than the focus events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Busy {
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// A calendar holding events by uid.
+pub trait Calendar: Send {
+    fn put(&self, event: &FocusEvent, expected: &Expected) -> Result<Written, CalendarError>;
+    fn delete(&self, uid: &str, expected: &Expected) -> Result<Written, CalendarError>;
+
+    /// The events the user is busy with between `from` and `to`, for the calendars which can
+    /// tell.
+    fn busy(&self, _from: DateTime<Utc>, _to: DateTime<Utc>) -> Result<Vec<Busy>, CalendarError> {
+        Ok(Vec::new())
+    }
+}
+
+/// A calendar collection of a CalDAV server, e.g. Nextcloud, Fastmail or Radicale.
+#[cfg(feature = "caldav")]
+pub struct CalDav {
+    agent: Agent,
+    url: String,
+    authorization: String,
+}
+
+#[cfg(feature = "caldav")]
+impl CalDav {
+    pub fn new(config: &CalendarConfig) -> Self {
+        CalDav {
+            agent: Agent::config_builder()
+                .timeout_global(Some(TIMEOUT))
+                // A precondition failing is a conflict rather than an error.
+                .http_status_as_error(false)
+                .build()
+                .into(),
+            url: config.url.trim_end_matches('/').to_string(),
+            authorization: format!(
+                "Basic {}",
+                base64(format!("{}:{}", config.username, config.password).as_bytes())
+            ),
+        }
+    }
+
+    fn event_url(&self, uid: &str) -> String {
+        format!("{}/{}.ics", self.url, uid)
+    }
+}
+
+#[cfg(feature = "caldav")]
+impl Calendar for CalDav {
+    fn put(&self, event: &FocusEvent, expected: &Expected) -> Result<Written, CalendarError> {
+        let mut request = self
+            .agent
+            .put(self.event_url(&event.uid))
+            .header("Authorization", &self.authorization)
+            .header("Content-Type", "text/calendar; charset=utf-8");
+        request = match expected {
+            Expected::Absent => request.header("If-None-Match", "*"),
+            Expected::Tag(etag) => request.header("If-Match", etag),
+            Expected::Unknown => request,
+        };
+        let response = request
+            .send(ics(event, Utc::now()))
+            .map_err(|e| CalendarError::Http(e.to_string()))?;
+        match response.status().as_u16() {
+            200..=299 => Ok(Written::Done(
+                response
+                    .headers()
+                    .get("ETag")
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string),
+            )),
+            // Deleted in the calendar, which is not undone.
+            404 if *expected != Expected::Absent => Ok(Written::Conflict),
+            412 => Ok(Written::Conflict),
+            status => Err(CalendarError::Http(format!("status {}", status))),
+        }
+    }
+
+    fn delete(&self, uid: &str, expected: &Expected) -> Result<Written, CalendarError> {
+        let mut request = self
+            .agent
+            .delete(self.event_url(uid))
+            .header("Authorization", &self.authorization);
+        if let Expected::Tag(etag) = expected {
+            request = request.header("If-Match", etag);
+        }
+        let response = request
+            .call()
+            .map_err(|e| CalendarError::Http(e.to_string()))?;
+        match response.status().as_u16() {
+            200..=299 | 404 => Ok(Written::Done(None)),
+            412 => Ok(Written::Conflict),
+            status => Err(CalendarError::Http(format!("status {}", status))),
+        }
+    }
+}
+
+/// `bytes` in standard base64, for the basic authentication.
+#[cfg(feature = "caldav")]
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// A focus event written to the calendar.
+#[derive(Debug, Clone)]
+struct Block {
+    event: FocusEvent,
+    expected: Expected,
+}
+
+/// `text` escaped for an iCalendar property value.
+#[cfg(feature = "caldav")]
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// The iCalendar object of `event`.
+#[cfg(feature = "caldav")]
+fn ics(event: &FocusEvent, now: DateTime<Utc>) -> String {
+    let time = |time: DateTime<Utc>| time.format("%Y%m%dT%H%M%SZ").to_string();
+    [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rustodoro//focus blocks//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", event.uid),
+        format!("DTSTAMP:{}", time(now)),
+        format!("DTSTART:{}", time(event.start)),
+        format!("DTEND:{}", time(event.end)),
+        format!("SUMMARY:{}", escape(&event.title)),
+        "TRANSP:OPAQUE".to_string(),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+        String::new(),
+    ]
+    .join("\r\n")
+}
+
+/// Keeps a focus event in the calendar over the work sessions.
+pub struct FocusBlocks<C: Calendar> {
+    title: String,
+    /// Minutes after the end of a block within which the next work session extends it.
+    extend_within: u64,
+    calendar: C,
+    /// The event of the running or of the last work session.
+    block: Option<Block>,
+    /// The duration of the running work session.
+    session: Option<Duration>,
+    settings: SharedSettings,
+    quiet: Option<Arc<Quiet>>,
+}
+
+impl<C: Calendar> FocusBlocks<C> {
+    pub fn new(title: String, extend_within: u64, calendar: C, settings: SharedSettings) -> Self {
+        FocusBlocks {
+            title,
+            extend_within,
+            calendar,
+            block: None,
+            session: None,
+            settings,
+            quiet: None,
+        }
+    }
+
+    /// Holds the meetings a work session runs into back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    /// Whether the meetings a work session runs into are told: not with the notifications off, nor
+    /// while keeping quiet.
+    fn tells(&self) -> bool {
+        !self.settings.lock().unwrap().no_notify
+            && !self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+    }
+
+    fn write(&mut self, mut block: Block) -> Result<(), CalendarError> {
+        match self.calendar.put(&block.event, &block.expected)? {
+            Written::Done(etag) => {
+                block.expected = etag.map_or(Expected::Unknown, Expected::Tag);
+                self.block = Some(block);
+                Ok(())
+            }
+            Written::Conflict => {
+                self.block = None;
+                Err(CalendarError::Edited)
+            }
+        }
+    }
+
+    /// Covers a work session starting now, extending the last block when it ended within
+    /// `extend_within` minutes, and returns the first event the session runs into, if any.
+    fn start(
+        &mut self,
+        duration: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Busy>, CalendarError> {
+        self.session = Some(duration);
+        let end = now + duration;
+        let within = TimeDelta::minutes(self.extend_within as i64);
+        let block = match self.block.take() {
+            Some(mut block) if now - block.event.end <= within => {
+                block.event.end = end;
+                block
+            }
+            _ => Block {
+                event: FocusEvent {
+                    uid: format!("rustodoro-{}@rustodoro", now.timestamp_millis()),
+                    title: self.title.clone(),
+                    start: now,
+                    end,
+                },
+                expected: Expected::Absent,
+            },
+        };
+        let busy = self.calendar.busy(now, end)?;
+        self.write(block)?;
+        Ok(busy.into_iter().min_by_key(|busy| busy.start))
+    }
+
+    /// Moves the end of the block, the work session ending at another time than planned.
+    fn move_end(&mut self, end: DateTime<Utc>) -> Result<(), CalendarError> {
+        match self.block.clone() {
+            Some(mut block) if block.event.end != end => {
+                block.event.end = end;
+                self.write(block)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Ends the block now, deleting it when it is too short to keep.
+    fn stop(&mut self, now: DateTime<Utc>) -> Result<(), CalendarError> {
+        self.session = None;
+        let Some(block) = self.block.clone() else {
+            return Ok(());
+        };
+        if now - block.event.start >= MIN_BLOCK {
+            return self.move_end(now);
+        }
+        self.block = None;
+        match self.calendar.delete(&block.event.uid, &block.expected)? {
+            Written::Done(_) => Ok(()),
+            Written::Conflict => Err(CalendarError::Edited),
+        }
+    }
+
+    /// Updates the calendar according to `event`, returning the event of the calendar a work
+    /// session starting runs into.
+    pub fn handle(
+        &mut self,
+        event: &Event,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Busy>, CalendarError> {
+        match event {
+            Event::SessionStarted {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                duration,
+                ..
+            } => self.start(*duration, now),
+            Event::Resumed { elapsed, .. } => match self.session {
+                Some(duration) => self.move_end(now + duration.saturating_sub(*elapsed)),
+                None => Ok(()),
+            }
+            .map(|()| None),
+            Event::Reset => match self.session {
+                Some(duration) => self.move_end(now + duration),
+                None => Ok(()),
+            }
+            .map(|()| None),
+            Event::SessionEnded {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                outcome,
+                ..
+            } => {
+                if *outcome == SessionOutcome::Completed {
+                    self.session = None;
+                    Ok(None)
+                } else {
+                    self.stop(now).map(|()| None)
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Follows the events until the event bus is dropped, notifying the user of the events a work
+    /// session runs into, and returns the first failure, ending the block of a work session the
+    /// run stopped in.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<CalendarError> {
+        let mut first_failure = None;
+        for event in events {
+            match self.handle(&event, Utc::now()) {
+                Ok(Some(busy)) if self.tells() => {
+                    let _ = Notification::new()
+                        .summary("Focus block")
+                        .body(&format!(
+                            "{} starts at {} during this work session",
+                            busy.title,
+                            busy.start.with_timezone(&Local).format("%H:%M")
+                        ))
+                        .icon("x-office-calendar")
+                        .show();
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    first_failure.get_or_insert(e);
+                }
+            }
+        }
+        if self.session.is_some()
+            && let Err(e) = self.stop(Utc::now())
+        {
+            first_failure.get_or_insert(e);
+        }
+        first_failure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::settings::Settings;
+
+    /// The writes made, as the uid, the end of the event, none for a deletion, and what the event
+    /// was expected to be.
+    type Writes = Arc<Mutex<Vec<(String, Option<DateTime<Utc>>, Expected)>>>;
+
+    /// A calendar with a meeting from 10:00 to 10:30, recording the writes, each new version of an
+    /// event getting the next ETag, and answering a conflict once `edited` is set.
+    struct Fake {
+        writes: Writes,
+        edited: Arc<Mutex<bool>>,
+    }
+
+    impl Calendar for Fake {
+        fn put(&self, event: &FocusEvent, expected: &Expected) -> Result<Written, CalendarError> {
+            if *self.edited.lock().unwrap() {
+                return Ok(Written::Conflict);
+            }
+            let mut writes = self.writes.lock().unwrap();
+            writes.push((event.uid.clone(), Some(event.end), expected.clone()));
+            Ok(Written::Done(Some(format!("\"{}\"", writes.len()))))
+        }
+
+        fn busy(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Busy>, CalendarError> {
+            let meeting = Busy {
+                title: "Standup".to_string(),
+                start: at(60),
+                end: at(90),
+            };
+            Ok(Vec::from_iter(
+                (meeting.start < to && meeting.end > from).then_some(meeting),
+            ))
+        }
+
+        fn delete(&self, uid: &str, expected: &Expected) -> Result<Written, CalendarError> {
+            self.writes
+                .lock()
+                .unwrap()
+                .push((uid.to_string(), None, expected.clone()));
+            Ok(Written::Done(None))
+        }
+    }
+
+    fn blocks() -> (FocusBlocks<Fake>, Writes, Arc<Mutex<bool>>) {
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let edited = Arc::new(Mutex::new(false));
+        let calendar = Fake {
+            writes: Arc::clone(&writes),
+            edited: Arc::clone(&edited),
+        };
+        (
+            FocusBlocks::new(
+                "Focus".to_string(),
+                10,
+                calendar,
+                Settings::default().into_shared(),
+            ),
+            writes,
+            edited,
+        )
+    }
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 9, 0, 0).unwrap() + TimeDelta::minutes(minute.into())
+    }
+
+    fn work(minutes: u64) -> Event {
+        Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: Duration::from_secs(minutes * 60),
+        }
+    }
+
+    fn work_ended(outcome: SessionOutcome) -> Event {
+        Event::SessionEnded {
+            session: SessionType::work(),
+            elapsed: Duration::ZERO,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn extends_the_block_over_short_breaks() {
+        let (mut blocks, writes, _) = blocks();
+        blocks.handle(&work(25), at(0)).unwrap();
+        blocks
+            .handle(&work_ended(SessionOutcome::Completed), at(25))
+            .unwrap();
+        blocks.handle(&work(25), at(30)).unwrap();
+        blocks
+            .handle(&work_ended(SessionOutcome::Completed), at(55))
+            .unwrap();
+        // A long break starts a block of its own.
+        let busy = blocks.handle(&work(25), at(80)).unwrap();
+        assert_eq!(busy.map(|busy| busy.title).as_deref(), Some("Standup"));
+        let writes = writes.lock().unwrap();
+        assert_eq!(writes.len(), 3);
+        assert_eq!(writes[0].0, writes[1].0);
+        assert_eq!(writes[1].1, Some(at(55)));
+        assert_eq!(writes[1].2, Expected::Tag("\"1\"".to_string()));
+        assert_ne!(writes[2].0, writes[1].0);
+        assert_eq!(writes[2].2, Expected::Absent);
+    }
+
+    #[test]
+    fn truncates_or_deletes_the_block_when_quitting_early() {
+        let (mut blocks, writes, _) = blocks();
+        blocks.handle(&work(25), at(0)).unwrap();
+        blocks
+            .handle(&work_ended(SessionOutcome::Interrupted), at(12))
+            .unwrap();
+        blocks.handle(&work(25), at(60)).unwrap();
+        blocks
+            .handle(&work_ended(SessionOutcome::Interrupted), at(62))
+            .unwrap();
+        let writes = writes.lock().unwrap();
+        assert_eq!(writes[1].1, Some(at(12)));
+        // The second block lasted two minutes.
+        assert_eq!(writes[3].0, writes[2].0);
+        assert_eq!(writes[3].1, None);
+    }
+
+    #[test]
+    fn tells_the_meetings_only_with_the_notifications_on() {
+        let (blocks, _, _) = blocks();
+        assert!(blocks.tells());
+        blocks.settings.lock().unwrap().no_notify = true;
+        assert!(!blocks.tells());
+    }
+
+    #[test]
+    fn leaves_an_edited_block_alone() {
+        let (mut blocks, writes, edited) = blocks();
+        blocks.handle(&work(25), at(0)).unwrap();
+        *edited.lock().unwrap() = true;
+        let resumed = Event::Resumed {
+            elapsed: Duration::from_secs(10 * 60),
+            paused: Duration::from_secs(5 * 60),
+        };
+        assert!(matches!(
+            blocks.handle(&resumed, at(15)),
+            Err(CalendarError::Edited)
+        ));
+        blocks
+            .handle(&work_ended(SessionOutcome::Interrupted), at(20))
+            .unwrap();
+        assert_eq!(writes.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "caldav")]
+    fn writes_icalendar_objects() {
+        let event = FocusEvent {
+            uid: "rustodoro-1@rustodoro".to_string(),
+            title: "Focus, do not disturb".to_string(),
+            start: at(0),
+            end: at(25),
+        };
+        let ics = ics(&event, at(0));
+        assert!(ics.contains("\r\nDTSTART:20261015T090000Z\r\nDTEND:20261015T092500Z\r\n"));
+        assert!(ics.contains("SUMMARY:Focus\\, do not disturb\r\n"));
+        assert_eq!(base64(b"me:secret"), "bWU6c2VjcmV0");
+        assert_eq!(base64(b"ab"), "YWI=");
+    }
+}
@@ -0,0 +1,312 @@
+// filepath: src/capture.rs
+//! Module telling whether a meeting app captures the microphone or the camera, so that the work
+//! sessions of a Pomodoro timer application pause during the calls and resume once they are over.
+//! The microphone is watched through PulseAudio or PipeWire on Linux, CoreAudio on macOS and the
+//! privacy settings of Windows, also read under WSL. The camera is watched through the processes
+//! holding a video device on Linux and the privacy settings of Windows, not on macOS.
+use std::{process::Command, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{
+    config::AutoPauseConfig,
+    platform::{self, Platform},
+};
+
+/// How long a capture check is trusted before checking again.
+const RECHECK: Duration = Duration::from_secs(5);
+
+/// What a meeting app captures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    Microphone,
+    Camera,
+}
+
+/// Whether the devices are being captured.
+pub trait CaptureProbe: Send {
+    /// `None` when it cannot be told, e.g. without `pactl`.
+    fn capturing(&self, device: Device) -> Option<bool>;
+}
+
+/// The running system.
+pub struct Os;
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl CaptureProbe for Os {
+    fn capturing(&self, device: Device) -> Option<bool> {
+        if cfg!(windows) || platform::current() == Platform::Wsl {
+            return consent_store(device);
+        }
+        match device {
+            Device::Microphone => microphone(),
+            Device::Camera => camera(),
+        }
+    }
+}
+
+/// Whether an app uses `device` as the privacy settings of Windows tell, through `reg.exe` under
+/// WSL.
+fn consent_store(device: Device) -> Option<bool> {
+    let key = format!(
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\{}",
+        match device {
+            Device::Microphone => "microphone",
+            Device::Camera => "webcam",
+        }
+    );
+    let program = if cfg!(windows) { "reg" } else { "reg.exe" };
+    output(program, &["query", &key, "/s", "/v", "LastUsedTimeStop"])
+        .map(|output| parse_reg(&output))
+}
+
+/// Whether an app of the `reg query` output is still using the device, its last use having no
+/// end yet.
+fn parse_reg(output: &str) -> bool {
+    output.lines().any(|line| {
+        line.split_whitespace().collect::<Vec<_>>() == ["LastUsedTimeStop", "REG_QWORD", "0x0"]
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn microphone() -> Option<bool> {
+    coreaudio::microphone_in_use()
+}
+
+/// Any recording of PulseAudio, or PipeWire through its PulseAudio server, but those of the
+/// monitors of the outputs, e.g. by a volume meter.
+#[cfg(not(target_os = "macos"))]
+fn microphone() -> Option<bool> {
+    let sources = output("pactl", &["list", "short", "sources"])?;
+    let recordings = output("pactl", &["list", "short", "source-outputs"])?;
+    Some(parse_pactl(&sources, &recordings))
+}
+
+/// Whether one of the `recordings` of `pactl list short source-outputs` records a source of
+/// `pactl list short sources` which is not a monitor.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn parse_pactl(sources: &str, recordings: &str) -> bool {
+    let monitors: Vec<&str> = sources
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let index = columns.next()?;
+            columns
+                .next()
+                .filter(|name| name.ends_with(".monitor"))
+                .map(|_| index)
+        })
+        .collect();
+    recordings
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .any(|source| !monitors.contains(&source))
+}
+
+#[cfg(target_os = "macos")]
+fn camera() -> Option<bool> {
+    None
+}
+
+/// Any process of the user holding a video device open.
+#[cfg(not(target_os = "macos"))]
+fn camera() -> Option<bool> {
+    use std::fs;
+
+    let processes = fs::read_dir("/proc").ok()?;
+    Some(processes.flatten().any(|process| {
+        fs::read_dir(process.path().join("fd"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|fd| {
+                fs::read_link(fd.path())
+                    .is_ok_and(|target| target.to_string_lossy().starts_with("/dev/video"))
+            })
+    }))
+}
+
+/// Whether the default input device runs for any process, as CoreAudio tells.
+#[cfg(target_os = "macos")]
+mod coreaudio {
+    use std::{ffi::c_void, mem, ptr};
+
+    #[repr(C)]
+    struct PropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    unsafe extern "C" {
+        fn AudioObjectGetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            size: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+
+    const SYSTEM_OBJECT: u32 = 1;
+    const SCOPE_GLOBAL: u32 = u32::from_be_bytes(*b"glob");
+    const DEFAULT_INPUT_DEVICE: u32 = u32::from_be_bytes(*b"dIn ");
+    const IS_RUNNING_SOMEWHERE: u32 = u32::from_be_bytes(*b"gone");
+
+    /// The property `selector` of `object`, none when CoreAudio fails to give it.
+    fn property(object: u32, selector: u32) -> Option<u32> {
+        let address = PropertyAddress {
+            selector,
+            scope: SCOPE_GLOBAL,
+            element: 0,
+        };
+        let mut value: u32 = 0;
+        let mut size = mem::size_of::<u32>() as u32;
+        // SAFETY: the address, the size and the value outlive the call, which writes at most
+        // `size` bytes into the value.
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object,
+                &address,
+                0,
+                ptr::null(),
+                &mut size,
+                (&mut value as *mut u32).cast(),
+            )
+        };
+        (status == 0).then_some(value)
+    }
+
+    pub fn microphone_in_use() -> Option<bool> {
+        let device = property(SYSTEM_OBJECT, DEFAULT_INPUT_DEVICE).filter(|device| *device != 0)?;
+        property(device, IS_RUNNING_SOMEWHERE).map(|running| running != 0)
+    }
+}
+
+struct Check {
+    probe: Box<dyn CaptureProbe>,
+    /// Whether a call was going on, and when this was checked.
+    last: Option<(Instant, bool)>,
+}
+
+/// Pauses the work sessions while the devices of the configuration are captured, e.g. during a
+/// call.
+pub struct AutoPause {
+    devices: Vec<Device>,
+    check: Mutex<Check>,
+}
+
+impl AutoPause {
+    pub fn new(config: &AutoPauseConfig, probe: impl CaptureProbe + 'static) -> Self {
+        let devices = [
+            (config.microphone, Device::Microphone),
+            (config.camera, Device::Camera),
+        ]
+        .into_iter()
+        .filter_map(|(watched, device)| watched.then_some(device))
+        .collect();
+        AutoPause {
+            devices,
+            check: Mutex::new(Check {
+                probe: Box::new(probe),
+                last: None,
+            }),
+        }
+    }
+
+    /// Whether the capture of one of the devices can be told on this system.
+    pub fn is_available(&self) -> bool {
+        let check = self.check.lock().unwrap();
+        self.devices
+            .iter()
+            .any(|device| check.probe.capturing(*device).is_some())
+    }
+
+    /// Whether one of the devices is captured at `now`, checked at most every 5 seconds.
+    pub fn in_call(&self, now: Instant) -> bool {
+        let mut check = self.check.lock().unwrap();
+        if let Some((at, in_call)) = check.last
+            && now.duration_since(at) < RECHECK
+        {
+            return in_call;
+        }
+        let in_call = self
+            .devices
+            .iter()
+            .any(|device| check.probe.capturing(*device) == Some(true));
+        check.last = Some((now, in_call));
+        in_call
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn reads_the_captures() {
+        let sources = "0\talsa_output.pci.analog-stereo.monitor\tPipeWire\ts32le 2ch 48000Hz\tIDLE\n\
+                       1\talsa_input.pci.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tRUNNING\n";
+        assert!(parse_pactl(sources, "42\t1\t97\ts16le 1ch 48000Hz\n"));
+        assert!(!parse_pactl(sources, "43\t0\t98\tfloat32le 2ch 48000Hz\n"));
+        assert!(!parse_pactl(sources, ""));
+        let reg = "\r\nHKEY_CURRENT_USER\\...\\microphone\\NonPackaged\\C:#Zoom#bin#Zoom.exe\r\n    \
+                   LastUsedTimeStop    REG_QWORD    0x0\r\n";
+        assert!(parse_reg(reg));
+        assert!(!parse_reg(&reg.replace("0x0", "0x1dc3a2b7c1d4e00")));
+    }
+
+    /// Captures the camera only, counting the checks.
+    struct Fake {
+        checks: Arc<AtomicU32>,
+    }
+
+    impl CaptureProbe for Fake {
+        fn capturing(&self, device: Device) -> Option<bool> {
+            self.checks.fetch_add(1, Ordering::SeqCst);
+            match device {
+                Device::Microphone => Some(false),
+                Device::Camera => Some(true),
+            }
+        }
+    }
+
+    #[test]
+    fn watches_the_configured_devices() {
+        let checks = Arc::new(AtomicU32::new(0));
+        let probe = || Fake {
+            checks: Arc::clone(&checks),
+        };
+        let microphone = AutoPauseConfig {
+            microphone: true,
+            camera: false,
+        };
+        let start = Instant::now();
+        assert!(!AutoPause::new(&microphone, probe()).in_call(start));
+        let auto_pause = AutoPause::new(&AutoPauseConfig::default(), probe());
+        assert!(auto_pause.in_call(start));
+        checks.store(0, Ordering::SeqCst);
+        // The last check still holds.
+        assert!(auto_pause.in_call(start + SECOND * 4));
+        assert_eq!(checks.load(Ordering::SeqCst), 0);
+        assert!(auto_pause.in_call(start + SECOND * 5));
+        assert_eq!(checks.load(Ordering::SeqCst), 2);
+    }
+}
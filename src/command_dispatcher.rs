@@ -1,70 +1,548 @@
 // filepath: src/command_dispatcher.rs
 //! Module handling command dispatching for a Pomodoro timer application.
-use std::{collections::HashMap, sync::mpsc::Sender, time::Duration};
+use std::{
+    io,
+    sync::{
+        Arc, Weak,
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
 
-use crossterm::{
-    event::{self, KeyCode, KeyEvent, KeyModifiers},
-    terminal,
+use crate::{
+    AppError,
+    command_line::{CommandLine, Typed},
+    config::KeysConfig,
+    events::{Event, EventBus},
+    history::{PauseReason, Review},
+    input::{self, Input},
+    keymap::{Count, KeyAction, Keymap},
+    output, prompt,
+    settings::{SettingField, SharedSettings},
+    terminal_guard::RawModeGuard,
+    types::Command,
 };
 
-use crate::{AppError, types::Command};
+/// How long the intent of a work session may be typed, or its review given, before the prompt
+/// gives up.
+const INTENT_TIMEBOX: Duration = Duration::from_secs(30);
+
+/// Input mode of the dispatcher: either keys are mapped to timer commands, or they drive the
+/// runtime settings menu.
+enum Mode {
+    Normal,
+    Settings {
+        selected: usize,
+    },
+    /// The key after a pause may give its reason.
+    PauseReason,
+    /// The key after a work session told an intent may review it, until the prompt gives up.
+    Review {
+        until: Instant,
+    },
+}
 
 pub struct CommandDispatcher {
-    tx: Sender<Command>,
-    command_parser: CommandParser,
+    tx: UnboundedSender<Command>,
+    keymap: Keymap,
+    /// The count typed before the next key.
+    count: Count,
+    command_line: CommandLine,
+    settings: SharedSettings,
+    /// Weak, so that the event bus closes once the scheduler is dropped.
+    events: Weak<EventBus>,
+    filter: KeyFilter,
+    mode: Mode,
+    /// The events of the run, followed to ask for the intent of each work session. The dispatcher
+    /// is woken as a work session starts or ends.
+    starts: Option<Receiver<Event>>,
+    /// The task set during the run, the default intent.
+    task: Option<String>,
+    /// The intent of the running work session, reviewed as it ends.
+    intent: Option<String>,
 }
 
 impl CommandDispatcher {
-    pub fn new(tx: Sender<Command>) -> Self {
+    /// The terminal being resized is told on `events`, which tell the work sessions starting when
+    /// their intent is asked for.
+    pub fn new(
+        tx: UnboundedSender<Command>,
+        settings: SharedSettings,
+        events: Arc<EventBus>,
+    ) -> Self {
+        let starts = settings
+            .lock()
+            .unwrap()
+            .ask_intent
+            .then(|| events.subscribe());
         CommandDispatcher {
-            tx: tx,
-            command_parser: CommandParser::new(),
+            tx,
+            keymap: Keymap::default(),
+            count: Count::default(),
+            command_line: CommandLine::default(),
+            settings,
+            events: Arc::downgrade(&events),
+            filter: KeyFilter::new(&KeysConfig::default()),
+            mode: Mode::Normal,
+            starts,
+            task: None,
+            intent: None,
         }
     }
 
+    /// Keys are bound, and behave when held down or pressed quickly, as `keys` tells.
+    pub fn with_keys(mut self, keys: &KeysConfig) -> Self {
+        self.keymap = Keymap::new(keys.layout, &keys.bindings);
+        self.filter = KeyFilter::new(keys);
+        self
+    }
+
     pub fn run(&mut self) -> Result<(), AppError> {
-        println!(
-            "Controls: [p] Pause | [Space] Toggle | [r] Resume | [s] Skip break | [x] Reset | [q]/[Esc]/[Ctrl+C] Quit\n"
-        );
-        terminal::enable_raw_mode().unwrap();
-        loop {
-            if event::poll(Duration::from_secs(1)).unwrap() {
-                if let event::Event::Key(key_event) = event::read().unwrap() {
-                    if (key_event.modifiers == KeyModifiers::CONTROL
-                        && (key_event.code == KeyCode::Char('c')))
-                        || key_event.code == KeyCode::Char('q')
-                        || key_event.code == KeyCode::Esc
-                    {
-                        break;
+        output::line(format!("Controls: {}\n", self.keymap.controls()));
+        let _raw_mode = RawModeGuard::enable()?;
+        let waker = input::waker();
+        let stop = waker.clone();
+        let _watching = on_stop(&self.tx, move || stop.wake())?;
+        if let Some(run) = self.starts.take() {
+            self.starts = Some(follow(run, waker));
+        }
+        match self.dispatch() {
+            // The run ended on its own, e.g. after its sets, as the key was pressed.
+            Err(AppError::ChannelSend(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Whether the timer stopped listening to commands, the scheduler being dropped.
+    fn stopped(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(events) = self.events.upgrade() {
+            events.emit(event);
+        }
+    }
+
+    fn dispatch(&mut self) -> Result<(), AppError> {
+        while let Some(terminal_event) = self.next_event()? {
+            let key_event = match terminal_event {
+                event::Event::Key(key_event) => key_event,
+                event::Event::Resize(columns, rows) => {
+                    output::resize(columns);
+                    self.emit(Event::Resized { columns, rows });
+                    continue;
+                }
+                // Pasted text is not meant as commands.
+                _ => continue,
+            };
+            if !self.filter.admits(&key_event) {
+                continue;
+            }
+            if key_event.modifiers == KeyModifiers::CONTROL && key_event.code == KeyCode::Char('c')
+            {
+                break;
+            }
+            // Any other key than a review goes on as usual, the session staying without one.
+            if let Mode::Review { until } = self.mode {
+                self.mode = Mode::Normal;
+                if let KeyCode::Char(key) = key_event.code
+                    && let Some(review) = Review::from_key(key)
+                    && Instant::now() < until
+                {
+                    output::line(format!("Reviewed: {}", review));
+                    self.emit(Event::Reviewed { review });
+                    continue;
+                }
+            }
+            // Any other key than a reason goes on as usual, the pause staying without one.
+            if let Mode::PauseReason = self.mode {
+                self.mode = Mode::Normal;
+                if let KeyCode::Char(key) = key_event.code
+                    && let Some(reason) = PauseReason::from_key(key)
+                {
+                    output::line(format!("Paused for: {}", reason));
+                    self.tx.send(Command::PauseReason(reason))?;
+                    continue;
+                }
+            }
+            if let Mode::Normal = self.mode
+                && let Some(digit) = self.keymap.digit(&key_event)
+            {
+                self.count.push(digit);
+                continue;
+            }
+            // The count applies to the key right after the digits only.
+            let count = self.count.take();
+            match self.mode {
+                Mode::Normal => match self.keymap.get(&key_event) {
+                    Some(KeyAction::Quit) => break,
+                    Some(KeyAction::Settings) => {
+                        self.mode = Mode::Settings { selected: 0 };
+                        self.print_settings(0);
                     }
-                    if let Some(cmd) = self.command_parser.get(&key_event) {
-                        self.tx.send(cmd.clone()).map_err(AppError::ChannelSend)?;
+                    Some(KeyAction::CommandLine) => match self.command_line.read()? {
+                        Some(Typed::Command(cmd)) => self.tx.send(cmd)?,
+                        Some(Typed::Quit) => break,
+                        None => {}
+                    },
+                    _ => {
+                        if let Some(cmd) = self.keymap.command(&key_event, count)
+                            && self.filter.debounced(&cmd, Instant::now())
+                        {
+                            let ask = cmd == Command::Pause
+                                && self.settings.lock().unwrap().ask_pause_reason;
+                            self.tx.send(cmd)?;
+                            if ask {
+                                self.mode = Mode::PauseReason;
+                                output::line(pause_reason_prompt());
+                            }
+                        }
                     }
+                },
+                Mode::Settings { selected } => self.handle_settings_key(&key_event, selected),
+                Mode::PauseReason | Mode::Review { .. } => {
+                    unreachable!("the reason and the review are handled before")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the next terminal event, none once the run ended on its own, e.g. after its sets
+    /// or when the runway is too short, so that the terminal leaves raw mode without a key press.
+    fn next_event(&mut self) -> Result<Option<event::Event>, AppError> {
+        loop {
+            if self.stopped() {
+                return Ok(None);
+            }
+            match input::next()? {
+                Input::Event(terminal_event) => return Ok(Some(terminal_event)),
+                // The run ended, or a work session started or ended.
+                Input::Woken => self.follow_run()?,
+            }
+        }
+    }
+
+    /// Asks for the review of the work session which ended since the dispatcher was last woken,
+    /// and for the intent of the one which started, the task being the default.
+    fn follow_run(&mut self) -> Result<(), AppError> {
+        let Some(starts) = &self.starts else {
+            return Ok(());
+        };
+        let (mut work_started, mut ended) = (false, None);
+        for event in starts.try_iter() {
+            match event {
+                Event::SessionStarted { session, .. } => work_started = session.is_work(),
+                Event::TaskChanged { task } => self.task = Some(task),
+                Event::Intent { intent } => self.intent = Some(intent),
+                Event::SessionEnded {
+                    session, outcome, ..
+                } if session.is_work() => {
+                    ended = self.intent.take().filter(|_| outcome.is_done());
                 }
+                _ => {}
             }
         }
-        terminal::disable_raw_mode().unwrap();
+        if let Mode::Review { until } = self.mode
+            && until <= Instant::now()
+        {
+            self.mode = Mode::Normal;
+        }
+        if let Some(intent) = ended {
+            self.mode = Mode::Review {
+                until: Instant::now() + INTENT_TIMEBOX,
+            };
+            output::line(review_prompt(&intent));
+        }
+        if !work_started {
+            return Ok(());
+        }
+        let default = {
+            let settings = self.settings.lock().unwrap();
+            // Private mode keeps the task off the screen.
+            (!settings.private)
+                .then(|| self.task.clone().or_else(|| settings.task.clone()))
+                .flatten()
+        };
+        if let Some(intent) = prompt::line_within(
+            "What's the ONE thing this pomodoro?",
+            default.as_deref(),
+            INTENT_TIMEBOX,
+        )? {
+            output::line(format!("Intent: {}", intent));
+            self.tx.send(Command::Intent(intent))?;
+        }
         Ok(())
     }
+
+    fn handle_settings_key(&mut self, key_event: &KeyEvent, selected: usize) {
+        let field = SettingField::ALL[selected];
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                let selected = (selected + SettingField::ALL.len() - 1) % SettingField::ALL.len();
+                self.mode = Mode::Settings { selected };
+                self.print_settings(selected);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                let selected = (selected + 1) % SettingField::ALL.len();
+                self.mode = Mode::Settings { selected };
+                self.print_settings(selected);
+            }
+            KeyCode::Left | KeyCode::Char('-') => {
+                self.settings.lock().unwrap().adjust(field, -1);
+                self.print_settings(selected);
+            }
+            KeyCode::Right | KeyCode::Char('+') => {
+                self.settings.lock().unwrap().adjust(field, 1);
+                self.print_settings(selected);
+            }
+            KeyCode::Enter | KeyCode::Esc => self.close_settings(),
+            _ if self.keymap.get(key_event) == Some(&KeyAction::Settings) => self.close_settings(),
+            _ => {}
+        }
+    }
+
+    fn close_settings(&mut self) {
+        self.mode = Mode::Normal;
+        output::line("\nSettings saved, they will apply from the next session.");
+    }
+
+    fn print_settings(&self, selected: usize) {
+        let settings = self.settings.lock().unwrap();
+        let mut close = vec!["[Enter]".to_string(), "[Esc]".to_string()];
+        close.extend(self.keymap.keys(&KeyAction::Settings));
+        let mut menu = format!(
+            "\nSettings: [Up/Down] Select | [Left/Right] Adjust | {} Close",
+            close.join("/")
+        );
+        for (index, field) in SettingField::ALL.iter().enumerate() {
+            menu.push_str(&format!(
+                "\n{} {:<14} {}",
+                if index == selected { ">" } else { " " },
+                field.label(),
+                settings.value(*field)
+            ));
+        }
+        output::line(menu);
+    }
 }
 
-struct CommandParser {
-    commands: HashMap<String, Command>,
+/// Calls `wake` once the timer stops listening to `tx`, the scheduler being dropped, unless the
+/// returned sender is dropped first. The commands are left to close either way, so that the timer
+/// still sees them close when the dispatcher stops first.
+fn on_stop(
+    tx: &UnboundedSender<Command>,
+    wake: impl FnOnce() + Send + 'static,
+) -> io::Result<oneshot::Sender<()>> {
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let tx = tx.clone();
+    let (watching, dropped) = oneshot::channel();
+    thread::spawn(move || {
+        runtime.block_on(async move {
+            tokio::select! {
+                _ = tx.closed() => wake(),
+                _ = dropped => {}
+            }
+        })
+    });
+    Ok(watching)
 }
 
-impl CommandParser {
-    fn new() -> Self {
-        let mut commands = HashMap::new();
-        commands.insert(KeyCode::Char('p').to_string(), Command::Pause);
-        commands.insert(KeyCode::Char(' ').to_string(), Command::PauseResume);
-        commands.insert(KeyCode::Char('x').to_string(), Command::Reset);
-        commands.insert(KeyCode::Char('r').to_string(), Command::Resume);
-        commands.insert(KeyCode::Char('s').to_string(), Command::Skip);
+/// Forwards the events of `run`, waking the dispatcher as a work session starts or ends.
+fn follow(run: Receiver<Event>, waker: input::Waker) -> Receiver<Event> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for event in run {
+            let wake = matches!(
+                &event,
+                Event::SessionStarted { session, .. } | Event::SessionEnded { session, .. }
+                    if session.is_work()
+            );
+            if tx.send(event).is_err() {
+                break;
+            }
+            if wake {
+                waker.wake();
+            }
+        }
+    });
+    rx
+}
+
+/// The line asking how the work session went against its intent.
+fn review_prompt(intent: &str) -> String {
+    let reviews: Vec<String> = Review::ALL
+        .iter()
+        .map(|review| format!("[{}] {}", review.key(), review))
+        .collect();
+    format!(
+        "Intent: {} - how did it go? {} | any other key to skip",
+        intent,
+        reviews.join(" | ")
+    )
+}
+
+/// The line asking for the reason of the pause, e.g. "Pause reason: [c] Call | ...".
+fn pause_reason_prompt() -> String {
+    let reasons: Vec<String> = PauseReason::ALL
+        .iter()
+        .map(|reason| format!("[{}] {}", reason.key(), reason))
+        .collect();
+    format!(
+        "Pause reason: {} | any other key to skip",
+        reasons.join(" | ")
+    )
+}
+
+/// Drops the key events which are not meant as commands: releases, repeats of a key held down
+/// unless they are wanted, and presses of the pause toggle right after the previous one.
+struct KeyFilter {
+    repeat: bool,
+    debounce: Duration,
+    last_toggle: Option<Instant>,
+}
+
+impl KeyFilter {
+    fn new(keys: &KeysConfig) -> Self {
+        KeyFilter {
+            repeat: keys.repeat,
+            debounce: Duration::from_millis(keys.debounce),
+            last_toggle: None,
+        }
+    }
+
+    /// Whether `key_event` is a press, or a repeat when they are wanted. Terminals only tell them
+    /// apart with the keyboard enhancements of kitty, and Windows.
+    fn admits(&self, key_event: &KeyEvent) -> bool {
+        match key_event.kind {
+            KeyEventKind::Press => true,
+            KeyEventKind::Repeat => self.repeat,
+            KeyEventKind::Release => false,
+        }
+    }
+
+    /// Whether `command`, sent at `now`, is not a toggle too close to the previous one. Held down,
+    /// a key repeats as presses in most terminals: the presses keep being dropped while they come
+    /// faster than the debounce.
+    fn debounced(&mut self, command: &Command, now: Instant) -> bool {
+        if *command != Command::PauseResume {
+            return true;
+        }
+        let previous = self.last_toggle.replace(now);
+        previous.is_none_or(|previous| now.duration_since(previous) >= self.debounce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{
+        runway::{Agenda, Runway},
+        scheduler::Scheduler,
+        settings::{Settings, Span},
+    };
+
+    fn settings() -> SharedSettings {
+        Settings {
+            cycles: 1,
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        }
+        .into_shared()
+    }
+
+    /// A meeting starting whenever asked.
+    struct Busy;
+
+    impl Agenda for Busy {
+        fn next(&self, from: DateTime<Utc>, _: DateTime<Utc>) -> Option<(String, DateTime<Utc>)> {
+            Some(("Standup".to_string(), from))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_once_the_run_ends_on_its_own() {
+        let settings = settings();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let events = Arc::new(EventBus::new());
+        let subscription = events.subscribe();
+        let dispatcher = CommandDispatcher::new(tx, Arc::clone(&settings), Arc::clone(&events));
+        let (wake, woken) = std::sync::mpsc::channel();
+        let _watching = on_stop(&dispatcher.tx, move || wake.send(()).unwrap()).unwrap();
+        let mut scheduler = Scheduler::new(rx, events, settings).unwrap().with_sets(1);
+        // No key is pressed during the run.
+        let summary = scheduler.run().await.unwrap();
+        assert_eq!(summary.sets, 1);
+        assert!(!dispatcher.stopped());
+        drop(scheduler);
+        // The dispatcher waiting for a key is woken.
+        woken.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(dispatcher.stopped());
+        // The dispatcher does not keep the event bus open, so its subscribers finish.
+        assert!(subscription.iter().count() > 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_once_the_runway_is_too_short() {
+        let settings = settings();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let events = Arc::new(EventBus::new());
+        let dispatcher = CommandDispatcher::new(tx, Arc::clone(&settings), Arc::clone(&events));
+        let runway = Runway::new(Span { seconds: 10 * 60 }, None, Some(Box::new(Busy)));
+        let mut scheduler = Scheduler::new(rx, events, settings)
+            .unwrap()
+            .with_runway(runway);
+        assert_eq!(scheduler.run().await.unwrap().work_cycles, 0);
+        drop(scheduler);
+        assert!(dispatcher.stopped());
+    }
+
+    #[tokio::test]
+    async fn leaves_the_commands_to_close() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (wake, woken) = std::sync::mpsc::channel();
+        let watching = on_stop(&tx, move || wake.send(()).unwrap()).unwrap();
+        // The dispatcher stops first, e.g. on Ctrl-C.
+        drop(watching);
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+        assert!(woken.recv().is_err());
+    }
 
-        CommandParser { commands }
+    #[test]
+    fn drops_the_repeats_and_the_releases() {
+        let filter = KeyFilter::new(&KeysConfig::default());
+        let key = |kind| KeyEvent::new_with_kind(KeyCode::Char(' '), KeyModifiers::NONE, kind);
+        assert!(filter.admits(&key(KeyEventKind::Press)));
+        assert!(!filter.admits(&key(KeyEventKind::Repeat)));
+        assert!(!filter.admits(&key(KeyEventKind::Release)));
+        let repeating = KeyFilter::new(&KeysConfig {
+            repeat: true,
+            debounce: 0,
+            ..KeysConfig::default()
+        });
+        assert!(repeating.admits(&key(KeyEventKind::Repeat)));
     }
 
-    fn get(&self, input: &KeyEvent) -> Option<Command> {
-        self.commands.get(&input.code.to_string()).cloned()
+    #[test]
+    fn debounces_the_pause_toggle() {
+        let mut filter = KeyFilter::new(&KeysConfig::default());
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        assert!(filter.debounced(&Command::PauseResume, at(0)));
+        // Held down, the key repeats every 30 ms after half a second.
+        assert!(!filter.debounced(&Command::PauseResume, at(500)));
+        assert!(!filter.debounced(&Command::PauseResume, at(530)));
+        assert!(filter.debounced(&Command::Skip, at(540)));
+        assert!(filter.debounced(&Command::PauseResume, at(1500)));
     }
 }
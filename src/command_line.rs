@@ -0,0 +1,260 @@
+// filepath: src/command_line.rs
+//! Module reading the commands typed after `:` in a Pomodoro timer application, as in Vim:
+//! `:extend 5`, `:task Write the report`, `:jump 3`, `:skip` or `:quit`. The line is edited in raw
+//! mode, Tab completing the name of the command and Up and Down going through the lines typed
+//! before.
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{Clear, ClearType},
+};
+
+use crate::{
+    input, output,
+    types::{Command, SessionIndex},
+};
+
+/// Longest extension of a session, in minutes.
+pub const MAX_EXTEND: u64 = 120;
+
+/// The names of the commands, completed by Tab.
+const NAMES: [&str; 10] = [
+    "acknowledge",
+    "extend",
+    "jump",
+    "pause",
+    "quit",
+    "reset",
+    "resume",
+    "skip",
+    "task",
+    "toggle",
+];
+
+/// What a line typed does.
+#[derive(Debug, PartialEq)]
+pub enum Typed {
+    Command(Command),
+    Quit,
+}
+
+/// Reads `line`, e.g. "extend 5". Names can be shortened as long as they stay unambiguous.
+pub fn parse(line: &str) -> Result<Typed, String> {
+    let line = line.trim();
+    let (name, argument) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(name, argument)| (name, argument.trim()));
+    let matching: Vec<&str> = NAMES
+        .iter()
+        .copied()
+        .filter(|known| known.starts_with(name))
+        .collect();
+    let name = match matching[..] {
+        _ if name.is_empty() => return Err("type a command, e.g. :skip".to_string()),
+        [name] => name,
+        // A name typed in full, e.g. "re" is ambiguous but "reset" is not.
+        _ if matching.contains(&name) => name,
+        [] => return Err(format!("unknown command {:?}", name)),
+        _ => {
+            return Err(format!("{:?} is ambiguous: {}", name, matching.join(", ")));
+        }
+    };
+    let command = match name {
+        "extend" => {
+            let minutes = argument
+                .parse()
+                .ok()
+                .filter(|minutes| (1..=MAX_EXTEND).contains(minutes))
+                .ok_or_else(|| {
+                    format!("give the minutes to extend by, from 1 to {}", MAX_EXTEND)
+                })?;
+            return Ok(Typed::Command(Command::Extend(Duration::from_secs(
+                minutes * 60,
+            ))));
+        }
+        "jump" => {
+            let index = argument
+                .parse()
+                .ok()
+                .and_then(SessionIndex::new)
+                .ok_or("give the session of the set to jump to, from 1")?;
+            return Ok(Typed::Command(Command::Jump(index)));
+        }
+        "task" if argument.is_empty() => return Err("give the task, e.g. :task Write".to_string()),
+        "task" => return Ok(Typed::Command(Command::SetTask(argument.to_string()))),
+        _ if !argument.is_empty() => return Err(format!(":{} takes no argument", name)),
+        "acknowledge" => Command::Acknowledge,
+        "pause" => Command::Pause,
+        "quit" => return Ok(Typed::Quit),
+        "reset" => Command::Reset,
+        "resume" => Command::Resume,
+        "skip" => Command::Skip,
+        // Toggling.
+        _ => Command::PauseResume,
+    };
+    Ok(Typed::Command(command))
+}
+
+/// `line` with the name of its command completed, as far as the names starting with it agree,
+/// and the names it may be completed to when they do not.
+fn complete(line: &str) -> (String, Vec<&'static str>) {
+    if line.contains(char::is_whitespace) {
+        return (line.to_string(), Vec::new());
+    }
+    let matching: Vec<&str> = NAMES
+        .iter()
+        .copied()
+        .filter(|name| name.starts_with(line))
+        .collect();
+    let Some(first) = matching.first() else {
+        return (line.to_string(), Vec::new());
+    };
+    let common = matching.iter().fold(first.len(), |common, name| {
+        first
+            .chars()
+            .zip(name.chars())
+            .take(common)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    match matching[..] {
+        [name] => (format!("{} ", name), Vec::new()),
+        _ => (first[..common].to_string(), matching),
+    }
+}
+
+/// The line editor of the commands, keeping the lines typed during the run.
+#[derive(Default)]
+pub struct CommandLine {
+    history: Vec<String>,
+}
+
+impl CommandLine {
+    /// Reads a line after `:`, the keys being read in raw mode and the progress bar hidden
+    /// meanwhile. Returns none when cancelled with Esc or Ctrl+C, or when erasing the `:`.
+    pub fn read(&mut self) -> io::Result<Option<Typed>> {
+        output::interact(|| {
+            let line = self.edit()?;
+            let mut stderr = io::stderr();
+            let Some(line) = line else {
+                write!(stderr, "\r")?;
+                execute!(stderr, Clear(ClearType::CurrentLine))?;
+                return Ok(None);
+            };
+            write!(stderr, "\r\n")?;
+            if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                self.history.push(line.clone());
+            }
+            match parse(&line) {
+                Ok(typed) => Ok(Some(typed)),
+                Err(e) => {
+                    write!(stderr, "error: {}\r\n", e)?;
+                    Ok(None)
+                }
+            }
+        })
+    }
+
+    /// Edits the line until Enter, drawn again after each key.
+    fn edit(&self) -> io::Result<Option<String>> {
+        let mut stderr = io::stderr();
+        let mut line = String::new();
+        // Index in the history of the line shown, the new one being past the end.
+        let mut recalled = self.history.len();
+        loop {
+            write!(stderr, "\r")?;
+            execute!(stderr, Clear(ClearType::CurrentLine))?;
+            write!(stderr, ":{}", line)?;
+            stderr.flush()?;
+            let Some(event) = input::read(None)? else {
+                continue;
+            };
+            let key = match event {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                // Only the first line of a paste, as Enter would end the command.
+                Event::Paste(text) => {
+                    line.push_str(text.lines().next().unwrap_or_default());
+                    continue;
+                }
+                _ => continue,
+            };
+            match (key.code, key.modifiers) {
+                (KeyCode::Char('c'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
+                    return Ok(None);
+                }
+                (KeyCode::Enter, _) => return Ok(Some(line)),
+                (KeyCode::Backspace, _) if line.is_empty() => return Ok(None),
+                (KeyCode::Backspace, _) => {
+                    line.pop();
+                }
+                (KeyCode::Tab, _) => {
+                    let (completed, candidates) = complete(&line);
+                    line = completed;
+                    if !candidates.is_empty() {
+                        write!(stderr, "\r\n{}\r\n", candidates.join("  "))?;
+                    }
+                }
+                (KeyCode::Up, _) if recalled > 0 => {
+                    recalled -= 1;
+                    line = self.history[recalled].clone();
+                }
+                (KeyCode::Down, _) if recalled < self.history.len() => {
+                    recalled += 1;
+                    line = self.history.get(recalled).cloned().unwrap_or_default();
+                }
+                (KeyCode::Char(c), modifiers) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                    line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_commands() {
+        assert_eq!(
+            parse("extend 5"),
+            Ok(Typed::Command(Command::Extend(Duration::from_secs(300))))
+        );
+        assert_eq!(
+            parse("task  Write the report "),
+            Ok(Typed::Command(Command::SetTask(
+                "Write the report".to_string()
+            )))
+        );
+        assert_eq!(parse("sk"), Ok(Typed::Command(Command::Skip)));
+        assert_eq!(parse("q"), Ok(Typed::Quit));
+        assert_eq!(parse("toggle"), Ok(Typed::Command(Command::PauseResume)));
+        assert_eq!(parse("reset"), Ok(Typed::Command(Command::Reset)));
+        assert!(parse("re").unwrap_err().contains("ambiguous"));
+        assert!(parse("extend").is_err());
+        assert!(parse("extend 500").is_err());
+        assert!(parse("task").is_err());
+        assert!(parse("skip 2").is_err());
+        assert!(parse("jump").is_err());
+        assert!(parse("jump 0").is_err());
+        assert_eq!(
+            parse("j 3"),
+            Ok(Typed::Command(Command::Jump(SessionIndex::new(3).unwrap())))
+        );
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn completes_the_names() {
+        assert_eq!(complete("ex"), ("extend ".to_string(), Vec::new()));
+        assert_eq!(complete("re"), ("res".to_string(), vec!["reset", "resume"]));
+        assert_eq!(complete("task W"), ("task W".to_string(), Vec::new()));
+        assert_eq!(complete("z"), ("z".to_string(), Vec::new()));
+    }
+}
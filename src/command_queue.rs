@@ -0,0 +1,253 @@
+// filepath: src/command_queue.rs
+//! Module defining how the commands queued while the timer is busy are replayed, for a Pomodoro
+//! timer application.
+use std::{collections::VecDeque, mem};
+
+use tokio::sync::mpsc::{UnboundedReceiver, error::TryRecvError};
+
+use crate::Command;
+
+/// The commands sent to the timer, read in batches: every command already queued when the timer
+/// asks for the next one is taken at once, and the batch is coalesced.
+///
+/// - Quitting wins: once the command dispatcher stopped, the queued commands are dropped.
+/// - Pause, resume and toggle commands are folded into their net effect, so an even number of
+///   toggles cancels out.
+/// - Repeated resets or skips count once.
+/// - The commands of a batch otherwise keep the order in which they were first sent.
+///
+/// Commands sent during a session which already ended are stale: the scheduler drops them with
+/// [`CommandQueue::discard_pending`] before starting the next session.
+pub struct CommandQueue {
+    rx: UnboundedReceiver<Command>,
+    pending: VecDeque<Command>,
+}
+
+/// Net effect of a series of pause, resume and toggle commands, whatever the timer state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PauseEffect {
+    Nothing,
+    Toggle,
+    Pause,
+    Resume,
+}
+
+impl PauseEffect {
+    fn then(self, command: &Command) -> Self {
+        match (self, command) {
+            (_, Command::Pause) => PauseEffect::Pause,
+            (_, Command::Resume) => PauseEffect::Resume,
+            (PauseEffect::Nothing, Command::PauseResume) => PauseEffect::Toggle,
+            (PauseEffect::Toggle, Command::PauseResume) => PauseEffect::Nothing,
+            (PauseEffect::Pause, Command::PauseResume) => PauseEffect::Resume,
+            (PauseEffect::Resume, Command::PauseResume) => PauseEffect::Pause,
+            (effect, _) => effect,
+        }
+    }
+
+    fn command(self) -> Option<Command> {
+        match self {
+            PauseEffect::Nothing => None,
+            PauseEffect::Toggle => Some(Command::PauseResume),
+            PauseEffect::Pause => Some(Command::Pause),
+            PauseEffect::Resume => Some(Command::Resume),
+        }
+    }
+}
+
+impl CommandQueue {
+    pub fn new(rx: UnboundedReceiver<Command>) -> Self {
+        CommandQueue {
+            rx,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Waits for the next command, or returns `None` once the command dispatcher stopped.
+    ///
+    /// This is cancel safe: if the returned future is dropped, no command is lost.
+    pub async fn next(&mut self) -> Option<Command> {
+        loop {
+            if self.rx.is_closed() {
+                self.pending.clear();
+                return None;
+            }
+            if let Some(command) = self.pending.pop_front() {
+                return Some(command);
+            }
+            let mut batch = vec![self.rx.recv().await?];
+            loop {
+                match self.rx.try_recv() {
+                    Ok(command) => batch.push(command),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return None,
+                }
+            }
+            self.pending = coalesce(batch);
+        }
+    }
+
+    /// Drops the commands sent so far.
+    pub fn discard_pending(&mut self) {
+        self.pending.clear();
+        while self.rx.try_recv().is_ok() {}
+    }
+}
+
+/// Reduces a batch of commands to what they mean to the timer.
+fn coalesce(batch: Vec<Command>) -> VecDeque<Command> {
+    // The net pause effect is only known at the end of the batch, so its place is kept empty
+    // until then.
+    let mut coalesced: Vec<Option<Command>> = Vec::new();
+    let mut pause_slot = None;
+    let mut pause_effect = PauseEffect::Nothing;
+    for command in batch {
+        match command {
+            Command::Pause | Command::Resume | Command::PauseResume => {
+                pause_slot.get_or_insert_with(|| {
+                    coalesced.push(None);
+                    coalesced.len() - 1
+                });
+                pause_effect = pause_effect.then(&command);
+            }
+            Command::Acknowledge
+            | Command::Reset
+            | Command::Skip
+            | Command::SkipBreaks(_)
+            | Command::Jump(_)
+            | Command::Reveal => {
+                let repeated = coalesced
+                    .iter()
+                    .flatten()
+                    .any(|queued| mem::discriminant(queued) == mem::discriminant(&command));
+                if !repeated {
+                    coalesced.push(Some(command));
+                }
+            }
+            // Extensions add up, and tasks, pause reasons and intents follow each other.
+            Command::Extend(_)
+            | Command::SetTask(_)
+            | Command::PauseReason(_)
+            | Command::Intent(_) => coalesced.push(Some(command)),
+        }
+    }
+    if let Some(slot) = pause_slot {
+        coalesced[slot] = pause_effect.command();
+    }
+    coalesced.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{sync::mpsc, time};
+
+    use super::*;
+
+    /// Sends `commands` at once and returns what the queue makes of them.
+    async fn replay(commands: Vec<Command>) -> Vec<Command> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut queue = CommandQueue::new(rx);
+        for command in commands {
+            tx.send(command).unwrap();
+        }
+        let mut replayed = Vec::new();
+        while let Ok(Some(command)) = time::timeout(Duration::from_secs(1), queue.next()).await {
+            replayed.push(command);
+        }
+        replayed
+    }
+
+    fn names(commands: &[Command]) -> Vec<String> {
+        commands
+            .iter()
+            .map(|command| format!("{command:?}"))
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn even_toggles_cancel_out() {
+        let replayed = replay(vec![
+            Command::PauseResume,
+            Command::PauseResume,
+            Command::Skip,
+        ])
+        .await;
+        assert_eq!(names(&replayed), ["Skip"]);
+        assert!(replay(vec![Command::PauseResume; 4]).await.is_empty());
+        assert_eq!(
+            names(&replay(vec![Command::PauseResume; 3]).await),
+            ["PauseResume"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_commands_fold_into_their_net_effect() {
+        let replayed = replay(vec![Command::Pause, Command::Resume]).await;
+        assert_eq!(names(&replayed), ["Resume"]);
+        let replayed = replay(vec![Command::Pause, Command::PauseResume]).await;
+        assert_eq!(names(&replayed), ["Resume"]);
+        let replayed = replay(vec![Command::Resume, Command::PauseResume]).await;
+        assert_eq!(names(&replayed), ["Pause"]);
+        let replayed = replay(vec![Command::PauseResume, Command::Pause, Command::Pause]).await;
+        assert_eq!(names(&replayed), ["Pause"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repeated_commands_count_once_in_order() {
+        let replayed = replay(vec![
+            Command::Skip,
+            Command::Pause,
+            Command::Reset,
+            Command::Skip,
+            Command::Reset,
+        ])
+        .await;
+        assert_eq!(names(&replayed), ["Skip", "Pause", "Reset"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn commands_sent_later_are_a_new_batch() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut queue = CommandQueue::new(rx);
+        tx.send(Command::PauseResume).unwrap();
+        assert!(matches!(queue.next().await, Some(Command::PauseResume)));
+        tx.send(Command::PauseResume).unwrap();
+        assert!(matches!(queue.next().await, Some(Command::PauseResume)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quitting_drops_the_queued_commands() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut queue = CommandQueue::new(rx);
+        tx.send(Command::Pause).unwrap();
+        tx.send(Command::Reset).unwrap();
+        drop(tx);
+        assert!(queue.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quitting_drops_the_rest_of_a_batch() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut queue = CommandQueue::new(rx);
+        tx.send(Command::Reset).unwrap();
+        tx.send(Command::Skip).unwrap();
+        assert!(matches!(queue.next().await, Some(Command::Reset)));
+        drop(tx);
+        assert!(queue.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn discarded_commands_are_not_replayed() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut queue = CommandQueue::new(rx);
+        tx.send(Command::Reset).unwrap();
+        tx.send(Command::Skip).unwrap();
+        assert!(matches!(queue.next().await, Some(Command::Reset)));
+        tx.send(Command::Pause).unwrap();
+        queue.discard_pending();
+        tx.send(Command::Resume).unwrap();
+        assert!(matches!(queue.next().await, Some(Command::Resume)));
+    }
+}
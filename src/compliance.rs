@@ -0,0 +1,216 @@
+// filepath: src/compliance.rs
+//! Module telling how well the user of a Pomodoro timer application takes their breaks. Each break
+//! is taken until its end, cut short or skipped, and the break compliance is the share of the
+//! breaks taken until their end. When the `[compliance]` table is set, skipping a break while the
+//! compliance of the day is below its threshold nags with a notification.
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate};
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    SessionType,
+    config::ComplianceConfig,
+    desktop::Notification,
+    events::{Event, SessionOutcome},
+    quiet::Quiet,
+    settings::SharedSettings,
+};
+
+/// Time into a break before which skipping it counts as skipping it, and after which as cutting it
+/// short.
+pub const SKIPPED_WITHIN: Duration = Duration::from_secs(60);
+
+/// How much of a break was taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Taken {
+    Full,
+    CutShort,
+    Skipped,
+}
+
+/// How much of a break ending with `outcome` after `elapsed` was taken, none when the run ended
+/// during the break.
+pub fn taken(outcome: SessionOutcome, elapsed: Duration) -> Option<Taken> {
+    match outcome {
+        SessionOutcome::Completed => Some(Taken::Full),
+        SessionOutcome::Interrupted => None,
+        SessionOutcome::FinishedEarly | SessionOutcome::Skipped if elapsed < SKIPPED_WITHIN => {
+            Some(Taken::Skipped)
+        }
+        SessionOutcome::FinishedEarly | SessionOutcome::Skipped => Some(Taken::CutShort),
+    }
+}
+
+/// Counts the breaks of the day from the events and nags when one of them is not taken until its
+/// end while the break compliance of the day is below the threshold.
+pub struct Nag {
+    /// Compliance, in percent, below which the nagging starts.
+    threshold: u64,
+    settings: SharedSettings,
+    day: NaiveDate,
+    /// Breaks taken until their end on `day`, and breaks in all.
+    taken: u64,
+    breaks: u64,
+    quiet: Option<Arc<Quiet>>,
+}
+
+impl Nag {
+    /// `taken` of the `breaks` taken on `day` before this run were taken until their end.
+    pub fn new(
+        config: &ComplianceConfig,
+        settings: SharedSettings,
+        day: NaiveDate,
+        taken: u64,
+        breaks: u64,
+    ) -> Self {
+        Nag {
+            threshold: config.nag_below,
+            settings,
+            day,
+            taken,
+            breaks,
+            quiet: None,
+        }
+    }
+
+    /// Holds the nagging back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    /// Handles the events until the event bus is dropped.
+    pub fn run(mut self, events: Receiver<Event>) {
+        for event in events {
+            if let Some(message) = self.handle(&event, Local::now())
+                && !self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+            {
+                // The timer already reports notification failures.
+                let _ = Notification::new()
+                    .summary("Pomodoro Timer")
+                    .body(&message)
+                    .icon("dialog-warning")
+                    .show();
+            }
+        }
+    }
+
+    /// Returns the nagging to send after `event`, if any.
+    pub fn handle(&mut self, event: &Event, now: DateTime<Local>) -> Option<String> {
+        let Event::SessionEnded {
+            session:
+                SessionType {
+                    kind: SessionKind::ShortBreak,
+                    ..
+                }
+                | SessionType {
+                    kind: SessionKind::LongBreak,
+                    ..
+                },
+            elapsed,
+            outcome,
+        } = event
+        else {
+            return None;
+        };
+        if now.date_naive() != self.day {
+            self.day = now.date_naive();
+            self.taken = 0;
+            self.breaks = 0;
+        }
+        let taken = taken(*outcome, *elapsed)?;
+        self.breaks += 1;
+        if taken == Taken::Full {
+            self.taken += 1;
+            return None;
+        }
+        let compliance = self.taken * 100 / self.breaks;
+        if compliance >= self.threshold || self.settings.lock().unwrap().no_notify {
+            return None;
+        }
+        Some(format!(
+            "Break {}: {}% of today's breaks were taken, below your {}%. Step away from the screen \
+             during the next one.",
+            if taken == Taken::Skipped {
+                "skipped"
+            } else {
+                "cut short"
+            },
+            compliance,
+            self.threshold
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::settings::Settings;
+
+    fn ended(elapsed: u64, outcome: SessionOutcome) -> Event {
+        Event::SessionEnded {
+            session: SessionType::short_break(),
+            elapsed: Duration::from_secs(elapsed),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn tells_skipped_from_cut_short() {
+        assert_eq!(
+            taken(SessionOutcome::Completed, Duration::from_secs(300)),
+            Some(Taken::Full)
+        );
+        assert_eq!(
+            taken(SessionOutcome::Skipped, Duration::ZERO),
+            Some(Taken::Skipped)
+        );
+        assert_eq!(
+            taken(SessionOutcome::Skipped, Duration::from_secs(120)),
+            Some(Taken::CutShort)
+        );
+        assert_eq!(
+            taken(SessionOutcome::Interrupted, Duration::from_secs(120)),
+            None
+        );
+    }
+
+    #[test]
+    fn nags_below_the_threshold_only() {
+        let config = ComplianceConfig { nag_below: 70 };
+        let day = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let now = Local.with_ymd_and_hms(2026, 10, 14, 11, 0, 0).unwrap();
+        let mut nag = Nag::new(&config, Settings::default().into_shared(), day, 3, 3);
+        // 3 out of 4, then 4 out of 5.
+        assert_eq!(nag.handle(&ended(0, SessionOutcome::Skipped), now), None);
+        assert_eq!(
+            nag.handle(&ended(300, SessionOutcome::Completed), now),
+            None
+        );
+        // 4 out of 6.
+        assert_eq!(
+            nag.handle(&ended(120, SessionOutcome::Skipped), now)
+                .unwrap(),
+            "Break cut short: 66% of today's breaks were taken, below your 70%. Step away from \
+             the screen during the next one."
+        );
+        assert_eq!(
+            nag.handle(&ended(60, SessionOutcome::Interrupted), now),
+            None
+        );
+
+        // Counting starts over the next day.
+        let tomorrow = now + chrono::Days::new(1);
+        assert!(
+            nag.handle(&ended(0, SessionOutcome::Skipped), tomorrow)
+                .unwrap()
+                .starts_with("Break skipped: 0% ")
+        );
+    }
+}
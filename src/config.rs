@@ -0,0 +1,4354 @@
+// filepath: src/config.rs
+//! Module handling the command-line arguments and the configuration file of a Pomodoro timer
+//! application.
+//!
+//! The effective configuration is built from layers, each overriding the previous one: the
+//! defaults, the configuration file, the `RUSTODORO_*` environment variables and the command-line
+//! flags. Every value remembers the layer it comes from.
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+
+use chrono::{
+    Datelike, Local, Weekday,
+    format::{Item, StrftimeItems},
+};
+use clap::{Args, Parser, Subcommand};
+use rustodoro_client::protocol::SessionKind;
+use serde::Deserialize;
+
+#[cfg(unix)]
+use crate::wait::Target;
+use crate::{
+    credentials::Service,
+    focus::{ProcessAction, default_hosts_file},
+    haptics::{self, VibrationsConfig},
+    import::Format,
+    keymap::{KeyAction, Layout, parse_key},
+    notes::{LINE_PLACEHOLDERS, PATH_PLACEHOLDERS},
+    notifier::{MAX_ESCALATE_AFTER, MIN_ESCALATE_AFTER, PLACEHOLDERS, Trigger},
+    pace::TimeOfDay,
+    query::{Filter, KindFilter, Since, SortKey},
+    render::{DisplayMode, Theme},
+    retention::Age,
+    settings::{
+        self, BreakStages, Checkpoint, Jitter, MAX_BREAK_JITTER, MAX_CYCLES, MAX_DAILY_GOAL,
+        MAX_DURATION_MINUTES, MAX_STAGES, MAX_TRANSITION_PAUSE, MINUTE, Ramp, SessionConfig,
+        Settings, Span, Step,
+    },
+    sounds::{Cue, MAX_FADE_IN, MAX_REPEAT, MIN_REPEAT, Sound, SoundsConfig},
+    template,
+};
+
+/// Environment variable overriding the location of the configuration file.
+const CONFIG_ENV: &str = "RUSTODORO_CONFIG";
+
+/// Content written by `config init`.
+const DEFAULT_FILE: &str = "\
+# Rustodoro configuration file.
+# RUSTODORO_* environment variables and command-line flags override these values.
+
+# Work session duration in minutes (1-1440).
+work = 25
+
+# Short break duration in minutes (1-1440).
+short_break = 5
+
+# Long break duration in minutes (1-1440), taken after the last cycle.
+long_break = 15
+
+# How much the breaks vary around their duration, either way, e.g. \"2m\" or \"90s\" (up to
+# 60 minutes). A break never gets shorter than a minute.
+break_jitter = \"0m\"
+
+# Moments at which the time left in the sessions is announced, with a notification and the
+# `checkpoint` sound: when a share of the session is left, e.g. \"50%\", or a duration, e.g. \"5m\"
+# or \"30s\". The last ten seconds are announced in any case.
+notify_at = []
+
+# Shortest work session worth starting before the next event of the calendar ([calendar] or
+# [gcal] below) or the `--deadline` given: a work session running into them is shortened to end
+# before, and none is started when less than this is left, e.g. \"10m\".
+# min_runway = \"10m\"
+
+# Number of work sessions before a long break (1-100).
+cycles = 4
+
+# Work durations growing with the cycles, replacing `work`: the duration of each cycle, the last
+# one going on, or a start growing by a step each cycle up to a maximum.
+# ramp = [15, 20, 25, 30]
+# ramp = { start = 15, step = 5, max = 40 }
+
+# Sessions of a set, repeated, replacing `cycles`: \"work\", \"short\" and \"long\" for the
+# work sessions, the short and the long breaks, or the name of a `[sessions]` table below.
+# sequence = [\"work\", \"work\", \"short\", \"work\", \"long\"]
+
+# Set to true to disable sound notifications.
+no_sound = false
+
+# Audio output device, as listed by `rustodoro audio list`, the default one when left out.
+# audio_device = \"USB Speakers\"
+
+# Set to true to disable desktop notifications.
+no_notify = false
+
+# Set to true to refresh the display less often and save battery.
+low_power = false
+
+# Set to true to keep ringing and notifying at the end of each session, less and less often,
+# until you press [a] or Enter, the next session waiting for it.
+require_ack = false
+
+# Set to true to ask why after pausing with [p]: [c] for a call, [b] for a bio break, [i] for
+# another interruption, any other key going on without a reason.
+ask_pause_reason = false
+
+# Set to true to ask for the one thing of each work session as it starts, the task being taken on
+# Enter, the prompt giving up after 30 seconds.
+ask_intent = false
+
+# Set to true while screen-sharing: the task is shown as \"Focus\" in the progress bar and the
+# notifications.
+private = false
+
+# Look of the session banners: \"emoji\" or \"plain\".
+theme = \"emoji\"
+
+# What the progress bar of the work sessions shows: \"bar\", or \"zen\" for a slowly filling dot
+# without any number, the time left showing for a few seconds when pressing t.
+display = \"bar\"
+
+# Seconds to wait after announcing a session before starting it (0-60).
+transition_pause = 0
+
+# Pomodoros to complete in a day, shown in the summary when quitting (1-100).
+daily_goal = 8
+
+# Workday window, used to remind you of the daily goal when falling behind pace.
+workday_start = \"09:00\"
+workday_end = \"17:00\"
+
+# Port of the local HTTP endpoint browser extensions poll to block sites during work sessions,
+# and editors to show the countdown, on 127.0.0.1 only. 0 disables it.
+serve_port = 0
+
+# Built-in Telegram bot, when rustodoro is built with the `telegram` feature. It reports the
+# sessions to the chat and accepts /status, /pause, /resume, /skip and /reset from it only. With
+# `escalate_after`, the ends of the sessions not acknowledged at the timer within so many seconds
+# (10-3600), e.g. by pressing [a], are sent again to the chat. The tokens of the integrations can
+# be left out and kept in the keychain instead, with `rustodoro auth set telegram`.
+# [telegram]
+# token = \"123456:your-bot-token\"
+# chat_id = 123456789
+# escalate_after = 120
+
+# Push notifications through ntfy (https://ntfy.sh), when rustodoro is built with the `ntfy`
+# feature. Subscribe to the topic in the ntfy app to get them on your phone. The events are among
+# work_started, work_ended, break_started and break_ended. With `escalate_after`, they are only
+# pushed when not acknowledged at the timer within so many seconds (10-3600), e.g. by pressing [a].
+# [ntfy]
+# server = \"https://ntfy.sh\"
+# topic = \"rustodoro-pick-a-hard-to-guess-name\"
+# token = \"tk_your-access-token\"
+# events = [\"work_ended\", \"break_ended\"]
+# escalate_after = 120
+
+# Texts of the notifications sent through Telegram, ntfy and the lights, by event among
+# work_started, work_ended, break_started and break_ended, the ones left out keeping theirs. The
+# `title` and the `body` take {{session}}, {{task}}, {{minutes}} (planned at the start, spent at the
+# end), {{cycle}} and {{time}}.
+# [notifications.work_ended]
+# title = \"🍅 {{task}} done\"
+# body = \"{{minutes}} minutes of focus, take a break.\"
+
+# Focus mode, keeping distractions away during the work sessions and reverting on the breaks.
+# The processes are warned about by default, `action = \"suspend\"` or `\"kill\"` acts on them.
+# Blocking sites edits the hosts file, which needs write access to it. `dry_run = true` only
+# notifies what would be done.
+# [focus]
+# processes = [\"slack\", \"discord\"]
+# action = \"warn\"
+# sites = [\"reddit.com\", \"news.ycombinator.com\"]
+# hosts_file = \"/etc/hosts\"
+# dry_run = false
+
+# Dimming the screen during the breaks, when rustodoro is built with the `dim` feature, to the
+# given percentage of its brightness. The backend is among auto, backlight (the sysfs backlight,
+# which needs write access to it), brightnessctl and xrandr (X11 only, for external monitors).
+# [dim]
+# level = 30
+# backend = \"auto\"
+
+# Status light, when rustodoro is built with the `led` feature: a blink(1) through blink1-tool, or
+# a color keyboard backlight, showing the color of the running session.
+# [led]
+# device = \"auto\"
+# work = \"#ff0000\"
+# short_break = \"#00ff00\"
+# long_break = \"#00ff00\"
+
+# Flashing every display at the end of the sessions, when rustodoro is built with the `flash`
+# feature, for those working without the sound in their ears. The `overlay` style covers each
+# monitor with what comes next for `seconds` (1-10) on Windows, WSL and macOS, and blinks the
+# displays elsewhere on X11, as the `blink` style does.
+# [flash]
+# style = \"overlay\"
+# seconds = 3
+
+# Light scenes, when rustodoro is built with the `lights` feature: `focus` is recalled when a work
+# session starts and `relax` when a break starts, the lights being restored when quitting. With
+# the hue backend, the token is the application key of the bridge and the scenes are scene ids;
+# with home_assistant, it is a long-lived access token and the scenes are entities such as
+# scene.focus.
+# [lights]
+# backend = \"hue\"
+# url = \"http://192.168.1.10\"
+# token = \"your-application-key\"
+# focus = \"focus-scene-id\"
+# relax = \"relax-scene-id\"
+# restore = true
+# retries = 2
+
+# Focus blocks, when rustodoro is built with the `caldav` feature: each work session is written as
+# a busy `title` event to the CalDAV calendar at `url`, extending the previous one when it ended
+# less than `extend_within` minutes before (0-120). Quitting early truncates the event, or deletes
+# it when it lasted less than 5 minutes. The password can be kept in the keychain instead, with
+# `rustodoro auth set calendar`.
+# [calendar]
+# url = \"https://dav.example.com/calendars/me/work/\"
+# username = \"me\"
+# password = \"your-app-password\"
+# title = \"Focus\"
+# extend_within = 10
+
+# Focus blocks in Google Calendar, when rustodoro is built with the `gcal` feature, written as with
+# [calendar] to `calendar` (\"primary\" or a calendar id), which also tells the meetings a work
+# session runs into. The client is an OAuth client of the \"TVs and Limited Input devices\" type;
+# `rustodoro auth google` then authorizes rustodoro, keeping its token in the keychain.
+# [gcal]
+# client_id = \"1234-abcd.apps.googleusercontent.com\"
+# client_secret = \"your-client-secret\"
+# calendar = \"primary\"
+# title = \"Focus\"
+# extend_within = 10
+
+# Shared leaderboard: each timer publishes the pomodoros of the day of its user in a directory
+# shared by the team, e.g. a synced folder or a network share, and `rustodoro leaderboard` ranks
+# them. Only the name and the count are shared, focus time with `share_focus = true`, never the
+# tasks. `publish = false` shows the board without appearing on it.
+# [leaderboard]
+# name = \"Alice\"
+# dir = \"/mnt/team/rustodoro\"
+# publish = true
+# share_focus = false
+
+# Schedule script: before each session, its `next_session(state)` Lua function can change the
+# duration of the session, or turn a short break into a long one and back, e.g. a 30-minute break
+# after 6 pomodoros. It is run by the given Lua interpreter.
+# [schedule]
+# script = \"/home/me/.config/rustodoro/schedule.lua\"
+# interpreter = \"lua\"
+
+# Sounds of the cues: \"none\", a built-in tone (\"beep\", \"chime\" or \"bell\") or the path to
+# an audio file (WAV, FLAC, MP3, Ogg Vorbis...). checkpoint plays at the moments of `notify_at`, and
+# goal_reached after the work session completing the daily goal. The sounds rise to their volume over `fade_in` seconds (0-10), and
+# with `repeat` (10-3600), the end of the breaks rings again every so many seconds, louder each
+# time, until acknowledged with [a] or five times.
+# [sounds]
+# session_start = \"none\"
+# checkpoint = \"none\"
+# ten_seconds_left = \"none\"
+# work_end = \"beep\"
+# break_end = \"beep\"
+# goal_reached = \"chime\"
+# fade_in = 0
+# repeat = 0
+
+# Vibrations of the cues, as an alternative to the sounds: \"none\", \"short\", \"long\",
+# \"double\" or milliseconds of pulses and pauses, e.g. \"300,100,300\". The phone vibrates under
+# Termux; elsewhere, `command` is run by the shell for each pulse, {{ms}} long.
+# [vibrations]
+# command = \"rumble --duration {{ms}}\"
+# work_end = \"double\"
+# break_end = \"double\"
+# goal_reached = \"long\"
+
+# Quiet hours: no sounds nor popups between `start` and `end`, e.g. overnight, nor while one of
+# the meeting `apps` runs or, on X11, a window is fullscreen. The terminal still shows everything.
+# [quiet]
+# start = \"22:00\"
+# end = \"08:00\"
+# apps = [\"zoom\", \"teams\"]
+# fullscreen = false
+
+# Finishing the work sessions early, when the keyboard and mouse were left alone for `idle`
+# seconds (30-3600) in their last `within` minutes (1-60), recording them as finished early. The
+# idle time comes from xprintidle on X11 and the HID system on macOS.
+# [auto_finish]
+# idle = 120
+# within = 5
+
+# Pausing the work sessions while a meeting app captures the `microphone` or the `camera`, and
+# resuming them once the call is over. The microphone is watched through PulseAudio or PipeWire on
+# Linux, CoreAudio on macOS and the privacy settings on Windows and WSL, the camera on Linux and
+# Windows only.
+# [auto_pause]
+# microphone = true
+# camera = true
+
+# Pruning the sessions older than `keep` (e.g. 90d, 12w, 6m or 2y) from the history on each run.
+# `rustodoro history prune` does it on demand.
+# [retention]
+# keep = \"1y\"
+
+# Sending the digest of the week to `email` with sendmail, on `day` from `at` on while the timer
+# runs, or on the next run when it did not. `rustodoro digest` makes one on demand.
+# [digest]
+# email = \"me@example.com\"
+# day = \"friday\"
+# at = \"16:00\"
+
+# Logging each work session, and the breaks with `breaks`, to the Markdown note of the day, e.g. of
+# an Obsidian vault: `path` takes the {{date}} of the session as `date_format` writes it, and
+# `line` {{date}}, {{start}}, {{end}}, {{minutes}}, {{task}}, {{intent}}, {{review}}, {{kind}} and
+# {{outcome}}. The lines go under `heading`, added at the end of the note when it is missing.
+# [notes]
+# path = \"~/Obsidian/Daily/{{date}}.md\"
+# line = \"- {{start}}–{{end}} 🍅 {{task}} ({{outcome}})\"
+# date_format = \"%Y-%m-%d\"
+# heading = \"## Pomodoros\"
+# breaks = false
+
+# Telling the commits made during the work sessions in `rustodoro report`, read from the git
+# repositories `repos`, by `author` or by the `user.email` of each repository.
+# [git]
+# repos = [\"~/code/app\", \"~/code/website\"]
+# author = \"me@example.com\"
+
+# Nagging, with a notification, after a break skipped or cut short while the share of the breaks
+# of the day taken until their end is below `nag_below` percent (1-100). A break skipped within
+# its first minute counts as skipped, later as cut short.
+# [compliance]
+# nag_below = 80
+
+# Eye-strain micro-breaks: every `every` minutes of work (5-60), a short notification asks to look
+# at something 20 feet away for `look_away` seconds (5-120), the 20-20-20 rule. The countdown goes
+# on meanwhile.
+# [eyes]
+# every = 20
+# look_away = 20
+
+# Keys of the timer: a key held down repeats its command with `repeat`, and presses of the pause
+# toggle closer than `debounce` milliseconds (0-5000, 0 for none) count as one. Pasted text is
+# ignored. With the `azerty` layout, the number row types digits without Shift. `bindings` gives
+# keys, by their character or Space, Enter, Esc, Tab, Backspace or F1 to F12, one of acknowledge,
+# pause, pause_resume, reset, resume, skip, extend, reveal, settings, command_line, quit or none.
+# [keys]
+# layout = \"qwerty\"
+# repeat = false
+# debounce = 600
+# [keys.bindings]
+# n = \"skip\"
+# q = \"none\"
+
+# Reminders rotating at the start of the breaks, each one at every `every` break (1-20), and in the
+# middle of every `every` work session as well with `mid_work`.
+# [[reminders]]
+# text = \"Stand up and walk around\"
+# every = 2
+# [[reminders]]
+# text = \"Drink a glass of water\"
+# mid_work = true
+
+# Sessions of their own, named in `sequence`, e.g. sequence = [\"work\", \"short\", \"review\",
+# \"long\"]. Each one lasts `minutes` (1-1440) and counts as `counts_as`, one of work, short_break or
+# long_break, in the history and the statistics. `color` is that of its progress bar: red, green,
+# yellow, blue, magenta, cyan or white.
+# [sessions.review]
+# label = \"Review\"
+# minutes = 15
+# counts_as = \"work\"
+# color = \"magenta\"
+
+# Stages the short and the long breaks go through, each announced with a countdown of its own:
+# `minutes` (1-1440) each, the last one lasting until the end of the break, up to 10 stages.
+# [stages]
+# short_break = [
+#   { label = \"Eyes closed\", minutes = 1 },
+#   { label = \"Walk\", minutes = 3 },
+#   { label = \"Plan the next pomodoro\", minutes = 1 },
+# ]
+
+# Schedule of the timer started with `--name writing`, overriding the values above. Each named
+# timer has its own control socket and history.
+# [timers.writing]
+# work = 50
+# short_break = 10
+# cycles = 3
+
+# Schedule of a day of the week, from monday to sunday, overriding the values above, e.g. light
+# Fridays. The profile of the new day applies from midnight on during a run.
+# [weekdays.friday]
+# work = 20
+# daily_goal = 6
+# [weekdays.tuesday]
+# sequence = [\"work\", \"short\"]
+";
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+    /// Path of the configuration file [default: ~/.config/rustodoro/config.toml]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Path of the session history [default: ~/.local/share/rustodoro/history.jsonl]
+    #[arg(long, global = true)]
+    pub history: Option<PathBuf>,
+    /// Path of the control socket [default: $XDG_RUNTIME_DIR/rustodoro.sock]
+    #[arg(long, global = true)]
+    pub socket: Option<PathBuf>,
+    /// Name of the timer, to run several at once, each with its own socket and history
+    #[arg(long, global = true, value_parser = parse_name)]
+    pub name: Option<String>,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Write a report of the session history
+    Report {
+        /// Write the report as a standalone HTML page to this file
+        #[arg(long, value_name = "FILE")]
+        html: PathBuf,
+        /// Number of days covered, today included
+        #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..=366))]
+        days: u32,
+    },
+    /// Show a year of pomodoros as a calendar grid
+    Heatmap,
+    /// Manage the audio output
+    Audio {
+        #[command(subcommand)]
+        action: AudioAction,
+    },
+    /// Play a sound, to check the audio device and the volume
+    TestSound {
+        /// Cue whose sound to play
+        #[arg(long, value_enum, default_value = "work_end")]
+        cue: Cue,
+    },
+    /// Show a desktop notification, to check the notification daemon
+    TestNotify,
+    /// Import the sessions recorded by another Pomodoro app into the history
+    Import {
+        /// App the file was exported from
+        #[arg(long, value_enum)]
+        from: Format,
+        /// File exported by the app
+        file: PathBuf,
+        /// Count the sessions to import without writing the history
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Back up the configuration file and the session history into a file
+    Backup {
+        /// File to write the backup to
+        file: PathBuf,
+    },
+    /// Restore the configuration file and the session history from a backup
+    Restore {
+        /// Backup written by `rustodoro backup`
+        file: PathBuf,
+        /// Replace the configuration file and the history when they exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Manage the session history
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Show what the running timer is doing, the one started with --name when given
+    #[cfg(unix)]
+    Status,
+    /// Show the progress of the running timer in this terminal, Ctrl+C leaving it running
+    #[cfg(unix)]
+    Attach,
+    /// Wait until the running timer reaches a state, exiting with 2 on timeout
+    #[cfg(unix)]
+    Wait {
+        /// State to wait for
+        #[arg(long = "for", value_enum)]
+        target: Target,
+        /// Seconds to wait at most [default: as long as the timer runs]
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+    },
+    /// Run a run recorded with --record again on a simulated clock, to reproduce what happened
+    Replay {
+        /// Journal written by --record
+        file: PathBuf,
+    },
+    /// Fast-forward through workdays on a simulated clock and tell what would happen, to check
+    /// a schedule configuration
+    Simulate {
+        /// Number of workdays to simulate
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..=366))]
+        days: u64,
+        /// Configuration file to simulate [default: the configuration file]
+        #[arg(long, value_name = "FILE")]
+        pattern: Option<PathBuf>,
+    },
+    /// Rank the pomodoros shared by the team for a day
+    Leaderboard {
+        /// Day to show, as YYYY-MM-DD [default: today]
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        day: Option<chrono::NaiveDate>,
+    },
+    /// Make the digest of the focus time, the goals and the top tasks, printed unless sent or
+    /// written
+    Digest {
+        /// Cover the last 7 days, today included (the default)
+        #[arg(long, conflicts_with = "daily")]
+        weekly: bool,
+        /// Cover today only
+        #[arg(long)]
+        daily: bool,
+        /// Send the digest to this address with sendmail
+        #[arg(long, value_name = "ADDRESS", conflicts_with = "output")]
+        email: Option<String>,
+        /// Write the digest to this file
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// List the achievements unlocked and the ones still to reach
+    Achievements,
+    /// Show the tomato garden grown by the pomodoros of the last days
+    Garden {
+        /// Number of days to show, today included
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u64).range(1..=366))]
+        days: u64,
+    },
+    /// Keep the tokens of the integrations in the keychain of the system
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    /// Queue runs to chain, and manage the notifications which could not be sent yet
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+    /// Learn the keys on a demo cycle where a second stands for a minute
+    Tutorial,
+    /// List the sessions left until the daily goal and when they would end, starting now
+    Plan,
+}
+
+#[derive(Subcommand)]
+pub enum QueueAction {
+    /// Queue a run with the schedule given by --work, --short-break, --long-break, --cycles,
+    /// --sets and --task, the others coming from the configuration
+    Add,
+    /// List the queued runs, the next one first
+    List,
+    /// Remove a queued run
+    Remove {
+        /// Number of the run, as listed by `rustodoro queue list`
+        index: usize,
+    },
+    /// Run the queued runs back-to-back, each leaving the queue once its sets are worked through
+    Run,
+    /// Show the notifications waiting for each backend
+    Status,
+    /// Send the notifications waiting now
+    Flush,
+}
+
+#[derive(Subcommand)]
+pub enum AuthAction {
+    /// Store the token of an integration, typed in or read from the standard input
+    Set { service: Service },
+    /// Remove the token of an integration
+    Remove { service: Service },
+    /// Authorize rustodoro to use Google Calendar, entering a code in a browser
+    #[cfg(feature = "gcal")]
+    Google,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Write a commented configuration file with the default values
+    Init {
+        /// Overwrite an existing configuration file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the effective configuration and where each value comes from
+    Show,
+    /// Open the configuration file in $VISUAL or $EDITOR
+    Edit,
+    /// Print the location of the configuration file
+    Path,
+    /// Check the configuration file without starting a run
+    Validate,
+}
+
+#[derive(Subcommand)]
+pub enum HistoryAction {
+    /// List the sessions of the history matching every filter given, the latest first
+    List(ListArgs),
+    /// Fix the task of a session, tag it or void it, or list the edits made to it without a change
+    Edit(EditArgs),
+    /// Remove the sessions older than an age from the history
+    Prune {
+        /// Age such as 30d, 8w, 6m or 1y [default: the keep of the [retention] table]
+        #[arg(long, value_name = "AGE")]
+        older_than: Option<Age>,
+    },
+    /// Write the history file again, one record per line
+    Compact,
+    /// Check the history file for unreadable, duplicated or old records
+    Doctor {
+        /// Repair the history, moving the unreadable lines to a file next to it
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Encrypt the history file, or change its key when it is encrypted already
+    Encrypt {
+        /// Keep a random key in the keychain of the system instead of asking for a passphrase
+        #[arg(long)]
+        keychain: bool,
+    },
+    /// Decrypt the history file
+    Decrypt,
+}
+
+#[derive(Args, Clone)]
+pub struct ListArgs {
+    /// Filters written as one expression, matched along with the flags, e.g. "task:report
+    /// since:monday type:work duration>=20m"
+    #[arg(value_name = "EXPR")]
+    pub filter: Option<Filter>,
+    /// Sessions whose task contains this text, whatever the case
+    #[arg(long)]
+    pub task: Option<String>,
+    /// Sessions started from this day on: today, yesterday, a weekday such as monday, a date such
+    /// as 2026-03-02 or an age such as 7d
+    #[arg(long, value_name = "DAY")]
+    pub since: Option<Since>,
+    /// Sessions of this type
+    #[arg(long = "type", value_enum)]
+    pub kind: Option<KindFilter>,
+    /// Sessions counted for at least this long, e.g. 20m
+    #[arg(long, value_name = "DURATION")]
+    pub min_duration: Option<Span>,
+    /// Order of the sessions
+    #[arg(long, value_enum, default_value = "started")]
+    pub sort: SortKey,
+    /// Reverse the order of the sessions
+    #[arg(long)]
+    pub reverse: bool,
+    /// Sessions per page
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+    /// Page to show, from 1, the last one when past the end
+    #[arg(long, default_value_t = 1)]
+    pub page: usize,
+    /// Print the sessions of the page as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct EditArgs {
+    /// ID of the session, as listed by `rustodoro history list`
+    pub id: String,
+    /// Replace the task of the session
+    #[arg(long)]
+    pub task: Option<String>,
+    /// Add a tag to the session, e.g. --tag client --tag deep
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Void the session, which then counts for nothing in the summaries and the reports
+    #[arg(long)]
+    pub void: bool,
+}
+
+#[derive(Subcommand)]
+pub enum AudioAction {
+    /// List the audio output devices
+    List,
+}
+
+#[derive(Args, Clone)]
+pub struct RunArgs {
+    /// Work duration in minutes [default: 25]
+    #[arg(short, long = "work", global = true)]
+    pub work_duration: Option<u64>,
+    /// Short break duration in minutes [default: 5]
+    #[arg(short, long = "short-break", global = true)]
+    pub short_break: Option<u64>,
+    /// Long break duration in minutes [default: 15]
+    #[arg(short, long = "long-break", global = true)]
+    pub long_break: Option<u64>,
+    /// Vary the breaks by up to this much either way, e.g. 2m or 90s [default: 0m]
+    #[arg(long = "break-jitter", value_name = "DURATION", global = true)]
+    pub break_jitter: Option<Jitter>,
+    /// Announce the time left at these moments, e.g. 50%,5m,1m
+    #[arg(
+        long = "notify-at",
+        value_name = "CHECKPOINTS",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub notify_at: Vec<Checkpoint>,
+    /// Shorten a work session running into the next calendar event or the deadline, and do not
+    /// start one with less than this left, e.g. 10m
+    #[arg(long = "min-runway", value_name = "DURATION", global = true)]
+    pub min_runway: Option<Span>,
+    /// Time the work sessions must end by, as HH:MM, shortening the last one
+    #[arg(long = "deadline", value_name = "HH:MM", global = true)]
+    pub deadline: Option<TimeOfDay>,
+    /// Number of Pomodoro cycles before a long break [default: 4]
+    #[arg(short, long = "cycles", global = true)]
+    pub cycles: Option<u64>,
+    /// Work durations of the cycles, as 15,20,25,30 or 15+5..40 (start+step..max)
+    #[arg(long = "ramp", value_name = "MINUTES", global = true)]
+    pub ramp: Option<Ramp>,
+    /// Disable sound notifications
+    #[arg(short, long = "no-sound", global = true)]
+    pub no_sound: bool,
+    /// Play the sounds on this audio output device, see `rustodoro audio list`
+    #[arg(long = "audio-device", value_name = "NAME", global = true)]
+    pub audio_device: Option<String>,
+    /// Disable desktop notifications
+    #[arg(long = "no-notify", global = true)]
+    pub no_notify: bool,
+    /// Refresh the display less often to save battery
+    #[arg(long = "low-power", global = true)]
+    pub low_power: bool,
+    /// Keep ringing at the end of each session until acknowledged with [a]
+    #[arg(long = "require-ack", global = true)]
+    pub require_ack: bool,
+    /// Ask for a reason after pausing with [p]: [c] call, [b] bio, [i] interruption
+    #[arg(long = "ask-pause-reason", global = true)]
+    pub ask_pause_reason: bool,
+    /// Ask for the one thing of each work session as it starts
+    #[arg(long = "ask-intent", global = true)]
+    pub ask_intent: bool,
+    /// Hide the task from the progress bar and the notifications, e.g. while screen-sharing
+    #[arg(long = "private", global = true)]
+    pub private: bool,
+    /// Look of the session banners [default: emoji]
+    #[arg(long = "theme", value_enum, global = true)]
+    pub theme: Option<Theme>,
+    /// What the progress bar of the work sessions shows [default: bar]
+    #[arg(long = "display", value_enum, global = true)]
+    pub display: Option<DisplayMode>,
+    /// Seconds to wait after announcing a session before starting it [default: 0]
+    #[arg(long = "transition-pause", value_name = "SECONDS", global = true)]
+    pub transition_pause: Option<u64>,
+    /// Pomodoros to complete in a day [default: 8]
+    #[arg(long = "daily-goal", global = true)]
+    pub daily_goal: Option<u64>,
+    /// Start of the workday, as HH:MM [default: 09:00]
+    #[arg(long = "workday-start", value_name = "HH:MM", global = true)]
+    pub workday_start: Option<TimeOfDay>,
+    /// End of the workday, as HH:MM [default: 17:00]
+    #[arg(long = "workday-end", value_name = "HH:MM", global = true)]
+    pub workday_end: Option<TimeOfDay>,
+    /// Serve the session state to browser extensions and editors on 127.0.0.1:<PORT>
+    #[arg(long = "serve-port", value_name = "PORT", global = true)]
+    pub serve_port: Option<u64>,
+    /// What you work on, recorded in the history with the work sessions
+    #[arg(short, long, global = true)]
+    pub task: Option<String>,
+    /// Stop after this many sets of cycles, the long break included [default: run until quit]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    pub sets: Option<u64>,
+    /// Do not offer to set rustodoro up when there is no configuration file yet
+    #[arg(long)]
+    pub no_wizard: bool,
+    /// Print a JSON summary of the run as the last line, for wrappers and scripts
+    #[arg(long)]
+    pub summary_json: bool,
+    /// Record the commands and the transitions of the run to this file, for `rustodoro replay`
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+}
+
+/// Values given by one configuration layer, every value being optional. This is also the format of
+/// the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub work: Option<u64>,
+    pub short_break: Option<u64>,
+    pub long_break: Option<u64>,
+    pub break_jitter: Option<Jitter>,
+    pub notify_at: Option<Vec<Checkpoint>>,
+    pub min_runway: Option<Span>,
+    pub cycles: Option<u64>,
+    pub ramp: Option<Ramp>,
+    pub sequence: Option<Vec<Step>>,
+    pub no_sound: Option<bool>,
+    pub audio_device: Option<String>,
+    pub no_notify: Option<bool>,
+    pub low_power: Option<bool>,
+    pub require_ack: Option<bool>,
+    pub ask_pause_reason: Option<bool>,
+    pub ask_intent: Option<bool>,
+    pub private: Option<bool>,
+    pub theme: Option<Theme>,
+    pub display: Option<DisplayMode>,
+    pub transition_pause: Option<u64>,
+    pub daily_goal: Option<u64>,
+    pub workday_start: Option<TimeOfDay>,
+    pub workday_end: Option<TimeOfDay>,
+    pub serve_port: Option<u64>,
+    pub telegram: Option<TelegramConfig>,
+    pub ntfy: Option<NtfyConfig>,
+    /// Texts of the notifications, by event.
+    pub notifications: Option<BTreeMap<Trigger, NotificationTemplate>>,
+    pub focus: Option<FocusConfig>,
+    pub dim: Option<DimConfig>,
+    pub led: Option<LedConfig>,
+    pub flash: Option<FlashConfig>,
+    pub lights: Option<LightsConfig>,
+    pub calendar: Option<CalendarConfig>,
+    pub gcal: Option<GcalConfig>,
+    pub leaderboard: Option<LeaderboardConfig>,
+    pub schedule: Option<ScheduleConfig>,
+    pub quiet: Option<QuietConfig>,
+    pub auto_finish: Option<AutoFinishConfig>,
+    pub auto_pause: Option<AutoPauseConfig>,
+    pub retention: Option<RetentionConfig>,
+    pub digest: Option<DigestConfig>,
+    pub notes: Option<NotesConfig>,
+    pub git: Option<GitConfig>,
+    pub compliance: Option<ComplianceConfig>,
+    pub eyes: Option<EyesConfig>,
+    pub keys: Option<KeysConfig>,
+    pub reminders: Option<Vec<ReminderConfig>>,
+    pub sounds: Option<SoundsConfig>,
+    pub vibrations: Option<VibrationsConfig>,
+    /// Custom sessions of the sequences, by name.
+    pub sessions: Option<BTreeMap<String, SessionConfig>>,
+    pub stages: Option<BreakStages>,
+    /// Schedules of the named timers, by name.
+    pub timers: Option<BTreeMap<String, ProfileConfig>>,
+    pub weekdays: Option<WeekdaysConfig>,
+}
+
+/// The `[telegram]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// Token of the bot, given by @BotFather, empty when it is kept in the keychain.
+    #[serde(default)]
+    pub token: String,
+    /// The only chat the bot talks to.
+    pub chat_id: i64,
+    /// Seconds after which the ends of the sessions not acknowledged are sent again.
+    pub escalate_after: Option<u64>,
+}
+
+/// The `[ntfy]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+    pub topic: String,
+    /// Access token of a protected topic.
+    pub token: Option<String>,
+    /// The session transitions pushed to the topic.
+    #[serde(default = "default_ntfy_events")]
+    pub events: Vec<Trigger>,
+    /// Seconds after which the events not acknowledged are pushed, instead of right away.
+    pub escalate_after: Option<u64>,
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+fn default_ntfy_events() -> Vec<Trigger> {
+    Trigger::DEFAULT.to_vec()
+}
+
+/// A `[notifications.<event>]` table of the configuration file, the texts of the notifications of
+/// the event with the placeholders of [`PLACEHOLDERS`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationTemplate {
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// The `[focus]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FocusConfig {
+    /// Names of the distracting processes.
+    #[serde(default)]
+    pub processes: Vec<String>,
+    #[serde(default)]
+    pub action: ProcessAction,
+    /// Distracting sites, blocked in the hosts file.
+    #[serde(default)]
+    pub sites: Vec<String>,
+    #[serde(default = "default_hosts_file")]
+    pub hosts_file: PathBuf,
+    /// Only notify what would be done.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// The `[dim]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DimConfig {
+    /// Brightness during the breaks, in percent of the brightness before.
+    #[serde(default = "default_dim_level")]
+    pub level: u64,
+    #[serde(default)]
+    pub backend: DimBackend,
+}
+
+fn default_dim_level() -> u64 {
+    30
+}
+
+/// How the screen is dimmed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DimBackend {
+    /// The first backend working on this system.
+    #[default]
+    Auto,
+    Backlight,
+    Brightnessctl,
+    Xrandr,
+}
+
+impl DimBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DimBackend::Auto => "auto",
+            DimBackend::Backlight => "backlight",
+            DimBackend::Brightnessctl => "brightnessctl",
+            DimBackend::Xrandr => "xrandr",
+        }
+    }
+}
+
+/// The `[flash]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct FlashConfig {
+    pub style: FlashStyle,
+    /// Seconds the overlay stays.
+    pub seconds: u64,
+}
+
+impl Default for FlashConfig {
+    fn default() -> Self {
+        FlashConfig {
+            style: FlashStyle::default(),
+            seconds: 3,
+        }
+    }
+}
+
+/// How the displays are flashed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlashStyle {
+    /// A large window over each monitor telling what comes next.
+    #[default]
+    Overlay,
+    /// The displays going dark a few times.
+    Blink,
+}
+
+impl FlashStyle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FlashStyle::Overlay => "overlay",
+            FlashStyle::Blink => "blink",
+        }
+    }
+}
+
+/// The `[led]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LedConfig {
+    #[serde(default)]
+    pub device: LedDevice,
+    #[serde(default = "Color::red")]
+    pub work: Color,
+    #[serde(default = "Color::green")]
+    pub short_break: Color,
+    #[serde(default = "Color::green")]
+    pub long_break: Color,
+}
+
+/// The light showing the session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LedDevice {
+    /// The first device found, a blink(1) first.
+    #[default]
+    Auto,
+    Blink1,
+    Keyboard,
+}
+
+impl LedDevice {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedDevice::Auto => "auto",
+            LedDevice::Blink1 => "blink1",
+            LedDevice::Keyboard => "keyboard",
+        }
+    }
+}
+
+/// A color, written "#rrggbb".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Color { red, green, blue }
+    }
+
+    fn red() -> Self {
+        Color::new(0xff, 0, 0)
+    }
+
+    fn green() -> Self {
+        Color::new(0, 0xff, 0)
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().strip_prefix('#').unwrap_or(s.trim());
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(red), Some(green), Some(blue)) => Ok(Color::new(red, green, blue)),
+            _ => Err(format!("must be a color such as #ff0000, got `{}`", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+/// The `[lights]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightsConfig {
+    pub backend: LightsBackend,
+    /// Address of the Hue bridge or of Home Assistant.
+    pub url: String,
+    /// Application key of the Hue bridge or Home Assistant access token, empty when it is kept in
+    /// the keychain.
+    #[serde(default)]
+    pub token: String,
+    /// Scene recalled when a work session starts.
+    pub focus: String,
+    /// Scene recalled when a break starts.
+    pub relax: String,
+    /// Put the lights back as they were before the first scene when quitting.
+    #[serde(default = "default_lights_restore")]
+    pub restore: bool,
+    /// Times a failed request is tried again.
+    #[serde(default = "default_lights_retries")]
+    pub retries: u32,
+}
+
+fn default_lights_restore() -> bool {
+    true
+}
+
+fn default_lights_retries() -> u32 {
+    2
+}
+
+/// Most retries of a light request, each waiting for the previous one to time out.
+const MAX_LIGHTS_RETRIES: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightsBackend {
+    Hue,
+    HomeAssistant,
+}
+
+impl LightsBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightsBackend::Hue => "hue",
+            LightsBackend::HomeAssistant => "home_assistant",
+        }
+    }
+}
+
+/// The `[calendar]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// Address of the CalDAV calendar collection.
+    pub url: String,
+    pub username: String,
+    /// Password or app password, empty when it is kept in the keychain.
+    #[serde(default)]
+    pub password: String,
+    /// Title of the focus events.
+    #[serde(default = "default_calendar_title")]
+    pub title: String,
+    /// Minutes after the end of a focus event within which the next work session extends it.
+    #[serde(default = "default_calendar_extend_within")]
+    pub extend_within: u64,
+}
+
+/// The `[gcal]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GcalConfig {
+    /// OAuth client of the "TVs and Limited Input devices" type.
+    pub client_id: String,
+    pub client_secret: String,
+    /// Id of the calendar, "primary" for the main calendar of the account.
+    #[serde(default = "default_gcal_calendar")]
+    pub calendar: String,
+    #[serde(default = "default_calendar_title")]
+    pub title: String,
+    #[serde(default = "default_calendar_extend_within")]
+    pub extend_within: u64,
+}
+
+fn default_gcal_calendar() -> String {
+    "primary".to_string()
+}
+
+fn default_calendar_title() -> String {
+    "Focus".to_string()
+}
+
+fn default_calendar_extend_within() -> u64 {
+    10
+}
+
+/// The `[leaderboard]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaderboardConfig {
+    /// Name shown to the other participants.
+    pub name: String,
+    /// Directory shared by the participants.
+    pub dir: PathBuf,
+    #[serde(default = "default_publish")]
+    pub publish: bool,
+    /// Also share the focus time of the day.
+    #[serde(default)]
+    pub share_focus: bool,
+}
+
+fn default_publish() -> bool {
+    true
+}
+
+/// Longest name on the leaderboard.
+pub const MAX_NAME_LEN: usize = 32;
+
+/// The `[schedule]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// The Lua script deciding the sessions.
+    pub script: PathBuf,
+    #[serde(default = "default_interpreter")]
+    pub interpreter: String,
+}
+
+fn default_interpreter() -> String {
+    "lua".to_string()
+}
+
+/// The `[quiet]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuietConfig {
+    /// Quiet hours, ending the next day when `end` comes before `start`.
+    pub start: Option<TimeOfDay>,
+    pub end: Option<TimeOfDay>,
+    /// Names of the meeting processes.
+    #[serde(default)]
+    pub apps: Vec<String>,
+    /// Keep quiet while a window is fullscreen.
+    #[serde(default)]
+    pub fullscreen: bool,
+}
+
+/// The `[auto_finish]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AutoFinishConfig {
+    /// Seconds without any keyboard nor mouse activity.
+    pub idle: u64,
+    /// Minutes before the end of the work sessions in which they can finish early.
+    pub within: u64,
+}
+
+impl Default for AutoFinishConfig {
+    fn default() -> Self {
+        AutoFinishConfig {
+            idle: 120,
+            within: 5,
+        }
+    }
+}
+
+/// The `[auto_pause]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AutoPauseConfig {
+    /// Pause while the microphone is captured.
+    pub microphone: bool,
+    /// Pause while the camera is captured.
+    pub camera: bool,
+}
+
+impl Default for AutoPauseConfig {
+    fn default() -> Self {
+        AutoPauseConfig {
+            microphone: true,
+            camera: true,
+        }
+    }
+}
+
+/// The `[retention]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Age of the sessions pruned from the history on each run.
+    pub keep: Age,
+}
+
+/// The `[digest]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DigestConfig {
+    /// Address the digest of the week is sent to.
+    pub email: String,
+    /// Day of the week it is sent on, from the time `at` on.
+    #[serde(default = "default_digest_day")]
+    pub day: Weekday,
+    #[serde(default = "default_digest_at")]
+    pub at: TimeOfDay,
+}
+
+fn default_digest_day() -> Weekday {
+    Weekday::Fri
+}
+
+fn default_digest_at() -> TimeOfDay {
+    TimeOfDay::new(16, 0)
+}
+
+/// The `[notes]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotesConfig {
+    /// Path of the daily notes, with the `{{date}}` placeholder.
+    pub path: String,
+    /// Line logging a session, with the placeholders of [`LINE_PLACEHOLDERS`].
+    #[serde(default = "default_notes_line")]
+    pub line: String,
+    /// strftime format of `{{date}}`.
+    #[serde(default = "default_notes_date_format")]
+    pub date_format: String,
+    /// Heading the lines go under.
+    #[serde(default)]
+    pub heading: Option<String>,
+    /// Whether the breaks are logged too.
+    #[serde(default)]
+    pub breaks: bool,
+}
+
+fn default_notes_line() -> String {
+    "- {{start}}–{{end}} 🍅 {{task}} ({{outcome}})".to_string()
+}
+
+fn default_notes_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+/// The `[git]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GitConfig {
+    /// Paths of the repositories, `~` standing for the home directory.
+    pub repos: Vec<String>,
+    /// Author of the commits, the `user.email` of each repository by default.
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// `path` with a leading `~` standing for the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The `[compliance]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComplianceConfig {
+    /// Break compliance of the day, in percent, below which skipping a break nags.
+    pub nag_below: u64,
+}
+
+/// The `[eyes]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct EyesConfig {
+    /// Minutes of work between two micro-breaks.
+    pub every: u64,
+    /// Seconds of each micro-break.
+    pub look_away: u64,
+}
+
+impl Default for EyesConfig {
+    fn default() -> Self {
+        EyesConfig {
+            every: 20,
+            look_away: 20,
+        }
+    }
+}
+
+/// The `[keys]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct KeysConfig {
+    /// Layout of the keyboard.
+    pub layout: Layout,
+    /// Actions of the keys, by key name, replacing the defaults.
+    pub bindings: BTreeMap<String, KeyAction>,
+    /// Whether a key held down repeats its command.
+    pub repeat: bool,
+    /// Milliseconds within which presses of the pause toggle count as one.
+    pub debounce: u64,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        KeysConfig {
+            layout: Layout::Qwerty,
+            bindings: BTreeMap::new(),
+            repeat: false,
+            debounce: 600,
+        }
+    }
+}
+
+/// A `[[reminders]]` table of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReminderConfig {
+    pub text: String,
+    /// Number of breaks, or of work sessions, between two showings.
+    #[serde(default = "ReminderConfig::default_every")]
+    pub every: u64,
+    /// Show it in the middle of the work sessions too.
+    #[serde(default)]
+    pub mid_work: bool,
+}
+
+impl ReminderConfig {
+    fn default_every() -> u64 {
+        1
+    }
+}
+
+/// A `[timers.<name>]` or `[weekdays.<day>]` table of the configuration file, the schedule of the
+/// timer started with `--name <name>` or of a day of the week, overriding the values of the file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub work: Option<u64>,
+    pub short_break: Option<u64>,
+    pub long_break: Option<u64>,
+    pub cycles: Option<u64>,
+    pub sequence: Option<Vec<Step>>,
+    pub daily_goal: Option<u64>,
+    pub workday_start: Option<TimeOfDay>,
+    pub workday_end: Option<TimeOfDay>,
+}
+
+impl ProfileConfig {
+    fn layer(&self) -> Layer {
+        Layer {
+            work: self.work,
+            short_break: self.short_break,
+            long_break: self.long_break,
+            cycles: self.cycles,
+            sequence: self.sequence.clone(),
+            daily_goal: self.daily_goal,
+            workday_start: self.workday_start,
+            workday_end: self.workday_end,
+            ..Layer::default()
+        }
+    }
+}
+
+/// The days of the week, Monday first.
+pub const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// The `[weekdays]` table of the configuration file, with the profile of each day of the week.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeekdaysConfig {
+    pub monday: Option<ProfileConfig>,
+    pub tuesday: Option<ProfileConfig>,
+    pub wednesday: Option<ProfileConfig>,
+    pub thursday: Option<ProfileConfig>,
+    pub friday: Option<ProfileConfig>,
+    pub saturday: Option<ProfileConfig>,
+    pub sunday: Option<ProfileConfig>,
+}
+
+impl WeekdaysConfig {
+    pub fn get(&self, day: Weekday) -> Option<&ProfileConfig> {
+        match day {
+            Weekday::Mon => self.monday.as_ref(),
+            Weekday::Tue => self.tuesday.as_ref(),
+            Weekday::Wed => self.wednesday.as_ref(),
+            Weekday::Thu => self.thursday.as_ref(),
+            Weekday::Fri => self.friday.as_ref(),
+            Weekday::Sat => self.saturday.as_ref(),
+            Weekday::Sun => self.sunday.as_ref(),
+        }
+    }
+}
+
+/// Checks the name of a timer, which becomes part of file names.
+fn parse_name(s: &str) -> Result<String, String> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if s.is_empty() || s.len() > 32 || !s.chars().all(valid) {
+        return Err(format!(
+            "must be up to 32 letters, digits, dashes or underscores, got `{}`",
+            s
+        ));
+    }
+    Ok(s.to_string())
+}
+
+/// The layer a value comes from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env,
+    Flag,
+}
+
+/// A setting that can be given as a flag, an environment variable and a configuration file key.
+struct Key {
+    flag: &'static str,
+    env: &'static str,
+    file_key: &'static str,
+}
+
+const WORK: Key = Key {
+    flag: "--work",
+    env: "RUSTODORO_WORK",
+    file_key: "work",
+};
+const SHORT_BREAK: Key = Key {
+    flag: "--short-break",
+    env: "RUSTODORO_SHORT_BREAK",
+    file_key: "short_break",
+};
+const LONG_BREAK: Key = Key {
+    flag: "--long-break",
+    env: "RUSTODORO_LONG_BREAK",
+    file_key: "long_break",
+};
+const CYCLES: Key = Key {
+    flag: "--cycles",
+    env: "RUSTODORO_CYCLES",
+    file_key: "cycles",
+};
+const NO_SOUND: Key = Key {
+    flag: "--no-sound",
+    env: "RUSTODORO_NO_SOUND",
+    file_key: "no_sound",
+};
+const AUDIO_DEVICE: Key = Key {
+    flag: "--audio-device",
+    env: "RUSTODORO_AUDIO_DEVICE",
+    file_key: "audio_device",
+};
+const NO_NOTIFY: Key = Key {
+    flag: "--no-notify",
+    env: "RUSTODORO_NO_NOTIFY",
+    file_key: "no_notify",
+};
+const LOW_POWER: Key = Key {
+    flag: "--low-power",
+    env: "RUSTODORO_LOW_POWER",
+    file_key: "low_power",
+};
+const REQUIRE_ACK: Key = Key {
+    flag: "--require-ack",
+    env: "RUSTODORO_REQUIRE_ACK",
+    file_key: "require_ack",
+};
+const ASK_PAUSE_REASON: Key = Key {
+    flag: "--ask-pause-reason",
+    env: "RUSTODORO_ASK_PAUSE_REASON",
+    file_key: "ask_pause_reason",
+};
+const ASK_INTENT: Key = Key {
+    flag: "--ask-intent",
+    env: "RUSTODORO_ASK_INTENT",
+    file_key: "ask_intent",
+};
+const PRIVATE: Key = Key {
+    flag: "--private",
+    env: "RUSTODORO_PRIVATE",
+    file_key: "private",
+};
+const NOTIFY_AT: Key = Key {
+    flag: "--notify-at",
+    env: "RUSTODORO_NOTIFY_AT",
+    file_key: "notify_at",
+};
+const MIN_RUNWAY: Key = Key {
+    flag: "--min-runway",
+    env: "RUSTODORO_MIN_RUNWAY",
+    file_key: "min_runway",
+};
+const BREAK_JITTER: Key = Key {
+    flag: "--break-jitter",
+    env: "RUSTODORO_BREAK_JITTER",
+    file_key: "break_jitter",
+};
+const SEQUENCE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "sequence",
+};
+const RAMP: Key = Key {
+    flag: "--ramp",
+    env: "RUSTODORO_RAMP",
+    file_key: "ramp",
+};
+const THEME: Key = Key {
+    flag: "--theme",
+    env: "RUSTODORO_THEME",
+    file_key: "theme",
+};
+const DISPLAY: Key = Key {
+    flag: "--display",
+    env: "RUSTODORO_DISPLAY",
+    file_key: "display",
+};
+const TRANSITION_PAUSE: Key = Key {
+    flag: "--transition-pause",
+    env: "RUSTODORO_TRANSITION_PAUSE",
+    file_key: "transition_pause",
+};
+const DAILY_GOAL: Key = Key {
+    flag: "--daily-goal",
+    env: "RUSTODORO_DAILY_GOAL",
+    file_key: "daily_goal",
+};
+const SERVE_PORT: Key = Key {
+    flag: "--serve-port",
+    env: "RUSTODORO_SERVE_PORT",
+    file_key: "serve_port",
+};
+// The `[telegram]` table only comes from the configuration file.
+const TELEGRAM_TOKEN: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "telegram.token",
+};
+const TELEGRAM_CHAT_ID: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "telegram.chat_id",
+};
+const TELEGRAM_ESCALATE_AFTER: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "telegram.escalate_after",
+};
+const NTFY_SERVER: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "ntfy.server",
+};
+const NOTIFICATIONS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "notifications",
+};
+const NTFY_TOPIC: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "ntfy.topic",
+};
+const NTFY_EVENTS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "ntfy.events",
+};
+const NTFY_ESCALATE_AFTER: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "ntfy.escalate_after",
+};
+const FOCUS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "focus",
+};
+const FOCUS_SITES: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "focus.sites",
+};
+const DIM_LEVEL: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "dim.level",
+};
+const LED: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "led",
+};
+const FLASH_SECONDS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "flash.seconds",
+};
+const LIGHTS_URL: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "lights.url",
+};
+const LIGHTS_TOKEN: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "lights.token",
+};
+const LIGHTS_FOCUS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "lights.focus",
+};
+const LIGHTS_RELAX: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "lights.relax",
+};
+const LIGHTS_RETRIES: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "lights.retries",
+};
+const CALENDAR_URL: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "calendar.url",
+};
+const CALENDAR_USERNAME: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "calendar.username",
+};
+const CALENDAR_TITLE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "calendar.title",
+};
+const CALENDAR_EXTEND_WITHIN: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "calendar.extend_within",
+};
+const GCAL_CLIENT_ID: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "gcal.client_id",
+};
+const GCAL_CLIENT_SECRET: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "gcal.client_secret",
+};
+const GCAL_CALENDAR: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "gcal.calendar",
+};
+const GCAL_TITLE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "gcal.title",
+};
+const GCAL_EXTEND_WITHIN: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "gcal.extend_within",
+};
+const LEADERBOARD_NAME: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "leaderboard.name",
+};
+const LEADERBOARD: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "leaderboard",
+};
+const SCHEDULE_SCRIPT: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "schedule.script",
+};
+const SCHEDULE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "schedule",
+};
+const SOUNDS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "sounds",
+};
+const VIBRATIONS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "vibrations",
+};
+const SOUNDS_FADE_IN: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "sounds.fade_in",
+};
+const SOUNDS_REPEAT: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "sounds.repeat",
+};
+const QUIET_END: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "quiet.end",
+};
+const QUIET: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "quiet",
+};
+const AUTO_FINISH_IDLE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "auto_finish.idle",
+};
+const AUTO_FINISH_WITHIN: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "auto_finish.within",
+};
+const AUTO_PAUSE_MICROPHONE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "auto_pause.microphone",
+};
+const COMPLIANCE_NAG_BELOW: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "compliance.nag_below",
+};
+const DIGEST_EMAIL: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "digest.email",
+};
+const NOTES_PATH: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "notes.path",
+};
+const NOTES_LINE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "notes.line",
+};
+const NOTES_DATE_FORMAT: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "notes.date_format",
+};
+const NOTES_HEADING: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "notes.heading",
+};
+const GIT_REPOS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "git.repos",
+};
+const GIT_AUTHOR: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "git.author",
+};
+const EYES_EVERY: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "eyes.every",
+};
+const EYES_LOOK_AWAY: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "eyes.look_away",
+};
+const KEYS_LAYOUT: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "keys.layout",
+};
+const KEYS_BINDINGS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "keys.bindings",
+};
+const KEYS_DEBOUNCE: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "keys.debounce",
+};
+const REMINDERS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "reminders",
+};
+const RETENTION_KEEP: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "retention.keep",
+};
+const SESSIONS: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "sessions",
+};
+const STAGES: Key = Key {
+    flag: "",
+    env: "",
+    file_key: "stages",
+};
+const TIMERS: Key = Key {
+    flag: "--name",
+    env: "",
+    file_key: "timers",
+};
+const WORKDAY_START: Key = Key {
+    flag: "--workday-start",
+    env: "RUSTODORO_WORKDAY_START",
+    file_key: "workday_start",
+};
+const WORKDAY_END: Key = Key {
+    flag: "--workday-end",
+    env: "RUSTODORO_WORKDAY_END",
+    file_key: "workday_end",
+};
+
+impl Source {
+    /// Names the origin of `key` in this layer, e.g. "flag --work".
+    fn describe(&self, key: &Key) -> String {
+        match self {
+            Source::Default => "default".to_string(),
+            Source::File(path) => format!("config file {}", path.display()),
+            Source::Env => format!("environment variable {}", key.env),
+            Source::Flag => format!("flag {}", key.flag),
+        }
+    }
+
+    /// Names `key` the way the user wrote it in this layer, for error messages.
+    fn setting(&self, key: &Key) -> String {
+        match self {
+            Source::Default => format!("`{}`", key.file_key),
+            Source::File(path) => format!("`{}` in {}", key.file_key, path.display()),
+            Source::Env => format!("`{}`", key.env),
+            Source::Flag => format!("`{}`", key.flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Value<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Value<T> {
+    fn default(value: T) -> Self {
+        Value {
+            value,
+            source: Source::Default,
+        }
+    }
+
+    fn merge(&mut self, value: Option<T>, source: &Source) {
+        if let Some(value) = value {
+            *self = Value {
+                value,
+                source: source.clone(),
+            };
+        }
+    }
+}
+
+/// The configuration after merging all the layers.
+#[derive(Debug)]
+pub struct EffectiveConfig {
+    pub work: Value<u64>,
+    pub short_break: Value<u64>,
+    pub long_break: Value<u64>,
+    pub break_jitter: Value<Jitter>,
+    pub notify_at: Value<Vec<Checkpoint>>,
+    pub min_runway: Value<Option<Span>>,
+    pub cycles: Value<u64>,
+    pub ramp: Value<Option<Ramp>>,
+    pub sequence: Value<Option<Vec<Step>>>,
+    pub no_sound: Value<bool>,
+    pub audio_device: Value<Option<String>>,
+    pub no_notify: Value<bool>,
+    pub low_power: Value<bool>,
+    pub require_ack: Value<bool>,
+    pub ask_pause_reason: Value<bool>,
+    pub ask_intent: Value<bool>,
+    pub private: Value<bool>,
+    pub theme: Value<Theme>,
+    pub display: Value<DisplayMode>,
+    pub transition_pause: Value<u64>,
+    pub daily_goal: Value<u64>,
+    pub workday_start: Value<TimeOfDay>,
+    pub workday_end: Value<TimeOfDay>,
+    /// Port of the browser endpoint, 0 when disabled.
+    pub serve_port: Value<u64>,
+    pub telegram: Value<Option<TelegramConfig>>,
+    pub ntfy: Value<Option<NtfyConfig>>,
+    pub notifications: Value<BTreeMap<Trigger, NotificationTemplate>>,
+    pub focus: Value<Option<FocusConfig>>,
+    pub dim: Value<Option<DimConfig>>,
+    pub led: Value<Option<LedConfig>>,
+    pub flash: Value<Option<FlashConfig>>,
+    pub lights: Value<Option<LightsConfig>>,
+    pub calendar: Value<Option<CalendarConfig>>,
+    pub gcal: Value<Option<GcalConfig>>,
+    pub leaderboard: Value<Option<LeaderboardConfig>>,
+    pub schedule: Value<Option<ScheduleConfig>>,
+    pub quiet: Value<Option<QuietConfig>>,
+    pub auto_finish: Value<Option<AutoFinishConfig>>,
+    pub auto_pause: Value<Option<AutoPauseConfig>>,
+    pub retention: Value<Option<RetentionConfig>>,
+    pub digest: Value<Option<DigestConfig>>,
+    pub notes: Value<Option<NotesConfig>>,
+    pub git: Value<Option<GitConfig>>,
+    pub compliance: Value<Option<ComplianceConfig>>,
+    pub eyes: Value<Option<EyesConfig>>,
+    pub keys: Value<Option<KeysConfig>>,
+    pub reminders: Value<Vec<ReminderConfig>>,
+    pub sounds: Value<SoundsConfig>,
+    pub vibrations: Value<Option<VibrationsConfig>>,
+    pub sessions: Value<BTreeMap<String, SessionConfig>>,
+    pub stages: Value<BreakStages>,
+}
+
+impl EffectiveConfig {
+    pub fn settings(&self) -> Settings {
+        Settings {
+            work_duration: self.work.value,
+            ramp: self.ramp.value.clone(),
+            sequence: self.sequence.value.clone(),
+            sessions: self.sessions.value.clone(),
+            stages: self.stages.value.clone(),
+            short_break: self.short_break.value,
+            long_break: self.long_break.value,
+            break_jitter: self.break_jitter.value,
+            notify_at: self.notify_at.value.clone(),
+            cycles: self.cycles.value,
+            no_sound: self.no_sound.value,
+            audio_device: self.audio_device.value.clone(),
+            sounds: self.sounds.value.clone(),
+            vibrations: self.vibrations.value.clone(),
+            no_notify: self.no_notify.value,
+            low_power: self.low_power.value,
+            require_ack: self.require_ack.value,
+            ask_pause_reason: self.ask_pause_reason.value,
+            ask_intent: self.ask_intent.value,
+            private: self.private.value,
+            // Given for the run only, with `--task`.
+            task: None,
+            theme: self.theme.value,
+            display: self.display.value,
+            transition_pause: self.transition_pause.value,
+            minute: MINUTE,
+            daily_goal: self.daily_goal.value,
+            workday_start: self.workday_start.value,
+            workday_end: self.workday_end.value,
+        }
+    }
+
+    /// Renders the configuration as a configuration file, each value annotated with its source.
+    pub fn render(&self) -> String {
+        let lines = [
+            (&WORK, self.work.value.to_string(), &self.work.source),
+            (
+                &SHORT_BREAK,
+                self.short_break.value.to_string(),
+                &self.short_break.source,
+            ),
+            (
+                &LONG_BREAK,
+                self.long_break.value.to_string(),
+                &self.long_break.source,
+            ),
+            (
+                &BREAK_JITTER,
+                format!("\"{}\"", self.break_jitter.value),
+                &self.break_jitter.source,
+            ),
+            (&CYCLES, self.cycles.value.to_string(), &self.cycles.source),
+        ];
+        let checkpoints: Vec<String> = self
+            .notify_at
+            .value
+            .iter()
+            .map(|checkpoint| format!("\"{}\"", checkpoint))
+            .collect();
+        let notify_at = (
+            &NOTIFY_AT,
+            format!("[{}]", checkpoints.join(", ")),
+            &self.notify_at.source,
+        );
+        let ramp = self
+            .ramp
+            .value
+            .as_ref()
+            .map(|ramp| (&RAMP, ramp.to_string(), &self.ramp.source));
+        let min_runway = self.min_runway.value.map(|span| {
+            (
+                &MIN_RUNWAY,
+                format!("\"{}\"", span),
+                &self.min_runway.source,
+            )
+        });
+        let sequence = self.sequence.value.as_ref().map(|steps| {
+            let steps: Vec<String> = steps.iter().map(|step| format!("\"{}\"", step)).collect();
+            (
+                &SEQUENCE,
+                format!("[{}]", steps.join(", ")),
+                &self.sequence.source,
+            )
+        });
+        let audio_device = self.audio_device.value.as_ref().map(|name| {
+            (
+                &AUDIO_DEVICE,
+                format!("{:?}", name),
+                &self.audio_device.source,
+            )
+        });
+        let no_sound = (
+            &NO_SOUND,
+            self.no_sound.value.to_string(),
+            &self.no_sound.source,
+        );
+        let lines = lines
+            .into_iter()
+            .chain([notify_at])
+            .chain(min_runway)
+            .chain(ramp)
+            .chain(sequence)
+            .chain([no_sound])
+            .chain(audio_device)
+            .chain([
+                (
+                    &NO_NOTIFY,
+                    self.no_notify.value.to_string(),
+                    &self.no_notify.source,
+                ),
+                (
+                    &LOW_POWER,
+                    self.low_power.value.to_string(),
+                    &self.low_power.source,
+                ),
+                (
+                    &REQUIRE_ACK,
+                    self.require_ack.value.to_string(),
+                    &self.require_ack.source,
+                ),
+                (
+                    &ASK_PAUSE_REASON,
+                    self.ask_pause_reason.value.to_string(),
+                    &self.ask_pause_reason.source,
+                ),
+                (
+                    &ASK_INTENT,
+                    self.ask_intent.value.to_string(),
+                    &self.ask_intent.source,
+                ),
+                (
+                    &PRIVATE,
+                    self.private.value.to_string(),
+                    &self.private.source,
+                ),
+                (
+                    &THEME,
+                    format!("\"{}\"", self.theme.value),
+                    &self.theme.source,
+                ),
+                (
+                    &DISPLAY,
+                    format!("\"{}\"", self.display.value),
+                    &self.display.source,
+                ),
+                (
+                    &TRANSITION_PAUSE,
+                    self.transition_pause.value.to_string(),
+                    &self.transition_pause.source,
+                ),
+                (
+                    &DAILY_GOAL,
+                    self.daily_goal.value.to_string(),
+                    &self.daily_goal.source,
+                ),
+                (
+                    &WORKDAY_START,
+                    format!("\"{}\"", self.workday_start.value),
+                    &self.workday_start.source,
+                ),
+                (
+                    &WORKDAY_END,
+                    format!("\"{}\"", self.workday_end.value),
+                    &self.workday_end.source,
+                ),
+                (
+                    &SERVE_PORT,
+                    self.serve_port.value.to_string(),
+                    &self.serve_port.source,
+                ),
+            ]);
+        let mut rendered: String = lines
+            .map(|(key, value, source)| {
+                let assignment = format!("{} = {}", key.file_key, value);
+                format!("{:<20} # {}\n", assignment, source.describe(key))
+            })
+            .collect();
+        if let Some(telegram) = &self.telegram.value {
+            // The token is a secret, `config show` output may end up in a bug report.
+            let source = self.telegram.source.describe(&TELEGRAM_TOKEN);
+            rendered.push_str("\n[telegram]\n");
+            let mut lines = Vec::new();
+            if !telegram.token.is_empty() {
+                lines.push("token = \"<hidden>\"".to_string());
+            }
+            lines.push(format!("chat_id = {}", telegram.chat_id));
+            if let Some(seconds) = telegram.escalate_after {
+                lines.push(format!("escalate_after = {}", seconds));
+            }
+            for line in lines {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(ntfy) = &self.ntfy.value {
+            let source = self.ntfy.source.describe(&NTFY_TOPIC);
+            let events: Vec<String> = ntfy
+                .events
+                .iter()
+                .map(|trigger| format!("\"{}\"", trigger.name()))
+                .collect();
+            rendered.push_str("\n[ntfy]\n");
+            let mut lines = vec![
+                format!("server = {:?}", ntfy.server),
+                format!("topic = {:?}", ntfy.topic),
+            ];
+            if ntfy.token.is_some() {
+                lines.push("token = \"<hidden>\"".to_string());
+            }
+            lines.push(format!("events = [{}]", events.join(", ")));
+            if let Some(seconds) = ntfy.escalate_after {
+                lines.push(format!("escalate_after = {}", seconds));
+            }
+            for line in lines {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        for (trigger, template) in &self.notifications.value {
+            let source = self.notifications.source.describe(&NOTIFICATIONS);
+            rendered.push_str(&format!("\n[notifications.{}]\n", trigger.name()));
+            let lines = [("title", &template.title), ("body", &template.body)];
+            for (key, text) in lines {
+                if let Some(text) = text {
+                    let line = format!("{} = {:?}", key, text);
+                    rendered.push_str(&format!("{:<20} # {}\n", line, source));
+                }
+            }
+        }
+        if let Some(focus) = &self.focus.value {
+            let source = self.focus.source.describe(&FOCUS);
+            let list = |items: &[String]| {
+                let items: Vec<String> = items.iter().map(|item| format!("{:?}", item)).collect();
+                format!("[{}]", items.join(", "))
+            };
+            rendered.push_str("\n[focus]\n");
+            for line in [
+                format!("processes = {}", list(&focus.processes)),
+                format!("action = \"{}\"", focus.action.name()),
+                format!("sites = {}", list(&focus.sites)),
+                format!("hosts_file = {:?}", focus.hosts_file.display().to_string()),
+                format!("dry_run = {}", focus.dry_run),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(dim) = &self.dim.value {
+            let source = self.dim.source.describe(&DIM_LEVEL);
+            rendered.push_str("\n[dim]\n");
+            for line in [
+                format!("level = {}", dim.level),
+                format!("backend = \"{}\"", dim.backend.name()),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(led) = &self.led.value {
+            let source = self.led.source.describe(&LED);
+            rendered.push_str("\n[led]\n");
+            for line in [
+                format!("device = \"{}\"", led.device.name()),
+                format!("work = \"{}\"", led.work),
+                format!("short_break = \"{}\"", led.short_break),
+                format!("long_break = \"{}\"", led.long_break),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(flash) = &self.flash.value {
+            let source = self.flash.source.describe(&FLASH_SECONDS);
+            rendered.push_str("\n[flash]\n");
+            for line in [
+                format!("style = \"{}\"", flash.style.name()),
+                format!("seconds = {}", flash.seconds),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(lights) = &self.lights.value {
+            let source = self.lights.source.describe(&LIGHTS_URL);
+            rendered.push_str("\n[lights]\n");
+            let mut lines = vec![
+                format!("backend = \"{}\"", lights.backend.name()),
+                format!("url = {:?}", lights.url),
+            ];
+            if !lights.token.is_empty() {
+                lines.push("token = \"<hidden>\"".to_string());
+            }
+            lines.extend([
+                format!("focus = {:?}", lights.focus),
+                format!("relax = {:?}", lights.relax),
+                format!("restore = {}", lights.restore),
+                format!("retries = {}", lights.retries),
+            ]);
+            for line in lines {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(calendar) = &self.calendar.value {
+            let source = self.calendar.source.describe(&CALENDAR_URL);
+            rendered.push_str("\n[calendar]\n");
+            let mut lines = vec![
+                format!("url = {:?}", calendar.url),
+                format!("username = {:?}", calendar.username),
+            ];
+            if !calendar.password.is_empty() {
+                lines.push("password = \"<hidden>\"".to_string());
+            }
+            lines.extend([
+                format!("title = {:?}", calendar.title),
+                format!("extend_within = {}", calendar.extend_within),
+            ]);
+            for line in lines {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(gcal) = &self.gcal.value {
+            let source = self.gcal.source.describe(&GCAL_CLIENT_ID);
+            rendered.push_str("\n[gcal]\n");
+            for line in [
+                format!("client_id = {:?}", gcal.client_id),
+                "client_secret = \"<hidden>\"".to_string(),
+                format!("calendar = {:?}", gcal.calendar),
+                format!("title = {:?}", gcal.title),
+                format!("extend_within = {}", gcal.extend_within),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(leaderboard) = &self.leaderboard.value {
+            let source = self.leaderboard.source.describe(&LEADERBOARD);
+            rendered.push_str("\n[leaderboard]\n");
+            for line in [
+                format!("name = {:?}", leaderboard.name),
+                format!("dir = {:?}", leaderboard.dir.display().to_string()),
+                format!("publish = {}", leaderboard.publish),
+                format!("share_focus = {}", leaderboard.share_focus),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(schedule) = &self.schedule.value {
+            let source = self.schedule.source.describe(&SCHEDULE);
+            rendered.push_str("\n[schedule]\n");
+            for line in [
+                format!("script = {:?}", schedule.script.display().to_string()),
+                format!("interpreter = {:?}", schedule.interpreter),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        let source = self.sounds.source.describe(&SOUNDS);
+        rendered.push_str("\n[sounds]\n");
+        for cue in Cue::ALL {
+            let line = format!(
+                "{} = {:?}",
+                cue.name(),
+                self.sounds.value.sound(cue).to_string()
+            );
+            rendered.push_str(&format!("{:<20} # {}\n", line, source));
+        }
+        for line in [
+            format!("fade_in = {}", self.sounds.value.fade_in),
+            format!("repeat = {}", self.sounds.value.repeat),
+        ] {
+            rendered.push_str(&format!("{:<20} # {}\n", line, source));
+        }
+        if let Some(vibrations) = &self.vibrations.value {
+            let source = self.vibrations.source.describe(&VIBRATIONS);
+            rendered.push_str("\n[vibrations]\n");
+            let command = vibrations
+                .command
+                .as_ref()
+                .map(|command| format!("command = {:?}", command));
+            let patterns = Cue::ALL
+                .map(|cue| format!("{} = {:?}", cue.name(), vibrations.pattern(cue).to_string()));
+            for line in command.into_iter().chain(patterns) {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(quiet) = &self.quiet.value {
+            let source = self.quiet.source.describe(&QUIET);
+            rendered.push_str("\n[quiet]\n");
+            let hours = quiet.start.zip(quiet.end).map(|(start, end)| {
+                [
+                    format!("start = \"{}\"", start),
+                    format!("end = \"{}\"", end),
+                ]
+            });
+            for line in hours.into_iter().flatten().chain([
+                format!("apps = {:?}", quiet.apps),
+                format!("fullscreen = {}", quiet.fullscreen),
+            ]) {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(auto_finish) = &self.auto_finish.value {
+            let source = self.auto_finish.source.describe(&AUTO_FINISH_IDLE);
+            rendered.push_str("\n[auto_finish]\n");
+            for line in [
+                format!("idle = {}", auto_finish.idle),
+                format!("within = {}", auto_finish.within),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(auto_pause) = &self.auto_pause.value {
+            let source = self.auto_pause.source.describe(&AUTO_PAUSE_MICROPHONE);
+            rendered.push_str("\n[auto_pause]\n");
+            for line in [
+                format!("microphone = {}", auto_pause.microphone),
+                format!("camera = {}", auto_pause.camera),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(retention) = &self.retention.value {
+            let source = self.retention.source.describe(&RETENTION_KEEP);
+            rendered.push_str("\n[retention]\n");
+            let line = format!("keep = \"{}\"", retention.keep);
+            rendered.push_str(&format!("{:<20} # {}\n", line, source));
+        }
+        if let Some(digest) = &self.digest.value {
+            let source = self.digest.source.describe(&DIGEST_EMAIL);
+            rendered.push_str("\n[digest]\n");
+            for line in [
+                format!("email = {:?}", digest.email),
+                format!("day = \"{}\"", digest.day),
+                format!("at = \"{}\"", digest.at),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(notes) = &self.notes.value {
+            let source = self.notes.source.describe(&NOTES_PATH);
+            rendered.push_str("\n[notes]\n");
+            let mut lines = vec![
+                format!("path = {:?}", notes.path),
+                format!("line = {:?}", notes.line),
+                format!("date_format = {:?}", notes.date_format),
+            ];
+            if let Some(heading) = &notes.heading {
+                lines.push(format!("heading = {:?}", heading));
+            }
+            lines.push(format!("breaks = {}", notes.breaks));
+            for line in lines {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(git) = &self.git.value {
+            let source = self.git.source.describe(&GIT_REPOS);
+            rendered.push_str("\n[git]\n");
+            let mut lines = vec![format!("repos = {:?}", git.repos)];
+            if let Some(author) = &git.author {
+                lines.push(format!("author = {:?}", author));
+            }
+            for line in lines {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(compliance) = &self.compliance.value {
+            let source = self.compliance.source.describe(&COMPLIANCE_NAG_BELOW);
+            rendered.push_str("\n[compliance]\n");
+            let line = format!("nag_below = {}", compliance.nag_below);
+            rendered.push_str(&format!("{:<20} # {}\n", line, source));
+        }
+        if let Some(eyes) = &self.eyes.value {
+            let source = self.eyes.source.describe(&EYES_EVERY);
+            rendered.push_str("\n[eyes]\n");
+            for line in [
+                format!("every = {}", eyes.every),
+                format!("look_away = {}", eyes.look_away),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        if let Some(keys) = &self.keys.value {
+            let source = self.keys.source.describe(&KEYS_LAYOUT);
+            rendered.push_str("\n[keys]\n");
+            for line in [
+                format!("layout = \"{}\"", keys.layout),
+                format!("repeat = {}", keys.repeat),
+                format!("debounce = {}", keys.debounce),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+            if !keys.bindings.is_empty() {
+                rendered.push_str("\n[keys.bindings]\n");
+                for (key, action) in &keys.bindings {
+                    let line = format!("{:?} = \"{}\"", key, action);
+                    rendered.push_str(&format!("{:<20} # {}\n", line, source));
+                }
+            }
+        }
+        for reminder in &self.reminders.value {
+            let source = self.reminders.source.describe(&REMINDERS);
+            rendered.push_str("\n[[reminders]]\n");
+            for line in [
+                format!("text = {:?}", reminder.text),
+                format!("every = {}", reminder.every),
+                format!("mid_work = {}", reminder.mid_work),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        for (name, session) in &self.sessions.value {
+            let source = self.sessions.source.describe(&SESSIONS);
+            rendered.push_str(&format!("\n[sessions.{}]\n", name));
+            let counts_as = match session.counts_as {
+                SessionKind::Work => "work",
+                SessionKind::ShortBreak => "short_break",
+                SessionKind::LongBreak => "long_break",
+            };
+            for line in [
+                format!("label = {:?}", session.label),
+                format!("minutes = {}", session.minutes),
+                format!("counts_as = \"{}\"", counts_as),
+                format!("color = \"{}\"", session.color),
+            ] {
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        let stages = &self.stages.value;
+        if stages != &BreakStages::default() {
+            let source = self.stages.source.describe(&STAGES);
+            rendered.push_str("\n[stages]\n");
+            for (name, stages) in [
+                ("short_break", &stages.short_break),
+                ("long_break", &stages.long_break),
+            ] {
+                let stages: Vec<String> = stages
+                    .iter()
+                    .map(|stage| {
+                        format!(
+                            "{{ label = {:?}, minutes = {} }}",
+                            stage.label, stage.minutes
+                        )
+                    })
+                    .collect();
+                let line = format!("{} = [{}]", name, stages.join(", "));
+                rendered.push_str(&format!("{:<20} # {}\n", line, source));
+            }
+        }
+        rendered
+    }
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    setting: String,
+    message: String,
+    hint: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot access config file {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("invalid config file {}:\n{}", path.display(), message.trim_end())]
+    Parse { path: PathBuf, message: String },
+    #[error("invalid settings{}", render_problems(.0))]
+    Invalid(Vec<Problem>),
+    #[error("config file {} already exists, use --force to overwrite it", .0.display())]
+    AlreadyExists(PathBuf),
+    #[error("cannot locate the configuration directory, use --config <path> or {CONFIG_ENV}")]
+    NoConfigDir,
+    #[error("cannot run editor `{editor}`: {source}, set $VISUAL or $EDITOR")]
+    Editor { editor: String, source: io::Error },
+    #[error("editor `{editor}` failed ({status}), the configuration file may not be saved")]
+    EditorFailed {
+        editor: String,
+        status: process::ExitStatus,
+    },
+}
+
+fn render_problems(problems: &[Problem]) -> String {
+    problems
+        .iter()
+        .map(|problem| {
+            format!(
+                "\n  - {} {}\n    hint: {}",
+                problem.setting, problem.message, problem.hint
+            )
+        })
+        .collect()
+}
+
+/// Returns the default location of the configuration file, following the XDG base directory
+/// convention.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config_dir.join("rustodoro").join("config.toml"))
+}
+
+/// Returns the configuration file to use and whether it was chosen explicitly, through `--config`
+/// or `RUSTODORO_CONFIG`.
+pub fn config_path(cli: &Cli) -> Option<(PathBuf, bool)> {
+    cli.config
+        .clone()
+        .or_else(|| env::var_os(CONFIG_ENV).map(PathBuf::from))
+        .map(|path| (path, true))
+        .or_else(|| default_config_path().map(|path| (path, false)))
+}
+
+/// Reads and parses the configuration file. A missing file is only an error when its path was
+/// given explicitly.
+pub fn load_file(path: &Path, explicit: bool) -> Result<Option<Layer>, ConfigError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => return Ok(None),
+        Err(source) => {
+            return Err(ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+}
+
+/// Validates the values of a single layer.
+pub fn validate_layer(layer: &Layer, source: &Source) -> Result<(), ConfigError> {
+    let mut problems = Vec::new();
+    check_duration(&mut problems, &WORK, source, layer.work);
+    check_duration(&mut problems, &SHORT_BREAK, source, layer.short_break);
+    check_duration(&mut problems, &LONG_BREAK, source, layer.long_break);
+    check_break_jitter(&mut problems, source, layer.break_jitter);
+    for checkpoint in layer.notify_at.iter().flatten() {
+        check_checkpoint(&mut problems, source, checkpoint);
+    }
+    check_min_runway(&mut problems, source, layer.min_runway);
+    check_cycles(&mut problems, source, layer.cycles);
+    if let Some(ramp) = &layer.ramp {
+        check_ramp(&mut problems, source, ramp);
+    }
+    if let Some(sequence) = &layer.sequence
+        && let Some(message) = settings::sequence_problem(sequence)
+    {
+        problems.push(Problem {
+            setting: source.setting(&SEQUENCE),
+            message,
+            hint: "e.g. [\"work\", \"work\", \"short\", \"work\", \"long\"]".to_string(),
+        });
+    }
+    check_transition_pause(&mut problems, source, layer.transition_pause);
+    check_audio_device(&mut problems, source, layer.audio_device.as_deref());
+    check_daily_goal(&mut problems, source, layer.daily_goal);
+    check_serve_port(&mut problems, source, layer.serve_port);
+    if let (Some(short_break), Some(long_break)) = (layer.short_break, layer.long_break) {
+        check_break_order(
+            &mut problems,
+            &source.setting(&LONG_BREAK),
+            short_break,
+            long_break,
+        );
+    }
+    if let (Some(start), Some(end)) = (layer.workday_start, layer.workday_end) {
+        check_workday(&mut problems, &source.setting(&WORKDAY_END), start, end);
+    }
+    if let Some(telegram) = &layer.telegram {
+        check_telegram(&mut problems, source, telegram);
+    }
+    if let Some(ntfy) = &layer.ntfy {
+        check_ntfy(&mut problems, source, ntfy);
+    }
+    for (trigger, template) in layer.notifications.iter().flatten() {
+        check_notification(&mut problems, source, *trigger, template);
+    }
+    if let Some(focus) = &layer.focus {
+        check_focus(&mut problems, source, focus);
+    }
+    if let Some(dim) = &layer.dim {
+        check_dim(&mut problems, source, dim);
+    }
+    if let Some(flash) = &layer.flash
+        && !(1..=10).contains(&flash.seconds)
+    {
+        problems.push(Problem {
+            setting: source.setting(&FLASH_SECONDS),
+            message: format!("must be from 1 to 10 seconds, got {}", flash.seconds),
+            hint: "e.g. seconds = 3".to_string(),
+        });
+    }
+    if let Some(lights) = &layer.lights {
+        check_lights(&mut problems, source, lights);
+    }
+    if let Some(calendar) = &layer.calendar {
+        check_calendar(&mut problems, source, calendar);
+    }
+    if let Some(gcal) = &layer.gcal {
+        check_gcal(&mut problems, source, gcal);
+    }
+    if let Some(leaderboard) = &layer.leaderboard {
+        check_leaderboard(&mut problems, source, leaderboard);
+    }
+    if let Some(schedule) = &layer.schedule {
+        check_schedule(&mut problems, source, schedule);
+    }
+    if let Some(quiet) = &layer.quiet {
+        check_quiet(&mut problems, source, quiet);
+    }
+    if let Some(auto_finish) = &layer.auto_finish {
+        check_auto_finish(&mut problems, source, auto_finish);
+    }
+    if let Some(auto_pause) = &layer.auto_pause
+        && !auto_pause.microphone
+        && !auto_pause.camera
+    {
+        problems.push(Problem {
+            setting: source.setting(&AUTO_PAUSE_MICROPHONE),
+            message: "watches neither the microphone nor the camera".to_string(),
+            hint: "remove [auto_pause], or set microphone = true".to_string(),
+        });
+    }
+    if let Some(digest) = &layer.digest {
+        check_digest(&mut problems, source, digest);
+    }
+    if let Some(notes) = &layer.notes {
+        check_notes(&mut problems, source, notes);
+    }
+    if let Some(git) = &layer.git {
+        check_git(&mut problems, source, git);
+    }
+    if let Some(compliance) = &layer.compliance {
+        check_compliance(&mut problems, source, compliance);
+    }
+    if let Some(eyes) = &layer.eyes {
+        check_eyes(&mut problems, source, eyes);
+    }
+    if let Some(keys) = &layer.keys {
+        check_keys(&mut problems, source, keys);
+    }
+    for reminder in layer.reminders.iter().flatten() {
+        check_reminder(&mut problems, source, reminder);
+    }
+    if let Some(sounds) = &layer.sounds {
+        check_sounds(&mut problems, source, sounds);
+    }
+    if let Some(vibrations) = &layer.vibrations {
+        check_vibrations(&mut problems, source, vibrations);
+    }
+    for (name, session) in layer.sessions.iter().flatten() {
+        check_session(&mut problems, source, name, session);
+    }
+    if let Some(stages) = &layer.stages {
+        check_stages(&mut problems, source, stages);
+    }
+    for (name, timer) in layer.timers.iter().flatten() {
+        if let Err(message) = parse_name(name) {
+            problems.push(Problem {
+                setting: source.setting(&TIMERS),
+                message,
+                hint: "the name of a table is the name given to --name".to_string(),
+            });
+        }
+        if let Err(ConfigError::Invalid(invalid)) = validate_layer(&timer.layer(), source) {
+            problems.extend(invalid);
+        }
+    }
+    for day in WEEK {
+        if let Some(profile) = layer
+            .weekdays
+            .as_ref()
+            .and_then(|weekdays| weekdays.get(day))
+            && let Err(ConfigError::Invalid(invalid)) = validate_layer(&profile.layer(), source)
+        {
+            problems.extend(invalid);
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(problems))
+    }
+}
+
+/// Builds the effective configuration from the defaults, the configuration file, the environment
+/// and the command-line flags, each layer overriding the previous one.
+pub fn resolve(cli: &Cli) -> Result<EffectiveConfig, ConfigError> {
+    resolve_file(cli, config_path(cli))
+}
+
+/// Same as [`resolve`], reading the configuration file `file` instead, with whether it was chosen
+/// explicitly.
+pub fn resolve_file(
+    cli: &Cli,
+    file: Option<(PathBuf, bool)>,
+) -> Result<EffectiveConfig, ConfigError> {
+    resolve_day(cli, file, Local::now().weekday())
+}
+
+/// Same as [`resolve_file`], for `day` of the week: its profile from the `[weekdays]` table
+/// applies, if any.
+pub fn resolve_day(
+    cli: &Cli,
+    file: Option<(PathBuf, bool)>,
+    day: Weekday,
+) -> Result<EffectiveConfig, ConfigError> {
+    let mut layers = Vec::new();
+    if let Some((path, explicit)) = file
+        && let Some(file) = load_file(&path, explicit)?
+    {
+        let weekday = file
+            .weekdays
+            .as_ref()
+            .and_then(|weekdays| weekdays.get(day))
+            .map(ProfileConfig::layer);
+        let timer = cli
+            .name
+            .as_ref()
+            .and_then(|name| file.timers.as_ref()?.get(name))
+            .map(ProfileConfig::layer);
+        layers.push((file, Source::File(path.clone())));
+        // The named timer is more specific than the day.
+        for profile in [weekday, timer].into_iter().flatten() {
+            layers.push((profile, Source::File(path.clone())));
+        }
+    }
+    layers.push((env_layer()?, Source::Env));
+    layers.push((
+        Layer {
+            work: cli.run.work_duration,
+            short_break: cli.run.short_break,
+            long_break: cli.run.long_break,
+            break_jitter: cli.run.break_jitter,
+            notify_at: (!cli.run.notify_at.is_empty()).then(|| cli.run.notify_at.clone()),
+            min_runway: cli.run.min_runway,
+            cycles: cli.run.cycles,
+            ramp: cli.run.ramp.clone(),
+            sequence: None,
+            no_sound: cli.run.no_sound.then_some(true),
+            audio_device: cli.run.audio_device.clone(),
+            no_notify: cli.run.no_notify.then_some(true),
+            low_power: cli.run.low_power.then_some(true),
+            require_ack: cli.run.require_ack.then_some(true),
+            ask_pause_reason: cli.run.ask_pause_reason.then_some(true),
+            ask_intent: cli.run.ask_intent.then_some(true),
+            private: cli.run.private.then_some(true),
+            theme: cli.run.theme,
+            display: cli.run.display,
+            transition_pause: cli.run.transition_pause,
+            daily_goal: cli.run.daily_goal,
+            workday_start: cli.run.workday_start,
+            workday_end: cli.run.workday_end,
+            serve_port: cli.run.serve_port,
+            telegram: None,
+            ntfy: None,
+            notifications: None,
+            focus: None,
+            dim: None,
+            led: None,
+            flash: None,
+            lights: None,
+            calendar: None,
+            gcal: None,
+            leaderboard: None,
+            schedule: None,
+            quiet: None,
+            auto_finish: None,
+            auto_pause: None,
+            retention: None,
+            digest: None,
+            notes: None,
+            git: None,
+            compliance: None,
+            eyes: None,
+            keys: None,
+            reminders: None,
+            sounds: None,
+            vibrations: None,
+            sessions: None,
+            stages: None,
+            timers: None,
+            weekdays: None,
+        },
+        Source::Flag,
+    ));
+
+    let defaults = Settings::default();
+    let mut config = EffectiveConfig {
+        work: Value::default(defaults.work_duration),
+        short_break: Value::default(defaults.short_break),
+        long_break: Value::default(defaults.long_break),
+        break_jitter: Value::default(defaults.break_jitter),
+        notify_at: Value::default(defaults.notify_at),
+        min_runway: Value::default(None),
+        cycles: Value::default(defaults.cycles),
+        ramp: Value::default(defaults.ramp),
+        sequence: Value::default(defaults.sequence),
+        no_sound: Value::default(defaults.no_sound),
+        audio_device: Value::default(defaults.audio_device),
+        no_notify: Value::default(defaults.no_notify),
+        low_power: Value::default(defaults.low_power),
+        require_ack: Value::default(defaults.require_ack),
+        ask_pause_reason: Value::default(defaults.ask_pause_reason),
+        ask_intent: Value::default(defaults.ask_intent),
+        private: Value::default(defaults.private),
+        theme: Value::default(defaults.theme),
+        display: Value::default(defaults.display),
+        transition_pause: Value::default(defaults.transition_pause),
+        daily_goal: Value::default(defaults.daily_goal),
+        workday_start: Value::default(defaults.workday_start),
+        workday_end: Value::default(defaults.workday_end),
+        serve_port: Value::default(0),
+        telegram: Value::default(None),
+        ntfy: Value::default(None),
+        notifications: Value::default(BTreeMap::new()),
+        focus: Value::default(None),
+        dim: Value::default(None),
+        led: Value::default(None),
+        flash: Value::default(None),
+        lights: Value::default(None),
+        calendar: Value::default(None),
+        gcal: Value::default(None),
+        leaderboard: Value::default(None),
+        schedule: Value::default(None),
+        quiet: Value::default(None),
+        auto_finish: Value::default(None),
+        auto_pause: Value::default(None),
+        retention: Value::default(None),
+        digest: Value::default(None),
+        notes: Value::default(None),
+        git: Value::default(None),
+        compliance: Value::default(None),
+        eyes: Value::default(None),
+        keys: Value::default(None),
+        reminders: Value::default(Vec::new()),
+        sounds: Value::default(defaults.sounds),
+        vibrations: Value::default(None),
+        sessions: Value::default(defaults.sessions),
+        stages: Value::default(defaults.stages),
+    };
+    for (layer, source) in layers {
+        validate_layer(&layer, &source)?;
+        config.work.merge(layer.work, &source);
+        config.short_break.merge(layer.short_break, &source);
+        config.long_break.merge(layer.long_break, &source);
+        config.break_jitter.merge(layer.break_jitter, &source);
+        config.notify_at.merge(layer.notify_at, &source);
+        config.min_runway.merge(layer.min_runway.map(Some), &source);
+        config.cycles.merge(layer.cycles, &source);
+        config.ramp.merge(layer.ramp.map(Some), &source);
+        config.sequence.merge(layer.sequence.map(Some), &source);
+        config.no_sound.merge(layer.no_sound, &source);
+        config
+            .audio_device
+            .merge(layer.audio_device.map(Some), &source);
+        config.no_notify.merge(layer.no_notify, &source);
+        config.low_power.merge(layer.low_power, &source);
+        config.require_ack.merge(layer.require_ack, &source);
+        config
+            .ask_pause_reason
+            .merge(layer.ask_pause_reason, &source);
+        config.ask_intent.merge(layer.ask_intent, &source);
+        config.private.merge(layer.private, &source);
+        config.theme.merge(layer.theme, &source);
+        config.display.merge(layer.display, &source);
+        config
+            .transition_pause
+            .merge(layer.transition_pause, &source);
+        config.daily_goal.merge(layer.daily_goal, &source);
+        config.workday_start.merge(layer.workday_start, &source);
+        config.workday_end.merge(layer.workday_end, &source);
+        config.serve_port.merge(layer.serve_port, &source);
+        config.telegram.merge(layer.telegram.map(Some), &source);
+        config.ntfy.merge(layer.ntfy.map(Some), &source);
+        config.notifications.merge(layer.notifications, &source);
+        config.focus.merge(layer.focus.map(Some), &source);
+        config.dim.merge(layer.dim.map(Some), &source);
+        config.led.merge(layer.led.map(Some), &source);
+        config.flash.merge(layer.flash.map(Some), &source);
+        config.lights.merge(layer.lights.map(Some), &source);
+        config.calendar.merge(layer.calendar.map(Some), &source);
+        config.gcal.merge(layer.gcal.map(Some), &source);
+        config
+            .leaderboard
+            .merge(layer.leaderboard.map(Some), &source);
+        config.schedule.merge(layer.schedule.map(Some), &source);
+        config.quiet.merge(layer.quiet.map(Some), &source);
+        config
+            .auto_finish
+            .merge(layer.auto_finish.map(Some), &source);
+        config.auto_pause.merge(layer.auto_pause.map(Some), &source);
+        config.retention.merge(layer.retention.map(Some), &source);
+        config.digest.merge(layer.digest.map(Some), &source);
+        config.notes.merge(layer.notes.map(Some), &source);
+        config.git.merge(layer.git.map(Some), &source);
+        config.compliance.merge(layer.compliance.map(Some), &source);
+        config.eyes.merge(layer.eyes.map(Some), &source);
+        config.keys.merge(layer.keys.map(Some), &source);
+        config.reminders.merge(layer.reminders, &source);
+        config.sounds.merge(layer.sounds, &source);
+        config.vibrations.merge(layer.vibrations.map(Some), &source);
+        config.sessions.merge(layer.sessions, &source);
+        config.stages.merge(layer.stages, &source);
+    }
+
+    let mut problems = Vec::new();
+    check_break_order(
+        &mut problems,
+        "long break",
+        config.short_break.value,
+        config.long_break.value,
+    );
+    check_workday(
+        &mut problems,
+        "workday end",
+        config.workday_start.value,
+        config.workday_end.value,
+    );
+    if let Some(sequence) = &config.sequence.value {
+        check_sequence_sessions(&mut problems, sequence, &config.sessions.value);
+    }
+    if !problems.is_empty() {
+        return Err(ConfigError::Invalid(problems));
+    }
+    Ok(config)
+}
+
+/// Writes the commented default configuration file.
+pub fn init(path: &Path, force: bool) -> Result<(), ConfigError> {
+    init_with(path, force, &[])
+}
+
+/// The content written by `config init`, with the settings of `values` given instead of their
+/// defaults.
+pub fn default_file_with(values: &[(&str, String)]) -> String {
+    DEFAULT_FILE
+        .lines()
+        .map(|line| {
+            let value = values.iter().find(|(key, _)| {
+                line.strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with(" = "))
+            });
+            match value {
+                Some((key, value)) => format!("{} = {}\n", key, value),
+                None => format!("{}\n", line),
+            }
+        })
+        .collect()
+}
+
+/// Writes the configuration file at `path` with the settings of `values` given.
+pub fn init_with(path: &Path, force: bool, values: &[(&str, String)]) -> Result<(), ConfigError> {
+    if path.exists() && !force {
+        return Err(ConfigError::AlreadyExists(path.to_path_buf()));
+    }
+    let io_error = |source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    fs::write(path, default_file_with(values)).map_err(io_error)
+}
+
+/// Opens the configuration file in the user's editor, creating it first if needed.
+pub fn edit(path: &Path) -> Result<(), ConfigError> {
+    if !path.exists() {
+        init(path, false)?;
+    }
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    run_editor(&editor, path)
+}
+
+/// Opens `path` in `editor`, which may come with arguments, e.g. `code --wait`, and fails when it
+/// exits with a failure, e.g. after `:cq` in Vim.
+fn run_editor(editor: &str, path: &Path) -> Result<(), ConfigError> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|source| ConfigError::Editor {
+            editor: editor.to_string(),
+            source,
+        })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ConfigError::EditorFailed {
+            editor: editor.to_string(),
+            status,
+        })
+    }
+}
+
+/// Runs a `config` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, action: &ConfigAction) -> i32 {
+    let result = match action {
+        ConfigAction::Show => resolve(cli).map(|config| print!("{}", config.render())),
+        action => match config_path(cli) {
+            None => Err(ConfigError::NoConfigDir),
+            Some((path, explicit)) => match action {
+                ConfigAction::Init { force } => init(&path, *force)
+                    .map(|_| println!("Configuration file written to {}.", path.display())),
+                ConfigAction::Edit => edit(&path).and_then(|_| validate(&path, true)),
+                ConfigAction::Path => {
+                    println!("{}", path.display());
+                    Ok(())
+                }
+                _ => validate(&path, explicit),
+            },
+        },
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn validate(path: &Path, explicit: bool) -> Result<(), ConfigError> {
+    match load_file(path, explicit)? {
+        Some(file) => {
+            validate_layer(&file, &Source::File(path.to_path_buf()))?;
+            println!("Configuration file {} is valid.", path.display());
+        }
+        None => println!(
+            "No configuration file at {}, the defaults will be used.",
+            path.display()
+        ),
+    }
+    Ok(())
+}
+
+/// Reads the `RUSTODORO_*` environment variables.
+fn env_layer() -> Result<Layer, ConfigError> {
+    let mut problems = Vec::new();
+    let layer = Layer {
+        work: env_number(&mut problems, &WORK),
+        short_break: env_number(&mut problems, &SHORT_BREAK),
+        long_break: env_number(&mut problems, &LONG_BREAK),
+        break_jitter: env_jitter(&mut problems),
+        notify_at: env_checkpoints(&mut problems),
+        min_runway: env_span(&mut problems, &MIN_RUNWAY),
+        cycles: env_number(&mut problems, &CYCLES),
+        ramp: env_ramp(&mut problems),
+        sequence: None,
+        no_sound: env_bool(&mut problems, &NO_SOUND),
+        audio_device: env::var(AUDIO_DEVICE.env).ok(),
+        no_notify: env_bool(&mut problems, &NO_NOTIFY),
+        low_power: env_bool(&mut problems, &LOW_POWER),
+        require_ack: env_bool(&mut problems, &REQUIRE_ACK),
+        ask_pause_reason: env_bool(&mut problems, &ASK_PAUSE_REASON),
+        ask_intent: env_bool(&mut problems, &ASK_INTENT),
+        private: env_bool(&mut problems, &PRIVATE),
+        theme: env_theme(&mut problems),
+        display: env_display(&mut problems),
+        transition_pause: env_number(&mut problems, &TRANSITION_PAUSE),
+        daily_goal: env_number(&mut problems, &DAILY_GOAL),
+        workday_start: env_time(&mut problems, &WORKDAY_START),
+        workday_end: env_time(&mut problems, &WORKDAY_END),
+        serve_port: env_number(&mut problems, &SERVE_PORT),
+        telegram: None,
+        ntfy: None,
+        notifications: None,
+        focus: None,
+        dim: None,
+        led: None,
+        flash: None,
+        lights: None,
+        calendar: None,
+        gcal: None,
+        leaderboard: None,
+        schedule: None,
+        quiet: None,
+        auto_finish: None,
+        auto_pause: None,
+        retention: None,
+        digest: None,
+        notes: None,
+        git: None,
+        compliance: None,
+        eyes: None,
+        keys: None,
+        reminders: None,
+        sounds: None,
+        vibrations: None,
+        sessions: None,
+        stages: None,
+        timers: None,
+        weekdays: None,
+    };
+    if problems.is_empty() {
+        Ok(layer)
+    } else {
+        Err(ConfigError::Invalid(problems))
+    }
+}
+
+fn env_number(problems: &mut Vec<Problem>, key: &Key) -> Option<u64> {
+    parse_env_number(problems, key, &env::var(key.env).ok()?)
+}
+
+/// The whole number of `value`, the environment variable of `key`.
+fn parse_env_number(problems: &mut Vec<Problem>, key: &Key, value: &str) -> Option<u64> {
+    match value.trim().parse() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(key),
+                message: format!("must be a whole number, got `{}`", value),
+                hint: format!("unset it or use a value such as {}=25", key.env),
+            });
+            None
+        }
+    }
+}
+
+fn env_bool(problems: &mut Vec<Problem>, key: &Key) -> Option<bool> {
+    let value = env::var(key.env).ok()?;
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" | "" => Some(false),
+        _ => {
+            problems.push(Problem {
+                setting: Source::Env.setting(key),
+                message: format!("must be a boolean, got `{}`", value),
+                hint: "use true or false".to_string(),
+            });
+            None
+        }
+    }
+}
+
+fn env_theme(problems: &mut Vec<Problem>) -> Option<Theme> {
+    let value = env::var(THEME.env).ok()?;
+    match <Theme as clap::ValueEnum>::from_str(value.trim(), true) {
+        Ok(theme) => Some(theme),
+        Err(_) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(&THEME),
+                message: format!("must be a theme name, got `{}`", value),
+                hint: "use emoji or plain".to_string(),
+            });
+            None
+        }
+    }
+}
+
+fn env_display(problems: &mut Vec<Problem>) -> Option<DisplayMode> {
+    let value = env::var(DISPLAY.env).ok()?;
+    match <DisplayMode as clap::ValueEnum>::from_str(value.trim(), true) {
+        Ok(display) => Some(display),
+        Err(_) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(&DISPLAY),
+                message: format!("must be a display name, got `{}`", value),
+                hint: "use bar or zen".to_string(),
+            });
+            None
+        }
+    }
+}
+
+fn env_ramp(problems: &mut Vec<Problem>) -> Option<Ramp> {
+    let value = env::var(RAMP.env).ok()?;
+    match value.parse() {
+        Ok(ramp) => Some(ramp),
+        Err(message) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(&RAMP),
+                message,
+                hint: format!("unset it or use a value such as {}=15,20,25,30", RAMP.env),
+            });
+            None
+        }
+    }
+}
+
+fn env_jitter(problems: &mut Vec<Problem>) -> Option<Jitter> {
+    let value = env::var(BREAK_JITTER.env).ok()?;
+    match value.parse() {
+        Ok(jitter) => Some(jitter),
+        Err(message) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(&BREAK_JITTER),
+                message,
+                hint: format!("unset it or use a value such as {}=2m", BREAK_JITTER.env),
+            });
+            None
+        }
+    }
+}
+
+fn env_span(problems: &mut Vec<Problem>, key: &Key) -> Option<Span> {
+    let value = env::var(key.env).ok()?;
+    match value.parse() {
+        Ok(span) => Some(span),
+        Err(message) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(key),
+                message,
+                hint: format!("unset it or use a value such as {}=10m", key.env),
+            });
+            None
+        }
+    }
+}
+
+fn env_checkpoints(problems: &mut Vec<Problem>) -> Option<Vec<Checkpoint>> {
+    let value = env::var(NOTIFY_AT.env).ok()?;
+    match value.split(',').map(str::parse).collect() {
+        Ok(checkpoints) => Some(checkpoints),
+        Err(message) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(&NOTIFY_AT),
+                message,
+                hint: format!(
+                    "unset it or use a value such as {}=50%,5m,1m",
+                    NOTIFY_AT.env
+                ),
+            });
+            None
+        }
+    }
+}
+
+fn env_time(problems: &mut Vec<Problem>, key: &Key) -> Option<TimeOfDay> {
+    let value = env::var(key.env).ok()?;
+    match value.parse() {
+        Ok(time) => Some(time),
+        Err(message) => {
+            problems.push(Problem {
+                setting: Source::Env.setting(key),
+                message,
+                hint: format!("use a 24-hour time such as {}=09:00", key.env),
+            });
+            None
+        }
+    }
+}
+
+fn check_ramp(problems: &mut Vec<Problem>, source: &Source, ramp: &Ramp) {
+    if let Some(message) = ramp.problem() {
+        problems.push(Problem {
+            setting: source.setting(&RAMP),
+            message,
+            hint: "e.g. [15, 20, 25, 30], or { start = 15, step = 5, max = 40 } to add 5 minutes each cycle"
+                .to_string(),
+        });
+    }
+}
+
+fn check_duration(problems: &mut Vec<Problem>, key: &Key, source: &Source, value: Option<u64>) {
+    match value {
+        Some(0) => problems.push(Problem {
+            setting: source.setting(key),
+            message: "must be at least 1 minute".to_string(),
+            hint: if key.flag == WORK.flag {
+                format!(
+                    "a classic Pomodoro lasts {} minutes",
+                    Settings::default().work_duration
+                )
+            } else {
+                "to skip a break, press [s] while it is running".to_string()
+            },
+        }),
+        Some(minutes) if minutes > MAX_DURATION_MINUTES => problems.push(Problem {
+            setting: source.setting(key),
+            message: format!(
+                "cannot exceed 24 hours ({} minutes), got {}",
+                MAX_DURATION_MINUTES, minutes
+            ),
+            hint: "durations are expressed in minutes, not seconds".to_string(),
+        }),
+        _ => {}
+    }
+}
+
+fn check_cycles(problems: &mut Vec<Problem>, source: &Source, value: Option<u64>) {
+    match value {
+        Some(0) => problems.push(Problem {
+            setting: source.setting(&CYCLES),
+            message: "must be at least 1".to_string(),
+            hint: format!(
+                "a long break usually comes after {} work sessions",
+                Settings::default().cycles
+            ),
+        }),
+        Some(cycles) if cycles > MAX_CYCLES => problems.push(Problem {
+            setting: source.setting(&CYCLES),
+            message: format!("cannot exceed {}, got {}", MAX_CYCLES, cycles),
+            hint: "cycles count the work sessions before a long break, the run repeats them"
+                .to_string(),
+        }),
+        _ => {}
+    }
+}
+
+fn check_break_jitter(problems: &mut Vec<Problem>, source: &Source, value: Option<Jitter>) {
+    if let Some(jitter) = value
+        && jitter.seconds > MAX_BREAK_JITTER
+    {
+        problems.push(Problem {
+            setting: source.setting(&BREAK_JITTER),
+            message: format!(
+                "cannot exceed {} minutes, got {}",
+                MAX_BREAK_JITTER / 60,
+                jitter
+            ),
+            hint: "use a few minutes such as 2m, or 0m to keep the breaks as they are".to_string(),
+        });
+    }
+}
+
+fn check_checkpoint(problems: &mut Vec<Problem>, source: &Source, checkpoint: &Checkpoint) {
+    if let Some(message) = checkpoint.problem() {
+        problems.push(Problem {
+            setting: source.setting(&NOTIFY_AT),
+            message,
+            hint: "e.g. [\"50%\", \"5m\", \"1m\"]".to_string(),
+        });
+    }
+}
+
+fn check_min_runway(problems: &mut Vec<Problem>, source: &Source, value: Option<Span>) {
+    if let Some(span) = value
+        && span.seconds > MAX_DURATION_MINUTES * 60
+    {
+        problems.push(Problem {
+            setting: source.setting(&MIN_RUNWAY),
+            message: format!(
+                "cannot exceed 24 hours ({} minutes), got {}",
+                MAX_DURATION_MINUTES, span
+            ),
+            hint: "a few minutes such as 10m, below which a session is not worth starting"
+                .to_string(),
+        });
+    }
+}
+
+fn check_audio_device(problems: &mut Vec<Problem>, source: &Source, value: Option<&str>) {
+    if value.is_some_and(|name| name.trim().is_empty()) {
+        problems.push(Problem {
+            setting: source.setting(&AUDIO_DEVICE),
+            message: "must name a device".to_string(),
+            hint: "see `rustodoro audio list`, or leave it out for the default device".to_string(),
+        });
+    }
+}
+
+fn check_transition_pause(problems: &mut Vec<Problem>, source: &Source, value: Option<u64>) {
+    if let Some(seconds) = value
+        && seconds > MAX_TRANSITION_PAUSE
+    {
+        problems.push(Problem {
+            setting: source.setting(&TRANSITION_PAUSE),
+            message: format!(
+                "cannot exceed {} seconds, got {}",
+                MAX_TRANSITION_PAUSE, seconds
+            ),
+            hint:
+                "the transition pause is expressed in seconds, use 0 to start sessions right away"
+                    .to_string(),
+        });
+    }
+}
+
+fn check_daily_goal(problems: &mut Vec<Problem>, source: &Source, value: Option<u64>) {
+    match value {
+        Some(0) => problems.push(Problem {
+            setting: source.setting(&DAILY_GOAL),
+            message: "must be at least 1".to_string(),
+            hint: format!(
+                "the default goal is {} pomodoros a day",
+                Settings::default().daily_goal
+            ),
+        }),
+        Some(goal) if goal > MAX_DAILY_GOAL => problems.push(Problem {
+            setting: source.setting(&DAILY_GOAL),
+            message: format!("cannot exceed {}, got {}", MAX_DAILY_GOAL, goal),
+            hint: "the goal counts pomodoros, not minutes".to_string(),
+        }),
+        _ => {}
+    }
+}
+
+fn check_serve_port(problems: &mut Vec<Problem>, source: &Source, value: Option<u64>) {
+    if let Some(port) = value
+        && port > u64::from(u16::MAX)
+    {
+        problems.push(Problem {
+            setting: source.setting(&SERVE_PORT),
+            message: format!("must be a port number up to {}, got {}", u16::MAX, port),
+            hint: "use 0 to disable the browser endpoint".to_string(),
+        });
+    }
+}
+
+fn check_break_order(
+    problems: &mut Vec<Problem>,
+    setting: &str,
+    short_break: u64,
+    long_break: u64,
+) {
+    if long_break < short_break {
+        problems.push(Problem {
+            setting: setting.to_string(),
+            message: format!(
+                "({} min) is shorter than the short break ({} min)",
+                long_break, short_break
+            ),
+            hint: "the durations may have been swapped, long breaks are meant to be longer"
+                .to_string(),
+        });
+    }
+}
+
+fn check_workday(problems: &mut Vec<Problem>, setting: &str, start: TimeOfDay, end: TimeOfDay) {
+    if end <= start {
+        problems.push(Problem {
+            setting: setting.to_string(),
+            message: format!("({}) is not after the workday start ({})", end, start),
+            hint: "times are written on 24 hours, e.g. 17:00 for 5pm".to_string(),
+        });
+    }
+}
+
+fn check_telegram(problems: &mut Vec<Problem>, source: &Source, telegram: &TelegramConfig) {
+    // Tokens look like `123456:ABC-DEF...`, the bot id coming first. A token left out is in the
+    // keychain.
+    let well_formed = telegram.token.split_once(':').is_some_and(|(id, secret)| {
+        !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) && !secret.is_empty()
+    });
+    if !telegram.token.is_empty() && !well_formed {
+        problems.push(Problem {
+            setting: source.setting(&TELEGRAM_TOKEN),
+            message: "is not a bot token".to_string(),
+            hint: "copy the token @BotFather gave when creating the bot, e.g. 123456:ABC-DEF"
+                .to_string(),
+        });
+    }
+    if telegram.chat_id == 0 {
+        problems.push(Problem {
+            setting: source.setting(&TELEGRAM_CHAT_ID),
+            message: "must not be 0".to_string(),
+            hint: "send a message to the bot and read the chat id from its getUpdates".to_string(),
+        });
+    }
+    check_escalate_after(
+        problems,
+        source,
+        &TELEGRAM_ESCALATE_AFTER,
+        telegram.escalate_after,
+    );
+}
+
+fn check_ntfy(problems: &mut Vec<Problem>, source: &Source, ntfy: &NtfyConfig) {
+    if !ntfy.server.starts_with("https://") && !ntfy.server.starts_with("http://") {
+        problems.push(Problem {
+            setting: source.setting(&NTFY_SERVER),
+            message: format!("must be an http(s) URL, got `{}`", ntfy.server),
+            hint: "leave it out to use https://ntfy.sh".to_string(),
+        });
+    }
+    let topic_chars = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if ntfy.topic.is_empty() || ntfy.topic.len() > 64 || !ntfy.topic.chars().all(topic_chars) {
+        problems.push(Problem {
+            setting: source.setting(&NTFY_TOPIC),
+            message: format!("is not a valid topic name, got `{}`", ntfy.topic),
+            hint: "topics have up to 64 letters, digits, `-` or `_`; anyone knowing the topic \
+                   gets the notifications, so pick a hard to guess one"
+                .to_string(),
+        });
+    }
+    if ntfy.events.is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&NTFY_EVENTS),
+            message: "must list at least one event".to_string(),
+            hint: "remove the [ntfy] table to disable push notifications".to_string(),
+        });
+    }
+    check_escalate_after(problems, source, &NTFY_ESCALATE_AFTER, ntfy.escalate_after);
+}
+
+fn check_notification(
+    problems: &mut Vec<Problem>,
+    source: &Source,
+    trigger: Trigger,
+    template: &NotificationTemplate,
+) {
+    for (key, text) in [("title", &template.title), ("body", &template.body)] {
+        let Some(text) = text else {
+            continue;
+        };
+        if text.trim().is_empty() {
+            problems.push(Problem {
+                setting: source.setting(&NOTIFICATIONS),
+                message: format!("the {} of {} is blank", key, trigger.name()),
+                hint: format!("leave the {} out to keep the built-in one", key),
+            });
+        }
+        if let Some(unknown) = template::unknown_placeholder(text, PLACEHOLDERS) {
+            problems.push(Problem {
+                setting: source.setting(&NOTIFICATIONS),
+                message: format!(
+                    "the {} of {} has an unknown placeholder {{{{{}}}}}",
+                    key,
+                    trigger.name(),
+                    unknown
+                ),
+                hint: format!(
+                    "use {}",
+                    PLACEHOLDERS
+                        .iter()
+                        .map(|name| format!("{{{{{}}}}}", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            });
+        }
+    }
+}
+
+fn check_escalate_after(
+    problems: &mut Vec<Problem>,
+    source: &Source,
+    key: &Key,
+    value: Option<u64>,
+) {
+    if let Some(seconds) = value
+        && !(MIN_ESCALATE_AFTER..=MAX_ESCALATE_AFTER).contains(&seconds)
+    {
+        problems.push(Problem {
+            setting: source.setting(key),
+            message: format!(
+                "must be from {} to {} seconds, got {}",
+                MIN_ESCALATE_AFTER, MAX_ESCALATE_AFTER, seconds
+            ),
+            hint: "leave it out to send the notifications right away".to_string(),
+        });
+    }
+}
+
+fn check_focus(problems: &mut Vec<Problem>, source: &Source, focus: &FocusConfig) {
+    if focus.processes.is_empty() && focus.sites.is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&FOCUS),
+            message: "lists neither processes nor sites".to_string(),
+            hint: "remove the [focus] table to disable the focus mode".to_string(),
+        });
+    }
+    let host_chars = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+    for site in &focus.sites {
+        if site.is_empty() || !site.chars().all(host_chars) {
+            problems.push(Problem {
+                setting: source.setting(&FOCUS_SITES),
+                message: format!("must list host names, got `{}`", site),
+                hint: "write sites without scheme nor path, e.g. reddit.com".to_string(),
+            });
+        }
+    }
+}
+
+fn check_dim(problems: &mut Vec<Problem>, source: &Source, dim: &DimConfig) {
+    if !(1..=100).contains(&dim.level) {
+        problems.push(Problem {
+            setting: source.setting(&DIM_LEVEL),
+            message: format!("must be between 1 and 100 percent, got {}", dim.level),
+            hint: "remove the [dim] table to disable dimming".to_string(),
+        });
+    }
+}
+
+fn check_lights(problems: &mut Vec<Problem>, source: &Source, lights: &LightsConfig) {
+    if !lights.url.starts_with("https://") && !lights.url.starts_with("http://") {
+        problems.push(Problem {
+            setting: source.setting(&LIGHTS_URL),
+            message: format!("must be an http(s) URL, got `{}`", lights.url),
+            hint: match lights.backend {
+                LightsBackend::Hue => "give the address of the bridge, e.g. http://192.168.1.10",
+                LightsBackend::HomeAssistant => "e.g. http://homeassistant.local:8123",
+            }
+            .to_string(),
+        });
+    }
+    // A token left out is in the keychain.
+    if !lights.token.is_empty() && lights.token.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&LIGHTS_TOKEN),
+            message: "is blank".to_string(),
+            hint: match lights.backend {
+                LightsBackend::Hue => "press the link button of the bridge and create a user",
+                LightsBackend::HomeAssistant => "create a long-lived access token in your profile",
+            }
+            .to_string(),
+        });
+    }
+    for (key, scene) in [
+        (&LIGHTS_FOCUS, &lights.focus),
+        (&LIGHTS_RELAX, &lights.relax),
+    ] {
+        let entity = lights.backend != LightsBackend::HomeAssistant || scene.starts_with("scene.");
+        if scene.is_empty() || !entity {
+            problems.push(Problem {
+                setting: source.setting(key),
+                message: format!("is not a scene, got `{}`", scene),
+                hint: match lights.backend {
+                    LightsBackend::Hue => "give the id of a scene of the bridge",
+                    LightsBackend::HomeAssistant => "give a scene entity, e.g. scene.focus",
+                }
+                .to_string(),
+            });
+        }
+    }
+    if lights.retries > MAX_LIGHTS_RETRIES {
+        problems.push(Problem {
+            setting: source.setting(&LIGHTS_RETRIES),
+            message: format!(
+                "cannot exceed {}, got {}",
+                MAX_LIGHTS_RETRIES, lights.retries
+            ),
+            hint: "a light that does not answer is retried at the next session anyway".to_string(),
+        });
+    }
+}
+
+fn check_calendar(problems: &mut Vec<Problem>, source: &Source, calendar: &CalendarConfig) {
+    if !calendar.url.starts_with("https://") && !calendar.url.starts_with("http://") {
+        problems.push(Problem {
+            setting: source.setting(&CALENDAR_URL),
+            message: format!("must be an http(s) URL, got `{}`", calendar.url),
+            hint: "give the address of the calendar collection, e.g. \
+                   https://cloud.example.com/remote.php/dav/calendars/me/personal/"
+                .to_string(),
+        });
+    }
+    if calendar.username.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&CALENDAR_USERNAME),
+            message: "is blank".to_string(),
+            hint: "give the user name of the CalDAV account".to_string(),
+        });
+    }
+    check_focus_blocks(
+        problems,
+        source,
+        (&CALENDAR_TITLE, &calendar.title),
+        (&CALENDAR_EXTEND_WITHIN, calendar.extend_within),
+    );
+}
+
+fn check_gcal(problems: &mut Vec<Problem>, source: &Source, gcal: &GcalConfig) {
+    if !gcal.client_id.ends_with(".apps.googleusercontent.com") {
+        problems.push(Problem {
+            setting: source.setting(&GCAL_CLIENT_ID),
+            message: format!("is not an OAuth client id, got `{}`", gcal.client_id),
+            hint: "create an OAuth client of the \"TVs and Limited Input devices\" type in the \
+                   Google Cloud console"
+                .to_string(),
+        });
+    }
+    if gcal.client_secret.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&GCAL_CLIENT_SECRET),
+            message: "is blank".to_string(),
+            hint: "copy the secret given with the client id".to_string(),
+        });
+    }
+    if gcal.calendar.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&GCAL_CALENDAR),
+            message: "is blank".to_string(),
+            hint: "leave it out to use the primary calendar of the account".to_string(),
+        });
+    }
+    check_focus_blocks(
+        problems,
+        source,
+        (&GCAL_TITLE, &gcal.title),
+        (&GCAL_EXTEND_WITHIN, gcal.extend_within),
+    );
+}
+
+/// Checks the title and the `extend_within` of the focus blocks of a calendar.
+fn check_focus_blocks(
+    problems: &mut Vec<Problem>,
+    source: &Source,
+    (title_key, title): (&Key, &str),
+    (extend_within_key, extend_within): (&Key, u64),
+) {
+    if title.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(title_key),
+            message: "is blank".to_string(),
+            hint: "leave it out to call the events Focus".to_string(),
+        });
+    }
+    if extend_within > 120 {
+        problems.push(Problem {
+            setting: source.setting(extend_within_key),
+            message: format!("must be from 0 to 120 minutes, got {}", extend_within),
+            hint: "use 0 to write an event per work session".to_string(),
+        });
+    }
+}
+
+fn check_leaderboard(
+    problems: &mut Vec<Problem>,
+    source: &Source,
+    leaderboard: &LeaderboardConfig,
+) {
+    let name = leaderboard.name.trim();
+    if name.chars().count() > MAX_NAME_LEN
+        || name.chars().any(char::is_control)
+        || !name.chars().any(|c| c.is_ascii_alphanumeric())
+    {
+        problems.push(Problem {
+            setting: source.setting(&LEADERBOARD_NAME),
+            message: format!(
+                "must be a name of at most {} characters with a letter or digit, got `{}`",
+                MAX_NAME_LEN, leaderboard.name
+            ),
+            hint: "it is shown to the other participants, a first name or a nickname will do"
+                .to_string(),
+        });
+    }
+}
+
+fn check_schedule(problems: &mut Vec<Problem>, source: &Source, schedule: &ScheduleConfig) {
+    if !schedule.script.is_file() {
+        problems.push(Problem {
+            setting: source.setting(&SCHEDULE_SCRIPT),
+            message: format!("is not a file, got `{}`", schedule.script.display()),
+            hint: "give the absolute path to a Lua script defining next_session(state)".to_string(),
+        });
+    }
+}
+
+fn check_vibrations(problems: &mut Vec<Problem>, source: &Source, vibrations: &VibrationsConfig) {
+    let Some(command) = &vibrations.command else {
+        return;
+    };
+    if command.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&VIBRATIONS),
+            message: "the command is blank".to_string(),
+            hint: "leave the command out to make the phone vibrate under Termux".to_string(),
+        });
+    } else if let Some(unknown) = template::unknown_placeholder(command, &haptics::PLACEHOLDERS) {
+        problems.push(Problem {
+            setting: source.setting(&VIBRATIONS),
+            message: format!("the command has an unknown placeholder {{{{{}}}}}", unknown),
+            hint: "use {{ms}}, the length of the pulse in milliseconds".to_string(),
+        });
+    }
+}
+
+fn check_sounds(problems: &mut Vec<Problem>, source: &Source, sounds: &SoundsConfig) {
+    for cue in Cue::ALL {
+        if let Sound::File(path) = sounds.sound(cue)
+            && !path.is_file()
+        {
+            problems.push(Problem {
+                setting: source.setting(&SOUNDS),
+                message: format!("{} is not a file, got `{}`", cue.name(), path.display()),
+                hint: "use none, beep, chime, bell or the absolute path to an audio file"
+                    .to_string(),
+            });
+        }
+    }
+    if sounds.fade_in > MAX_FADE_IN {
+        problems.push(Problem {
+            setting: source.setting(&SOUNDS_FADE_IN),
+            message: format!("must be from 0 to {}, got {}", MAX_FADE_IN, sounds.fade_in),
+            hint: "use 0 to start the sounds at full volume".to_string(),
+        });
+    }
+    if sounds.repeat != 0 && !(MIN_REPEAT..=MAX_REPEAT).contains(&sounds.repeat) {
+        problems.push(Problem {
+            setting: source.setting(&SOUNDS_REPEAT),
+            message: format!(
+                "must be from {} to {} seconds, got {}",
+                MIN_REPEAT, MAX_REPEAT, sounds.repeat
+            ),
+            hint: "use 0 to play the end of the breaks once".to_string(),
+        });
+    }
+}
+
+fn check_auto_finish(problems: &mut Vec<Problem>, source: &Source, auto_finish: &AutoFinishConfig) {
+    if !(30..=3600).contains(&auto_finish.idle) {
+        problems.push(Problem {
+            setting: source.setting(&AUTO_FINISH_IDLE),
+            message: format!("must be from 30 to 3600 seconds, got {}", auto_finish.idle),
+            hint: "e.g. idle = 120 to finish after two minutes away".to_string(),
+        });
+    }
+    if !(1..=60).contains(&auto_finish.within) {
+        problems.push(Problem {
+            setting: source.setting(&AUTO_FINISH_WITHIN),
+            message: format!("must be from 1 to 60 minutes, got {}", auto_finish.within),
+            hint: "e.g. within = 5 for the last five minutes of the work sessions".to_string(),
+        });
+    }
+}
+
+fn check_digest(problems: &mut Vec<Problem>, source: &Source, digest: &DigestConfig) {
+    let email = digest.email.trim();
+    if email.is_empty() || !email.contains('@') || email.contains(char::is_whitespace) {
+        problems.push(Problem {
+            setting: source.setting(&DIGEST_EMAIL),
+            message: format!("must be an email address, got {:?}", digest.email),
+            hint: "e.g. email = \"me@example.com\"".to_string(),
+        });
+    }
+}
+
+fn check_notes(problems: &mut Vec<Problem>, source: &Source, notes: &NotesConfig) {
+    if notes.path.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&NOTES_PATH),
+            message: "is blank".to_string(),
+            hint: "e.g. path = \"~/Obsidian/Daily/{{date}}.md\"".to_string(),
+        });
+    }
+    for (key, template, known) in [
+        (&NOTES_PATH, &notes.path, PATH_PLACEHOLDERS),
+        (&NOTES_LINE, &notes.line, LINE_PLACEHOLDERS),
+    ] {
+        if let Some(unknown) = template::unknown_placeholder(template, known) {
+            problems.push(Problem {
+                setting: source.setting(key),
+                message: format!("has an unknown placeholder {{{{{}}}}}", unknown),
+                hint: format!(
+                    "use {}",
+                    known
+                        .iter()
+                        .map(|name| format!("{{{{{}}}}}", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            });
+        }
+    }
+    if notes.line.contains('\n') {
+        problems.push(Problem {
+            setting: source.setting(&NOTES_LINE),
+            message: "spans several lines".to_string(),
+            hint: "a session is logged on a single line".to_string(),
+        });
+    }
+    if StrftimeItems::new(&notes.date_format).any(|item| item == Item::Error)
+        || notes.date_format.contains('/')
+    {
+        problems.push(Problem {
+            setting: source.setting(&NOTES_DATE_FORMAT),
+            message: format!(
+                "is not a date format of a file name, got {:?}",
+                notes.date_format
+            ),
+            hint: "e.g. date_format = \"%Y-%m-%d\", the format of Obsidian".to_string(),
+        });
+    }
+    if let Some(heading) = &notes.heading
+        && (heading.trim().is_empty() || heading.contains('\n'))
+    {
+        problems.push(Problem {
+            setting: source.setting(&NOTES_HEADING),
+            message: "must be a single line".to_string(),
+            hint: "e.g. heading = \"## Pomodoros\", or leave it out to log at the end of the note"
+                .to_string(),
+        });
+    }
+}
+
+fn check_git(problems: &mut Vec<Problem>, source: &Source, git: &GitConfig) {
+    if git.repos.is_empty() || git.repos.iter().any(|repo| repo.trim().is_empty()) {
+        problems.push(Problem {
+            setting: source.setting(&GIT_REPOS),
+            message: "must list the paths of repositories".to_string(),
+            hint: "e.g. repos = [\"~/code/app\"]".to_string(),
+        });
+    }
+    if git
+        .author
+        .as_ref()
+        .is_some_and(|author| author.trim().is_empty())
+    {
+        problems.push(Problem {
+            setting: source.setting(&GIT_AUTHOR),
+            message: "is blank".to_string(),
+            hint: "leave it out to read the commits of the user.email of each repository"
+                .to_string(),
+        });
+    }
+}
+
+fn check_compliance(problems: &mut Vec<Problem>, source: &Source, compliance: &ComplianceConfig) {
+    if !(1..=100).contains(&compliance.nag_below) {
+        problems.push(Problem {
+            setting: source.setting(&COMPLIANCE_NAG_BELOW),
+            message: format!(
+                "must be from 1 to 100 percent, got {}",
+                compliance.nag_below
+            ),
+            hint: "e.g. nag_below = 80 to nag when fewer than 4 breaks out of 5 are taken"
+                .to_string(),
+        });
+    }
+}
+
+fn check_eyes(problems: &mut Vec<Problem>, source: &Source, eyes: &EyesConfig) {
+    if !(5..=60).contains(&eyes.every) {
+        problems.push(Problem {
+            setting: source.setting(&EYES_EVERY),
+            message: format!("must be from 5 to 60 minutes, got {}", eyes.every),
+            hint: "e.g. every = 20 for the 20-20-20 rule".to_string(),
+        });
+    }
+    if !(5..=120).contains(&eyes.look_away) {
+        problems.push(Problem {
+            setting: source.setting(&EYES_LOOK_AWAY),
+            message: format!("must be from 5 to 120 seconds, got {}", eyes.look_away),
+            hint: "e.g. look_away = 20 for the 20-20-20 rule".to_string(),
+        });
+    }
+}
+
+fn check_keys(problems: &mut Vec<Problem>, source: &Source, keys: &KeysConfig) {
+    for key in keys.bindings.keys() {
+        if parse_key(key).is_none() {
+            problems.push(Problem {
+                setting: source.setting(&KEYS_BINDINGS),
+                message: format!("unknown key {:?}", key),
+                hint: "name a key by its character, or Space, Enter, Esc, Tab, Backspace or F1 to \
+                       F12"
+                .to_string(),
+            });
+        }
+    }
+    if keys.debounce > 5000 {
+        problems.push(Problem {
+            setting: source.setting(&KEYS_DEBOUNCE),
+            message: format!("must be from 0 to 5000 milliseconds, got {}", keys.debounce),
+            hint: "e.g. debounce = 600, a little more than the delay before a held key repeats"
+                .to_string(),
+        });
+    }
+}
+
+fn check_reminder(problems: &mut Vec<Problem>, source: &Source, reminder: &ReminderConfig) {
+    if reminder.text.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&REMINDERS),
+            message: "the text of a reminder is empty".to_string(),
+            hint: "e.g. text = \"Stand up and stretch\"".to_string(),
+        });
+    }
+    if !(1..=20).contains(&reminder.every) {
+        problems.push(Problem {
+            setting: source.setting(&REMINDERS),
+            message: format!("must be from 1 to 20 breaks, got {}", reminder.every),
+            hint: "e.g. every = 2 for every other break".to_string(),
+        });
+    }
+}
+
+fn check_session(
+    problems: &mut Vec<Problem>,
+    source: &Source,
+    name: &str,
+    session: &SessionConfig,
+) {
+    if matches!(
+        Step::from(name.to_string()),
+        Step::Work | Step::Short | Step::Long
+    ) {
+        problems.push(Problem {
+            setting: source.setting(&SESSIONS),
+            message: format!("cannot redefine the built-in session {:?}", name),
+            hint: "give the table another name, e.g. [sessions.review]".to_string(),
+        });
+    }
+    if session.label.trim().is_empty() {
+        problems.push(Problem {
+            setting: source.setting(&SESSIONS),
+            message: format!("the label of session {:?} is empty", name),
+            hint: "e.g. label = \"Review\"".to_string(),
+        });
+    }
+    if !(1..=MAX_DURATION_MINUTES).contains(&session.minutes) {
+        problems.push(Problem {
+            setting: source.setting(&SESSIONS),
+            message: format!(
+                "session {:?} must last from 1 to {} minutes, got {}",
+                name, MAX_DURATION_MINUTES, session.minutes
+            ),
+            hint: "e.g. minutes = 15".to_string(),
+        });
+    }
+}
+
+fn check_stages(problems: &mut Vec<Problem>, source: &Source, stages: &BreakStages) {
+    for (name, stages) in [
+        ("short_break", &stages.short_break),
+        ("long_break", &stages.long_break),
+    ] {
+        if stages.len() > MAX_STAGES {
+            problems.push(Problem {
+                setting: source.setting(&STAGES),
+                message: format!(
+                    "{} cannot have more than {} stages, got {}",
+                    name,
+                    MAX_STAGES,
+                    stages.len()
+                ),
+                hint: "merge the shortest stages".to_string(),
+            });
+        }
+        for stage in stages {
+            if stage.label.trim().is_empty() {
+                problems.push(Problem {
+                    setting: source.setting(&STAGES),
+                    message: format!("the label of a stage of {} is empty", name),
+                    hint: "e.g. { label = \"Walk\", minutes = 3 }".to_string(),
+                });
+            }
+            if !(1..=MAX_DURATION_MINUTES).contains(&stage.minutes) {
+                problems.push(Problem {
+                    setting: source.setting(&STAGES),
+                    message: format!(
+                        "stage {:?} of {} must last from 1 to {} minutes, got {}",
+                        stage.label, name, MAX_DURATION_MINUTES, stage.minutes
+                    ),
+                    hint: "e.g. { label = \"Walk\", minutes = 3 }".to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Checks that the custom sessions of `sequence` are declared, and that one session at least is
+/// work.
+fn check_sequence_sessions(
+    problems: &mut Vec<Problem>,
+    sequence: &[Step],
+    sessions: &BTreeMap<String, SessionConfig>,
+) {
+    for step in sequence {
+        if let Step::Custom(name) = step
+            && !sessions.contains_key(name)
+        {
+            problems.push(Problem {
+                setting: "sequence".to_string(),
+                message: format!("names the undeclared session {:?}", name),
+                hint: format!("declare it in a [sessions.{}] table", name),
+            });
+        }
+    }
+    let counts_as_work = |step: &Step| match step {
+        Step::Work => true,
+        Step::Custom(name) => sessions
+            .get(name)
+            .is_some_and(|session| session.counts_as == SessionKind::Work),
+        Step::Short | Step::Long => false,
+    };
+    if !sequence.iter().any(counts_as_work) {
+        problems.push(Problem {
+            setting: "sequence".to_string(),
+            message: "must hold at least a work session".to_string(),
+            hint: "add \"work\" or a session counting as work".to_string(),
+        });
+    }
+}
+
+fn check_quiet(problems: &mut Vec<Problem>, source: &Source, quiet: &QuietConfig) {
+    match (quiet.start, quiet.end) {
+        (Some(start), Some(end)) if start == end => problems.push(Problem {
+            setting: source.setting(&QUIET_END),
+            message: format!("must differ from the start, got {} for both", end),
+            hint: "e.g. start = \"22:00\" and end = \"08:00\" for quiet nights".to_string(),
+        }),
+        (Some(_), None) | (None, Some(_)) => problems.push(Problem {
+            setting: source.setting(&QUIET_END),
+            message: "quiet hours need both a start and an end".to_string(),
+            hint: "e.g. start = \"22:00\" and end = \"08:00\" for quiet nights".to_string(),
+        }),
+        (None, None) if quiet.apps.is_empty() && !quiet.fullscreen => problems.push(Problem {
+            setting: source.setting(&QUIET),
+            message: "sets neither quiet hours, apps nor fullscreen".to_string(),
+            hint: "remove the [quiet] table to never keep quiet".to_string(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The settings and the messages of the problems of `layer`, from `source`.
+    fn problems(layer: &Layer, source: Source) -> Vec<(String, String)> {
+        match validate_layer(layer, &source) {
+            Ok(()) => Vec::new(),
+            Err(ConfigError::Invalid(problems)) => problems
+                .into_iter()
+                .map(|problem| (problem.setting, problem.message))
+                .collect(),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    fn problem(setting: &str, message: &str) -> (String, String) {
+        (setting.to_string(), message.to_string())
+    }
+
+    #[test]
+    fn rejects_the_durations_out_of_range() {
+        let file = Source::File(PathBuf::from("/home/me/.config/rustodoro/config.toml"));
+        let layer = Layer {
+            work: Some(0),
+            ..Layer::default()
+        };
+        assert_eq!(
+            problems(&layer, file),
+            [problem(
+                "`work` in /home/me/.config/rustodoro/config.toml",
+                "must be at least 1 minute"
+            )]
+        );
+        let layer = Layer {
+            short_break: Some(1441),
+            ..Layer::default()
+        };
+        assert_eq!(
+            problems(&layer, Source::Flag),
+            [problem(
+                "`--short-break`",
+                "cannot exceed 24 hours (1440 minutes), got 1441"
+            )]
+        );
+        let layer = Layer {
+            work: Some(1440),
+            ..Layer::default()
+        };
+        assert!(problems(&layer, Source::Flag).is_empty());
+    }
+
+    #[test]
+    fn rejects_the_cycles_out_of_range() {
+        let cycles = |cycles| Layer {
+            cycles: Some(cycles),
+            ..Layer::default()
+        };
+        assert_eq!(
+            problems(&cycles(0), Source::Flag),
+            [problem("`--cycles`", "must be at least 1")]
+        );
+        assert_eq!(
+            problems(&cycles(101), Source::File(PathBuf::from("config.toml"))),
+            [problem(
+                "`cycles` in config.toml",
+                "cannot exceed 100, got 101"
+            )]
+        );
+        assert!(problems(&cycles(100), Source::Env).is_empty());
+    }
+
+    #[test]
+    fn rejects_a_long_break_shorter_than_the_short_one() {
+        let layer = Layer {
+            short_break: Some(10),
+            long_break: Some(5),
+            ..Layer::default()
+        };
+        assert_eq!(
+            problems(&layer, Source::Env),
+            [problem(
+                "`RUSTODORO_LONG_BREAK`",
+                "(5 min) is shorter than the short break (10 min)"
+            )]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fails_with_the_editor() {
+        let path = Path::new("config.toml");
+        assert!(run_editor("true", path).is_ok());
+        assert!(matches!(
+            run_editor("false", path),
+            Err(ConfigError::EditorFailed { .. })
+        ));
+        assert!(matches!(
+            run_editor("rustodoro-missing-editor", path),
+            Err(ConfigError::Editor { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_the_invalid_environment_values() {
+        let mut problems = Vec::new();
+        assert_eq!(parse_env_number(&mut problems, &CYCLES, " 4 "), Some(4));
+        assert_eq!(parse_env_number(&mut problems, &CYCLES, "four"), None);
+        let problems: Vec<(String, String)> = problems
+            .into_iter()
+            .map(|problem| (problem.setting, problem.message))
+            .collect();
+        assert_eq!(
+            problems,
+            [problem(
+                "`RUSTODORO_CYCLES`",
+                "must be a whole number, got `four`"
+            )]
+        );
+    }
+}
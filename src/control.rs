@@ -0,0 +1,362 @@
+// filepath: src/control.rs
+//! Module serving the control socket of a Pomodoro timer application, letting other processes
+//! query the running session and send commands with the `rustodoro-client` protocol.
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    iter,
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
+
+use rustodoro_client::{
+    Client,
+    protocol::{Action, PROTOCOL_VERSION, Request, Response, Snapshot},
+};
+use tokio::sync::mpsc::WeakUnboundedSender;
+
+use crate::{
+    config::Cli,
+    events::EventBus,
+    remote::{self, Tracker},
+    settings::SharedSettings,
+    today::SharedToday,
+    types::Command,
+};
+
+/// The socket the timer listens on, removed when dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    /// Listens on `path` until dropped, for the timer called `name`.
+    pub fn start(
+        path: PathBuf,
+        name: Option<String>,
+        events: &EventBus,
+        commands: WeakUnboundedSender<Command>,
+        settings: SharedSettings,
+        today: SharedToday,
+    ) -> io::Result<Self> {
+        let listener = bind(&path)?;
+        let tracker = remote::follow(events, settings, today);
+        let name: Arc<Option<String>> = Arc::new(name);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tracker = Arc::clone(&tracker);
+                let commands = commands.clone();
+                let name = Arc::clone(&name);
+                thread::spawn(move || serve(stream, &name, &tracker, &commands));
+            }
+        });
+        Ok(ControlSocket { path })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Binds `path`, taking over the socket left by a timer that did not exit cleanly.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another timer is listening",
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    // Commands are only accepted from the same user.
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Answers the requests of a connection, one per line.
+fn serve(
+    stream: UnixStream,
+    name: &Option<String>,
+    tracker: &Mutex<Tracker>,
+    commands: &WeakUnboundedSender<Command>,
+) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let response = match parse(&line) {
+            Ok(request) if request.command == Action::Watch => {
+                watch(&mut writer, name, tracker);
+                return;
+            }
+            Ok(request) => respond(request, name, tracker, commands),
+            Err(error) => Response::error(error),
+        };
+        if write(&mut writer, &response).is_err() {
+            return;
+        }
+    }
+}
+
+fn write(writer: &mut UnixStream, response: &Response) -> io::Result<()> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+/// Sends a snapshot of the timer, then another after each event, until either side stops.
+fn watch(writer: &mut UnixStream, name: &Option<String>, tracker: &Mutex<Tracker>) {
+    let (first, updates) = {
+        let mut tracker = tracker.lock().unwrap();
+        (tracker.snapshot(Instant::now()), tracker.watch())
+    };
+    for snapshot in iter::once(first).chain(updates) {
+        let response = Response::status(Snapshot {
+            name: name.clone(),
+            ..snapshot
+        });
+        if write(writer, &response).is_err() {
+            return;
+        }
+    }
+}
+
+/// The request on `line`, or the error to send back.
+fn parse(line: &str) -> Result<Request, String> {
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return Err(format!("invalid request: {}", e)),
+    };
+    // The version is checked first, a request of another version may not parse.
+    match value.get("version").and_then(serde_json::Value::as_u64) {
+        Some(version) if version == u64::from(PROTOCOL_VERSION) => {}
+        Some(version) => {
+            return Err(format!(
+                "unsupported protocol version {}, this timer speaks version {}",
+                version, PROTOCOL_VERSION
+            ));
+        }
+        None => return Err("missing protocol version".to_string()),
+    }
+    serde_json::from_value(value).map_err(|e| format!("invalid request: {}", e))
+}
+
+fn respond(
+    request: Request,
+    name: &Option<String>,
+    tracker: &Mutex<Tracker>,
+    commands: &WeakUnboundedSender<Command>,
+) -> Response {
+    let Some(command) = remote::command(request.command) else {
+        return Response::status(Snapshot {
+            name: name.clone(),
+            ..remote::snapshot(tracker)
+        });
+    };
+    if remote::send(commands, command) {
+        Response::accepted()
+    } else {
+        Response::error("the timer is stopping")
+    }
+}
+
+/// The socket of the timer addressed by `--socket`, else by `--name`.
+pub fn socket_path(cli: &Cli) -> PathBuf {
+    cli.socket
+        .clone()
+        .unwrap_or_else(|| rustodoro_client::socket_path(cli.name.as_deref()))
+}
+
+/// Runs the `status` subcommand, asking the timer addressed by `--socket` or `--name` what it is
+/// doing, and returns the process exit code.
+pub fn run_status(cli: &Cli) -> i32 {
+    match Client::new(socket_path(cli)).status() {
+        Ok(status) => {
+            if let Some(name) = &status.name {
+                print!("[{}] ", name);
+            }
+            println!("{}", remote::describe(&status));
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use rustodoro_client::{ClientError, Session, SessionKind};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{SessionType, events::Event, settings::Settings, today::Today};
+
+    fn socket_path(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join(name)
+    }
+
+    fn started() -> Event {
+        Event::SessionStarted {
+            session: SessionType::short_break(),
+            cycle: 2,
+            duration: Duration::from_secs(300),
+        }
+    }
+
+    /// Waits for the events to reach the socket, which follows them on its own thread.
+    fn wait_for_session(client: &Client) -> Session {
+        for _ in 0..100 {
+            if let Some(session) = client.status().unwrap().session {
+                return session;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the session never started");
+    }
+
+    #[test]
+    fn clients_query_and_drive_the_timer() {
+        let events = EventBus::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let path = socket_path("drive.sock");
+        let socket = ControlSocket::start(
+            path.clone(),
+            Some("writing".to_string()),
+            &events,
+            tx.downgrade(),
+            Settings::default().into_shared(),
+            Today::empty().into_shared(),
+        )
+        .unwrap();
+        let client = Client::new(&path);
+
+        let status = client.status().unwrap();
+        assert_eq!(
+            (status.name.as_deref(), status.session),
+            (Some("writing"), None)
+        );
+        events.emit(started());
+        let session = wait_for_session(&client);
+        assert_eq!(session.kind, SessionKind::ShortBreak);
+        assert_eq!(session.remaining_secs, 300);
+
+        client.skip().unwrap();
+        client.toggle().unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Command::Skip)));
+        assert!(matches!(rx.try_recv(), Ok(Command::PauseResume)));
+
+        // Quitting drops the last strong sender.
+        drop(tx);
+        assert!(matches!(client.pause(), Err(ClientError::Rejected(_))));
+        drop(socket);
+        assert!(!path.exists());
+        assert!(matches!(client.status(), Err(ClientError::Connect { .. })));
+    }
+
+    #[test]
+    fn watchers_follow_the_sessions() {
+        let events = EventBus::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let path = socket_path("watch.sock");
+        let _socket = ControlSocket::start(
+            path.clone(),
+            None,
+            &events,
+            tx.downgrade(),
+            Settings::default().into_shared(),
+            Today::empty().into_shared(),
+        )
+        .unwrap();
+
+        let mut watch = Client::new(&path).watch().unwrap();
+        assert_eq!(watch.next().unwrap().unwrap().session, None);
+        events.emit(started());
+        let session = watch.next().unwrap().unwrap().session.unwrap();
+        assert_eq!(session.kind, SessionKind::ShortBreak);
+        events.emit(Event::Paused {
+            elapsed: Duration::from_secs(60),
+        });
+        assert!(watch.next().unwrap().unwrap().session.unwrap().paused);
+        // The timer stopping closes the connection.
+        drop(events);
+        assert!(watch.next().is_none());
+    }
+
+    #[test]
+    fn rejects_other_protocol_versions() {
+        let events = EventBus::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let path = socket_path("version.sock");
+        let _socket = ControlSocket::start(
+            path.clone(),
+            None,
+            &events,
+            tx.downgrade(),
+            Settings::default().into_shared(),
+            Today::empty().into_shared(),
+        )
+        .unwrap();
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"{\"version\":2,\"command\":\"teleport\"}\n")
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        let response: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(response.version, PROTOCOL_VERSION);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("unsupported protocol version 2, this timer speaks version 1")
+        );
+    }
+
+    #[test]
+    fn a_single_timer_listens_on_a_socket() {
+        let events = EventBus::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let path = socket_path("single.sock");
+        let start = || {
+            ControlSocket::start(
+                path.clone(),
+                None,
+                &events,
+                tx.downgrade(),
+                Settings::default().into_shared(),
+                Today::empty().into_shared(),
+            )
+        };
+        let first = start().unwrap();
+        let error = start().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+
+        // A timer that did not exit cleanly leaves a socket file nobody listens on.
+        drop(first);
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        assert!(start().is_ok());
+    }
+}
@@ -0,0 +1,206 @@
+// filepath: src/credentials.rs
+//! Module keeping the tokens of the integrations of a Pomodoro timer application out of its
+//! configuration file: `rustodoro auth set telegram` stores the token of the bot in the keychain of
+//! the system, and a `[telegram]` table without a token then gets it from there when the timer
+//! starts. A token written in the configuration file still comes first.
+use std::io::{self, BufRead, IsTerminal};
+
+use crate::{
+    config::{AuthAction, EffectiveConfig},
+    keychain::{self, Keychain, KeychainError},
+    prompt,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialError {
+    #[error("no token for [{0}], add it to the configuration file or run `rustodoro auth set {0}`")]
+    Missing(Service),
+    #[error("no token was entered")]
+    Empty,
+    #[error("cannot read the token: {0}")]
+    Terminal(io::Error),
+    #[error(transparent)]
+    Keychain(#[from] KeychainError),
+}
+
+/// An integration holding a token.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Service {
+    Telegram,
+    Ntfy,
+    Lights,
+    Calendar,
+    /// The refresh token of `rustodoro auth google`.
+    Google,
+}
+
+impl Service {
+    /// Account of the token in the keychain, also the table of the integration.
+    fn account(&self) -> &'static str {
+        match self {
+            Service::Telegram => "telegram",
+            Service::Ntfy => "ntfy",
+            Service::Lights => "lights",
+            Service::Calendar => "calendar",
+            Service::Google => "google",
+        }
+    }
+}
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.account())
+    }
+}
+
+/// The token of `service`, the one of the configuration file when `configured` is not empty.
+fn token(
+    keychain: &dyn Keychain,
+    service: Service,
+    configured: &str,
+) -> Result<String, CredentialError> {
+    if !configured.is_empty() {
+        return Ok(configured.to_string());
+    }
+    keychain
+        .get(service.account())?
+        .ok_or(CredentialError::Missing(service))
+}
+
+/// Fills the tokens left out of the tables of `config` from `keychain`, for the integrations
+/// rustodoro was built with. The token of a public ntfy topic is optional: it is only looked for,
+/// and a keychain which cannot be reached means there is none.
+pub fn fill(config: &mut EffectiveConfig, keychain: &dyn Keychain) -> Result<(), CredentialError> {
+    if cfg!(feature = "telegram")
+        && let Some(telegram) = &mut config.telegram.value
+    {
+        telegram.token = token(keychain, Service::Telegram, &telegram.token)?;
+    }
+    if cfg!(feature = "lights")
+        && let Some(lights) = &mut config.lights.value
+    {
+        lights.token = token(keychain, Service::Lights, &lights.token)?;
+    }
+    if cfg!(feature = "caldav")
+        && let Some(calendar) = &mut config.calendar.value
+    {
+        calendar.password = token(keychain, Service::Calendar, &calendar.password)?;
+    }
+    if cfg!(feature = "ntfy")
+        && let Some(ntfy) = &mut config.ntfy.value
+        && ntfy.token.is_none()
+    {
+        ntfy.token = match keychain.get(Service::Ntfy.account()) {
+            Ok(token) => token,
+            Err(KeychainError::Unavailable { .. }) => None,
+            #[cfg(not(unix))]
+            Err(KeychainError::Unsupported) => None,
+            Err(e) => return Err(e.into()),
+        };
+    }
+    Ok(())
+}
+
+/// The token to store, typed in the terminal without echoing it or read from the standard input.
+fn read_token(service: Service) -> Result<String, CredentialError> {
+    let token = if io::stdin().is_terminal() {
+        prompt::hidden(&format!("Token of {}: ", service))
+            .map_err(CredentialError::Terminal)?
+            .unwrap_or_default()
+    } else {
+        let mut line = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(CredentialError::Terminal)?;
+        line
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(CredentialError::Empty);
+    }
+    Ok(token.to_string())
+}
+
+fn run_action(action: &AuthAction, keychain: &dyn Keychain) -> Result<(), CredentialError> {
+    match action {
+        AuthAction::Set { service } => {
+            keychain.set(service.account(), &read_token(*service)?)?;
+            println!(
+                "Token of {} stored in the keychain, leave it out of the [{}] table.",
+                service, service
+            );
+        }
+        AuthAction::Remove { service } => {
+            if keychain.remove(service.account())? {
+                println!("Token of {} removed from the keychain.", service);
+            } else {
+                println!("No token of {} in the keychain.", service);
+            }
+        }
+        #[cfg(feature = "gcal")]
+        AuthAction::Google => unreachable!("`auth google` is run by the gcal module"),
+    }
+    Ok(())
+}
+
+/// Runs the `auth` subcommand and returns the process exit code.
+pub fn run_command(action: &AuthAction) -> i32 {
+    match run_action(action, &keychain::Os) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::BTreeMap};
+
+    use super::*;
+
+    /// A keychain kept in memory.
+    #[derive(Default)]
+    struct Memory(RefCell<BTreeMap<String, String>>);
+
+    impl Keychain for Memory {
+        fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+            Ok(self.0.borrow().get(account).cloned())
+        }
+
+        fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+            self.0
+                .borrow_mut()
+                .insert(account.to_string(), secret.to_string());
+            Ok(())
+        }
+
+        fn remove(&self, account: &str) -> Result<bool, KeychainError> {
+            Ok(self.0.borrow_mut().remove(account).is_some())
+        }
+    }
+
+    #[test]
+    fn the_configured_token_comes_first() {
+        let keychain = Memory::default();
+        assert!(matches!(
+            token(&keychain, Service::Telegram, ""),
+            Err(CredentialError::Missing(Service::Telegram))
+        ));
+        keychain.set("telegram", "123456:kept").unwrap();
+        assert_eq!(
+            token(&keychain, Service::Telegram, "").unwrap(),
+            "123456:kept"
+        );
+        assert_eq!(
+            token(&keychain, Service::Telegram, "123456:written").unwrap(),
+            "123456:written"
+        );
+        assert!(matches!(
+            token(&keychain, Service::Lights, ""),
+            Err(CredentialError::Missing(Service::Lights))
+        ));
+    }
+}
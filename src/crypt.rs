@@ -0,0 +1,411 @@
+// filepath: src/crypt.rs
+//! Module encrypting the session history of a Pomodoro timer application at rest, for the users
+//! logging sensitive task names. Each record is sealed on its line with ChaCha20-Poly1305, so that
+//! a session is still appended without rewriting the file, while the header line stays in the
+//! clear and tells where the key comes from: a passphrase, stretched with PBKDF2 and the salt of
+//! the header, or a random key kept in the keychain of the system. The header also holds a known
+//! line sealed with the key, so that a wrong key is refused even before the first record.
+//!
+//! Sealing and opening the records needs the `encrypt` feature.
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "encrypt")]
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keychain::{self, Keychain, KeychainError},
+    prompt,
+};
+
+/// Environment variable giving the passphrase of the history, asked for in the terminal otherwise.
+pub const PASSPHRASE_ENV: &str = "RUSTODORO_HISTORY_PASSPHRASE";
+/// Start of the sealed lines, the others being in the clear.
+const SEALED: &str = "sealed:";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+#[cfg(feature = "encrypt")]
+const NONCE_LEN: usize = 12;
+/// Rounds of PBKDF2-HMAC-SHA256 stretching the new passphrases.
+const ITERATIONS: u32 = 600_000;
+/// The line sealed in the header, which the key must open.
+const KEY_CHECK: &str = "rustodoro";
+
+/// The ciphers of the encryptions met so far, so that the passphrase is asked for once per run.
+static CIPHERS: Mutex<Vec<(Encryption, Arc<Cipher>)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptError {
+    #[cfg(not(feature = "encrypt"))]
+    #[error("rustodoro was built without the encrypt feature, the history cannot be encrypted")]
+    Disabled,
+    #[error("the history is encrypted with a passphrase, set {PASSPHRASE_ENV} to read it")]
+    NoPassphrase,
+    #[error("the passphrase is empty")]
+    EmptyPassphrase,
+    #[error("the passphrases do not match")]
+    Mismatch,
+    #[error("no passphrase was entered")]
+    Cancelled,
+    #[error("cannot read the passphrase: {0}")]
+    Terminal(io::Error),
+    #[error("cannot decrypt the history, is the passphrase right?")]
+    Undecryptable,
+    #[error("the key of the history is not in the keychain, under the {0} account")]
+    MissingKey(String),
+    #[error("the key of the history in the keychain is invalid")]
+    InvalidKey,
+    #[cfg(feature = "encrypt")]
+    #[error("the system cannot generate random data")]
+    Random,
+    #[error(transparent)]
+    Keychain(#[from] KeychainError),
+}
+
+/// Where the key of an encrypted history comes from, as its header records it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "key", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Encryption {
+    /// Derived from a passphrase with the hexadecimal `salt`.
+    Passphrase {
+        salt: String,
+        iterations: u32,
+        /// The key check, missing from the headers written before it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        check: Option<String>,
+    },
+    /// Kept in the keychain under `account`.
+    Keychain {
+        account: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        check: Option<String>,
+    },
+}
+
+/// Seals and opens the lines of a history.
+pub struct Cipher {
+    #[cfg(feature = "encrypt")]
+    key: LessSafeKey,
+    #[cfg(not(feature = "encrypt"))]
+    never: std::convert::Infallible,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(feature = "encrypt")]
+fn random<const N: usize>() -> Result<[u8; N], CryptError> {
+    let mut bytes = [0; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| CryptError::Random)?;
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "encrypt"))]
+fn random<const N: usize>() -> Result<[u8; N], CryptError> {
+    Err(CryptError::Disabled)
+}
+
+/// Whether `line` of a history is sealed.
+pub fn is_sealed(line: &str) -> bool {
+    line.starts_with(SEALED)
+}
+
+impl Cipher {
+    #[cfg(feature = "encrypt")]
+    fn new(key: &[u8; KEY_LEN]) -> Result<Self, CryptError> {
+        let key =
+            UnboundKey::new(&aead::CHACHA20_POLY1305, key).expect("keys have the right length");
+        Ok(Cipher {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    #[cfg(not(feature = "encrypt"))]
+    fn new(_key: &[u8; KEY_LEN]) -> Result<Self, CryptError> {
+        Err(CryptError::Disabled)
+    }
+
+    #[cfg(feature = "encrypt")]
+    fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Self, CryptError> {
+        let mut key = [0; KEY_LEN];
+        let iterations = std::num::NonZeroU32::new(iterations).ok_or(CryptError::Undecryptable)?;
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Cipher::new(&key)
+    }
+
+    #[cfg(not(feature = "encrypt"))]
+    fn derive(_passphrase: &str, _salt: &[u8], _iterations: u32) -> Result<Self, CryptError> {
+        Err(CryptError::Disabled)
+    }
+
+    /// Seals `line`, behind a nonce of its own.
+    #[cfg(feature = "encrypt")]
+    pub fn seal(&self, line: &str) -> Result<String, CryptError> {
+        let nonce = random::<NONCE_LEN>()?;
+        let mut sealed = line.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| CryptError::Random)?;
+        Ok(format!("{}{}{}", SEALED, to_hex(&nonce), to_hex(&sealed)))
+    }
+
+    #[cfg(not(feature = "encrypt"))]
+    pub fn seal(&self, _line: &str) -> Result<String, CryptError> {
+        match self.never {}
+    }
+
+    /// Opens the `sealed` line, failing when it was sealed with another key or altered.
+    #[cfg(feature = "encrypt")]
+    pub fn open(&self, sealed: &str) -> Result<String, CryptError> {
+        let bytes = sealed
+            .strip_prefix(SEALED)
+            .and_then(from_hex)
+            .filter(|bytes| bytes.len() >= NONCE_LEN)
+            .ok_or(CryptError::Undecryptable)?;
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| CryptError::Undecryptable)?;
+        let mut line = sealed.to_vec();
+        let line = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut line)
+            .map_err(|_| CryptError::Undecryptable)?;
+        String::from_utf8(line.to_vec()).map_err(|_| CryptError::Undecryptable)
+    }
+
+    #[cfg(not(feature = "encrypt"))]
+    pub fn open(&self, _sealed: &str) -> Result<String, CryptError> {
+        match self.never {}
+    }
+}
+
+/// Asks for a passphrase in the terminal, without echoing it.
+fn ask(prompt: &str) -> Result<String, CryptError> {
+    if !io::stdin().is_terminal() {
+        return Err(CryptError::NoPassphrase);
+    }
+    prompt::hidden(prompt)
+        .map_err(CryptError::Terminal)?
+        .ok_or(CryptError::Cancelled)
+}
+
+/// The passphrase of the history, from the environment or the terminal.
+fn passphrase() -> Result<String, CryptError> {
+    match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => ask("Passphrase of the history: "),
+    }
+}
+
+/// Remembers `cipher` as the one of `encryption` for the rest of the run.
+fn remember(encryption: &Encryption, cipher: Cipher) -> Arc<Cipher> {
+    let cipher = Arc::new(cipher);
+    CIPHERS
+        .lock()
+        .unwrap()
+        .push((encryption.clone(), Arc::clone(&cipher)));
+    cipher
+}
+
+impl Encryption {
+    /// A new encryption with a passphrase, from the environment or asked for twice.
+    pub fn with_passphrase() -> Result<Self, CryptError> {
+        let salt = random::<SALT_LEN>()?;
+        let passphrase = match env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => passphrase,
+            Err(_) => {
+                let passphrase = ask("New passphrase of the history: ")?;
+                if ask("Again: ")? != passphrase {
+                    return Err(CryptError::Mismatch);
+                }
+                passphrase
+            }
+        };
+        if passphrase.is_empty() {
+            return Err(CryptError::EmptyPassphrase);
+        }
+        Self::passphrase(&passphrase, salt, ITERATIONS)
+    }
+
+    fn passphrase(
+        passphrase: &str,
+        salt: [u8; SALT_LEN],
+        iterations: u32,
+    ) -> Result<Self, CryptError> {
+        let cipher = Cipher::derive(passphrase, &salt, iterations)?;
+        let encryption = Encryption::Passphrase {
+            salt: to_hex(&salt),
+            iterations,
+            check: Some(cipher.seal(KEY_CHECK)?),
+        };
+        remember(&encryption, cipher);
+        Ok(encryption)
+    }
+
+    /// A new encryption with a random key, stored in `keychain`.
+    pub fn in_keychain(keychain: &dyn Keychain) -> Result<Self, CryptError> {
+        let key = random::<KEY_LEN>()?;
+        let cipher = Cipher::new(&key)?;
+        let account = format!("history-{}", to_hex(&random::<4>()?));
+        keychain.set(&account, &to_hex(&key))?;
+        let encryption = Encryption::Keychain {
+            account,
+            check: Some(cipher.seal(KEY_CHECK)?),
+        };
+        remember(&encryption, cipher);
+        Ok(encryption)
+    }
+
+    /// The cipher of the history, asking for the passphrase the first time.
+    pub fn cipher(&self) -> Result<Arc<Cipher>, CryptError> {
+        if let Some((_, cipher)) = CIPHERS
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(encryption, _)| encryption == self)
+        {
+            return Ok(Arc::clone(cipher));
+        }
+        let cipher = self.unlock(passphrase)?;
+        Ok(remember(self, cipher))
+    }
+
+    /// The cipher of the history, the key derived from the passphrase `passphrase` gives or read
+    /// from the keychain, refused when it does not open the key check of the header.
+    fn unlock(
+        &self,
+        passphrase: impl FnOnce() -> Result<String, CryptError>,
+    ) -> Result<Cipher, CryptError> {
+        let (cipher, check, wrong) = match self {
+            Encryption::Passphrase {
+                salt,
+                iterations,
+                check,
+            } => {
+                let salt = from_hex(salt).ok_or(CryptError::Undecryptable)?;
+                let cipher = Cipher::derive(&passphrase()?, &salt, *iterations)?;
+                (cipher, check, CryptError::Undecryptable)
+            }
+            Encryption::Keychain { account, check } => {
+                let key = keychain::Os
+                    .get(account)?
+                    .ok_or_else(|| CryptError::MissingKey(account.clone()))?;
+                let key = from_hex(&key)
+                    .and_then(|key| <[u8; KEY_LEN]>::try_from(key).ok())
+                    .ok_or(CryptError::InvalidKey)?;
+                (Cipher::new(&key)?, check, CryptError::InvalidKey)
+            }
+        };
+        match check {
+            Some(check) if cipher.open(check).ok().as_deref() != Some(KEY_CHECK) => Err(wrong),
+            _ => Ok(cipher),
+        }
+    }
+
+    /// Forgets the key of the history, removing it from `keychain` when it is kept there.
+    pub fn forget(&self, keychain: &dyn Keychain) -> Result<(), CryptError> {
+        if let Encryption::Keychain { account, .. } = self {
+            keychain.remove(account)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    #[cfg(feature = "encrypt")]
+    use crate::history::History;
+
+    /// An encryption with a passphrase, remembered without asking for it, for the tests of the
+    /// encrypted histories.
+    #[cfg(feature = "encrypt")]
+    pub fn encryption(passphrase: &str) -> Encryption {
+        Encryption::passphrase(passphrase, random().unwrap(), 1000).unwrap()
+    }
+
+    #[test]
+    fn reads_the_hexadecimal() {
+        assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+        assert_eq!(from_hex("000fff"), Some(vec![0, 15, 255]));
+        assert_eq!(from_hex("0f0"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn opens_what_it_sealed_only() {
+        let cipher = encryption("correct horse").cipher().unwrap();
+        let line = r#"{"kind":"work","task":"Acme merger memo"}"#;
+        let sealed = cipher.seal(line).unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("Acme"));
+        // Each line has a nonce of its own.
+        assert_ne!(cipher.seal(line).unwrap(), sealed);
+        assert_eq!(cipher.open(&sealed).unwrap(), line);
+
+        let other = encryption("battery staple").cipher().unwrap();
+        assert!(matches!(
+            other.open(&sealed),
+            Err(CryptError::Undecryptable)
+        ));
+        let mut altered = sealed.clone();
+        let last = altered.pop().unwrap();
+        altered.push(if last == '0' { '1' } else { '0' });
+        assert!(cipher.open(&altered).is_err());
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn refuses_a_wrong_passphrase_on_an_empty_history() {
+        let history = History::new(
+            env::temp_dir()
+                .join(format!("rustodoro-test-{}", std::process::id()))
+                .join("crypt-empty.jsonl"),
+        );
+        history
+            .rewrite_with(&[], Some(&encryption("correct horse")))
+            .unwrap();
+        let encryption = history.encryption().unwrap().unwrap();
+        assert!(matches!(
+            encryption.unlock(|| Ok("battery staple".to_string())),
+            Err(CryptError::Undecryptable)
+        ));
+        assert!(
+            encryption
+                .unlock(|| Ok("correct horse".to_string()))
+                .is_ok()
+        );
+    }
+}
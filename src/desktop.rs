@@ -0,0 +1,93 @@
+// filepath: src/desktop.rs
+//! Module showing the desktop notifications of a Pomodoro timer application through the
+//! notification daemon, or as Windows toasts under WSL and Android ones under Termux. Built
+//! without the desktop feature, e.g. for a server without D-Bus, the notifications fail to show
+//! elsewhere, which the timer reports once as for a missing daemon.
+use std::io;
+
+use crate::platform;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[cfg(feature = "desktop")]
+    #[error(transparent)]
+    Daemon(#[from] notify_rust::error::Error),
+    #[error("cannot show the notification through {platform}: {source}")]
+    Platform {
+        platform: platform::Platform,
+        source: io::Error,
+    },
+    #[cfg(not(feature = "desktop"))]
+    #[error("rustodoro was built without the desktop feature")]
+    Unsupported,
+}
+
+/// How long the notification stays, those of WSL and Termux staying as the system decides.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+pub enum Timeout {
+    Milliseconds(u32),
+}
+
+/// A desktop notification, built as with the notification daemon.
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+pub struct Notification {
+    summary: String,
+    body: String,
+    icon: String,
+    timeout: Option<Timeout>,
+}
+
+impl Notification {
+    pub fn new() -> Self {
+        Notification::default()
+    }
+
+    pub fn summary(&mut self, summary: &str) -> &mut Self {
+        self.summary = summary.to_string();
+        self
+    }
+
+    pub fn body(&mut self, body: &str) -> &mut Self {
+        self.body = body.to_string();
+        self
+    }
+
+    pub fn icon(&mut self, icon: &str) -> &mut Self {
+        self.icon = icon.to_string();
+        self
+    }
+
+    pub fn timeout(&mut self, timeout: Timeout) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn show(&self) -> Result<(), Error> {
+        match platform::current() {
+            platform::Platform::Native => self.show_on_daemon(),
+            platform => platform::notify(&self.summary, &self.body)
+                .map_err(|source| Error::Platform { platform, source }),
+        }
+    }
+
+    #[cfg(feature = "desktop")]
+    fn show_on_daemon(&self) -> Result<(), Error> {
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .summary(&self.summary)
+            .body(&self.body)
+            .icon(&self.icon);
+        if let Some(Timeout::Milliseconds(milliseconds)) = self.timeout {
+            notification.timeout(notify_rust::Timeout::Milliseconds(milliseconds));
+        }
+        notification.show()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "desktop"))]
+    fn show_on_daemon(&self) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+}
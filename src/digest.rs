@@ -0,0 +1,370 @@
+// filepath: src/digest.rs
+//! Module making the digest of a Pomodoro timer application, a plain text summary of the focus
+//! time, the daily goals reached and the top tasks of the last week, or of the day. `rustodoro
+//! digest` prints it, writes it to a file or sends it by email through sendmail.
+//!
+//! With a `[digest]` table, the timer sends the digest of the week on its day, from its time on,
+//! or on the next run when it was not running then. The time it was last sent is kept next to the
+//! history, so that it is sent once a week.
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::Receiver,
+};
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+
+use crate::{
+    aggregate,
+    config::{self, Cli, ConfigError, DigestConfig},
+    events::Event,
+    history::{HISTORY_ENV, History, HistoryError, SessionRecord, history_path},
+    summary::{Stats, format_duration},
+};
+
+/// Program sending the digests by email.
+const SENDMAIL: &str = "sendmail";
+/// Most tasks listed.
+const TOP_TASKS: usize = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DigestError {
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+    #[error("cannot write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+    #[error("cannot run {SENDMAIL}, is a mail transfer agent installed? {0}")]
+    Unavailable(io::Error),
+    #[error("{SENDMAIL} failed: {0}")]
+    Failed(String),
+}
+
+/// Renders the digest of the days from `first` to `last` included, `goal` being the daily goal.
+pub fn render(records: &[SessionRecord], first: NaiveDate, last: NaiveDate, goal: u64) -> String {
+    let per_day = aggregate::pomodoros_per_day(records, first, last);
+    let per_task = aggregate::focus_per_task(records, first);
+    let period: Vec<&SessionRecord> = records
+        .iter()
+        .filter(|record| (first..=last).contains(&record.started_at.date_naive()))
+        .collect();
+    let stats = Stats::from_records(period.iter().copied());
+    let active_days = per_day.iter().filter(|(_, count)| *count > 0).count();
+    let goal_days = per_day.iter().filter(|(_, count)| *count >= goal).count();
+
+    let mut rendered = if first == last {
+        format!("Pomodoro digest of {}\n\n", first.format("%A, %B %-d, %Y"))
+    } else {
+        format!(
+            "Pomodoro digest, {} to {}\n\n",
+            first.format("%B %-d"),
+            last.format("%B %-d, %Y")
+        )
+    };
+    let (pomodoros, reached) = if first == last {
+        (
+            stats.pomodoros.to_string(),
+            if goal_days == 1 {
+                "reached"
+            } else {
+                "not reached"
+            }
+            .to_string(),
+        )
+    } else {
+        (
+            format!(
+                "{}, on {} of {} days",
+                stats.pomodoros,
+                active_days,
+                per_day.len()
+            ),
+            format!("reached on {} of {} days", goal_days, per_day.len()),
+        )
+    };
+    let lines = [
+        ("Pomodoros", pomodoros),
+        ("Focused", format_duration(stats.focused)),
+        ("Daily goal", format!("{} pomodoros, {}", goal, reached)),
+        (
+            "Focus score",
+            stats
+                .focus()
+                .map_or_else(|| "-".to_string(), |focus| format!("{}/100", focus)),
+        ),
+        (
+            "Breaks taken",
+            stats.break_compliance().map_or_else(
+                || "-".to_string(),
+                |compliance| format!("{:.0}%", compliance * 100.0),
+            ),
+        ),
+    ];
+    for (label, value) in lines {
+        rendered.push_str(&format!("{:<14}{}\n", format!("{}:", label), value));
+    }
+    rendered.push_str("\nTop tasks:\n");
+    if per_task.is_empty() {
+        rendered.push_str("  none, name them with --task\n");
+    }
+    let width = per_task
+        .iter()
+        .take(TOP_TASKS)
+        .map(|(task, _)| task.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (rank, (task, focused)) in per_task.iter().take(TOP_TASKS).enumerate() {
+        rendered.push_str(&format!(
+            "  {}. {:<width$}  {}\n",
+            rank + 1,
+            task,
+            format_duration(*focused),
+            width = width
+        ));
+    }
+    rendered
+}
+
+fn subject(first: NaiveDate, last: NaiveDate) -> String {
+    if first == last {
+        format!("Pomodoro digest of {}", first.format("%B %-d"))
+    } else {
+        format!(
+            "Pomodoro digest, {} to {}",
+            first.format("%B %-d"),
+            last.format("%B %-d")
+        )
+    }
+}
+
+/// Sends `body` to `email` through sendmail.
+fn send(email: &str, subject: &str, body: &str) -> Result<(), DigestError> {
+    let message = format!(
+        "To: {}\nSubject: {}\nMIME-Version: 1.0\nContent-Type: text/plain; charset=utf-8\n\n{}",
+        email, subject, body
+    );
+    let mut child = Command::new(SENDMAIL)
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(DigestError::Unavailable)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(message.as_bytes())
+            .map_err(DigestError::Unavailable)?;
+    }
+    let output = child.wait_with_output().map_err(DigestError::Unavailable)?;
+    if !output.status.success() {
+        return Err(DigestError::Failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The first day of the week ending on `last`.
+fn week_of(last: NaiveDate) -> NaiveDate {
+    last - Days::new(6)
+}
+
+/// Sends the digest of the week on the day of a `[digest]` table while the timer runs.
+pub struct Mailer {
+    config: DigestConfig,
+    history: History,
+    /// File holding when the digest was last sent.
+    sent: PathBuf,
+    goal: u64,
+}
+
+impl Mailer {
+    /// A mailer for the week of `history`, `goal` being the daily goal.
+    pub fn new(config: DigestConfig, history_path: &Path, goal: u64) -> Self {
+        Mailer {
+            config,
+            history: History::new(history_path.to_path_buf()),
+            sent: history_path.with_extension("digest"),
+            goal,
+        }
+    }
+
+    /// The last time the digest was due on, at `now`.
+    fn due_at(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let back =
+            (7 + now.weekday().num_days_from_monday() - self.config.day.num_days_from_monday()) % 7;
+        let day = now.date_naive() - Days::new(u64::from(back));
+        let due = day
+            .and_time(self.config.at.0)
+            .and_local_timezone(Local)
+            .earliest()?;
+        if due <= now {
+            Some(due)
+        } else {
+            due.checked_sub_days(Days::new(7))
+        }
+    }
+
+    fn last_sent(&self) -> Option<DateTime<Local>> {
+        let content = fs::read_to_string(&self.sent).ok()?;
+        DateTime::parse_from_rfc3339(content.trim())
+            .ok()
+            .map(|sent| sent.with_timezone(&Local))
+    }
+
+    /// Whether the digest is due at `now`, having been last sent at `last_sent`.
+    fn is_due(&self, now: DateTime<Local>, last_sent: Option<DateTime<Local>>) -> bool {
+        match (self.due_at(now), last_sent) {
+            (Some(due), Some(sent)) => sent < due,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Sends the digest of the week if it is due at `now`.
+    fn send_if_due(&self, now: DateTime<Local>) -> Result<(), DigestError> {
+        if !self.is_due(now, self.last_sent()) {
+            return Ok(());
+        }
+        let records = self.history.sessions()?;
+        let last = now.date_naive();
+        let first = week_of(last);
+        send(
+            &self.config.email,
+            &subject(first, last),
+            &render(&records, first, last, self.goal),
+        )?;
+        fs::write(&self.sent, now.to_rfc3339()).map_err(|source| DigestError::Write {
+            path: self.sent.clone(),
+            source,
+        })
+    }
+
+    /// Checks whether the digest is due when starting and after each session until the event
+    /// bus is dropped, and returns the first failure to send it, if any. A digest which could
+    /// not be sent is tried again after the next session.
+    pub fn run(self, events: Receiver<Event>) -> Option<DigestError> {
+        let mut failure = self.send_if_due(Local::now()).err();
+        for event in events {
+            if let Event::SessionEnded { .. } = event
+                && let Err(e) = self.send_if_due(Local::now())
+            {
+                failure.get_or_insert(e);
+            }
+        }
+        failure
+    }
+}
+
+fn run_action(
+    cli: &Cli,
+    daily: bool,
+    email: Option<&str>,
+    output: Option<&Path>,
+) -> Result<Option<String>, DigestError> {
+    let goal = config::resolve(cli)?.daily_goal.value;
+    let path =
+        history_path(cli.history.as_deref(), cli.name.as_deref()).ok_or(DigestError::NoHistory)?;
+    let records = History::new(path).sessions()?;
+    let last = Local::now().date_naive();
+    let first = if daily { last } else { week_of(last) };
+    let digest = render(&records, first, last, goal);
+    match (email, output) {
+        (Some(email), _) => {
+            send(email, &subject(first, last), &digest)?;
+            println!("Digest sent to {}.", email);
+            Ok(None)
+        }
+        (None, Some(output)) => {
+            fs::write(output, digest).map_err(|source| DigestError::Write {
+                path: output.to_path_buf(),
+                source,
+            })?;
+            println!("Digest written to {}.", output.display());
+            Ok(None)
+        }
+        (None, None) => Ok(Some(digest)),
+    }
+}
+
+/// Runs the `digest` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, daily: bool, email: Option<&str>, output: Option<&Path>) -> i32 {
+    match run_action(cli, daily, email, output) {
+        Ok(digest) => {
+            print!("{}", digest.unwrap_or_default());
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Weekday};
+
+    use super::*;
+    use crate::{events::SessionOutcome, history::SessionKind, pace::TimeOfDay};
+
+    fn work(day: u32, task: &str) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap(),
+            kind: SessionKind::Work,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: Some(100),
+            task: Some(task.to_string()),
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn digests_the_week() {
+        let mut records = vec![work(1, "Old")];
+        records.extend((0..3).map(|_| work(12, "Writing")));
+        records.push(work(14, "Review"));
+        let last = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let digest = render(&records, week_of(last), last, 3);
+        assert!(digest.starts_with("Pomodoro digest, October 8 to October 14, 2026\n\n"));
+        assert!(digest.contains("Pomodoros:    4, on 2 of 7 days\n"));
+        assert!(digest.contains("Daily goal:   3 pomodoros, reached on 1 of 7 days\n"));
+        assert!(digest.contains("  1. Writing  1h 15m\n  2. Review   25m\n"));
+        assert!(!digest.contains("Old"));
+    }
+
+    #[test]
+    fn is_due_once_a_week() {
+        let config = DigestConfig {
+            email: "me@example.com".to_string(),
+            day: Weekday::Fri,
+            at: TimeOfDay::new(16, 0),
+        };
+        let mailer = Mailer::new(config, Path::new("history.jsonl"), 8);
+        // Friday, October 16, 2026.
+        let friday = |hour| Local.with_ymd_and_hms(2026, 10, 16, hour, 0, 0).unwrap();
+        assert!(!mailer.is_due(friday(15), Some(friday(16) - Days::new(7))));
+        assert!(mailer.is_due(friday(16), Some(friday(16) - Days::new(7))));
+        assert!(!mailer.is_due(friday(17), Some(friday(16))));
+        // Missed on Friday, sent on Monday.
+        let monday = Local.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap();
+        assert!(mailer.is_due(monday, Some(friday(15))));
+        assert!(!mailer.is_due(monday, Some(friday(18))));
+    }
+}
@@ -0,0 +1,385 @@
+// filepath: src/dim.rs
+//! Module dimming the screen during the breaks of a Pomodoro timer application, to make staying
+//! at the desk less inviting, and restoring its brightness when the work resumes.
+//!
+//! The brightness is set through the first backend working on the system: the sysfs backlight of
+//! Linux, `brightnessctl`, or `xrandr` on X11, which dims external monitors too. A system none of
+//! them works on is left alone.
+use std::{env, fs, io, path::PathBuf, process, sync::mpsc::Receiver};
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    SessionType,
+    config::{DimBackend, DimConfig},
+    events::Event,
+};
+
+/// Where Linux lists the backlights.
+const BACKLIGHTS: &str = "/sys/class/backlight";
+
+#[derive(Debug, thiserror::Error)]
+#[error("cannot {action} the screen brightness with {backend}: {source}")]
+pub struct DimError {
+    pub backend: &'static str,
+    pub action: &'static str,
+    pub source: io::Error,
+}
+
+/// A way to set the brightness of the screen, from 0 to 1.
+pub trait Screen: Send {
+    fn name(&self) -> &'static str;
+    fn brightness(&self) -> io::Result<f64>;
+    fn set_brightness(&self, brightness: f64) -> io::Result<()>;
+}
+
+/// The first backend of `backend` working on this system, if any.
+pub fn detect(backend: DimBackend) -> Option<Box<dyn Screen>> {
+    let backlight = || Backlight::find().map(|screen| Box::new(screen) as Box<dyn Screen>);
+    let brightnessctl = || {
+        Brightnessctl
+            .brightness()
+            .is_ok()
+            .then(|| Box::new(Brightnessctl) as Box<dyn Screen>)
+    };
+    // Under Wayland, xrandr only reaches the outputs of XWayland and changes nothing.
+    let xrandr = || {
+        (env::var_os("DISPLAY").is_some()
+            && env::var_os("WAYLAND_DISPLAY").is_none()
+            && Xrandr.brightness().is_ok())
+        .then(|| Box::new(Xrandr) as Box<dyn Screen>)
+    };
+    match backend {
+        DimBackend::Auto => backlight().or_else(brightnessctl).or_else(xrandr),
+        DimBackend::Backlight => backlight(),
+        DimBackend::Brightnessctl => brightnessctl(),
+        DimBackend::Xrandr => xrandr(),
+    }
+}
+
+/// The raw value of `brightness` on a device going up to `max`, never turning the backlight off.
+fn raw(brightness: f64, max: u64) -> u64 {
+    ((brightness * max as f64).round() as u64).clamp(1, max.max(1))
+}
+
+/// The backlight of a laptop screen, written directly in sysfs. This needs write access to it,
+/// usually given to the `video` group by a udev rule.
+pub struct Backlight {
+    dir: PathBuf,
+}
+
+impl Backlight {
+    /// The first backlight whose brightness can be written.
+    fn find() -> Option<Self> {
+        fs::read_dir(BACKLIGHTS)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|dir| {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .open(dir.join("brightness"))
+                    .is_ok()
+            })
+            .map(|dir| Backlight { dir })
+    }
+
+    fn read(&self, file: &str) -> io::Result<u64> {
+        fs::read_to_string(self.dir.join(file))?
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("unreadable {}", file)))
+    }
+}
+
+impl Screen for Backlight {
+    fn name(&self) -> &'static str {
+        "the backlight"
+    }
+
+    fn brightness(&self) -> io::Result<f64> {
+        Ok(self.read("brightness")? as f64 / self.read("max_brightness")?.max(1) as f64)
+    }
+
+    fn set_brightness(&self, brightness: f64) -> io::Result<()> {
+        let max = self.read("max_brightness")?;
+        fs::write(
+            self.dir.join("brightness"),
+            raw(brightness, max).to_string(),
+        )
+    }
+}
+
+/// The `brightnessctl` command, going through logind when the backlight is not writable.
+pub struct Brightnessctl;
+
+impl Brightnessctl {
+    fn info(&self) -> io::Result<(u64, u64)> {
+        parse_brightnessctl(&output("brightnessctl", &["-m", "info"])?).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected brightnessctl output",
+            )
+        })
+    }
+}
+
+impl Screen for Brightnessctl {
+    fn name(&self) -> &'static str {
+        "brightnessctl"
+    }
+
+    fn brightness(&self) -> io::Result<f64> {
+        let (current, max) = self.info()?;
+        Ok(current as f64 / max.max(1) as f64)
+    }
+
+    fn set_brightness(&self, brightness: f64) -> io::Result<()> {
+        let (_, max) = self.info()?;
+        output(
+            "brightnessctl",
+            &["-q", "set", &raw(brightness, max).to_string()],
+        )
+        .map(drop)
+    }
+}
+
+/// The software brightness of the connected outputs of X11, shading the picture rather than
+/// lowering the backlight.
+pub struct Xrandr;
+
+impl Screen for Xrandr {
+    fn name(&self) -> &'static str {
+        "xrandr"
+    }
+
+    fn brightness(&self) -> io::Result<f64> {
+        parse_xrandr(&output("xrandr", &["--verbose"])?)
+            .first()
+            .map(|(_, brightness)| *brightness)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no connected output"))
+    }
+
+    fn set_brightness(&self, brightness: f64) -> io::Result<()> {
+        let brightness = format!("{:.2}", brightness.max(0.05));
+        for (name, _) in parse_xrandr(&output("xrandr", &["--verbose"])?) {
+            output("xrandr", &["--output", &name, "--brightness", &brightness])?;
+        }
+        Ok(())
+    }
+}
+
+/// The standard output of `program`, failing with its error output.
+fn output(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = process::Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Reads the current and maximum brightness in `brightnessctl -m info` output, e.g.
+/// `intel_backlight,backlight,19200,80%,24000`.
+fn parse_brightnessctl(output: &str) -> Option<(u64, u64)> {
+    let fields: Vec<&str> = output.lines().next()?.split(',').collect();
+    Some((fields.get(2)?.parse().ok()?, fields.get(4)?.parse().ok()?))
+}
+
+/// Reads the connected outputs and their brightness in `xrandr --verbose` output.
+pub fn parse_xrandr(output: &str) -> Vec<(String, f64)> {
+    let mut outputs = Vec::new();
+    let mut connected = None;
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            let mut words = line.split_whitespace();
+            let name = words.next();
+            connected = name.filter(|_| words.next() == Some("connected"));
+        } else if let Some(value) = line.trim().strip_prefix("Brightness:")
+            && let (Some(name), Ok(brightness)) = (connected.take(), value.trim().parse())
+        {
+            outputs.push((name.to_string(), brightness));
+        }
+    }
+    outputs
+}
+
+pub struct Dimmer {
+    screen: Box<dyn Screen>,
+    /// Fraction of the brightness kept during the breaks.
+    level: f64,
+    /// Brightness before the break, while the screen is dimmed.
+    saved: Option<f64>,
+}
+
+impl Dimmer {
+    pub fn new(config: &DimConfig, screen: Box<dyn Screen>) -> Self {
+        Dimmer {
+            screen,
+            level: config.level as f64 / 100.0,
+            saved: None,
+        }
+    }
+
+    fn error(&self, action: &'static str) -> impl FnOnce(io::Error) -> DimError {
+        let backend = self.screen.name();
+        move |source| DimError {
+            backend,
+            action,
+            source,
+        }
+    }
+
+    fn dim(&mut self) -> Result<(), DimError> {
+        if self.saved.is_some() {
+            return Ok(());
+        }
+        let brightness = self.screen.brightness().map_err(self.error("read"))?;
+        self.screen
+            .set_brightness(brightness * self.level)
+            .map_err(self.error("dim"))?;
+        self.saved = Some(brightness);
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), DimError> {
+        match self.saved.take() {
+            Some(brightness) => self
+                .screen
+                .set_brightness(brightness)
+                .map_err(self.error("restore")),
+            None => Ok(()),
+        }
+    }
+
+    /// Dims the screen at the start of a break and restores it at the start of a work session.
+    pub fn handle(&mut self, event: &Event) -> Result<(), DimError> {
+        match event {
+            Event::SessionStarted {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                ..
+            } => self.restore(),
+            Event::SessionStarted { .. } => self.dim(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Follows the events until the event bus is dropped, restoring the brightness then, and
+    /// returns the first failure.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<DimError> {
+        let mut first_failure = None;
+        for event in events {
+            if let Err(e) = self.handle(&event) {
+                first_failure.get_or_insert(e);
+            }
+        }
+        if let Err(e) = self.restore() {
+            first_failure.get_or_insert(e);
+        }
+        first_failure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex, mpsc},
+        time::Duration,
+    };
+
+    use super::*;
+
+    /// A screen remembering the brightness it is set to.
+    struct Fake(Arc<Mutex<f64>>);
+
+    impl Screen for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn brightness(&self) -> io::Result<f64> {
+            Ok(*self.0.lock().unwrap())
+        }
+
+        fn set_brightness(&self, brightness: f64) -> io::Result<()> {
+            *self.0.lock().unwrap() = brightness;
+            Ok(())
+        }
+    }
+
+    fn started(session: SessionType) -> Event {
+        Event::SessionStarted {
+            session,
+            cycle: 1,
+            duration: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn reads_the_brightness_tools() {
+        assert_eq!(
+            parse_brightnessctl("intel_backlight,backlight,19200,80%,24000\n"),
+            Some((19200, 24000))
+        );
+        assert_eq!(parse_brightnessctl("brightnessctl: command failed"), None);
+
+        let xrandr = "\
+Screen 0: minimum 320 x 200, current 3840 x 1080, maximum 16384 x 16384
+eDP-1 connected primary 1920x1080+0+0 (0x48) normal (normal left inverted right x axis y axis) 309mm x 174mm
+\tIdentifier: 0x42
+\tBrightness: 0.80
+HDMI-1 disconnected (normal left inverted right x axis y axis)
+\tBrightness: 1.0
+DP-1 connected 1920x1080+1920+0 (0x4a) normal (normal left inverted right x axis y axis) 527mm x 296mm
+\tBrightness: 1.00
+";
+        assert_eq!(
+            parse_xrandr(xrandr),
+            [("eDP-1".to_string(), 0.8), ("DP-1".to_string(), 1.0)]
+        );
+        assert_eq!(raw(0.3, 24000), 7200);
+        assert_eq!(raw(0.001, 7), 1);
+    }
+
+    #[test]
+    fn dims_the_breaks_only() {
+        let brightness = Arc::new(Mutex::new(0.8));
+        let config = DimConfig {
+            level: 25,
+            backend: DimBackend::Auto,
+        };
+        let mut dimmer = Dimmer::new(&config, Box::new(Fake(Arc::clone(&brightness))));
+
+        dimmer.handle(&started(SessionType::work())).unwrap();
+        assert_eq!(*brightness.lock().unwrap(), 0.8);
+        dimmer.handle(&started(SessionType::short_break())).unwrap();
+        assert_eq!(*brightness.lock().unwrap(), 0.2);
+        // A dimmed screen is not dimmed further.
+        dimmer.handle(&started(SessionType::long_break())).unwrap();
+        assert_eq!(*brightness.lock().unwrap(), 0.2);
+        dimmer.handle(&started(SessionType::work())).unwrap();
+        assert_eq!(*brightness.lock().unwrap(), 0.8);
+    }
+
+    #[test]
+    fn restores_the_brightness_when_quitting() {
+        let brightness = Arc::new(Mutex::new(1.0));
+        let config = DimConfig {
+            level: 50,
+            backend: DimBackend::Auto,
+        };
+        let dimmer = Dimmer::new(&config, Box::new(Fake(Arc::clone(&brightness))));
+        let (tx, rx) = mpsc::channel();
+        tx.send(started(SessionType::short_break())).unwrap();
+        drop(tx);
+
+        assert!(dimmer.run(rx).is_none());
+        assert_eq!(*brightness.lock().unwrap(), 1.0);
+    }
+}
@@ -0,0 +1,186 @@
+// filepath: src/doctor.rs
+//! Module checking the history file of a Pomodoro timer application for what a crash, a sync
+//! conflict or a hand edit may leave behind, and repairing it: records which cannot be read, blank
+//! lines, duplicated or disordered sessions, and records from an older version.
+use std::{collections::HashSet, fmt, fs};
+
+use crate::history::{self, History, HistoryError, SCHEMA_VERSION, SessionRecord};
+
+/// What is wrong with a history file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Diagnosis {
+    /// Version of the records, when older or newer than the current one.
+    pub version: Option<u32>,
+    /// The lines which cannot be read, with the reason.
+    pub unreadable: Vec<(usize, String)>,
+    pub blank: usize,
+    pub duplicates: usize,
+    /// Sessions starting before the one above them.
+    pub disordered: usize,
+    pub sessions: usize,
+}
+
+impl Diagnosis {
+    pub fn is_healthy(&self) -> bool {
+        self.version.is_none()
+            && self.unreadable.is_empty()
+            && self.blank == 0
+            && self.duplicates == 0
+            && self.disordered == 0
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(version) = self.version {
+            writeln!(
+                f,
+                "  - the records are from version {}, the current one being {}",
+                version, SCHEMA_VERSION
+            )?;
+        }
+        for (line, reason) in &self.unreadable {
+            writeln!(f, "  - line {} cannot be read: {}", line, reason)?;
+        }
+        for (count, what) in [
+            (self.blank, "blank lines"),
+            (self.duplicates, "duplicated sessions"),
+            (self.disordered, "sessions out of order"),
+        ] {
+            if count > 0 {
+                writeln!(f, "  - {} {}", count, what)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the content of a history file, and returns the records it should hold.
+pub fn examine(content: &str) -> (Diagnosis, Vec<SessionRecord>) {
+    let parsed = history::parse(content);
+    let mut diagnosis = Diagnosis {
+        version: (parsed.version != SCHEMA_VERSION).then_some(parsed.version),
+        blank: parsed.blank,
+        ..Diagnosis::default()
+    };
+    let mut seen = HashSet::new();
+    let mut records: Vec<SessionRecord> = Vec::new();
+    for line in parsed.lines {
+        match line.record {
+            Ok(record) => {
+                if !seen.insert(serde_json::to_string(&record).expect("records always serialize")) {
+                    diagnosis.duplicates += 1;
+                    continue;
+                }
+                if records
+                    .last()
+                    .is_some_and(|last| record.started_at < last.started_at)
+                {
+                    diagnosis.disordered += 1;
+                }
+                records.push(record);
+            }
+            Err(e) => diagnosis.unreadable.push((line.number, e.to_string())),
+        }
+    }
+    records.sort_by_key(|record| record.started_at);
+    diagnosis.sessions = records.len();
+    (diagnosis, records)
+}
+
+/// Checks the history and prints what is wrong, repairing it when `repair` is set. The unreadable
+/// lines are then kept aside, in a file next to the history.
+pub fn run(history: &History, repair: bool) -> Result<(), HistoryError> {
+    let content = history.read()?;
+    let (diagnosis, records) = examine(&content);
+    println!(
+        "History {}: {} sessions.",
+        history.path().display(),
+        diagnosis.sessions
+    );
+    if diagnosis.is_healthy() {
+        println!("No problem found.");
+        return Ok(());
+    }
+    print!("{}", diagnosis);
+    if diagnosis
+        .version
+        .is_some_and(|version| version > SCHEMA_VERSION)
+    {
+        println!("Update rustodoro to read this history.");
+        return Ok(());
+    }
+    if !repair {
+        println!("Run `rustodoro history doctor --repair` to fix it.");
+        return Ok(());
+    }
+    if !diagnosis.unreadable.is_empty() {
+        let aside = history.path().with_extension("jsonl.unreadable");
+        let unreadable: String = content
+            .lines()
+            .enumerate()
+            .filter(|(index, _)| {
+                diagnosis
+                    .unreadable
+                    .iter()
+                    .any(|(line, _)| *line == index + 1)
+            })
+            .map(|(_, line)| format!("{}\n", line))
+            .collect();
+        fs::write(&aside, unreadable).map_err(|source| HistoryError::Io {
+            path: aside.clone(),
+            source,
+        })?;
+        println!("The unreadable lines were moved to {}.", aside.display());
+    }
+    history.rewrite(&records)?;
+    println!("History repaired.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORK: &str = r#"{"started_at":"2026-03-14T09:00:00+01:00","kind":"work","planned":1500,"elapsed":1500,"outcome":"completed","pauses":0,"resets":0}"#;
+
+    fn at(hour: u32) -> String {
+        WORK.replace("T09", &format!("T{:02}", hour))
+    }
+
+    #[test]
+    fn finds_what_is_wrong() {
+        let content = format!("{}\n\n{}\n{{\"started_at\": \n{}\n", at(10), at(9), at(9));
+        let (diagnosis, records) = examine(&content);
+        assert_eq!(diagnosis.version, Some(1));
+        assert_eq!(diagnosis.unreadable.len(), 1);
+        assert_eq!(diagnosis.unreadable[0].0, 4);
+        assert_eq!(
+            (diagnosis.blank, diagnosis.duplicates, diagnosis.disordered),
+            (1, 1, 1)
+        );
+        assert_eq!(records.len(), 2);
+        assert!(records[0].started_at < records[1].started_at);
+        assert!(
+            diagnosis
+                .to_string()
+                .contains("  - 1 sessions out of order\n")
+        );
+    }
+
+    #[test]
+    fn a_repaired_history_is_healthy() {
+        let path = std::env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join("doctor.jsonl");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("{}\nnot json\n{}\n", at(10), at(9))).unwrap();
+        let history = History::new(path.clone());
+        run(&history, true).unwrap();
+        assert!(examine(&history.read().unwrap()).0.is_healthy());
+        assert_eq!(
+            fs::read_to_string(path.with_extension("jsonl.unreadable")).unwrap(),
+            "not json\n"
+        );
+    }
+}
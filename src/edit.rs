@@ -0,0 +1,196 @@
+// filepath: src/edit.rs
+//! Module editing the sessions of the history of a Pomodoro timer application after the fact:
+//! `rustodoro history edit <id>` fixes the task of a session, tags it or voids it, a voided session
+//! counting for nothing in the summaries and the reports. Each change is kept with the session,
+//! along with when it was made, and listed when no change is given.
+use chrono::{DateTime, Local};
+
+use crate::{
+    config::EditArgs,
+    history::{Change, Edit, History, HistoryError, SessionRecord},
+    notes,
+};
+
+/// The changes of `args` which change something to `record`.
+fn changes(record: &SessionRecord, args: &EditArgs) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if let Some(task) = args.task.as_deref().map(str::trim)
+        && !task.is_empty()
+        && record.task.as_deref() != Some(task)
+    {
+        changes.push(Change::Task {
+            from: record.task.clone(),
+            to: task.to_string(),
+        });
+    }
+    let mut tags = record.tags.clone();
+    for tag in args.tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|known| known == tag) {
+            tags.push(tag.to_string());
+            changes.push(Change::Tag {
+                tag: tag.to_string(),
+            });
+        }
+    }
+    if args.void && !record.voided {
+        changes.push(Change::Void);
+    }
+    changes
+}
+
+/// Applies `change` to `record`, adding it to the edits of the session as made `at`.
+fn apply(record: &mut SessionRecord, change: Change, at: DateTime<Local>) {
+    match &change {
+        Change::Task { to, .. } => record.task = Some(to.clone()),
+        Change::Tag { tag } => record.tags.push(tag.clone()),
+        Change::Void => record.voided = true,
+    }
+    record.edits.push(Edit { at, change });
+}
+
+/// The edits made to `record`, the first one first.
+fn trail(record: &SessionRecord) -> String {
+    let mut rendered = format!(
+        "Session {}, {} started {}",
+        record.id,
+        notes::kind(record.kind),
+        record.started_at.format("%Y-%m-%d %H:%M")
+    );
+    if record.edits.is_empty() {
+        rendered.push_str(", never edited.\n");
+        return rendered;
+    }
+    rendered.push_str(":\n");
+    for edit in &record.edits {
+        rendered.push_str(&format!(
+            "{}  {}\n",
+            edit.at.format("%Y-%m-%d %H:%M"),
+            edit.change
+        ));
+    }
+    rendered
+}
+
+/// Makes the changes of `args` to their session, `now`, and returns what to tell: the changes
+/// made, or the edits of the session when none is given. None when no session has the ID.
+pub fn run(
+    history: &History,
+    args: &EditArgs,
+    now: DateTime<Local>,
+) -> Result<Option<String>, HistoryError> {
+    let mut made = Vec::new();
+    let record = history.update(&args.id, |record| {
+        made = changes(record, args);
+        for change in made.iter().cloned() {
+            apply(record, change, now);
+        }
+        !made.is_empty()
+    })?;
+    let Some(record) = record else {
+        return Ok(None);
+    };
+    let told = if args.task.is_none() && args.tags.is_empty() && !args.void {
+        trail(&record)
+    } else if made.is_empty() {
+        format!("Session {} left as it was.\n", record.id)
+    } else {
+        let made: Vec<String> = made.iter().map(Change::to_string).collect();
+        format!("Session {} edited: {}.\n", record.id, made.join(", "))
+    };
+    Ok(Some(told))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{events::SessionOutcome, history::SessionKind};
+
+    #[test]
+    fn edits_the_sessions_with_a_trail() {
+        let path = env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join("edit.jsonl");
+        let _ = fs::remove_file(&path);
+        let history = History::new(path);
+        let started_at = Local.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        let record = SessionRecord {
+            id: "mm8xk1c0".to_string(),
+            started_at,
+            kind: SessionKind::Work,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: Some(100),
+            task: Some("Wirte report".to_string()),
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        };
+        history.append(&record).unwrap();
+        let args = |task: Option<&str>, tags: &[&str], void: bool| EditArgs {
+            id: "mm8xk1c0".to_string(),
+            task: task.map(str::to_string),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            void,
+        };
+        let now = Local.with_ymd_and_hms(2026, 3, 4, 10, 12, 0).unwrap();
+        let edit = |args: EditArgs| run(&history, &args, now).unwrap();
+
+        assert_eq!(
+            edit(args(None, &[], false)).unwrap(),
+            "Session mm8xk1c0, work started 2026-03-02 09:00, never edited.\n"
+        );
+        assert_eq!(
+            edit(args(
+                Some("Write report"),
+                &["client", " client", ""],
+                false
+            ))
+            .unwrap(),
+            "Session mm8xk1c0 edited: task \"Wirte report\" → \"Write report\", tagged \"client\".\n"
+        );
+        assert_eq!(
+            edit(args(Some("Write report"), &["client"], false)).unwrap(),
+            "Session mm8xk1c0 left as it was.\n"
+        );
+        assert_eq!(
+            edit(args(None, &[], true)).unwrap(),
+            "Session mm8xk1c0 edited: voided.\n"
+        );
+        assert_eq!(
+            edit(args(None, &[], false)).unwrap(),
+            "Session mm8xk1c0, work started 2026-03-02 09:00:\n\
+             2026-03-04 10:12  task \"Wirte report\" → \"Write report\"\n\
+             2026-03-04 10:12  tagged \"client\"\n\
+             2026-03-04 10:12  voided\n"
+        );
+        let edited = &history.load().unwrap()[0];
+        assert_eq!(edited.task.as_deref(), Some("Write report"));
+        assert_eq!(edited.tags, ["client"]);
+        assert!(edited.voided);
+        // The voided session counts for nothing.
+        assert!(history.sessions().unwrap().is_empty());
+        assert_eq!(
+            run(
+                &history,
+                &EditArgs {
+                    id: "unknown".to_string(),
+                    ..args(None, &[], true)
+                },
+                now
+            )
+            .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// filepath: src/events.rs
+//! Module broadcasting the state changes of a Pomodoro timer application to the parts interested
+//! in them.
+use std::{
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    SessionType,
+    history::{PauseReason, Review},
+    state_machine::Transition,
+};
+
+/// How a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOutcome {
+    /// The countdown reached zero.
+    Completed,
+    /// The user walked away from the end of the work session, which finished without them.
+    FinishedEarly,
+    /// The user skipped the break.
+    Skipped,
+    /// The user quit during the session.
+    Interrupted,
+}
+
+impl SessionOutcome {
+    /// Whether the session counts as done, having completed or finished early.
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self,
+            SessionOutcome::Completed | SessionOutcome::FinishedEarly
+        )
+    }
+}
+
+/// A state change of the timer. Durations are the time counted by the session countdown, pauses
+/// excluded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    SessionStarted {
+        session: SessionType,
+        cycle: u64,
+        duration: Duration,
+    },
+    /// The sessions of the day plan after the running one take `ahead`, up to the daily goal.
+    Planned {
+        ahead: Duration,
+    },
+    Tick {
+        elapsed: Duration,
+        remaining: Duration,
+    },
+    Paused {
+        elapsed: Duration,
+    },
+    /// The running pause was for `reason`.
+    PauseReason {
+        reason: PauseReason,
+    },
+    /// The running work session is meant for `intent`.
+    Intent {
+        intent: String,
+    },
+    /// The work session which just ended went as `review` tells against its intent.
+    Reviewed {
+        review: Review,
+    },
+    Resumed {
+        elapsed: Duration,
+        /// How long the session stayed paused.
+        paused: Duration,
+    },
+    Reset,
+    /// Skip was pressed at the very end of a work session: the upcoming break will be skipped.
+    NextBreakSkipped,
+    /// The user acknowledged the end of the session.
+    Acknowledged,
+    /// The running session was lengthened `by` some time.
+    Extended {
+        by: Duration,
+    },
+    /// The work sessions are labelled with `task` from the running one on.
+    TaskChanged {
+        task: String,
+    },
+    SessionEnded {
+        session: SessionType,
+        elapsed: Duration,
+        outcome: SessionOutcome,
+    },
+    /// The session changed state, which the other events detail.
+    Transition(Transition),
+    /// The terminal was resized to `columns` by `rows` characters.
+    Resized {
+        columns: u16,
+        rows: u16,
+    },
+}
+
+/// Dispatches every emitted event to all the subscribers.
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<Event>>>,
+    timed: Mutex<Vec<Sender<(Instant, Event)>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus {
+            subscribers: Mutex::new(Vec::new()),
+            timed: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a receiver getting all the events emitted from now on.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Returns a receiver getting all the events emitted from now on, with the time on the clock
+    /// of the timer when they were, the paused clock of a simulated run included.
+    pub fn subscribe_timed(&self) -> Receiver<(Instant, Event)> {
+        let (tx, rx) = mpsc::channel();
+        self.timed.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Sends `event` to the subscribers, forgetting those which dropped their receiver.
+    pub fn emit(&self, event: Event) {
+        let mut timed = self.timed.lock().unwrap();
+        if !timed.is_empty() {
+            let now = Instant::now();
+            timed.retain(|tx| tx.send((now, event.clone())).is_ok());
+        }
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
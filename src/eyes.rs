@@ -0,0 +1,164 @@
+// filepath: src/eyes.rs
+//! Module reminding the user of a Pomodoro timer application to rest their eyes during the work
+//! sessions, following the 20-20-20 rule: every 20 minutes, look at something 20 feet away for 20
+//! seconds. The micro-breaks are timed on the work counted by the ticks of the session, pauses
+//! excluded, on a thread of their own: the countdown of the session is left alone.
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    SessionType,
+    config::EyesConfig,
+    desktop::{Notification, Timeout},
+    events::Event,
+    quiet::Quiet,
+    settings::SharedSettings,
+};
+
+/// Time left in a work session under which a micro-break is skipped, the break coming anyway.
+const NEAR_BREAK: Duration = Duration::from_secs(2 * 60);
+
+/// Sends a short notification each time a micro-break is due.
+pub struct EyeBreaks {
+    every: Duration,
+    look_away: Duration,
+    settings: SharedSettings,
+    quiet: Option<Arc<Quiet>>,
+    /// Work time of the session at which the next micro-break is due, none outside work sessions.
+    due: Option<Duration>,
+}
+
+impl EyeBreaks {
+    pub fn new(config: &EyesConfig, settings: SharedSettings) -> Self {
+        EyeBreaks {
+            every: Duration::from_secs(config.every * 60),
+            look_away: Duration::from_secs(config.look_away),
+            settings,
+            quiet: None,
+            due: None,
+        }
+    }
+
+    /// Holds the micro-breaks back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    /// Handles the events until the event bus is dropped.
+    pub fn run(mut self, events: Receiver<Event>) {
+        for event in events {
+            if let Some(message) = self.handle(&event)
+                && !self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+            {
+                // The notification goes away by itself once the micro-break is over. The timer
+                // already reports notification failures.
+                let _ = Notification::new()
+                    .summary("Eye break")
+                    .body(&message)
+                    .icon("dialog-information")
+                    .timeout(Timeout::Milliseconds(self.look_away.as_millis() as u32))
+                    .show();
+            }
+        }
+    }
+
+    /// Returns the micro-break to announce after `event`, if any.
+    pub fn handle(&mut self, event: &Event) -> Option<String> {
+        match event {
+            Event::SessionStarted {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                ..
+            } => self.due = Some(self.every),
+            Event::SessionStarted { .. } | Event::SessionEnded { .. } => self.due = None,
+            // The work time of the session starts over.
+            Event::Reset if self.due.is_some() => self.due = Some(self.every),
+            Event::Tick { elapsed, remaining } => {
+                let due = self.due.as_mut().filter(|due| elapsed >= due)?;
+                // A micro-break missed, e.g. while the ticks are 15 seconds apart in low-power
+                // mode, is not made up for.
+                while *due <= *elapsed {
+                    *due += self.every;
+                }
+                if *remaining < NEAR_BREAK || self.settings.lock().unwrap().no_notify {
+                    return None;
+                }
+                return Some(format!(
+                    "Look at something 20 feet away for {} seconds.",
+                    self.look_away.as_secs()
+                ));
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn work(minutes: u64) -> Event {
+        Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: MINUTE * minutes as u32,
+        }
+    }
+
+    fn tick(elapsed: Duration, duration: Duration) -> Event {
+        Event::Tick {
+            elapsed,
+            remaining: duration - elapsed,
+        }
+    }
+
+    fn eye_breaks() -> EyeBreaks {
+        EyeBreaks::new(&EyesConfig::default(), Settings::default().into_shared())
+    }
+
+    #[test]
+    fn reminds_every_twenty_minutes_of_work() {
+        let mut eyes = eye_breaks();
+        let duration = MINUTE * 50;
+        assert_eq!(eyes.handle(&tick(MINUTE * 20, duration)), None);
+        eyes.handle(&work(50));
+        assert_eq!(eyes.handle(&tick(MINUTE * 19, duration)), None);
+        assert_eq!(
+            eyes.handle(&tick(MINUTE * 20, duration)).unwrap(),
+            "Look at something 20 feet away for 20 seconds."
+        );
+        assert_eq!(eyes.handle(&tick(MINUTE * 21, duration)), None);
+        // The reset starts the work time over.
+        eyes.handle(&Event::Reset);
+        assert_eq!(eyes.handle(&tick(MINUTE * 10, duration)), None);
+        assert!(eyes.handle(&tick(MINUTE * 20, duration)).is_some());
+        assert!(eyes.handle(&tick(MINUTE * 40, duration)).is_some());
+    }
+
+    #[test]
+    fn skips_the_micro_break_near_the_break() {
+        let mut eyes = eye_breaks();
+        let duration = MINUTE * 21;
+        eyes.handle(&work(21));
+        assert_eq!(eyes.handle(&tick(MINUTE * 20, duration)), None);
+        // Nor is there any during the breaks.
+        eyes.handle(&Event::SessionStarted {
+            session: SessionType::long_break(),
+            cycle: 4,
+            duration: MINUTE * 30,
+        });
+        assert_eq!(eyes.handle(&tick(MINUTE * 20, MINUTE * 30)), None);
+    }
+}
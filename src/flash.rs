@@ -0,0 +1,245 @@
+// filepath: src/flash.rs
+//! Module flashing every display at the end of the sessions of a Pomodoro timer application, for
+//! those who work without the sound in their ears.
+//!
+//! The overlay covers each monitor with what comes next for a few seconds: a topmost window of
+//! PowerShell on Windows and under WSL, and of AppKit through `osascript` on macOS. On X11, the
+//! displays blink through `xrandr` instead. A system none of them works on is left alone.
+use std::{env, io, process, sync::mpsc::Receiver, thread, time::Duration};
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    config::{FlashConfig, FlashStyle},
+    dim,
+    events::Event,
+    platform::{self, Platform},
+};
+
+/// Brightness of the displays while they blink, and how long each blink lasts.
+const BLINK_BRIGHTNESS: f64 = 0.15;
+const BLINK: Duration = Duration::from_millis(250);
+const BLINKS: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+#[error("cannot flash the displays with {backend}: {source}")]
+pub struct FlashError {
+    pub backend: &'static str,
+    pub source: io::Error,
+}
+
+/// What covers or blinks the displays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    PowerShell,
+    Osascript,
+    Xrandr,
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::PowerShell => "PowerShell",
+            Backend::Osascript => "osascript",
+            Backend::Xrandr => "xrandr",
+        }
+    }
+}
+
+/// The backend showing `style` on this system, the displays blinking on X11 where no overlay can
+/// be shown.
+pub fn detect(style: FlashStyle) -> Option<Backend> {
+    let overlay = if cfg!(windows) || platform::current() == Platform::Wsl {
+        Some(Backend::PowerShell)
+    } else if cfg!(target_os = "macos") {
+        Some(Backend::Osascript)
+    } else {
+        None
+    };
+    // Under Wayland, xrandr only reaches the outputs of XWayland and changes nothing.
+    let xrandr = || {
+        (env::var_os("DISPLAY").is_some()
+            && env::var_os("WAYLAND_DISPLAY").is_none()
+            && output("xrandr", &["--verbose"]).is_ok())
+        .then_some(Backend::Xrandr)
+    };
+    match style {
+        FlashStyle::Overlay => overlay.or_else(xrandr),
+        FlashStyle::Blink => xrandr(),
+    }
+}
+
+/// The standard output of `program`, failing with its error output.
+fn output(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = process::Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// What the overlay tells once `ended` ended, and its background color.
+fn announcement(ended: SessionKind) -> (&'static str, &'static str) {
+    match ended {
+        SessionKind::Work => ("Time for a break", "#2e7d32"),
+        _ => ("Back to work", "#c62828"),
+    }
+}
+
+/// The PowerShell script covering each screen with `text` on `color` for `duration`.
+fn powershell_script(text: &str, color: &str, duration: Duration) -> String {
+    format!(
+        "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+         $forms = foreach ($screen in [System.Windows.Forms.Screen]::AllScreens) {{ \
+         $form = New-Object System.Windows.Forms.Form; \
+         $form.FormBorderStyle = 'None'; $form.StartPosition = 'Manual'; $form.Bounds = $screen.Bounds; \
+         $form.TopMost = $true; $form.ShowInTaskbar = $false; $form.Opacity = 0.85; \
+         $form.BackColor = [System.Drawing.ColorTranslator]::FromHtml({}); \
+         $label = New-Object System.Windows.Forms.Label; \
+         $label.Dock = 'Fill'; $label.TextAlign = 'MiddleCenter'; $label.ForeColor = 'White'; \
+         $label.Font = New-Object System.Drawing.Font('Segoe UI', 72); $label.Text = {}; \
+         $form.Controls.Add($label); $form.Show(); $form }}; \
+         $end = (Get-Date).AddMilliseconds({}); \
+         while ((Get-Date) -lt $end) {{ [System.Windows.Forms.Application]::DoEvents(); Start-Sleep -Milliseconds 50 }}; \
+         $forms | ForEach-Object {{ $_.Close() }}",
+        platform::quote(color),
+        platform::quote(text),
+        duration.as_millis()
+    )
+}
+
+/// The JavaScript for Automation covering each screen with `text` on `color` for `duration`, in
+/// windows above the others, even fullscreen ones, letting the clicks through.
+fn osascript_script(text: &str, color: &str, duration: Duration) -> String {
+    let channel = |range: std::ops::Range<usize>| {
+        u8::from_str_radix(&color[range], 16).map_or(0.0, |value| f64::from(value) / 255.0)
+    };
+    format!(
+        "ObjC.import('Cocoa'); \
+         $.NSApplication.sharedApplication.setActivationPolicy($.NSApplicationActivationPolicyAccessory); \
+         var screens = $.NSScreen.screens; var windows = []; \
+         for (var i = 0; i < screens.count; i++) {{ \
+         var frame = screens.objectAtIndex(i).frame; \
+         var win = $.NSWindow.alloc.initWithContentRectStyleMaskBackingDefer(frame, $.NSWindowStyleMaskBorderless, $.NSBackingStoreBuffered, false); \
+         win.level = 1000; win.opaque = false; win.ignoresMouseEvents = true; \
+         win.backgroundColor = $.NSColor.colorWithSRGBRedGreenBlueAlpha({:.3}, {:.3}, {:.3}, 0.85); \
+         var label = $.NSTextField.labelWithString({}); \
+         label.font = $.NSFont.boldSystemFontOfSize(72); label.textColor = $.NSColor.whiteColor; \
+         label.alignment = $.NSTextAlignmentCenter; \
+         label.frame = $.NSMakeRect(0, frame.size.height / 2 - 50, frame.size.width, 100); \
+         win.contentView.addSubview(label); win.orderFrontRegardless; windows.push(win); }} \
+         $.NSRunLoop.currentRunLoop.runUntilDate($.NSDate.dateWithTimeIntervalSinceNow({:.1}));",
+        channel(1..3),
+        channel(3..5),
+        channel(5..7),
+        serde_json::to_string(text).expect("strings always serialize"),
+        duration.as_secs_f64()
+    )
+}
+
+/// Blinks every connected output, bringing back the brightness it had.
+fn blink() -> io::Result<()> {
+    let outputs = dim::parse_xrandr(&output("xrandr", &["--verbose"])?);
+    let set = |brightness: Option<f64>| -> io::Result<()> {
+        for (name, before) in &outputs {
+            let brightness = format!("{:.2}", brightness.unwrap_or(*before));
+            output("xrandr", &["--output", name, "--brightness", &brightness])?;
+        }
+        Ok(())
+    };
+    for _ in 0..BLINKS {
+        set(Some(BLINK_BRIGHTNESS))?;
+        thread::sleep(BLINK);
+        set(None)?;
+        thread::sleep(BLINK);
+    }
+    Ok(())
+}
+
+pub struct Flasher {
+    backend: Backend,
+    /// How long the overlay stays.
+    duration: Duration,
+}
+
+impl Flasher {
+    pub fn new(config: &FlashConfig, backend: Backend) -> Self {
+        Flasher {
+            backend,
+            duration: Duration::from_secs(config.seconds),
+        }
+    }
+
+    /// Flashes the displays once a session comes to its end, rather than being skipped or quit.
+    pub fn handle(&self, event: &Event) -> Result<(), FlashError> {
+        let Event::SessionEnded {
+            session, outcome, ..
+        } = event
+        else {
+            return Ok(());
+        };
+        if !outcome.is_done() {
+            return Ok(());
+        }
+        let (text, color) = announcement(session.kind);
+        match self.backend {
+            Backend::PowerShell => output(
+                "powershell.exe",
+                &[
+                    "-NoProfile",
+                    "-Command",
+                    &powershell_script(text, color, self.duration),
+                ],
+            )
+            .map(drop),
+            Backend::Osascript => output(
+                "osascript",
+                &[
+                    "-l",
+                    "JavaScript",
+                    "-e",
+                    &osascript_script(text, color, self.duration),
+                ],
+            )
+            .map(drop),
+            Backend::Xrandr => blink(),
+        }
+        .map_err(|source| FlashError {
+            backend: self.backend.name(),
+            source,
+        })
+    }
+
+    /// Follows the events until the event bus is dropped, and returns the first failure.
+    pub fn run(self, events: Receiver<Event>) -> Option<FlashError> {
+        let mut first_failure = None;
+        for event in events {
+            if let Err(e) = self.handle(&event) {
+                first_failure.get_or_insert(e);
+            }
+        }
+        first_failure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_overlay_scripts() {
+        let (text, color) = announcement(SessionKind::Work);
+        let script = powershell_script(text, color, Duration::from_secs(3));
+        assert!(script.contains("FromHtml('#2e7d32')"));
+        assert!(script.contains("$label.Text = 'Time for a break'"));
+        assert!(script.contains("AddMilliseconds(3000)"));
+        let script = osascript_script("Don't stop", "#c62828", Duration::from_secs(2));
+        assert!(script.contains("colorWithSRGBRedGreenBlueAlpha(0.776, 0.157, 0.157, 0.85)"));
+        assert!(script.contains("labelWithString(\"Don't stop\")"));
+        assert!(script.contains("dateWithTimeIntervalSinceNow(2.0)"));
+        assert_eq!(announcement(SessionKind::LongBreak).0, "Back to work");
+    }
+}
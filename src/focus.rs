@@ -0,0 +1,598 @@
+// filepath: src/focus.rs
+//! Module keeping distractions away during the work sessions of a Pomodoro timer application: it
+//! warns about, suspends or stops the configured processes and blocks the configured sites in the
+//! hosts file, reverting everything when the work session ends.
+//!
+//! Nothing here asks for more privileges than the timer runs with: acting on the processes of
+//! other users or editing the hosts file fails with a reported error rather than escalating.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, mpsc::Receiver},
+    time::{Duration, Instant},
+};
+
+use rustodoro_client::protocol::SessionKind;
+use serde::Deserialize;
+
+use crate::{
+    SessionType, config::FocusConfig, desktop::Notification, events::Event, quiet::Quiet,
+    settings::SharedSettings,
+};
+
+/// How often the processes are looked for again during a work session.
+const RESCAN: Duration = Duration::from_secs(30);
+/// Lines delimiting the block the timer manages in the hosts file.
+const BLOCK_START: &str = "# BEGIN rustodoro focus mode";
+const BLOCK_END: &str = "# END rustodoro focus mode";
+
+/// What to do with the distracting processes. Acting on them takes an explicit choice, the
+/// default only warns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessAction {
+    #[default]
+    Warn,
+    /// Stop the processes until the work session ends.
+    Suspend,
+    /// Ask the processes to terminate.
+    Kill,
+}
+
+impl ProcessAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProcessAction::Warn => "warn",
+            ProcessAction::Suspend => "suspend",
+            ProcessAction::Kill => "kill",
+        }
+    }
+}
+
+/// The hosts file of the platform.
+pub fn default_hosts_file() -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts")
+    } else {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FocusError {
+    #[error("cannot list the running processes: {0}")]
+    Processes(io::Error),
+    #[error("cannot {action} {name} (pid {pid}): {source}")]
+    Signal {
+        action: &'static str,
+        name: String,
+        pid: u32,
+        source: io::Error,
+    },
+    #[error("cannot edit {}, sites are not blocked: {source}", path.display())]
+    Hosts { path: PathBuf, source: io::Error },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    Stop,
+    Continue,
+    Terminate,
+}
+
+/// The processes of the platform.
+pub trait System: Send {
+    fn processes(&self) -> io::Result<Vec<Process>>;
+    fn signal(&self, pid: u32, signal: Signal) -> io::Result<()>;
+}
+
+/// The processes of the running system, through the `ps` and `kill` commands, or `tasklist` and
+/// `taskkill` on Windows.
+pub struct Os;
+
+#[cfg(unix)]
+impl System for Os {
+    fn processes(&self) -> io::Result<Vec<Process>> {
+        let output = process::Command::new("ps")
+            .args(["-A", "-o", "pid=", "-o", "comm="])
+            .output()?;
+        Ok(parse_ps(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn signal(&self, pid: u32, signal: Signal) -> io::Result<()> {
+        let signal = match signal {
+            Signal::Stop => "-STOP",
+            Signal::Continue => "-CONT",
+            Signal::Terminate => "-TERM",
+        };
+        let output = process::Command::new("kill")
+            .args([signal, &pid.to_string()])
+            .output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(windows)]
+impl System for Os {
+    fn processes(&self) -> io::Result<Vec<Process>> {
+        let output = process::Command::new("tasklist")
+            .args(["/fo", "csv", "/nh"])
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split("\",\"");
+                let name = fields.next()?.trim_start_matches('"');
+                let pid = fields.next()?.parse().ok()?;
+                Some(Process {
+                    pid,
+                    name: name.trim_end_matches(".exe").to_string(),
+                })
+            })
+            .collect())
+    }
+
+    fn signal(&self, pid: u32, signal: Signal) -> io::Result<()> {
+        if signal != Signal::Terminate {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "suspending processes is not supported on Windows",
+            ));
+        }
+        let status = process::Command::new("taskkill")
+            .args(["/pid", &pid.to_string()])
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other("taskkill failed"))
+        }
+    }
+}
+
+/// Reads `ps -o pid= -o comm=` output, which gives the path of the executables on macOS.
+fn parse_ps(output: &str) -> Vec<Process> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (pid, command) = line.trim().split_once(char::is_whitespace)?;
+            let name = Path::new(command.trim()).file_name()?.to_string_lossy();
+            Some(Process {
+                pid: pid.parse().ok()?,
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Whether `name` is the configured process `pattern`, knowing Linux truncates process names to
+/// 15 characters.
+pub fn is_named(name: &str, pattern: &str) -> bool {
+    let (name, pattern) = (name.to_lowercase(), pattern.to_lowercase());
+    name == pattern || (name.len() == 15 && pattern.starts_with(&name))
+}
+
+/// `hosts` with the block sending `sites` and their `www.` subdomain nowhere.
+fn with_block(hosts: &str, sites: &[String]) -> String {
+    let mut content = without_block(hosts);
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(BLOCK_START);
+    content.push('\n');
+    for site in sites {
+        let site = site.trim_start_matches("www.");
+        for host in [site.to_string(), format!("www.{}", site)] {
+            content.push_str(&format!("0.0.0.0 {}\n:: {}\n", host, host));
+        }
+    }
+    content.push_str(BLOCK_END);
+    content.push('\n');
+    content
+}
+
+/// `hosts` without the block of the timer, if any.
+fn without_block(hosts: &str) -> String {
+    let mut inside = false;
+    hosts
+        .split_inclusive('\n')
+        .filter(|line| {
+            let line = line.trim_end();
+            if line == BLOCK_START {
+                inside = true;
+            }
+            let keep = !inside;
+            if line == BLOCK_END {
+                inside = false;
+            }
+            keep
+        })
+        .collect()
+}
+
+/// What a step of the focus mode did, for the notification to the user.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub notes: Vec<String>,
+    pub failures: Vec<FocusError>,
+}
+
+pub struct Focus<S: System> {
+    config: FocusConfig,
+    system: S,
+    /// Processes dealt with during the current work session.
+    handled: Vec<u32>,
+    suspended: Vec<Process>,
+    blocking: bool,
+    last_scan: Option<Instant>,
+    settings: SharedSettings,
+    quiet: Option<Arc<Quiet>>,
+}
+
+impl<S: System> Focus<S> {
+    pub fn new(config: FocusConfig, system: S, settings: SharedSettings) -> Self {
+        Focus {
+            config,
+            system,
+            handled: Vec::new(),
+            suspended: Vec::new(),
+            blocking: false,
+            last_scan: None,
+            settings,
+            quiet: None,
+        }
+    }
+
+    /// Holds the notes on what was done back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    fn dry_run(&self, note: String) -> Option<String> {
+        self.config.dry_run.then(|| format!("Dry run: {}", note))
+    }
+
+    /// Deals with the distracting processes not dealt with yet in this work session.
+    fn scan(&mut self, now: Instant, report: &mut Report) {
+        self.last_scan = Some(now);
+        if self.config.processes.is_empty() {
+            return;
+        }
+        let processes = match self.system.processes() {
+            Ok(processes) => processes,
+            Err(e) => return report.failures.push(FocusError::Processes(e)),
+        };
+        let own = process::id();
+        for process in processes {
+            if process.pid == own
+                || self.handled.contains(&process.pid)
+                || !self
+                    .config
+                    .processes
+                    .iter()
+                    .any(|pattern| is_named(&process.name, pattern))
+            {
+                continue;
+            }
+            self.handled.push(process.pid);
+            let (signal, verb, done) = match self.config.action {
+                ProcessAction::Warn => {
+                    report.notes.push(format!(
+                        "{} is running during the work session",
+                        process.name
+                    ));
+                    continue;
+                }
+                ProcessAction::Suspend => (Signal::Stop, "suspend", "Suspended"),
+                ProcessAction::Kill => (Signal::Terminate, "stop", "Stopped"),
+            };
+            let target = format!("{} (pid {})", process.name, process.pid);
+            if let Some(note) = self.dry_run(format!("would {} {}", verb, target)) {
+                report.notes.push(note);
+                continue;
+            }
+            match self.system.signal(process.pid, signal) {
+                Ok(()) => {
+                    report.notes.push(format!("{} {}", done, target));
+                    if signal == Signal::Stop {
+                        self.suspended.push(process);
+                    }
+                }
+                Err(source) => report.failures.push(FocusError::Signal {
+                    action: verb,
+                    name: process.name,
+                    pid: process.pid,
+                    source,
+                }),
+            }
+        }
+    }
+
+    fn edit_hosts(&self, edit: impl FnOnce(&str) -> String) -> Result<(), FocusError> {
+        let path = &self.config.hosts_file;
+        let error = |source| FocusError::Hosts {
+            path: path.clone(),
+            source,
+        };
+        let content = fs::read_to_string(path).map_err(error)?;
+        let edited = edit(&content);
+        if edited != content {
+            fs::write(path, edited).map_err(error)?;
+        }
+        Ok(())
+    }
+
+    /// Starts the focus mode at the start of a work session.
+    pub fn engage(&mut self, now: Instant) -> Report {
+        let mut report = Report::default();
+        self.handled.clear();
+        self.scan(now, &mut report);
+        if !self.config.sites.is_empty() {
+            let sites = self.config.sites.join(", ");
+            if let Some(note) = self.dry_run(format!("would block {}", sites)) {
+                report.notes.push(note);
+            } else {
+                match self.edit_hosts(|hosts| with_block(hosts, &self.config.sites)) {
+                    Ok(()) => {
+                        self.blocking = true;
+                        report.notes.push(format!("Blocked {}", sites));
+                    }
+                    Err(e) => report.failures.push(e),
+                }
+            }
+        }
+        report
+    }
+
+    /// Reverts the focus mode. Also run when starting, in case the timer did not exit cleanly
+    /// while blocking the sites.
+    pub fn release(&mut self) -> Report {
+        let mut report = Report::default();
+        for process in self.suspended.drain(..) {
+            if let Err(source) = self.system.signal(process.pid, Signal::Continue) {
+                report.failures.push(FocusError::Signal {
+                    action: "resume",
+                    name: process.name,
+                    pid: process.pid,
+                    source,
+                });
+            }
+        }
+        self.last_scan = None;
+        let stale = !self.blocking
+            && fs::read_to_string(&self.config.hosts_file)
+                .is_ok_and(|hosts| hosts.contains(BLOCK_START));
+        if (self.blocking || stale) && !self.config.dry_run {
+            match self.edit_hosts(without_block) {
+                Ok(()) => self.blocking = false,
+                Err(e) => report.failures.push(e),
+            }
+        }
+        report
+    }
+
+    /// Runs the focus mode according to `event`.
+    pub fn handle(&mut self, event: &Event, now: Instant) -> Report {
+        match event {
+            Event::SessionStarted {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                ..
+            } => self.engage(now),
+            Event::SessionEnded {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                ..
+            } => self.release(),
+            Event::Tick { .. }
+                if self
+                    .last_scan
+                    .is_some_and(|last| now.duration_since(last) >= RESCAN) =>
+            {
+                let mut report = Report::default();
+                self.scan(now, &mut report);
+                report
+            }
+            _ => Report::default(),
+        }
+    }
+
+    /// Follows the events until the event bus is dropped, notifying the user of what was done,
+    /// unless the notifications are off or the timer keeps quiet, and returns the first failure.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<FocusError> {
+        let mut first_failure = None;
+        let (settings, quiet) = (Arc::clone(&self.settings), self.quiet.clone());
+        let mut notify = |report: Report| {
+            let mut lines = report.notes;
+            lines.extend(report.failures.iter().map(ToString::to_string));
+            if !lines.is_empty()
+                && !settings.lock().unwrap().no_notify
+                && !quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+            {
+                // Failures are reported when quitting too.
+                let _ = Notification::new()
+                    .summary("Focus mode")
+                    .body(&lines.join("\n"))
+                    .icon("dialog-information")
+                    .show();
+            }
+            if first_failure.is_none() {
+                first_failure = report.failures.into_iter().next();
+            }
+        };
+        notify(self.release());
+        for event in events {
+            notify(self.handle(&event, Instant::now()));
+        }
+        notify(self.release());
+        first_failure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::settings::Settings;
+
+    /// The signals sent, with the pid they were sent to.
+    type Signals = Arc<Mutex<Vec<(u32, Signal)>>>;
+
+    /// A system running the given processes and recording the signals sent.
+    struct Fake {
+        processes: Vec<Process>,
+        signals: Signals,
+    }
+
+    impl System for Fake {
+        fn processes(&self) -> io::Result<Vec<Process>> {
+            Ok(self.processes.clone())
+        }
+
+        fn signal(&self, pid: u32, signal: Signal) -> io::Result<()> {
+            self.signals.lock().unwrap().push((pid, signal));
+            Ok(())
+        }
+    }
+
+    fn focus(action: ProcessAction, dry_run: bool) -> (Focus<Fake>, Signals) {
+        let signals = Arc::new(Mutex::new(Vec::new()));
+        let system = Fake {
+            processes: vec![
+                Process {
+                    pid: 10,
+                    name: "Slack".to_string(),
+                },
+                Process {
+                    pid: 11,
+                    name: "vim".to_string(),
+                },
+            ],
+            signals: Arc::clone(&signals),
+        };
+        let config = FocusConfig {
+            processes: vec!["slack".to_string()],
+            action,
+            sites: Vec::new(),
+            hosts_file: env::temp_dir().join("rustodoro-no-hosts"),
+            dry_run,
+        };
+        (
+            Focus::new(config, system, Settings::default().into_shared()),
+            signals,
+        )
+    }
+
+    fn work_started() -> Event {
+        Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: Duration::from_secs(25 * 60),
+        }
+    }
+
+    #[test]
+    fn reads_process_lists() {
+        let processes = parse_ps("  1 /sbin/launchd\n 420 Slack\nbogus\n");
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].name, "launchd");
+        assert_eq!(processes[1].pid, 420);
+        assert!(is_named("gnome-calculato", "gnome-calculator"));
+        assert!(!is_named("slackware", "slack"));
+    }
+
+    #[test]
+    fn suspends_processes_during_work_only() {
+        let (mut focus, signals) = focus(ProcessAction::Suspend, false);
+        let now = Instant::now();
+        let report = focus.handle(&work_started(), now);
+        assert_eq!(report.notes, ["Suspended Slack (pid 10)"]);
+        // Rescans leave the processes already dealt with alone.
+        let tick = Event::Tick {
+            elapsed: RESCAN,
+            remaining: Duration::from_secs(60),
+        };
+        focus.handle(&tick, now + RESCAN);
+        focus.handle(
+            &Event::SessionEnded {
+                session: SessionType::work(),
+                elapsed: Duration::from_secs(25 * 60),
+                outcome: crate::events::SessionOutcome::Completed,
+            },
+            now + RESCAN * 2,
+        );
+        assert_eq!(
+            *signals.lock().unwrap(),
+            [(10, Signal::Stop), (10, Signal::Continue)]
+        );
+    }
+
+    #[test]
+    fn dry_runs_and_warnings_leave_processes_alone() {
+        let (mut focus, signals) = focus(ProcessAction::Kill, true);
+        let report = focus.engage(Instant::now());
+        assert_eq!(report.notes, ["Dry run: would stop Slack (pid 10)"]);
+        let (mut warning, _) = self::focus(ProcessAction::Warn, false);
+        let report = warning.engage(Instant::now());
+        assert_eq!(report.notes, ["Slack is running during the work session"]);
+        assert!(signals.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn blocks_sites_in_a_block_of_its_own() {
+        let hosts = "127.0.0.1 localhost\n";
+        let blocked = with_block(hosts, &["www.reddit.com".to_string()]);
+        assert_eq!(
+            blocked,
+            "127.0.0.1 localhost\n# BEGIN rustodoro focus mode\n0.0.0.0 reddit.com\n:: reddit.com\n0.0.0.0 www.reddit.com\n:: www.reddit.com\n# END rustodoro focus mode\n"
+        );
+        assert_eq!(with_block(&blocked, &["reddit.com".to_string()]), blocked);
+        assert_eq!(without_block(&blocked), hosts);
+    }
+
+    #[test]
+    fn reverts_the_hosts_file() {
+        let path = env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join("hosts");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+        let (mut focus, _) = focus(ProcessAction::Warn, false);
+        focus.config.sites = vec!["example.com".to_string()];
+        focus.config.hosts_file = path.clone();
+
+        let report = focus.engage(Instant::now());
+        assert!(report.failures.is_empty());
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains("0.0.0.0 example.com")
+        );
+        focus.release();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "127.0.0.1 localhost\n");
+    }
+}
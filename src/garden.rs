@@ -0,0 +1,192 @@
+// filepath: src/garden.rs
+//! Module growing a tomato garden from the history of a Pomodoro timer application: each day has
+//! a plant, which bears a tomato per pomodoro completed and loses a leaf per work session
+//! abandoned, wilting when more were abandoned than completed. The plant of the day is shown in
+//! the banner of each work session, and `rustodoro garden` shows the plants of the last days.
+//!
+//! The garden is drawn from the history alone, so it grows back the same wherever the history
+//! goes.
+use chrono::{Days, Local, NaiveDate};
+
+use crate::{
+    config::{self, Cli, ConfigError},
+    events::SessionOutcome,
+    history::{HISTORY_ENV, History, HistoryError, SessionKind, SessionRecord, history_path},
+    render::Theme,
+    summary,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum GardenError {
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+}
+
+/// The plant of a day.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Plant {
+    /// Pomodoros completed.
+    pub tomatoes: u64,
+    /// Work sessions abandoned before their end.
+    pub wilted: u64,
+}
+
+impl Plant {
+    /// The plant grown by the sessions of `records`, usually those of a day.
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a SessionRecord>) -> Self {
+        let mut plant = Plant::default();
+        for record in records {
+            if record.kind != SessionKind::Work {
+                continue;
+            }
+            if record.outcome.is_done() {
+                plant.tomatoes += 1;
+            } else if record.outcome == SessionOutcome::Interrupted {
+                plant.wilted += 1;
+            }
+        }
+        plant
+    }
+
+    pub fn render(&self, theme: Theme) -> String {
+        let (sprout, healthy, wilting, tomato, leaf) = match theme {
+            Theme::Emoji => ("🌱", "🌿", "🥀", "🍅", "🍂"),
+            Theme::Plain => (",", "Y", "~", "o", "x"),
+        };
+        let stem = if self.wilted > self.tomatoes {
+            wilting
+        } else if self.tomatoes > 0 {
+            healthy
+        } else {
+            sprout
+        };
+        let fruits = tomato.repeat(self.tomatoes as usize) + &leaf.repeat(self.wilted as usize);
+        if fruits.is_empty() {
+            stem.to_string()
+        } else {
+            format!("{} {}", stem, fruits)
+        }
+    }
+}
+
+/// The plants of the `days` days up to `last`, oldest first.
+fn plants(records: &[SessionRecord], last: NaiveDate, days: u64) -> Vec<(NaiveDate, Plant)> {
+    (0..days)
+        .rev()
+        .filter_map(|back| last.checked_sub_days(Days::new(back)))
+        .map(|day| (day, Plant::from_records(summary::on_day(records, day))))
+        .collect()
+}
+
+fn render(plants: &[(NaiveDate, Plant)], theme: Theme) -> String {
+    let tomatoes: u64 = plants.iter().map(|(_, plant)| plant.tomatoes).sum();
+    let wilted: u64 = plants.iter().map(|(_, plant)| plant.wilted).sum();
+    let mut rendered = format!(
+        "Garden of the last {} days: {} tomato{}, {} wilted lea{}\n\n",
+        plants.len(),
+        tomatoes,
+        if tomatoes == 1 { "" } else { "es" },
+        wilted,
+        if wilted == 1 { "f" } else { "ves" }
+    );
+    for (day, plant) in plants {
+        rendered.push_str(&format!(
+            "  {}  {}\n",
+            day.format("%a %m-%d"),
+            plant.render(theme)
+        ));
+    }
+    rendered.push_str(match theme {
+        Theme::Emoji => "\n🍅 a pomodoro completed, 🍂 a work session abandoned\n",
+        Theme::Plain => "\no a pomodoro completed, x a work session abandoned\n",
+    });
+    rendered
+}
+
+fn run_action(cli: &Cli, days: u64) -> Result<String, GardenError> {
+    let theme = config::resolve(cli)?.theme.value;
+    let path =
+        history_path(cli.history.as_deref(), cli.name.as_deref()).ok_or(GardenError::NoHistory)?;
+    let records = History::new(path).sessions()?;
+    let today = Local::now().date_naive();
+    Ok(render(&plants(&records, today, days), theme))
+}
+
+/// Runs the `garden` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, days: u64) -> i32 {
+    match run_action(cli, days) {
+        Ok(rendered) => {
+            print!("{}", rendered);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record(day: u32, kind: SessionKind, outcome: SessionOutcome) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap(),
+            kind,
+            planned: 25 * 60,
+            elapsed: 10 * 60,
+            outcome,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: None,
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn grows_a_plant_a_day() {
+        let records = [
+            record(12, SessionKind::Work, SessionOutcome::Completed),
+            record(12, SessionKind::ShortBreak, SessionOutcome::Interrupted),
+            record(12, SessionKind::Work, SessionOutcome::FinishedEarly),
+            record(12, SessionKind::Work, SessionOutcome::Interrupted),
+            record(13, SessionKind::Work, SessionOutcome::Interrupted),
+        ];
+        let last = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let plants = plants(&records, last, 3);
+        assert_eq!(
+            plants.iter().map(|(_, plant)| *plant).collect::<Vec<_>>(),
+            [
+                Plant {
+                    tomatoes: 2,
+                    wilted: 1
+                },
+                Plant {
+                    tomatoes: 0,
+                    wilted: 1
+                },
+                Plant::default(),
+            ]
+        );
+        assert_eq!(plants[0].1.render(Theme::Emoji), "🌿 🍅🍅🍂");
+        assert_eq!(plants[1].1.render(Theme::Plain), "~ x");
+        let rendered = render(&plants, Theme::Emoji);
+        assert!(rendered.starts_with("Garden of the last 3 days: 2 tomatoes, 2 wilted leaves\n"));
+        assert!(rendered.contains("\n  Wed 10-14  🌱\n"));
+    }
+}
@@ -0,0 +1,393 @@
+// filepath: src/gcal.rs
+//! Module connecting a Pomodoro timer application to Google Calendar: `rustodoro auth google`
+//! authorizes it through the OAuth device flow, keeping the refresh token in the keychain, and the
+//! timer then writes the focus blocks to the calendar and reads the events the user is busy with
+//! from it. The access tokens only live in memory, refreshed as they expire.
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use ureq::{Agent, http::Response};
+
+use crate::{
+    calendar::{Busy, Calendar, CalendarError, Expected, FocusEvent, TIMEOUT, Written},
+    config::{self, Cli, GcalConfig},
+    keychain::{self, Keychain},
+};
+
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const API: &str = "https://www.googleapis.com/calendar/v3";
+/// Reading and writing the events of the calendars, the only access asked for.
+const SCOPE: &str = "https://www.googleapis.com/auth/calendar.events";
+/// Account of the refresh token in the keychain, the one of `rustodoro auth remove google`.
+const ACCOUNT: &str = "google";
+/// Access tokens are refreshed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// The code the user enters to authorize rustodoro.
+#[derive(Debug, Deserialize)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_url: String,
+    /// Seconds the code can be entered within.
+    expires_in: u64,
+    /// Seconds between two polls for the tokens.
+    interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tokens {
+    access_token: String,
+    expires_in: u64,
+    /// Only given when authorizing.
+    refresh_token: Option<String>,
+}
+
+/// The error of a token request, e.g. `authorization_pending` while the code is not entered.
+#[derive(Debug, Deserialize)]
+struct OAuthError {
+    error: String,
+}
+
+fn http(e: impl ToString) -> CalendarError {
+    CalendarError::Http(e.to_string())
+}
+
+fn agent() -> Agent {
+    Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        // A precondition failing is a conflict rather than an error.
+        .http_status_as_error(false)
+        .build()
+        .into()
+}
+
+/// The body of a successful `response`, or the status and the message of its error.
+fn json_body(response: Response<ureq::Body>) -> Result<Value, CalendarError> {
+    let status = response.status().as_u16();
+    let body: Value = response.into_body().read_json().unwrap_or(Value::Null);
+    if (200..300).contains(&status) {
+        return Ok(body);
+    }
+    let message = body["error"]["message"]
+        .as_str()
+        .or(body["error"].as_str())
+        .unwrap_or("no details");
+    Err(http(format!("status {}: {}", status, message)))
+}
+
+/// Asks for the tokens, the refresh token being `grant` or the device code being entered.
+fn request_tokens(
+    agent: &Agent,
+    config: &GcalConfig,
+    grant: &[(&str, &str)],
+) -> Result<Result<Tokens, OAuthError>, CalendarError> {
+    let form = [
+        ("client_id", config.client_id.as_str()),
+        ("client_secret", config.client_secret.as_str()),
+    ];
+    let response = agent
+        .post(TOKEN_URL)
+        .send_form(form.iter().chain(grant).copied())
+        .map_err(http)?;
+    let ok = response.status().is_success();
+    let mut body = response.into_body();
+    if ok {
+        body.read_json().map(Ok).map_err(http)
+    } else {
+        body.read_json().map(Err).map_err(http)
+    }
+}
+
+/// Runs the device flow, the user entering a code on another device, and keeps the refresh token
+/// in `keychain`.
+fn authorize(config: &GcalConfig, keychain: &dyn Keychain) -> Result<(), CalendarError> {
+    let agent = agent();
+    let response = agent
+        .post(DEVICE_CODE_URL)
+        .send_form([("client_id", config.client_id.as_str()), ("scope", SCOPE)])
+        .map_err(http)?;
+    let code: DeviceCode = serde_json::from_value(json_body(response)?).map_err(http)?;
+    println!(
+        "Open {} and enter the code {} to let rustodoro use your calendar.",
+        code.verification_url, code.user_code
+    );
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval.max(1));
+    let grant = [
+        ("device_code", code.device_code.as_str()),
+        ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+    ];
+    while Instant::now() < deadline {
+        thread::sleep(interval);
+        match request_tokens(&agent, config, &grant)? {
+            Ok(tokens) => {
+                let refresh_token = tokens
+                    .refresh_token
+                    .ok_or_else(|| http("Google gave no refresh token"))?;
+                keychain.set(ACCOUNT, &refresh_token)?;
+                return Ok(());
+            }
+            Err(e) if e.error == "authorization_pending" => {}
+            Err(e) if e.error == "slow_down" => interval += Duration::from_secs(5),
+            Err(e) if e.error == "access_denied" => return Err(CalendarError::Denied),
+            Err(e) if e.error != "expired_token" => return Err(http(e.error)),
+            Err(_) => break,
+        }
+    }
+    Err(CalendarError::Expired)
+}
+
+/// Runs `auth google` and returns the process exit code.
+pub fn run_command(cli: &Cli) -> i32 {
+    let gcal = match config::resolve(cli) {
+        Ok(effective) => effective.gcal.value,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return 2;
+        }
+    };
+    let Some(gcal) = gcal else {
+        eprintln!("error: there is no [gcal] table in the configuration file");
+        eprintln!("  hint: add one with the client_id and client_secret of your OAuth client");
+        return 2;
+    };
+    match authorize(&gcal, &keychain::Os) {
+        Ok(()) => {
+            println!("Authorized, the refresh token is kept in the keychain.");
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// The Google event id of the focus event `uid`, ids only taking the digits and the letters up
+/// to v.
+fn event_id(uid: &str) -> String {
+    uid.chars()
+        .filter(|c| c.is_ascii_digit() || ('a'..='v').contains(c))
+        .collect()
+}
+
+/// `s` percent-encoded for a path segment, calendar ids being email addresses.
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// The events of an `events.list` reply the user is busy with: neither cancelled, nor free, nor
+/// lasting all day, nor focus blocks.
+fn busy_events(reply: &Value) -> Vec<Busy> {
+    let time = |time: &Value| {
+        let time = DateTime::parse_from_rfc3339(time["dateTime"].as_str()?).ok()?;
+        Some(time.with_timezone(&Utc))
+    };
+    reply["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|item| {
+            item["status"] != "cancelled"
+                && item["transparency"] != "transparent"
+                && !item["id"].as_str().unwrap_or("").starts_with("rustodoro")
+        })
+        .filter_map(|item| {
+            Some(Busy {
+                title: item["summary"].as_str().unwrap_or("An event").to_string(),
+                start: time(&item["start"])?,
+                end: time(&item["end"])?,
+            })
+        })
+        .collect()
+}
+
+/// A calendar of a Google account.
+pub struct Google {
+    agent: Agent,
+    config: GcalConfig,
+    refresh_token: String,
+    /// The access token, with the time it is refreshed at.
+    access: Mutex<Option<(String, Instant)>>,
+}
+
+impl Google {
+    /// Connects to the calendar with the refresh token of `rustodoro auth google`.
+    pub fn connect(config: &GcalConfig, keychain: &dyn Keychain) -> Result<Self, CalendarError> {
+        Ok(Google {
+            agent: agent(),
+            config: config.clone(),
+            refresh_token: keychain.get(ACCOUNT)?.ok_or(CalendarError::Unauthorized)?,
+            access: Mutex::new(None),
+        })
+    }
+
+    fn access_token(&self) -> Result<String, CalendarError> {
+        let mut access = self.access.lock().unwrap();
+        if let Some((token, refresh_at)) = &*access
+            && Instant::now() < *refresh_at
+        {
+            return Ok(token.clone());
+        }
+        let grant = [
+            ("refresh_token", self.refresh_token.as_str()),
+            ("grant_type", "refresh_token"),
+        ];
+        let tokens = match request_tokens(&self.agent, &self.config, &grant)? {
+            Ok(tokens) => tokens,
+            // The user revoked the access.
+            Err(e) if e.error == "invalid_grant" => return Err(CalendarError::Unauthorized),
+            Err(e) => return Err(http(e.error)),
+        };
+        let lifetime = Duration::from_secs(tokens.expires_in).saturating_sub(EXPIRY_MARGIN);
+        *access = Some((tokens.access_token.clone(), Instant::now() + lifetime));
+        Ok(tokens.access_token)
+    }
+
+    fn authorization(&self) -> Result<String, CalendarError> {
+        Ok(format!("Bearer {}", self.access_token()?))
+    }
+
+    fn events_url(&self) -> String {
+        format!("{}/calendars/{}/events", API, encode(&self.config.calendar))
+    }
+}
+
+impl Calendar for Google {
+    fn put(&self, event: &FocusEvent, expected: &Expected) -> Result<Written, CalendarError> {
+        let id = event_id(&event.uid);
+        let body = json!({
+            "id": id,
+            "summary": event.title,
+            "start": { "dateTime": event.start.to_rfc3339() },
+            "end": { "dateTime": event.end.to_rfc3339() },
+            "transparency": "opaque",
+        });
+        let authorization = self.authorization()?;
+        let response = match expected {
+            Expected::Absent => self
+                .agent
+                .post(self.events_url())
+                .header("Authorization", authorization)
+                .send_json(body),
+            Expected::Tag(etag) => self
+                .agent
+                .put(format!("{}/{}", self.events_url(), id))
+                .header("Authorization", authorization)
+                .header("If-Match", etag)
+                .send_json(body),
+            Expected::Unknown => self
+                .agent
+                .put(format!("{}/{}", self.events_url(), id))
+                .header("Authorization", authorization)
+                .send_json(body),
+        }
+        .map_err(http)?;
+        match response.status().as_u16() {
+            // Created already, or deleted in the calendar, which is not undone.
+            409 | 412 => Ok(Written::Conflict),
+            404 | 410 if *expected != Expected::Absent => Ok(Written::Conflict),
+            _ => {
+                let reply = json_body(response)?;
+                Ok(Written::Done(reply["etag"].as_str().map(str::to_string)))
+            }
+        }
+    }
+
+    fn delete(&self, uid: &str, expected: &Expected) -> Result<Written, CalendarError> {
+        let mut request = self
+            .agent
+            .delete(format!("{}/{}", self.events_url(), event_id(uid)))
+            .header("Authorization", self.authorization()?);
+        if let Expected::Tag(etag) = expected {
+            request = request.header("If-Match", etag);
+        }
+        let response = request.call().map_err(http)?;
+        match response.status().as_u16() {
+            404 | 410 => Ok(Written::Done(None)),
+            412 => Ok(Written::Conflict),
+            _ => json_body(response).map(|_| Written::Done(None)),
+        }
+    }
+
+    fn busy(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Busy>, CalendarError> {
+        let response = self
+            .agent
+            .get(self.events_url())
+            .header("Authorization", self.authorization()?)
+            .query("timeMin", from.to_rfc3339())
+            .query("timeMax", to.to_rfc3339())
+            .query("singleEvents", "true")
+            .query("orderBy", "startTime")
+            .call()
+            .map_err(http)?;
+        Ok(busy_events(&json_body(response)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_events_as_google_wants() {
+        assert_eq!(
+            event_id("rustodoro-1760515200000@rustodoro"),
+            "rustodoro1760515200000rustodoro"
+        );
+        assert_eq!(encode("me@example.com"), "me%40example.com");
+        assert_eq!(encode("primary"), "primary");
+    }
+
+    #[test]
+    fn reads_the_busy_events() {
+        let reply = json!({
+            "items": [
+                {
+                    "id": "abc",
+                    "summary": "Standup",
+                    "start": { "dateTime": "2026-10-15T10:00:00+02:00" },
+                    "end": { "dateTime": "2026-10-15T10:15:00+02:00" }
+                },
+                {
+                    "id": "rustodoro1760515200000rustodoro",
+                    "summary": "Focus",
+                    "start": { "dateTime": "2026-10-15T09:00:00+02:00" },
+                    "end": { "dateTime": "2026-10-15T09:25:00+02:00" }
+                },
+                {
+                    "id": "def",
+                    "summary": "Lunch",
+                    "transparency": "transparent",
+                    "start": { "dateTime": "2026-10-15T12:00:00+02:00" },
+                    "end": { "dateTime": "2026-10-15T13:00:00+02:00" }
+                },
+                {
+                    "id": "ghi",
+                    "summary": "Holiday",
+                    "start": { "date": "2026-10-15" },
+                    "end": { "date": "2026-10-16" }
+                }
+            ]
+        });
+        let busy = busy_events(&reply);
+        assert_eq!(busy.len(), 1);
+        assert_eq!(busy[0].title, "Standup");
+        assert_eq!(busy[0].start.to_rfc3339(), "2026-10-15T08:00:00+00:00");
+    }
+}
@@ -0,0 +1,172 @@
+// filepath: src/git.rs
+//! Module reading the commits of git repositories for a Pomodoro timer application, to tell which
+//! were made during the work sessions of the history.
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use chrono::{DateTime, Local, NaiveDate, TimeDelta};
+
+use crate::history::{SessionKind, SessionRecord};
+
+/// Separates the fields of a commit in the output of `git log`.
+const SEPARATOR: char = '\u{1f}';
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error("cannot run git, is it installed? {0}")]
+    Unavailable(io::Error),
+    #[error("cannot read the commits of {}: {message}", repo.display())]
+    Failed { repo: PathBuf, message: String },
+}
+
+/// A commit of a repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    /// Name of the directory of the repository.
+    pub repo: String,
+    /// Abbreviated hash.
+    pub hash: String,
+    /// When it was authored, rebases keeping it.
+    pub time: DateTime<Local>,
+    pub subject: String,
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String, GitError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(GitError::Unavailable)?;
+    if !output.status.success() {
+        return Err(GitError::Failed {
+            repo: repo.to_path_buf(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The commits of all the branches of `repo` since the day `since`, merges excluded, by `author`,
+/// or by the `user.email` of the repository when not given.
+pub fn log(repo: &Path, since: NaiveDate, author: Option<&str>) -> Result<Vec<Commit>, GitError> {
+    let author = match author {
+        Some(author) => Some(author.to_string()),
+        // Without a user.email, git config fails and the commits of everyone are read.
+        None => git(repo, &["config", "user.email"])
+            .ok()
+            .map(|email| email.trim().to_string())
+            .filter(|email| !email.is_empty()),
+    };
+    let since = format!("--since={} 00:00", since.format("%Y-%m-%d"));
+    let format = format!("--format=%h{0}%aI{0}%s", SEPARATOR);
+    let mut args = vec!["log", "--all", "--no-merges", &since, &format];
+    let author = author.map(|author| format!("--author={}", author));
+    if let Some(author) = &author {
+        args.push(author);
+    }
+    let name = repo.file_name().map_or_else(
+        || repo.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    Ok(parse_log(&name, &git(repo, &args)?))
+}
+
+/// The commits of the output of `git log` of `repo`, skipping the lines which cannot be read.
+fn parse_log(repo: &str, output: &str) -> Vec<Commit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, SEPARATOR);
+            let hash = fields.next()?;
+            let time = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+            Some(Commit {
+                repo: repo.to_string(),
+                hash: hash.to_string(),
+                time: time.with_timezone(&Local),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The commits made during the work session `record`, from its start to its end, pauses included,
+/// in the order of `commits`. Breaks have none.
+pub fn during<'a>(record: &SessionRecord, commits: &'a [Commit]) -> Vec<&'a Commit> {
+    if record.kind != SessionKind::Work {
+        return Vec::new();
+    }
+    let end = record.started_at + TimeDelta::seconds((record.elapsed + record.paused) as i64);
+    commits
+        .iter()
+        .filter(|commit| record.started_at <= commit.time && commit.time <= end)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::events::SessionOutcome;
+
+    #[test]
+    fn reads_the_log() {
+        let output = "1a2b3c4\u{1f}2026-03-10T09:10:00+01:00\u{1f}Fix the parser\n\
+                      garbage\n\
+                      5d6e7f8\u{1f}2026-03-10T10:00:00+01:00\u{1f}Split\u{1f}the lexer\n";
+        let commits = parse_log("app", output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].repo, "app");
+        assert_eq!(commits[0].hash, "1a2b3c4");
+        assert_eq!(commits[0].time.to_rfc3339(), {
+            let time = DateTime::parse_from_rfc3339("2026-03-10T09:10:00+01:00").unwrap();
+            time.with_timezone(&Local).to_rfc3339()
+        });
+        assert_eq!(commits[1].subject, "Split\u{1f}the lexer");
+    }
+
+    #[test]
+    fn matches_the_commits_of_a_work_session() {
+        let started_at = Local.with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap();
+        let commit = |minutes| Commit {
+            repo: "app".to_string(),
+            hash: format!("{:07}", minutes),
+            time: started_at + TimeDelta::minutes(minutes),
+            subject: String::new(),
+        };
+        let commits = [commit(-1), commit(0), commit(20), commit(30), commit(31)];
+        let record = SessionRecord {
+            id: String::new(),
+            started_at,
+            kind: SessionKind::Work,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 1,
+            resets: 0,
+            paused: 5 * 60,
+            focus: None,
+            task: None,
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        };
+        let hashes: Vec<&str> = during(&record, &commits)
+            .iter()
+            .map(|commit| commit.hash.as_str())
+            .collect();
+        assert_eq!(hashes, ["0000000", "0000020", "0000030"]);
+        let break_record = SessionRecord {
+            kind: SessionKind::ShortBreak,
+            ..record
+        };
+        assert!(during(&break_record, &commits).is_empty());
+    }
+}
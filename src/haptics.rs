@@ -0,0 +1,280 @@
+// filepath: src/haptics.rs
+//! Module making the device of a Pomodoro timer application vibrate at the moments the sounds are
+//! played, as an alternative to them: each cue of the `[vibrations]` table takes a pattern of
+//! pulses and pauses, which a backend plays. The phone vibrates under Termux, and a command of
+//! your own drives anything else, e.g. the haptics of a laptop or a gamepad through its force
+//! feedback.
+use std::{fmt, io, process::Command, str::FromStr, sync::Arc, thread, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    platform::{self, Platform},
+    sounds::Cue,
+    template,
+};
+
+/// Placeholder of the command, the length of the pulse in milliseconds.
+pub const PLACEHOLDERS: [&str; 1] = ["ms"];
+/// Shortest and longest pulse or pause, in milliseconds.
+const MIN_STEP: u64 = 10;
+const MAX_STEP: u64 = 5000;
+/// Most pulses and pauses in a pattern.
+const MAX_STEPS: usize = 16;
+/// The named patterns, in milliseconds.
+const NAMED: [(&str, &[u64]); 4] = [
+    ("none", &[]),
+    ("short", &[200]),
+    ("long", &[800]),
+    ("double", &[200, 150, 200]),
+];
+
+/// A vibration, pulses alternating with pauses, written as a name (`none`, `short`, `long` or
+/// `double`) or as milliseconds, e.g. `"300,100,300"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern {
+    pub steps: Vec<Duration>,
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let milliseconds: Vec<u64> = match NAMED.iter().find(|(name, _)| *name == s) {
+            Some((_, milliseconds)) => milliseconds.to_vec(),
+            None => s
+                .split(',')
+                .map(|step| step.trim().parse::<u64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| {
+                    format!(
+                        "must be none, short, long, double or milliseconds such as 300,100,300, got {:?}",
+                        s
+                    )
+                })?,
+        };
+        if milliseconds.len() > MAX_STEPS {
+            return Err(format!("must have at most {} steps", MAX_STEPS));
+        }
+        if let Some(step) = milliseconds
+            .iter()
+            .find(|step| !(MIN_STEP..=MAX_STEP).contains(*step))
+        {
+            return Err(format!(
+                "each step must be from {} to {} milliseconds, got {}",
+                MIN_STEP, MAX_STEP, step
+            ));
+        }
+        Ok(Pattern {
+            steps: milliseconds
+                .into_iter()
+                .map(Duration::from_millis)
+                .collect(),
+        })
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let milliseconds: Vec<u64> = self
+            .steps
+            .iter()
+            .map(|step| step.as_millis() as u64)
+            .collect();
+        match NAMED.iter().find(|(_, named)| *named == milliseconds) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => {
+                let steps: Vec<String> = milliseconds.iter().map(u64::to_string).collect();
+                write!(f, "{}", steps.join(","))
+            }
+        }
+    }
+}
+
+/// The `[vibrations]` table of the configuration file. Once there, the ends of the sessions
+/// vibrate twice unless configured otherwise.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct VibrationsConfig {
+    /// Command making the device vibrate for `{{ms}}` milliseconds, the phone under Termux when
+    /// none.
+    pub command: Option<String>,
+    pub session_start: Pattern,
+    pub checkpoint: Pattern,
+    pub ten_seconds_left: Pattern,
+    pub work_end: Pattern,
+    pub break_end: Pattern,
+    pub goal_reached: Pattern,
+}
+
+impl Default for VibrationsConfig {
+    fn default() -> Self {
+        let double: Pattern = "double".parse().expect("the named patterns are valid");
+        VibrationsConfig {
+            command: None,
+            session_start: Pattern::default(),
+            checkpoint: Pattern::default(),
+            ten_seconds_left: Pattern::default(),
+            work_end: double.clone(),
+            break_end: double,
+            goal_reached: Pattern::default(),
+        }
+    }
+}
+
+impl VibrationsConfig {
+    /// The pattern of `cue`.
+    pub fn pattern(&self, cue: Cue) -> &Pattern {
+        match cue {
+            Cue::SessionStart => &self.session_start,
+            Cue::Checkpoint => &self.checkpoint,
+            Cue::TenSecondsLeft => &self.ten_seconds_left,
+            Cue::WorkEnd => &self.work_end,
+            Cue::BreakEnd => &self.break_end,
+            Cue::GoalReached => &self.goal_reached,
+        }
+    }
+}
+
+/// What makes the device vibrate.
+pub trait Vibrator: Send + Sync {
+    /// Starts a pulse of `length`, without waiting for its end.
+    fn pulse(&self, length: Duration) -> io::Result<()>;
+}
+
+/// The phone, through `termux-vibrate` of Termux:API.
+pub struct Termux;
+
+impl Vibrator for Termux {
+    fn pulse(&self, length: Duration) -> io::Result<()> {
+        let milliseconds = length.as_millis().to_string();
+        run(Command::new("termux-vibrate").args(["-f", "-d", &milliseconds]))
+    }
+}
+
+/// A command of the user, run by the shell with the length of the pulse.
+pub struct Custom {
+    pub command: String,
+}
+
+impl Vibrator for Custom {
+    fn pulse(&self, length: Duration) -> io::Result<()> {
+        let command = template::fill(&self.command, &[("ms", length.as_millis().to_string())]);
+        run(Command::new("sh").args(["-c", &command]))
+    }
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// The vibrations of the cues, played by their backend.
+pub struct Haptics {
+    config: VibrationsConfig,
+    vibrator: Arc<dyn Vibrator>,
+}
+
+impl Haptics {
+    pub fn new(config: VibrationsConfig, vibrator: Arc<dyn Vibrator>) -> Self {
+        Haptics { config, vibrator }
+    }
+
+    /// The vibrations of `config` on the command it gives, or else on the phone under Termux.
+    /// None where there is nothing to vibrate.
+    pub fn detect(config: &VibrationsConfig) -> Option<Self> {
+        let vibrator: Arc<dyn Vibrator> = match &config.command {
+            Some(command) => Arc::new(Custom {
+                command: command.clone(),
+            }),
+            None if platform::current() == Platform::Termux => Arc::new(Termux),
+            None => return None,
+        };
+        Some(Haptics::new(config.clone(), vibrator))
+    }
+
+    /// Plays the pattern of `cue` on its own thread, so the timer doesn't wait for it. A failing
+    /// pulse ends the pattern, the sound and the notification still telling the moment.
+    pub fn play(&self, cue: Cue) {
+        let steps = self.config.pattern(cue).steps.clone();
+        if steps.is_empty() {
+            return;
+        }
+        let vibrator = Arc::clone(&self.vibrator);
+        thread::spawn(move || {
+            for (index, step) in steps.into_iter().enumerate() {
+                if index % 2 == 0 && vibrator.pulse(step).is_err() {
+                    return;
+                }
+                thread::sleep(step);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, mpsc};
+
+    use super::*;
+
+    #[test]
+    fn reads_the_patterns() {
+        let pattern = |value: &str| value.parse::<Pattern>();
+        assert_eq!(pattern("none").unwrap().steps, []);
+        assert_eq!(
+            pattern(" 300, 100 ,300").unwrap().steps,
+            [300, 100, 300].map(Duration::from_millis)
+        );
+        assert_eq!(pattern("200,150,200").unwrap().to_string(), "double");
+        assert_eq!(pattern("300,100").unwrap().to_string(), "300,100");
+        assert!(pattern("buzz").is_err());
+        assert!(pattern("5,100").unwrap_err().contains("from 10 to 5000"));
+        let config: VibrationsConfig = toml::from_str("goal_reached = \"long\"").unwrap();
+        assert_eq!(config.pattern(Cue::WorkEnd).to_string(), "double");
+        assert_eq!(config.pattern(Cue::GoalReached).to_string(), "long");
+        assert_eq!(config.pattern(Cue::SessionStart).to_string(), "none");
+        assert!(toml::from_str::<VibrationsConfig>("work_end = \"buzz\"").is_err());
+    }
+
+    /// Records the pulses.
+    struct Recorder(Mutex<mpsc::Sender<Duration>>);
+
+    impl Vibrator for Recorder {
+        fn pulse(&self, length: Duration) -> io::Result<()> {
+            self.0.lock().unwrap().send(length).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pulses_between_the_pauses() {
+        let (tx, rx) = mpsc::channel();
+        let config = VibrationsConfig {
+            work_end: "30,10,50".parse().unwrap(),
+            ..VibrationsConfig::default()
+        };
+        let haptics = Haptics::new(config, Arc::new(Recorder(Mutex::new(tx))));
+        haptics.play(Cue::SessionStart);
+        haptics.play(Cue::WorkEnd);
+        drop(haptics);
+        let pulses: Vec<Duration> = rx.iter().collect();
+        assert_eq!(pulses, [30, 50].map(Duration::from_millis));
+    }
+}
@@ -0,0 +1,164 @@
+// filepath: src/heatmap.rs
+//! Module rendering the session history of a Pomodoro timer application as a year-at-a-glance
+//! grid in the terminal, one cell per day.
+use std::io::{self, IsTerminal};
+
+use chrono::{Datelike, Days, Local, NaiveDate};
+use colored::Colorize;
+
+use crate::{
+    aggregate,
+    config::Cli,
+    history::{History, history_path},
+    report::ReportError,
+};
+
+/// Number of weeks shown, the last one containing today.
+const WEEKS: u64 = 53;
+/// Characters of the intensity levels when colors are disabled, from no pomodoro to the most.
+const LEVEL_CHARS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+/// Colors of the intensity levels from 1 to 4, as RGB components.
+const LEVEL_COLORS: [(u8, u8, u8); 4] =
+    [(155, 233, 168), (64, 196, 99), (48, 161, 78), (33, 110, 57)];
+
+/// Runs the `heatmap` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli) -> i32 {
+    let result = history_path(cli.history.as_deref(), cli.name.as_deref())
+        .ok_or(ReportError::NoHistory)
+        .and_then(|path| Ok(History::new(path).sessions()?));
+    match result {
+        Ok(records) => {
+            let today = Local::now().date_naive();
+            let first = first_day(today);
+            let per_day = aggregate::pomodoros_per_day(&records, first, today);
+            let color =
+                io::stdout().is_terminal() && colored::control::SHOULD_COLORIZE.should_colorize();
+            print!("{}", render(&per_day, color));
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// The Monday starting the first week shown when `today` is in the last one.
+fn first_day(today: NaiveDate) -> NaiveDate {
+    let monday = today - Days::new(u64::from(today.weekday().num_days_from_monday()));
+    monday - Days::new((WEEKS - 1) * 7)
+}
+
+/// Intensity level of a day from 0 to 4, relative to the busiest day.
+fn level(count: u64, max: u64) -> usize {
+    if count == 0 {
+        0
+    } else {
+        (count * 4).div_ceil(max.max(1)).clamp(1, 4) as usize
+    }
+}
+
+fn cell(level: usize, color: bool) -> String {
+    if !color {
+        return LEVEL_CHARS[level].to_string();
+    }
+    match level {
+        0 => "·".bright_black().to_string(),
+        level => {
+            let (red, green, blue) = LEVEL_COLORS[level - 1];
+            "█".truecolor(red, green, blue).to_string()
+        }
+    }
+}
+
+/// Renders `per_day`, which must start on a Monday, as rows of weekdays and columns of weeks, with
+/// the months above and a legend below.
+pub fn render(per_day: &[(NaiveDate, u64)], color: bool) -> String {
+    let Some((first, _)) = per_day.first() else {
+        return String::new();
+    };
+    let max = per_day.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let weeks = per_day.len().div_ceil(7);
+
+    // Month names start above the first week holding the 1st of the month, if there is room.
+    let mut months = vec![' '; weeks + 3];
+    for week in 0..weeks {
+        let monday = *first + Days::new(week as u64 * 7);
+        let starts_month = week == 0 || (0..7).any(|day| (monday + Days::new(day)).day() == 1);
+        let month = (monday + Days::new(6)).format("%b").to_string();
+        if starts_month && months[week..].iter().take(4).all(|c| *c == ' ') {
+            for (offset, c) in month.chars().enumerate() {
+                months[week + offset] = c;
+            }
+        }
+    }
+    let mut output = format!("    {}\n", months.iter().collect::<String>().trim_end());
+
+    for weekday in 0..7 {
+        let label = match weekday {
+            0 => "Mon",
+            2 => "Wed",
+            4 => "Fri",
+            _ => "",
+        };
+        let mut line = format!("{:<4}", label);
+        for week in 0..weeks {
+            if let Some((_, count)) = per_day.get(week * 7 + weekday) {
+                line.push_str(&cell(level(*count, max), color));
+            }
+        }
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    let legend: String = (0..5).map(|level| cell(level, color)).collect();
+    let total: u64 = per_day.iter().map(|(_, count)| count).sum();
+    output.push_str(&format!(
+        "\n    Less {} More    {} pomodoros since {}\n",
+        legend,
+        total,
+        first.format("%B %-d, %Y")
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn shows_a_year_of_whole_weeks() {
+        // 2026-10-14 is a Wednesday.
+        let first = first_day(date(2026, 10, 14));
+        assert_eq!(first, date(2025, 10, 13));
+        assert_eq!(first.weekday().num_days_from_monday(), 0);
+    }
+
+    #[test]
+    fn levels_are_relative_to_the_busiest_day() {
+        assert_eq!(level(0, 8), 0);
+        assert_eq!(level(1, 8), 1);
+        assert_eq!(level(3, 8), 2);
+        assert_eq!(level(8, 8), 4);
+    }
+
+    #[test]
+    fn renders_weekdays_as_rows() {
+        let first = date(2026, 3, 2);
+        let per_day: Vec<(NaiveDate, u64)> = (0..10)
+            .map(|day| (first + Days::new(day), if day == 8 { 4 } else { 0 }))
+            .collect();
+        let grid = render(&per_day, false);
+        let lines: Vec<&str> = grid.lines().collect();
+        assert_eq!(lines[0], "    Mar");
+        assert_eq!(lines[1], "Mon ··");
+        assert_eq!(lines[2], "    ·█");
+        assert_eq!(lines[3], "Wed ··");
+        assert_eq!(lines[4], "    ·");
+        assert!(grid.ends_with("Less ·░▒▓█ More    4 pomodoros since March 2, 2026\n"));
+    }
+}
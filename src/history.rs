@@ -0,0 +1,1021 @@
+// filepath: src/history.rs
+//! Module keeping the history of the sessions of a Pomodoro timer application, as one JSON record
+//! per line, so that summaries can cover more than the current run.
+//!
+//! The first line of the file gives the version of its records, those without one being from the
+//! first version. Records from older versions are migrated as they are read, and the file is
+//! upgraded when the timer starts.
+//!
+//! Each session has an ID, from the time it started, so that it can be edited after the fact. The
+//! edits are kept with it.
+//!
+//! An encrypted history gives in its first line where its key comes from, its records being
+//! sealed one per line as well.
+use std::{
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeZone};
+use rustodoro_client::protocol;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SessionType,
+    crypt::{self, CryptError, Encryption},
+    events::{Event, SessionOutcome},
+};
+
+/// Environment variable overriding the location of the history file.
+pub const HISTORY_ENV: &str = "RUSTODORO_HISTORY";
+/// Version of the session records, given by the first line of the history file.
+pub const SCHEMA_VERSION: u32 = 4;
+
+type Object = serde_json::Map<String, serde_json::Value>;
+
+/// Migrations of the records, the one at index `i` upgrading them from version `i + 1`.
+const MIGRATIONS: [fn(&mut Object); SCHEMA_VERSION as usize - 1] = [
+    // 2: the time spent paused is recorded.
+    |record| {
+        record.entry("paused").or_insert(0.into());
+    },
+    // 3: the reasons of the pauses are recorded.
+    |record| {
+        record
+            .entry("pause_reasons")
+            .or_insert(serde_json::Value::Array(Vec::new()));
+    },
+    // 4: the sessions have an ID.
+    |record| {
+        let id = record
+            .get("started_at")
+            .and_then(|started_at| started_at.as_str())
+            .and_then(|started_at| DateTime::parse_from_rfc3339(started_at).ok())
+            .map(|started_at| session_id(&started_at))
+            .unwrap_or_default();
+        record.entry("id").or_insert(id.into());
+    },
+];
+
+/// The ID of the session started at `started_at`: the milliseconds since the epoch, in base 36.
+pub fn session_id<Tz: TimeZone>(started_at: &DateTime<Tz>) -> String {
+    let mut millis = started_at.timestamp_millis().max(0) as u64;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(char::from_digit((millis % 36) as u32, 36).expect("digits are below 36"));
+        millis /= 36;
+        if millis == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+/// The first line of the history file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Header {
+    schema: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption: Option<Encryption>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl From<&SessionType> for SessionKind {
+    fn from(session: &SessionType) -> Self {
+        match session.kind {
+            protocol::SessionKind::Work => SessionKind::Work,
+            protocol::SessionKind::ShortBreak => SessionKind::ShortBreak,
+            protocol::SessionKind::LongBreak => SessionKind::LongBreak,
+        }
+    }
+}
+
+/// Why a session was paused, given with a key right after the pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    Call,
+    Bio,
+    Interruption,
+}
+
+impl PauseReason {
+    pub const ALL: [PauseReason; 3] = [
+        PauseReason::Call,
+        PauseReason::Bio,
+        PauseReason::Interruption,
+    ];
+
+    /// The key giving the reason after the pause.
+    pub fn key(&self) -> char {
+        match self {
+            PauseReason::Call => 'c',
+            PauseReason::Bio => 'b',
+            PauseReason::Interruption => 'i',
+        }
+    }
+
+    /// The reason given by `key`, if any.
+    pub fn from_key(key: char) -> Option<Self> {
+        PauseReason::ALL
+            .into_iter()
+            .find(|reason| reason.key() == key.to_ascii_lowercase())
+    }
+
+    /// How `count` pauses for the reason read, e.g. "2 bio breaks".
+    pub fn count(&self, count: usize) -> String {
+        let (one, many) = match self {
+            PauseReason::Call => ("call", "calls"),
+            PauseReason::Bio => ("bio break", "bio breaks"),
+            PauseReason::Interruption => ("interruption", "interruptions"),
+        };
+        format!("{} {}", count, if count == 1 { one } else { many })
+    }
+}
+
+impl fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PauseReason::Call => write!(f, "Call"),
+            PauseReason::Bio => write!(f, "Bio"),
+            PauseReason::Interruption => write!(f, "Interruption"),
+        }
+    }
+}
+
+/// How the work session went against its intent, given with a key right after it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Review {
+    Done,
+    Partial,
+    Missed,
+}
+
+impl Review {
+    pub const ALL: [Review; 3] = [Review::Done, Review::Partial, Review::Missed];
+
+    /// The key giving the review after the session.
+    pub fn key(&self) -> char {
+        match self {
+            Review::Done => 'y',
+            Review::Partial => 'h',
+            Review::Missed => 'n',
+        }
+    }
+
+    /// The review given by `key`, if any.
+    pub fn from_key(key: char) -> Option<Self> {
+        Review::ALL
+            .into_iter()
+            .find(|review| review.key() == key.to_ascii_lowercase())
+    }
+}
+
+impl fmt::Display for Review {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Review::Done => write!(f, "✓ done"),
+            Review::Partial => write!(f, "partial"),
+            Review::Missed => write!(f, "✗ missed"),
+        }
+    }
+}
+
+/// A change made to a session after the fact, by `rustodoro history edit`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Task { from: Option<String>, to: String },
+    Tag { tag: String },
+    Void,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Task {
+                from: Some(from),
+                to,
+            } => write!(f, "task {:?} → {:?}", from, to),
+            Change::Task { from: None, to } => write!(f, "task {:?}", to),
+            Change::Tag { tag } => write!(f, "tagged {:?}", tag),
+            Change::Void => write!(f, "voided"),
+        }
+    }
+}
+
+/// A change of the audit trail of a session, and when it was made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edit {
+    pub at: DateTime<Local>,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// A session which ended, as stored in the history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// See [`session_id`].
+    pub id: String,
+    pub started_at: DateTime<Local>,
+    pub kind: SessionKind,
+    /// Planned duration, in seconds.
+    pub planned: u64,
+    /// Time counted by the countdown, pauses excluded, in seconds.
+    pub elapsed: u64,
+    pub outcome: SessionOutcome,
+    pub pauses: u32,
+    pub resets: u32,
+    /// Time spent paused, in seconds.
+    #[serde(default)]
+    pub paused: u64,
+    /// Focus score out of 100 of the work sessions, see [`Interruptions::focus_score`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus: Option<u64>,
+    /// What the user worked on, given with `--task`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// Why the session was paused, for the pauses a reason was given for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pause_reasons: Vec<PauseReason>,
+    /// The one thing the work session was meant for, told as it started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<String>,
+    /// How the work session went against its intent, told as it ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<Review>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether the session was voided after the fact, counting for nothing since.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub voided: bool,
+    /// The changes made to the session after the fact, the first one first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<Edit>,
+}
+
+impl SessionRecord {
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs(self.elapsed)
+    }
+
+    pub fn interruptions(&self) -> Interruptions {
+        Interruptions {
+            pauses: self.pauses,
+            resets: self.resets,
+            paused: Duration::from_secs(self.paused),
+        }
+    }
+}
+
+/// How often and how long a session was interrupted.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Interruptions {
+    pub pauses: u32,
+    pub resets: u32,
+    pub paused: Duration,
+}
+
+impl Interruptions {
+    /// A score out of 100 of how focused a work session was: each pause takes 10 points off, each
+    /// reset 15, and each minute paused one more, up to 30. A session voided by quitting only
+    /// keeps the share of it which ran.
+    pub fn focus_score(
+        &self,
+        outcome: SessionOutcome,
+        elapsed: Duration,
+        planned: Duration,
+    ) -> u64 {
+        let paused = (self.paused.as_secs() / 60).min(30);
+        let penalty = 10 * u64::from(self.pauses) + 15 * u64::from(self.resets) + paused;
+        let score = 100u64.saturating_sub(penalty);
+        if outcome == SessionOutcome::Interrupted {
+            let ran = elapsed.as_secs_f64() / planned.as_secs_f64().max(1.0);
+            (score as f64 * ran.min(1.0)).round() as u64
+        } else {
+            score
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("cannot access history file {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error(
+        "invalid record at line {line} of history file {}: {source}, see `rustodoro history doctor`",
+        path.display()
+    )]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error(
+        "history file {} is from a newer rustodoro (version {version}), update it to read the history",
+        path.display()
+    )]
+    Newer { path: PathBuf, version: u32 },
+    #[error("cannot read history file {}: {source}", path.display())]
+    Crypt { path: PathBuf, source: CryptError },
+}
+
+/// A line of a history file holding a record, counted from 1.
+pub struct Line {
+    pub number: usize,
+    pub record: Result<SessionRecord, serde_json::Error>,
+}
+
+/// The content of a history file.
+pub struct Parsed {
+    /// Version of the records, before their migration.
+    pub version: u32,
+    pub lines: Vec<Line>,
+    pub blank: usize,
+}
+
+/// Reads the content of a history file, migrating its records to the current version.
+pub fn parse(content: &str) -> Parsed {
+    let mut parsed = Parsed {
+        version: 1,
+        lines: Vec::new(),
+        blank: 0,
+    };
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            parsed.blank += 1;
+            continue;
+        }
+        if parsed.lines.is_empty()
+            && let Ok(header) = serde_json::from_str::<Header>(line)
+        {
+            parsed.version = header.schema;
+            continue;
+        }
+        let migrations = MIGRATIONS
+            .get(parsed.version.saturating_sub(1) as usize..)
+            .unwrap_or_default();
+        let record = serde_json::from_str::<Object>(line).and_then(|mut object| {
+            for migrate in migrations {
+                migrate(&mut object);
+            }
+            serde_json::from_value(object.into())
+        });
+        parsed.lines.push(Line {
+            number: index + 1,
+            record,
+        });
+    }
+    parsed
+}
+
+fn header(encryption: Option<&Encryption>) -> String {
+    let header = Header {
+        schema: SCHEMA_VERSION,
+        encryption: encryption.cloned(),
+    };
+    serde_json::to_string(&header).expect("headers always serialize") + "\n"
+}
+
+/// How a history is encrypted, from its `first` line.
+fn encryption_of(first: &str) -> Option<Encryption> {
+    serde_json::from_str::<Header>(first)
+        .ok()
+        .and_then(|header| header.encryption)
+}
+
+/// Returns the default location of the history file, following the XDG base directory convention,
+/// `history-<name>.jsonl` for the timer called `name`.
+pub fn default_history_path(name: Option<&str>) -> Option<PathBuf> {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    let file = match name {
+        Some(name) => format!("history-{}.jsonl", name),
+        None => "history.jsonl".to_string(),
+    };
+    Some(data_dir.join("rustodoro").join(file))
+}
+
+/// Returns the history file to use: `flag`, `RUSTODORO_HISTORY` or the default location, where
+/// each named timer has a history of its own.
+pub fn history_path(flag: Option<&Path>, name: Option<&str>) -> Option<PathBuf> {
+    flag.map(Path::to_path_buf)
+        .or_else(|| env::var_os(HISTORY_ENV).map(PathBuf::from))
+        .or_else(|| default_history_path(name))
+}
+
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        History { path }
+    }
+
+    fn io_error(&self, source: io::Error) -> HistoryError {
+        HistoryError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    fn crypt_error(&self, source: CryptError) -> HistoryError {
+        HistoryError::Crypt {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// The line of `record`, sealed with the key of `encryption` when there is one.
+    fn line(
+        &self,
+        record: &SessionRecord,
+        encryption: Option<&Encryption>,
+    ) -> Result<String, HistoryError> {
+        let line = serde_json::to_string(record).expect("records always serialize");
+        match encryption {
+            Some(encryption) => encryption
+                .cipher()
+                .and_then(|cipher| cipher.seal(&line))
+                .map_err(|e| self.crypt_error(e)),
+            None => Ok(line),
+        }
+    }
+
+    /// How the history file is encrypted, as its first line gives it, none when it is not.
+    pub fn encryption(&self) -> Result<Option<Encryption>, HistoryError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(self.io_error(e)),
+        };
+        let mut first = String::new();
+        BufReader::new(file)
+            .read_line(&mut first)
+            .map_err(|e| self.io_error(e))?;
+        Ok(encryption_of(&first))
+    }
+
+    /// Gets the key of an encrypted history, asking for its passphrase if needed, and checks it
+    /// against the key check of the header, or on the records for a header written without one.
+    /// The key is then known for the rest of the run.
+    pub fn unlock(&self) -> Result<(), HistoryError> {
+        if self.encryption()?.is_some() {
+            self.read()?;
+        }
+        Ok(())
+    }
+
+    /// Appends `record` to the history file, creating it if needed.
+    pub fn append(&self, record: &SessionRecord) -> Result<(), HistoryError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| self.io_error(e))?;
+        }
+        let mut line = self.line(record, self.encryption()?.as_ref())?;
+        line.push('\n');
+        // A new history starts with its version.
+        if fs::metadata(&self.path).map_or(true, |metadata| metadata.len() == 0) {
+            line.insert_str(0, &header(None));
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| self.io_error(e))
+    }
+
+    /// Replaces the records with `records`, through a file renamed over the history so that a
+    /// failure leaves it as it was. An encrypted history stays encrypted with the same key.
+    pub fn rewrite(&self, records: &[SessionRecord]) -> Result<(), HistoryError> {
+        self.rewrite_with(records, self.encryption()?.as_ref())
+    }
+
+    /// Replaces the records with `records`, encrypted following `encryption`.
+    pub fn rewrite_with(
+        &self,
+        records: &[SessionRecord],
+        encryption: Option<&Encryption>,
+    ) -> Result<(), HistoryError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| self.io_error(e))?;
+        }
+        let mut content = header(encryption);
+        for record in records {
+            content.push_str(&self.line(record, encryption)?);
+            content.push('\n');
+        }
+        let temporary = self.path.with_extension("jsonl.tmp");
+        fs::write(&temporary, content)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|e| self.io_error(e))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The content of the history file, a missing file meaning an empty history. The records of
+    /// an encrypted history are decrypted.
+    pub fn read(&self) -> Result<String, HistoryError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(String::new()),
+            Err(e) => return Err(self.io_error(e)),
+        };
+        let first = content.lines().next().unwrap_or_default();
+        let Some(encryption) = encryption_of(first) else {
+            return Ok(content);
+        };
+        let cipher = encryption.cipher().map_err(|e| self.crypt_error(e))?;
+        content
+            .lines()
+            .map(|line| {
+                let line = if crypt::is_sealed(line) {
+                    cipher.open(line).map_err(|e| self.crypt_error(e))?
+                } else {
+                    line.to_string()
+                };
+                Ok(line + "\n")
+            })
+            .collect()
+    }
+
+    /// The sessions which count, those voided left out.
+    pub fn sessions(&self) -> Result<Vec<SessionRecord>, HistoryError> {
+        let mut records = self.load()?;
+        records.retain(|record| !record.voided);
+        Ok(records)
+    }
+
+    /// Applies `change` to the session with the ID `id`, writing the history again when it tells
+    /// it changed the session, and returns the session. None when no session has that ID.
+    pub fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut SessionRecord) -> bool,
+    ) -> Result<Option<SessionRecord>, HistoryError> {
+        let mut records = self.load()?;
+        let Some(record) = records.iter_mut().find(|record| record.id == id) else {
+            return Ok(None);
+        };
+        let changed = change(record);
+        let record = record.clone();
+        if changed {
+            self.rewrite(&records)?;
+        }
+        Ok(Some(record))
+    }
+
+    /// Reads all the records, migrated to the current version.
+    pub fn load(&self) -> Result<Vec<SessionRecord>, HistoryError> {
+        let content = self.read()?;
+        let parsed = parse(&content);
+        if parsed.version > SCHEMA_VERSION {
+            return Err(HistoryError::Newer {
+                path: self.path.clone(),
+                version: parsed.version,
+            });
+        }
+        parsed
+            .lines
+            .into_iter()
+            .map(|line| {
+                line.record.map_err(|source| HistoryError::Parse {
+                    path: self.path.clone(),
+                    line: line.number,
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// Writes the history file again with its records migrated to the current version, when
+    /// they are from an older one, which is returned.
+    pub fn upgrade(&self) -> Result<Option<u32>, HistoryError> {
+        let version = parse(&self.read()?).version;
+        if version >= SCHEMA_VERSION || !self.path.exists() {
+            return Ok(None);
+        }
+        self.rewrite(&self.load()?)?;
+        Ok(Some(version))
+    }
+}
+
+/// Turns the events of a run into session records, appending them to the history as sessions end.
+pub struct Recorder {
+    history: Option<History>,
+    task: Option<String>,
+    current: Option<SessionRecord>,
+    records: Vec<SessionRecord>,
+    /// Whether the work sessions given an intent wait for their review before being kept.
+    reviews: bool,
+    /// Records not kept yet, the first waiting for its review.
+    held: Vec<SessionRecord>,
+}
+
+impl Recorder {
+    /// Without a history, the records are only kept in memory. Work sessions are labelled with
+    /// `task`.
+    pub fn new(history: Option<History>, task: Option<String>) -> Self {
+        Recorder {
+            history,
+            task,
+            current: None,
+            records: Vec::new(),
+            reviews: false,
+            held: Vec::new(),
+        }
+    }
+
+    /// Holds the work sessions given an intent until they are reviewed, the next work session
+    /// starts or the run ends, the sessions ending meanwhile waiting too so that the order holds.
+    pub fn with_reviews(mut self) -> Self {
+        self.reviews = true;
+        self
+    }
+
+    /// Records the events until the event bus is dropped, and returns the records of the run with
+    /// the first failure to write the history, if any.
+    pub fn run(mut self, events: Receiver<Event>) -> (Vec<SessionRecord>, Option<HistoryError>) {
+        let mut failure = None;
+        for event in events {
+            if let Err(e) = self.handle(&event) {
+                failure.get_or_insert(e);
+            }
+        }
+        if let Err(e) = self.finish() {
+            failure.get_or_insert(e);
+        }
+        (self.records, failure)
+    }
+
+    /// Keeps the records held for a review which did not come, once the run is over.
+    pub fn finish(&mut self) -> Result<(), HistoryError> {
+        for record in std::mem::take(&mut self.held) {
+            self.keep(record)?;
+        }
+        Ok(())
+    }
+
+    /// Appends `record` to the history and to the records of the run.
+    fn keep(&mut self, record: SessionRecord) -> Result<(), HistoryError> {
+        if let Some(history) = &self.history {
+            history.append(&record)?;
+        }
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Takes the records of the sessions which ended since the last call.
+    pub fn take(&mut self) -> Vec<SessionRecord> {
+        std::mem::take(&mut self.records)
+    }
+
+    pub fn handle(&mut self, event: &Event) -> Result<(), HistoryError> {
+        match event {
+            Event::SessionStarted {
+                session, duration, ..
+            } => {
+                if session.is_work() {
+                    self.finish()?;
+                }
+                let started_at = Local::now();
+                self.current = Some(SessionRecord {
+                    id: session_id(&started_at),
+                    started_at,
+                    kind: session.into(),
+                    planned: duration.as_secs(),
+                    elapsed: 0,
+                    outcome: SessionOutcome::Interrupted,
+                    pauses: 0,
+                    resets: 0,
+                    paused: 0,
+                    focus: None,
+                    task: if session.is_work() {
+                        self.task.clone()
+                    } else {
+                        None
+                    },
+                    pause_reasons: Vec::new(),
+                    intent: None,
+                    review: None,
+                    tags: Vec::new(),
+                    voided: false,
+                    edits: Vec::new(),
+                });
+            }
+            Event::Paused { .. } => {
+                if let Some(record) = &mut self.current {
+                    record.pauses += 1;
+                }
+            }
+            Event::PauseReason { reason } => {
+                if let Some(record) = &mut self.current {
+                    record.pause_reasons.push(*reason);
+                }
+            }
+            Event::Intent { intent } => {
+                if let Some(record) = &mut self.current
+                    && record.kind == SessionKind::Work
+                {
+                    record.intent = Some(intent.clone());
+                }
+            }
+            Event::Resumed { paused, .. } => {
+                if let Some(record) = &mut self.current {
+                    record.paused += paused.as_secs();
+                }
+            }
+            Event::Reset => {
+                if let Some(record) = &mut self.current {
+                    record.resets += 1;
+                }
+            }
+            Event::Extended { by } => {
+                if let Some(record) = &mut self.current {
+                    record.planned += by.as_secs();
+                }
+            }
+            Event::TaskChanged { task } => {
+                self.task = Some(task.clone());
+                if let Some(record) = &mut self.current
+                    && record.kind == SessionKind::Work
+                {
+                    record.task = Some(task.clone());
+                }
+            }
+            Event::SessionEnded {
+                elapsed, outcome, ..
+            } => {
+                if let Some(mut record) = self.current.take() {
+                    record.elapsed = elapsed.as_secs();
+                    record.outcome = *outcome;
+                    if record.kind == SessionKind::Work {
+                        record.focus = Some(record.interruptions().focus_score(
+                            record.outcome,
+                            record.elapsed(),
+                            Duration::from_secs(record.planned),
+                        ));
+                    }
+                    let reviewed = self.reviews
+                        && record.kind == SessionKind::Work
+                        && record.intent.is_some()
+                        && record.outcome.is_done();
+                    if reviewed || !self.held.is_empty() {
+                        self.held.push(record);
+                    } else {
+                        self.keep(record)?;
+                    }
+                }
+            }
+            Event::Reviewed { review } => {
+                if let Some(record) = self.held.first_mut() {
+                    record.review = Some(*review);
+                }
+                self.finish()?;
+            }
+            Event::Planned { .. }
+            | Event::Tick { .. }
+            | Event::NextBreakSkipped
+            | Event::Acknowledged
+            | Event::Transition(_)
+            | Event::Resized { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn temp_history(name: &str) -> History {
+        let path = env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_file(&path);
+        History::new(path)
+    }
+
+    fn work_session(recorder: &mut Recorder, elapsed: Duration, outcome: SessionOutcome) {
+        let session = SessionType::work();
+        let events = [
+            Event::SessionStarted {
+                session: session.clone(),
+                cycle: 1,
+                duration: MINUTE * 25,
+            },
+            Event::Intent {
+                intent: "Draft the outline".to_string(),
+            },
+            Event::Paused { elapsed: MINUTE },
+            Event::PauseReason {
+                reason: PauseReason::Call,
+            },
+            Event::Resumed {
+                elapsed: MINUTE,
+                paused: MINUTE * 2,
+            },
+            Event::SessionEnded {
+                session,
+                elapsed,
+                outcome,
+            },
+        ];
+        for event in &events {
+            recorder.handle(event).unwrap();
+        }
+    }
+
+    #[test]
+    fn records_ended_sessions() {
+        let mut recorder = Recorder::new(None, None);
+        work_session(&mut recorder, MINUTE * 25, SessionOutcome::Completed);
+        let record = &recorder.records[0];
+        assert_eq!(record.kind, SessionKind::Work);
+        assert_eq!(record.planned, 25 * 60);
+        assert_eq!(record.elapsed(), MINUTE * 25);
+        assert_eq!(record.outcome, SessionOutcome::Completed);
+        assert_eq!(record.pauses, 1);
+        assert_eq!(record.resets, 0);
+        assert_eq!(record.paused, 2 * 60);
+        assert_eq!(record.pause_reasons, [PauseReason::Call]);
+        assert_eq!(record.intent.as_deref(), Some("Draft the outline"));
+        assert_eq!(record.focus, Some(88));
+    }
+
+    #[test]
+    fn holds_the_sessions_until_reviewed() {
+        let mut recorder = Recorder::new(None, None).with_reviews();
+        work_session(&mut recorder, MINUTE * 25, SessionOutcome::Completed);
+        let short_break = SessionType::short_break();
+        for event in [
+            Event::SessionStarted {
+                session: short_break.clone(),
+                cycle: 1,
+                duration: MINUTE * 5,
+            },
+            Event::SessionEnded {
+                session: short_break,
+                elapsed: MINUTE * 5,
+                outcome: SessionOutcome::Completed,
+            },
+        ] {
+            recorder.handle(&event).unwrap();
+        }
+        assert!(recorder.take().is_empty());
+        recorder
+            .handle(&Event::Reviewed {
+                review: Review::Partial,
+            })
+            .unwrap();
+        let records = recorder.take();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].review, Some(Review::Partial));
+        assert_eq!(records[1].kind, SessionKind::ShortBreak);
+        // Without a review, the next work session lets the last one go.
+        work_session(&mut recorder, MINUTE * 25, SessionOutcome::Completed);
+        assert!(recorder.take().is_empty());
+        recorder
+            .handle(&Event::SessionStarted {
+                session: SessionType::work(),
+                cycle: 2,
+                duration: MINUTE * 25,
+            })
+            .unwrap();
+        assert_eq!(recorder.take()[0].review, None);
+    }
+
+    #[test]
+    fn focus_score_weighs_the_interruptions() {
+        let planned = MINUTE * 25;
+        let score = |pauses, resets, paused, outcome, elapsed| {
+            Interruptions {
+                pauses,
+                resets,
+                paused,
+            }
+            .focus_score(outcome, elapsed, planned)
+        };
+        assert_eq!(score(0, 0, MINUTE, SessionOutcome::Completed, planned), 99);
+        assert_eq!(
+            score(2, 1, MINUTE * 5, SessionOutcome::Completed, planned),
+            60
+        );
+        // Long pauses take 30 points off at most.
+        assert_eq!(
+            score(1, 0, MINUTE * 90, SessionOutcome::Completed, planned),
+            60
+        );
+        assert_eq!(
+            score(9, 9, MINUTE, SessionOutcome::FinishedEarly, planned),
+            0
+        );
+        // Quitting voids the session, which keeps the share that ran.
+        assert_eq!(
+            score(
+                0,
+                0,
+                Duration::ZERO,
+                SessionOutcome::Interrupted,
+                MINUTE * 10
+            ),
+            40
+        );
+    }
+
+    #[test]
+    fn history_keeps_records_across_runs() {
+        let history = temp_history("across-runs.jsonl");
+        let mut recorder = Recorder::new(Some(History::new(history.path.clone())), None);
+        work_session(&mut recorder, MINUTE * 25, SessionOutcome::Completed);
+        let mut recorder = Recorder::new(Some(History::new(history.path.clone())), None);
+        work_session(&mut recorder, MINUTE * 3, SessionOutcome::Interrupted);
+
+        let records = history.load().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].outcome, SessionOutcome::Completed);
+        assert_eq!(records[1], recorder.records[0]);
+    }
+
+    #[test]
+    fn migrates_the_old_records() {
+        let history = temp_history("old.jsonl");
+        fs::create_dir_all(history.path.parent().unwrap()).unwrap();
+        let old = r#"{"started_at":"2026-03-14T09:00:00+01:00","kind":"work","planned":1500,"elapsed":1500,"outcome":"completed","pauses":0,"resets":0}"#;
+        fs::write(&history.path, format!("{}\n", old)).unwrap();
+        let migrated = &history.load().unwrap()[0];
+        assert_eq!(migrated.paused, 0);
+        assert_eq!(migrated.id, "mmq1ce80");
+        assert_eq!(history.upgrade().unwrap(), Some(1));
+        let content = fs::read_to_string(&history.path).unwrap();
+        assert!(content.starts_with(&format!("{{\"schema\":{}}}\n", SCHEMA_VERSION)));
+        assert_eq!(history.upgrade().unwrap(), None);
+
+        fs::write(&history.path, "{\"schema\":99}\n").unwrap();
+        assert!(matches!(
+            history.load(),
+            Err(HistoryError::Newer { version: 99, .. })
+        ));
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn encrypted_history_keeps_its_key() {
+        let history = temp_history("encrypted.jsonl");
+        let mut recorder = Recorder::new(
+            Some(History::new(history.path.clone())),
+            Some("Acme merger memo".to_string()),
+        );
+        work_session(&mut recorder, MINUTE * 25, SessionOutcome::Completed);
+        let encryption = crypt::tests::encryption("correct horse");
+        history
+            .rewrite_with(&history.load().unwrap(), Some(&encryption))
+            .unwrap();
+        work_session(&mut recorder, MINUTE * 25, SessionOutcome::Completed);
+        history.rewrite(&history.load().unwrap()).unwrap();
+
+        let content = fs::read_to_string(&history.path).unwrap();
+        assert!(!content.contains("Acme"));
+        assert_eq!(history.encryption().unwrap(), Some(encryption));
+        assert_eq!(history.load().unwrap(), recorder.records);
+    }
+
+    #[test]
+    fn missing_history_is_empty() {
+        assert!(temp_history("missing.jsonl").load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_record_reports_its_line() {
+        let history = temp_history("invalid.jsonl");
+        fs::create_dir_all(history.path.parent().unwrap()).unwrap();
+        fs::write(&history.path, "\n{\"oops\": 1}\n").unwrap();
+        assert!(matches!(
+            history.load(),
+            Err(HistoryError::Parse { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+// filepath: src/icon.rs
+//! Module drawing the icon of the running session of a Pomodoro timer application, for the
+//! buttons of a Stream Deck: the minutes left in large digits on the color of the session, grey
+//! while paused, over a bar of the time left. The icon is encoded as a PNG of its own, stored
+//! without compression, so that no image library is needed.
+use rustodoro_client::protocol::{SessionKind, Snapshot};
+
+/// Width and height of the icon, in pixels: twice the 72 pixels of the keys, for the sharp ones.
+pub const SIZE: usize = 144;
+
+type Rgb = [u8; 3];
+
+const WORK: Rgb = [217, 83, 79];
+const SHORT_BREAK: Rgb = [92, 184, 92];
+const LONG_BREAK: Rgb = [66, 139, 202];
+/// Background while paused or between two sessions.
+const IDLE: Rgb = [85, 85, 85];
+const INK: Rgb = [255, 255, 255];
+
+/// Height of the bar of the time left, at the bottom.
+const BAR: usize = 12;
+/// Widest digits, in pixels per dot of the font.
+const MAX_SCALE: usize = 11;
+
+/// Glyphs of 5 by 7 dots, a row per byte, the highest of the 5 bits being the leftmost dot.
+const FONT: [(char, [u8; 7]); 11] = [
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+];
+
+/// A square of `SIZE` pixels.
+struct Canvas {
+    pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    fn new(background: Rgb) -> Self {
+        Canvas {
+            pixels: vec![background; SIZE * SIZE],
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..(y + height).min(SIZE) {
+            for column in x..(x + width).min(SIZE) {
+                self.pixels[row * SIZE + column] = color;
+            }
+        }
+    }
+
+    /// Draws `text` centered above the bar, as large as it fits.
+    fn text(&mut self, text: &str, color: Rgb) {
+        let glyphs: Vec<&[u8; 7]> = text
+            .chars()
+            .filter_map(|c| FONT.iter().find(|(glyph, _)| *glyph == c))
+            .map(|(_, rows)| rows)
+            .collect();
+        if glyphs.is_empty() {
+            return;
+        }
+        // A dot of space between the glyphs, and two around the text.
+        let dots = glyphs.len() * 6 - 1;
+        let scale = ((SIZE - 8) / (dots + 4)).min(MAX_SCALE);
+        let left = (SIZE - dots * scale) / 2;
+        let top = (SIZE - BAR - 7 * scale) / 2;
+        for (index, rows) in glyphs.iter().enumerate() {
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0x10 >> column) != 0 {
+                        let x = left + (index * 6 + column) * scale;
+                        self.fill(x, top + row * scale, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The pixels as RGB bytes, row after row.
+    fn bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flatten().copied().collect()
+    }
+}
+
+/// The icon of `status` as a PNG.
+pub fn render(status: &Snapshot) -> Vec<u8> {
+    let Some(session) = &status.session else {
+        let mut canvas = Canvas::new(IDLE);
+        canvas.text("--", INK);
+        return png(SIZE, SIZE, &canvas.bytes());
+    };
+    let color = match session.kind {
+        SessionKind::Work => WORK,
+        SessionKind::ShortBreak => SHORT_BREAK,
+        SessionKind::LongBreak => LONG_BREAK,
+    };
+    let mut canvas = Canvas::new(if session.paused { IDLE } else { color });
+    canvas.text(&session.remaining_secs.div_ceil(60).to_string(), INK);
+    let left = session.remaining_secs as usize * SIZE / session.duration_secs.max(1) as usize;
+    canvas.fill(0, SIZE - BAR, SIZE, BAR, [0, 0, 0]);
+    canvas.fill(
+        0,
+        SIZE - BAR,
+        left,
+        BAR,
+        if session.paused { color } else { INK },
+    );
+    png(SIZE, SIZE, &canvas.bytes())
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// The RGB `pixels` of an image of `width` by `height` as a PNG, their zlib stream made of stored
+/// blocks.
+fn png(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in pixels.chunks(width * 3) {
+        // No filter.
+        raw.push(0);
+        raw.extend(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = raw.chunks(u16::MAX as usize);
+    let count = blocks.len();
+    for (index, block) in blocks.enumerate() {
+        zlib.push(u8::from(index + 1 == count));
+        let length = block.len() as u16;
+        zlib.extend(length.to_le_bytes());
+        zlib.extend((!length).to_le_bytes());
+        zlib.extend(block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, then the default compression, filtering and no interlacing.
+    header.extend([8, 2, 0, 0, 0]);
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib);
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use rustodoro_client::protocol::Session;
+
+    use super::*;
+
+    /// The chunks of `png`, checking their CRC.
+    fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+            assert_eq!(crc32fast::hash(&rest[4..8 + length]), crc);
+            chunks.push((
+                String::from_utf8(rest[4..8].to_vec()).unwrap(),
+                rest[8..8 + length].to_vec(),
+            ));
+            rest = &rest[12 + length..];
+        }
+        chunks
+    }
+
+    /// The pixels of an icon made by [`png`], read back from its stored blocks.
+    fn decode(png: &[u8]) -> Vec<Rgb> {
+        let (_, zlib) = chunks(png).remove(1);
+        let mut raw = Vec::new();
+        let mut rest = &zlib[2..zlib.len() - 4];
+        while !rest.is_empty() {
+            let length = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+            raw.extend(&rest[5..5 + length]);
+            rest = &rest[5 + length..];
+        }
+        assert_eq!(adler32(&raw).to_be_bytes(), zlib[zlib.len() - 4..]);
+        raw.chunks(SIZE * 3 + 1)
+            .flat_map(|row| row[1..].chunks(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]))
+            .collect()
+    }
+
+    fn status(remaining_secs: u64, paused: bool) -> Snapshot {
+        Snapshot {
+            session: Some(Session {
+                kind: SessionKind::Work,
+                cycle: 1,
+                cycles: 4,
+                duration_secs: 1500,
+                remaining_secs,
+                paused,
+                plan_secs: None,
+            }),
+            ..Snapshot::default()
+        }
+    }
+
+    #[test]
+    fn encodes_a_valid_png() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        let png = render(&status(754, false));
+        let kinds: Vec<String> = chunks(&png).into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+        let (_, header) = &chunks(&png)[0];
+        assert_eq!(header[..8], [0, 0, 0, 144, 0, 0, 0, 144]);
+        assert_eq!(decode(&png).len(), SIZE * SIZE);
+    }
+
+    #[test]
+    fn draws_the_minutes_left_and_the_bar() {
+        let pixels = decode(&render(&status(754, false)));
+        assert_eq!(pixels[0], WORK);
+        // 13 minutes, rounded up as the countdown shows them, drawn in the middle.
+        let middle = &pixels[(SIZE / 2 - 10) * SIZE..(SIZE / 2 + 10) * SIZE];
+        assert!(middle.contains(&INK));
+        // Half of the session is left.
+        let bar = &pixels[(SIZE - 1) * SIZE..];
+        assert_eq!(
+            bar.iter().filter(|pixel| **pixel == INK).count(),
+            754 * SIZE / 1500
+        );
+
+        let paused = decode(&render(&status(754, true)));
+        assert_eq!(paused[0], IDLE);
+        assert_eq!(paused[(SIZE - 1) * SIZE], WORK);
+        assert_eq!(decode(&render(&Snapshot::default()))[0], IDLE);
+    }
+}
@@ -0,0 +1,182 @@
+// filepath: src/idle.rs
+//! Module measuring how long the keyboard and mouse of a Pomodoro timer application have been left
+//! alone, so that a work session the user walked away from near its end can finish early and the
+//! break start right away.
+use std::{process::Command, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::config::AutoFinishConfig;
+
+/// How long a measure of the idle time is trusted before measuring again.
+const RECHECK: Duration = Duration::from_secs(10);
+
+/// How long the user has not touched the keyboard nor the mouse.
+pub trait IdleClock: Send {
+    /// `None` when it cannot be measured, e.g. on Wayland.
+    fn idle(&self) -> Option<Duration>;
+}
+
+/// The running desktop: `xprintidle` on X11, the HID system on macOS.
+pub struct Os;
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl IdleClock for Os {
+    #[cfg(target_os = "macos")]
+    fn idle(&self) -> Option<Duration> {
+        parse_ioreg(&output("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn idle(&self) -> Option<Duration> {
+        parse_xprintidle(&output("xprintidle", &[])?)
+    }
+}
+
+/// The idle time printed by `xprintidle`, in milliseconds.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn parse_xprintidle(output: &str) -> Option<Duration> {
+    output.trim().parse().ok().map(Duration::from_millis)
+}
+
+/// The idle time from the `"HIDIdleTime" = 1234567890` line of `ioreg`, in nanoseconds.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_ioreg(output: &str) -> Option<Duration> {
+    output
+        .lines()
+        .find_map(|line| line.split_once("\"HIDIdleTime\" = "))
+        .and_then(|(_, nanos)| nanos.trim().parse().ok())
+        .map(Duration::from_nanos)
+}
+
+struct Measure {
+    clock: Box<dyn IdleClock>,
+    /// The idle time measured, and when.
+    last: Option<(Instant, Option<Duration>)>,
+}
+
+/// Finishes the work sessions early when the user left in their last minutes.
+pub struct AutoFinish {
+    config: AutoFinishConfig,
+    measure: Mutex<Measure>,
+}
+
+impl AutoFinish {
+    pub fn new(config: AutoFinishConfig, clock: impl IdleClock + 'static) -> Self {
+        AutoFinish {
+            config,
+            measure: Mutex::new(Measure {
+                clock: Box::new(clock),
+                last: None,
+            }),
+        }
+    }
+
+    /// Whether the idle time can be measured on this desktop.
+    pub fn is_available(&self) -> bool {
+        self.measure.lock().unwrap().clock.idle().is_some()
+    }
+
+    /// The idle time at `now`, measured at most every 10 seconds.
+    fn idle(&self, now: Instant) -> Option<Duration> {
+        let mut measure = self.measure.lock().unwrap();
+        if let Some((at, idle)) = measure.last
+            && now.duration_since(at) < RECHECK
+        {
+            return idle.map(|idle| idle + now.duration_since(at));
+        }
+        let idle = measure.clock.idle();
+        measure.last = Some((now, idle));
+        idle
+    }
+
+    /// How long the user has been away, when a work session with `remaining` time left is to
+    /// finish early at `now`.
+    pub fn away(&self, remaining: Duration, now: Instant) -> Option<Duration> {
+        if remaining > Duration::from_secs(self.config.within * 60) {
+            return None;
+        }
+        self.idle(now)
+            .filter(|idle| *idle >= Duration::from_secs(self.config.idle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::AtomicU32, atomic::Ordering};
+
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    /// Idle for the given time, counting the measures.
+    struct Fake {
+        idle: Option<Duration>,
+        measures: Arc<AtomicU32>,
+    }
+
+    impl IdleClock for Fake {
+        fn idle(&self) -> Option<Duration> {
+            self.measures.fetch_add(1, Ordering::SeqCst);
+            self.idle
+        }
+    }
+
+    #[test]
+    fn reads_the_idle_time() {
+        assert_eq!(
+            parse_xprintidle("15230\n"),
+            Some(Duration::from_millis(15230))
+        );
+        assert_eq!(parse_xprintidle("couldn't open display"), None);
+        let ioreg = "    | |   \"HIDIdleTime\" = 2500000000\n    | |   \"HIDKind\" = 1\n";
+        assert_eq!(parse_ioreg(ioreg), Some(Duration::from_millis(2500)));
+        assert_eq!(parse_ioreg(""), None);
+    }
+
+    #[test]
+    fn finishes_only_near_the_end_once_away_long_enough() {
+        let measures = Arc::new(AtomicU32::new(0));
+        let auto_finish = AutoFinish::new(
+            AutoFinishConfig {
+                idle: 120,
+                within: 5,
+            },
+            Fake {
+                idle: Some(MINUTE * 3),
+                measures: Arc::clone(&measures),
+            },
+        );
+        let start = Instant::now();
+        assert_eq!(auto_finish.away(MINUTE * 6, start), None);
+        assert_eq!(measures.load(Ordering::SeqCst), 0);
+        assert_eq!(auto_finish.away(MINUTE * 5, start), Some(MINUTE * 3));
+        // The last measure still holds, the user being away for longer since.
+        assert_eq!(
+            auto_finish.away(MINUTE * 4, start + SECOND * 5),
+            Some(MINUTE * 3 + SECOND * 5)
+        );
+        assert_eq!(measures.load(Ordering::SeqCst), 1);
+
+        let auto_finish = AutoFinish::new(
+            AutoFinishConfig {
+                idle: 300,
+                within: 5,
+            },
+            Fake {
+                idle: Some(MINUTE * 3),
+                measures: Arc::new(AtomicU32::new(0)),
+            },
+        );
+        assert_eq!(auto_finish.away(MINUTE, start), None);
+    }
+}
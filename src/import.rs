@@ -0,0 +1,461 @@
+// filepath: src/import.rs
+//! Module importing the sessions recorded by other Pomodoro apps into the history of a Pomodoro
+//! timer application, so that years of prior data carry over. The foreign records are mapped to
+//! session records, and those overlapping a session already in the history are skipped, which
+//! makes importing the same file twice harmless.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::Deserialize;
+
+use crate::{
+    config::Cli,
+    events::SessionOutcome,
+    history::{
+        HISTORY_ENV, History, HistoryError, SessionKind, SessionRecord, history_path, session_id,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+    #[error("cannot read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid record at line {line} of {}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// The app a file to import was exported from.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// The JSON statistics of Pomotroid
+    Pomotroid,
+    /// The CSV export of Flow
+    Flow,
+    /// The CSV detailed report of Toggl Track
+    TogglCsv,
+}
+
+/// A record which cannot be read, at a line of the file counted from 1.
+type Invalid = (usize, String);
+
+/// Runs the `import` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, format: Format, file: &Path, dry_run: bool) -> i32 {
+    match import(cli, format, file, dry_run) {
+        Ok((imported, skipped)) => {
+            let verb = if dry_run { "Would import" } else { "Imported" };
+            println!(
+                "{} {} sessions, {} skipped as overlapping the history.",
+                verb, imported, skipped
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn import(
+    cli: &Cli,
+    format: Format,
+    file: &Path,
+    dry_run: bool,
+) -> Result<(usize, usize), ImportError> {
+    let path =
+        history_path(cli.history.as_deref(), cli.name.as_deref()).ok_or(ImportError::NoHistory)?;
+    let content = fs::read_to_string(file).map_err(|source| ImportError::Read {
+        path: file.to_path_buf(),
+        source,
+    })?;
+    let foreign = parse(format, &content).map_err(|(line, message)| ImportError::Parse {
+        path: file.to_path_buf(),
+        line,
+        message,
+    })?;
+    let history = History::new(path);
+    let mut records = history.load()?;
+    let (imported, skipped) = merge(&mut records, foreign);
+    if !dry_run && imported > 0 {
+        history.rewrite(&records)?;
+    }
+    Ok((imported, skipped))
+}
+
+/// The sessions of a file exported in `format`.
+pub fn parse(format: Format, content: &str) -> Result<Vec<SessionRecord>, Invalid> {
+    match format {
+        Format::Pomotroid => parse_pomotroid(content),
+        Format::Flow => parse_flow(content),
+        Format::TogglCsv => parse_toggl(content),
+    }
+}
+
+/// When `record` ends.
+fn end(record: &SessionRecord) -> DateTime<Local> {
+    record.started_at + record.elapsed()
+}
+
+/// Adds the `foreign` records which overlap neither a record of `records` nor one added before,
+/// keeping `records` in chronological order. Returns how many were added and skipped.
+pub fn merge(records: &mut Vec<SessionRecord>, foreign: Vec<SessionRecord>) -> (usize, usize) {
+    let (mut imported, mut skipped) = (0, 0);
+    for record in foreign {
+        let overlaps = records.iter().any(|existing| {
+            record.started_at == existing.started_at
+                || (record.started_at < end(existing) && existing.started_at < end(&record))
+        });
+        if overlaps {
+            skipped += 1;
+        } else {
+            records.push(record);
+            imported += 1;
+        }
+    }
+    records.sort_by_key(|record| record.started_at);
+    (imported, skipped)
+}
+
+/// A session imported whole, the other apps recording what ran rather than what was planned.
+fn record(
+    started_at: DateTime<Local>,
+    kind: SessionKind,
+    duration: Duration,
+    task: Option<String>,
+) -> SessionRecord {
+    SessionRecord {
+        id: session_id(&started_at),
+        started_at,
+        kind,
+        planned: duration.as_secs(),
+        elapsed: duration.as_secs(),
+        outcome: SessionOutcome::Completed,
+        pauses: 0,
+        resets: 0,
+        paused: 0,
+        focus: None,
+        task: task.filter(|task| !task.trim().is_empty()),
+        pause_reasons: Vec::new(),
+        intent: None,
+        review: None,
+        tags: Vec::new(),
+        voided: false,
+        edits: Vec::new(),
+    }
+}
+
+fn local(datetime: NaiveDateTime) -> Result<DateTime<Local>, String> {
+    Local
+        .from_local_datetime(&datetime)
+        .earliest()
+        .ok_or_else(|| format!("{} does not exist in the local time zone", datetime))
+}
+
+/// A date and time such as `2026-03-14 09:30:00`, `2026-03-14 09:30` or in RFC 3339.
+fn parse_datetime(value: &str) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Local));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .ok_or_else(|| format!("invalid date and time `{}`", value))
+        .and_then(local)
+}
+
+/// A duration written `HH:MM:SS`, the hours going past 24 when needed.
+fn parse_hms(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{}`", value);
+    let parts: Vec<u64> = value
+        .trim()
+        .split(':')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    match parts[..] {
+        [hours, minutes, seconds] if minutes < 60 && seconds < 60 => {
+            Ok(Duration::from_secs(hours * 3600 + minutes * 60 + seconds))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// The fields of a CSV line, which may be quoted, doubling the quotes inside.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// A CSV file whose header names the columns.
+struct Csv<'a> {
+    header: Vec<String>,
+    /// The other lines with their number, counted from 1.
+    rows: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Csv<'a> {
+    fn new(content: &'a str) -> Self {
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| (index + 1, line));
+        let header = lines
+            .next()
+            .map(|(_, line)| csv_fields(line.trim_start_matches('\u{feff}')))
+            .unwrap_or_default();
+        Csv {
+            header,
+            rows: lines.collect(),
+        }
+    }
+
+    fn optional(&self, column: &str) -> Option<usize> {
+        self.header
+            .iter()
+            .position(|name| name.trim().eq_ignore_ascii_case(column))
+    }
+
+    fn column(&self, column: &str) -> Result<usize, Invalid> {
+        self.optional(column)
+            .ok_or_else(|| (1, format!("no {} column", column)))
+    }
+
+    /// The rows with their line number.
+    fn rows(&self) -> impl Iterator<Item = (usize, Vec<String>)> + '_ {
+        self.rows
+            .iter()
+            .map(|(line, content)| (*line, csv_fields(content)))
+    }
+}
+
+/// The field at `index`, empty when the row is too short.
+fn field(fields: &[String], index: usize) -> &str {
+    fields.get(index).map_or("", String::as_str)
+}
+
+/// Pomotroid statistics: an array of rounds, each with its `type` (`work`, `short-break` or
+/// `long-break`), its `timestamp` in milliseconds since the epoch and its `duration` in seconds.
+fn parse_pomotroid(content: &str) -> Result<Vec<SessionRecord>, Invalid> {
+    #[derive(Deserialize)]
+    struct Round {
+        #[serde(rename = "type")]
+        kind: String,
+        timestamp: i64,
+        duration: u64,
+    }
+    let rounds: Vec<Round> =
+        serde_json::from_str(content).map_err(|e| (e.line(), e.to_string()))?;
+    rounds
+        .into_iter()
+        .enumerate()
+        .map(|(index, round)| {
+            let invalid = |message: String| (1, format!("round {}: {}", index + 1, message));
+            let kind = match round.kind.as_str() {
+                "work" => SessionKind::Work,
+                "short-break" => SessionKind::ShortBreak,
+                "long-break" => SessionKind::LongBreak,
+                other => return Err(invalid(format!("unknown type `{}`", other))),
+            };
+            let started_at = Local
+                .timestamp_millis_opt(round.timestamp)
+                .single()
+                .ok_or_else(|| invalid(format!("invalid timestamp {}", round.timestamp)))?;
+            Ok(record(
+                started_at,
+                kind,
+                Duration::from_secs(round.duration),
+                None,
+            ))
+        })
+        .collect()
+}
+
+/// The Flow export: a `Type` (`Flow` or `Break`), `Start` and `End` column per session, and an
+/// optional `Title`.
+fn parse_flow(content: &str) -> Result<Vec<SessionRecord>, Invalid> {
+    let csv = Csv::new(content);
+    let (kind, start, end) = (
+        csv.column("Type")?,
+        csv.column("Start")?,
+        csv.column("End")?,
+    );
+    let title = csv.optional("Title");
+    csv.rows()
+        .map(|(line, fields)| {
+            let kind = match field(&fields, kind).trim() {
+                "Flow" => SessionKind::Work,
+                "Break" => SessionKind::ShortBreak,
+                other => return Err((line, format!("unknown session type `{}`", other))),
+            };
+            let start = parse_datetime(field(&fields, start)).map_err(|e| (line, e))?;
+            let end = parse_datetime(field(&fields, end)).map_err(|e| (line, e))?;
+            let duration = (end - start)
+                .to_std()
+                .map_err(|_| (line, "the session ends before it starts".to_string()))?;
+            let task = title
+                .filter(|_| kind == SessionKind::Work)
+                .map(|title| field(&fields, title).to_string());
+            Ok(record(start, kind, duration, task))
+        })
+        .collect()
+}
+
+/// The Toggl Track detailed report: a time entry per line with its `Start date`, `Start time` and
+/// `Duration`, the `Description` being the task, or the `Project` when there is none.
+fn parse_toggl(content: &str) -> Result<Vec<SessionRecord>, Invalid> {
+    let csv = Csv::new(content);
+    let description = csv.column("Description")?;
+    let project = csv.column("Project")?;
+    let (date, time, duration) = (
+        csv.column("Start date")?,
+        csv.column("Start time")?,
+        csv.column("Duration")?,
+    );
+    csv.rows()
+        .map(|(line, fields)| {
+            let date = field(&fields, date);
+            let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| (line, format!("invalid date `{}`", date)))?;
+            let time = field(&fields, time);
+            let time = NaiveTime::parse_from_str(time.trim(), "%H:%M:%S")
+                .map_err(|_| (line, format!("invalid time `{}`", time)))?;
+            let started_at = local(date.and_time(time)).map_err(|e| (line, e))?;
+            let duration = parse_hms(field(&fields, duration)).map_err(|e| (line, e))?;
+            let task = [description, project]
+                .into_iter()
+                .map(|index| field(&fields, index))
+                .find(|task| !task.trim().is_empty());
+            Ok(record(
+                started_at,
+                SessionKind::Work,
+                duration,
+                task.map(str::to_string),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 3, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_the_csv_fields() {
+        assert_eq!(
+            csv_fields(r#"a,"b, c","say ""hi""",,"#),
+            ["a", "b, c", "say \"hi\"", "", ""]
+        );
+        assert_eq!(
+            parse_hms("26:05:09"),
+            Ok(Duration::from_secs(26 * 3600 + 309))
+        );
+        assert!(parse_hms("1:60:00").is_err());
+    }
+
+    #[test]
+    fn reads_the_time_entries_of_toggl() {
+        let content =
+            "\u{feff}User,Project,Description,Start date,Start time,End date,End time,Duration
+Me,Rustodoro,\"Write the docs, again\",2026-03-14,09:00:00,2026-03-14,09:25:00,00:25:00
+Me,Rustodoro,,2026-03-14,10:00:00,2026-03-14,10:50:00,00:50:00
+";
+        let records = parse(Format::TogglCsv, content).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].started_at, at(14, 9, 0));
+        assert_eq!(records[0].elapsed(), MINUTE * 25);
+        assert_eq!(records[0].task.as_deref(), Some("Write the docs, again"));
+        assert_eq!(records[1].task.as_deref(), Some("Rustodoro"));
+        assert_eq!(
+            parse(Format::TogglCsv, "Description,Start date\n").map(|records| records.len()),
+            Err((1, "no Project column".to_string()))
+        );
+    }
+
+    #[test]
+    fn reads_the_sessions_of_flow_and_pomotroid() {
+        let content = "Type,Start,End,Title
+Flow,2026-03-14 09:00:00,2026-03-14 09:25:00,Review
+Break,2026-03-14 09:25,2026-03-14 09:30,
+Nap,2026-03-14 09:30,2026-03-14 09:40,
+";
+        assert_eq!(
+            parse(Format::Flow, content),
+            Err((4, "unknown session type `Nap`".to_string()))
+        );
+        let records = parse(Format::Flow, &content.replace("Nap", "Flow")).unwrap();
+        let kinds: Vec<SessionKind> = records.iter().map(|record| record.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                SessionKind::Work,
+                SessionKind::ShortBreak,
+                SessionKind::Work
+            ]
+        );
+        assert_eq!(records[0].task.as_deref(), Some("Review"));
+        assert_eq!(records[1].elapsed(), MINUTE * 5);
+
+        let timestamp = at(14, 9, 0).timestamp_millis();
+        let content = format!(
+            r#"[{{"type": "work", "timestamp": {}, "duration": 1500}},
+                {{"type": "long-break", "timestamp": {}, "duration": 900}}]"#,
+            timestamp,
+            timestamp + 1_500_000
+        );
+        let records = parse(Format::Pomotroid, &content).unwrap();
+        assert_eq!(records[0].started_at, at(14, 9, 0));
+        assert_eq!(records[1].kind, SessionKind::LongBreak);
+        assert_eq!(records[1].started_at, at(14, 9, 25));
+    }
+
+    #[test]
+    fn skips_the_sessions_overlapping_the_history() {
+        let work =
+            |hour, minute| record(at(14, hour, minute), SessionKind::Work, MINUTE * 25, None);
+        let mut records = vec![work(9, 0), work(11, 0)];
+        let (imported, skipped) = merge(
+            &mut records,
+            vec![work(9, 0), work(9, 10), work(10, 0), work(10, 20)],
+        );
+        assert_eq!((imported, skipped), (1, 3));
+        let starts: Vec<DateTime<Local>> = records.iter().map(|record| record.started_at).collect();
+        assert_eq!(starts, [at(14, 9, 0), at(14, 10, 0), at(14, 11, 0)]);
+    }
+}
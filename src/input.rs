@@ -0,0 +1,140 @@
+// filepath: src/input.rs
+//! Module reading the terminal events for a Pomodoro timer application on a thread of its own, so
+//! that waiting for them can be cut short: the command dispatcher blocks until a key is pressed or
+//! it is woken, e.g. when the run ends, rather than looking for the end of the run on a timer.
+//!
+//! The events are read one at a time, when one is waited for. An event read after its reader gave
+//! up waiting, e.g. as the run ended, goes to the next one rather than being lost.
+use std::{
+    io,
+    sync::{
+        Mutex, OnceLock,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::Instant,
+};
+
+use crossterm::event::{self, Event};
+
+/// What the reading thread and the wakers send.
+enum Message {
+    Read(io::Result<Event>),
+    Wake,
+}
+
+/// What the reader waits for.
+pub enum Input {
+    Event(Event),
+    /// The reader was woken.
+    Woken,
+}
+
+struct Queue {
+    /// Asks the thread to read the next event.
+    asks: Sender<()>,
+    messages: Receiver<Message>,
+    /// Whether an event was asked for and not received yet.
+    asked: bool,
+    /// Whether a wake came while waiting for an event only, kept for the next wait for an input.
+    woken: bool,
+}
+
+struct Reader {
+    queue: Mutex<Queue>,
+    wakes: Sender<Message>,
+}
+
+static READER: OnceLock<Reader> = OnceLock::new();
+
+fn reader() -> &'static Reader {
+    READER.get_or_init(|| {
+        let (asks, asked) = mpsc::channel();
+        let (wakes, messages) = mpsc::channel();
+        let events = wakes.clone();
+        thread::spawn(move || {
+            for () in asked {
+                if events.send(Message::Read(event::read())).is_err() {
+                    break;
+                }
+            }
+        });
+        Reader {
+            queue: Mutex::new(Queue {
+                asks,
+                messages,
+                asked: false,
+                woken: false,
+            }),
+            wakes,
+        }
+    })
+}
+
+/// The next input, a wake included when `wakes` is set, none once `deadline` passed.
+fn receive(deadline: Option<Instant>, wakes: bool) -> io::Result<Option<Input>> {
+    let mut queue = reader().queue.lock().unwrap();
+    if wakes && queue.woken {
+        queue.woken = false;
+        return Ok(Some(Input::Woken));
+    }
+    loop {
+        if !queue.asked {
+            // The thread only stops with the process, keeping the sender of the messages.
+            let _ = queue.asks.send(());
+            queue.asked = true;
+        }
+        let message = match deadline {
+            None => queue
+                .messages
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+            Some(deadline) => queue
+                .messages
+                .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+        };
+        match message {
+            Ok(Message::Read(read)) => {
+                queue.asked = false;
+                return read.map(|event| Some(Input::Event(event)));
+            }
+            Ok(Message::Wake) if wakes => return Ok(Some(Input::Woken)),
+            Ok(Message::Wake) => queue.woken = true,
+            Err(RecvTimeoutError::Timeout) => return Ok(None),
+            Err(RecvTimeoutError::Disconnected) => unreachable!("the reader keeps a sender"),
+        }
+    }
+}
+
+/// Waits for the next terminal event or to be woken.
+pub fn next() -> io::Result<Input> {
+    Ok(receive(None, true)?.expect("waiting without a deadline"))
+}
+
+/// Waits for the next terminal event, none once `deadline` passed when there is one. Wakes are
+/// kept for the next wait for an input.
+pub fn read(deadline: Option<Instant>) -> io::Result<Option<Event>> {
+    Ok(receive(deadline, false)?.map(|input| match input {
+        Input::Event(event) => event,
+        Input::Woken => unreachable!("the wakes are kept"),
+    }))
+}
+
+/// Wakes the wait for an input, or the next one when there is none.
+pub fn waker() -> Waker {
+    Waker {
+        wakes: reader().wakes.clone(),
+    }
+}
+
+/// Wakes the wait for an input as `input::waker` tells.
+#[derive(Clone)]
+pub struct Waker {
+    wakes: Sender<Message>,
+}
+
+impl Waker {
+    pub fn wake(&self) {
+        let _ = self.wakes.send(Message::Wake);
+    }
+}
@@ -0,0 +1,474 @@
+// filepath: src/journal.rs
+//! Module recording the commands received and the transitions of a run of a Pomodoro timer
+//! application to a journal file, and replaying them: `rustodoro replay <file>` drives the engine
+//! on a simulated clock with the recorded commands at their recorded times, to reproduce what
+//! happened, and tells where the replay departs from the recording.
+use std::{
+    fs::{self, File},
+    future,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    time::{self, Instant},
+};
+
+use crate::{
+    Command,
+    events::{Event, EventBus, SessionOutcome},
+    scheduler::Scheduler,
+    settings::Settings,
+};
+
+/// Version of the journal format.
+const FORMAT: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+    #[error("cannot access the journal {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{} is not a journal of rustodoro, line {line}: {message}", path.display())]
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// The settings a run depends on, written first in the journal. The ramps, the session sequences,
+/// the break jitter, the schedule scripts and the changes made from the settings menu are not
+/// recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Recorded {
+    work: u64,
+    short_break: u64,
+    long_break: u64,
+    cycles: u64,
+    require_ack: bool,
+    transition_pause: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    journal: u32,
+    settings: Recorded,
+}
+
+/// What happened at a time of the run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Command {
+        command: Command,
+    },
+    Transition {
+        what: String,
+    },
+    /// The user quit.
+    Quit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Line {
+    /// Milliseconds since the start of the run.
+    at_ms: u64,
+    #[serde(flatten)]
+    entry: Entry,
+}
+
+/// The journal being written during a run.
+#[derive(Clone)]
+pub struct Journal {
+    writer: Arc<Mutex<BufWriter<File>>>,
+    start: Instant,
+}
+
+impl Journal {
+    /// Creates the journal at `path`, the run starting now.
+    pub fn create(path: &Path, settings: &Settings) -> Result<Self, JournalError> {
+        let io_error = |source| JournalError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        let header = Header {
+            journal: FORMAT,
+            settings: Recorded {
+                work: settings.work_duration,
+                short_break: settings.short_break,
+                long_break: settings.long_break,
+                cycles: settings.cycles,
+                require_ack: settings.require_ack,
+                transition_pause: settings.transition_pause,
+            },
+        };
+        let line = serde_json::to_string(&header).expect("headers always serialize");
+        writeln!(writer, "{}", line)
+            .and_then(|_| writer.flush())
+            .map_err(io_error)?;
+        Ok(Journal {
+            writer: Arc::new(Mutex::new(writer)),
+            start: Instant::now(),
+        })
+    }
+
+    /// Writing is best effort, a journal must not stop the timer.
+    fn write(&self, at: Instant, entry: Entry) {
+        let line = Line {
+            at_ms: at.saturating_duration_since(self.start).as_millis() as u64,
+            entry,
+        };
+        let line = serde_json::to_string(&line).expect("entries always serialize");
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writeln!(writer, "{}", line).and_then(|_| writer.flush());
+    }
+
+    /// Records the commands going through, and returns their new receiver.
+    pub fn tap(&self, mut commands: UnboundedReceiver<Command>) -> UnboundedReceiver<Command> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let journal = self.clone();
+        thread::spawn(move || {
+            while let Some(command) = commands.blocking_recv() {
+                journal.write(
+                    Instant::now(),
+                    Entry::Command {
+                        command: command.clone(),
+                    },
+                );
+                if tx.send(command).is_err() {
+                    return;
+                }
+            }
+            journal.write(Instant::now(), Entry::Quit);
+        });
+        rx
+    }
+
+    /// Records the transitions emitted from now on, on a thread of its own.
+    pub fn follow(&self, events: &EventBus) {
+        let events = events.subscribe_timed();
+        let journal = self.clone();
+        thread::spawn(move || {
+            for (at, event) in events {
+                if let Some(what) = describe(&event) {
+                    journal.write(at, Entry::Transition { what });
+                }
+            }
+        });
+    }
+}
+
+fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}m{:02}s", seconds / 60, seconds % 60)
+}
+
+/// The transition `event` stands for, none for the ticks.
+fn describe(event: &Event) -> Option<String> {
+    let what = match event {
+        Event::SessionStarted {
+            session,
+            cycle,
+            duration,
+        } => format!("{} #{} started for {}", session, cycle, clock(*duration)),
+        Event::SessionEnded {
+            session,
+            elapsed,
+            outcome,
+        } => {
+            let outcome = match outcome {
+                SessionOutcome::Completed => "completed",
+                SessionOutcome::FinishedEarly => "finished early",
+                SessionOutcome::Skipped => "skipped",
+                SessionOutcome::Interrupted => "interrupted",
+            };
+            format!("{} {} after {}", session, outcome, clock(*elapsed))
+        }
+        Event::Paused { elapsed } => format!("paused at {}", clock(*elapsed)),
+        Event::PauseReason { reason } => format!("paused for: {}", reason),
+        Event::Intent { intent } => format!("intent: {:?}", intent),
+        Event::Reviewed { review } => format!("reviewed: {}", review),
+        Event::Resumed { elapsed, .. } => format!("resumed at {}", clock(*elapsed)),
+        Event::Reset => "reset".to_string(),
+        Event::NextBreakSkipped => "next break skipped".to_string(),
+        Event::Acknowledged => "acknowledged".to_string(),
+        Event::Extended { by } => format!("extended by {}", clock(*by)),
+        Event::TaskChanged { task } => format!("task set to {:?}", task),
+        Event::Planned { .. }
+        | Event::Tick { .. }
+        | Event::Transition(_)
+        | Event::Resized { .. } => return None,
+    };
+    Some(what)
+}
+
+/// Commands sent at given times, standing for the command dispatcher.
+///
+/// Once [`Script::quit_at`] is reached, the sender is dropped as if the command dispatcher had
+/// stopped. Without a quit time, the channel stays open forever.
+pub struct Script {
+    commands: Vec<(Duration, Command)>,
+    quit_at: Option<Duration>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Script {
+            commands: Vec::new(),
+            quit_at: None,
+        }
+    }
+
+    /// Schedules `command` to be sent at `time`. Commands must be scheduled in order.
+    pub fn at(mut self, time: Duration, command: Command) -> Self {
+        assert!(
+            self.commands.last().is_none_or(|(last, _)| *last <= time),
+            "commands must be scheduled in chronological order"
+        );
+        self.commands.push((time, command));
+        self
+    }
+
+    /// Simulates the user quitting at `time`.
+    pub fn quit_at(mut self, time: Duration) -> Self {
+        self.quit_at = Some(time);
+        self
+    }
+
+    /// Starts sending the commands, times being relative to now, and returns their receiver.
+    pub fn spawn(self) -> UnboundedReceiver<Command> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let start = Instant::now();
+        tokio::spawn(async move {
+            for (time, command) in self.commands {
+                if self.quit_at.is_some_and(|quit_at| quit_at <= time) {
+                    break;
+                }
+                time::sleep_until(start + time).await;
+                if tx.send(command).is_err() {
+                    return;
+                }
+            }
+            match self.quit_at {
+                Some(quit_at) => time::sleep_until(start + quit_at).await,
+                None => future::pending().await,
+            }
+            drop(tx);
+        });
+        rx
+    }
+}
+
+/// A journal read back.
+struct Recording {
+    settings: Recorded,
+    script: Script,
+    transitions: Vec<(Duration, String)>,
+}
+
+fn parse(path: &Path, content: &str) -> Result<Recording, JournalError> {
+    let invalid = |line: usize, message: String| JournalError::Invalid {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let (_, first) = lines
+        .next()
+        .ok_or_else(|| invalid(1, "empty file".to_string()))?;
+    let header: Header = serde_json::from_str(first).map_err(|e| invalid(1, e.to_string()))?;
+    if header.journal != FORMAT {
+        return Err(invalid(
+            1,
+            format!("unsupported journal version {}", header.journal),
+        ));
+    }
+    let mut recording = Recording {
+        settings: header.settings,
+        script: Script::new(),
+        transitions: Vec::new(),
+    };
+    for (index, line) in lines {
+        let line: Line =
+            serde_json::from_str(line).map_err(|e| invalid(index + 1, e.to_string()))?;
+        let at = Duration::from_millis(line.at_ms);
+        if matches!(line.entry, Entry::Command { .. })
+            && recording
+                .script
+                .commands
+                .last()
+                .is_some_and(|(last, _)| at < *last)
+        {
+            return Err(invalid(index + 1, "command out of order".to_string()));
+        }
+        match line.entry {
+            Entry::Command { command } => recording.script = recording.script.at(at, command),
+            Entry::Transition { what } => recording.transitions.push((at, what)),
+            Entry::Quit => recording.script = recording.script.quit_at(at),
+        }
+    }
+    Ok(recording)
+}
+
+/// Runs the engine with the settings and the commands of `recording` on the paused clock, and
+/// returns the transitions.
+async fn replay(recording: Recording) -> Vec<(Duration, String)> {
+    let settings = Settings {
+        work_duration: recording.settings.work,
+        short_break: recording.settings.short_break,
+        long_break: recording.settings.long_break,
+        cycles: recording.settings.cycles,
+        require_ack: recording.settings.require_ack,
+        transition_pause: recording.settings.transition_pause,
+        no_sound: true,
+        no_notify: true,
+        ..Settings::default()
+    };
+    let start = Instant::now();
+    let events = Arc::new(EventBus::new());
+    let subscription = events.subscribe_timed();
+    let result = match Scheduler::new(recording.script.spawn(), events, settings.into_shared()) {
+        Ok(mut scheduler) => scheduler.run().await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("warning: the replay stopped early: {}", e);
+    }
+    subscription
+        .try_iter()
+        .filter_map(|(at, event)| Some((at - start, describe(&event)?)))
+        .collect()
+}
+
+/// The first transition where `replayed` departs from `recorded`, by index.
+fn divergence(recorded: &[(Duration, String)], replayed: &[(Duration, String)]) -> Option<usize> {
+    let same = recorded
+        .iter()
+        .zip(replayed)
+        .take_while(|((_, recorded), (_, replayed))| recorded == replayed)
+        .count();
+    (same < recorded.len().max(replayed.len())).then_some(same)
+}
+
+fn run(file: &Path) -> Result<bool, JournalError> {
+    let io_error = |source| JournalError::Io {
+        path: file.to_path_buf(),
+        source,
+    };
+    let content = fs::read_to_string(file).map_err(io_error)?;
+    let recording = parse(file, &content)?;
+    let recorded = recording.transitions.clone();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .map_err(io_error)?;
+    let replayed = runtime.block_on(replay(recording));
+    for (at, what) in &replayed {
+        println!("{:>8}  {}", clock(*at), what);
+    }
+    match divergence(&recorded, &replayed) {
+        None => {
+            println!(
+                "\nThe replay matches the {} recorded transitions.",
+                recorded.len()
+            );
+            Ok(true)
+        }
+        Some(index) => {
+            let show = |transition: Option<&(Duration, String)>| match transition {
+                Some((at, what)) => format!("`{}` at {}", what, clock(*at)),
+                None => "nothing".to_string(),
+            };
+            println!(
+                "\nThe replay departs from the recording at transition {}: recorded {}, replayed {}.",
+                index + 1,
+                show(recorded.get(index)),
+                show(replayed.get(index))
+            );
+            Ok(false)
+        }
+    }
+}
+
+/// Runs the `replay` subcommand and returns the process exit code, 1 when the replay departs from
+/// the recording.
+pub fn run_command(file: &Path) -> i32 {
+    match run(file) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn journal(lines: &[String]) -> String {
+        let header = r#"{"journal":1,"settings":{"work":25,"short_break":5,"long_break":15,"cycles":4,"require_ack":false,"transition_pause":0}}"#;
+        format!("{}\n{}\n", header, lines.join("\n"))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replays_the_recorded_commands() {
+        let content = journal(&[
+            r#"{"at_ms":600000,"type":"command","command":"pause"}"#.to_string(),
+            r#"{"at_ms":660000,"type":"command","command":"resume"}"#.to_string(),
+            r#"{"at_ms":960000,"type":"command","command":"reset"}"#.to_string(),
+            r#"{"at_ms":1200000,"type":"quit"}"#.to_string(),
+        ]);
+        let recording = parse(Path::new("journal.jsonl"), &content).unwrap();
+        assert_eq!(recording.script.commands.len(), 3);
+        assert_eq!(recording.script.quit_at, Some(MINUTE * 20));
+
+        let replayed = replay(recording).await;
+        let what: Vec<&str> = replayed.iter().map(|(_, what)| what.as_str()).collect();
+        assert_eq!(
+            what,
+            [
+                "Work session #1 started for 25m00s",
+                "paused at 10m00s",
+                "resumed at 10m00s",
+                "reset",
+                "Work session interrupted after 4m00s",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_where_the_replay_departs() {
+        let at = |what: &str| (MINUTE, what.to_string());
+        let recorded = [at("reset"), at("paused at 1m00s")];
+        assert_eq!(divergence(&recorded, &recorded), None);
+        assert_eq!(divergence(&recorded, &[at("reset"), at("skip")]), Some(1));
+        assert_eq!(divergence(&recorded, &[at("reset")]), Some(1));
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_journal() {
+        let path = Path::new("journal.jsonl");
+        assert!(matches!(
+            parse(path, "{\"kind\":\"work\"}\n"),
+            Err(JournalError::Invalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse(path, &journal(&["{\"at_ms\":1}".to_string()])),
+            Err(JournalError::Invalid { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+// filepath: src/keychain.rs
+//! Module keeping the secrets of a Pomodoro timer application in the keychain of the system, out of
+//! its files: the Secret Service through `secret-tool` on Linux, the login keychain through
+//! `security` on macOS. Each secret is stored under the `rustodoro` service and an account naming
+//! it. There is no keychain elsewhere, e.g. on Windows, where using it fails with a clear error.
+use std::io;
+#[cfg(unix)]
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Service the secrets are stored under.
+#[cfg(unix)]
+const SERVICE: &str = "rustodoro";
+
+#[derive(Debug, thiserror::Error)]
+// Without a keychain program, only `Unsupported` is ever returned.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub enum KeychainError {
+    #[error("cannot run {program}, is it installed? {source}")]
+    Unavailable {
+        program: &'static str,
+        source: io::Error,
+    },
+    #[error("{program} failed: {message}")]
+    Failed {
+        program: &'static str,
+        message: String,
+    },
+    #[cfg(not(unix))]
+    #[error(
+        "the keychain of this system is not supported, only the Secret Service on Linux and the \
+         login keychain on macOS are"
+    )]
+    Unsupported,
+}
+
+/// Stores secrets by account.
+pub trait Keychain {
+    /// The secret of `account`, none when there is none.
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError>;
+    /// Stores `secret` for `account`, replacing the one there was.
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError>;
+    /// Removes the secret of `account`, returning whether there was one.
+    fn remove(&self, account: &str) -> Result<bool, KeychainError>;
+}
+
+/// The keychain of the running system.
+pub struct Os;
+
+#[cfg(target_os = "macos")]
+const PROGRAM: &str = "security";
+#[cfg(all(unix, not(target_os = "macos")))]
+const PROGRAM: &str = "secret-tool";
+
+/// Runs the keychain program with `args`, writing `input` to it, and returns its output when it
+/// succeeded, or its exit code and error output otherwise.
+#[cfg(unix)]
+fn run(args: &[&str], input: Option<&str>) -> Result<Result<String, (i32, String)>, KeychainError> {
+    let unavailable = |source| KeychainError::Unavailable {
+        program: PROGRAM,
+        source,
+    };
+    let mut child = Command::new(PROGRAM)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(unavailable)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.unwrap_or_default().as_bytes())
+            .map_err(unavailable)?;
+    }
+    let output = child.wait_with_output().map_err(unavailable)?;
+    if output.status.success() {
+        Ok(Ok(String::from_utf8_lossy(&output.stdout).into_owned()))
+    } else {
+        Ok(Err((
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )))
+    }
+}
+
+#[cfg(unix)]
+fn failed(message: String) -> KeychainError {
+    KeychainError::Failed {
+        program: PROGRAM,
+        message,
+    }
+}
+
+/// The secret printed by the keychain program, without the line ending it adds.
+#[cfg(unix)]
+fn secret(output: &str) -> Option<String> {
+    let secret = output.strip_suffix('\n').unwrap_or(output);
+    (!secret.is_empty()).then(|| secret.to_string())
+}
+
+impl Keychain for Os {
+    #[cfg(target_os = "macos")]
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        match run(
+            &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+            None,
+        )? {
+            Ok(output) => Ok(secret(&output)),
+            // The item could not be found.
+            Err((44, _)) => Ok(None),
+            Err((_, message)) => Err(failed(message)),
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        match run(&["lookup", "service", SERVICE, "account", account], None)? {
+            Ok(output) => Ok(secret(&output)),
+            // Nothing matches, which it does not explain.
+            Err((1, message)) if message.is_empty() => Ok(None),
+            Err((_, message)) => Err(failed(message)),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        run(
+            &[
+                "add-generic-password",
+                "-U",
+                "-s",
+                SERVICE,
+                "-a",
+                account,
+                "-w",
+                secret,
+            ],
+            None,
+        )?
+        .map(|_| ())
+        .map_err(|(_, message)| failed(message))
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        let label = format!("rustodoro {}", account);
+        // The secret is read from the standard input, out of sight of the other processes.
+        run(
+            &[
+                "store", "--label", &label, "service", SERVICE, "account", account,
+            ],
+            Some(secret),
+        )?
+        .map(|_| ())
+        .map_err(|(_, message)| failed(message))
+    }
+
+    #[cfg(not(unix))]
+    fn get(&self, _account: &str) -> Result<Option<String>, KeychainError> {
+        Err(KeychainError::Unsupported)
+    }
+
+    #[cfg(not(unix))]
+    fn set(&self, _account: &str, _secret: &str) -> Result<(), KeychainError> {
+        Err(KeychainError::Unsupported)
+    }
+
+    #[cfg(unix)]
+    fn remove(&self, account: &str) -> Result<bool, KeychainError> {
+        if self.get(account)?.is_none() {
+            return Ok(false);
+        }
+        #[cfg(target_os = "macos")]
+        let args = ["delete-generic-password", "-s", SERVICE, "-a", account];
+        #[cfg(not(target_os = "macos"))]
+        let args = ["clear", "service", SERVICE, "account", account];
+        run(&args, None)?
+            .map(|_| true)
+            .map_err(|(_, message)| failed(message))
+    }
+
+    #[cfg(not(unix))]
+    fn remove(&self, _account: &str) -> Result<bool, KeychainError> {
+        Err(KeychainError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_the_secret_printed() {
+        assert_eq!(secret("hunter2\n").as_deref(), Some("hunter2"));
+        assert_eq!(secret(" spaced \n").as_deref(), Some(" spaced "));
+        assert_eq!(secret("\n"), None);
+    }
+}
@@ -0,0 +1,376 @@
+// filepath: src/keymap.rs
+//! Module mapping the keys of a Pomodoro timer application to what they do. The defaults can be
+//! rebound in the configuration file, and the keyboard layout is taken into account: on an AZERTY
+//! keyboard, the keys of the number row count as their digits without Shift. Digits typed before a
+//! key are its count, as in Vim: `3+` extends the session by 3 minutes and `2s` skips two breaks.
+use std::{fmt, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::{command_line::MAX_EXTEND, types::Command};
+
+/// Layout of the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Azerty,
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Qwerty => write!(f, "qwerty"),
+            Layout::Azerty => write!(f, "azerty"),
+        }
+    }
+}
+
+/// Characters of the number row of an AZERTY keyboard without Shift, from 1 to 0.
+const AZERTY_NUMBER_ROW: [char; 10] = ['&', 'é', '"', '\'', '(', '-', 'è', '_', 'ç', 'à'];
+
+impl Layout {
+    /// The key `code` typed on this layout stands for.
+    fn read(self, code: KeyCode) -> KeyCode {
+        match (self, code) {
+            (Layout::Azerty, KeyCode::Char(c)) => AZERTY_NUMBER_ROW
+                .iter()
+                .position(|key| *key == c)
+                .and_then(|index| char::from_digit((index as u32 + 1) % 10, 10))
+                .map_or(code, KeyCode::Char),
+            _ => code,
+        }
+    }
+}
+
+/// What a key does.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    Acknowledge,
+    Pause,
+    PauseResume,
+    Reset,
+    Resume,
+    Skip,
+    /// Extends the running session by a minute, or by the count.
+    Extend,
+    /// Shows the time left in the zen display.
+    Reveal,
+    /// Opens and closes the settings menu.
+    Settings,
+    /// Reads a command typed after `:`.
+    CommandLine,
+    Quit,
+    /// Nothing, unbinding the key.
+    None,
+}
+
+impl KeyAction {
+    /// The command sent to the timer, if the action is one, with the `count` typed before. Only
+    /// extending and skipping take a count.
+    pub fn command(&self, count: Option<u32>) -> Option<Command> {
+        Some(match self {
+            KeyAction::Acknowledge => Command::Acknowledge,
+            KeyAction::Pause => Command::Pause,
+            KeyAction::PauseResume => Command::PauseResume,
+            KeyAction::Reset => Command::Reset,
+            KeyAction::Resume => Command::Resume,
+            KeyAction::Skip => count.map_or(Command::Skip, Command::SkipBreaks),
+            KeyAction::Extend => {
+                let minutes = u64::from(count.unwrap_or(1)).min(MAX_EXTEND);
+                Command::Extend(Duration::from_secs(minutes * 60))
+            }
+            KeyAction::Reveal => Command::Reveal,
+            KeyAction::Settings | KeyAction::CommandLine | KeyAction::Quit | KeyAction::None => {
+                return None;
+            }
+        })
+    }
+
+    /// How the controls line names the action.
+    fn label(&self) -> &'static str {
+        match self {
+            KeyAction::Acknowledge => "Acknowledge",
+            KeyAction::Pause => "Pause",
+            KeyAction::PauseResume => "Toggle",
+            KeyAction::Reset => "Reset",
+            KeyAction::Resume => "Resume",
+            KeyAction::Skip => "Skip break",
+            KeyAction::Extend => "Extend",
+            KeyAction::Reveal => "Time",
+            KeyAction::Settings => "Settings",
+            KeyAction::CommandLine => "Command",
+            KeyAction::Quit => "Quit",
+            KeyAction::None => "",
+        }
+    }
+}
+
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeyAction::Acknowledge => "acknowledge",
+            KeyAction::Pause => "pause",
+            KeyAction::PauseResume => "pause_resume",
+            KeyAction::Reset => "reset",
+            KeyAction::Resume => "resume",
+            KeyAction::Skip => "skip",
+            KeyAction::Extend => "extend",
+            KeyAction::Reveal => "reveal",
+            KeyAction::Settings => "settings",
+            KeyAction::CommandLine => "command_line",
+            KeyAction::Quit => "quit",
+            KeyAction::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The key named `name`: a character, or Space, Enter, Esc, Tab, Backspace and F1 to F12 in any
+/// case.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    Some(match name.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+    })
+}
+
+/// The count typed before a key, the digits typed so far.
+#[derive(Default)]
+pub struct Count(Option<u32>);
+
+impl Count {
+    pub fn push(&mut self, digit: u32) {
+        self.0 = Some(self.0.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+    }
+
+    /// The count typed, if not 0, starting over for the next key.
+    pub fn take(&mut self) -> Option<u32> {
+        self.0.take().filter(|count| *count > 0)
+    }
+}
+
+/// The keys bound to an action, in the order the controls line shows them.
+pub struct Keymap {
+    layout: Layout,
+    bindings: Vec<(KeyCode, KeyAction)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = [
+            (KeyCode::Char('p'), KeyAction::Pause),
+            (KeyCode::Char(' '), KeyAction::PauseResume),
+            (KeyCode::Char('r'), KeyAction::Resume),
+            (KeyCode::Char('s'), KeyAction::Skip),
+            (KeyCode::Char('x'), KeyAction::Reset),
+            (KeyCode::Char('+'), KeyAction::Extend),
+            (KeyCode::Char('t'), KeyAction::Reveal),
+            (KeyCode::Char('a'), KeyAction::Acknowledge),
+            (KeyCode::Enter, KeyAction::Acknowledge),
+            (KeyCode::Char('o'), KeyAction::Settings),
+            (KeyCode::Char(':'), KeyAction::CommandLine),
+            (KeyCode::Char('q'), KeyAction::Quit),
+            (KeyCode::Esc, KeyAction::Quit),
+        ];
+        Keymap {
+            layout: Layout::Qwerty,
+            bindings: bindings.into(),
+        }
+    }
+}
+
+impl Keymap {
+    /// The default keys on `layout`, rebound by `bindings` of key names to actions. Unknown key
+    /// names are skipped, the configuration having reported them.
+    pub fn new<'a>(
+        layout: Layout,
+        bindings: impl IntoIterator<Item = (&'a String, &'a KeyAction)>,
+    ) -> Self {
+        let mut keymap = Keymap {
+            layout,
+            ..Keymap::default()
+        };
+        for (name, action) in bindings {
+            let Some(code) = parse_key(name) else {
+                continue;
+            };
+            match keymap.bindings.iter_mut().find(|(key, _)| *key == code) {
+                Some((_, bound)) => *bound = action.clone(),
+                None => keymap.bindings.push((code, action.clone())),
+            }
+        }
+        keymap
+            .bindings
+            .retain(|(_, action)| *action != KeyAction::None);
+        keymap
+    }
+
+    /// What `key_event` does. Ctrl+C always quits.
+    pub fn get(&self, key_event: &KeyEvent) -> Option<&KeyAction> {
+        if key_event.modifiers == KeyModifiers::CONTROL && key_event.code == KeyCode::Char('c') {
+            return Some(&KeyAction::Quit);
+        }
+        let code = self.layout.read(key_event.code);
+        self.bindings
+            .iter()
+            .find(|(key, _)| *key == code)
+            .map(|(_, action)| action)
+    }
+
+    /// The command `key_event` sends to the timer, if any, typed after `count`.
+    pub fn command(&self, key_event: &KeyEvent, count: Option<u32>) -> Option<Command> {
+        self.get(key_event).and_then(|action| action.command(count))
+    }
+
+    /// The digit `key_event` adds to the count, unless the key is bound to an action.
+    pub fn digit(&self, key_event: &KeyEvent) -> Option<u32> {
+        if key_event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            || self.get(key_event).is_some()
+        {
+            return None;
+        }
+        match self.layout.read(key_event.code) {
+            KeyCode::Char(c) => c.to_digit(10),
+            _ => None,
+        }
+    }
+
+    /// The keys bound to `action`, e.g. "[o]".
+    pub fn keys(&self, action: &KeyAction) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| bound == action)
+            .map(|(key, _)| format!("[{}]", key))
+            .collect()
+    }
+
+    /// The line listing the keys of each action, e.g. "[p] Pause | [q]/[Esc]/[Ctrl+C] Quit".
+    pub fn controls(&self) -> String {
+        let mut actions: Vec<(&KeyAction, Vec<String>)> = Vec::new();
+        for (key, action) in &self.bindings {
+            let key = format!("[{}]", key);
+            match actions.iter_mut().find(|(bound, _)| *bound == action) {
+                Some((_, keys)) => keys.push(key),
+                None => actions.push((action, vec![key])),
+            }
+        }
+        // Quitting comes last, with Ctrl+C which cannot be rebound.
+        let quit = match actions
+            .iter()
+            .position(|(action, _)| **action == KeyAction::Quit)
+        {
+            Some(index) => actions.remove(index).1,
+            None => Vec::new(),
+        };
+        actions.push((&KeyAction::Quit, quit));
+        if let Some((_, keys)) = actions.last_mut() {
+            keys.push("[Ctrl+C]".to_string());
+        }
+        let controls: Vec<String> = actions
+            .iter()
+            .map(|(action, keys)| format!("{} {}", keys.join("/"), action.label()))
+            .collect();
+        controls.join(" | ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn names_the_keys() {
+        assert_eq!(parse_key("n"), Some(KeyCode::Char('n')));
+        assert_eq!(parse_key("Space"), Some(KeyCode::Char(' ')));
+        assert_eq!(parse_key("F5"), Some(KeyCode::F(5)));
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("shift"), None);
+    }
+
+    #[test]
+    fn rebinds_the_keys() {
+        let bindings = BTreeMap::from([
+            ("n".to_string(), KeyAction::Skip),
+            ("q".to_string(), KeyAction::None),
+            ("Space".to_string(), KeyAction::Pause),
+        ]);
+        let keymap = Keymap::new(Layout::Qwerty, &bindings);
+        assert_eq!(keymap.command(&key('n'), None), Some(Command::Skip));
+        assert_eq!(keymap.command(&key('s'), None), Some(Command::Skip));
+        assert_eq!(keymap.command(&key(' '), None), Some(Command::Pause));
+        assert_eq!(keymap.get(&key('q')), None);
+        assert_eq!(
+            keymap.get(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(&KeyAction::Quit)
+        );
+        assert_eq!(
+            keymap.controls(),
+            "[p]/[Space] Pause | [r] Resume | [s]/[n] Skip break | [x] Reset | [+] Extend | \
+             [t] Time | [a]/[Enter] Acknowledge | [o] Settings | [:] Command | [Esc]/[Ctrl+C] Quit"
+        );
+    }
+
+    #[test]
+    fn reads_the_number_row_of_azerty_as_digits() {
+        let bindings = BTreeMap::from([("1".to_string(), KeyAction::Reset)]);
+        let keymap = Keymap::new(Layout::Azerty, &bindings);
+        assert_eq!(keymap.command(&key('&'), None), Some(Command::Reset));
+        assert_eq!(keymap.command(&key('1'), None), Some(Command::Reset));
+        assert_eq!(Layout::Azerty.read(KeyCode::Char('à')), KeyCode::Char('0'));
+        assert_eq!(Layout::Qwerty.read(KeyCode::Char('&')), KeyCode::Char('&'));
+        assert_eq!(keymap.command(&key('p'), None), Some(Command::Pause));
+    }
+
+    #[test]
+    fn counts_the_digits_typed_before() {
+        let keymap = Keymap::new(Layout::Azerty, &BTreeMap::new());
+        let mut count = Count::default();
+        for c in ['1', '"'] {
+            count.push(keymap.digit(&key(c)).unwrap());
+        }
+        let count = count.take();
+        assert_eq!(count, Some(13));
+        assert_eq!(
+            keymap.command(&key('+'), count),
+            Some(Command::Extend(Duration::from_secs(13 * 60)))
+        );
+        assert_eq!(
+            keymap.command(&key('+'), None),
+            Some(Command::Extend(Duration::from_secs(60)))
+        );
+        assert_eq!(
+            keymap.command(&key('s'), Some(2)),
+            Some(Command::SkipBreaks(2))
+        );
+        assert_eq!(keymap.command(&key('s'), None), Some(Command::Skip));
+        assert_eq!(keymap.command(&key('x'), Some(2)), Some(Command::Reset));
+        assert_eq!(keymap.digit(&key('p')), None);
+
+        let bound = BTreeMap::from([("1".to_string(), KeyAction::Reset)]);
+        assert_eq!(Keymap::new(Layout::Qwerty, &bound).digit(&key('1')), None);
+        assert_eq!(Count::default().take(), None);
+    }
+}
@@ -0,0 +1,332 @@
+// filepath: src/leaderboard.rs
+//! Module sharing the pomodoros of the day between the participants of a team using a Pomodoro
+//! timer application, and ranking them on a daily leaderboard.
+//!
+//! The participants share a directory, e.g. a synced folder or a network share. Each timer keeps
+//! the entry of its user up to date in a file of its own there, holding the name, the day and the
+//! count of pomodoros, and the focus time when shared. Tasks are never shared.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+    time::Duration,
+};
+
+use chrono::{Local, NaiveDate};
+use rustodoro_client::protocol::SessionKind;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SessionType,
+    config::{self, Cli, LeaderboardConfig},
+    events::Event,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LeaderboardError {
+    #[error(transparent)]
+    Config(#[from] config::ConfigError),
+    #[error("no [leaderboard] table in the configuration file, see `rustodoro config init`")]
+    NotConfigured,
+    #[error("cannot read the leaderboard in {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("cannot publish to the leaderboard in {}: {source}", path.display())]
+    Publish { path: PathBuf, source: io::Error },
+}
+
+/// What a participant shares for a day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub day: NaiveDate,
+    pub pomodoros: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_minutes: Option<u64>,
+}
+
+/// Name of the file of the participant called `name`, which must contain an ASCII letter or
+/// digit.
+fn file_name(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{}.json", slug.trim_matches('-'))
+}
+
+/// Writes `entry` in `dir`, through a temporary file so that readers never see half of it.
+fn publish(dir: &Path, entry: &Entry) -> Result<(), LeaderboardError> {
+    let path = dir.join(file_name(&entry.name));
+    let error = |source| LeaderboardError::Publish {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let temporary = dir.join(format!(".{}.tmp", file_name(&entry.name)));
+    let content = serde_json::to_string(entry).map_err(|e| error(io::Error::other(e)))?;
+    fs::write(&temporary, content).map_err(error)?;
+    fs::rename(&temporary, &path).map_err(error)
+}
+
+/// The entries of `day` in `dir`, the best first. Files that cannot be read, e.g. while they are
+/// synced, are left out.
+fn load(dir: &Path, day: NaiveDate) -> Result<Vec<Entry>, LeaderboardError> {
+    let entries = fs::read_dir(dir).map_err(|source| LeaderboardError::Read {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    let mut entries: Vec<Entry> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| serde_json::from_str::<Entry>(&fs::read_to_string(path).ok()?).ok())
+        .filter(|entry| entry.day == day)
+        .collect();
+    entries.sort_by(|a, b| b.pomodoros.cmp(&a.pomodoros).then(a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// Renders the leaderboard of `day`, marking the entry of `you`.
+pub fn render(entries: &[Entry], day: NaiveDate, you: &str) -> String {
+    let mut rendered = format!("Leaderboard for {}\n\n", day.format("%A, %B %-d"));
+    if entries.is_empty() {
+        rendered.push_str("No pomodoros shared yet.\n");
+        return rendered;
+    }
+    let width = entries
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut rank = 0;
+    let mut previous = None;
+    for (index, entry) in entries.iter().enumerate() {
+        // Participants with the same count share the rank.
+        if previous != Some(entry.pomodoros) {
+            rank = index + 1;
+            previous = Some(entry.pomodoros);
+        }
+        let mut line = format!(
+            "{:>3}. {:<width$}  {:>3} pomodoro{}",
+            rank,
+            entry.name,
+            entry.pomodoros,
+            if entry.pomodoros == 1 { "" } else { "s" },
+            width = width
+        );
+        if let Some(minutes) = entry.focus_minutes {
+            line.push_str(&format!(", {}h{:02} focused", minutes / 60, minutes % 60));
+        }
+        if entry.name == you {
+            line.push_str("  (you)");
+        }
+        rendered.push_str(line.trim_end());
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// Runs the `leaderboard` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, day: Option<NaiveDate>) -> i32 {
+    let day = day.unwrap_or_else(|| Local::now().date_naive());
+    let result = config::resolve(cli)
+        .map_err(LeaderboardError::from)
+        .and_then(|effective| {
+            let config = effective
+                .leaderboard
+                .value
+                .ok_or(LeaderboardError::NotConfigured)?;
+            Ok((load(&config.dir, day)?, config.name.trim().to_string()))
+        });
+    match result {
+        Ok((entries, name)) => {
+            print!("{}", render(&entries, day, &name));
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// Keeps the entry of the user up to date while the timer runs.
+pub struct Publisher {
+    config: LeaderboardConfig,
+    day: NaiveDate,
+    pomodoros: u64,
+    focused: Duration,
+}
+
+impl Publisher {
+    /// A publisher starting from what the history holds for `day`.
+    pub fn new(
+        config: LeaderboardConfig,
+        day: NaiveDate,
+        pomodoros: u64,
+        focused: Duration,
+    ) -> Self {
+        Publisher {
+            config,
+            day,
+            pomodoros,
+            focused,
+        }
+    }
+
+    fn entry(&self) -> Entry {
+        Entry {
+            name: self.config.name.trim().to_string(),
+            day: self.day,
+            pomodoros: self.pomodoros,
+            focus_minutes: self
+                .config
+                .share_focus
+                .then_some(self.focused.as_secs() / 60),
+        }
+    }
+
+    /// The entry to publish after `event` on `today`, if it changed.
+    pub fn handle(&mut self, event: &Event, today: NaiveDate) -> Option<Entry> {
+        let Event::SessionEnded {
+            session:
+                SessionType {
+                    kind: SessionKind::Work,
+                    ..
+                },
+            elapsed,
+            outcome,
+        } = event
+        else {
+            return None;
+        };
+        if today != self.day {
+            self.day = today;
+            self.pomodoros = 0;
+            self.focused = Duration::ZERO;
+        }
+        self.focused += *elapsed;
+        if outcome.is_done() {
+            self.pomodoros += 1;
+        } else if !self.config.share_focus {
+            return None;
+        }
+        Some(self.entry())
+    }
+
+    /// Publishes the entry now and after each work session until the event bus is dropped, and
+    /// returns the first failure.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<LeaderboardError> {
+        let dir = self.config.dir.clone();
+        let mut first_failure = publish(&dir, &self.entry()).err();
+        for event in events {
+            if let Some(entry) = self.handle(&event, Local::now().date_naive())
+                && let Err(e) = publish(&dir, &entry)
+            {
+                first_failure.get_or_insert(e);
+            }
+        }
+        first_failure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::events::SessionOutcome;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    fn entry(name: &str, pomodoros: u64) -> Entry {
+        Entry {
+            name: name.to_string(),
+            day: day(2),
+            pomodoros,
+            focus_minutes: None,
+        }
+    }
+
+    fn ended(outcome: SessionOutcome) -> Event {
+        Event::SessionEnded {
+            session: SessionType::work(),
+            elapsed: Duration::from_secs(25 * 60),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn ranks_the_entries_of_the_day() {
+        let dir = env::temp_dir().join(format!("rustodoro-leaderboard-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        publish(&dir, &entry("Alice", 3)).unwrap();
+        publish(&dir, &entry("Bob Smith", 5)).unwrap();
+        publish(&dir, &entry("Chloé", 3)).unwrap();
+        publish(
+            &dir,
+            &Entry {
+                day: day(1),
+                ..entry("Dan", 9)
+            },
+        )
+        .unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let entries = load(&dir, day(2)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(file_name("Bob Smith"), "bob-smith.json");
+        assert_eq!(
+            render(&entries, day(2), "Alice"),
+            "\
+Leaderboard for Thursday, May 2
+
+  1. Bob Smith    5 pomodoros
+  2. Alice        3 pomodoros  (you)
+  2. Chloé        3 pomodoros
+"
+        );
+    }
+
+    #[test]
+    fn publishes_the_pomodoros_of_the_day() {
+        let config = LeaderboardConfig {
+            name: " Alice ".to_string(),
+            dir: PathBuf::new(),
+            publish: true,
+            share_focus: true,
+        };
+        let mut publisher = Publisher::new(config, day(1), 4, Duration::from_secs(100 * 60));
+
+        let published = publisher.handle(&ended(SessionOutcome::Completed), day(1));
+        assert_eq!(
+            published,
+            Some(Entry {
+                name: "Alice".to_string(),
+                day: day(1),
+                pomodoros: 5,
+                focus_minutes: Some(125),
+            })
+        );
+        // A new day starts from scratch.
+        let published = publisher
+            .handle(&ended(SessionOutcome::Interrupted), day(2))
+            .unwrap();
+        assert_eq!(
+            (published.pomodoros, published.focus_minutes),
+            (0, Some(25))
+        );
+        assert_eq!(publisher.handle(&Event::Reset, day(2)), None);
+    }
+}
@@ -0,0 +1,267 @@
+// filepath: src/led.rs
+//! Module showing the running session of a Pomodoro timer application on a status light, red
+//! during the work sessions and green during the breaks by default, so that the people around can
+//! tell whether the user is focusing.
+//!
+//! The light is a blink(1), driven through `blink1-tool`, or a color keyboard backlight of the Linux
+//! multicolor LED class. It is turned off when the timer quits.
+use std::{fs, io, path::PathBuf, process, sync::mpsc::Receiver};
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    config::{Color, LedConfig, LedDevice},
+    events::Event,
+};
+
+/// Where Linux lists the LEDs.
+const LEDS: &str = "/sys/class/leds";
+/// The color of a light turned off.
+const OFF: Color = Color::new(0, 0, 0);
+
+#[derive(Debug, thiserror::Error)]
+#[error("cannot light {device}: {source}")]
+pub struct LedError {
+    pub device: &'static str,
+    pub source: io::Error,
+}
+
+/// A light showing a color.
+pub trait Light: Send {
+    fn name(&self) -> &'static str;
+    fn show(&self, color: Color) -> io::Result<()>;
+}
+
+/// The first light of `device` found on this system, if any.
+pub fn detect(device: LedDevice) -> Option<Box<dyn Light>> {
+    let blink1 = || Blink1::plugged().then(|| Box::new(Blink1) as Box<dyn Light>);
+    let keyboard = || KeyboardBacklight::find().map(|light| Box::new(light) as Box<dyn Light>);
+    match device {
+        LedDevice::Auto => blink1().or_else(keyboard),
+        LedDevice::Blink1 => blink1(),
+        LedDevice::Keyboard => keyboard(),
+    }
+}
+
+/// A blink(1) USB light, through the `blink1-tool` command.
+pub struct Blink1;
+
+impl Blink1 {
+    fn plugged() -> bool {
+        process::Command::new("blink1-tool")
+            .arg("--list")
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("serialnum"))
+    }
+}
+
+impl Light for Blink1 {
+    fn name(&self) -> &'static str {
+        "the blink(1)"
+    }
+
+    fn show(&self, color: Color) -> io::Result<()> {
+        let rgb = format!("--rgb={}", color.to_string().trim_start_matches('#'));
+        let output = process::Command::new("blink1-tool")
+            .args(["-q", &rgb])
+            .output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+}
+
+/// A keyboard backlight of the multicolor LED class, written in sysfs. This needs write access to
+/// it, usually given by a udev rule.
+pub struct KeyboardBacklight {
+    dir: PathBuf,
+}
+
+impl KeyboardBacklight {
+    /// The first color keyboard backlight that can be written.
+    fn find() -> Option<Self> {
+        fs::read_dir(LEDS)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|dir| {
+                dir.file_name()
+                    .is_some_and(|name| name.to_string_lossy().contains("kbd_backlight"))
+                    && dir.join("multi_index").exists()
+                    && fs::OpenOptions::new()
+                        .write(true)
+                        .open(dir.join("multi_intensity"))
+                        .is_ok()
+            })
+            .map(|dir| KeyboardBacklight { dir })
+    }
+}
+
+impl Light for KeyboardBacklight {
+    fn name(&self) -> &'static str {
+        "the keyboard backlight"
+    }
+
+    fn show(&self, color: Color) -> io::Result<()> {
+        let max: u32 = fs::read_to_string(self.dir.join("max_brightness"))?
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "unreadable max_brightness"))?;
+        let index = fs::read_to_string(self.dir.join("multi_index"))?;
+        let intensities = intensities(&index, color, max).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unknown color channels `{}`", index.trim()),
+            )
+        })?;
+        fs::write(self.dir.join("multi_intensity"), intensities)?;
+        let brightness = if color == OFF { 0 } else { max };
+        fs::write(self.dir.join("brightness"), brightness.to_string())
+    }
+}
+
+/// The `multi_intensity` of `color` on a LED whose channels are named in `index`, e.g.
+/// `red green blue`, each going up to `max`.
+fn intensities(index: &str, color: Color, max: u32) -> Option<String> {
+    let scale = |channel: u8| (u32::from(channel) * max / 255).to_string();
+    let channels: Option<Vec<String>> = index
+        .split_whitespace()
+        .map(|channel| match channel {
+            "red" => Some(scale(color.red)),
+            "green" => Some(scale(color.green)),
+            "blue" => Some(scale(color.blue)),
+            _ => None,
+        })
+        .collect();
+    channels
+        .filter(|channels| !channels.is_empty())
+        .map(|channels| channels.join(" "))
+}
+
+pub struct StatusLight {
+    light: Box<dyn Light>,
+    config: LedConfig,
+}
+
+impl StatusLight {
+    pub fn new(config: LedConfig, light: Box<dyn Light>) -> Self {
+        StatusLight { light, config }
+    }
+
+    /// The color to show from `event` on, if it changes.
+    fn color(&self, event: &Event) -> Option<Color> {
+        match event {
+            Event::SessionStarted { session, .. } => Some(match session.kind {
+                SessionKind::Work => self.config.work,
+                SessionKind::ShortBreak => self.config.short_break,
+                SessionKind::LongBreak => self.config.long_break,
+            }),
+            _ => None,
+        }
+    }
+
+    fn show(&self, color: Color) -> Result<(), LedError> {
+        self.light.show(color).map_err(|source| LedError {
+            device: self.light.name(),
+            source,
+        })
+    }
+
+    /// Follows the events until the event bus is dropped, turning the light off then, and returns
+    /// the first failure.
+    pub fn run(self, events: Receiver<Event>) -> Option<LedError> {
+        let mut first_failure = None;
+        for event in events {
+            if let Some(color) = self.color(&event)
+                && let Err(e) = self.show(color)
+            {
+                first_failure.get_or_insert(e);
+            }
+        }
+        if let Err(e) = self.show(OFF) {
+            first_failure.get_or_insert(e);
+        }
+        first_failure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex, mpsc},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::SessionType;
+
+    /// A light recording the colors it shows.
+    struct Fake(Arc<Mutex<Vec<Color>>>);
+
+    impl Light for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn show(&self, color: Color) -> io::Result<()> {
+            self.0.lock().unwrap().push(color);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_colors() {
+        let orange: Color = "#ff8000".parse().unwrap();
+        assert_eq!(orange, Color::new(0xff, 0x80, 0));
+        assert_eq!(orange.to_string(), "#ff8000");
+        assert_eq!("00FF00".parse(), Ok(Color::new(0, 0xff, 0)));
+        assert!("#ff80".parse::<Color>().is_err());
+        assert!("#gg0000".parse::<Color>().is_err());
+
+        assert_eq!(
+            intensities("red green blue\n", orange, 100).as_deref(),
+            Some("100 50 0")
+        );
+        assert_eq!(
+            intensities("blue red green", orange, 255).as_deref(),
+            Some("0 255 128")
+        );
+        assert_eq!(intensities("white", orange, 255), None);
+    }
+
+    #[test]
+    fn shows_the_color_of_each_session() {
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let config = LedConfig {
+            device: LedDevice::Auto,
+            work: Color::new(0xff, 0, 0),
+            short_break: Color::new(0, 0xff, 0),
+            long_break: Color::new(0, 0, 0xff),
+        };
+        let light = StatusLight::new(config, Box::new(Fake(Arc::clone(&shown))));
+        let (tx, rx) = mpsc::channel();
+        for session in [SessionType::work(), SessionType::long_break()] {
+            tx.send(Event::SessionStarted {
+                session,
+                cycle: 4,
+                duration: Duration::from_secs(60),
+            })
+            .unwrap();
+            tx.send(Event::Paused {
+                elapsed: Duration::from_secs(10),
+            })
+            .unwrap();
+        }
+        drop(tx);
+
+        assert!(light.run(rx).is_none());
+        assert_eq!(
+            *shown.lock().unwrap(),
+            [Color::new(0xff, 0, 0), Color::new(0, 0, 0xff), OFF]
+        );
+    }
+}
@@ -0,0 +1,271 @@
+// filepath: src/lights.rs
+//! Module switching the lights of the room to a focus scene during the work sessions of a Pomodoro
+//! timer application and to a relax scene during the breaks, through a Philips Hue bridge or Home
+//! Assistant. The lights are put back as they were when the timer quits.
+//!
+//! The scenes are recalled as notifications of the session starts, retried by the router.
+use std::{sync::Mutex, time::Duration};
+
+use serde_json::{Value, json};
+use ureq::Agent;
+
+use crate::{
+    config::{LightsBackend, LightsConfig},
+    notifier::{Notification, Notifier, NotifierError, Trigger},
+};
+
+/// Longest wait for the bridge, which is on the local network.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Scene Home Assistant saves the lights in before the first scene.
+const RESTORE_SCENE: &str = "rustodoro_restore";
+
+/// How the lights were before the first scene.
+enum Snapshot {
+    /// Ids of the Hue lights, with the state putting each back.
+    Hue(Vec<(String, Value)>),
+    /// The lights are saved in the restore scene of Home Assistant.
+    HomeAssistant,
+}
+
+pub struct Lights {
+    agent: Agent,
+    config: LightsConfig,
+    snapshot: Mutex<Option<Snapshot>>,
+}
+
+impl Lights {
+    pub fn new(config: &LightsConfig) -> Self {
+        Lights {
+            agent: Agent::config_builder()
+                .timeout_global(Some(TIMEOUT))
+                .build()
+                .into(),
+            config: config.clone(),
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    /// The triggers recalling a scene.
+    pub fn triggers() -> Vec<Trigger> {
+        vec![Trigger::WorkStarted, Trigger::BreakStarted]
+    }
+
+    fn error(&self, message: impl ToString) -> NotifierError {
+        NotifierError {
+            backend: match self.config.backend {
+                LightsBackend::Hue => "Hue",
+                LightsBackend::HomeAssistant => "Home Assistant",
+            },
+            message: message.to_string(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        let base = self.config.url.trim_end_matches('/');
+        match self.config.backend {
+            LightsBackend::Hue => format!("{}/api/{}/{}", base, self.config.token, path),
+            LightsBackend::HomeAssistant => format!("{}/api/{}", base, path),
+        }
+    }
+
+    /// The answer of the bridge, which reports most Hue errors in a successful response.
+    fn check(&self, reply: Result<Value, ureq::Error>) -> Result<Value, NotifierError> {
+        let reply = reply.map_err(|e| self.error(e))?;
+        match hue_error(&reply) {
+            Some(description) => Err(self.error(description)),
+            None => Ok(reply),
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Value, NotifierError> {
+        let request = self.agent.get(self.url(path));
+        let reply = match self.config.backend {
+            LightsBackend::Hue => request.call(),
+            LightsBackend::HomeAssistant => request
+                .header("Authorization", format!("Bearer {}", self.config.token))
+                .call(),
+        };
+        self.check(reply.and_then(|mut response| response.body_mut().read_json()))
+    }
+
+    /// Changes the lights, with a PUT to the Hue bridge or a POST to Home Assistant.
+    fn write(&self, path: &str, body: Value) -> Result<Value, NotifierError> {
+        let reply = match self.config.backend {
+            LightsBackend::Hue => self.agent.put(self.url(path)).send_json(body),
+            LightsBackend::HomeAssistant => self
+                .agent
+                .post(self.url(path))
+                .header("Authorization", format!("Bearer {}", self.config.token))
+                .send_json(body),
+        };
+        self.check(reply.and_then(|mut response| response.body_mut().read_json()))
+    }
+
+    fn recall(&self, scene: &str) -> Result<(), NotifierError> {
+        match self.config.backend {
+            LightsBackend::Hue => self.write("groups/0/action", json!({ "scene": scene })),
+            LightsBackend::HomeAssistant => {
+                self.write("services/scene/turn_on", json!({ "entity_id": scene }))
+            }
+        }
+        .map(drop)
+    }
+
+    fn take_snapshot(&self) -> Result<Snapshot, NotifierError> {
+        match self.config.backend {
+            LightsBackend::Hue => Ok(Snapshot::Hue(hue_states(&self.read("lights")?))),
+            LightsBackend::HomeAssistant => {
+                let mut entities = Vec::new();
+                for scene in [&self.config.focus, &self.config.relax] {
+                    for entity in scene_entities(&self.read(&format!("states/{}", scene))?) {
+                        if !entities.contains(&entity) {
+                            entities.push(entity);
+                        }
+                    }
+                }
+                self.write(
+                    "services/scene/create",
+                    json!({ "scene_id": RESTORE_SCENE, "snapshot_entities": entities }),
+                )?;
+                Ok(Snapshot::HomeAssistant)
+            }
+        }
+    }
+}
+
+impl Notifier for Lights {
+    fn send(&self, notification: &Notification) -> Result<(), NotifierError> {
+        let scene = match notification.trigger {
+            Trigger::WorkStarted => &self.config.focus,
+            Trigger::BreakStarted => &self.config.relax,
+            _ => return Ok(()),
+        };
+        if self.config.restore {
+            let mut snapshot = self.snapshot.lock().unwrap();
+            if snapshot.is_none() {
+                *snapshot = Some(self.take_snapshot()?);
+            }
+        }
+        self.recall(scene)
+    }
+
+    fn attempts(&self) -> u32 {
+        self.config.retries + 1
+    }
+
+    /// Scenes are silent, so they keep following the sessions while the timer keeps quiet.
+    fn interrupts(&self) -> bool {
+        false
+    }
+
+    fn restore(&self) -> Result<(), NotifierError> {
+        match self.snapshot.lock().unwrap().take() {
+            None => Ok(()),
+            Some(Snapshot::Hue(states)) => {
+                for (id, state) in states {
+                    self.write(&format!("lights/{}/state", id), state)?;
+                }
+                Ok(())
+            }
+            Some(Snapshot::HomeAssistant) => self.recall(&format!("scene.{}", RESTORE_SCENE)),
+        }
+    }
+}
+
+/// The description of the first error in a Hue response, e.g.
+/// `[{"error":{"type":1,"description":"unauthorized user"}}]`.
+fn hue_error(reply: &Value) -> Option<String> {
+    reply.as_array()?.iter().find_map(|item| {
+        let description = item.get("error")?.get("description")?;
+        Some(description.as_str().unwrap_or("unknown error").to_string())
+    })
+}
+
+/// The states putting back the reachable lights listed by `GET /api/<key>/lights`. A light
+/// rejects any other change while it is off.
+fn hue_states(lights: &Value) -> Vec<(String, Value)> {
+    let Some(lights) = lights.as_object() else {
+        return Vec::new();
+    };
+    lights
+        .iter()
+        .filter_map(|(id, light)| {
+            let state = light.get("state")?;
+            if state.get("reachable") == Some(&Value::Bool(false)) {
+                return None;
+            }
+            let on = state.get("on")?.as_bool()?;
+            let mut restored = json!({ "on": on });
+            if on {
+                let fields: &[&str] = match state.get("colormode").and_then(Value::as_str) {
+                    Some("xy") => &["bri", "xy"],
+                    Some("ct") => &["bri", "ct"],
+                    Some("hs") => &["bri", "hue", "sat"],
+                    _ => &["bri"],
+                };
+                for field in fields {
+                    if let Some(value) = state.get(*field) {
+                        restored[*field] = value.clone();
+                    }
+                }
+            }
+            Some((id.clone(), restored))
+        })
+        .collect()
+}
+
+/// The entities of a Home Assistant scene, from its state.
+fn scene_entities(state: &Value) -> Vec<String> {
+    state["attributes"]["entity_id"]
+        .as_array()
+        .map(|entities| {
+            entities
+                .iter()
+                .filter_map(|entity| entity.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_hue_bridge() {
+        let lights = json!({
+            "1": { "state": { "on": true, "bri": 200, "colormode": "ct", "ct": 366, "xy": [0.4, 0.4], "reachable": true } },
+            "2": { "state": { "on": false, "bri": 254, "reachable": true } },
+            "3": { "state": { "on": true, "bri": 10, "reachable": false } }
+        });
+        assert_eq!(
+            hue_states(&lights),
+            [
+                (
+                    "1".to_string(),
+                    json!({ "on": true, "bri": 200, "ct": 366 })
+                ),
+                ("2".to_string(), json!({ "on": false })),
+            ]
+        );
+        assert_eq!(
+            hue_error(&json!([{ "error": { "type": 1, "description": "unauthorized user" } }])),
+            Some("unauthorized user".to_string())
+        );
+        assert_eq!(
+            hue_error(&json!([{ "success": { "/groups/0/action/scene": "abc" } }])),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_the_home_assistant_scenes() {
+        let state = json!({
+            "entity_id": "scene.focus",
+            "state": "2024-05-01T09:00:00+00:00",
+            "attributes": { "entity_id": ["light.desk", "light.ceiling"], "friendly_name": "Focus" }
+        });
+        assert_eq!(scene_entities(&state), ["light.desk", "light.ceiling"]);
+        assert!(scene_entities(&json!({ "state": "unknown" })).is_empty());
+    }
+}
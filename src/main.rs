@@ -1,130 +1,856 @@
 // filepath: src/main.rs
 //! A command-line Pomodoro timer application with interactive controls.
+mod achievements;
+mod aggregate;
+mod alerts;
+#[cfg(unix)]
+mod attach;
+mod audio;
+mod backup;
+#[cfg(any(feature = "caldav", feature = "gcal"))]
+mod calendar;
+mod capture;
 mod command_dispatcher;
+mod command_line;
+mod command_queue;
+mod compliance;
+mod config;
+#[cfg(unix)]
+mod control;
+mod credentials;
+mod crypt;
+mod desktop;
+mod digest;
+#[cfg(feature = "dim")]
+mod dim;
+mod doctor;
+mod edit;
+mod events;
+mod eyes;
+#[cfg(feature = "flash")]
+mod flash;
+mod focus;
+mod garden;
+#[cfg(feature = "gcal")]
+mod gcal;
+mod git;
+mod haptics;
+mod heatmap;
+mod history;
+mod icon;
+mod idle;
+mod import;
+mod input;
+mod journal;
+mod keychain;
+mod keymap;
+mod leaderboard;
+#[cfg(feature = "led")]
+mod led;
+#[cfg(feature = "lights")]
+mod lights;
+mod notes;
+mod notifier;
+#[cfg(feature = "ntfy")]
+mod ntfy;
+mod outbox;
+mod output;
+mod pace;
+mod plan;
+mod platform;
+mod plugins;
+mod preview;
+mod prompt;
+mod query;
+mod quiet;
+mod reminders;
+mod remote;
+mod render;
+mod report;
+mod retention;
+mod run_queue;
+mod runway;
+mod scheduler;
+mod scripting;
+mod serve;
 mod session_timer;
+mod settings;
+mod simulate;
+mod sounds;
+mod state_machine;
+mod summary;
+#[cfg(feature = "telegram")]
+mod telegram;
+mod template;
+mod terminal_guard;
+#[cfg(test)]
+mod testing;
+mod today;
+mod tutorial;
 mod types;
+#[cfg(unix)]
+mod wait;
+mod wasm;
+mod weekdays;
+mod wizard;
 
-use std::sync::{Arc, Mutex};
-use std::{
-    sync::mpsc::{self},
-    thread,
-    time::Duration,
-};
+use std::process;
+use std::sync::Arc;
+use std::thread;
 
 use clap::Parser;
+use tokio::sync::mpsc;
 
 use command_dispatcher::CommandDispatcher;
-use crossterm::terminal;
-use session_timer::SessionTimer;
+use config::{AudioAction, Cli, CliCommand, QueueAction};
+use events::EventBus;
+use history::{History, Recorder, SessionRecord};
+use pace::PaceReminder;
+use scheduler::{RunSummary, Scheduler};
+use settings::Settings;
+use today::Today;
 use types::AppError;
 use types::SessionType;
 
 use types::Command;
 
-#[derive(Parser)]
-struct Config {
-    #[arg(short, long = "work", default_value_t = 25)]
-    work_duration: u64,
-    #[arg(short, long = "short-break", default_value_t = 5)]
-    short_break: u64,
-    #[arg(short, long = "long-break", default_value_t = 15)]
-    long_break: u64,
-    #[arg(short, long = "cycles", default_value_t = 4)]
-    cycles: u64,
-    #[arg(short, long = "no-sound", default_value_t = false)]
-    no_sound: bool,
-}
-
 fn main() {
-    let config = Config::parse();
-    let (tx, rx) = mpsc::channel::<Command>();
+    terminal_guard::install_panic_hook();
+    let cli = Cli::parse();
+    match &cli.command {
+        Some(CliCommand::Config { action }) => process::exit(config::run_command(&cli, action)),
+        Some(CliCommand::Report { html, days }) => {
+            process::exit(report::run_command(&cli, html, *days))
+        }
+        Some(CliCommand::Heatmap) => process::exit(heatmap::run_command(&cli)),
+        Some(CliCommand::Audio {
+            action: AudioAction::List,
+        }) => process::exit(audio::run_command(&cli)),
+        Some(CliCommand::TestSound { cue }) => process::exit(preview::run_sound(&cli, *cue)),
+        Some(CliCommand::TestNotify) => process::exit(preview::run_notify(&cli)),
+        Some(CliCommand::Import {
+            from,
+            file,
+            dry_run,
+        }) => process::exit(import::run_command(&cli, *from, file, *dry_run)),
+        Some(CliCommand::Backup { file }) => process::exit(backup::run_backup(&cli, file)),
+        Some(CliCommand::Restore { file, force }) => {
+            process::exit(backup::run_restore(&cli, file, *force))
+        }
+        Some(CliCommand::History { action }) => process::exit(retention::run_command(&cli, action)),
+        #[cfg(unix)]
+        Some(CliCommand::Status) => process::exit(control::run_status(&cli)),
+        #[cfg(unix)]
+        Some(CliCommand::Attach) => process::exit(attach::run_command(&cli)),
+        #[cfg(unix)]
+        Some(CliCommand::Wait { target, timeout }) => {
+            process::exit(wait::run_command(&cli, *target, *timeout))
+        }
+        Some(CliCommand::Replay { file }) => process::exit(journal::run_command(file)),
+        Some(CliCommand::Simulate { days, pattern }) => {
+            process::exit(simulate::run_command(&cli, *days, pattern.as_deref()))
+        }
+        Some(CliCommand::Leaderboard { day }) => {
+            process::exit(leaderboard::run_command(&cli, *day))
+        }
+        Some(CliCommand::Digest {
+            daily,
+            email,
+            output,
+            ..
+        }) => process::exit(digest::run_command(
+            &cli,
+            *daily,
+            email.as_deref(),
+            output.as_deref(),
+        )),
+        Some(CliCommand::Achievements) => process::exit(achievements::run_command(&cli)),
+        Some(CliCommand::Garden { days }) => process::exit(garden::run_command(&cli, *days)),
+        #[cfg(feature = "gcal")]
+        Some(CliCommand::Auth {
+            action: config::AuthAction::Google,
+        }) => process::exit(gcal::run_command(&cli)),
+        Some(CliCommand::Auth { action }) => process::exit(credentials::run_command(action)),
+        Some(CliCommand::Queue {
+            action: action @ (QueueAction::Status | QueueAction::Flush),
+        }) => process::exit(outbox::run_command(&cli, action)),
+        Some(CliCommand::Queue {
+            action: QueueAction::Run,
+        }) => {}
+        Some(CliCommand::Queue { action }) => process::exit(run_queue::run_command(&cli, action)),
+        Some(CliCommand::Tutorial) => process::exit(tutorial::run_command()),
+        Some(CliCommand::Plan) => process::exit(plan::run_command(&cli)),
+        None => {}
+    }
+    let result = match &cli.command {
+        Some(CliCommand::Queue { .. }) => run_queue::default_run_queue_path()
+            .ok_or(run_queue::RunQueueError::NoQueue.into())
+            .and_then(|path| run_queue::run_all(&cli, &run_queue::RunQueue::new(path), run)),
+        _ => run(&cli),
+    };
+    if let Err(e) = &result {
+        // The command dispatcher may still hold the terminal in raw mode.
+        terminal_guard::restore();
+        eprintln!("error: {}", e);
+        if let Some(hint) = e.hint() {
+            eprintln!("  hint: {}", hint);
+        }
+    }
+    let report = summary::RunReport::new(&result);
+    if cli.run.summary_json {
+        println!(
+            "{}",
+            serde_json::to_string(&report).expect("reports always serialize")
+        );
+    }
+    process::exit(report.exit_code);
+}
 
-    let rx_arc = Arc::new(Mutex::new(rx));
+fn run(cli: &Cli) -> Result<RunSummary, AppError> {
+    if !cli.run.no_wizard {
+        wizard::offer(cli)?;
+    }
+    let mut effective = config::resolve(cli)?;
+    credentials::fill(&mut effective, &keychain::Os)?;
+    let config = Settings {
+        task: cli.run.task.clone(),
+        ..effective.settings()
+    };
+    // The keyboard is one input source among others: its thread only needs a sender, while the
+    // timer runs on the async runtime.
+    let (tx, rx) = mpsc::unbounded_channel::<Command>();
+    let journal = match &cli.run.record {
+        Some(path) => Some(journal::Journal::create(path, &config)?),
+        None => None,
+    };
+    let rx = match &journal {
+        Some(journal) => journal.tap(rx),
+        None => rx,
+    };
 
+    if let Some(name) = &cli.name {
+        print!("[{}] ", name);
+    }
     println!(
-        "Starting Pomodoro: {} min work, {} min short break, {} min long break, {} cycles, sound: {}\n",
-        config.work_duration,
+        "Starting Pomodoro: {} work, {} min short break, {} min long break, {}, sound: {}, notifications: {}\n",
+        config.work_description(),
         config.short_break,
         config.long_break,
-        config.cycles,
-        if config.no_sound { "off" } else { "on" }
+        match &config.sequence {
+            Some(steps) => format!("{}-session sequence", steps.len()),
+            None => format!("{} cycles", config.cycles),
+        },
+        if config.no_sound { "off" } else { "on" },
+        if config.no_notify { "off" } else { "on" }
     );
 
-    let command_dispatcher_thread = thread::spawn(move || CommandDispatcher::new(tx).run());
+    let settings = config.into_shared();
+    let history_path = history::history_path(cli.history.as_deref(), cli.name.as_deref());
+    let today = chrono::Local::now().date_naive();
+    if let Some(path) = &history_path {
+        // The passphrase of an encrypted history is asked for before the timer takes the terminal.
+        History::new(path.clone()).unlock()?;
+        match History::new(path.clone()).upgrade() {
+            Ok(Some(version)) => println!(
+                "note: the history was upgraded from version {} to {}",
+                version,
+                history::SCHEMA_VERSION
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("warning: {}", e),
+        }
+    }
+    if let (Some(path), Some(retention)) = (&history_path, &effective.retention.value) {
+        let cutoff = retention.keep.cutoff(today);
+        match retention::prune(&History::new(path.clone()), cutoff) {
+            Ok(pruned) if pruned.removed > 0 => println!(
+                "note: {} sessions started before {} were pruned from the history",
+                pruned.removed, cutoff
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("warning: {}", e),
+        }
+    }
+    // A history that cannot be read is reported with the summary, the reminders then count this
+    // run only.
+    let all = history_path
+        .clone()
+        .and_then(|path| History::new(path).sessions().ok())
+        .unwrap_or_default();
+    let today_stats = summary::Stats::from_records(summary::on_day(&all, today));
+    let plant = garden::Plant::from_records(summary::on_day(&all, today));
+    let pomodoros_today = Today::new(today, today_stats.pomodoros)
+        .with_abandoned(plant.wilted)
+        .into_shared();
+    if let Some(week) = weekdays::Week::resolve(cli, config::config_path(cli))? {
+        week.follow(Arc::clone(&settings));
+    }
+    let events = Arc::new(EventBus::new());
+    if let Some(journal) = &journal {
+        journal.follow(&events);
+    }
+
+    #[cfg(unix)]
+    let _control = {
+        let path = control::socket_path(cli);
+        let commands = tx.downgrade();
+        // Remote control is optional, the timer runs without it.
+        match control::ControlSocket::start(
+            path.clone(),
+            cli.name.clone(),
+            &events,
+            commands,
+            Arc::clone(&settings),
+            Arc::clone(&pomodoros_today),
+        ) {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                eprintln!(
+                    "warning: cannot listen on {}, remote control is disabled: {}",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    };
 
-    let mut total_work_cycles = 0;
+    let serve_port = effective.serve_port.value as u16;
+    if serve_port != 0
+        && let Err(e) = serve::start(
+            serve_port,
+            &events,
+            tx.downgrade(),
+            Arc::clone(&settings),
+            Arc::clone(&pomodoros_today),
+        )
+    {
+        eprintln!(
+            "warning: cannot serve on 127.0.0.1:{}, browser extensions and editors will not see the sessions: {}",
+            serve_port, e
+        );
+    }
 
-    'controllerCycle: loop {
-        for current_cycle in 1..=config.cycles {
-            let mut session_timer = SessionTimer::new(
-                Arc::clone(&rx_arc),
-                Duration::from_secs(config.work_duration) * 60,
-                SessionType::Work("Work session"),
-                current_cycle,
-                config.cycles,
-                config.no_sound,
+    #[cfg(feature = "telegram")]
+    let telegram_route = effective.telegram.value.as_ref().and_then(|telegram| {
+        match telegram::start(
+            telegram,
+            &events,
+            tx.downgrade(),
+            Arc::clone(&settings),
+            Arc::clone(&pomodoros_today),
+        ) {
+            // The bot reports every session already, it only needs a route for the escalations.
+            Ok(escalations) => telegram.escalate_after.map(|seconds| notifier::Route {
+                notifier: Box::new(escalations),
+                triggers: notifier::Trigger::DEFAULT.to_vec(),
+                escalate_after: Some(std::time::Duration::from_secs(seconds)),
+            }),
+            Err(e) => {
+                eprintln!("warning: the Telegram bot is disabled: {}", e);
+                None
+            }
+        }
+    });
+    #[cfg(not(feature = "telegram"))]
+    let telegram_route = {
+        if effective.telegram.value.is_some() {
+            eprintln!(
+                "warning: rustodoro was built without the telegram feature, [telegram] is ignored"
             );
+        }
+        None
+    };
 
-            let session_timer_thread = thread::spawn(move || session_timer.run());
+    #[cfg(feature = "ntfy")]
+    let ntfy_route = effective.ntfy.value.as_ref().map(|ntfy| notifier::Route {
+        notifier: Box::new(ntfy::Ntfy::new(ntfy)),
+        triggers: ntfy.events.clone(),
+        escalate_after: ntfy.escalate_after.map(std::time::Duration::from_secs),
+    });
+    #[cfg(not(feature = "ntfy"))]
+    let ntfy_route = {
+        if effective.ntfy.value.is_some() {
+            eprintln!("warning: rustodoro was built without the ntfy feature, [ntfy] is ignored");
+        }
+        None
+    };
+    #[cfg(feature = "lights")]
+    let lights_route = effective
+        .lights
+        .value
+        .as_ref()
+        .map(|lights| notifier::Route {
+            notifier: Box::new(lights::Lights::new(lights)),
+            triggers: lights::Lights::triggers(),
+            escalate_after: None,
+        });
+    #[cfg(not(feature = "lights"))]
+    let lights_route = {
+        if effective.lights.value.is_some() {
+            eprintln!(
+                "warning: rustodoro was built without the lights feature, [lights] is ignored"
+            );
+        }
+        None
+    };
+    let quiet = effective
+        .quiet
+        .value
+        .clone()
+        .map(|config| Arc::new(quiet::Quiet::new(config, quiet::Os)));
+    let mut router = notifier::Router::new(
+        telegram_route
+            .into_iter()
+            .chain(ntfy_route)
+            .chain(lights_route)
+            .collect(),
+    );
+    if let Some(quiet) = &quiet {
+        router = router.with_quiet(Arc::clone(quiet));
+    }
+    if !effective.notifications.value.is_empty() {
+        router = router.with_templates(notifier::Templates::new(
+            effective.notifications.value.clone(),
+            Arc::clone(&settings),
+        ));
+    }
+    let outbox_path = outbox::default_outbox_path();
+    if let Some(path) = &outbox_path {
+        router = router.with_outbox(outbox::Outbox::new(path.clone()));
+    }
+    let router_thread = (!router.is_empty()).then(|| {
+        let router_events = events.subscribe();
+        thread::spawn(move || router.run(router_events))
+    });
 
-            match session_timer_thread.join() {
-                Ok(res) => {
-                    if let Err(_) = res {
-                        break 'controllerCycle;
-                    } else {
-                        total_work_cycles += 1;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Worker thread panicked: {:?}", e);
-                }
+    #[cfg(feature = "caldav")]
+    let calendar_thread = effective.calendar.value.clone().map(|config| {
+        let calendar = calendar::CalDav::new(&config);
+        let mut blocks = calendar::FocusBlocks::new(
+            config.title,
+            config.extend_within,
+            calendar,
+            Arc::clone(&settings),
+        );
+        if let Some(quiet) = &quiet {
+            blocks = blocks.with_quiet(Arc::clone(quiet));
+        }
+        let calendar_events = events.subscribe();
+        thread::spawn(move || blocks.run(calendar_events))
+    });
+    #[cfg(not(feature = "caldav"))]
+    if effective.calendar.value.is_some() {
+        eprintln!("warning: rustodoro was built without the caldav feature, [calendar] is ignored");
+    }
+    #[cfg(feature = "gcal")]
+    let gcal_thread = effective.gcal.value.as_ref().and_then(|config| {
+        let google = match gcal::Google::connect(config, &keychain::Os) {
+            Ok(google) => google,
+            Err(e) => {
+                eprintln!("warning: {}, [gcal] is ignored", e);
+                return None;
             }
+        };
+        let mut blocks = calendar::FocusBlocks::new(
+            config.title.clone(),
+            config.extend_within,
+            google,
+            Arc::clone(&settings),
+        );
+        if let Some(quiet) = &quiet {
+            blocks = blocks.with_quiet(Arc::clone(quiet));
+        }
+        let gcal_events = events.subscribe();
+        Some(thread::spawn(move || blocks.run(gcal_events)))
+    });
+    #[cfg(not(feature = "gcal"))]
+    if effective.gcal.value.is_some() {
+        eprintln!("warning: rustodoro was built without the gcal feature, [gcal] is ignored");
+    }
+
+    let focus_thread = effective.focus.value.clone().map(|config| {
+        let mut focus = focus::Focus::new(config, focus::Os, Arc::clone(&settings));
+        if let Some(quiet) = &quiet {
+            focus = focus.with_quiet(Arc::clone(quiet));
+        }
+        let focus_events = events.subscribe();
+        thread::spawn(move || focus.run(focus_events))
+    });
+
+    #[cfg(feature = "dim")]
+    let dim_thread = effective.dim.value.as_ref().and_then(|config| {
+        let Some(screen) = dim::detect(config.backend) else {
+            eprintln!(
+                "warning: the screen cannot be dimmed with the {} backend here, [dim] is ignored",
+                config.backend.name()
+            );
+            return None;
+        };
+        let dimmer = dim::Dimmer::new(config, screen);
+        let dim_events = events.subscribe();
+        Some(thread::spawn(move || dimmer.run(dim_events)))
+    });
+    #[cfg(not(feature = "dim"))]
+    if effective.dim.value.is_some() {
+        eprintln!("warning: rustodoro was built without the dim feature, [dim] is ignored");
+    }
 
-            let (break_duration, break_type) = if current_cycle == config.cycles {
-                (
-                    Duration::from_secs(config.long_break * 60),
-                    SessionType::LongBreak("Long break"),
-                )
-            } else {
-                (
-                    Duration::from_secs(config.short_break * 60),
-                    SessionType::ShortBreak("Short break"),
-                )
-            };
-
-            let mut session_timer = SessionTimer::new(
-                Arc::clone(&rx_arc),
-                break_duration,
-                break_type,
-                current_cycle,
-                config.cycles,
-                config.no_sound,
+    #[cfg(feature = "led")]
+    let led_thread = effective.led.value.clone().and_then(|config| {
+        let Some(light) = led::detect(config.device) else {
+            eprintln!(
+                "warning: no {} status light was found, [led] is ignored",
+                config.device.name()
             );
+            return None;
+        };
+        let status_light = led::StatusLight::new(config, light);
+        let led_events = events.subscribe();
+        Some(thread::spawn(move || status_light.run(led_events)))
+    });
+    #[cfg(not(feature = "led"))]
+    if effective.led.value.is_some() {
+        eprintln!("warning: rustodoro was built without the led feature, [led] is ignored");
+    }
 
-            let session_timer_thread = thread::spawn(move || session_timer.run());
+    #[cfg(feature = "flash")]
+    let flash_thread = effective.flash.value.as_ref().and_then(|config| {
+        let Some(backend) = flash::detect(config.style) else {
+            eprintln!(
+                "warning: the displays cannot be flashed with the {} style here, [flash] is ignored",
+                config.style.name()
+            );
+            return None;
+        };
+        let flasher = flash::Flasher::new(config, backend);
+        let flash_events = events.subscribe();
+        Some(thread::spawn(move || flasher.run(flash_events)))
+    });
+    #[cfg(not(feature = "flash"))]
+    if effective.flash.value.is_some() {
+        eprintln!("warning: rustodoro was built without the flash feature, [flash] is ignored");
+    }
 
-            match session_timer_thread.join() {
-                Ok(res) => {
-                    if let Err(_) = res {
-                        break 'controllerCycle;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Worker thread panicked: {:?}", e);
+    let (found_plugins, plugin_errors) = plugins::plugins_dir()
+        .map(|dir| plugins::discover(&dir))
+        .unwrap_or_default();
+    for e in plugin_errors {
+        eprintln!("warning: {}", e);
+    }
+    let plugins = plugins::Plugins::start(found_plugins, tx.downgrade());
+    let plugins_thread = (!plugins.is_empty()).then(|| {
+        let plugin_events = events.subscribe();
+        thread::spawn(move || plugins.run(plugin_events))
+    });
+
+    let dispatcher_settings = Arc::clone(&settings);
+    let dispatcher_events = Arc::clone(&events);
+    let dispatcher_keys = effective.keys.value.clone().unwrap_or_default();
+    // Built before the run starts, so that the dispatcher sees the first session start.
+    let mut command_dispatcher = CommandDispatcher::new(tx, dispatcher_settings, dispatcher_events)
+        .with_keys(&dispatcher_keys);
+    let command_dispatcher_thread = thread::spawn(move || command_dispatcher.run());
+
+    // The goal of the day the run ends on, which may differ from the one it started on.
+    let final_settings = Arc::clone(&settings);
+    let ask_intent = settings.lock().unwrap().ask_intent;
+    let mut recorder = Recorder::new(history_path.clone().map(History::new), cli.run.task.clone());
+    if ask_intent {
+        recorder = recorder.with_reviews();
+    }
+    let recorder_events = events.subscribe();
+    let recorder_thread = thread::spawn(move || recorder.run(recorder_events));
+    let mut reminder = PaceReminder::new(Arc::clone(&settings), today, today_stats.pomodoros);
+    if let Some(quiet) = &quiet {
+        reminder = reminder.with_quiet(Arc::clone(quiet));
+    }
+    let reminder_events = events.subscribe();
+    let reminder_thread = thread::spawn(move || reminder.run(reminder_events));
+    let nag_thread = effective.compliance.value.as_ref().map(|config| {
+        let mut nag = compliance::Nag::new(
+            config,
+            Arc::clone(&settings),
+            today,
+            today_stats.breaks_taken,
+            today_stats.breaks,
+        );
+        if let Some(quiet) = &quiet {
+            nag = nag.with_quiet(Arc::clone(quiet));
+        }
+        let nag_events = events.subscribe();
+        thread::spawn(move || nag.run(nag_events))
+    });
+    let eyes_thread = effective.eyes.value.as_ref().map(|config| {
+        let mut eye_breaks = eyes::EyeBreaks::new(config, Arc::clone(&settings));
+        if let Some(quiet) = &quiet {
+            eye_breaks = eye_breaks.with_quiet(Arc::clone(quiet));
+        }
+        let eyes_events = events.subscribe();
+        thread::spawn(move || eye_breaks.run(eyes_events))
+    });
+    let reminders_thread = (!effective.reminders.value.is_empty()).then(|| {
+        let mut reminders =
+            reminders::Reminders::new(effective.reminders.value.clone(), Arc::clone(&settings));
+        if let Some(quiet) = &quiet {
+            reminders = reminders.with_quiet(Arc::clone(quiet));
+        }
+        let reminders_events = events.subscribe();
+        thread::spawn(move || reminders.run(reminders_events))
+    });
+    let publisher_thread = effective
+        .leaderboard
+        .value
+        .clone()
+        .filter(|config| config.publish)
+        .map(|config| {
+            let publisher = leaderboard::Publisher::new(
+                config,
+                today,
+                today_stats.pomodoros,
+                today_stats.focused,
+            );
+            let publisher_events = events.subscribe();
+            thread::spawn(move || publisher.run(publisher_events))
+        });
+    let achievements_thread = history_path.as_ref().and_then(|path| {
+        let history = History::new(path.clone());
+        let store = achievements::Store::of(path);
+        match achievements::Tracker::new(&history, store, Arc::clone(&settings)) {
+            Ok(mut tracker) => {
+                if let Some(quiet) = &quiet {
+                    tracker = tracker.with_quiet(Arc::clone(quiet));
                 }
+                let achievements_events = events.subscribe();
+                Some(thread::spawn(move || tracker.run(achievements_events)))
             }
+            Err(e) => {
+                eprintln!("warning: {}, the achievements are not tracked", e);
+                None
+            }
+        }
+    });
+    let digest_thread = effective
+        .digest
+        .value
+        .clone()
+        .zip(history_path.clone())
+        .map(|(config, path)| {
+            let mailer = digest::Mailer::new(config, &path, effective.daily_goal.value);
+            let digest_events = events.subscribe();
+            thread::spawn(move || mailer.run(digest_events))
+        });
+    let notes_thread = effective.notes.value.clone().map(|config| {
+        let mut notes = notes::DailyNotes::new(config, cli.run.task.clone());
+        if ask_intent {
+            notes = notes.with_reviews();
+        }
+        let notes_events = events.subscribe();
+        thread::spawn(move || notes.run(notes_events))
+    });
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+    let mut scheduler = Scheduler::new(rx, events, settings)?.with_today(pomodoros_today);
+    if let Some(sets) = cli.run.sets {
+        scheduler = scheduler.with_sets(sets);
+    }
+    if let Some(config) = effective.schedule.value.clone() {
+        scheduler = scheduler.with_schedule(scripting::Schedule::new(config));
+    }
+    if let Some(quiet) = quiet {
+        scheduler = scheduler.with_quiet(quiet);
+    }
+    if effective.min_runway.value.is_some() || cli.run.deadline.is_some() {
+        let min = effective.min_runway.value.unwrap_or_default();
+        scheduler = scheduler.with_runway(runway::Runway::new(
+            min,
+            cli.run.deadline,
+            agenda(&effective),
+        ));
+    }
+    if let Some(config) = effective.auto_finish.value.clone() {
+        let auto_finish = idle::AutoFinish::new(config, idle::Os);
+        if auto_finish.is_available() {
+            scheduler = scheduler.with_auto_finish(Arc::new(auto_finish));
+        } else {
+            eprintln!(
+                "warning: the idle time cannot be measured here (xprintidle on X11, or macOS), [auto_finish] is ignored"
+            );
+        }
+    }
+    if let Some(config) = &effective.auto_pause.value {
+        let auto_pause = capture::AutoPause::new(config, capture::Os);
+        if auto_pause.is_available() {
+            scheduler = scheduler.with_auto_pause(Arc::new(auto_pause));
+        } else {
+            eprintln!(
+                "warning: the microphone and the camera cannot be watched here (pactl on Linux, macOS, or Windows), [auto_pause] is ignored"
+            );
+        }
+    }
+    if let Some(vibrations) = &effective.vibrations.value
+        && haptics::Haptics::detect(vibrations).is_none()
+    {
+        eprintln!(
+            "warning: nothing vibrates here without a command (the phone does under Termux), [vibrations] is ignored"
+        );
+    }
+    let summary = runtime.block_on(scheduler.run())?;
+    if let Some(e) = scheduler.schedule_failure() {
+        eprintln!("warning: {}", e);
+    }
+    // Dropping the scheduler closes the commands, which stops the command dispatcher and gives the
+    // terminal back, and the event bus, which lets the recorder finish.
+    drop(scheduler);
+    let dispatched = command_dispatcher_thread
+        .join()
+        .map_err(|_| AppError::ThreadPanic)?;
+    let (records, failure) = recorder_thread.join().map_err(|_| AppError::ThreadPanic)?;
+    reminder_thread.join().map_err(|_| AppError::ThreadPanic)?;
+    if let Some(nag_thread) = nag_thread {
+        nag_thread.join().map_err(|_| AppError::ThreadPanic)?;
+    }
+    if let Some(eyes_thread) = eyes_thread {
+        eyes_thread.join().map_err(|_| AppError::ThreadPanic)?;
+    }
+    if let Some(reminders_thread) = reminders_thread {
+        reminders_thread.join().map_err(|_| AppError::ThreadPanic)?;
+    }
+    if let Some(publisher_thread) = publisher_thread
+        && let Some(e) = publisher_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    if let Some(achievements_thread) = achievements_thread
+        && let Some(e) = achievements_thread
+            .join()
+            .map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    if let Some(digest_thread) = digest_thread
+        && let Some(e) = digest_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: the digest of the week was not sent: {}", e);
+    }
+    if let Some(notes_thread) = notes_thread
+        && let Some(e) = notes_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    // Joined before printing anything, the focus mode reverting what it did when the run ends.
+    if let Some(focus_thread) = focus_thread
+        && let Some(e) = focus_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    #[cfg(feature = "caldav")]
+    if let Some(calendar_thread) = calendar_thread
+        && let Some(e) = calendar_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    #[cfg(feature = "gcal")]
+    if let Some(gcal_thread) = gcal_thread
+        && let Some(e) = gcal_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    #[cfg(feature = "dim")]
+    if let Some(dim_thread) = dim_thread
+        && let Some(e) = dim_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    #[cfg(feature = "led")]
+    if let Some(led_thread) = led_thread
+        && let Some(e) = led_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    #[cfg(feature = "flash")]
+    if let Some(flash_thread) = flash_thread
+        && let Some(e) = flash_thread.join().map_err(|_| AppError::ThreadPanic)?
+    {
+        eprintln!("warning: {}", e);
+    }
+    if let Some(plugins_thread) = plugins_thread {
+        for e in plugins_thread.join().map_err(|_| AppError::ThreadPanic)? {
+            eprintln!("warning: {}", e);
+        }
+    }
+    if let Some(router_thread) = router_thread {
+        if let Some(e) = router_thread.join().map_err(|_| AppError::ThreadPanic)? {
+            eprintln!("warning: {}", e);
+        }
+        let queued = outbox_path.map_or(0, |path| {
+            outbox::Outbox::new(path)
+                .load()
+                .map_or(0, |queued| queued.len())
+        });
+        if queued > 0 {
+            eprintln!(
+                "note: {} notifications could not be sent yet, see `rustodoro queue status`",
+                queued
+            );
         }
     }
 
     println!(
-        "\nPomodoro session ended. Total work cycles completed: {} for a total of {} min",
-        total_work_cycles,
-        total_work_cycles * config.work_duration
+        "\nPomodoro session ended. Total work cycles completed: {} for a total of {} min\n",
+        summary.work_cycles, summary.work_minutes
     );
+    let goal = final_settings.lock().unwrap().daily_goal;
+    print_summary(&records, failure, history_path.map(History::new), goal);
 
-    // Wait for the command dispatcher to finish
-    match command_dispatcher_thread.join().unwrap() {
-        Ok(_) => (),
-        Err(_) => terminal::disable_raw_mode().unwrap(),
+    dispatched?;
+    Ok(summary)
+}
+
+/// The calendar telling the next event the work sessions must end before, [calendar] first. A
+/// Google Calendar which cannot be connected to was already warned about.
+#[cfg_attr(
+    not(any(feature = "caldav", feature = "gcal")),
+    allow(unused_variables)
+)]
+fn agenda(effective: &config::EffectiveConfig) -> Option<Box<dyn runway::Agenda>> {
+    #[cfg(feature = "caldav")]
+    if let Some(config) = &effective.calendar.value {
+        return Some(Box::new(calendar::CalDav::new(config)));
+    }
+    #[cfg(feature = "gcal")]
+    if let Some(config) = &effective.gcal.value {
+        return gcal::Google::connect(config, &keychain::Os)
+            .ok()
+            .map(|google| Box::new(google) as Box<dyn runway::Agenda>);
     }
+    None
+}
+
+/// Prints the statistics of the run and of the whole day. The history only adds to the summary,
+/// so failing to access it is a warning.
+fn print_summary(
+    records: &[SessionRecord],
+    failure: Option<history::HistoryError>,
+    history: Option<History>,
+    goal: u64,
+) {
+    let run = summary::Stats::from_records(records);
+    let today = match (failure, history) {
+        (Some(e), _) => Err(e),
+        (None, Some(history)) => history.sessions().map(Some),
+        (None, None) => Ok(None),
+    };
+    let today = match today {
+        Ok(all) => all.map(|all| {
+            summary::Stats::from_records(summary::on_day(&all, chrono::Local::now().date_naive()))
+        }),
+        Err(e) => {
+            eprintln!("warning: {}", e);
+            None
+        }
+    };
+    print!("{}", summary::render(&run, today.as_ref(), goal));
 }
@@ -0,0 +1,362 @@
+// filepath: src/notes.rs
+//! Module logging the sessions of a Pomodoro timer application to Markdown daily notes, e.g. of an
+//! Obsidian vault: as each session ends, a line templated with its time range, task and outcome is
+//! added to the note of the day, at the end of the section under a heading added when the note
+//! lacks it.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+};
+
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::{
+    config::{NotesConfig, expand_home},
+    events::{Event, SessionOutcome},
+    history::{Recorder, SessionKind, SessionRecord},
+    template::fill,
+};
+
+/// The placeholders of the path of the notes.
+pub const PATH_PLACEHOLDERS: &[&str] = &["date"];
+/// The placeholders of the lines logged.
+pub const LINE_PLACEHOLDERS: &[&str] = &[
+    "date", "start", "end", "minutes", "task", "intent", "review", "kind", "outcome",
+];
+
+#[derive(Debug, thiserror::Error)]
+#[error("cannot write the daily note {}: {source}", path.display())]
+pub struct NotesError {
+    path: PathBuf,
+    source: io::Error,
+}
+
+pub fn kind(kind: SessionKind) -> &'static str {
+    match kind {
+        SessionKind::Work => "work",
+        SessionKind::ShortBreak => "short break",
+        SessionKind::LongBreak => "long break",
+    }
+}
+
+pub fn outcome(outcome: SessionOutcome) -> &'static str {
+    match outcome {
+        SessionOutcome::Completed => "completed",
+        SessionOutcome::FinishedEarly => "finished early",
+        SessionOutcome::Skipped => "skipped",
+        SessionOutcome::Interrupted => "interrupted",
+    }
+}
+
+/// Appends a line per session ended to the daily note of the day it started.
+pub struct DailyNotes {
+    config: NotesConfig,
+    recorder: Recorder,
+}
+
+impl DailyNotes {
+    /// Work sessions are labelled with `task`.
+    pub fn new(config: NotesConfig, task: Option<String>) -> Self {
+        DailyNotes {
+            config,
+            recorder: Recorder::new(None, task),
+        }
+    }
+
+    /// Logs the work sessions given an intent once reviewed, see [`Recorder::with_reviews`].
+    pub fn with_reviews(mut self) -> Self {
+        self.recorder = self.recorder.with_reviews();
+        self
+    }
+
+    /// Logs the sessions until the event bus is dropped, and returns the first failure to write a
+    /// note, if any.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<NotesError> {
+        let mut failure = None;
+        for event in events {
+            if let Err(e) = self.handle(&event) {
+                failure.get_or_insert(e);
+            }
+        }
+        // Records cannot fail to be written without a history.
+        let _ = self.recorder.finish();
+        if let Err(e) = self.log_ended() {
+            failure.get_or_insert(e);
+        }
+        failure
+    }
+
+    fn handle(&mut self, event: &Event) -> Result<(), NotesError> {
+        let _ = self.recorder.handle(event);
+        self.log_ended()
+    }
+
+    /// Logs the sessions the recorder kept since the last call.
+    fn log_ended(&mut self) -> Result<(), NotesError> {
+        for record in self.recorder.take() {
+            if record.kind == SessionKind::Work || self.config.breaks {
+                self.log(&record)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The path of the note of the day `record` started on, and the line logging it, the session
+    /// having ended at `end`.
+    fn render(&self, record: &SessionRecord, end: DateTime<Local>) -> (PathBuf, String) {
+        let date = record
+            .started_at
+            .format(&self.config.date_format)
+            .to_string();
+        let path = expand_home(&fill(&self.config.path, &[("date", date.clone())]));
+        let values = [
+            ("date", date),
+            ("start", record.started_at.format("%H:%M").to_string()),
+            ("end", end.format("%H:%M").to_string()),
+            ("minutes", (record.elapsed / 60).to_string()),
+            (
+                "task",
+                record
+                    .task
+                    .clone()
+                    .unwrap_or_else(|| kind(record.kind).to_string()),
+            ),
+            ("intent", record.intent.clone().unwrap_or_default()),
+            (
+                "review",
+                record
+                    .review
+                    .map(|review| review.to_string())
+                    .unwrap_or_default(),
+            ),
+            ("kind", kind(record.kind).to_string()),
+            ("outcome", outcome(record.outcome).to_string()),
+        ];
+        (path, fill(&self.config.line, &values))
+    }
+
+    fn log(&self, record: &SessionRecord) -> Result<(), NotesError> {
+        let spent = TimeDelta::seconds((record.elapsed + record.paused) as i64);
+        let (path, line) = self.render(record, record.started_at + spent);
+        self.append(&path, &line)
+            .map_err(|source| NotesError { path, source })
+    }
+
+    /// Adds `line` to the note at `path`, created with its directory when missing.
+    fn append(&self, path: &Path, line: &str) -> io::Result<()> {
+        let note = match fs::read_to_string(path) {
+            Ok(note) => note,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, insert(&note, self.config.heading.as_deref(), line))
+    }
+}
+
+/// The level of the Markdown heading `line`, if it is one.
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    (level > 0 && line[level..].starts_with(' ')).then_some(level)
+}
+
+/// `note` with `line` added at the end of the section under `heading`, the heading being added at
+/// the end of the note when missing, or at the end of the note without a heading.
+fn insert(note: &str, heading: Option<&str>, line: &str) -> String {
+    let mut lines: Vec<&str> = note.lines().collect();
+    match heading.map(|heading| {
+        let found = lines.iter().position(|line| line.trim() == heading.trim());
+        (heading, found)
+    }) {
+        None => lines.push(line),
+        Some((heading, None)) => {
+            if lines.iter().any(|line| !line.trim().is_empty()) {
+                lines.push("");
+            }
+            lines.extend([heading, "", line]);
+        }
+        Some((heading, Some(at))) => {
+            let level = heading_level(heading.trim()).unwrap_or(usize::MAX);
+            let end = lines[at + 1..]
+                .iter()
+                .position(|line| heading_level(line).is_some_and(|other| other <= level))
+                .map_or(lines.len(), |next| at + 1 + next);
+            match lines[at + 1..end]
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+            {
+                Some(last) => lines.insert(at + 2 + last, line),
+                None => {
+                    lines.insert(at + 1, "");
+                    lines.insert(at + 2, line);
+                    // And a blank line before the next heading.
+                    if at + 3 < lines.len() {
+                        lines.insert(at + 3, "");
+                    }
+                }
+            }
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use super::*;
+    use crate::types::SessionType;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn config(dir: &str) -> NotesConfig {
+        let dir = env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join(dir);
+        let _ = fs::remove_dir_all(&dir);
+        NotesConfig {
+            path: format!("{}/{{{{date}}}}.md", dir.display()),
+            line: "- {{start}}–{{end}} {{task}} ({{outcome}})".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            heading: Some("## Pomodoros".to_string()),
+            breaks: false,
+        }
+    }
+
+    fn session(notes: &mut DailyNotes, session: SessionType, outcome: SessionOutcome) {
+        let events = [
+            Event::SessionStarted {
+                session: session.clone(),
+                cycle: 1,
+                duration: MINUTE * 25,
+            },
+            Event::SessionEnded {
+                session,
+                elapsed: MINUTE * 25,
+                outcome,
+            },
+        ];
+        for event in &events {
+            notes.handle(event).unwrap();
+        }
+    }
+
+    #[test]
+    fn renders_the_line_of_a_session() {
+        let notes = DailyNotes::new(config("render"), None);
+        let started_at = Local::now()
+            .with_time(chrono::NaiveTime::from_hms_opt(9, 5, 0).unwrap())
+            .unwrap();
+        let record = SessionRecord {
+            id: String::new(),
+            started_at,
+            kind: SessionKind::Work,
+            planned: 1500,
+            elapsed: 1500,
+            outcome: SessionOutcome::FinishedEarly,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: Some("Write the report".to_string()),
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        };
+        let (path, line) = notes.render(&record, started_at + TimeDelta::minutes(27));
+        assert!(
+            path.ends_with(format!("{}.md", started_at.format("%Y-%m-%d"))),
+            "{}",
+            path.display()
+        );
+        assert_eq!(line, "- 09:05–09:32 Write the report (finished early)");
+    }
+
+    #[test]
+    fn appends_the_work_sessions_under_the_heading() {
+        let config = config("append");
+        let mut notes = DailyNotes::new(config, Some("Review".to_string()));
+        let (path, _) = notes.render(
+            &SessionRecord {
+                id: String::new(),
+                started_at: Local::now(),
+                kind: SessionKind::Work,
+                planned: 0,
+                elapsed: 0,
+                outcome: SessionOutcome::Completed,
+                pauses: 0,
+                resets: 0,
+                paused: 0,
+                focus: None,
+                task: None,
+                pause_reasons: Vec::new(),
+                intent: None,
+                review: None,
+                tags: Vec::new(),
+                voided: false,
+                edits: Vec::new(),
+            },
+            Local::now(),
+        );
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "# Today\nNo meetings").unwrap();
+
+        session(
+            &mut notes,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            SessionOutcome::Completed,
+        );
+        session(
+            &mut notes,
+            SessionType {
+                label: "Break".to_string(),
+                ..SessionType::short_break()
+            },
+            SessionOutcome::Completed,
+        );
+        session(
+            &mut notes,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            SessionOutcome::Interrupted,
+        );
+
+        let note = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = note.lines().collect();
+        assert_eq!(lines[..4], ["# Today", "No meetings", "", "## Pomodoros"]);
+        assert_eq!(lines.len(), 7, "{}", note);
+        assert!(lines[5].ends_with("Review (completed)"), "{}", note);
+        assert!(lines[6].ends_with("Review (interrupted)"), "{}", note);
+    }
+
+    #[test]
+    fn inserts_the_lines_at_the_end_of_their_section() {
+        let note = "# Monday\n\n## Pomodoros\n\n- 09:00\n\n## Notes\nLunch\n";
+        assert_eq!(
+            insert(note, Some("## Pomodoros"), "- 10:00"),
+            "# Monday\n\n## Pomodoros\n\n- 09:00\n- 10:00\n\n## Notes\nLunch\n"
+        );
+        assert_eq!(
+            insert("## Pomodoros\n## Notes\n", Some("## Pomodoros"), "- 10:00"),
+            "## Pomodoros\n\n- 10:00\n\n## Notes\n"
+        );
+        assert_eq!(
+            insert("# Monday\n", Some("## Pomodoros"), "- 10:00"),
+            "# Monday\n\n## Pomodoros\n\n- 10:00\n"
+        );
+        assert_eq!(insert("", None, "- 10:00"), "- 10:00\n");
+    }
+}
@@ -0,0 +1,791 @@
+// filepath: src/notifier.rs
+//! Module routing the session transitions of a Pomodoro timer application to the notification
+//! backends reaching the user away from the desk, each backend receiving the triggers it is
+//! configured for. A backend can also be kept for escalations: it only receives the notifications
+//! the user did not acknowledge at the timer in time, e.g. to reach the phone of someone who left
+//! the desk.
+//!
+//! The notifications a backend could not send are queued for it, when it accepts them, and sent
+//! again later. Their texts can be templated in the configuration file, by trigger.
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use rustodoro_client::protocol::SessionKind;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::NotificationTemplate,
+    events::{Event, SessionOutcome},
+    outbox::{self, Backoff, Outbox, Queued},
+    quiet::Quiet,
+    settings::{PRIVATE_TASK, SharedSettings},
+    template::fill,
+};
+
+/// Wait before sending a failed notification again.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Shortest and longest delays accepted before escalating a notification, in seconds.
+pub const MIN_ESCALATE_AFTER: u64 = 10;
+pub const MAX_ESCALATE_AFTER: u64 = 60 * 60;
+/// The placeholders of the texts of the notifications.
+pub const PLACEHOLDERS: &[&str] = &["session", "task", "minutes", "cycle", "time"];
+
+/// A session transition notifications can be sent for. Skipped and interrupted sessions are left
+/// out, the user being at the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    WorkStarted,
+    WorkEnded,
+    BreakStarted,
+    BreakEnded,
+}
+
+impl Trigger {
+    /// The triggers of a backend not given any: the ends of the sessions.
+    pub const DEFAULT: [Trigger; 2] = [Trigger::WorkEnded, Trigger::BreakEnded];
+
+    /// Name of the trigger in the configuration file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Trigger::WorkStarted => "work_started",
+            Trigger::WorkEnded => "work_ended",
+            Trigger::BreakStarted => "break_started",
+            Trigger::BreakEnded => "break_ended",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub trigger: Trigger,
+    pub title: String,
+    pub message: String,
+    /// Whether the notification is sent as it was not acknowledged in time.
+    pub escalated: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("cannot send the {backend} notification: {message}")]
+pub struct NotifierError {
+    pub backend: &'static str,
+    pub message: String,
+}
+
+/// A notification backend.
+pub trait Notifier: Send {
+    fn send(&self, notification: &Notification) -> Result<(), NotifierError>;
+
+    /// How many times a notification is tried before giving up on it.
+    fn attempts(&self) -> u32 {
+        1
+    }
+
+    /// Whether the notifications reach the user as sounds or popups, and so are held back while
+    /// the timer keeps quiet.
+    fn interrupts(&self) -> bool {
+        true
+    }
+
+    /// Reverts what the notifications changed, when the timer quits.
+    fn restore(&self) -> Result<(), NotifierError> {
+        Ok(())
+    }
+
+    /// Name of the backend in the queue, for the backends whose notifications are worth sending
+    /// late rather than dropped when they cannot be sent.
+    fn queue(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// A backend with the triggers it receives.
+pub struct Route {
+    pub notifier: Box<dyn Notifier>,
+    pub triggers: Vec<Trigger>,
+    /// Only sends the notifications not acknowledged within this delay, when set.
+    pub escalate_after: Option<Duration>,
+}
+
+/// The texts of the notifications set in the configuration file, by trigger, with what fills them
+/// besides the events: the task and the cycle of the session.
+pub struct Templates {
+    templates: BTreeMap<Trigger, NotificationTemplate>,
+    settings: SharedSettings,
+    task: Option<String>,
+    cycle: u64,
+}
+
+impl Templates {
+    pub fn new(
+        templates: BTreeMap<Trigger, NotificationTemplate>,
+        settings: SharedSettings,
+    ) -> Self {
+        let task = settings.lock().unwrap().task.clone();
+        Templates {
+            templates,
+            settings,
+            task,
+            cycle: 0,
+        }
+    }
+
+    /// Keeps up with the task and the cycle of the sessions.
+    fn follow(&mut self, event: &Event) {
+        match event {
+            Event::SessionStarted { cycle, .. } => self.cycle = *cycle,
+            Event::TaskChanged { task } => self.task = Some(task.clone()),
+            _ => {}
+        }
+    }
+
+    /// `notification` of `event` with the texts of its trigger, if the configuration file sets
+    /// them.
+    fn apply(&self, event: &Event, notification: Notification) -> Notification {
+        let Some(template) = self.templates.get(&notification.trigger) else {
+            return notification;
+        };
+        let (session, duration) = match event {
+            Event::SessionStarted {
+                session, duration, ..
+            } => (session, duration),
+            Event::SessionEnded {
+                session, elapsed, ..
+            } => (session, elapsed),
+            _ => return notification,
+        };
+        // The task stays hidden in private mode, which the settings menu may have turned on.
+        let task = match &self.task {
+            Some(_) if self.settings.lock().unwrap().private => PRIVATE_TASK,
+            Some(task) => task,
+            None => "",
+        };
+        let values = [
+            ("session", session.to_string()),
+            ("task", task.to_string()),
+            ("minutes", duration.as_secs().div_ceil(60).to_string()),
+            ("cycle", self.cycle.to_string()),
+            ("time", Local::now().format("%H:%M").to_string()),
+        ];
+        let filled = |text: &Option<String>, default: String| {
+            text.as_deref().map_or(default, |text| fill(text, &values))
+        };
+        Notification {
+            title: filled(&template.title, notification.title),
+            message: filled(&template.body, notification.message),
+            ..notification
+        }
+    }
+}
+
+/// A notification held back until its route escalates it.
+struct Pending {
+    due: Instant,
+    route: usize,
+    notification: Notification,
+}
+
+/// Whether `event` shows that the user is at the timer, which acknowledges the notifications
+/// held back.
+fn acknowledges(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Acknowledged
+            | Event::Paused { .. }
+            | Event::Resumed { .. }
+            | Event::Reset
+            | Event::NextBreakSkipped
+            | Event::SessionEnded {
+                outcome: SessionOutcome::Skipped,
+                ..
+            }
+    )
+}
+
+pub struct Router {
+    routes: Vec<Route>,
+    templates: Option<Templates>,
+    retry_delay: Duration,
+    quiet: Option<Arc<Quiet>>,
+    outbox: Option<Outbox>,
+    backoff: Backoff,
+}
+
+impl Router {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Router {
+            routes,
+            templates: None,
+            retry_delay: RETRY_DELAY,
+            quiet: None,
+            outbox: None,
+            backoff: Backoff::starting(),
+        }
+    }
+
+    /// Queues the notifications which could not be sent in `outbox`, sending those queued there
+    /// before as well.
+    pub fn with_outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Words the notifications with `templates`.
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    /// Holds the interrupting notifications back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Sends `notification` through `notifier`, trying again as many times as it allows.
+    fn deliver(
+        &self,
+        notifier: &dyn Notifier,
+        notification: &Notification,
+    ) -> Result<(), NotifierError> {
+        let mut result = notifier.send(notification);
+        for _ in 1..notifier.attempts() {
+            if result.is_ok() {
+                break;
+            }
+            thread::sleep(self.retry_delay);
+            result = notifier.send(notification);
+        }
+        result
+    }
+
+    fn is_quiet_for(&self, route: &Route) -> bool {
+        let quiet = self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet());
+        quiet && route.notifier.interrupts()
+    }
+
+    /// Sends `notification` through `route` unless the timer keeps quiet, queueing it when it
+    /// cannot be sent and the backend accepts it.
+    fn send(
+        &mut self,
+        route: usize,
+        notification: &Notification,
+        queued: &mut Vec<Queued>,
+    ) -> Result<(), NotifierError> {
+        let route = &self.routes[route];
+        if self.is_quiet_for(route) {
+            return Ok(());
+        }
+        let result = self.deliver(route.notifier.as_ref(), notification);
+        let Some(backend) = route.notifier.queue().filter(|_| self.outbox.is_some()) else {
+            return result;
+        };
+        let waiting = queued.iter().any(|queued| queued.backend == backend);
+        match result {
+            // The backend is reachable again, the notifications waiting for it can follow.
+            Ok(()) if waiting => self.backoff.hurry(Instant::now()),
+            Ok(()) => {}
+            Err(_) => {
+                if !waiting {
+                    self.backoff.failed(Instant::now());
+                }
+                queued.push(Queued {
+                    backend: backend.to_string(),
+                    queued_at: Local::now(),
+                    notification: notification.clone(),
+                });
+                self.save(queued)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self, queued: &[Queued]) -> Result<(), NotifierError> {
+        match &self.outbox {
+            Some(outbox) => outbox.save(queued).map_err(|e| NotifierError {
+                backend: "queue",
+                message: e.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether some of `queued` can be sent through a route.
+    fn has_retries(&self, queued: &[Queued]) -> bool {
+        queued.iter().any(|queued| {
+            self.routes
+                .iter()
+                .any(|route| route.notifier.queue() == Some(&*queued.backend))
+        })
+    }
+
+    /// Sends the notifications queued for the routes, in batches, until a backend fails.
+    fn retry(&mut self, queued: &mut Vec<Queued>) -> Result<(), NotifierError> {
+        let mut failed = false;
+        for route in &self.routes {
+            let Some(backend) = route.notifier.queue() else {
+                continue;
+            };
+            if self.is_quiet_for(route) {
+                continue;
+            }
+            while let Some((notification, count)) = outbox::batch(queued, backend) {
+                if self
+                    .deliver(route.notifier.as_ref(), &notification)
+                    .is_err()
+                {
+                    failed = true;
+                    break;
+                }
+                outbox::remove_sent(queued, backend, count);
+            }
+        }
+        if failed {
+            self.backoff.failed(Instant::now());
+        } else {
+            self.backoff.succeeded(Instant::now());
+        }
+        self.save(queued)
+    }
+
+    /// Sends the notifications until the event bus is dropped, then lets the backends restore
+    /// what they changed, and returns the first failure, which does not stop the other
+    /// notifications. The notifications held back for escalation are dropped when quitting, those
+    /// queued are kept for the next run.
+    pub fn run(mut self, events: Receiver<Event>) -> Option<NotifierError> {
+        let mut failure = None;
+        let mut pending: Vec<Pending> = Vec::new();
+        let mut queued = match &self.outbox {
+            Some(outbox) => outbox.load().unwrap_or_else(|e| {
+                failure = Some(NotifierError {
+                    backend: "queue",
+                    message: e.to_string(),
+                });
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        outbox::expire(&mut queued, Local::now());
+        loop {
+            // Only wake up without an event when an escalation or a retry is due.
+            let retry = self.has_retries(&queued).then_some(self.backoff.next);
+            let wake = pending.iter().map(|pending| pending.due).chain(retry).min();
+            let received = match wake {
+                Some(due) => {
+                    match events.recv_timeout(due.saturating_duration_since(Instant::now())) {
+                        Ok(event) => Some(event),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match events.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => break,
+                },
+            };
+            if let Some(event) = received {
+                if acknowledges(&event) {
+                    pending.clear();
+                }
+                if let Some(templates) = &mut self.templates {
+                    templates.follow(&event);
+                }
+                let notification = notification(&event).map(|notification| match &self.templates {
+                    Some(templates) => templates.apply(&event, notification),
+                    None => notification,
+                });
+                if let Some(notification) = notification {
+                    for index in 0..self.routes.len() {
+                        let route = &self.routes[index];
+                        if !route.triggers.contains(&notification.trigger) {
+                            continue;
+                        }
+                        match route.escalate_after {
+                            Some(delay) => pending.push(Pending {
+                                due: Instant::now() + delay,
+                                route: index,
+                                notification: Notification {
+                                    escalated: true,
+                                    ..notification.clone()
+                                },
+                            }),
+                            None => {
+                                if let Err(e) = self.send(index, &notification, &mut queued) {
+                                    failure.get_or_insert(e);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            let now = Instant::now();
+            let (due, waiting) = pending.into_iter().partition(|pending| pending.due <= now);
+            pending = waiting;
+            for Pending {
+                route,
+                notification,
+                ..
+            } in due
+            {
+                if let Err(e) = self.send(route, &notification, &mut queued) {
+                    failure.get_or_insert(e);
+                }
+            }
+            if self.has_retries(&queued)
+                && self.backoff.next <= now
+                && let Err(e) = self.retry(&mut queued)
+            {
+                failure.get_or_insert(e);
+            }
+        }
+        for route in &self.routes {
+            if let Err(e) = route.notifier.restore() {
+                failure.get_or_insert(e);
+            }
+        }
+        failure
+    }
+}
+
+/// The notification announcing `event`, for the session transitions only.
+fn notification(event: &Event) -> Option<Notification> {
+    let (trigger, title, message) = match event {
+        Event::SessionStarted {
+            session, duration, ..
+        } => {
+            let minutes = duration.as_secs().div_ceil(60);
+            match session.kind {
+                SessionKind::Work => (
+                    Trigger::WorkStarted,
+                    format!("{} started", session),
+                    format!("{} minutes of focus.", minutes),
+                ),
+                _ => (
+                    Trigger::BreakStarted,
+                    format!("{} started", session),
+                    format!("Step away for {} minutes.", minutes),
+                ),
+            }
+        }
+        Event::SessionEnded {
+            session, outcome, ..
+        } if outcome.is_done() => match session.kind {
+            SessionKind::Work => (
+                Trigger::WorkEnded,
+                format!("{} completed", session),
+                "Time for a break.".to_string(),
+            ),
+            _ => (
+                Trigger::BreakEnded,
+                format!("{} is over", session),
+                "Back to work.".to_string(),
+            ),
+        },
+        _ => return None,
+    };
+    Some(Notification {
+        trigger,
+        title,
+        message,
+        escalated: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex, mpsc},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{SessionType, settings::Settings};
+
+    /// Records the titles it is sent, failing when asked to.
+    struct Recording {
+        sent: Arc<Mutex<Vec<String>>>,
+        fails: bool,
+    }
+
+    impl Notifier for Recording {
+        fn send(&self, notification: &Notification) -> Result<(), NotifierError> {
+            self.sent.lock().unwrap().push(notification.title.clone());
+            if self.fails {
+                Err(NotifierError {
+                    backend: "test",
+                    message: "offline".to_string(),
+                })
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Fails its first sends, as many as given, and counts the restorations.
+    struct Flaky {
+        failures: Arc<Mutex<u32>>,
+        restored: Arc<Mutex<u32>>,
+    }
+
+    impl Notifier for Flaky {
+        fn send(&self, _: &Notification) -> Result<(), NotifierError> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures == 0 {
+                return Ok(());
+            }
+            *failures -= 1;
+            Err(NotifierError {
+                backend: "flaky",
+                message: "timed out".to_string(),
+            })
+        }
+
+        fn attempts(&self) -> u32 {
+            3
+        }
+
+        fn restore(&self) -> Result<(), NotifierError> {
+            *self.restored.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    /// Sends the messages while online, queueing them otherwise.
+    struct Unreliable {
+        online: Arc<Mutex<bool>>,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Notifier for Unreliable {
+        fn send(&self, notification: &Notification) -> Result<(), NotifierError> {
+            if !*self.online.lock().unwrap() {
+                return Err(NotifierError {
+                    backend: "unreliable",
+                    message: "network is unreachable".to_string(),
+                });
+            }
+            self.sent.lock().unwrap().push(notification.message.clone());
+            Ok(())
+        }
+
+        fn queue(&self) -> Option<&'static str> {
+            Some("unreliable")
+        }
+    }
+
+    fn ended(session: SessionType, outcome: SessionOutcome) -> Event {
+        Event::SessionEnded {
+            session,
+            elapsed: Duration::from_secs(60),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn notifies_the_session_transitions() {
+        let started = Event::SessionStarted {
+            session: SessionType::long_break(),
+            cycle: 4,
+            duration: Duration::from_secs(15 * 60),
+        };
+        let break_started = notification(&started).unwrap();
+        assert_eq!(break_started.trigger, Trigger::BreakStarted);
+        assert_eq!(break_started.message, "Step away for 15 minutes.");
+        assert_eq!(
+            notification(&ended(SessionType::work(), SessionOutcome::Completed))
+                .map(|notification| notification.title),
+            Some("Work session completed".to_string())
+        );
+        assert_eq!(
+            notification(&ended(SessionType::short_break(), SessionOutcome::Skipped)),
+            None
+        );
+    }
+
+    #[test]
+    fn fills_the_templates_of_the_triggers() {
+        let settings = Settings {
+            task: Some("Write the report".to_string()),
+            ..Settings::default()
+        }
+        .into_shared();
+        let template = NotificationTemplate {
+            title: Some("{{task}} done".to_string()),
+            body: None,
+        };
+        let mut templates = Templates::new(
+            BTreeMap::from([(Trigger::WorkEnded, template)]),
+            Arc::clone(&settings),
+        );
+        templates.follow(&Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 2,
+            duration: Duration::from_secs(25 * 60),
+        });
+        let word = |templates: &Templates, event: &Event| {
+            templates.apply(event, notification(event).unwrap())
+        };
+
+        let work_ended = ended(SessionType::work(), SessionOutcome::Completed);
+        let filled = word(&templates, &work_ended);
+        assert_eq!(filled.title, "Write the report done");
+        assert_eq!(filled.message, "Time for a break.");
+        let break_ended = ended(SessionType::short_break(), SessionOutcome::Completed);
+        assert_eq!(word(&templates, &break_ended).title, "Short break is over");
+
+        settings.lock().unwrap().private = true;
+        assert_eq!(word(&templates, &work_ended).title, "Focus done");
+    }
+
+    #[test]
+    fn routes_each_trigger_to_its_backends() {
+        let work = Arc::new(Mutex::new(Vec::new()));
+        let all = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new(vec![
+            Route {
+                notifier: Box::new(Recording {
+                    sent: Arc::clone(&work),
+                    fails: false,
+                }),
+                triggers: vec![Trigger::WorkEnded],
+                escalate_after: None,
+            },
+            Route {
+                notifier: Box::new(Recording {
+                    sent: Arc::clone(&all),
+                    fails: true,
+                }),
+                triggers: Trigger::DEFAULT.to_vec(),
+                escalate_after: None,
+            },
+        ]);
+        let (tx, rx) = mpsc::channel();
+        tx.send(ended(SessionType::work(), SessionOutcome::Completed))
+            .unwrap();
+        tx.send(ended(SessionType::short_break(), SessionOutcome::Completed))
+            .unwrap();
+        drop(tx);
+
+        let failure = router.run(rx).unwrap();
+        assert_eq!(
+            failure.to_string(),
+            "cannot send the test notification: offline"
+        );
+        assert_eq!(*work.lock().unwrap(), ["Work session completed"]);
+        assert_eq!(
+            *all.lock().unwrap(),
+            ["Work session completed", "Short break is over"]
+        );
+    }
+
+    #[test]
+    fn escalates_the_notifications_not_acknowledged() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new(vec![Route {
+            notifier: Box::new(Recording {
+                sent: Arc::clone(&sent),
+                fails: false,
+            }),
+            triggers: Trigger::DEFAULT.to_vec(),
+            escalate_after: Some(Duration::from_millis(20)),
+        }]);
+        let (tx, rx) = mpsc::channel();
+        let router = thread::spawn(move || router.run(rx));
+        // Acknowledged right away, the end of the work session is not escalated.
+        tx.send(ended(SessionType::work(), SessionOutcome::Completed))
+            .unwrap();
+        tx.send(Event::Acknowledged).unwrap();
+        tx.send(ended(SessionType::short_break(), SessionOutcome::Completed))
+            .unwrap();
+        thread::sleep(Duration::from_millis(200));
+        // Quitting drops what is still held back.
+        tx.send(ended(SessionType::work(), SessionOutcome::Completed))
+            .unwrap();
+        drop(tx);
+
+        assert!(router.join().unwrap().is_none());
+        assert_eq!(*sent.lock().unwrap(), ["Short break is over"]);
+    }
+
+    #[test]
+    fn retries_and_restores_the_backends() {
+        let failures = Arc::new(Mutex::new(2));
+        let restored = Arc::new(Mutex::new(0));
+        let router = Router {
+            routes: vec![Route {
+                notifier: Box::new(Flaky {
+                    failures: Arc::clone(&failures),
+                    restored: Arc::clone(&restored),
+                }),
+                triggers: Trigger::DEFAULT.to_vec(),
+                escalate_after: None,
+            }],
+            templates: None,
+            retry_delay: Duration::ZERO,
+            quiet: None,
+            outbox: None,
+            backoff: Backoff::starting(),
+        };
+        let (tx, rx) = mpsc::channel();
+        tx.send(ended(SessionType::work(), SessionOutcome::Completed))
+            .unwrap();
+        drop(tx);
+
+        assert!(router.run(rx).is_none());
+        assert_eq!(*failures.lock().unwrap(), 0);
+        assert_eq!(*restored.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn queues_what_cannot_be_sent_until_the_backend_is_back() {
+        let path = std::env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join("outbox.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let online = Arc::new(Mutex::new(false));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let router = Router {
+            routes: vec![Route {
+                notifier: Box::new(Unreliable {
+                    online: Arc::clone(&online),
+                    sent: Arc::clone(&sent),
+                }),
+                triggers: Trigger::DEFAULT.to_vec(),
+                escalate_after: None,
+            }],
+            templates: None,
+            retry_delay: Duration::ZERO,
+            quiet: None,
+            outbox: Some(Outbox::new(path.clone())),
+            backoff: Backoff::new(Duration::from_millis(50)),
+        };
+        let (tx, rx) = mpsc::channel();
+        let router = thread::spawn(move || router.run(rx));
+        tx.send(ended(SessionType::work(), SessionOutcome::Completed))
+            .unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(Outbox::new(path.clone()).load().unwrap().len(), 1);
+        *online.lock().unwrap() = true;
+        thread::sleep(Duration::from_millis(200));
+        drop(tx);
+
+        // Not a failure, the notification was sent late.
+        assert!(router.join().unwrap().is_none());
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("Time for a break. (at "));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,87 @@
+// filepath: src/ntfy.rs
+//! Module sending the notifications of a Pomodoro timer application to an ntfy topic, so that
+//! they reach the phone subscribed to it.
+use std::time::Duration;
+
+use ureq::Agent;
+
+use crate::{
+    config::NtfyConfig,
+    notifier::{Notification, Notifier, NotifierError, Trigger},
+};
+
+/// Longest wait for the ntfy server, notifications being sent in the background.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Ntfy {
+    agent: Agent,
+    url: String,
+    token: Option<String>,
+}
+
+impl Ntfy {
+    pub fn new(config: &NtfyConfig) -> Self {
+        Ntfy {
+            agent: Agent::config_builder()
+                .timeout_global(Some(TIMEOUT))
+                .build()
+                .into(),
+            url: topic_url(&config.server, &config.topic),
+            token: config.token.clone(),
+        }
+    }
+}
+
+fn topic_url(server: &str, topic: &str) -> String {
+    format!("{}/{}", server.trim_end_matches('/'), topic)
+}
+
+/// The ntfy tags of a trigger, shown as emoji in front of the title.
+fn tags(trigger: Trigger) -> &'static str {
+    match trigger {
+        Trigger::WorkStarted | Trigger::WorkEnded => "tomato",
+        Trigger::BreakStarted | Trigger::BreakEnded => "coffee",
+    }
+}
+
+impl Notifier for Ntfy {
+    fn send(&self, notification: &Notification) -> Result<(), NotifierError> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .header("Title", &notification.title)
+            .header("Tags", tags(notification.trigger));
+        if notification.escalated {
+            // Makes the phone vibrate longer, the user having missed the timer.
+            request = request.header("Priority", "high");
+        }
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request
+            .send(&notification.message)
+            .map(drop)
+            .map_err(|e| NotifierError {
+                backend: "ntfy",
+                message: e.to_string(),
+            })
+    }
+
+    fn queue(&self) -> Option<&'static str> {
+        Some("ntfy")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posts_to_the_topic_url() {
+        assert_eq!(
+            topic_url("https://ntfy.sh/", "rustodoro-42"),
+            "https://ntfy.sh/rustodoro-42"
+        );
+        assert_eq!(tags(Trigger::BreakEnded), "coffee");
+    }
+}
@@ -0,0 +1,365 @@
+// filepath: src/outbox.rs
+//! Module keeping the notifications of a Pomodoro timer application which could not reach their
+//! backend, e.g. while the network is down, in a file so that they are not lost: they are sent
+//! again with a growing delay while the timer runs, several at once as a single notification, and
+//! by `rustodoro queue flush` otherwise. They are kept for a day, after which they are stale.
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{self, Cli, ConfigError, QueueAction},
+    credentials::{self, CredentialError},
+    history, keychain,
+    notifier::{Notification, Notifier, NotifierError},
+};
+
+/// Delay before sending the queued notifications again, doubled on each failure up to the longest.
+const FIRST_RETRY: Duration = Duration::from_secs(30);
+const LONGEST_RETRY: Duration = Duration::from_secs(15 * 60);
+/// Most notifications sent as one.
+const BATCH: usize = 10;
+/// Age after which a queued notification is dropped.
+const MAX_AGE: TimeDelta = TimeDelta::days(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutboxError {
+    #[error("cannot access the queue {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("invalid queue {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("cannot locate the queue, set $XDG_DATA_HOME or $HOME")]
+    NoQueue,
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Credential(#[from] CredentialError),
+}
+
+/// A notification waiting for its backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Queued {
+    pub backend: String,
+    pub queued_at: DateTime<Local>,
+    pub notification: Notification,
+}
+
+/// The file of the queued notifications, one JSON record per line.
+pub struct Outbox {
+    path: PathBuf,
+}
+
+/// Returns the location of the queue, next to the history.
+pub fn default_outbox_path() -> Option<PathBuf> {
+    history::default_history_path(None).map(|path| path.with_file_name("outbox.jsonl"))
+}
+
+impl Outbox {
+    pub fn new(path: PathBuf) -> Self {
+        Outbox { path }
+    }
+
+    fn io_error(&self, source: io::Error) -> OutboxError {
+        OutboxError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// The queued notifications, oldest first, a missing file meaning there is none.
+    pub fn load(&self) -> Result<Vec<Queued>, OutboxError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.io_error(e)),
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|source| OutboxError::Parse {
+                    path: self.path.clone(),
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces the queued notifications with `queued`, removing the file when there is none.
+    pub fn save(&self, queued: &[Queued]) -> Result<(), OutboxError> {
+        if queued.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(self.io_error(e)),
+                _ => Ok(()),
+            };
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| self.io_error(e))?;
+        }
+        let content: String = queued
+            .iter()
+            .map(|queued| serde_json::to_string(queued).expect("notifications always serialize"))
+            .map(|line| line + "\n")
+            .collect();
+        let temporary = self.path.with_extension("jsonl.tmp");
+        fs::write(&temporary, content)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|e| self.io_error(e))
+    }
+}
+
+/// Drops the notifications stale on `now`, returning how many.
+pub fn expire(queued: &mut Vec<Queued>, now: DateTime<Local>) -> usize {
+    let before = queued.len();
+    queued.retain(|queued| now - queued.queued_at < MAX_AGE);
+    before - queued.len()
+}
+
+/// The notification sending the first `BATCH` notifications queued for `backend` at once, with
+/// how many it covers.
+pub fn batch(queued: &[Queued], backend: &str) -> Option<(Notification, usize)> {
+    let batch: Vec<&Queued> = queued
+        .iter()
+        .filter(|queued| queued.backend == backend)
+        .take(BATCH)
+        .collect();
+    let first = batch.first()?;
+    let at = |queued: &Queued| queued.queued_at.format("%H:%M").to_string();
+    let notification = if batch.len() == 1 {
+        Notification {
+            message: format!("{} (at {})", first.notification.message, at(first)),
+            ..first.notification.clone()
+        }
+    } else {
+        Notification {
+            title: format!("{} notifications missed", batch.len()),
+            message: batch
+                .iter()
+                .map(|queued| format!("{} {}", at(queued), queued.notification.title))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            escalated: batch.iter().any(|queued| queued.notification.escalated),
+            ..first.notification.clone()
+        }
+    };
+    Some((notification, batch.len()))
+}
+
+/// Removes the first `count` notifications queued for `backend`, once sent.
+pub fn remove_sent(queued: &mut Vec<Queued>, backend: &str, count: usize) {
+    let mut left = count;
+    queued.retain(|queued| {
+        if left > 0 && queued.backend == backend {
+            left -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+/// When to send the queued notifications again, waiting longer after each failure.
+pub struct Backoff {
+    first: Duration,
+    delay: Duration,
+    pub next: Instant,
+}
+
+impl Backoff {
+    /// Waits `first` before the first attempt.
+    pub fn new(first: Duration) -> Self {
+        Backoff {
+            first,
+            delay: first,
+            next: Instant::now() + first,
+        }
+    }
+
+    pub fn starting() -> Self {
+        Backoff::new(FIRST_RETRY)
+    }
+
+    /// Delays the next attempt after one failed at `now`.
+    pub fn failed(&mut self, now: Instant) {
+        self.next = now + self.delay;
+        self.delay = (self.delay * 2).min(LONGEST_RETRY.max(self.first));
+    }
+
+    /// Starts over after an attempt which succeeded at `now`.
+    pub fn succeeded(&mut self, now: Instant) {
+        self.delay = self.first;
+        self.next = now + self.first;
+    }
+
+    /// Makes the next attempt right away, a backend being reachable again.
+    pub fn hurry(&mut self, now: Instant) {
+        self.next = now;
+    }
+}
+
+/// The backends the queued notifications can be sent to outside of a run.
+fn notifiers(cli: &Cli) -> Result<Vec<Box<dyn Notifier>>, OutboxError> {
+    let mut effective = config::resolve(cli)?;
+    credentials::fill(&mut effective, &keychain::Os)?;
+    #[cfg(feature = "ntfy")]
+    let ntfy = effective
+        .ntfy
+        .value
+        .as_ref()
+        .map(|ntfy| Box::new(crate::ntfy::Ntfy::new(ntfy)) as Box<dyn Notifier>);
+    #[cfg(not(feature = "ntfy"))]
+    let ntfy = {
+        let _ = effective;
+        None
+    };
+    Ok(ntfy.into_iter().collect())
+}
+
+/// Sends the notifications queued for each of `notifiers`, returning how many were sent with the
+/// failures, the notifications of a backend which failed being kept.
+fn flush(queued: &mut Vec<Queued>, notifiers: &[Box<dyn Notifier>]) -> (usize, Vec<NotifierError>) {
+    let mut sent = 0;
+    let mut failures = Vec::new();
+    for notifier in notifiers {
+        let Some(backend) = notifier.queue() else {
+            continue;
+        };
+        while let Some((notification, count)) = batch(queued, backend) {
+            if let Err(e) = notifier.send(&notification) {
+                failures.push(e);
+                break;
+            }
+            remove_sent(queued, backend, count);
+            sent += count;
+        }
+    }
+    (sent, failures)
+}
+
+fn run_action(cli: &Cli, action: &QueueAction) -> Result<(), OutboxError> {
+    let outbox = Outbox::new(default_outbox_path().ok_or(OutboxError::NoQueue)?);
+    let mut queued = outbox.load()?;
+    let expired = expire(&mut queued, Local::now());
+    if expired > 0 {
+        println!("{} notifications older than a day were dropped.", expired);
+    }
+    match action {
+        QueueAction::Status => {
+            if queued.is_empty() {
+                println!("No notification is queued.");
+            }
+            let mut backends: Vec<&str> = queued.iter().map(|queued| &*queued.backend).collect();
+            backends.sort();
+            backends.dedup();
+            for backend in backends {
+                let mut of = queued.iter().filter(|queued| queued.backend == backend);
+                let oldest = of.next().expect("the backend has a notification");
+                println!(
+                    "{}: {} queued, the oldest at {}",
+                    backend,
+                    of.count() + 1,
+                    oldest.queued_at.format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+        QueueAction::Flush => {
+            let notifiers = notifiers(cli)?;
+            let (sent, failures) = flush(&mut queued, &notifiers);
+            for e in failures {
+                eprintln!("warning: {}", e);
+            }
+            println!(
+                "{} notifications sent, {} still queued.",
+                sent,
+                queued.len()
+            );
+        }
+        QueueAction::Add | QueueAction::List | QueueAction::Remove { .. } | QueueAction::Run => {
+            unreachable!("the runs are queued by the run queue")
+        }
+    }
+    outbox.save(&queued)
+}
+
+/// Runs the `queue` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, action: &QueueAction) -> i32 {
+    match run_action(cli, action) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::notifier::Trigger;
+
+    fn queued(backend: &str, title: &str, hour: u32) -> Queued {
+        Queued {
+            backend: backend.to_string(),
+            queued_at: Local.with_ymd_and_hms(2026, 10, 14, hour, 25, 0).unwrap(),
+            notification: Notification {
+                trigger: Trigger::WorkEnded,
+                title: title.to_string(),
+                message: "Time for a break.".to_string(),
+                escalated: false,
+            },
+        }
+    }
+
+    #[test]
+    fn batches_the_notifications_of_a_backend() {
+        let mut all = vec![
+            queued("ntfy", "Work session completed", 9),
+            queued("other", "Short break is over", 9),
+            queued("ntfy", "Work session completed", 10),
+        ];
+        let (notification, count) = batch(&all, "ntfy").unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(notification.title, "2 notifications missed");
+        assert_eq!(
+            notification.message,
+            "09:25 Work session completed\n10:25 Work session completed"
+        );
+        remove_sent(&mut all, "ntfy", count);
+        assert_eq!(all, [queued("other", "Short break is over", 9)]);
+        let (single, _) = batch(&all, "other").unwrap();
+        assert_eq!(single.message, "Time for a break. (at 09:25)");
+        assert_eq!(batch(&all, "ntfy"), None);
+
+        let mut stale = vec![queued("ntfy", "Work session completed", 9)];
+        let tomorrow = Local.with_ymd_and_hms(2026, 10, 15, 10, 0, 0).unwrap();
+        assert_eq!(expire(&mut stale, tomorrow), 1);
+    }
+
+    #[test]
+    fn waits_longer_after_each_failure() {
+        let now = Instant::now();
+        let mut backoff = Backoff::starting();
+        backoff.failed(now);
+        assert_eq!(backoff.next, now + FIRST_RETRY);
+        backoff.failed(now);
+        assert_eq!(backoff.next, now + FIRST_RETRY * 2);
+        for _ in 0..10 {
+            backoff.failed(now);
+        }
+        assert_eq!(backoff.next, now + LONGEST_RETRY);
+        backoff.succeeded(now);
+        assert_eq!(backoff.next, now + FIRST_RETRY);
+        backoff.failed(now);
+        backoff.failed(now);
+        assert_eq!(backoff.next, now + FIRST_RETRY * 2);
+    }
+}
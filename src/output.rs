@@ -0,0 +1,182 @@
+// filepath: src/output.rs
+//! Module writing to the terminal for a Pomodoro timer application while it runs. The keys are
+//! read in raw mode meanwhile, where a line feed does not go back to the start of the line, and
+//! the progress bar of the running session is redrawn in place: every line written during a run
+//! goes through here, so that its line endings are right and the bar is hidden while it is
+//! written, then drawn again below it. While the user is asked something, the bar is not drawn
+//! and the lines of the other modules wait for the answer.
+use std::{
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    execute, queue,
+    terminal::{self, Clear, ClearType},
+};
+use indicatif::{ProgressBar, ProgressDrawTarget};
+
+use crate::render::{self, Color};
+
+struct Terminal {
+    /// The progress bar drawn at the bottom of the terminal, if any.
+    bar: Option<ProgressBar>,
+    /// The output held while the user is asked something.
+    held: Option<Vec<String>>,
+    /// Color of the progress bar, kept when it is laid out again.
+    color: Color,
+}
+
+static TERMINAL: Mutex<Terminal> = Mutex::new(Terminal {
+    bar: None,
+    held: None,
+    color: Color::Cyan,
+});
+
+/// Keeps a progress bar below the output while alive.
+pub struct Shown {
+    _private: (),
+}
+
+impl Drop for Shown {
+    fn drop(&mut self) {
+        TERMINAL.lock().unwrap().bar = None;
+    }
+}
+
+/// Writes the output above `bar`, filling in `color`, for as long as the returned guard is alive.
+pub fn show(bar: &ProgressBar, color: Color) -> Shown {
+    let mut terminal = TERMINAL.lock().unwrap();
+    terminal.bar = Some(bar.clone());
+    terminal.color = color;
+    Shown { _private: () }
+}
+
+/// Lays the progress bar out again for a terminal now `columns` wide.
+pub fn resize(columns: u16) {
+    let terminal = TERMINAL.lock().unwrap();
+    // Hidden while asking, the bar is drawn again afterwards anyway.
+    let Some(bar) = terminal.bar.as_ref().filter(|bar| !bar.is_hidden()) else {
+        return;
+    };
+    // Spinners have no length, and no bar to narrow.
+    if bar.length().is_some()
+        && let Ok(style) = render::progress_style(columns, terminal.color)
+    {
+        bar.set_style(style);
+    }
+    // The line drawn for the former width may have wrapped.
+    let _ = execute!(
+        io::stderr(),
+        MoveToColumn(0),
+        Clear(ClearType::FromCursorDown)
+    );
+    bar.force_draw();
+}
+
+/// `text` with its line feeds going back to the start of the line, for a terminal in raw mode.
+fn crlf(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+/// Writes `text` to the standard output, the progress bar hidden meanwhile.
+fn write_now(bar: Option<&ProgressBar>, text: &str) {
+    let text = if terminal::is_raw_mode_enabled().unwrap_or(false) {
+        crlf(text)
+    } else {
+        text.to_string()
+    };
+    let write = || {
+        let mut stdout = io::stdout();
+        // Nothing to do about a terminal gone.
+        let _ = stdout
+            .write_all(text.as_bytes())
+            .and_then(|()| stdout.flush());
+    };
+    match bar {
+        Some(bar) => bar.suspend(write),
+        None => write(),
+    }
+}
+
+/// Writes `text`, or holds it while the user is asked something.
+fn write(text: String) {
+    let mut terminal = TERMINAL.lock().unwrap();
+    match &mut terminal.held {
+        Some(held) => held.push(text),
+        None => write_now(terminal.bar.as_ref(), &text),
+    }
+}
+
+/// Writes `text` as it is, e.g. a banner or the start of a line.
+pub fn print(text: &str) {
+    write(text.to_string());
+}
+
+/// Writes `text` on lines of its own.
+pub fn line(text: impl AsRef<str>) {
+    write(format!("{}\n", text.as_ref()));
+}
+
+/// Clears the line the cursor is on, for `text` to replace it.
+pub fn replace_line(text: &str) {
+    let mut bytes = Vec::new();
+    // Writing to memory does not fail.
+    let _ = queue!(bytes, Clear(ClearType::CurrentLine));
+    write(format!("{}\r{}", String::from_utf8_lossy(&bytes), text));
+}
+
+/// Runs `ask`, which asks the user something, with the progress bar not drawn meanwhile and the
+/// output of the other modules held until it returns.
+pub fn interact<T>(ask: impl FnOnce() -> T) -> T {
+    let bar = {
+        let mut terminal = TERMINAL.lock().unwrap();
+        if terminal.held.is_some() {
+            // Asking again within the same interaction.
+            drop(terminal);
+            return ask();
+        }
+        terminal.held = Some(Vec::new());
+        terminal.bar.clone().filter(|bar| !bar.is_hidden())
+    };
+    if let Some(bar) = &bar {
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+        // The bar is drawn on the standard error, where it is left.
+        let _ = execute!(io::stderr(), Clear(ClearType::CurrentLine));
+        eprint!("\r");
+    }
+    let answer = ask();
+    let mut terminal = TERMINAL.lock().unwrap();
+    let held = terminal.held.take().unwrap_or_default();
+    if let Some(bar) = bar.filter(|bar| !bar.is_finished()) {
+        bar.set_draw_target(ProgressDrawTarget::stderr());
+        bar.force_draw();
+    }
+    for text in held {
+        write_now(terminal.bar.as_ref(), &text);
+    }
+    answer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_the_lines_for_raw_mode() {
+        assert_eq!(crlf("a\nb\r\nc"), "a\r\nb\r\nc");
+        assert_eq!(crlf("\rStarting in 3s"), "\rStarting in 3s");
+    }
+
+    #[test]
+    fn holds_the_output_while_asking() {
+        let held = interact(|| {
+            print("");
+            interact(|| print(""));
+            TERMINAL.lock().unwrap().held.clone()
+        });
+        assert_eq!(held, Some(vec![String::new(), String::new()]));
+        assert!(TERMINAL.lock().unwrap().held.is_none());
+    }
+}
@@ -0,0 +1,272 @@
+// filepath: src/pace.rs
+//! Module reminding the user of a Pomodoro timer application of the daily goal when they fall
+//! behind the pace needed to reach it within the workday.
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Timelike};
+use serde::Deserialize;
+
+use crate::{desktop::Notification, events::Event, quiet::Quiet, settings::SharedSettings};
+
+/// Shortest time between two reminders.
+const REMINDER_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A time of the day, written "HH:MM".
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TimeOfDay(pub NaiveTime);
+
+impl TimeOfDay {
+    pub const fn new(hour: u32, minute: u32) -> Self {
+        match NaiveTime::from_hms_opt(hour, minute, 0) {
+            Some(time) => TimeOfDay(time),
+            None => panic!("invalid time of day"),
+        }
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NaiveTime::parse_from_str(s.trim(), "%H:%M")
+            .map(TimeOfDay)
+            .map_err(|_| format!("must be a time such as 09:00, got `{}`", s))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0.hour(), self.0.minute())
+    }
+}
+
+/// Where the user stands against the daily goal when behind pace.
+#[derive(Debug, PartialEq)]
+pub struct Behind {
+    pub done: u64,
+    pub goal: u64,
+    /// Pomodoros per hour needed to reach the goal by the end of the workday.
+    pub needed_per_hour: f64,
+}
+
+/// Compares `done` pomodoros at `now` with a steady pace reaching `goal` at the end of the
+/// workday. Nothing is reported outside of the workday or once the goal is reached.
+pub fn check(
+    done: u64,
+    goal: u64,
+    start: TimeOfDay,
+    end: TimeOfDay,
+    now: NaiveTime,
+) -> Option<Behind> {
+    if done >= goal || now <= start.0 || now >= end.0 {
+        return None;
+    }
+    let workday = (end.0 - start.0).as_seconds_f64();
+    let passed = (now - start.0).as_seconds_f64();
+    let expected = (goal as f64 * passed / workday).floor() as u64;
+    if done >= expected {
+        return None;
+    }
+    let hours_left = (end.0 - now).as_seconds_f64() / 3600.0;
+    Some(Behind {
+        done,
+        goal,
+        needed_per_hour: (goal - done) as f64 / hours_left,
+    })
+}
+
+impl Behind {
+    pub fn message(&self, end: TimeOfDay) -> String {
+        format!(
+            "{} of {} pomodoros done. About {:.1} per hour until {} would still get you there.",
+            self.done, self.goal, self.needed_per_hour, end
+        )
+    }
+}
+
+/// Counts the pomodoros of the day from the events and sends a notification when falling behind
+/// pace, at most once an hour.
+pub struct PaceReminder {
+    settings: SharedSettings,
+    day: NaiveDate,
+    done: u64,
+    last_reminder: Option<DateTime<Local>>,
+    quiet: Option<Arc<Quiet>>,
+}
+
+impl PaceReminder {
+    /// `done` is the number of pomodoros completed on `day` before this run.
+    pub fn new(settings: SharedSettings, day: NaiveDate, done: u64) -> Self {
+        PaceReminder {
+            settings,
+            day,
+            done,
+            last_reminder: None,
+            quiet: None,
+        }
+    }
+
+    /// Holds the reminders back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    /// Handles the events until the event bus is dropped.
+    pub fn run(mut self, events: Receiver<Event>) {
+        for event in events {
+            if let Some(message) = self.handle(&event, Local::now())
+                && !self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet())
+            {
+                // The timer already reports notification failures.
+                let _ = Notification::new()
+                    .summary("Pomodoro Timer")
+                    .body(&message)
+                    .icon("dialog-information")
+                    .show();
+            }
+        }
+    }
+
+    /// Returns the reminder to send after `event`, if any.
+    pub fn handle(&mut self, event: &Event, now: DateTime<Local>) -> Option<String> {
+        let Event::SessionEnded {
+            session, outcome, ..
+        } = event
+        else {
+            return None;
+        };
+        if now.date_naive() != self.day {
+            self.day = now.date_naive();
+            self.done = 0;
+        }
+        if session.is_work() && outcome.is_done() {
+            self.done += 1;
+        }
+        if self
+            .last_reminder
+            .is_some_and(|last| (now - last).to_std().unwrap_or_default() < REMINDER_INTERVAL)
+        {
+            return None;
+        }
+        let settings = self.settings.lock().unwrap().clone();
+        if settings.no_notify {
+            return None;
+        }
+        let behind = check(
+            self.done,
+            settings.daily_goal,
+            settings.workday_start,
+            settings.workday_end,
+            now.time(),
+        )?;
+        self.last_reminder = Some(now);
+        Some(behind.message(settings.workday_end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{SessionType, events::SessionOutcome, settings::Settings};
+
+    const NINE: TimeOfDay = TimeOfDay::new(9, 0);
+    const FIVE_PM: TimeOfDay = TimeOfDay::new(17, 0);
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_times_of_day() {
+        assert_eq!("09:30".parse(), Ok(TimeOfDay::new(9, 30)));
+        assert_eq!(TimeOfDay::new(7, 5).to_string(), "07:05");
+        assert!("9am".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn behind_pace_suggests_the_pace_needed() {
+        // At 15:00, 6 of the 8 hours are over: 6 pomodoros were expected.
+        let behind = check(2, 8, NINE, FIVE_PM, at(15, 0)).unwrap();
+        assert_eq!(behind.needed_per_hour, 3.0);
+        assert_eq!(
+            behind.message(FIVE_PM),
+            "2 of 8 pomodoros done. About 3.0 per hour until 17:00 would still get you there."
+        );
+    }
+
+    #[test]
+    fn on_pace_or_outside_the_workday_is_quiet() {
+        assert_eq!(check(6, 8, NINE, FIVE_PM, at(15, 0)), None);
+        assert_eq!(check(0, 8, NINE, FIVE_PM, at(8, 0)), None);
+        assert_eq!(check(0, 8, NINE, FIVE_PM, at(18, 0)), None);
+        assert_eq!(check(8, 8, NINE, FIVE_PM, at(16, 0)), None);
+    }
+
+    #[test]
+    fn reminds_at_most_once_an_hour() {
+        let settings = Settings {
+            no_notify: false,
+            ..Settings::default()
+        };
+        let day = NaiveDate::from_ymd_opt(2026, 3, 16).unwrap();
+        let mut reminder = PaceReminder::new(settings.into_shared(), day, 1);
+        let ended = Event::SessionEnded {
+            session: SessionType::work(),
+            elapsed: Duration::from_secs(25 * 60),
+            outcome: SessionOutcome::Completed,
+        };
+        let now = |hour, minute| {
+            Local
+                .with_ymd_and_hms(2026, 3, 16, hour, minute, 0)
+                .unwrap()
+        };
+        assert!(
+            reminder
+                .handle(&ended, now(15, 0))
+                .unwrap()
+                .starts_with("2 of 8")
+        );
+        assert_eq!(reminder.handle(&ended, now(15, 30)), None);
+        assert!(
+            reminder
+                .handle(&ended, now(16, 0))
+                .unwrap()
+                .starts_with("4 of 8")
+        );
+    }
+
+    #[test]
+    fn counting_starts_over_the_next_day() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 16).unwrap();
+        let mut reminder = PaceReminder::new(Settings::default().into_shared(), day, 7);
+        let ended = Event::SessionEnded {
+            session: SessionType::short_break(),
+            elapsed: Duration::from_secs(5 * 60),
+            outcome: SessionOutcome::Completed,
+        };
+        let next_day = Local.with_ymd_and_hms(2026, 3, 17, 12, 0, 0).unwrap();
+        assert!(
+            reminder
+                .handle(&ended, next_day)
+                .unwrap()
+                .starts_with("0 of 8")
+        );
+    }
+}
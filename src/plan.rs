@@ -0,0 +1,225 @@
+// filepath: src/plan.rs
+//! Module projecting the day plan of a Pomodoro timer application: the sessions left until the
+//! daily goal is reached, following the sets of the settings, and when they end. The progress bar
+//! and `rustodoro status` tell when the running session and the plan end, and `rustodoro plan`
+//! lists the sessions of the plan starting now.
+//!
+//! The projection takes the durations as configured: the jitter of the breaks, the schedule script
+//! and the calendar may still change them.
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeDelta};
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    SessionType,
+    config::{self, Cli, ConfigError},
+    history::{History, history_path},
+    settings::Settings,
+    summary,
+};
+
+/// A session of the day plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Planned {
+    pub session: SessionType,
+    pub duration: Duration,
+}
+
+/// The sessions from the one at `next` in the set, from 0, with `cycle` work sessions started in
+/// the set before it, until `pomodoros` more work sessions are done, the last of them ending the
+/// plan. `next` past the end of the set starts a new set.
+pub fn ahead(settings: &Settings, next: usize, cycle: u64, pomodoros: u64) -> Vec<Planned> {
+    let sequence: Vec<SessionType> = settings
+        .sequence()
+        .iter()
+        .map(|step| settings.session_type(step))
+        .collect();
+    let mut planned = Vec::new();
+    if !sequence.iter().any(SessionType::is_work) {
+        return planned;
+    }
+    let (mut step, mut cycle, mut left) = (next, cycle, pomodoros);
+    while left > 0 {
+        if step >= sequence.len() {
+            (step, cycle) = (0, 0);
+        }
+        let session = sequence[step].clone();
+        step += 1;
+        let minutes = if session.is_work() {
+            cycle += 1;
+            left -= 1;
+            session
+                .minutes
+                .unwrap_or_else(|| settings.work_minutes(cycle))
+        } else {
+            session.minutes.unwrap_or(match session.kind {
+                SessionKind::LongBreak => settings.long_break,
+                _ => settings.short_break,
+            })
+        };
+        planned.push(Planned {
+            duration: settings.minute * minutes as u32,
+            session,
+        });
+    }
+    planned
+}
+
+/// The time the day plan takes after the `running` session, followed by the one at `next` in the
+/// set with `cycle` work sessions started before it, `done` pomodoros being completed today. None
+/// when the daily goal was reached before the running session.
+pub fn time_ahead(
+    settings: &Settings,
+    running: &SessionType,
+    next: usize,
+    cycle: u64,
+    done: u64,
+) -> Option<Duration> {
+    let left = settings
+        .daily_goal
+        .checked_sub(done)
+        .filter(|left| *left > 0)?
+        - u64::from(running.is_work());
+    Some(
+        ahead(settings, next, cycle, left)
+            .iter()
+            .map(|planned| planned.duration)
+            .sum(),
+    )
+}
+
+/// When the running session, with `remaining` left at `now`, ends, and the day plan after it when
+/// it takes `ahead`, e.g. "ends 16:42, day plan done 18:10".
+pub fn projection(now: DateTime<Local>, remaining: Duration, ahead: Option<Duration>) -> String {
+    match ahead {
+        Some(ahead) => format!(
+            "ends {}, day plan done {}",
+            at(now, remaining),
+            at(now, remaining + ahead)
+        ),
+        None => format!("ends {}", at(now, remaining)),
+    }
+}
+
+/// The wall-clock time `duration` after `now`.
+fn at(now: DateTime<Local>, duration: Duration) -> String {
+    let end = now + TimeDelta::from_std(duration).unwrap_or(TimeDelta::MAX);
+    end.format("%H:%M").to_string()
+}
+
+/// The day plan starting now with a new set, after `done` pomodoros completed today.
+fn render(settings: &Settings, done: u64, now: DateTime<Local>) -> String {
+    let left = settings.daily_goal.saturating_sub(done);
+    if left == 0 {
+        return format!(
+            "The daily goal of {} pomodoros is reached, {} done today.\n",
+            settings.daily_goal, done
+        );
+    }
+    let mut rendered = format!(
+        "{} of the {} pomodoros of the daily goal left:\n",
+        left, settings.daily_goal
+    );
+    let mut elapsed = Duration::ZERO;
+    for planned in ahead(settings, 0, 0, left) {
+        rendered.push_str(&format!(
+            "{}  {:<16} {:>3} min\n",
+            at(now, elapsed),
+            planned.session.to_string(),
+            planned.duration.as_secs() / settings.minute.as_secs().max(1)
+        ));
+        elapsed += planned.duration;
+    }
+    rendered.push_str(&format!("Day plan done at {}.\n", at(now, elapsed)));
+    rendered
+}
+
+fn run_action(cli: &Cli) -> Result<String, ConfigError> {
+    let settings = config::resolve(cli)?.settings();
+    let now = Local::now();
+    // A history that cannot be read counts no pomodoro, as for the run.
+    let records = history_path(cli.history.as_deref(), cli.name.as_deref())
+        .and_then(|path| History::new(path).sessions().ok())
+        .unwrap_or_default();
+    let done = summary::Stats::from_records(summary::on_day(&records, now.date_naive())).pomodoros;
+    Ok(render(&settings, done, now))
+}
+
+/// Runs the `plan` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli) -> i32 {
+    match run_action(cli) {
+        Ok(rendered) => {
+            print!("{}", rendered);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn plans_the_sessions_up_to_the_daily_goal() {
+        let settings = Settings {
+            cycles: 2,
+            daily_goal: 3,
+            ..Settings::default()
+        };
+        // During the first work session of the set, nothing done yet today.
+        let planned: Vec<(String, u64)> = ahead(&settings, 1, 1, 2)
+            .iter()
+            .map(|planned| (planned.session.to_string(), planned.duration.as_secs() / 60))
+            .collect();
+        assert_eq!(
+            planned,
+            [
+                ("Short break".to_string(), 5),
+                ("Work session".to_string(), 25),
+                ("Long break".to_string(), 15),
+                ("Work session".to_string(), 25),
+            ]
+        );
+        let (work, short_break) = (SessionType::work(), SessionType::short_break());
+        assert_eq!(time_ahead(&settings, &work, 1, 1, 0), Some(MINUTE * 70));
+        // The last work session of the goal ends the plan, which the break after it is not in.
+        assert_eq!(time_ahead(&settings, &work, 3, 2, 2), Some(Duration::ZERO));
+        assert_eq!(time_ahead(&settings, &work, 3, 2, 3), None);
+        assert_eq!(
+            time_ahead(&settings, &short_break, 2, 1, 2),
+            Some(MINUTE * 25)
+        );
+    }
+
+    #[test]
+    fn projects_the_ends() {
+        let now = Local.with_ymd_and_hms(2026, 3, 2, 16, 17, 0).unwrap();
+        assert_eq!(
+            projection(now, MINUTE * 25, Some(MINUTE * 88)),
+            "ends 16:42, day plan done 18:10"
+        );
+        assert_eq!(projection(now, MINUTE * 25, None), "ends 16:42");
+        let settings = Settings {
+            cycles: 2,
+            daily_goal: 2,
+            ..Settings::default()
+        };
+        assert_eq!(
+            render(&settings, 0, now),
+            "2 of the 2 pomodoros of the daily goal left:\n\
+             16:17  Work session      25 min\n\
+             16:42  Short break        5 min\n\
+             16:47  Work session      25 min\n\
+             Day plan done at 17:12.\n"
+        );
+    }
+}
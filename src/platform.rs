@@ -0,0 +1,277 @@
+// filepath: src/platform.rs
+//! Module telling whether a Pomodoro timer application runs under WSL or Termux, where D-Bus and
+//! ALSA are usually missing. Under WSL, the notifications show as Windows toasts, through
+//! `wsl-notify-send` or else PowerShell, and the sounds play on the Windows host. Under Termux, on
+//! Android, they go through the Termux:API commands, the tones making the phone vibrate. The sounds
+//! ring the terminal bell when these programs cannot be run.
+use std::{
+    env, fmt, fs,
+    io::{self, Write},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use crate::sounds::{Sound, Tone};
+
+/// Name of the toasts on the Windows side, and of the notification of Termux.
+const APP_ID: &str = "Rustodoro";
+/// Shortest gap between the wall clock and the timer's clock taken as the device sleeping, rather
+/// than as the timer waking up late.
+const MIN_SLEEP: Duration = Duration::from_secs(2);
+
+/// Where the timer runs, as far as the notifications and the sounds are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// The notification daemon and the audio output of the system.
+    Native,
+    /// Windows Subsystem for Linux.
+    Wsl,
+    /// Termux, on Android.
+    Termux,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Native => write!(f, "the desktop"),
+            Platform::Wsl => write!(f, "WSL"),
+            Platform::Termux => write!(f, "Termux"),
+        }
+    }
+}
+
+/// The platform the timer runs on, detected once.
+pub fn current() -> Platform {
+    static PLATFORM: OnceLock<Platform> = OnceLock::new();
+    *PLATFORM.get_or_init(|| {
+        if !cfg!(target_os = "linux") && !cfg!(target_os = "android") {
+            return Platform::Native;
+        }
+        detect(
+            |name| env::var(name).ok(),
+            fs::read_to_string("/proc/sys/kernel/osrelease")
+                .ok()
+                .as_deref(),
+        )
+    })
+}
+
+/// Termux when its variables are set, WSL when the distribution variable is set or the kernel
+/// release is one of Microsoft's.
+fn detect(var: impl Fn(&str) -> Option<String>, osrelease: Option<&str>) -> Platform {
+    if var("TERMUX_VERSION").is_some()
+        || var("PREFIX").is_some_and(|prefix| prefix.contains("com.termux"))
+    {
+        Platform::Termux
+    } else if var("WSL_DISTRO_NAME").is_some()
+        || osrelease.is_some_and(|release| release.to_lowercase().contains("microsoft"))
+    {
+        Platform::Wsl
+    } else {
+        Platform::Native
+    }
+}
+
+/// How long the device slept while `monotonic` went by on the timer's clock and `wall` on the
+/// wall clock, the former standing still while Android sleeps.
+pub fn slept(wall: Duration, monotonic: Duration) -> Duration {
+    let slept = wall.saturating_sub(monotonic);
+    if slept < MIN_SLEEP {
+        Duration::ZERO
+    } else {
+        slept
+    }
+}
+
+/// `text` as a PowerShell string, taken literally.
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// The PowerShell script showing a toast titled `summary`.
+fn toast_script(summary: &str, body: &str) -> String {
+    format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $texts = $xml.GetElementsByTagName('text'); \
+         $texts.Item(0).AppendChild($xml.CreateTextNode({})) > $null; \
+         $texts.Item(1).AppendChild($xml.CreateTextNode({})) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+        quote(summary),
+        quote(body),
+        quote(APP_ID)
+    )
+}
+
+/// Runs `program` with `args` to its end, failing with its error output.
+fn run(program: &str, args: &[&str]) -> io::Result<()> {
+    let output = Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Shows the notification of the platform, replacing the previous one on Termux. On WSL, it is a
+/// toast of the Windows host, through `wsl-notify-send` when installed, else PowerShell.
+pub fn notify(summary: &str, body: &str) -> io::Result<()> {
+    if current() == Platform::Termux {
+        return run(
+            "termux-notification",
+            &["--id", APP_ID, "--title", summary, "--content", body],
+        );
+    }
+    match run("wsl-notify-send.exe", &["--category", summary, body]) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => run(
+            "powershell.exe",
+            &["-NoProfile", "-Command", &toast_script(summary, body)],
+        ),
+        result => result,
+    }
+}
+
+/// The PowerShell script playing `sound`: the tones as console beeps of the same notes, a file
+/// through the sound player, which only reads WAV files, at the Windows path `path`.
+fn sound_script(sound: &Sound, path: Option<&str>) -> Option<String> {
+    let script = match (sound, path) {
+        (Sound::None, _) => return None,
+        (Sound::Tone(Tone::Beep), _) => "[console]::Beep(440, 250)".to_string(),
+        (Sound::Tone(Tone::Chime), _) => {
+            "[console]::Beep(660, 150); [console]::Beep(880, 300)".to_string()
+        }
+        (Sound::Tone(Tone::Bell), _) => "[console]::Beep(1320, 800)".to_string(),
+        (Sound::File(_), Some(path)) => {
+            format!("(New-Object Media.SoundPlayer {}).PlaySync()", quote(path))
+        }
+        (Sound::File(_), None) => return None,
+    };
+    Some(script)
+}
+
+/// The Windows path of the file at `path`, as `wslpath` tells it.
+fn windows_path(path: &Path) -> Option<String> {
+    let output = Command::new("wslpath")
+        .arg("-w")
+        .arg(path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The Termux:API command playing `sound`: the tones as vibrations as long as them, a file through
+/// the media player.
+fn termux_command(sound: &Sound) -> Option<Vec<String>> {
+    let vibrate = |milliseconds: u64| {
+        ["termux-vibrate", "-f", "-d", &milliseconds.to_string()]
+            .map(str::to_string)
+            .to_vec()
+    };
+    match sound {
+        Sound::None => None,
+        Sound::Tone(Tone::Beep) => Some(vibrate(250)),
+        Sound::Tone(Tone::Chime) => Some(vibrate(450)),
+        Sound::Tone(Tone::Bell) => Some(vibrate(800)),
+        Sound::File(path) => Some(vec![
+            "termux-media-player".to_string(),
+            "play".to_string(),
+            path.display().to_string(),
+        ]),
+    }
+}
+
+/// Starts playing `sound` with the programs of the platform, returning the player to wait for.
+/// Rings the terminal bell instead when they cannot be run or, on WSL, the file has no Windows
+/// path.
+pub fn play(sound: &Sound) -> Option<Child> {
+    let command = match current() {
+        Platform::Termux => termux_command(sound),
+        _ => {
+            let path = match sound {
+                Sound::File(path) => windows_path(path),
+                _ => None,
+            };
+            sound_script(sound, path.as_deref()).map(|script| {
+                ["powershell.exe", "-NoProfile", "-Command", &script]
+                    .map(str::to_string)
+                    .to_vec()
+            })
+        }
+    };
+    let player = command.and_then(|command| {
+        Command::new(&command[0])
+            .args(&command[1..])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()
+    });
+    if player.is_none() && *sound != Sound::None {
+        let mut stderr = io::stderr();
+        let _ = stderr.write_all(b"\x07").and_then(|_| stderr.flush());
+    }
+    player
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn detects_the_platform() {
+        let none = |_: &str| None;
+        let termux =
+            |name: &str| (name == "PREFIX").then(|| "/data/data/com.termux/files/usr".to_string());
+        let wsl = |name: &str| (name == "WSL_DISTRO_NAME").then(|| "Ubuntu".to_string());
+        assert_eq!(detect(termux, None), Platform::Termux);
+        assert_eq!(detect(wsl, None), Platform::Wsl);
+        assert_eq!(
+            detect(none, Some("5.15.153.1-microsoft-standard-WSL2\n")),
+            Platform::Wsl
+        );
+        assert_eq!(detect(none, Some("4.4.0-19041-Microsoft")), Platform::Wsl);
+        assert_eq!(detect(none, Some("6.8.0-45-generic")), Platform::Native);
+        assert_eq!(detect(none, None), Platform::Native);
+    }
+
+    #[test]
+    fn counts_the_time_asleep() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(slept(minute * 5, minute), minute * 4);
+        // Waking up a little late is no sleep, nor is the wall clock going back.
+        assert_eq!(
+            slept(minute + Duration::from_secs(1), minute),
+            Duration::ZERO
+        );
+        assert_eq!(slept(Duration::ZERO, minute), Duration::ZERO);
+        assert_eq!(
+            termux_command(&Sound::Tone(Tone::Chime)).unwrap(),
+            ["termux-vibrate", "-f", "-d", "450"]
+        );
+    }
+
+    #[test]
+    fn writes_the_powershell_scripts() {
+        let script = toast_script("Pomodoro Timer", "Time's up");
+        assert!(script.contains("CreateTextNode('Pomodoro Timer')"));
+        assert!(script.contains("CreateTextNode('Time''s up')"));
+        assert_eq!(
+            sound_script(&Sound::Tone(Tone::Beep), None).as_deref(),
+            Some("[console]::Beep(440, 250)")
+        );
+        let file = Sound::File(PathBuf::from("/home/me/gong.wav"));
+        assert_eq!(
+            sound_script(&file, Some(r"\\wsl$\Ubuntu\home\me\gong.wav")).as_deref(),
+            Some(r"(New-Object Media.SoundPlayer '\\wsl$\Ubuntu\home\me\gong.wav').PlaySync()")
+        );
+        assert_eq!(sound_script(&file, None), None);
+        assert_eq!(sound_script(&Sound::None, None), None);
+    }
+}
@@ -0,0 +1,537 @@
+// filepath: src/plugins.rs
+//! Module running the plugins of a Pomodoro timer application: the executables of the plugin
+//! directory, started with the timer, receive its events as JSON lines on their standard input
+//! and can send it commands as JSON lines on their standard output. WASM modules described by a
+//! manifest there are run the same way, in a sandbox.
+//!
+//! A plugin cannot hold the timer back: the events are queued for each plugin on a thread of its
+//! own and dropped when it does not keep up, a plugin that crashes is restarted a few times then
+//! disabled, and the plugins still running when the timer quits are stopped.
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command as Process, ExitStatus, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, SyncSender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use rustodoro_client::protocol::{Action, SessionKind};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::WeakUnboundedSender;
+
+use crate::{
+    config,
+    events::{Event, SessionOutcome},
+    remote,
+    types::Command,
+    wasm,
+};
+
+/// Version of the plugin protocol, carried by every event.
+pub const PLUGIN_PROTOCOL_VERSION: u32 = 1;
+/// Times a crashed plugin is started again before being disabled.
+const RESTARTS: u32 = 3;
+/// Events queued for a plugin before the next ones are dropped.
+const QUEUE: usize = 256;
+/// Wait for a plugin to exit once its input is closed before killing it.
+const GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("cannot start plugin {name}: {source}")]
+    Spawn { name: String, source: io::Error },
+    #[error("invalid manifest for plugin {name}: {message}")]
+    Manifest { name: String, message: String },
+    #[error("plugin {name} exited with {status}{}", last_words(.stderr))]
+    Crashed {
+        name: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    #[error("plugin {name} was disabled after crashing {} times, lastly with {status}{}", RESTARTS + 1, last_words(.stderr))]
+    Disabled {
+        name: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+}
+
+fn last_words(stderr: &str) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", stderr)
+    }
+}
+
+/// An event as sent to the plugins.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Message {
+    SessionStarted {
+        session: SessionKind,
+        cycle: u64,
+        duration_secs: u64,
+    },
+    Tick {
+        elapsed_secs: u64,
+        remaining_secs: u64,
+    },
+    Paused {
+        elapsed_secs: u64,
+    },
+    Resumed {
+        elapsed_secs: u64,
+    },
+    Reset,
+    NextBreakSkipped,
+    SessionEnded {
+        session: SessionKind,
+        elapsed_secs: u64,
+        outcome: SessionOutcome,
+    },
+}
+
+#[derive(Serialize)]
+struct Envelope {
+    version: u32,
+    #[serde(flatten)]
+    message: Message,
+}
+
+/// A line a plugin writes to command the timer.
+#[derive(Deserialize)]
+struct Request {
+    command: Action,
+}
+
+impl Message {
+    /// The message sending `event`, the transitions being left out of the protocol.
+    fn new(event: &Event) -> Option<Self> {
+        Some(match event {
+            Event::SessionStarted {
+                session,
+                cycle,
+                duration,
+            } => Message::SessionStarted {
+                session: session.kind,
+                cycle: *cycle,
+                duration_secs: duration.as_secs(),
+            },
+            Event::Tick { elapsed, remaining } => Message::Tick {
+                elapsed_secs: elapsed.as_secs(),
+                remaining_secs: remaining.as_secs(),
+            },
+            Event::Paused { elapsed } => Message::Paused {
+                elapsed_secs: elapsed.as_secs(),
+            },
+            Event::Resumed { elapsed, .. } => Message::Resumed {
+                elapsed_secs: elapsed.as_secs(),
+            },
+            Event::Reset => Message::Reset,
+            Event::NextBreakSkipped => Message::NextBreakSkipped,
+            Event::SessionEnded {
+                session,
+                elapsed,
+                outcome,
+            } => Message::SessionEnded {
+                session: session.kind,
+                elapsed_secs: elapsed.as_secs(),
+                outcome: *outcome,
+            },
+            Event::Planned { .. }
+            | Event::PauseReason { .. }
+            | Event::Intent { .. }
+            | Event::Reviewed { .. }
+            | Event::Acknowledged
+            | Event::Extended { .. }
+            | Event::TaskChanged { .. }
+            | Event::Transition(_)
+            | Event::Resized { .. } => return None,
+        })
+    }
+}
+
+/// The JSON line sending `event` to the plugins, if they receive it.
+fn line(event: &Event) -> Option<String> {
+    let envelope = Envelope {
+        version: PLUGIN_PROTOCOL_VERSION,
+        message: Message::new(event)?,
+    };
+    serde_json::to_string(&envelope).ok()
+}
+
+/// The plugin directory, next to the default configuration file.
+pub fn plugins_dir() -> Option<PathBuf> {
+    Some(config::default_config_path()?.parent()?.join("plugins"))
+}
+
+/// What a plugin is allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+    /// Whether the commands of the plugin are run.
+    pub commands: bool,
+    /// Whether the plugin receives the tick events, sent every second.
+    pub ticks: bool,
+}
+
+/// A plugin to start.
+#[derive(Debug, PartialEq)]
+pub struct Plugin {
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// The directory the plugin runs in.
+    pub dir: PathBuf,
+    pub capabilities: Capabilities,
+}
+
+impl Plugin {
+    /// An executable, which can do anything its user can.
+    fn executable(path: &Path) -> Self {
+        Plugin {
+            name: name(path),
+            program: path.to_path_buf(),
+            args: Vec::new(),
+            dir: path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            capabilities: Capabilities {
+                commands: true,
+                ticks: true,
+            },
+        }
+    }
+}
+
+/// The plugins of `dir` by name, its executables and its WASM manifests, hidden files left out,
+/// with the manifests that cannot be loaded.
+pub fn discover(dir: &Path) -> (Vec<Plugin>, Vec<PluginError>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    let mut plugins = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            match wasm::load(&path) {
+                Ok(plugin) => plugins.push(plugin),
+                Err(e) => errors.push(e),
+            }
+        } else if fs::metadata(&path).is_ok_and(|metadata| is_executable(&metadata)) {
+            plugins.push(Plugin::executable(&path));
+        }
+    }
+    (plugins, errors)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    metadata.is_file()
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// A started plugin.
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    /// Last line the plugin wrote to its error output.
+    stderr: Arc<Mutex<String>>,
+}
+
+impl Running {
+    fn start(
+        plugin: &Plugin,
+        commands: &WeakUnboundedSender<Command>,
+    ) -> Result<Self, PluginError> {
+        let mut child = Process::new(&plugin.program)
+            .args(&plugin.args)
+            .current_dir(&plugin.dir)
+            .env(
+                "RUSTODORO_PLUGIN_PROTOCOL",
+                PLUGIN_PROTOCOL_VERSION.to_string(),
+            )
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| PluginError::Spawn {
+                name: plugin.name.clone(),
+                source,
+            })?;
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            unreachable!("the standard streams of the plugin are piped");
+        };
+        let commands = commands.clone();
+        let allowed = plugin.capabilities.commands;
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                // Lines that are not commands are ignored, as is status, which has no answer, and
+                // all of them from a plugin not allowed to send commands.
+                if allowed
+                    && let Ok(request) = serde_json::from_str::<Request>(&line)
+                    && let Some(command) = remote::command(request.command)
+                    && !remote::send(&commands, command)
+                {
+                    break;
+                }
+            }
+        });
+        let last_line = Arc::new(Mutex::new(String::new()));
+        let tracked = Arc::clone(&last_line);
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if !line.trim().is_empty() {
+                    *tracked.lock().unwrap() = line.trim().to_string();
+                }
+            }
+        });
+        Ok(Running {
+            child,
+            stdin,
+            stderr: last_line,
+        })
+    }
+
+    /// How the plugin exited, once it does. Its error output is given a moment to be read.
+    fn exit_status(&mut self) -> io::Result<ExitStatus> {
+        let status = self.child.wait()?;
+        thread::sleep(Duration::from_millis(50));
+        Ok(status)
+    }
+
+    /// Closes the input of the plugin and waits for it to exit, killing it after the grace period.
+    fn stop(self) {
+        let Running {
+            mut child, stdin, ..
+        } = self;
+        drop(stdin);
+        let deadline = Instant::now() + GRACE;
+        while Instant::now() < deadline {
+            if !matches!(child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Feeds a plugin with `lines` until they end, restarting it when it crashes, and returns the
+/// first failure.
+fn supervise(
+    plugin: Plugin,
+    lines: Receiver<Arc<str>>,
+    commands: WeakUnboundedSender<Command>,
+) -> Option<PluginError> {
+    let mut running = match Running::start(&plugin, &commands) {
+        Ok(running) => running,
+        Err(e) => return Some(e),
+    };
+    let mut failure = None;
+    let mut restarts = 0;
+    for line in lines {
+        if writeln!(running.stdin, "{}", line).is_ok() {
+            continue;
+        }
+        // The plugin closed its input: it exited, on its own or not.
+        let status = running.exit_status();
+        if status.as_ref().is_ok_and(ExitStatus::success) {
+            return failure;
+        }
+        let stderr = running.stderr.lock().unwrap().clone();
+        match status {
+            Ok(status) if restarts == RESTARTS => {
+                return Some(PluginError::Disabled {
+                    name: plugin.name.clone(),
+                    status,
+                    stderr,
+                });
+            }
+            Ok(status) => {
+                failure.get_or_insert(PluginError::Crashed {
+                    name: plugin.name.clone(),
+                    status,
+                    stderr,
+                });
+            }
+            // The plugin cannot be waited for, there is nothing to restart.
+            Err(_) => return failure,
+        }
+        restarts += 1;
+        running = match Running::start(&plugin, &commands) {
+            Ok(running) => running,
+            Err(e) => return failure.or(Some(e)),
+        };
+        // The event the crashed plugin missed.
+        let _ = writeln!(running.stdin, "{}", line);
+    }
+    running.stop();
+    failure
+}
+
+/// The plugins started with the timer.
+pub struct Plugins {
+    /// The queue of each plugin, with whether it receives the ticks.
+    queues: Vec<(SyncSender<Arc<str>>, bool)>,
+    supervisors: Vec<JoinHandle<Option<PluginError>>>,
+}
+
+impl Plugins {
+    /// Starts the plugins, each on a thread of its own.
+    pub fn start(list: Vec<Plugin>, commands: WeakUnboundedSender<Command>) -> Self {
+        let mut plugins = Plugins {
+            queues: Vec::new(),
+            supervisors: Vec::new(),
+        };
+        for plugin in list {
+            let (queue, lines) = mpsc::sync_channel(QUEUE);
+            let commands = commands.clone();
+            plugins.queues.push((queue, plugin.capabilities.ticks));
+            plugins
+                .supervisors
+                .push(thread::spawn(move || supervise(plugin, lines, commands)));
+        }
+        plugins
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.supervisors.is_empty()
+    }
+
+    /// Sends the events to the plugins until the event bus is dropped, then stops them and
+    /// returns their failures.
+    pub fn run(self, events: Receiver<Event>) -> Vec<PluginError> {
+        for event in events {
+            let Some(line) = line(&event) else {
+                continue;
+            };
+            let line: Arc<str> = line.into();
+            let tick = matches!(event, Event::Tick { .. });
+            for (queue, _) in self.queues.iter().filter(|(_, ticks)| *ticks || !tick) {
+                // A plugin that does not keep up misses events, a disabled one all of them.
+                let _ = queue.try_send(Arc::clone(&line));
+            }
+        }
+        drop(self.queues);
+        self.supervisors
+            .into_iter()
+            .filter_map(|supervisor| supervisor.join().ok().flatten())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use tokio::sync::mpsc as channel;
+
+    use super::*;
+    use crate::SessionType;
+
+    #[test]
+    fn sends_versioned_json_lines() {
+        let started = Event::SessionStarted {
+            session: SessionType::short_break(),
+            cycle: 2,
+            duration: Duration::from_secs(300),
+        };
+        assert_eq!(
+            line(&started).unwrap(),
+            r#"{"version":1,"event":"session_started","session":"short_break","cycle":2,"duration_secs":300}"#
+        );
+        let ended = Event::SessionEnded {
+            session: SessionType::work(),
+            elapsed: Duration::from_secs(1500),
+            outcome: SessionOutcome::Completed,
+        };
+        assert_eq!(
+            line(&ended).unwrap(),
+            r#"{"version":1,"event":"session_ended","session":"work","elapsed_secs":1500,"outcome":"completed"}"#
+        );
+        assert_eq!(
+            line(&Event::Reset).as_deref(),
+            Some(r#"{"version":1,"event":"reset"}"#)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn forwards_commands_and_reports_crashes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("rustodoro-plugins-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let script = |name: &str, content: &str| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        // Skips the break as soon as it starts.
+        script(
+            "skipper",
+            "#!/bin/sh\nwhile read -r line; do\n  case \"$line\" in *short_break*) echo '{\"command\":\"skip\"}' ;; esac\ndone\n",
+        );
+        script("crasher", "#!/bin/sh\necho 'out of cheese' >&2\nexit 3\n");
+        fs::write(dir.join("notes.txt"), "not a plugin").unwrap();
+
+        let (found, errors) = discover(&dir);
+        let names: Vec<&str> = found.iter().map(|plugin| plugin.name.as_str()).collect();
+        assert_eq!(names, ["crasher", "skipper"]);
+        assert!(errors.is_empty());
+        let (tx, mut rx) = channel::unbounded_channel();
+        let plugins = Plugins::start(found, tx.downgrade());
+        let (events, received) = mpsc::channel();
+        thread::spawn(move || {
+            for _ in 0..6 {
+                events
+                    .send(Event::SessionStarted {
+                        session: SessionType::short_break(),
+                        cycle: 1,
+                        duration: Duration::from_secs(300),
+                    })
+                    .unwrap();
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        let failures = plugins.run(received);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Command::Skip)));
+        let failures: Vec<String> = failures.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            failures,
+            [
+                "plugin crasher was disabled after crashing 4 times, lastly with exit status: 3: out of cheese"
+            ]
+        );
+        drop(tx);
+    }
+}
@@ -0,0 +1,89 @@
+// filepath: src/preview.rs
+//! Module previewing the alerts of a Pomodoro timer application, so that the audio device, the
+//! volume and the notification daemon can be checked without running a session. The alerts go
+//! through the same code as during a run, with the configured settings.
+use crate::{
+    AppError,
+    alerts::Alerts,
+    config::{self, Cli},
+    settings::Settings,
+    sounds::{Cue, Sound},
+};
+
+/// Reports `e` the way the timer does, and returns the process exit code.
+fn failure(e: AppError) -> i32 {
+    eprintln!("error: {}", e);
+    if let Some(hint) = e.hint() {
+        eprintln!("  hint: {}", hint);
+    }
+    1
+}
+
+fn settings(cli: &Cli) -> Result<Settings, AppError> {
+    Ok(config::resolve(cli)?.settings())
+}
+
+/// Runs the `test-sound` subcommand and returns the process exit code.
+pub fn run_sound(cli: &Cli, cue: Cue) -> i32 {
+    let result = settings(cli).and_then(|settings| {
+        if settings.no_sound {
+            println!("note: the sound is disabled, the timer will not play it");
+        }
+        let sound = settings.sounds.sound(cue);
+        if *sound == Sound::None {
+            println!(
+                "note: {} plays no sound, see the [sounds] table",
+                cue.name()
+            );
+            return Ok(false);
+        }
+        // Opened even when the sound is disabled, which is the point of the preview.
+        let mut alerts = Alerts::new(&Settings {
+            no_sound: false,
+            ..settings.clone()
+        })?;
+        println!(
+            "Playing {} ({}) on {}...",
+            cue.name(),
+            sound,
+            settings
+                .audio_device
+                .as_deref()
+                .unwrap_or("the default audio device")
+        );
+        alerts.play(cue)?;
+        alerts.wait_for_sound();
+        Ok(true)
+    });
+    match result {
+        Ok(played) => {
+            if played {
+                println!("Done. If you heard nothing, check the volume and --audio-device.");
+            }
+            0
+        }
+        Err(e) => failure(e),
+    }
+}
+
+/// Runs the `test-notify` subcommand and returns the process exit code.
+pub fn run_notify(cli: &Cli) -> i32 {
+    let result = settings(cli).and_then(|settings| {
+        if settings.no_notify {
+            println!("note: the notifications are disabled, the timer will not show them");
+        }
+        let mut alerts = Alerts::new(&Settings {
+            no_sound: true,
+            ..settings
+        })?;
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(alerts.notify("Notifications are working."))
+    });
+    match result {
+        Ok(()) => {
+            println!("Notification sent. If none showed up, check your notification daemon.");
+            0
+        }
+        Err(e) => failure(e),
+    }
+}
@@ -0,0 +1,229 @@
+// filepath: src/prompt.rs
+//! Module asking the user questions in the terminal for a Pomodoro timer application: numbers,
+//! yes or no, and secrets typed without echo. The keys are read in raw mode, which is left as it
+//! was found, so that the prompts also work while the timer holds the terminal.
+//!
+//! Each prompt returns none when the user cancels it with Esc or Ctrl-C. The progress bar of the
+//! running session is not drawn while asking, nor the output of the run written.
+use std::{
+    io::{self, Write},
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal,
+};
+
+use crate::{input, output};
+
+/// Raw mode for as long as it is alive, unless the terminal was in raw mode already.
+struct Raw {
+    enabled: bool,
+}
+
+impl Raw {
+    fn enable() -> io::Result<Self> {
+        let enabled = !terminal::is_raw_mode_enabled()?;
+        if enabled {
+            terminal::enable_raw_mode()?;
+        }
+        Ok(Raw { enabled })
+    }
+}
+
+impl Drop for Raw {
+    fn drop(&mut self) {
+        if self.enabled {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+/// What a key does to the answer being typed.
+enum Key {
+    Char(char),
+    Erase,
+    Submit,
+    Cancel,
+}
+
+/// What `event` does to the answer, none when it is not a key press doing anything.
+fn key_of(event: Event) -> Option<Key> {
+    let Event::Key(key) = event else {
+        return None;
+    };
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match key.code {
+        KeyCode::Enter => Some(Key::Submit),
+        KeyCode::Esc => Some(Key::Cancel),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Cancel),
+        KeyCode::Char(c) => Some(Key::Char(c)),
+        KeyCode::Backspace => Some(Key::Erase),
+        _ => None,
+    }
+}
+
+/// The next key pressed, none once `deadline` passed.
+fn next_key(deadline: Option<Instant>) -> io::Result<Option<Key>> {
+    while let Some(event) = input::read(deadline)? {
+        if let Some(key) = key_of(event) {
+            return Ok(Some(key));
+        }
+    }
+    Ok(None)
+}
+
+/// Asks `question` and reads the answer up to Enter, echoing it when `echo` is set.
+fn read(question: &str, echo: bool) -> io::Result<Option<String>> {
+    output::interact(|| read_answer(question, echo, None))
+}
+
+/// Reads the answer up to Enter, or up to `deadline` when there is one, keeping what was typed.
+fn read_answer(
+    question: &str,
+    echo: bool,
+    deadline: Option<Instant>,
+) -> io::Result<Option<String>> {
+    let mut stderr = io::stderr();
+    write!(stderr, "{}", question)?;
+    stderr.flush()?;
+    let raw = Raw::enable()?;
+    let mut answer = String::new();
+    let result = loop {
+        let Some(key) = next_key(deadline)? else {
+            break Some(answer);
+        };
+        match key {
+            Key::Char(c) => {
+                answer.push(c);
+                if echo {
+                    write!(stderr, "{}", c)?;
+                }
+            }
+            Key::Erase => {
+                if answer.pop().is_some() && echo {
+                    write!(stderr, "\x08 \x08")?;
+                }
+            }
+            Key::Submit => break Some(answer),
+            Key::Cancel => break None,
+        }
+        stderr.flush()?;
+    };
+    drop(raw);
+    // In raw mode, a line feed does not go back to the start of the line.
+    write!(stderr, "\r\n")?;
+    Ok(result)
+}
+
+/// Reads a secret without echoing it.
+pub fn hidden(question: &str) -> io::Result<Option<String>> {
+    read(question, false)
+}
+
+/// The number answered, `default` when the answer is empty.
+fn parse_number(answer: &str, default: u64, range: &RangeInclusive<u64>) -> Result<u64, String> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(default);
+    }
+    match answer.parse() {
+        Ok(number) if range.contains(&number) => Ok(number),
+        _ => Err(format!(
+            "type a number from {} to {}",
+            range.start(),
+            range.end()
+        )),
+    }
+}
+
+/// The yes or no answered, `default` when the answer is empty.
+fn parse_yes_no(answer: &str, default: bool) -> Result<bool, String> {
+    match answer.trim().to_lowercase().as_str() {
+        "" => Ok(default),
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Err("type y or n".to_string()),
+    }
+}
+
+/// The line answered, `default` when the answer is empty.
+fn parse_line(answer: &str, default: Option<&str>) -> Option<String> {
+    match answer.trim() {
+        "" => default.map(str::to_string),
+        answer => Some(answer.to_string()),
+    }
+}
+
+/// Asks until the answer reads with `parse`.
+fn ask<T>(question: &str, parse: impl Fn(&str) -> Result<T, String>) -> io::Result<Option<T>> {
+    output::interact(|| {
+        loop {
+            let Some(answer) = read(question, true)? else {
+                return Ok(None);
+            };
+            match parse(&answer) {
+                Ok(value) => return Ok(Some(value)),
+                Err(hint) => eprint!("  {}\r\n", hint),
+            }
+        }
+    })
+}
+
+/// Asks for a number of `range`, `default` being taken on Enter.
+pub fn number(question: &str, default: u64, range: RangeInclusive<u64>) -> io::Result<Option<u64>> {
+    ask(&format!("{} [{}]: ", question, default), |answer| {
+        parse_number(answer, default, &range)
+    })
+}
+
+/// Asks a yes or no question, `default` being taken on Enter.
+pub fn yes_no(question: &str, default: bool) -> io::Result<Option<bool>> {
+    let choices = if default { "Y/n" } else { "y/N" };
+    ask(&format!("{} [{}]: ", question, choices), |answer| {
+        parse_yes_no(answer, default)
+    })
+}
+
+/// Asks for a line of text, `default` being taken on Enter, and what was typed once `timebox` went
+/// by. None when nothing was typed and there is no default.
+pub fn line_within(
+    question: &str,
+    default: Option<&str>,
+    timebox: Duration,
+) -> io::Result<Option<String>> {
+    let question = match default {
+        Some(default) => format!("{} [{}]: ", question, default),
+        None => format!("{}: ", question),
+    };
+    let deadline = Instant::now() + timebox;
+    let answer = output::interact(|| read_answer(&question, true, Some(deadline)))?;
+    Ok(answer.and_then(|answer| parse_line(&answer, default)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_answers() {
+        assert_eq!(parse_number("", 25, &(1..=1440)), Ok(25));
+        assert_eq!(parse_number(" 50 ", 25, &(1..=1440)), Ok(50));
+        assert!(parse_number("0", 25, &(1..=1440)).is_err());
+        assert!(parse_number("half", 25, &(1..=1440)).is_err());
+        assert_eq!(parse_yes_no("", true), Ok(true));
+        assert_eq!(parse_yes_no("N", true), Ok(false));
+        assert_eq!(parse_yes_no("yes", false), Ok(true));
+        assert!(parse_yes_no("maybe", false).is_err());
+        assert_eq!(parse_line(" ", Some("report")).as_deref(), Some("report"));
+        assert_eq!(
+            parse_line(" Draft the outline ", Some("report")).as_deref(),
+            Some("Draft the outline")
+        );
+        assert_eq!(parse_line("", None), None);
+    }
+}
@@ -0,0 +1,477 @@
+// filepath: src/query.rs
+//! Module querying the session history of a Pomodoro timer application: `rustodoro history list
+//! --task report --since monday --type work --min-duration 20m` picks the sessions matching every
+//! filter given, sorts them and shows a page of them, as a table or as JSON. The filters may also be
+//! written as one expression, e.g. `task:report since:monday type:work duration>=20m`.
+use std::{cmp::Reverse, str::FromStr};
+
+use clap::ValueEnum;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use crate::{
+    config::ListArgs,
+    history::{SessionKind, SessionRecord},
+    notes,
+    retention::Age,
+    settings::Span,
+    summary::format_duration,
+};
+
+/// The first day of the sessions listed: `today`, `yesterday`, a weekday for its last occurrence,
+/// e.g. `monday`, a date such as `2026-03-02`, or an age such as `7d` or `2w`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Since {
+    Today,
+    Yesterday,
+    Weekday(Weekday),
+    Date(NaiveDate),
+    Age(Age),
+}
+
+impl Since {
+    /// The first day listed on `today`.
+    pub fn first_day(&self, today: NaiveDate) -> NaiveDate {
+        let back = |days: u32| {
+            today
+                .checked_sub_days(Days::new(u64::from(days)))
+                .unwrap_or(NaiveDate::MIN)
+        };
+        match self {
+            Since::Today => today,
+            Since::Yesterday => back(1),
+            Since::Weekday(weekday) => back(
+                (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7,
+            ),
+            Since::Date(date) => *date,
+            Since::Age(age) => age.cutoff(today),
+        }
+    }
+}
+
+impl FromStr for Since {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().to_lowercase();
+        match trimmed.as_str() {
+            "today" => return Ok(Since::Today),
+            "yesterday" => return Ok(Since::Yesterday),
+            _ => {}
+        }
+        if let Ok(weekday) = trimmed.parse() {
+            return Ok(Since::Weekday(weekday));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(&trimmed, "%Y-%m-%d") {
+            return Ok(Since::Date(date));
+        }
+        trimmed.parse().map(Since::Age).map_err(|_| {
+            format!(
+                "must be today, yesterday, a weekday, a date such as 2026-03-02 or an age such as 7d, got `{}`",
+                s
+            )
+        })
+    }
+}
+
+/// The sessions of a type.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum KindFilter {
+    Work,
+    /// The short and the long breaks.
+    Break,
+    ShortBreak,
+    LongBreak,
+}
+
+impl KindFilter {
+    fn matches(&self, kind: SessionKind) -> bool {
+        match self {
+            KindFilter::Work => kind == SessionKind::Work,
+            KindFilter::Break => kind != SessionKind::Work,
+            KindFilter::ShortBreak => kind == SessionKind::ShortBreak,
+            KindFilter::LongBreak => kind == SessionKind::LongBreak,
+        }
+    }
+}
+
+/// What the sessions are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum SortKey {
+    /// The latest first.
+    #[default]
+    Started,
+    /// The longest first, as counted by the countdown.
+    Duration,
+    /// The highest focus score first.
+    Focus,
+    /// In the alphabetical order of the tasks.
+    Task,
+}
+
+/// Filters of the sessions, given as flags or as an expression.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    pub task: Option<String>,
+    pub since: Option<Since>,
+    pub kind: Option<KindFilter>,
+    pub min_duration: Option<Span>,
+}
+
+impl Filter {
+    /// The filters of the flags of `args`.
+    fn of_flags(args: &ListArgs) -> Self {
+        Filter {
+            task: args.task.clone(),
+            since: args.since,
+            kind: args.kind,
+            min_duration: args.min_duration,
+        }
+    }
+
+    /// Whether `record` matches every filter, on `today`.
+    fn matches(&self, record: &SessionRecord, today: NaiveDate) -> bool {
+        let task = self.task.as_ref().is_none_or(|wanted| {
+            record
+                .task
+                .as_ref()
+                .is_some_and(|task| task.to_lowercase().contains(&wanted.to_lowercase()))
+        });
+        let since = self
+            .since
+            .is_none_or(|since| record.started_at.date_naive() >= since.first_day(today));
+        let kind = self.kind.is_none_or(|kind| kind.matches(record.kind));
+        let long_enough = self
+            .min_duration
+            .is_none_or(|min| record.elapsed >= min.seconds);
+        task && since && kind && long_enough
+    }
+}
+
+/// The terms of `expression`, split on the spaces out of double quotes, which are left out.
+fn terms(expression: &str) -> Result<Vec<String>, String> {
+    let (mut terms, mut term, mut quoted) = (Vec::new(), String::new(), false);
+    for c in expression.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("has an unclosed quote: `{}`", expression));
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    Ok(terms)
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Reads the terms `task:TEXT`, `since:DAY`, `type:TYPE` and `duration>=DURATION`, the text
+    /// of a task going in double quotes when it has spaces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter::default();
+        for term in terms(s)? {
+            if let Some(min) = term.strip_prefix("duration>=") {
+                filter.min_duration = Some(min.parse()?);
+                continue;
+            }
+            match term.split_once(':') {
+                Some(("task", task)) => filter.task = Some(task.to_string()),
+                Some(("since", since)) => filter.since = Some(since.parse()?),
+                Some(("type", kind)) => {
+                    filter.kind = Some(KindFilter::from_str(kind, true).map_err(|_| {
+                        format!(
+                            "type must be work, break, short-break or long-break, got `{}`",
+                            kind
+                        )
+                    })?)
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown filter `{}`, use task:, since:, type: or duration>=",
+                        term
+                    ));
+                }
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// The sessions of `records` matching the filters of `args`, both the flags and the expression,
+/// sorted as it tells.
+pub fn select<'a>(
+    records: &'a [SessionRecord],
+    args: &ListArgs,
+    today: NaiveDate,
+) -> Vec<&'a SessionRecord> {
+    let flags = Filter::of_flags(args);
+    let mut selected: Vec<&SessionRecord> = records
+        .iter()
+        .filter(|record| {
+            flags.matches(record, today)
+                && args
+                    .filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(record, today))
+        })
+        .collect();
+    match args.sort {
+        SortKey::Started => selected.sort_by_key(|record| Reverse(record.started_at)),
+        SortKey::Duration => selected.sort_by_key(|record| Reverse(record.elapsed)),
+        SortKey::Focus => selected.sort_by_key(|record| Reverse(record.focus)),
+        SortKey::Task => selected.sort_by_key(|record| record.task.clone()),
+    }
+    if args.reverse {
+        selected.reverse();
+    }
+    selected
+}
+
+/// The page `page`, from 1, of `limit` sessions of `selected`, the last one past the end, with
+/// its number and the number of pages.
+fn page<'a>(
+    selected: &'a [&'a SessionRecord],
+    limit: usize,
+    page: usize,
+) -> (&'a [&'a SessionRecord], usize, usize) {
+    let limit = limit.max(1);
+    let pages = selected.len().div_ceil(limit).max(1);
+    let page = page.clamp(1, pages);
+    let start = (page - 1).saturating_mul(limit);
+    let end = start.saturating_add(limit).min(selected.len());
+    (&selected[start..end], page, pages)
+}
+
+/// The sessions of `records` listed as `args` tells, on `today`.
+pub fn render(records: &[SessionRecord], args: &ListArgs, today: NaiveDate) -> String {
+    let selected = select(records, args, today);
+    let (shown, page, pages) = page(&selected, args.limit, args.page);
+    if args.json {
+        return serde_json::to_string_pretty(shown).expect("records always serialize") + "\n";
+    }
+    if selected.is_empty() {
+        return "No session matches.\n".to_string();
+    }
+    let mut rendered = format!(
+        "{:<8}  {:<16}  {:<11}  {:>6}  {:<14}  {:>5}  Task\n",
+        "ID", "Started", "Type", "Time", "Outcome", "Focus"
+    );
+    for record in shown {
+        let mut task = record.task.clone().unwrap_or("-".to_string());
+        for tag in &record.tags {
+            task.push_str(&format!(" #{}", tag));
+        }
+        rendered.push_str(&format!(
+            "{:<8}  {:<16}  {:<11}  {:>6}  {:<14}  {:>5}  {}\n",
+            record.id,
+            record.started_at.format("%Y-%m-%d %H:%M"),
+            notes::kind(record.kind),
+            format_duration(record.elapsed()),
+            if record.voided {
+                "voided"
+            } else {
+                notes::outcome(record.outcome)
+            },
+            record
+                .focus
+                .map_or("-".to_string(), |focus| focus.to_string()),
+            task
+        ));
+    }
+    let sessions = match selected.len() {
+        1 => "1 session".to_string(),
+        count => format!("{} sessions", count),
+    };
+    if pages > 1 {
+        rendered.push_str(&format!("Page {} of {}, {}.\n", page, pages, sessions));
+    } else {
+        rendered.push_str(&format!("{}.\n", sessions));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+    use crate::events::SessionOutcome;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    fn record(day: u32, kind: SessionKind, minutes: u64, task: Option<&str>) -> SessionRecord {
+        SessionRecord {
+            id: format!("{:02}{:02}", day, minutes),
+            started_at: Local.with_ymd_and_hms(2026, 3, day, 9, 0, 0).unwrap(),
+            kind,
+            planned: minutes * 60,
+            elapsed: minutes * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: Some(90),
+            task: task.map(str::to_string),
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    fn args() -> ListArgs {
+        ListArgs {
+            filter: None,
+            task: None,
+            since: None,
+            kind: None,
+            min_duration: None,
+            sort: SortKey::default(),
+            reverse: false,
+            limit: 20,
+            page: 1,
+            json: false,
+        }
+    }
+
+    #[test]
+    fn reads_the_first_days() {
+        // A Wednesday.
+        let today = date(4);
+        let first = |since: &str| since.parse::<Since>().unwrap().first_day(today);
+        assert_eq!(first("today"), today);
+        assert_eq!(first("Yesterday"), date(3));
+        assert_eq!(first("monday"), date(2));
+        assert_eq!(first("wed"), today);
+        assert_eq!(
+            first("thursday"),
+            NaiveDate::from_ymd_opt(2026, 2, 26).unwrap()
+        );
+        assert_eq!(first("2026-03-01"), date(1));
+        assert_eq!(first("2w"), NaiveDate::from_ymd_opt(2026, 2, 18).unwrap());
+        assert!("someday".parse::<Since>().is_err());
+    }
+
+    #[test]
+    fn lists_the_matching_sessions() {
+        let records = [
+            record(2, SessionKind::Work, 25, Some("Write the report")),
+            record(2, SessionKind::ShortBreak, 5, None),
+            record(3, SessionKind::Work, 15, Some("Report review")),
+            record(4, SessionKind::Work, 50, Some("Inbox")),
+            SessionRecord {
+                tags: vec!["q1".to_string()],
+                ..record(4, SessionKind::Work, 30, Some("Quarterly report"))
+            },
+        ];
+        let report = ListArgs {
+            task: Some("report".to_string()),
+            since: Some("monday".parse().unwrap()),
+            kind: Some(KindFilter::Work),
+            min_duration: Some(Span { seconds: 20 * 60 }),
+            ..args()
+        };
+        let tasks = |args: &ListArgs| -> Vec<Option<String>> {
+            select(&records, args, date(4))
+                .iter()
+                .map(|record| record.task.clone())
+                .collect()
+        };
+        assert_eq!(
+            tasks(&report),
+            [
+                Some("Quarterly report".to_string()),
+                Some("Write the report".to_string())
+            ]
+        );
+        let by_duration = ListArgs {
+            sort: SortKey::Duration,
+            reverse: true,
+            kind: Some(KindFilter::Break),
+            ..args()
+        };
+        assert_eq!(tasks(&by_duration), [None]);
+        let rendered = render(&records, &report, date(4));
+        assert!(rendered.starts_with("ID        Started "));
+        assert!(rendered.contains(
+            "0430      2026-03-04 09:00  work            30m  completed          90  Quarterly report #q1\n"
+        ));
+        assert!(rendered.ends_with("2 sessions.\n"));
+        let paged = ListArgs {
+            limit: 2,
+            page: 2,
+            ..args()
+        };
+        let rendered = render(&records, &paged, date(4));
+        assert!(rendered.contains("Report review"));
+        assert!(rendered.ends_with("Page 2 of 3, 5 sessions.\n"));
+        // Past the end, the last page is shown.
+        let past = ListArgs {
+            page: 9,
+            ..paged.clone()
+        };
+        let rendered = render(&records, &past, date(4));
+        assert_eq!(rendered.lines().count(), 3);
+        assert!(rendered.ends_with("Page 3 of 3, 5 sessions.\n"));
+        let json = ListArgs {
+            json: true,
+            ..report.clone()
+        };
+        let listed: Vec<SessionRecord> =
+            serde_json::from_str(&render(&records, &json, date(4))).unwrap();
+        assert_eq!(listed.len(), 2);
+        let nothing = ListArgs {
+            task: Some("garden".to_string()),
+            ..args()
+        };
+        assert_eq!(render(&records, &nothing, date(4)), "No session matches.\n");
+        let expression = ListArgs {
+            filter: Some(
+                "task:report since:monday type:work duration>=20m"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..args()
+        };
+        assert_eq!(tasks(&expression), tasks(&report));
+        let huge = ListArgs {
+            limit: usize::MAX,
+            page: 2,
+            ..args()
+        };
+        let rendered = render(&records, &huge, date(4));
+        assert_eq!(rendered.lines().count(), 7);
+        assert!(rendered.contains("Write the report"));
+        assert!(rendered.ends_with("\n5 sessions.\n"));
+    }
+
+    #[test]
+    fn reads_the_filter_expressions() {
+        assert_eq!(
+            "task:\"quarterly report\"  type:Long-Break duration>=90m".parse(),
+            Ok(Filter {
+                task: Some("quarterly report".to_string()),
+                kind: Some(KindFilter::LongBreak),
+                min_duration: Some(Span { seconds: 90 * 60 }),
+                ..Filter::default()
+            })
+        );
+        assert_eq!("".parse(), Ok(Filter::default()));
+        for invalid in ["task:\"report", "when:today", "since:someday", "type:nap"] {
+            assert!(invalid.parse::<Filter>().is_err(), "{}", invalid);
+        }
+    }
+}
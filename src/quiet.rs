@@ -0,0 +1,267 @@
+// filepath: src/quiet.rs
+//! Module keeping a Pomodoro timer application quiet when the user must not be disturbed: during
+//! the quiet hours, e.g. from 22:00 to 08:00, while a meeting app runs, or while a window is
+//! fullscreen. The sounds and popups are then held back, and only the terminal shows the changes.
+//!
+//! This is the policy the alerts, the notification router and the pace reminders consult before
+//! making any noise.
+use std::{
+    fmt, io,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, NaiveTime};
+
+use crate::{
+    config::QuietConfig,
+    focus::{self, Process, System},
+    pace::TimeOfDay,
+};
+
+/// How long the running apps and the fullscreen window are trusted before being looked at again.
+const RESCAN: Duration = Duration::from_secs(30);
+
+/// Why the timer keeps quiet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    Hours,
+    Meeting(String),
+    Fullscreen,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Hours => write!(f, "quiet hours"),
+            Reason::Meeting(app) => write!(f, "{} is running", app),
+            Reason::Fullscreen => write!(f, "a window is fullscreen"),
+        }
+    }
+}
+
+/// What the desktop is busy with.
+pub trait Desktop: Send {
+    fn processes(&self) -> io::Result<Vec<Process>>;
+    fn is_fullscreen(&self) -> bool;
+}
+
+/// The running desktop. Fullscreen windows are found through `xprop` on X11 only.
+pub struct Os;
+
+impl Desktop for Os {
+    fn processes(&self) -> io::Result<Vec<Process>> {
+        focus::Os.processes()
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn is_fullscreen(&self) -> bool {
+        let xprop = |args: &[&str]| {
+            std::process::Command::new("xprop")
+                .args(args)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+        // _NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007
+        let Some(window) = xprop(&["-root", "_NET_ACTIVE_WINDOW"])
+            .and_then(|active| Some(active.rsplit_once('#')?.1.trim().to_string()))
+        else {
+            return false;
+        };
+        xprop(&["-id", &window, "_NET_WM_STATE"])
+            .is_some_and(|state| state.contains("_NET_WM_STATE_FULLSCREEN"))
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    fn is_fullscreen(&self) -> bool {
+        false
+    }
+}
+
+/// Whether `now` is within the hours from `start` to `end`, which end the next day when `end`
+/// comes first.
+pub fn in_hours(start: TimeOfDay, end: TimeOfDay, now: NaiveTime) -> bool {
+    if start <= end {
+        start.0 <= now && now < end.0
+    } else {
+        now >= start.0 || now < end.0
+    }
+}
+
+struct Detection {
+    desktop: Box<dyn Desktop>,
+    /// What was detected on the desktop, and when.
+    last: Option<(Instant, Option<Reason>)>,
+}
+
+/// The quiet policy, shared by everything making noise.
+pub struct Quiet {
+    config: QuietConfig,
+    detection: Mutex<Detection>,
+}
+
+impl Quiet {
+    pub fn new(config: QuietConfig, desktop: impl Desktop + 'static) -> Self {
+        Quiet {
+            config,
+            detection: Mutex::new(Detection {
+                desktop: Box::new(desktop),
+                last: None,
+            }),
+        }
+    }
+
+    /// The meeting app running or the fullscreen window, looked for at most every 30 seconds.
+    fn detect(&self, instant: Instant) -> Option<Reason> {
+        if self.config.apps.is_empty() && !self.config.fullscreen {
+            return None;
+        }
+        let mut detection = self.detection.lock().unwrap();
+        if let Some((at, reason)) = &detection.last
+            && instant.duration_since(*at) < RESCAN
+        {
+            return reason.clone();
+        }
+        // A process list that cannot be read makes no noise of its own: the timer just isn't
+        // kept quiet for meetings.
+        let processes = detection.desktop.processes().unwrap_or_default();
+        let reason = self
+            .config
+            .apps
+            .iter()
+            .find(|app| {
+                processes
+                    .iter()
+                    .any(|process| focus::is_named(&process.name, app))
+            })
+            .map(|app| Reason::Meeting(app.clone()))
+            .or_else(|| {
+                (self.config.fullscreen && detection.desktop.is_fullscreen())
+                    .then_some(Reason::Fullscreen)
+            });
+        detection.last = Some((instant, reason.clone()));
+        reason
+    }
+
+    /// Why the timer keeps quiet at `now`, if it does.
+    pub fn reason(&self, now: NaiveTime, instant: Instant) -> Option<Reason> {
+        if let (Some(start), Some(end)) = (self.config.start, self.config.end)
+            && in_hours(start, end, now)
+        {
+            return Some(Reason::Hours);
+        }
+        self.detect(instant)
+    }
+
+    /// Whether the timer keeps quiet right now.
+    pub fn is_quiet(&self) -> bool {
+        self.reason(Local::now().time(), Instant::now()).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::AtomicU32, atomic::Ordering};
+
+    use super::*;
+
+    /// Runs the given processes, counting the scans.
+    struct Fake {
+        running: Vec<&'static str>,
+        fullscreen: bool,
+        scans: Arc<AtomicU32>,
+    }
+
+    impl Desktop for Fake {
+        fn processes(&self) -> io::Result<Vec<Process>> {
+            self.scans.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .running
+                .iter()
+                .enumerate()
+                .map(|(pid, name)| Process {
+                    pid: pid as u32,
+                    name: name.to_string(),
+                })
+                .collect())
+        }
+
+        fn is_fullscreen(&self) -> bool {
+            self.fullscreen
+        }
+    }
+
+    fn config(apps: &[&str], fullscreen: bool) -> QuietConfig {
+        QuietConfig {
+            start: Some(TimeOfDay::new(22, 0)),
+            end: Some(TimeOfDay::new(8, 0)),
+            apps: apps.iter().map(|app| app.to_string()).collect(),
+            fullscreen,
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_may_span_midnight() {
+        let night = (TimeOfDay::new(22, 0), TimeOfDay::new(8, 0));
+        assert!(in_hours(night.0, night.1, at(23, 30)));
+        assert!(in_hours(night.0, night.1, at(7, 59)));
+        assert!(!in_hours(night.0, night.1, at(8, 0)));
+        let lunch = (TimeOfDay::new(12, 0), TimeOfDay::new(13, 0));
+        assert!(in_hours(lunch.0, lunch.1, at(12, 30)));
+        assert!(!in_hours(lunch.0, lunch.1, at(21, 0)));
+    }
+
+    #[test]
+    fn keeps_quiet_during_meetings() {
+        let scans = Arc::new(AtomicU32::new(0));
+        let quiet = Quiet::new(
+            config(&["zoom"], true),
+            Fake {
+                running: vec!["bash", "zoom"],
+                fullscreen: true,
+                scans: Arc::clone(&scans),
+            },
+        );
+        let start = Instant::now();
+        assert_eq!(quiet.reason(at(23, 0), start), Some(Reason::Hours));
+        assert_eq!(
+            quiet.reason(at(10, 0), start),
+            Some(Reason::Meeting("zoom".to_string()))
+        );
+        // The desktop is only looked at again once the scan is stale.
+        quiet.reason(at(10, 0), start + Duration::from_secs(10));
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+        quiet.reason(at(10, 0), start + RESCAN);
+        assert_eq!(scans.load(Ordering::SeqCst), 2);
+
+        let quiet = Quiet::new(
+            config(&["teams"], true),
+            Fake {
+                running: vec!["bash"],
+                fullscreen: true,
+                scans: Arc::new(AtomicU32::new(0)),
+            },
+        );
+        assert_eq!(
+            quiet
+                .reason(at(10, 0), start)
+                .map(|reason| reason.to_string()),
+            Some("a window is fullscreen".to_string())
+        );
+        let quiet = Quiet::new(
+            config(&[], false),
+            Fake {
+                running: vec!["zoom"],
+                fullscreen: true,
+                scans: Arc::new(AtomicU32::new(0)),
+            },
+        );
+        assert_eq!(quiet.reason(at(10, 0), start), None);
+    }
+}
@@ -0,0 +1,234 @@
+// filepath: src/reminders.rs
+//! Module showing the reminders of the `[[reminders]]` tables, e.g. to stand up, stretch or drink
+//! some water, with a notification at the start of the breaks. Each reminder has its cadence, in
+//! breaks, and the reminders due at a break take turns: a single one is shown each time. Those set
+//! with `mid_work` are shown in the middle of the work sessions too, briefly.
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    SessionType,
+    config::ReminderConfig,
+    desktop::{Notification, Timeout},
+    events::Event,
+    quiet::Quiet,
+    settings::SharedSettings,
+};
+
+/// How long the reminders of the middle of the work sessions stay on screen.
+const MID_WORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A reminder to show, and when.
+#[derive(Debug, PartialEq)]
+pub enum Showing {
+    BreakStart(String),
+    MidWork(String),
+}
+
+/// The reminders due at a break, or in the middle of a work session, taking turns.
+struct Rotation {
+    /// Breaks, or work sessions, started so far.
+    count: u64,
+    /// Index of the reminder to look at first the next time.
+    next: usize,
+}
+
+impl Rotation {
+    /// Counts a break or a work session, and returns the reminder to show then, among those
+    /// `eligible`.
+    fn advance<'a>(
+        &mut self,
+        reminders: &'a [ReminderConfig],
+        eligible: impl Fn(&ReminderConfig) -> bool,
+    ) -> Option<&'a str> {
+        self.count += 1;
+        let index = (0..reminders.len())
+            .map(|offset| (self.next + offset) % reminders.len())
+            .find(|index| {
+                let reminder = &reminders[*index];
+                eligible(reminder) && self.count.is_multiple_of(reminder.every)
+            })?;
+        self.next = index + 1;
+        Some(&reminders[index].text)
+    }
+}
+
+pub struct Reminders {
+    reminders: Vec<ReminderConfig>,
+    settings: SharedSettings,
+    quiet: Option<Arc<Quiet>>,
+    breaks: Rotation,
+    work: Rotation,
+    /// Work time of the session at which its reminder is shown, none outside work sessions.
+    middle: Option<Duration>,
+}
+
+impl Reminders {
+    pub fn new(reminders: Vec<ReminderConfig>, settings: SharedSettings) -> Self {
+        Reminders {
+            reminders,
+            settings,
+            quiet: None,
+            breaks: Rotation { count: 0, next: 0 },
+            work: Rotation { count: 0, next: 0 },
+            middle: None,
+        }
+    }
+
+    /// Holds the reminders back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.quiet = Some(quiet);
+        self
+    }
+
+    /// Handles the events until the event bus is dropped.
+    pub fn run(mut self, events: Receiver<Event>) {
+        for event in events {
+            let Some(showing) = self.handle(&event) else {
+                continue;
+            };
+            if self.quiet.as_ref().is_some_and(|quiet| quiet.is_quiet()) {
+                continue;
+            }
+            let mut notification = Notification::new();
+            notification
+                .summary("Pomodoro Timer")
+                .icon("dialog-information");
+            match &showing {
+                Showing::BreakStart(text) => notification.body(text),
+                Showing::MidWork(text) => notification
+                    .body(text)
+                    .timeout(Timeout::Milliseconds(MID_WORK_TIMEOUT.as_millis() as u32)),
+            };
+            // The timer already reports notification failures.
+            let _ = notification.show();
+        }
+    }
+
+    /// Returns the reminder to show after `event`, if any.
+    pub fn handle(&mut self, event: &Event) -> Option<Showing> {
+        let showing = match event {
+            Event::SessionStarted {
+                session:
+                    SessionType {
+                        kind: SessionKind::Work,
+                        ..
+                    },
+                duration,
+                ..
+            } => {
+                self.middle = Some(*duration / 2);
+                return None;
+            }
+            Event::SessionStarted { .. } => {
+                self.middle = None;
+                let text = self.breaks.advance(&self.reminders, |_| true)?;
+                Showing::BreakStart(text.to_string())
+            }
+            Event::SessionEnded { .. } => {
+                self.middle = None;
+                return None;
+            }
+            Event::Tick { elapsed, .. } if self.middle.is_some_and(|middle| *elapsed >= middle) => {
+                self.middle = None;
+                let text = self
+                    .work
+                    .advance(&self.reminders, |reminder| reminder.mid_work)?;
+                Showing::MidWork(text.to_string())
+            }
+            _ => return None,
+        };
+        (!self.settings.lock().unwrap().no_notify).then_some(showing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn reminder(text: &str, every: u64, mid_work: bool) -> ReminderConfig {
+        ReminderConfig {
+            text: text.to_string(),
+            every,
+            mid_work,
+        }
+    }
+
+    fn short_break() -> Event {
+        Event::SessionStarted {
+            session: SessionType::short_break(),
+            cycle: 1,
+            duration: MINUTE * 5,
+        }
+    }
+
+    fn shown(showing: Option<Showing>) -> Option<String> {
+        match showing? {
+            Showing::BreakStart(text) => Some(text),
+            Showing::MidWork(text) => Some(format!("mid-work: {}", text)),
+        }
+    }
+
+    #[test]
+    fn reminders_take_turns_at_their_cadence() {
+        let mut reminders = Reminders::new(
+            vec![
+                reminder("Stand up", 1, false),
+                reminder("Stretch", 2, false),
+                reminder("Drink water", 3, false),
+            ],
+            Settings::default().into_shared(),
+        );
+        let texts: Vec<Option<String>> = (0..6)
+            .map(|_| shown(reminders.handle(&short_break())))
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "Stand up",
+                "Stretch",
+                "Drink water",
+                "Stand up",
+                "Stand up",
+                "Stretch"
+            ]
+            .map(|text| Some(text.to_string()))
+        );
+    }
+
+    #[test]
+    fn shows_the_mid_work_reminders_halfway() {
+        let mut reminders = Reminders::new(
+            vec![
+                reminder("Stand up", 1, false),
+                reminder("Drink water", 1, true),
+            ],
+            Settings::default().into_shared(),
+        );
+        let work = Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: MINUTE * 25,
+        };
+        let tick = |minutes: u32| Event::Tick {
+            elapsed: MINUTE * minutes,
+            remaining: MINUTE * (25 - minutes),
+        };
+        assert_eq!(shown(reminders.handle(&work)), None);
+        assert_eq!(shown(reminders.handle(&tick(12))), None);
+        assert_eq!(
+            shown(reminders.handle(&tick(13))).as_deref(),
+            Some("mid-work: Drink water")
+        );
+        // Once per work session.
+        assert_eq!(shown(reminders.handle(&tick(14))), None);
+        assert_eq!(shown(reminders.handle(&tick(20))), None);
+    }
+}
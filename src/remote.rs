@@ -0,0 +1,376 @@
+// filepath: src/remote.rs
+//! Module shared by the remote controls of a Pomodoro timer application: the snapshots of the
+//! timer they report and the commands they send.
+use std::{
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use rustodoro_client::protocol::{Action, Session, SessionKind, Snapshot, Totals};
+use tokio::sync::mpsc::WeakUnboundedSender;
+
+use crate::{
+    events::{Event, EventBus},
+    plan,
+    settings::SharedSettings,
+    today::SharedToday,
+    types::Command,
+};
+
+/// Follows the running session from the timer events, to take snapshots of it on demand.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    session: Option<Session>,
+    /// Time left in the session when `since`.
+    remaining: Duration,
+    /// When the session last counted down, none while it is paused.
+    since: Option<Instant>,
+    totals: Totals,
+    /// The pomodoros of the day, counted by the scheduler. Without it, those of the run are.
+    today: Option<SharedToday>,
+    /// Where to send the snapshots after each event.
+    watchers: Vec<Sender<Snapshot>>,
+}
+
+/// The tracker kept up to date by [`follow`].
+pub type SharedTracker = Arc<Mutex<Tracker>>;
+
+/// Follows the events emitted from now on, on a thread of its own, along with the pomodoros of
+/// the day in `today`.
+pub fn follow(events: &EventBus, settings: SharedSettings, today: SharedToday) -> SharedTracker {
+    let tracker = Arc::new(Mutex::new(Tracker {
+        today: Some(today),
+        ..Tracker::default()
+    }));
+    let events = events.subscribe();
+    let tracked = Arc::clone(&tracker);
+    thread::spawn(move || {
+        for event in events {
+            let cycles = settings.lock().unwrap().cycles_per_set();
+            let mut tracked = tracked.lock().unwrap();
+            let now = Instant::now();
+            tracked.update(&event, cycles, now);
+            tracked.notify(now);
+        }
+        // The watchers see the timer stop.
+        tracked.lock().unwrap().watchers.clear();
+    });
+    tracker
+}
+
+/// The snapshot of the timer now.
+pub fn snapshot(tracker: &Mutex<Tracker>) -> Snapshot {
+    tracker.lock().unwrap().snapshot(Instant::now())
+}
+
+/// Describes `status` in a few words, for people.
+pub fn describe(status: &Snapshot) -> String {
+    let pomodoros = |count: u64| match count {
+        1 => "1 pomodoro".to_string(),
+        count => format!("{} pomodoros", count),
+    };
+    let done = match (status.totals.pomodoros, status.totals.today) {
+        (0, 0) => String::new(),
+        (run, today) if run == today => format!("\n{} done so far.", pomodoros(run)),
+        (0, today) => format!("\n{} done today.", pomodoros(today)),
+        // Before midnight, the run counts the sessions of the day before too.
+        (run, today) => format!("\n{} done so far, {} today.", pomodoros(run), today),
+    };
+    let Some(session) = &status.session else {
+        return format!("Between two sessions.{}", done);
+    };
+    let name = match session.kind {
+        SessionKind::Work => format!("Work session {}/{}", session.cycle, session.cycles),
+        SessionKind::ShortBreak => "Short break".to_string(),
+        SessionKind::LongBreak => "Long break".to_string(),
+    };
+    format!(
+        "{}: {:02}:{:02} left{}, {}.{}",
+        name,
+        session.remaining_secs / 60,
+        session.remaining_secs % 60,
+        if session.paused { ", paused" } else { "" },
+        plan::projection(
+            Local::now(),
+            Duration::from_secs(session.remaining_secs),
+            session.plan_secs.map(Duration::from_secs)
+        ),
+        done
+    )
+}
+
+impl Tracker {
+    /// Takes `event`, emitted at `now`, into account.
+    pub fn update(&mut self, event: &Event, cycles: u64, now: Instant) {
+        match event {
+            Event::SessionStarted {
+                session,
+                cycle,
+                duration,
+            } => {
+                self.session = Some(Session {
+                    kind: session.kind,
+                    cycle: *cycle,
+                    cycles,
+                    duration_secs: duration.as_secs(),
+                    remaining_secs: duration.as_secs(),
+                    paused: false,
+                    plan_secs: None,
+                });
+                self.remaining = *duration;
+                self.since = Some(now);
+            }
+            Event::SessionEnded {
+                session,
+                elapsed,
+                outcome,
+            } => {
+                if session.is_work() {
+                    self.totals.work_secs += elapsed.as_secs();
+                    if outcome.is_done() {
+                        self.totals.pomodoros += 1;
+                    }
+                }
+                self.session = None;
+            }
+            Event::Extended { by } => {
+                if let Some(session) = &mut self.session {
+                    session.duration_secs += by.as_secs();
+                    self.remaining += *by;
+                }
+            }
+            Event::Planned { ahead } => {
+                if let Some(session) = &mut self.session {
+                    session.plan_secs = Some(ahead.as_secs());
+                }
+            }
+            event => {
+                let Some(session) = self.session.as_ref() else {
+                    return;
+                };
+                let duration = Duration::from_secs(session.duration_secs);
+                match event {
+                    Event::Tick { remaining, .. } => {
+                        self.remaining = *remaining;
+                        self.since = Some(now);
+                    }
+                    Event::Paused { elapsed } => {
+                        self.remaining = duration.saturating_sub(*elapsed);
+                        self.since = None;
+                    }
+                    Event::Resumed { elapsed, .. } => {
+                        self.remaining = duration.saturating_sub(*elapsed);
+                        self.since = Some(now);
+                    }
+                    Event::Reset => {
+                        self.remaining = duration;
+                        self.since = self.since.map(|_| now);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// The state of the timer at `now`, the running session counting down between two ticks.
+    /// Receives a snapshot after each event from now on, until the receiver is dropped.
+    pub fn watch(&mut self) -> Receiver<Snapshot> {
+        let (tx, rx) = mpsc::channel();
+        self.watchers.push(tx);
+        rx
+    }
+
+    fn notify(&mut self, now: Instant) {
+        if self.watchers.is_empty() {
+            return;
+        }
+        let snapshot = self.snapshot(now);
+        self.watchers
+            .retain(|watcher| watcher.send(snapshot.clone()).is_ok());
+    }
+
+    pub fn snapshot(&self, now: Instant) -> Snapshot {
+        let session = self.session.clone().map(|session| {
+            let remaining = match self.since {
+                Some(since) => self.remaining.saturating_sub(now - since),
+                None => self.remaining,
+            };
+            Session {
+                // Rounded up, as a countdown shows it.
+                remaining_secs: remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0),
+                paused: self.since.is_none(),
+                ..session
+            }
+        });
+        // Read when asked, so that the count starts over at midnight without waiting for a
+        // session to end.
+        let today = match &self.today {
+            Some(today) => today.lock().unwrap().on(Local::now().date_naive()),
+            None => self.totals.pomodoros,
+        };
+        Snapshot {
+            name: None,
+            session,
+            totals: Totals {
+                today,
+                ..self.totals
+            },
+        }
+    }
+}
+
+/// The timer command carrying out `action`, none for a status request.
+pub fn command(action: Action) -> Option<Command> {
+    match action {
+        Action::Status | Action::Watch => None,
+        Action::Pause => Some(Command::Pause),
+        Action::Resume => Some(Command::Resume),
+        Action::Toggle => Some(Command::PauseResume),
+        Action::Skip => Some(Command::Skip),
+        Action::Reset => Some(Command::Reset),
+        Action::SkipBreaks(count) => Some(Command::SkipBreaks(count)),
+        Action::Extend(secs) => Some(Command::Extend(Duration::from_secs(secs))),
+        Action::SetTask(task) => Some(Command::SetTask(task)),
+        Action::Jump(position) => Some(Command::Jump(position.into())),
+    }
+}
+
+/// Sends `command` to the timer, returning false once it stopped listening. Remote controls only
+/// hold a weak sender, so that they do not keep the timer running once the keyboard quits.
+pub fn send(commands: &WeakUnboundedSender<Command>, command: Command) -> bool {
+    commands
+        .upgrade()
+        .is_some_and(|tx| tx.send(command).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SessionType, events::SessionOutcome, today::Today};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn started() -> Event {
+        Event::SessionStarted {
+            session: SessionType::short_break(),
+            cycle: 2,
+            duration: SECOND * 300,
+        }
+    }
+
+    #[test]
+    fn snapshots_the_running_session() {
+        let start = Instant::now();
+        let mut tracker = Tracker::default();
+        tracker.update(&started(), 4, start);
+        tracker.update(
+            &Event::Tick {
+                elapsed: SECOND * 60,
+                remaining: SECOND * 240,
+            },
+            4,
+            start + SECOND * 60,
+        );
+        // Between two ticks, e.g. in low-power mode.
+        let session = tracker.snapshot(start + SECOND * 70).session.unwrap();
+        assert_eq!(session.kind, SessionKind::ShortBreak);
+        assert_eq!((session.cycle, session.cycles), (2, 4));
+        assert_eq!(session.remaining_secs, 230);
+        assert!(!session.paused);
+
+        tracker.update(
+            &Event::Paused {
+                elapsed: SECOND * 75,
+            },
+            4,
+            start + SECOND * 75,
+        );
+        let session = tracker.snapshot(start + SECOND * 500).session.unwrap();
+        assert_eq!((session.remaining_secs, session.paused), (225, true));
+        tracker.update(&Event::Reset, 4, start + SECOND * 500);
+        let session = tracker.snapshot(start + SECOND * 600).session.unwrap();
+        assert_eq!((session.remaining_secs, session.paused), (300, true));
+        tracker.update(
+            &Event::SessionEnded {
+                session: SessionType::short_break(),
+                elapsed: SECOND * 300,
+                outcome: SessionOutcome::Completed,
+            },
+            4,
+            start + SECOND * 900,
+        );
+        assert_eq!(tracker.snapshot(start + SECOND * 900), Snapshot::default());
+    }
+
+    #[test]
+    fn counts_the_work_sessions() {
+        let start = Instant::now();
+        let mut tracker = Tracker::default();
+        for outcome in [SessionOutcome::Completed, SessionOutcome::Interrupted] {
+            tracker.update(
+                &Event::SessionEnded {
+                    session: SessionType::work(),
+                    elapsed: SECOND * 1500,
+                    outcome,
+                },
+                4,
+                start,
+            );
+        }
+        tracker.update(
+            &Event::SessionEnded {
+                session: SessionType::long_break(),
+                elapsed: SECOND * 900,
+                outcome: SessionOutcome::Completed,
+            },
+            4,
+            start,
+        );
+        assert_eq!(
+            tracker.snapshot(start).totals,
+            Totals {
+                pomodoros: 1,
+                work_secs: 3000,
+                today: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn tells_the_pomodoros_of_the_day() {
+        let start = Instant::now();
+        let yesterday = Local::now().date_naive().pred_opt().unwrap();
+        let today = Today::new(yesterday, 5).into_shared();
+        let mut tracker = Tracker {
+            today: Some(Arc::clone(&today)),
+            ..Tracker::default()
+        };
+        tracker.update(
+            &Event::SessionEnded {
+                session: SessionType::work(),
+                elapsed: SECOND * 1500,
+                outcome: SessionOutcome::Completed,
+            },
+            4,
+            start,
+        );
+        // The count of yesterday is over.
+        let status = tracker.snapshot(start);
+        assert_eq!((status.totals.pomodoros, status.totals.today), (1, 0));
+        assert_eq!(
+            describe(&status),
+            "Between two sessions.\n1 pomodoro done so far, 0 today."
+        );
+        today.lock().unwrap().count(Local::now().date_naive());
+        today.lock().unwrap().count(Local::now().date_naive());
+        assert_eq!(
+            describe(&tracker.snapshot(start)),
+            "Between two sessions.\n1 pomodoro done so far, 2 today."
+        );
+    }
+}
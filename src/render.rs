@@ -0,0 +1,240 @@
+// filepath: src/render.rs
+//! Module rendering the text shown between the sessions of a Pomodoro timer application, and the
+//! progress bar counting them down.
+use std::{fmt, time::Duration};
+
+use crossterm::terminal;
+use indicatif::{ProgressStyle, style::TemplateError};
+use rustodoro_client::protocol::SessionKind;
+use serde::Deserialize;
+
+use crate::{SessionType, garden::Plant};
+
+/// Width of the dividers framing a session banner.
+const DIVIDER_WIDTH: usize = 48;
+
+/// Widest progress bar counting a session down, in characters.
+const BAR_WIDTH: u16 = 40;
+/// Narrowest progress bar, the line wrapping in narrower terminals.
+const MIN_BAR_WIDTH: u16 = 10;
+/// Width of the block filling up in the zen display with the plain theme.
+const ZEN_BLOCK_WIDTH: u16 = 8;
+/// Characters taken by the rest of the line of the progress bar, a long message included.
+const BAR_SURROUNDINGS: u16 = 60;
+
+/// Width of the progress bar in a terminal `columns` wide.
+fn bar_width(columns: u16) -> u16 {
+    columns
+        .saturating_sub(BAR_SURROUNDINGS)
+        .clamp(MIN_BAR_WIDTH, BAR_WIDTH)
+}
+
+/// Style of the progress bar counting a session down in a terminal `columns` wide, the bar
+/// narrowing for the line not to wrap, and filling in `color`.
+pub fn progress_style(columns: u16, color: Color) -> Result<ProgressStyle, TemplateError> {
+    let template = format!(
+        "{{spinner:.green}} [{{bar:{}.{}/blue}}] {{pos}}/{{len}} ({{eta}}) < {{msg}} >",
+        bar_width(columns),
+        color
+    );
+    Ok(ProgressStyle::with_template(&template)?.progress_chars("#>-"))
+}
+
+/// Color of the progress bar of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    #[default]
+    Cyan,
+    White,
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+            Color::White => "white",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Width of the terminal, in characters, 80 when it cannot be told.
+pub fn columns() -> u16 {
+    terminal::size().map_or(80, |(columns, _)| columns)
+}
+
+/// How the text around the progress bar looks.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Box-drawing dividers and an emoji per kind of session.
+    #[default]
+    Emoji,
+    /// ASCII only, for terminals or fonts without emoji.
+    Plain,
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Theme::Emoji => write!(f, "emoji"),
+            Theme::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+/// What the progress bar of the work sessions shows.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// The bar with the time elapsed, the length and the time left.
+    #[default]
+    Bar,
+    /// A dot or a block slowly filling up, without any number, the time showing for a few
+    /// seconds on a keypress.
+    Zen,
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayMode::Bar => write!(f, "bar"),
+            DisplayMode::Zen => write!(f, "zen"),
+        }
+    }
+}
+
+pub struct Renderer {
+    theme: Theme,
+}
+
+impl Renderer {
+    pub fn new(theme: Theme) -> Self {
+        Renderer { theme }
+    }
+
+    /// The banner announcing `session`, with its `duration` when it differs from the settings.
+    /// Lines end with "\r\n" as the terminal is in raw mode.
+    pub fn transition(
+        &self,
+        session: &SessionType,
+        cycle: u64,
+        cycles: u64,
+        duration: Option<Duration>,
+    ) -> String {
+        let (divider, separator, emoji) = match self.theme {
+            Theme::Emoji => {
+                let emoji = match session.kind {
+                    SessionKind::Work => "🍅  ",
+                    SessionKind::ShortBreak => "☕  ",
+                    SessionKind::LongBreak => "🌴  ",
+                };
+                ("─", "·", emoji)
+            }
+            Theme::Plain => ("-", "-", ""),
+        };
+        let mut title = format!("{emoji}{session} {separator} cycle {cycle}/{cycles}");
+        if let Some(duration) = duration {
+            let (minutes, seconds) = (duration.as_secs() / 60, duration.as_secs() % 60);
+            title.push_str(&format!(" {separator} {minutes}m{seconds:02}s"));
+        }
+        let divider = divider.repeat(DIVIDER_WIDTH);
+        format!("\r\n{divider}\r\n{title}\r\n{divider}\r\n")
+    }
+
+    /// Style of the progress bar of a work session in the zen display, filling in `color` without
+    /// any number: a dot with the emoji theme, a block otherwise.
+    pub fn zen_style(&self, color: Color) -> Result<ProgressStyle, TemplateError> {
+        let (width, chars, frame) = match self.theme {
+            Theme::Emoji => (1, "●◕◑◔○", ("", "")),
+            Theme::Plain => (ZEN_BLOCK_WIDTH, "#-", ("[", "]")),
+        };
+        let template = format!(
+            "{}{{bar:{}.{}/blue}}{} {{msg}}",
+            frame.0, width, color, frame.1
+        );
+        Ok(ProgressStyle::with_template(&template)?.progress_chars(chars))
+    }
+
+    /// The line showing the plant of the day under the banner of a work session.
+    pub fn garden(&self, plant: &Plant) -> String {
+        format!("Garden of the day: {}\r\n", plant.render(self.theme))
+    }
+
+    /// The countdown line shown during the transition pause, `left` being rounded up to the
+    /// second.
+    pub fn starting_in(&self, left: Duration) -> String {
+        let seconds = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+        match self.theme {
+            Theme::Emoji => format!("⏳ Starting in {seconds}s…"),
+            Theme::Plain => format!("Starting in {seconds}s..."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn narrows_the_bar_to_the_terminal() {
+        assert_eq!(bar_width(120), 40);
+        assert_eq!(bar_width(80), 20);
+        assert_eq!(bar_width(40), 10);
+        assert!(progress_style(80, Color::Magenta).is_ok());
+        for theme in [Theme::Emoji, Theme::Plain] {
+            assert!(Renderer::new(theme).zen_style(Color::Red).is_ok());
+        }
+    }
+
+    #[test]
+    fn plain_theme_has_no_emoji() {
+        let banner = Renderer::new(Theme::Plain).transition(&SessionType::long_break(), 4, 4, None);
+        assert!(banner.is_ascii());
+        assert!(banner.contains("Long break - cycle 4/4"));
+    }
+
+    #[test]
+    fn emoji_theme_marks_the_kind_of_session() {
+        let renderer = Renderer::new(Theme::Emoji);
+        assert!(
+            renderer
+                .transition(&SessionType::work(), 1, 4, None)
+                .contains("🍅  Work session · cycle 1/4")
+        );
+        assert!(
+            renderer
+                .transition(
+                    &SessionType::short_break(),
+                    1,
+                    4,
+                    Some(Duration::from_secs(372))
+                )
+                .contains("☕  Short break · cycle 1/4 · 6m12s")
+        );
+    }
+
+    #[test]
+    fn countdown_rounds_up() {
+        let renderer = Renderer::new(Theme::Plain);
+        assert_eq!(
+            renderer.starting_in(Duration::from_millis(2500)),
+            "Starting in 3s..."
+        );
+        assert_eq!(
+            renderer.starting_in(Duration::from_secs(2)),
+            "Starting in 2s..."
+        );
+    }
+}
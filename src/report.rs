@@ -0,0 +1,481 @@
+// filepath: src/report.rs
+//! Module generating the standalone HTML focus report of a Pomodoro timer application from the
+//! session history.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Days, Local, NaiveDate, Weekday};
+
+use crate::{
+    aggregate::{self, Reviews},
+    config::{self, Cli, ConfigError, expand_home},
+    git::{self, Commit},
+    history::{
+        HISTORY_ENV, History, HistoryError, PauseReason, SessionKind, SessionRecord, history_path,
+    },
+    summary::{Stats, format_duration},
+};
+
+/// Page the report is rendered into, each `{{name}}` being replaced by its content.
+const TEMPLATE: &str = include_str!("report.html");
+
+/// Color of the bars and of the busiest heatmap cells, as RGB components.
+const ACCENT: (u8, u8, u8) = (217, 83, 79);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+    #[error("cannot write report {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Runs the `report` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, html: &Path, days: u32) -> i32 {
+    match write_report(cli, html, days) {
+        Ok(()) => {
+            println!("Report written to {}.", html.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn write_report(cli: &Cli, html: &Path, days: u32) -> Result<(), ReportError> {
+    let path =
+        history_path(cli.history.as_deref(), cli.name.as_deref()).ok_or(ReportError::NoHistory)?;
+    let records = History::new(path).sessions()?;
+    let last = Local::now().date_naive();
+    let first = last - Days::new(u64::from(days.max(1)) - 1);
+    let commits = config::resolve(cli)?.git.value.map(|config| {
+        let mut commits = Vec::new();
+        for repo in &config.repos {
+            match git::log(&expand_home(repo), first, config.author.as_deref()) {
+                Ok(log) => commits.extend(log),
+                Err(e) => eprintln!("warning: {}", e),
+            }
+        }
+        commits
+    });
+    fs::write(html, render_html(&records, first, last, commits.as_deref())).map_err(|source| {
+        ReportError::Write {
+            path: html.to_path_buf(),
+            source,
+        }
+    })
+}
+
+/// Renders the report of the days from `first` to `last` included, with the `commits` made during
+/// the work sessions when the repositories were read.
+pub fn render_html(
+    records: &[SessionRecord],
+    first: NaiveDate,
+    last: NaiveDate,
+    commits: Option<&[Commit]>,
+) -> String {
+    let per_day = aggregate::pomodoros_per_day(records, first, last);
+    let per_hour = aggregate::focus_per_hour(records, first);
+    let per_task = aggregate::focus_per_task(records, first);
+
+    let pomodoros: u64 = per_day.iter().map(|(_, count)| count).sum();
+    let focused: Duration = per_hour.iter().flatten().sum();
+    let active_days = per_day.iter().filter(|(_, count)| *count > 0).count();
+    let focus = aggregate::average_focus(records, first);
+    let (pauses, reasons) = aggregate::pause_reasons(records, first);
+    let reviews = aggregate::reviews_per_task(records, first);
+    let breaks = Stats::from_records(
+        records
+            .iter()
+            .filter(|record| record.started_at.date_naive() >= first),
+    );
+
+    TEMPLATE
+        .replace(
+            "{{period}}",
+            &format!(
+                "{} to {}",
+                first.format("%B %-d, %Y"),
+                last.format("%B %-d, %Y")
+            ),
+        )
+        .replace("{{pomodoros}}", &pomodoros.to_string())
+        .replace("{{focused}}", &format_duration(focused))
+        .replace("{{active_days}}", &active_days.to_string())
+        .replace(
+            "{{focus}}",
+            &focus.map_or_else(|| "-".to_string(), |focus| format!("{}/100", focus)),
+        )
+        .replace(
+            "{{compliance}}",
+            &breaks.break_compliance().map_or_else(
+                || "-".to_string(),
+                |compliance| format!("{:.0}%", compliance * 100.0),
+            ),
+        )
+        .replace(
+            "{{breaks}}",
+            &format!(
+                "{} taken until their end, {} cut short, {} skipped.",
+                breaks.breaks_taken, breaks.breaks_cut_short, breaks.breaks_skipped
+            ),
+        )
+        .replace("{{pauses}}", &pauses_told(pauses, &reasons))
+        .replace("{{daily_bars}}", &daily_bars(&per_day))
+        .replace("{{first_day}}", &first.format("%b %-d").to_string())
+        .replace("{{last_day}}", &last.format("%b %-d").to_string())
+        .replace("{{heatmap}}", &heatmap(&per_hour))
+        .replace(
+            "{{commits}}",
+            &commits.map_or_else(String::new, |commits| {
+                commits_during(records, first, last, commits)
+            }),
+        )
+        // Replaced last, as task names come from the user.
+        .replace("{{tasks}}", &tasks(&per_task))
+        .replace("{{intents}}", &intents(&reviews))
+}
+
+/// The pauses of the work sessions and their reasons, e.g. "12 pauses: 5 calls, 4 interruptions, 2
+/// bio breaks, 1 without a reason."
+fn pauses_told(pauses: u64, reasons: &[(PauseReason, usize)]) -> String {
+    if pauses == 0 {
+        return "No pause during the work sessions.".to_string();
+    }
+    let mut counts: Vec<String> = reasons
+        .iter()
+        .map(|(reason, count)| reason.count(*count))
+        .collect();
+    let given: usize = reasons.iter().map(|(_, count)| count).sum();
+    let unexplained = pauses.saturating_sub(given as u64);
+    if unexplained > 0 && !counts.is_empty() {
+        counts.push(format!("{} without a reason", unexplained));
+    }
+    let told = match pauses {
+        1 => "1 pause".to_string(),
+        _ => format!("{} pauses", pauses),
+    };
+    if counts.is_empty() {
+        format!("{}.", told)
+    } else {
+        format!("{}: {}.", told, counts.join(", "))
+    }
+}
+
+fn daily_bars(per_day: &[(NaiveDate, u64)]) -> String {
+    let max = per_day
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    per_day
+        .iter()
+        .map(|(day, count)| {
+            let class = if *count == 0 { " class=\"empty\"" } else { "" };
+            format!(
+                "<div{} style=\"height: {}%\" title=\"{}: {}\"></div>\n",
+                class,
+                count * 100 / max,
+                day.format("%a %b %-d"),
+                count
+            )
+        })
+        .collect()
+}
+
+fn heatmap(per_hour: &[[Duration; 24]; 7]) -> String {
+    let max = per_hour.iter().flatten().max().copied().unwrap_or_default();
+    let mut rows = String::from("<tr><th></th>");
+    for hour in 0..24 {
+        rows.push_str(&format!("<th>{:02}</th>", hour));
+    }
+    rows.push_str("</tr>\n");
+    let (red, green, blue) = ACCENT;
+    for (weekday, hours) in per_hour.iter().enumerate() {
+        let name = Weekday::try_from(weekday as u8).expect("7 weekdays");
+        rows.push_str(&format!("<tr><th>{}</th>", name));
+        for (hour, focused) in hours.iter().enumerate() {
+            let alpha = if max.is_zero() {
+                0.0
+            } else {
+                focused.as_secs_f64() / max.as_secs_f64()
+            };
+            rows.push_str(&format!(
+                "<td style=\"background: rgba({red}, {green}, {blue}, {:.2})\" title=\"{} {:02}:00: {}\"></td>",
+                alpha.max(0.05),
+                name,
+                hour,
+                format_duration(*focused)
+            ));
+        }
+        rows.push_str("</tr>\n");
+    }
+    rows
+}
+
+fn tasks(per_task: &[(String, Duration)]) -> String {
+    let Some((_, longest)) = per_task.first() else {
+        return "<tr><td>No work session in this period.</td></tr>\n".to_string();
+    };
+    per_task
+        .iter()
+        .map(|(task, focused)| {
+            format!(
+                "<tr><td>{}</td><td style=\"width: 60%\"><div class=\"bar\" style=\"width: {:.0}%\"></div></td><td>{}</td></tr>\n",
+                escape(task),
+                focused.as_secs_f64() * 100.0 / longest.as_secs_f64().max(1.0),
+                format_duration(*focused)
+            )
+        })
+        .collect()
+}
+
+/// The rows of the intents carried out per task, e.g. "67%: 1 done, 2 partial".
+fn intents(per_task: &[(String, Reviews)]) -> String {
+    if per_task.is_empty() {
+        return "<tr><td>No work session reviewed in this period.</td></tr>\n".to_string();
+    }
+    per_task
+        .iter()
+        .map(|(task, reviews)| {
+            let counts: Vec<String> = [
+                (reviews.done, "done"),
+                (reviews.partial, "partial"),
+                (reviews.missed, "missed"),
+            ]
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, told)| format!("{} {}", count, told))
+            .collect();
+            format!(
+                "<tr><td>{}</td><td style=\"width: 60%\"><div class=\"bar\" style=\"width: {}%\"></div></td><td>{}%: {}</td></tr>\n",
+                escape(task),
+                reviews.completion(),
+                reviews.completion(),
+                counts.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// The section telling the commits made during the work sessions from `first` to `last`.
+fn commits_during(
+    records: &[SessionRecord],
+    first: NaiveDate,
+    last: NaiveDate,
+    commits: &[Commit],
+) -> String {
+    let sessions: Vec<(&SessionRecord, Vec<&Commit>)> = records
+        .iter()
+        .filter(|record| {
+            record.kind == SessionKind::Work
+                && (first..=last).contains(&record.started_at.date_naive())
+        })
+        .map(|record| (record, git::during(record, commits)))
+        .collect();
+    let productive = sessions.iter().filter(|(_, made)| !made.is_empty()).count();
+    let total: usize = sessions.iter().map(|(_, made)| made.len()).sum();
+    let mut section = format!(
+        "<h2>Commits</h2>\n<p>{} commit{} during {} of the {} work sessions.</p>\n",
+        total,
+        if total == 1 { "" } else { "s" },
+        productive,
+        sessions.len()
+    );
+    if productive == 0 {
+        return section;
+    }
+    section.push_str("<table class=\"commits\">\n");
+    for (record, made) in sessions.iter().filter(|(_, made)| !made.is_empty()) {
+        let subjects: Vec<String> = made
+            .iter()
+            .map(|commit| {
+                format!(
+                    "<code>{} {}</code> {}",
+                    escape(&commit.repo),
+                    escape(&commit.hash),
+                    escape(&commit.subject)
+                )
+            })
+            .collect();
+        section.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            record.started_at.format("%a %b %-d %H:%M"),
+            record.task.as_deref().map_or_else(String::new, escape),
+            subjects.join("<br>")
+        ));
+    }
+    section.push_str("</table>\n");
+    section
+}
+
+/// Escapes `text` for HTML content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('{', "&#123;")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{events::SessionOutcome, history::Review};
+
+    fn work(day: u32, task: Option<&str>) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local.with_ymd_and_hms(2026, 3, day, 9, 0, 0).unwrap(),
+            kind: SessionKind::Work,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: task.map(str::to_string),
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    #[test]
+    fn fills_every_placeholder() {
+        let html = render_html(&[work(10, None), work(12, None)], date(1), date(14), None);
+        assert!(!html.contains("{{"));
+        assert!(html.contains("March 1, 2026 to March 14, 2026"));
+        assert!(html.contains("<strong>2</strong>pomodoros"));
+        assert!(html.contains("<strong>50m</strong>focused"));
+        assert!(html.contains("<strong>-</strong>focus score"));
+        assert!(html.contains("<strong>-</strong>break compliance"));
+        assert_eq!(html.matches("title=\"").count(), 14 + 7 * 24);
+    }
+
+    #[test]
+    fn tells_the_breaks_skipped() {
+        let mut records = vec![work(10, None)];
+        for (elapsed, outcome) in [
+            (5 * 60, SessionOutcome::Completed),
+            (3 * 60, SessionOutcome::Skipped),
+            (0, SessionOutcome::Skipped),
+            (5 * 60, SessionOutcome::Completed),
+        ] {
+            records.push(SessionRecord {
+                kind: SessionKind::ShortBreak,
+                planned: 5 * 60,
+                elapsed,
+                outcome,
+                ..work(10, None)
+            });
+        }
+        let html = render_html(&records, date(1), date(14), None);
+        assert!(html.contains("<strong>50%</strong>break compliance"));
+        assert!(html.contains("2 taken until their end, 1 cut short, 1 skipped."));
+    }
+
+    #[test]
+    fn tells_the_reasons_of_the_pauses() {
+        let html = render_html(&[work(10, None)], date(1), date(14), None);
+        assert!(html.contains("No pause during the work sessions."));
+        let records = [
+            SessionRecord {
+                pauses: 4,
+                pause_reasons: vec![PauseReason::Call, PauseReason::Bio, PauseReason::Call],
+                ..work(10, None)
+            },
+            SessionRecord {
+                pauses: 2,
+                ..work(11, None)
+            },
+        ];
+        let html = render_html(&records, date(1), date(14), None);
+        assert!(html.contains("6 pauses: 2 calls, 1 bio break, 3 without a reason."));
+        let html = render_html(&records[1..], date(1), date(14), None);
+        assert!(html.contains("2 pauses."));
+    }
+
+    #[test]
+    fn rates_the_intents_per_task() {
+        let html = render_html(&[work(10, None)], date(1), date(14), None);
+        assert!(html.contains("No work session reviewed in this period."));
+        let reviewed = |task, review| SessionRecord {
+            intent: Some("Draft the outline".to_string()),
+            review: Some(review),
+            ..work(10, Some(task))
+        };
+        let records = [
+            reviewed("Report", Review::Done),
+            reviewed("Report", Review::Partial),
+            reviewed("Report", Review::Partial),
+        ];
+        let html = render_html(&records, date(1), date(14), None);
+        assert!(html.contains("<td>67%: 1 done, 2 partial</td>"));
+    }
+
+    #[test]
+    fn escapes_task_names() {
+        let html = render_html(
+            &[work(10, Some("<b>{{tasks}}</b>"))],
+            date(1),
+            date(14),
+            None,
+        );
+        assert!(html.contains("&lt;b&gt;&#123;&#123;tasks}}&lt;/b&gt;"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn tells_the_commits_made_during_the_work_sessions() {
+        let commit = |day, minutes, subject: &str| Commit {
+            repo: "app".to_string(),
+            hash: "1a2b3c4".to_string(),
+            time: work(day, None).started_at + chrono::TimeDelta::minutes(minutes),
+            subject: subject.to_string(),
+        };
+        let commits = [
+            commit(10, 10, "Fix <the> parser"),
+            commit(10, 20, "Split the lexer"),
+            commit(10, 40, "Lunch"),
+        ];
+        let records = [work(10, Some("Parser")), work(12, None)];
+        let html = render_html(&records, date(1), date(14), Some(&commits));
+        assert!(html.contains("2 commits during 1 of the 2 work sessions."));
+        assert!(html.contains("<td>Parser</td>"));
+        assert!(html.contains("Fix &lt;the&gt; parser<br>"));
+        assert!(!html.contains("Lunch"));
+        let html = render_html(&records, date(1), date(14), None);
+        assert!(!html.contains("Commits"));
+    }
+
+    #[test]
+    fn reports_an_empty_period() {
+        let html = render_html(&[], date(1), date(7), None);
+        assert!(html.contains("No work session in this period."));
+        assert!(html.contains("<strong>0</strong>pomodoros"));
+    }
+}
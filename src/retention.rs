@@ -0,0 +1,294 @@
+// filepath: src/retention.rs
+//! Module keeping the session history of a Pomodoro timer application from growing for years:
+//! the sessions older than an age are pruned, by `rustodoro history prune` or on each run
+//! following the `[retention]` table, and the history file can be compacted.
+use std::{fmt, fs, str::FromStr};
+
+use chrono::{Days, Local, Months, NaiveDate};
+
+use crate::{
+    config::{self, Cli, ConfigError, HistoryAction},
+    crypt::{CryptError, Encryption},
+    doctor, edit,
+    history::{HISTORY_ENV, History, HistoryError, history_path},
+    keychain, query,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RetentionError {
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Crypt(#[from] CryptError),
+    #[error("cannot locate the history file, use --history <path> or {HISTORY_ENV}")]
+    NoHistory,
+    #[error("no age to prune the sessions at, use --older-than or keep in the [retention] table")]
+    NoAge,
+    #[error("no session {0} in the history, see `rustodoro history list` for the IDs")]
+    NoSession(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+/// How old sessions get before being pruned, written e.g. `30d`, `8w`, `6m` or `1y`.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct Age {
+    amount: u32,
+    unit: Unit,
+}
+
+impl Age {
+    /// The first day kept on `today`.
+    pub fn cutoff(&self, today: NaiveDate) -> NaiveDate {
+        let cutoff = match self.unit {
+            Unit::Days => today.checked_sub_days(Days::new(u64::from(self.amount))),
+            Unit::Weeks => today.checked_sub_days(Days::new(7 * u64::from(self.amount))),
+            Unit::Months => today.checked_sub_months(Months::new(self.amount)),
+            Unit::Years => today.checked_sub_months(Months::new(12 * self.amount)),
+        };
+        cutoff.unwrap_or(NaiveDate::MIN)
+    }
+}
+
+impl FromStr for Age {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("must be an age such as 30d, 8w, 6m or 1y, got `{}`", s);
+        let trimmed = s.trim();
+        let Some((last, _)) = trimmed.char_indices().last() else {
+            return Err(invalid());
+        };
+        let (amount, unit) = trimmed.split_at(last);
+        let unit = match unit {
+            "d" => Unit::Days,
+            "w" => Unit::Weeks,
+            "m" => Unit::Months,
+            "y" => Unit::Years,
+            _ => return Err(invalid()),
+        };
+        match amount.parse() {
+            Ok(amount) if amount > 0 => Ok(Age { amount, unit }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Age {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit {
+            Unit::Days => 'd',
+            Unit::Weeks => 'w',
+            Unit::Months => 'm',
+            Unit::Years => 'y',
+        };
+        write!(f, "{}{}", self.amount, unit)
+    }
+}
+
+/// The sessions pruned from the history, and those kept.
+#[derive(Debug, PartialEq)]
+pub struct Pruned {
+    pub removed: usize,
+    pub kept: usize,
+}
+
+/// Removes the sessions started before `cutoff` from `history`, which is only written again when
+/// some are.
+pub fn prune(history: &History, cutoff: NaiveDate) -> Result<Pruned, HistoryError> {
+    let (kept, removed): (Vec<_>, Vec<_>) = history
+        .load()?
+        .into_iter()
+        .partition(|record| record.started_at.date_naive() >= cutoff);
+    if !removed.is_empty() {
+        history.rewrite(&kept)?;
+    }
+    Ok(Pruned {
+        removed: removed.len(),
+        kept: kept.len(),
+    })
+}
+
+/// Runs the `history` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, action: &HistoryAction) -> i32 {
+    match run_action(cli, action) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn run_action(cli: &Cli, action: &HistoryAction) -> Result<(), RetentionError> {
+    let path = history_path(cli.history.as_deref(), cli.name.as_deref())
+        .ok_or(RetentionError::NoHistory)?;
+    let history = History::new(path.clone());
+    match action {
+        HistoryAction::List(args) => {
+            print!(
+                "{}",
+                query::render(&history.load()?, args, Local::now().date_naive())
+            );
+        }
+        HistoryAction::Edit(args) => {
+            let edited = edit::run(&history, args, Local::now())?
+                .ok_or_else(|| RetentionError::NoSession(args.id.clone()))?;
+            print!("{}", edited);
+        }
+        HistoryAction::Prune { older_than } => {
+            let age = match older_than {
+                Some(age) => *age,
+                None => config::resolve(cli)?
+                    .retention
+                    .value
+                    .map(|retention| retention.keep)
+                    .ok_or(RetentionError::NoAge)?,
+            };
+            let cutoff = age.cutoff(Local::now().date_naive());
+            let pruned = prune(&history, cutoff)?;
+            println!(
+                "Removed {} sessions started before {}, {} kept.",
+                pruned.removed, cutoff, pruned.kept
+            );
+        }
+        HistoryAction::Doctor { repair } => doctor::run(&history, *repair)?,
+        HistoryAction::Compact => {
+            let size = || fs::metadata(&path).map_or(0, |metadata| metadata.len());
+            let before = size();
+            history.rewrite(&history.load()?)?;
+            println!("History compacted from {} to {} bytes.", before, size());
+        }
+        HistoryAction::Encrypt { keychain } => {
+            // The records are read with the previous key, before asking for the new one.
+            let records = history.load()?;
+            let previous = history.encryption()?;
+            let encryption = if *keychain {
+                Encryption::in_keychain(&keychain::Os)?
+            } else {
+                Encryption::with_passphrase()?
+            };
+            history.rewrite_with(&records, Some(&encryption))?;
+            if let Some(previous) = previous {
+                previous.forget(&keychain::Os)?;
+            }
+            println!(
+                "History encrypted, {} sessions, with {}.",
+                records.len(),
+                match encryption {
+                    Encryption::Passphrase { .. } => "a passphrase",
+                    Encryption::Keychain { .. } => "a key in the keychain",
+                }
+            );
+        }
+        HistoryAction::Decrypt => {
+            let Some(previous) = history.encryption()? else {
+                println!("The history is not encrypted.");
+                return Ok(());
+            };
+            history.rewrite_with(&history.load()?, None)?;
+            previous.forget(&keychain::Os)?;
+            println!("History decrypted.");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        events::SessionOutcome,
+        history::{SessionKind, SessionRecord},
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn work(day: NaiveDate) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local
+                .from_local_datetime(&day.and_hms_opt(9, 0, 0).unwrap())
+                .unwrap(),
+            kind: SessionKind::Work,
+            planned: 25 * 60,
+            elapsed: 25 * 60,
+            outcome: SessionOutcome::Completed,
+            pauses: 0,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: None,
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reads_the_ages() {
+        let today = date(2026, 3, 31);
+        let cutoff = |age: &str| age.parse::<Age>().unwrap().cutoff(today);
+        assert_eq!(cutoff("30d"), date(2026, 3, 1));
+        assert_eq!(cutoff("2w"), date(2026, 3, 17));
+        assert_eq!(cutoff(" 1m "), date(2026, 2, 28));
+        assert_eq!(cutoff("1y"), date(2025, 3, 31));
+        assert_eq!("18m".parse::<Age>().unwrap().to_string(), "18m");
+        for invalid in ["", "y", "0d", "1h", "-1y", "1.5y", "1é"] {
+            assert!(invalid.parse::<Age>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn prunes_the_old_sessions() {
+        let path = env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join("prune.jsonl");
+        let _ = fs::remove_file(&path);
+        let history = History::new(path.clone());
+        for day in [date(2024, 6, 1), date(2025, 3, 31), date(2026, 3, 1)] {
+            history.append(&work(day)).unwrap();
+        }
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "\n\n").unwrap();
+
+        let cutoff = date(2025, 3, 31);
+        assert_eq!(
+            prune(&history, cutoff).unwrap(),
+            Pruned {
+                removed: 1,
+                kept: 2
+            }
+        );
+        let records = history.load().unwrap();
+        assert_eq!(records, [work(date(2025, 3, 31)), work(date(2026, 3, 1))]);
+        // The blank lines went away with the pruned session, the version coming first.
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        assert_eq!(prune(&history, cutoff).unwrap().removed, 0);
+    }
+}
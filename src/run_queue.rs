@@ -0,0 +1,309 @@
+// filepath: src/run_queue.rs
+//! Module chaining the runs of a Pomodoro timer application: `rustodoro queue add --work 50
+//! --cycles 2` queues a run with its own schedule, e.g. deep work in the morning then admin blocks
+//! in the afternoon, and `rustodoro queue run` runs the queued runs back-to-back. A run leaves the
+//! queue once its sets are worked through, one quitting early staying first for the next time.
+use std::{fmt, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{self, Cli, ConfigError, QueueAction, RunArgs},
+    history,
+    scheduler::RunSummary,
+    types::AppError,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RunQueueError {
+    #[error("cannot access the run queue {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("invalid run queue {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("cannot locate the run queue, set $XDG_DATA_HOME or $HOME")]
+    NoQueue,
+    #[error("there is no run {0} in the queue, see `rustodoro queue list`")]
+    NoRun(usize),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+/// A run waiting in the queue, the values it leaves out coming from the configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedRun {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub work: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_break: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_break: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u64>,
+    /// Sets of cycles after which the run ends.
+    pub sets: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+}
+
+impl QueuedRun {
+    /// The run given by the options of `rustodoro queue add`, a set long unless --sets is given.
+    pub fn from_args(args: &RunArgs) -> Self {
+        QueuedRun {
+            work: args.work_duration,
+            short_break: args.short_break,
+            long_break: args.long_break,
+            cycles: args.cycles,
+            sets: args.sets.unwrap_or(1),
+            task: args.task.clone(),
+        }
+    }
+
+    /// `args` with the values of the run over them.
+    pub fn apply(&self, args: &RunArgs) -> RunArgs {
+        RunArgs {
+            work_duration: self.work.or(args.work_duration),
+            short_break: self.short_break.or(args.short_break),
+            long_break: self.long_break.or(args.long_break),
+            cycles: self.cycles.or(args.cycles),
+            sets: Some(self.sets),
+            task: self.task.clone().or_else(|| args.task.clone()),
+            ..args.clone()
+        }
+    }
+}
+
+impl fmt::Display for QueuedRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minutes = |name: &str, minutes: Option<u64>| {
+            minutes.map(|minutes| format!("{} {} min", name, minutes))
+        };
+        let values: Vec<String> = [
+            minutes("work", self.work),
+            minutes("short break", self.short_break),
+            minutes("long break", self.long_break),
+            self.cycles.map(|cycles| format!("{} cycles", cycles)),
+            Some(match self.sets {
+                1 => "1 set".to_string(),
+                sets => format!("{} sets", sets),
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        write!(f, "{}", values.join(", "))?;
+        if let Some(task) = &self.task {
+            write!(f, ": {}", task)?;
+        }
+        Ok(())
+    }
+}
+
+/// The file of the queued runs, one JSON record per line, the next run first.
+pub struct RunQueue {
+    path: PathBuf,
+}
+
+/// Returns the location of the run queue, next to the history.
+pub fn default_run_queue_path() -> Option<PathBuf> {
+    history::default_history_path(None).map(|path| path.with_file_name("runs.jsonl"))
+}
+
+impl RunQueue {
+    pub fn new(path: PathBuf) -> Self {
+        RunQueue { path }
+    }
+
+    fn io_error(&self, source: io::Error) -> RunQueueError {
+        RunQueueError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// The queued runs, a missing file meaning there is none.
+    pub fn load(&self) -> Result<Vec<QueuedRun>, RunQueueError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.io_error(e)),
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|source| RunQueueError::Parse {
+                    path: self.path.clone(),
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces the queued runs with `runs`, removing the file when there is none.
+    pub fn save(&self, runs: &[QueuedRun]) -> Result<(), RunQueueError> {
+        if runs.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(self.io_error(e)),
+                _ => Ok(()),
+            };
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| self.io_error(e))?;
+        }
+        let content: String = runs
+            .iter()
+            .map(|run| serde_json::to_string(run).expect("runs always serialize"))
+            .map(|line| line + "\n")
+            .collect();
+        let temporary = self.path.with_extension("jsonl.tmp");
+        fs::write(&temporary, content)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|e| self.io_error(e))
+    }
+}
+
+/// Runs the queued runs one after the other with `run`, each on the options of `cli` with its own
+/// values over them. A run quitting early stops the queue and stays first in it.
+pub fn run_all(
+    cli: &Cli,
+    queue: &RunQueue,
+    mut run: impl FnMut(&Cli) -> Result<RunSummary, AppError>,
+) -> Result<RunSummary, AppError> {
+    let mut total = RunSummary::default();
+    let mut first = true;
+    loop {
+        let mut runs = queue.load()?;
+        let Some(next) = runs.first().cloned() else {
+            if first {
+                println!("No run is queued, add one with `rustodoro queue add`.");
+            }
+            return Ok(total);
+        };
+        println!("Run 1 of {} in the queue: {}", runs.len(), next);
+        let mut args = next.apply(&cli.run);
+        // The setup is offered once, before the first run.
+        args.no_wizard = cli.run.no_wizard || !first;
+        first = false;
+        let summary = run(&Cli {
+            command: None,
+            config: cli.config.clone(),
+            history: cli.history.clone(),
+            socket: cli.socket.clone(),
+            name: cli.name.clone(),
+            run: args,
+        })?;
+        total.work_cycles += summary.work_cycles;
+        total.work_minutes += summary.work_minutes;
+        total.sets += summary.sets;
+        if summary.sets < next.sets {
+            if runs.len() > 1 {
+                println!(
+                    "The run was left before its end, {} runs stay queued.",
+                    runs.len()
+                );
+            }
+            return Ok(total);
+        }
+        // Loaded again, as runs may have been queued meanwhile.
+        runs = queue.load()?;
+        if runs.first() == Some(&next) {
+            runs.remove(0);
+        }
+        queue.save(&runs)?;
+    }
+}
+
+fn run_action(cli: &Cli, action: &QueueAction) -> Result<(), RunQueueError> {
+    let queue = RunQueue::new(default_run_queue_path().ok_or(RunQueueError::NoQueue)?);
+    let mut runs = queue.load()?;
+    match action {
+        QueueAction::Add => {
+            // Checked now rather than when the run comes.
+            config::resolve(cli)?;
+            let run = QueuedRun::from_args(&cli.run);
+            println!("Queued run {}: {}", runs.len() + 1, run);
+            runs.push(run);
+        }
+        QueueAction::List => {
+            if runs.is_empty() {
+                println!("No run is queued.");
+            }
+            for (index, run) in runs.iter().enumerate() {
+                println!("{}. {}", index + 1, run);
+            }
+            return Ok(());
+        }
+        QueueAction::Remove { index } => {
+            let run = index
+                .checked_sub(1)
+                .filter(|index| *index < runs.len())
+                .map(|index| runs.remove(index))
+                .ok_or(RunQueueError::NoRun(*index))?;
+            println!("Removed run {}: {}", index, run);
+        }
+        QueueAction::Status | QueueAction::Flush | QueueAction::Run => {
+            unreachable!("the notifications and the runs are handled elsewhere")
+        }
+    }
+    queue.save(&runs)
+}
+
+/// Runs the `queue add`, `queue list` and `queue remove` subcommands and returns the process exit
+/// code.
+pub fn run_command(cli: &Cli, action: &QueueAction) -> i32 {
+    match run_action(cli, action) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn queued(args: &[&str]) -> QueuedRun {
+        let cli = Cli::parse_from(["rustodoro", "queue", "add"].iter().chain(args));
+        QueuedRun::from_args(&cli.run)
+    }
+
+    #[test]
+    fn runs_the_queue_back_to_back() {
+        let path = std::env::temp_dir()
+            .join(format!("rustodoro-test-{}", std::process::id()))
+            .join("runs.jsonl");
+        let _ = fs::remove_file(&path);
+        let queue = RunQueue::new(path);
+        let deep = queued(&["--work", "50", "--cycles", "2", "--task", "Deep work"]);
+        let admin = queued(&["--work", "15", "--sets", "2"]);
+        assert_eq!(deep.to_string(), "work 50 min, 2 cycles, 1 set: Deep work");
+        assert_eq!(admin.to_string(), "work 15 min, 2 sets");
+        queue.save(&[deep.clone(), admin.clone()]).unwrap();
+
+        let cli = Cli::parse_from(["rustodoro", "queue", "run", "--short-break", "3"]);
+        let mut started = Vec::new();
+        let total = run_all(&cli, &queue, |cli| {
+            started.push((cli.run.work_duration, cli.run.short_break, cli.run.sets));
+            // The second run is quit in its second set.
+            Ok(RunSummary {
+                work_cycles: 2,
+                work_minutes: 2 * cli.run.work_duration.unwrap(),
+                sets: 1,
+            })
+        })
+        .unwrap();
+        assert_eq!(
+            started,
+            [(Some(50), Some(3), Some(1)), (Some(15), Some(3), Some(2))]
+        );
+        assert_eq!(total.work_minutes, 130);
+        assert_eq!(queue.load().unwrap(), [admin]);
+    }
+}
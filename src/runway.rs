@@ -0,0 +1,145 @@
+// filepath: src/runway.rs
+//! Module keeping the work sessions of a Pomodoro timer application clear of what comes next: a
+//! work session which would run into the next event of the calendar, or past the deadline given,
+//! is shortened to end before it, and not started at all when less than the minimum runway is
+//! left.
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+
+#[cfg(any(feature = "caldav", feature = "gcal"))]
+use crate::calendar::Calendar;
+use crate::{pace::TimeOfDay, settings::Span};
+
+/// What tells the next event the user is busy with, e.g. a calendar.
+pub trait Agenda: Send {
+    /// The title and the start of the first event starting between `from` and `to`, if any.
+    fn next(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<(String, DateTime<Utc>)>;
+}
+
+/// A calendar the server of which cannot be reached is taken as free.
+#[cfg(any(feature = "caldav", feature = "gcal"))]
+impl<C: Calendar + Send> Agenda for C {
+    fn next(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<(String, DateTime<Utc>)> {
+        self.busy(from, to)
+            .ok()?
+            .into_iter()
+            // The events already running are the user's to leave.
+            .filter(|busy| busy.start >= from)
+            .min_by_key(|busy| busy.start)
+            .map(|busy| (busy.title, busy.start))
+    }
+}
+
+/// How a work session fits before what comes next.
+#[derive(Debug, PartialEq)]
+pub enum Fit {
+    /// Nothing comes before its end.
+    Full,
+    /// It is shortened to `minutes` to end before `before`.
+    Shortened { minutes: u64, before: String },
+    /// Only `left` is left before `before`, less than the minimum runway.
+    Refused { left: Duration, before: String },
+}
+
+pub struct Runway {
+    /// Shortest work session worth starting.
+    min: Span,
+    deadline: Option<TimeOfDay>,
+    agenda: Option<Box<dyn Agenda>>,
+}
+
+impl Runway {
+    pub fn new(min: Span, deadline: Option<TimeOfDay>, agenda: Option<Box<dyn Agenda>>) -> Self {
+        Runway {
+            min,
+            deadline,
+            agenda,
+        }
+    }
+
+    /// How a work session of `minutes` minutes, each lasting `minute`, fits when started at `now`.
+    pub fn fit(&self, minutes: u64, minute: Duration, now: DateTime<Local>) -> Fit {
+        let end = now + minute * minutes as u32;
+        let deadline = self
+            .deadline
+            .and_then(|deadline| now.with_time(deadline.0).single())
+            .map(|deadline| {
+                (
+                    format!("the deadline at {}", deadline.format("%H:%M")),
+                    deadline,
+                )
+            });
+        let event = self
+            .agenda
+            .as_ref()
+            .and_then(|agenda| agenda.next(now.to_utc(), end.to_utc()))
+            .map(|(title, start)| {
+                let start = start.with_timezone(&Local);
+                (format!("{} at {}", title, start.format("%H:%M")), start)
+            });
+        let Some((before, at)) = deadline
+            .into_iter()
+            .chain(event)
+            .filter(|(_, at)| *at < end)
+            .min_by_key(|(_, at)| *at)
+        else {
+            return Fit::Full;
+        };
+        let left = (at - now).to_std().unwrap_or_default();
+        let minutes = left.as_secs() / minute.as_secs().max(1);
+        if minutes == 0 || left < Duration::from_secs(self.min.seconds) {
+            Fit::Refused { left, before }
+        } else {
+            Fit::Shortened { minutes, before }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone};
+
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    /// A meeting starting at the given time.
+    struct Meeting(DateTime<Utc>);
+
+    impl Agenda for Meeting {
+        fn next(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<(String, DateTime<Utc>)> {
+            (from..to)
+                .contains(&self.0)
+                .then(|| ("Standup".to_string(), self.0))
+        }
+    }
+
+    #[test]
+    fn fits_the_work_sessions_before_what_comes_next() {
+        let now = Local.with_ymd_and_hms(2026, 3, 2, 9, 40, 0).unwrap();
+        let standup = (now + TimeDelta::minutes(18)).to_utc();
+        let runway = Runway::new(
+            Span { seconds: 10 * 60 },
+            Some(TimeOfDay::new(17, 0)),
+            Some(Box::new(Meeting(standup))),
+        );
+        assert_eq!(
+            runway.fit(25, MINUTE, now),
+            Fit::Shortened {
+                minutes: 18,
+                before: "Standup at 09:58".to_string()
+            }
+        );
+        assert_eq!(runway.fit(15, MINUTE, now), Fit::Full);
+
+        let late = Local.with_ymd_and_hms(2026, 3, 2, 16, 52, 0).unwrap();
+        assert_eq!(
+            runway.fit(25, MINUTE, late),
+            Fit::Refused {
+                left: MINUTE * 8,
+                before: "the deadline at 17:00".to_string()
+            }
+        );
+    }
+}
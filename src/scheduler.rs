@@ -0,0 +1,746 @@
+// filepath: src/scheduler.rs
+//! Module chaining the work sessions and breaks of a Pomodoro timer application.
+use std::sync::Arc;
+
+use chrono::Local;
+use rustodoro_client::protocol::SessionKind;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{
+    AppError, Command, SessionType,
+    alerts::Alerts,
+    capture::AutoPause,
+    command_queue::CommandQueue,
+    events::{EventBus, SessionOutcome},
+    idle::AutoFinish,
+    output, plan,
+    quiet::Quiet,
+    runway::{Fit, Runway},
+    scripting::{Decision, Schedule, ScriptError, State},
+    session_timer::SessionTimer,
+    settings::{Settings, SharedSettings},
+    today::{SharedToday, Today},
+    types::SessionIndex,
+};
+
+/// What the user achieved during a run.
+#[derive(Debug, Default, PartialEq)]
+pub struct RunSummary {
+    pub work_cycles: u64,
+    pub work_minutes: u64,
+    /// Sets of cycles worked through, up to the last work session before the long break.
+    pub sets: u64,
+}
+
+pub struct Scheduler {
+    commands: CommandQueue,
+    events: Arc<EventBus>,
+    settings: SharedSettings,
+    alerts: Alerts,
+    schedule: Option<Schedule>,
+    /// Draws the jitter of the breaks.
+    rng: fastrand::Rng,
+    /// Pomodoros completed today, before this run included.
+    today: SharedToday,
+    auto_finish: Option<Arc<AutoFinish>>,
+    auto_pause: Option<Arc<AutoPause>>,
+    runway: Option<Runway>,
+    /// Sets of cycles after which the run ends, if any.
+    sets: Option<u64>,
+}
+
+impl Scheduler {
+    pub fn new(
+        rx: UnboundedReceiver<Command>,
+        events: Arc<EventBus>,
+        settings: SharedSettings,
+    ) -> Result<Self, AppError> {
+        let alerts = Alerts::new(&settings.lock().unwrap())?;
+        Ok(Scheduler {
+            commands: CommandQueue::new(rx),
+            events,
+            settings,
+            alerts,
+            schedule: None,
+            rng: fastrand::Rng::new(),
+            today: Today::empty().into_shared(),
+            auto_finish: None,
+            auto_pause: None,
+            runway: None,
+            sets: None,
+        })
+    }
+
+    /// Lets `schedule` decide each session.
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Counts the pomodoros of the day in `today`, along with those completed before this run.
+    pub fn with_today(mut self, today: SharedToday) -> Self {
+        self.today = today;
+        self
+    }
+
+    /// Finishes the work sessions early whenever `auto_finish` finds the user left near their end.
+    pub fn with_auto_finish(mut self, auto_finish: Arc<AutoFinish>) -> Self {
+        self.auto_finish = Some(auto_finish);
+        self
+    }
+
+    /// Pauses the work sessions whenever `auto_pause` finds a call going on.
+    pub fn with_auto_pause(mut self, auto_pause: Arc<AutoPause>) -> Self {
+        self.auto_pause = Some(auto_pause);
+        self
+    }
+
+    /// Fits the work sessions before what `runway` tells comes next.
+    pub fn with_runway(mut self, runway: Runway) -> Self {
+        self.runway = Some(runway);
+        self
+    }
+
+    /// Ends the run once `sets` sets of cycles are worked through, after the long break.
+    pub fn with_sets(mut self, sets: u64) -> Self {
+        self.sets = Some(sets);
+        self
+    }
+
+    /// Holds the sounds and notifications back whenever `quiet` says so.
+    pub fn with_quiet(mut self, quiet: Arc<Quiet>) -> Self {
+        self.alerts.set_quiet(quiet);
+        self
+    }
+
+    /// The first failure of the schedule script.
+    pub fn schedule_failure(&mut self) -> Option<ScriptError> {
+        self.schedule.as_mut()?.take_failure()
+    }
+
+    /// The session to run instead of `next`, as decided by the schedule script if any.
+    async fn decide(
+        &mut self,
+        next: Decision,
+        cycle: u64,
+        settings: &Settings,
+        summary: &RunSummary,
+    ) -> Decision {
+        let Some(mut schedule) = self.schedule.take() else {
+            return next;
+        };
+        let state = State {
+            next,
+            cycle,
+            cycles: settings.cycles_per_set(),
+            pomodoros: summary.work_cycles,
+            work_minutes: summary.work_minutes,
+            work: settings.work_duration,
+            short_break: settings.short_break,
+            long_break: settings.long_break,
+        };
+        // The script runs off the timer, which has nothing to do between two sessions.
+        let (schedule, decision) = tokio::task::spawn_blocking(move || {
+            let decision = schedule.decide(&state);
+            (schedule, decision)
+        })
+        .await
+        .expect("the schedule script is run on its own thread");
+        self.schedule = Some(schedule);
+        decision
+    }
+
+    /// How a work session of `minutes` minutes starting now fits before what comes next.
+    async fn fit(&mut self, minutes: u64, settings: &Settings) -> Fit {
+        let Some(runway) = self.runway.take() else {
+            return Fit::Full;
+        };
+        let minute = settings.minute;
+        // The calendar is asked off the timer, as the script is.
+        let (runway, fit) = tokio::task::spawn_blocking(move || {
+            let fit = runway.fit(minutes, minute, Local::now());
+            (runway, fit)
+        })
+        .await
+        .expect("the runway is measured on its own thread");
+        self.runway = Some(runway);
+        fit
+    }
+
+    /// Runs work sessions and breaks one after the other, until the user quits, the sets are
+    /// worked through or too little time is left for another work session. Once the scheduler is
+    /// dropped, the commands are closed, which stops the command dispatcher.
+    pub async fn run(&mut self) -> Result<RunSummary, AppError> {
+        let mut summary = RunSummary::default();
+
+        loop {
+            // Work sessions started in this set, each break taking the cycle of the work before.
+            let mut current_cycle = 0;
+            let mut step = 0;
+            let mut skip_breaks: u32 = 0;
+            // Settings are read again before each session so changes from the settings menu apply
+            // from the next session on.
+            loop {
+                let current = self.settings.lock().unwrap().clone();
+                let sequence: Vec<SessionType> = current
+                    .sequence()
+                    .iter()
+                    .map(|step| current.session_type(step))
+                    .collect();
+                let Some(session) = sequence.get(step).cloned() else {
+                    break;
+                };
+                step += 1;
+
+                if session.is_work() {
+                    current_cycle += 1;
+                    let planned = Decision {
+                        session: SessionKind::Work,
+                        minutes: session
+                            .minutes
+                            .unwrap_or_else(|| current.work_minutes(current_cycle)),
+                    };
+                    let mut work = self
+                        .decide(planned, current_cycle, &current, &summary)
+                        .await;
+                    match self.fit(work.minutes, &current).await {
+                        Fit::Full => {}
+                        Fit::Shortened { minutes, before } => {
+                            output::line(format!(
+                                "note: the work session is shortened to {} min to end before {}",
+                                minutes, before
+                            ));
+                            work.minutes = minutes;
+                        }
+                        Fit::Refused { left, before } => {
+                            output::line(format!(
+                                "Not starting another work session: {} min left before {}, less than the minimum runway",
+                                left.as_secs() / 60,
+                                before
+                            ));
+                            return Ok(summary);
+                        }
+                    }
+                    let mut session_timer = SessionTimer::new(
+                        Arc::clone(&self.events),
+                        current.minute * work.minutes as u32,
+                        session,
+                        current_cycle,
+                        &current,
+                    );
+                    let started = Local::now().date_naive();
+                    {
+                        let mut today = self.today.lock().unwrap();
+                        let done = today.on(started);
+                        if done + 1 == current.daily_goal {
+                            session_timer.reaches_goal();
+                        }
+                        session_timer.plans(plan::time_ahead(
+                            &current,
+                            &sequence[step - 1],
+                            step,
+                            current_cycle,
+                            done,
+                        ));
+                        session_timer.shows_plant(today.plant(started));
+                    }
+                    if let Some(auto_finish) = &self.auto_finish {
+                        session_timer.auto_finish(Arc::clone(auto_finish));
+                    }
+                    if let Some(auto_pause) = &self.auto_pause {
+                        session_timer.auto_pause(Arc::clone(auto_pause));
+                    }
+
+                    match self.run_session(&mut session_timer).await {
+                        // Left for another session of the set.
+                        Ok(SessionOutcome::Skipped) => {}
+                        Ok(_) => {
+                            self.today.lock().unwrap().count(started);
+                            summary.work_cycles += 1;
+                            summary.work_minutes += work.minutes;
+                            if !sequence[step..].iter().any(SessionType::is_work) {
+                                summary.sets += 1;
+                            }
+                        }
+                        Err(e) if e.is_quit() => return Ok(summary),
+                        Err(e) => return Err(e),
+                    }
+                    match self.alarm(&mut session_timer).await {
+                        Ok(()) => {}
+                        Err(e) if e.is_quit() => return Ok(summary),
+                        Err(e) => return Err(e),
+                    }
+                    self.keep_task(&session_timer);
+                    if let Some(index) = session_timer.jumped_to() {
+                        (step, current_cycle) = jump(&sequence, index);
+                        continue;
+                    }
+                    // Skipping applies to the breaks right after, if any.
+                    skip_breaks = skip_breaks.max(session_timer.breaks_skipped());
+                    continue;
+                }
+
+                let planned = Decision {
+                    session: session.kind,
+                    minutes: session.minutes.unwrap_or(match session.kind {
+                        SessionKind::LongBreak => current.long_break,
+                        _ => current.short_break,
+                    }),
+                };
+                let cycle = current_cycle.max(1);
+                let decided = self.decide(planned, cycle, &current, &summary).await;
+                let jitter = current.break_jitter;
+                let offset = self
+                    .rng
+                    .i64(-(jitter.seconds as i64)..=jitter.seconds as i64);
+                let break_duration = jitter.apply(current.minute * decided.minutes as u32, offset);
+                // A schedule script turning the break into another one turns it into a built-in one.
+                let break_type = if decided.session == session.kind {
+                    session
+                } else {
+                    SessionType::from(decided.session)
+                };
+
+                let mut session_timer = SessionTimer::new(
+                    Arc::clone(&self.events),
+                    break_duration,
+                    break_type,
+                    cycle,
+                    &current,
+                );
+                if skip_breaks > 0 {
+                    skip_breaks -= 1;
+                    session_timer.skip();
+                }
+                let done = self.today.lock().unwrap().on(Local::now().date_naive());
+                session_timer.plans(plan::time_ahead(
+                    &current,
+                    &sequence[step - 1],
+                    step,
+                    current_cycle,
+                    done,
+                ));
+
+                match self.run_session(&mut session_timer).await {
+                    Ok(_) => {}
+                    Err(e) if e.is_quit() => return Ok(summary),
+                    Err(e) => return Err(e),
+                }
+                match self.alarm(&mut session_timer).await {
+                    Ok(()) => {}
+                    Err(e) if e.is_quit() => return Ok(summary),
+                    Err(e) => return Err(e),
+                }
+                skip_breaks = skip_breaks.max(session_timer.breaks_skipped());
+                self.keep_task(&session_timer);
+                if let Some(index) = session_timer.jumped_to() {
+                    (step, current_cycle) = jump(&sequence, index);
+                }
+            }
+            if self.sets.is_some_and(|sets| summary.sets >= sets) {
+                return Ok(summary);
+            }
+        }
+    }
+
+    async fn run_session(
+        &mut self,
+        session_timer: &mut SessionTimer,
+    ) -> Result<SessionOutcome, AppError> {
+        // Commands sent before the session started were meant for the previous one.
+        self.commands.discard_pending();
+        session_timer
+            .run(&mut self.commands, &mut self.alerts)
+            .await
+    }
+
+    /// Labels the work sessions after `session_timer` with the task set during it, if any.
+    fn keep_task(&self, session_timer: &SessionTimer) {
+        if let Some(task) = session_timer.task() {
+            self.settings.lock().unwrap().task = Some(task.to_string());
+        }
+    }
+
+    /// Waits for the end of the session to be acknowledged, if required, before the next one.
+    async fn alarm(&mut self, session_timer: &mut SessionTimer) -> Result<(), AppError> {
+        session_timer
+            .alarm(&mut self.commands, &mut self.alerts)
+            .await
+    }
+}
+
+/// The step of `sequence` of the session at `index`, the last one past its end, and the work
+/// sessions started before it.
+fn jump(sequence: &[SessionType], index: SessionIndex) -> (usize, u64) {
+    let step = index.step().min(sequence.len() - 1);
+    let cycle = sequence[..step]
+        .iter()
+        .filter(|session| session.is_work())
+        .count();
+    (step, cycle as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::{
+        events::Event,
+        render::Color,
+        settings::{Ramp, SessionConfig, Settings, Step},
+        testing::Script,
+    };
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    async fn run(script: Script, settings: Settings) -> (RunSummary, Duration) {
+        let start = Instant::now();
+        let mut scheduler = Scheduler::new(
+            script.spawn(),
+            Arc::new(EventBus::new()),
+            settings.into_shared(),
+        )
+        .unwrap();
+        let summary = scheduler.run().await.unwrap();
+        (summary, start.elapsed())
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn counts_completed_work_sessions() {
+        // 4 × 25 min of work, 3 × 5 min short breaks, then 5 min into the long break.
+        let quit_at = MINUTE * (4 * 25 + 3 * 5 + 5);
+        let (summary, now) = run(Script::new().quit_at(quit_at), settings()).await;
+        assert_eq!(
+            summary,
+            RunSummary {
+                work_cycles: 4,
+                work_minutes: 100,
+                sets: 1,
+            }
+        );
+        assert_eq!(now, quit_at);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn interrupted_work_session_is_not_counted() {
+        let (summary, _) = run(Script::new().quit_at(MINUTE * 24), settings()).await;
+        assert_eq!(summary, RunSummary::default());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn long_break_comes_after_the_last_cycle() {
+        let settings = Settings {
+            cycles: 2,
+            ..settings()
+        };
+        // Work 0-25, short break 25-30, work 30-55, long break 55-70, work 70-95. With a short
+        // break instead, the third work session would be over at 85 min.
+        let (summary, _) = run(Script::new().quit_at(MINUTE * 90), settings).await;
+        assert_eq!((summary.work_cycles, summary.sets), (2, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_task_set_labels_the_next_sessions() {
+        let settings = settings().into_shared();
+        let events = Arc::new(EventBus::new());
+        let started = events.subscribe();
+        let mut scheduler = Scheduler::new(
+            Script::new()
+                .at(MINUTE * 5, Command::SetTask("Write the report".to_string()))
+                .quit_at(MINUTE * 31)
+                .spawn(),
+            Arc::clone(&events),
+            Arc::clone(&settings),
+        )
+        .unwrap();
+        scheduler.run().await.unwrap();
+        drop(scheduler);
+        drop(events);
+
+        assert_eq!(
+            settings.lock().unwrap().task.as_deref(),
+            Some("Write the report")
+        );
+        let changed = started
+            .into_iter()
+            .filter(|event| matches!(event, Event::TaskChanged { .. }))
+            .count();
+        assert_eq!(changed, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_sequence_replaces_the_cycles() {
+        let settings = Settings {
+            sequence: Some(vec![
+                Step::Work,
+                Step::Work,
+                Step::Short,
+                Step::Work,
+                Step::Long,
+            ]),
+            ..settings()
+        };
+        let events = Arc::new(EventBus::new());
+        let started = events.subscribe();
+        let mut scheduler = Scheduler::new(
+            Script::new()
+                .quit_at(MINUTE * (3 * 25 + 5 + 15 + 1))
+                .spawn(),
+            Arc::clone(&events),
+            settings.into_shared(),
+        )
+        .unwrap();
+        let summary = scheduler.run().await.unwrap();
+        drop(scheduler);
+        drop(events);
+
+        assert_eq!((summary.work_cycles, summary.sets), (3, 1));
+        let sessions: Vec<String> = started
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::SessionStarted { session, cycle, .. } => {
+                    Some(format!("{} {}", session, cycle))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sessions,
+            [
+                "Work session 1",
+                "Work session 2",
+                "Short break 2",
+                "Work session 3",
+                "Long break 3",
+                "Work session 1"
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_the_custom_sessions_of_a_sequence() {
+        let session = |label: &str, minutes, counts_as| SessionConfig {
+            label: label.to_string(),
+            minutes,
+            counts_as,
+            color: Color::Magenta,
+        };
+        let settings = Settings {
+            sequence: Some(vec![
+                Step::Work,
+                Step::Custom("review".to_string()),
+                Step::Custom("walk".to_string()),
+                Step::Long,
+            ]),
+            sessions: BTreeMap::from([
+                (
+                    "review".to_string(),
+                    session("Review", 10, SessionKind::Work),
+                ),
+                (
+                    "walk".to_string(),
+                    session("Walk", 7, SessionKind::ShortBreak),
+                ),
+            ]),
+            ..settings()
+        };
+        let events = Arc::new(EventBus::new());
+        let started = events.subscribe();
+        let mut scheduler = Scheduler::new(
+            Script::new()
+                .quit_at(MINUTE * (25 + 10 + 7 + 15 + 1))
+                .spawn(),
+            Arc::clone(&events),
+            settings.into_shared(),
+        )
+        .unwrap();
+        let summary = scheduler.run().await.unwrap();
+        drop(scheduler);
+        drop(events);
+
+        assert_eq!(
+            (summary.work_cycles, summary.work_minutes, summary.sets),
+            (2, 35, 1)
+        );
+        let sessions: Vec<String> = started
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::SessionStarted { session, cycle, .. } => {
+                    Some(format!("{} {}", session, cycle))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sessions,
+            [
+                "Work session 1",
+                "Review 2",
+                "Walk 2",
+                "Long break 2",
+                "Work session 1"
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jumps_to_another_session_of_the_set() {
+        let events = Arc::new(EventBus::new());
+        let started = events.subscribe();
+        // The first work session is left at 10 min for the last one of the set, 10-35, then the
+        // long break 35-50.
+        let script = Script::new()
+            .at(MINUTE * 10, Command::Jump(SessionIndex::new(7).unwrap()))
+            .quit_at(MINUTE * 55);
+        let mut scheduler = Scheduler::new(
+            script.spawn(),
+            Arc::clone(&events),
+            settings().into_shared(),
+        )
+        .unwrap();
+        let summary = scheduler.run().await.unwrap();
+        drop(scheduler);
+        drop(events);
+
+        assert_eq!((summary.work_cycles, summary.sets), (1, 1));
+        let sessions: Vec<String> = started
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::SessionStarted { session, cycle, .. } => {
+                    Some(format!("{} {}", session, cycle))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sessions,
+            [
+                "Work session 1",
+                "Work session 4",
+                "Long break 4",
+                "Work session 1"
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ramp_sets_the_work_duration_of_each_cycle() {
+        let settings = Settings {
+            cycles: 2,
+            ramp: Some(Ramp::List(vec![10, 20])),
+            ..settings()
+        };
+        // Work 0-10, short break 10-15, work 15-35, long break 35-50, then the ramp starts over
+        // with work 50-60.
+        let (summary, _) = run(Script::new().quit_at(MINUTE * 64), settings).await;
+        assert_eq!(summary.work_cycles, 3);
+        assert_eq!(summary.work_minutes, 40);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_ends_the_break_early() {
+        // The short break is skipped at 26 min, so the second work session ends at 51 min.
+        let script = Script::new()
+            .at(MINUTE * 26, Command::Skip)
+            .quit_at(MINUTE * 52);
+        let (summary, _) = run(script, settings()).await;
+        assert_eq!(summary.work_cycles, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_is_ignored_during_work() {
+        let script = Script::new()
+            .at(MINUTE * 10, Command::Skip)
+            .quit_at(MINUTE * 24);
+        let (summary, _) = run(script, settings()).await;
+        assert_eq!(summary.work_cycles, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_session_waits_for_the_acknowledgment() {
+        let settings = Settings {
+            require_ack: true,
+            ..settings()
+        };
+        // Work 0-25, acknowledged at 30, short break 30-35, acknowledged at 36 after skipping in
+        // vain, work 36-61. Without waiting, the second work session would be over at 55 min.
+        let script = |quit_at| {
+            Script::new()
+                .at(MINUTE * 26, Command::Skip)
+                .at(MINUTE * 30, Command::Acknowledge)
+                .at(MINUTE * 35 + MINUTE / 2, Command::Skip)
+                .at(MINUTE * 36, Command::Acknowledge)
+                .quit_at(quit_at)
+        };
+        let (summary, _) = run(script(MINUTE * 60), settings.clone()).await;
+        assert_eq!(summary.work_cycles, 1);
+        let (summary, _) = run(script(MINUTE * 62), settings).await;
+        assert_eq!(summary.work_cycles, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_forever_repeating_cycles() {
+        let settings = Settings {
+            cycles: 1,
+            ..settings()
+        };
+        // Each round is 25 min of work followed by the 15 min long break.
+        let (summary, _) = run(Script::new().quit_at(MINUTE * 40 * 3), settings).await;
+        assert_eq!(summary.work_cycles, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jitter_varies_the_breaks() {
+        let settings = Settings {
+            cycles: 2,
+            break_jitter: "2m".parse().unwrap(),
+            ..settings()
+        };
+        let events = Arc::new(EventBus::new());
+        let started = events.subscribe();
+        let mut scheduler = Scheduler::new(
+            Script::new().quit_at(MINUTE * 130).spawn(),
+            Arc::clone(&events),
+            settings.into_shared(),
+        )
+        .unwrap();
+        scheduler.rng = fastrand::Rng::with_seed(7);
+        scheduler.run().await.unwrap();
+        drop(scheduler);
+        drop(events);
+
+        let breaks: Vec<(SessionType, Duration)> = started
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::SessionStarted {
+                    session, duration, ..
+                } if !session.is_work() => Some((session, duration)),
+                _ => None,
+            })
+            .collect();
+        assert!(breaks.len() >= 3);
+        for (session, duration) in &breaks {
+            let planned = match session.kind {
+                SessionKind::LongBreak => MINUTE * 15,
+                _ => MINUTE * 5,
+            };
+            assert!(duration.abs_diff(planned) <= MINUTE * 2);
+        }
+        assert!(
+            breaks
+                .iter()
+                .any(|(_, duration)| duration.as_secs() % 60 != 0)
+        );
+    }
+}
@@ -0,0 +1,361 @@
+// filepath: src/scripting.rs
+//! Module letting a Lua script decide the sessions of a Pomodoro timer application, e.g. a
+//! 30-minute break after 6 pomodoros.
+//!
+//! Before each session, the script of the `[schedule]` table is run by the Lua interpreter with the
+//! state of the timer. Its `next_session(state)` function returns the session to run, or nothing to
+//! keep the one the timer planned:
+//!
+//! ```lua
+//! function next_session(state)
+//!   if state.next.session ~= "work" and state.pomodoros % 6 == 0 then
+//!     return { session = "long_break", minutes = 30 }
+//!   end
+//! end
+//! ```
+//!
+//! The state is versioned: fields are only ever added to it. A script that fails, hangs or returns
+//! an invalid decision leaves the planned session, and the first failure is reported when quitting.
+//!
+//! The interpreter is not built in: `lua`, or the one the table names, must be installed.
+use std::{
+    io::{self, Read},
+    path::Path,
+    process::{Command as Process, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{config::ScheduleConfig, settings::MAX_DURATION_MINUTES};
+
+/// Version of the state given to the script.
+pub const STATE_VERSION: u32 = 1;
+/// Longest a script can take to decide.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("cannot run the schedule script with {interpreter}: {source}")]
+    Spawn {
+        interpreter: String,
+        source: io::Error,
+    },
+    #[error("the schedule script failed{}", if .0.is_empty() { String::new() } else { format!(": {}", .0) })]
+    Failed(String),
+    #[error("the schedule script did not decide within {} seconds", TIMEOUT.as_secs())]
+    TimedOut,
+    #[error("the schedule script returned `{decision}`: {reason}")]
+    Invalid { decision: String, reason: String },
+}
+
+/// The state of the timer before a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    /// The session the timer planned.
+    pub next: Decision,
+    /// The cycle of the session, from 1 to `cycles`.
+    pub cycle: u64,
+    pub cycles: u64,
+    /// The work sessions completed since the timer started.
+    pub pomodoros: u64,
+    pub work_minutes: u64,
+    /// The durations of the settings, in minutes.
+    pub work: u64,
+    pub short_break: u64,
+    pub long_break: u64,
+}
+
+/// A session to run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub session: SessionKind,
+    pub minutes: u64,
+}
+
+fn name(kind: SessionKind) -> &'static str {
+    match kind {
+        SessionKind::Work => "work",
+        SessionKind::ShortBreak => "short_break",
+        SessionKind::LongBreak => "long_break",
+    }
+}
+
+impl State {
+    /// The state as a Lua table constructor.
+    fn to_lua(&self) -> String {
+        format!(
+            "{{ version = {}, next = {{ session = \"{}\", minutes = {} }}, cycle = {}, cycles = {}, \
+             pomodoros = {}, work_minutes = {}, durations = {{ work = {}, short_break = {}, \
+             long_break = {} }} }}",
+            STATE_VERSION,
+            name(self.next.session),
+            self.next.minutes,
+            self.cycle,
+            self.cycles,
+            self.pomodoros,
+            self.work_minutes,
+            self.work,
+            self.short_break,
+            self.long_break
+        )
+    }
+}
+
+/// A Lua string literal holding `text`.
+fn lua_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// The chunk running the script at `path` and writing its decision as `<session> <minutes>`.
+fn chunk(path: &Path, state: &State) -> String {
+    format!(
+        "local state = {}\n\
+         dofile({})\n\
+         local decision = next_session(state)\n\
+         if type(decision) == \"table\" then\n  \
+           io.write(tostring(decision.session or state.next.session), \" \", \
+           tostring(decision.minutes or state.next.minutes), \"\\n\")\n\
+         end\n",
+        state.to_lua(),
+        lua_string(&path.display().to_string())
+    )
+}
+
+/// The decision written by the script, the planned session when it wrote nothing. A work session
+/// cannot be turned into a break, nor a break into a work session.
+fn parse(output: &str, state: &State) -> Result<Decision, ScriptError> {
+    let output = output.trim();
+    if output.is_empty() {
+        return Ok(state.next);
+    }
+    let invalid = |reason: &str| ScriptError::Invalid {
+        decision: output.to_string(),
+        reason: reason.to_string(),
+    };
+    let (session, minutes) = output
+        .split_once(' ')
+        .ok_or_else(|| invalid("expected a session and minutes"))?;
+    let session = match session {
+        "work" => SessionKind::Work,
+        "short_break" => SessionKind::ShortBreak,
+        "long_break" => SessionKind::LongBreak,
+        _ => return Err(invalid("the session is work, short_break or long_break")),
+    };
+    if (session == SessionKind::Work) != (state.next.session == SessionKind::Work) {
+        return Err(invalid("a work session cannot be swapped for a break"));
+    }
+    // Lua 5.3 and later write integral floats as `30.0`.
+    let minutes = minutes.strip_suffix(".0").unwrap_or(minutes);
+    match minutes.parse() {
+        Ok(minutes) if (1..=MAX_DURATION_MINUTES).contains(&minutes) => {
+            Ok(Decision { session, minutes })
+        }
+        _ => Err(invalid(&format!(
+            "the minutes are a whole number from 1 to {}",
+            MAX_DURATION_MINUTES
+        ))),
+    }
+}
+
+/// Reads `pipe` to the end on a thread of its own, so that a script writing more than the pipe
+/// holds is not blocked while the timer waits for it to exit.
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut read = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut read);
+        }
+        read
+    })
+}
+
+/// The script deciding the sessions.
+pub struct Schedule {
+    config: ScheduleConfig,
+    failure: Option<ScriptError>,
+}
+
+impl Schedule {
+    pub fn new(config: ScheduleConfig) -> Self {
+        Schedule {
+            config,
+            failure: None,
+        }
+    }
+
+    fn run(&self, state: &State) -> Result<Decision, ScriptError> {
+        let mut child = Process::new(&self.config.interpreter)
+            .arg("-e")
+            .arg(chunk(&self.config.script, state))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| ScriptError::Spawn {
+                interpreter: self.config.interpreter.clone(),
+                source,
+            })?;
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+        let deadline = Instant::now() + TIMEOUT;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    // The pipes are left to their threads, a process the script started may keep
+                    // them open.
+                    return Err(ScriptError::TimedOut);
+                }
+            }
+        };
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        if !status.success() {
+            let last_line = stderr.lines().rfind(|line| !line.trim().is_empty());
+            return Err(ScriptError::Failed(
+                last_line.unwrap_or_default().trim().to_string(),
+            ));
+        }
+        parse(&stdout, state)
+    }
+
+    /// The session to run in `state`, the planned one when the script fails.
+    pub fn decide(&mut self, state: &State) -> Decision {
+        self.run(state).unwrap_or_else(|e| {
+            self.failure.get_or_insert(e);
+            state.next
+        })
+    }
+
+    /// The first failure of the script.
+    pub fn take_failure(&mut self) -> Option<ScriptError> {
+        self.failure.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn state(session: SessionKind, minutes: u64) -> State {
+        State {
+            next: Decision { session, minutes },
+            cycle: 2,
+            cycles: 4,
+            pomodoros: 6,
+            work_minutes: 150,
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+        }
+    }
+
+    #[test]
+    fn gives_the_state_as_a_lua_table() {
+        let state = state(SessionKind::ShortBreak, 5);
+        assert_eq!(
+            state.to_lua(),
+            "{ version = 1, next = { session = \"short_break\", minutes = 5 }, cycle = 2, \
+             cycles = 4, pomodoros = 6, work_minutes = 150, durations = { work = 25, \
+             short_break = 5, long_break = 15 } }"
+        );
+        assert_eq!(
+            lua_string("C:\\Users\\me \"lua\"\n"),
+            "\"C:\\\\Users\\\\me \\\"lua\\\"\\n\""
+        );
+        assert!(
+            chunk(Path::new("/home/me/schedule.lua"), &state)
+                .contains("dofile(\"/home/me/schedule.lua\")")
+        );
+    }
+
+    #[test]
+    fn reads_the_decision() {
+        let before_break = state(SessionKind::ShortBreak, 5);
+        assert_eq!(parse("", &before_break).unwrap(), before_break.next);
+        assert_eq!(
+            parse("long_break 30.0\n", &before_break).unwrap(),
+            Decision {
+                session: SessionKind::LongBreak,
+                minutes: 30
+            }
+        );
+        let invalid: Vec<String> = ["work 25", "long_break 0", "nap 20", "long_break"]
+            .into_iter()
+            .map(|output| parse(output, &before_break).unwrap_err().to_string())
+            .collect();
+        assert_eq!(
+            invalid,
+            [
+                "the schedule script returned `work 25`: a work session cannot be swapped for a break",
+                "the schedule script returned `long_break 0`: the minutes are a whole number from 1 to 1440",
+                "the schedule script returned `nap 20`: the session is work, short_break or long_break",
+                "the schedule script returned `long_break`: expected a session and minutes",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_planned_session_when_the_script_cannot_run() {
+        let mut schedule = Schedule::new(ScheduleConfig {
+            script: PathBuf::from("schedule.lua"),
+            interpreter: "rustodoro-missing-lua".to_string(),
+        });
+        let state = state(SessionKind::Work, 25);
+        assert_eq!(schedule.decide(&state), state.next);
+        assert!(matches!(
+            schedule.take_failure(),
+            Some(ScriptError::Spawn { .. })
+        ));
+        assert!(schedule.take_failure().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_the_output_of_a_chatty_script() {
+        use std::{env, fs, os::unix::fs::PermissionsExt};
+
+        let dir = env::temp_dir().join(format!("rustodoro-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // Far more than a pipe holds before the script exits.
+        let interpreter = dir.join("chatty-lua");
+        fs::write(
+            &interpreter,
+            "#!/bin/sh
+             yes 'warning: a chatty line of the schedule script' | head -n 5000 >&2
+             echo 'lua: schedule.lua:1: boom' >&2
+             exit 1
+",
+        )
+        .unwrap();
+        fs::set_permissions(&interpreter, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut schedule = Schedule::new(ScheduleConfig {
+            script: PathBuf::from("schedule.lua"),
+            interpreter: interpreter.display().to_string(),
+        });
+        let state = state(SessionKind::Work, 25);
+        assert_eq!(schedule.decide(&state), state.next);
+        assert!(matches!(
+            schedule.take_failure(),
+            Some(ScriptError::Failed(line)) if line == "lua: schedule.lua:1: boom"
+        ));
+    }
+}
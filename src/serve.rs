@@ -0,0 +1,381 @@
+// filepath: src/serve.rs
+//! Module serving the session state of a Pomodoro timer application over HTTP on the loopback
+//! interface, for the browser extensions blocking sites during the work sessions, for the editors
+//! showing the countdown in their status line and for the buttons of a Stream Deck.
+//!
+//! The endpoints form a versioned contract, fields only ever being added within `v1`:
+//!
+//! - `GET /v1/focus` answers a [`FocusState`], for the browser extensions.
+//! - `GET /v1/status` answers an [`EditorStatus`], for the editors polling it.
+//! - `GET /v1/icon` answers the icon of the session as a PNG, for the buttons of a Stream Deck.
+//! - `POST /v1/pause`, `/v1/resume`, `/v1/toggle`, `/v1/skip` (or `/v1/next`) and `/v1/reset`
+//!   send the command to the timer and answer `202 Accepted` with `{"version":1}`.
+//!
+//! The server reads no body and keeps no connection alive. A page reaching it learns at most
+//! whether the user is focusing: commands must carry the `X-Rustodoro` header, which a page cannot
+//! send without a CORS preflight that the server never allows, and requests naming another host,
+//! e.g. through DNS rebinding, are refused.
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use rustodoro_client::protocol::{
+    Action, EditorStatus, FOCUS_PROTOCOL_VERSION, FocusState, Snapshot,
+};
+use tokio::sync::mpsc::WeakUnboundedSender;
+
+use crate::{
+    events::EventBus, icon, remote, settings::SharedSettings, today::SharedToday, types::Command,
+};
+
+/// Longest wait for a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest request, headers included, accepted.
+const MAX_LINE: usize = 8 * 1024;
+/// Most requests served at once, the others getting a 503.
+const MAX_CLIENTS: usize = 16;
+/// Header the commands must carry.
+const COMMAND_HEADER: &str = "x-rustodoro";
+
+/// What the server reads of a request.
+#[derive(Debug, Default, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    /// The host and port the client reached, none from clients not telling it.
+    host: Option<String>,
+    /// Whether the request carries the [`COMMAND_HEADER`].
+    command_header: bool,
+}
+
+impl Request {
+    /// Reads the request line and the headers from `reader`, none when the client sent nothing.
+    fn read(reader: &mut impl BufRead) -> Option<Request> {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut words = line.split_whitespace();
+        let mut request = Request {
+            method: words.next()?.to_string(),
+            path: words.next()?.to_string(),
+            ..Request::default()
+        };
+        line.clear();
+        while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+            if let Some((name, value)) = line.split_once(':') {
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "host" => request.host = Some(value.trim().to_ascii_lowercase()),
+                    COMMAND_HEADER => request.command_header = true,
+                    _ => {}
+                }
+            }
+            line.clear();
+        }
+        Some(request)
+    }
+
+    /// Whether the client reached the loopback interface by its address or as localhost, on
+    /// `port`.
+    fn is_local(&self, port: u16) -> bool {
+        self.host.as_ref().is_none_or(|host| {
+            ["127.0.0.1", "localhost"]
+                .iter()
+                .any(|name| host == name || host.strip_prefix(name) == Some(&format!(":{}", port)))
+        })
+    }
+}
+
+/// Listens on `port` of the loopback interface, 0 picking any free port, and returns the address
+/// listened on. The server runs as long as the process, sending the commands it gets to
+/// `commands`.
+pub fn start(
+    port: u16,
+    events: &EventBus,
+    commands: WeakUnboundedSender<Command>,
+    settings: SharedSettings,
+    today: SharedToday,
+) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    let address = listener.local_addr()?;
+    let tracker = remote::follow(events, settings, today);
+    let clients = Arc::new(AtomicUsize::new(0));
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let status = remote::snapshot(&tracker);
+            let commands = commands.clone();
+            let clients = Arc::clone(&clients);
+            let busy = clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS;
+            thread::spawn(move || {
+                serve(stream, address.port(), &status, &commands, busy);
+                clients.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    Ok(address)
+}
+
+fn serve(
+    stream: TcpStream,
+    port: u16,
+    status: &Snapshot,
+    commands: &WeakUnboundedSender<Command>,
+    busy: bool,
+) {
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream).take(MAX_LINE as u64);
+    let Some(request) = Request::read(&mut reader) else {
+        return;
+    };
+    let json = "application/json";
+    let (code, content_type, body) = if busy {
+        (
+            "503 Service Unavailable",
+            json,
+            error("too many requests at once").into_bytes(),
+        )
+    } else {
+        match respond(&request, port, status) {
+            Reply::Answer(code, body) => (code, json, body.into_bytes()),
+            Reply::Icon(png) => ("200 OK", "image/png", png),
+            Reply::Command(command) => {
+                if remote::send(commands, command) {
+                    let accepted = serde_json::json!({ "version": FOCUS_PROTOCOL_VERSION });
+                    ("202 Accepted", json, accepted.to_string().into_bytes())
+                } else {
+                    let stopping = error("the timer is stopping");
+                    ("503 Service Unavailable", json, stopping.into_bytes())
+                }
+            }
+        }
+    };
+    let _ = write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        code,
+        content_type,
+        body.len()
+    )
+    .and_then(|()| writer.write_all(&body));
+}
+
+/// How the server answers a request.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    /// The status line and the body.
+    Answer(&'static str, String),
+    /// The icon of the session, as a PNG.
+    Icon(Vec<u8>),
+    /// The command to send to the timer.
+    Command(Command),
+}
+
+fn error(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// How to answer `request`, made to `port`, the timer being at `status`.
+fn respond(request: &Request, port: u16, status: &Snapshot) -> Reply {
+    if !request.is_local(port) {
+        return Reply::Answer("403 Forbidden", error("only local clients are served"));
+    }
+    let action = match request.path.as_str() {
+        "/v1/pause" => Some(Action::Pause),
+        "/v1/resume" => Some(Action::Resume),
+        "/v1/toggle" => Some(Action::Toggle),
+        // What a single button needs: the break, or the next one at the end of a work session.
+        "/v1/skip" | "/v1/next" => Some(Action::Skip),
+        "/v1/reset" => Some(Action::Reset),
+        _ => None,
+    };
+    match (request.method.as_str(), request.path.as_str(), action) {
+        ("GET", "/v1/focus", _) => Reply::Answer(
+            "200 OK",
+            serde_json::to_string(&FocusState::from(status)).unwrap_or_default(),
+        ),
+        ("GET", "/v1/status", _) => Reply::Answer(
+            "200 OK",
+            serde_json::to_string(&EditorStatus::from(status)).unwrap_or_default(),
+        ),
+        ("GET", "/v1/icon", _) => Reply::Icon(icon::render(status)),
+        ("POST", _, Some(action)) if request.command_header => {
+            Reply::Command(remote::command(action).expect("a command"))
+        }
+        ("POST", _, Some(_)) => Reply::Answer(
+            "403 Forbidden",
+            error("commands must carry the X-Rustodoro header"),
+        ),
+        ("GET", _, Some(_)) => Reply::Answer(
+            "405 Method Not Allowed",
+            error("commands are sent with POST"),
+        ),
+        ("POST", "/v1/focus" | "/v1/status" | "/v1/icon", None) => Reply::Answer(
+            "405 Method Not Allowed",
+            error("the state is read with GET"),
+        ),
+        ("GET" | "POST", _, None) => Reply::Answer(
+            "404 Not Found",
+            error("unknown path, use /v1/focus, /v1/status, /v1/icon or a command"),
+        ),
+        _ => Reply::Answer(
+            "405 Method Not Allowed",
+            error("only GET and POST are supported"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{SessionType, events::Event, settings::Settings, today::Today};
+
+    const PORT: u16 = 8765;
+
+    fn request(method: &str, path: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            host: Some(format!("127.0.0.1:{}", PORT)),
+            command_header: false,
+        }
+    }
+
+    fn code(request: &Request) -> &'static str {
+        match respond(request, PORT, &Snapshot::default()) {
+            Reply::Answer(code, _) => code,
+            Reply::Icon(_) => "icon",
+            Reply::Command(_) => "command",
+        }
+    }
+
+    fn send(address: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{}", request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn reads_the_request() {
+        let mut reader =
+            "POST /v1/toggle HTTP/1.1\r\nHost: LocalHost:8765\r\nX-Rustodoro: 1\r\n\r\n".as_bytes();
+        let request = Request::read(&mut reader).unwrap();
+        assert_eq!(
+            request,
+            Request {
+                method: "POST".to_string(),
+                path: "/v1/toggle".to_string(),
+                host: Some("localhost:8765".to_string()),
+                command_header: true,
+            }
+        );
+        assert!(request.is_local(PORT));
+        assert!(!request.is_local(9000));
+        assert_eq!(Request::read(&mut "".as_bytes()), None);
+    }
+
+    #[test]
+    fn answers_the_known_paths_only() {
+        assert_eq!(code(&request("GET", "/v1/focus")), "200 OK");
+        assert_eq!(code(&request("GET", "/v1/status")), "200 OK");
+        assert_eq!(code(&request("GET", "/v1/icon")), "icon");
+        assert_eq!(code(&request("GET", "/")), "404 Not Found");
+        assert_eq!(
+            code(&request("POST", "/v1/focus")),
+            "405 Method Not Allowed"
+        );
+        assert_eq!(
+            code(&request("GET", "/v1/toggle")),
+            "405 Method Not Allowed"
+        );
+        assert_eq!(
+            code(&request("OPTIONS", "/v1/toggle")),
+            "405 Method Not Allowed"
+        );
+    }
+
+    #[test]
+    fn guards_the_commands() {
+        assert_eq!(code(&request("POST", "/v1/toggle")), "403 Forbidden");
+        let command = Request {
+            command_header: true,
+            ..request("POST", "/v1/next")
+        };
+        assert_eq!(
+            respond(&command, PORT, &Snapshot::default()),
+            Reply::Command(Command::Skip)
+        );
+        // A page of a rebound domain resolving to the loopback interface.
+        let rebound = Request {
+            host: Some(format!("evil.example:{}", PORT)),
+            ..command
+        };
+        assert_eq!(code(&rebound), "403 Forbidden");
+        let rebound = Request {
+            host: Some("evil.example".to_string()),
+            ..request("GET", "/v1/status")
+        };
+        assert_eq!(code(&rebound), "403 Forbidden");
+    }
+
+    #[test]
+    fn serves_the_running_session_and_takes_commands() {
+        let events = EventBus::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let address = start(
+            0,
+            &events,
+            tx.downgrade(),
+            Settings::default().into_shared(),
+            Today::empty().into_shared(),
+        )
+        .unwrap();
+        assert!(address.ip().is_loopback());
+        events.emit(Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: Duration::from_secs(1500),
+        });
+
+        // The state follows the events on a thread of its own.
+        let get = |path| format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, address);
+        let mut response = String::new();
+        for _ in 0..100 {
+            response = send(address, &get("/v1/focus"));
+            if response.contains("\"active\":true") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(
+            "\r\n\r\n{\"version\":1,\"active\":true,\"session\":\"work\",\"remaining_secs\":1500,\"paused\":false}"
+        ));
+        let response = send(address, &get("/v1/status"));
+        assert!(response.contains("\"label\":\"🍅 25:00\""), "{}", response);
+
+        let response = send(
+            address,
+            &format!(
+                "POST /v1/toggle HTTP/1.1\r\nHost: {}\r\nX-Rustodoro: 1\r\n\r\n",
+                address
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 202 Accepted\r\n"));
+        assert!(response.ends_with("\r\n\r\n{\"version\":1}"));
+        assert_eq!(rx.try_recv().ok(), Some(Command::PauseResume));
+    }
+}
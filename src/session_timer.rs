@@ -1,138 +1,1382 @@
 // filepath: src/session_timer.rs
 //! Module handling the session timer logic for a Pomodoro timer application.
-use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::io::{self, IsTerminal};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use indicatif::ProgressBar;
-use notify_rust::Notification;
-use rodio::OutputStream;
-use rodio::source::{SineWave, Source};
+use chrono::Local;
+use indicatif::{ProgressBar, ProgressStyle, style::TemplateError};
+use tokio::time::{self, Instant};
+
+use rustodoro_client::protocol::SessionKind;
 
-// Replace these with the correct paths to your types:
 use crate::AppError;
 use crate::Command;
 use crate::SessionType;
+use crate::alerts::Alerts;
+use crate::audio::Switch;
+use crate::capture::AutoPause;
+use crate::command_queue::CommandQueue;
+use crate::events::{Event, EventBus, SessionOutcome};
+use crate::garden::Plant;
+use crate::history::{Interruptions, PauseReason};
+use crate::idle::AutoFinish;
+use crate::output;
+use crate::plan;
+use crate::platform::{self, Platform};
+use crate::render::{self, DisplayMode, Renderer};
+use crate::settings::{Checkpoint, PRIVATE_TASK, Settings};
+use crate::sounds::Cue;
+use crate::state_machine::{Machine, Phase, State, Trigger};
+use crate::types::SessionIndex;
+
+/// Time left when the "10 seconds left" notification is sent.
+const LAST_SECONDS_NOTICE: Duration = Duration::from_secs(10);
+/// Time left in a work session from which pressing skip applies to the upcoming break.
+const SKIP_GRACE: Duration = Duration::from_secs(5);
+/// Interval between two refreshes of the progress bar.
+const TICK: Duration = Duration::from_secs(1);
+/// Interval between two refreshes of the progress bar in low-power mode.
+const LOW_POWER_TICK: Duration = Duration::from_secs(15);
+/// How long the time left shows in the zen display after pressing the key.
+const REVEAL: Duration = Duration::from_secs(5);
+/// Interval between the end of a session and the first ring of its alarm, doubling after each
+/// ring.
+const ALARM_INTERVAL: Duration = Duration::from_secs(15);
+/// Longest interval between two rings of the alarm.
+const MAX_ALARM_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
 pub struct SessionTimer {
-    rx: Arc<Mutex<Receiver<Command>>>,
+    events: Arc<EventBus>,
     duration: Duration,
-    is_paused: bool,
+    machine: Machine,
     session: SessionType,
+    /// The session as the progress bar and the notifications name it, with its task.
+    label: String,
+    /// Whether the task is kept off the screen.
+    private: bool,
+    /// The task set during the session, for the sessions after it.
+    task: Option<String>,
     current_cycle: u64,
     total_cycles: u64,
     sound: bool,
-    sink: rodio::Sink,
-    _stream: OutputStream, // Keep the stream alive
+    notifications: bool,
+    /// Whether the end of the session rings until acknowledged.
+    require_ack: bool,
+    /// Moments at which the time left is announced.
+    checkpoints: Vec<Checkpoint>,
+    /// Stages of the break, by the time elapsed when each starts.
+    stages: Vec<(Duration, String)>,
+    tick: Duration,
+    renderer: Renderer,
+    /// Whether the progress bar fills without any number, the work sessions only.
+    zen: bool,
+    transition_pause: Duration,
+    /// Whether the duration is drawn with a jitter, and so shown in the banner.
+    jittered: bool,
+    skip_requested: bool,
+    /// Breaks to skip after this session, as many as the count typed before skip.
+    breaks_skipped: u32,
+    /// The session of the set the user left this one for.
+    jumped_to: Option<SessionIndex>,
+    /// Whether completing this work session reaches the daily goal.
+    reaches_goal: bool,
+    /// The plant of the day, shown in the banner of a work session.
+    plant: Option<Plant>,
+    /// Finishes this work session early when the user left.
+    auto_finish: Option<Arc<AutoFinish>>,
+    /// Pauses this work session during the calls.
+    auto_pause: Option<Arc<AutoPause>>,
+    /// Whether the time the device sleeps counts, the timer's clock standing still meanwhile.
+    suspend_aware: bool,
+    /// Time the day plan takes after this session, none once the daily goal is reached.
+    plan: Option<Duration>,
+    /// What the progress bar tells after the session, e.g. that the next break is skipped.
+    note: Option<String>,
 }
 
 impl SessionTimer {
     pub fn new(
-        rx: Arc<Mutex<Receiver<Command>>>,
+        events: Arc<EventBus>,
         duration: Duration,
         session: SessionType,
         current_cycle: u64,
-        total_cycles: u64,
-        no_sound: bool,
+        settings: &Settings,
     ) -> Self {
-        let mut stream =
-            rodio::OutputStreamBuilder::open_default_stream().expect("open default audio stream");
-        stream.log_on_drop(false);
+        let label = label(&session, settings.shown_task());
+        let mut start = Duration::ZERO;
+        let stages = settings
+            .stages
+            .of(session.kind)
+            .iter()
+            .map(|stage| {
+                let starts = start;
+                start += settings.minute * stage.minutes as u32;
+                (starts, stage.label.clone())
+            })
+            .collect();
         SessionTimer {
-            rx: rx,
-            is_paused: false,
+            events,
+            label,
+            private: settings.private,
+            task: None,
+            machine: Machine::new(),
+            jittered: settings.break_jitter.seconds > 0 && !session.is_work(),
+            zen: settings.display == DisplayMode::Zen && session.is_work(),
             duration,
             session,
             current_cycle,
-            total_cycles,
-            sound: !no_sound,
-            sink: rodio::Sink::connect_new(stream.mixer()),
-            _stream: stream,
+            total_cycles: settings.cycles_per_set(),
+            sound: !settings.no_sound,
+            notifications: !settings.no_notify,
+            require_ack: settings.require_ack,
+            checkpoints: settings.notify_at.clone(),
+            stages,
+            tick: if settings.low_power {
+                LOW_POWER_TICK
+            } else {
+                TICK
+            },
+            renderer: Renderer::new(settings.theme),
+            transition_pause: Duration::from_secs(settings.transition_pause),
+            skip_requested: false,
+            breaks_skipped: 0,
+            jumped_to: None,
+            reaches_goal: false,
+            plant: None,
+            auto_finish: None,
+            auto_pause: None,
+            suspend_aware: platform::current() == Platform::Termux,
+            plan: None,
+            note: None,
         }
     }
 
-    pub fn run(&mut self) -> Result<(), AppError> {
-        let progress_bar = ProgressBar::new(self.duration.as_secs());
-        progress_bar.set_message(format!(
-            "{} (#{}/{})",
-            self.session, self.current_cycle, self.total_cycles,
-        ));
-        progress_bar.set_style(
-            indicatif::ProgressStyle::with_template(
-                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta}) < {msg} >",
-            )
-            .unwrap()
-            .progress_chars("#>-"),
+    /// Makes the session end as skipped as soon as it starts. Only breaks can be skipped.
+    pub fn skip(&mut self) {
+        self.skip_requested = !self.session.is_work();
+    }
+
+    /// Plays the goal reached sound after the work session, once completed.
+    pub fn reaches_goal(&mut self) {
+        self.reaches_goal = self.session.is_work();
+    }
+
+    /// Shows `plant`, the plant of the day, in the banner of the work session.
+    pub fn shows_plant(&mut self, plant: Plant) {
+        if self.session.is_work() {
+            self.plant = Some(plant);
+        }
+    }
+
+    /// Projects the end of the day plan, which takes `ahead` after this session.
+    pub fn plans(&mut self, ahead: Option<Duration>) {
+        self.plan = ahead;
+    }
+
+    /// Finishes the work session early when `auto_finish` finds the user left near its end.
+    pub fn auto_finish(&mut self, auto_finish: Arc<AutoFinish>) {
+        if self.session.is_work() {
+            self.auto_finish = Some(auto_finish);
+        }
+    }
+
+    /// Pauses the work session while `auto_pause` finds a call going on.
+    pub fn auto_pause(&mut self, auto_pause: Arc<AutoPause>) {
+        if self.session.is_work() {
+            self.auto_pause = Some(auto_pause);
+        }
+    }
+
+    /// Whether a call is going on, for the work sessions paused during the calls.
+    fn in_call(&self) -> bool {
+        self.auto_pause
+            .as_ref()
+            .is_some_and(|auto_pause| auto_pause.in_call(Instant::now()))
+    }
+
+    /// Whether skip was pressed at the very end of this work session, for the upcoming break.
+    pub fn skips_next_break(&self) -> bool {
+        self.skip_requested && self.session.is_work()
+    }
+
+    /// How many of the breaks after this session are skipped: the one skip was pressed for at the
+    /// end of a work session, or as many as the count typed before skip.
+    pub fn breaks_skipped(&self) -> u32 {
+        self.breaks_skipped.max(u32::from(self.skips_next_break()))
+    }
+
+    /// The session of the set the user left this one for, if any.
+    pub fn jumped_to(&self) -> Option<SessionIndex> {
+        self.jumped_to
+    }
+
+    /// The task set during the session, if any, which labels the work sessions after it.
+    pub fn task(&self) -> Option<&str> {
+        self.task.as_deref()
+    }
+
+    /// Moves the session to the state `trigger` leads to and emits the transition, returning
+    /// whether it was allowed.
+    fn fire(&mut self, trigger: Trigger) -> bool {
+        match self.machine.fire(trigger) {
+            Ok(transition) => {
+                self.events.emit(Event::Transition(transition));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Counts the session down, taking the commands from `commands`.
+    pub async fn run(
+        &mut self,
+        commands: &mut CommandQueue,
+        alerts: &mut Alerts,
+    ) -> Result<SessionOutcome, AppError> {
+        self.fire(Trigger::Announce(Phase::from(&self.session)));
+        if let Err(e) = self.transition(commands).await {
+            self.fire(Trigger::Quit);
+            return Err(e);
+        }
+        self.events.emit(Event::SessionStarted {
+            session: self.session.clone(),
+            cycle: self.current_cycle,
+            duration: self.duration,
+        });
+        if let Some(ahead) = self.plan {
+            self.events.emit(Event::Planned { ahead });
+        }
+        if !(self.skip_requested && self.fire(Trigger::Skip)) {
+            self.fire(Trigger::Start);
+        }
+        let mut elapsed = Duration::ZERO;
+        let result = self.countdown(commands, alerts, &mut elapsed).await;
+        let outcome = match &result {
+            Ok(outcome) => *outcome,
+            Err(e) if e.is_quit() => {
+                self.fire(Trigger::Quit);
+                SessionOutcome::Interrupted
+            }
+            Err(_) => return result,
+        };
+        self.events.emit(Event::SessionEnded {
+            session: self.session.clone(),
+            elapsed,
+            outcome,
+        });
+        result
+    }
+
+    /// Rings at the end of the completed session until acknowledged, if required, less and less
+    /// often. Commands other than acknowledging are ignored, except quitting.
+    pub async fn alarm(
+        &mut self,
+        commands: &mut CommandQueue,
+        alerts: &mut Alerts,
+    ) -> Result<(), AppError> {
+        if !self.require_ack || self.machine.state() != State::Finished(SessionOutcome::Completed) {
+            return Ok(());
+        }
+        self.fire(Trigger::Alarm(Phase::from(&self.session)));
+        let (cue, next) = match self.session.kind {
+            SessionKind::Work => (Cue::WorkEnd, "break"),
+            _ => (Cue::BreakEnd, "work session"),
+        };
+        let message = format!(
+            "{} over, press [a] or Enter to start the {}",
+            self.session, next
         );
+        let progress_bar = ProgressBar::new_spinner();
+        progress_bar.set_message(message.clone());
         progress_bar.tick();
+        let _shown = output::show(&progress_bar, self.session.color);
+        let mut rings = 0;
+        let mut deadline = Instant::now() + alarm_interval(rings);
+        let result = loop {
+            match time::timeout_at(deadline, commands.next()).await {
+                Ok(Some(Command::Acknowledge)) => break Ok(()),
+                Ok(Some(_)) => {}
+                Ok(None) => break Err(AppError::Disconnected),
+                Err(_) => {
+                    rings += 1;
+                    deadline = Instant::now() + alarm_interval(rings);
+                    self.play(alerts, cue);
+                    self.notify(alerts, &message).await;
+                }
+            }
+        };
+        // The sound ringing again at the end of the breaks is acknowledged along.
+        alerts.acknowledge();
+        if result.is_ok() {
+            self.events.emit(Event::Acknowledged);
+        }
+        progress_bar.finish_and_clear();
+        self.fire(match result {
+            Ok(()) => Trigger::Acknowledge,
+            Err(_) => Trigger::Quit,
+        });
+        result
+    }
 
-        let mut remaining_secs = self.duration.as_secs();
-        while remaining_secs > 0 {
-            if remaining_secs == 10 {
-                send_notification(&format!("{}: 00:10s left", self.session));
+    /// Announces the session, then waits for the transition pause unless the session is already
+    /// skipped. Commands are ignored until the session starts, except quitting.
+    async fn transition(&self, commands: &mut CommandQueue) -> Result<(), AppError> {
+        output::print(&self.renderer.transition(
+            &self.session,
+            self.current_cycle,
+            self.total_cycles,
+            self.jittered.then_some(self.duration),
+        ));
+        if let Some(plant) = &self.plant {
+            output::print(&self.renderer.garden(plant));
+        }
+        if self.skip_requested {
+            return Ok(());
+        }
+        let animate = io::stdout().is_terminal();
+        let end = Instant::now() + self.transition_pause;
+        while Instant::now() < end {
+            let left = end - Instant::now();
+            if animate {
+                output::print(&format!("\r{}", self.renderer.starting_in(left)));
+            }
+            // Wake up when the countdown shown changes, i.e. on the next whole second left.
+            let shown = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+            let refresh = end - Duration::from_secs(shown - 1);
+            if let Ok(None) = time::timeout_at(refresh, commands.next()).await {
+                return Err(AppError::Disconnected);
             }
+        }
+        if animate && !self.transition_pause.is_zero() {
+            output::replace_line("");
+        }
+        Ok(())
+    }
 
-            if self.is_paused {
-                match self.rx.lock().unwrap().recv() {
-                    Ok(cmd) => match cmd {
-                        Command::Resume | Command::PauseResume => {
-                            self.is_paused = false;
-                            progress_bar.reset_eta();
-                        }
-                        _ => {}
-                    },
-                    Err(e) => return Err(AppError::ChannelRecv(e)),
-                }
-            } else {
-                match self.rx.lock().unwrap().recv_timeout(Duration::from_secs(1)) {
-                    Ok(cmd) => match cmd {
-                        Command::Skip if !matches!(self.session, SessionType::Work(_)) => {
-                            break;
+    async fn countdown(
+        &mut self,
+        commands: &mut CommandQueue,
+        alerts: &mut Alerts,
+        elapsed: &mut Duration,
+    ) -> Result<SessionOutcome, AppError> {
+        let progress_bar = ProgressBar::new(self.duration.as_secs());
+        progress_bar.set_message(self.message(self.duration - *elapsed));
+        progress_bar.set_style(self.style(false)?);
+        progress_bar.tick();
+        let _shown = output::show(&progress_bar, self.session.color);
+        if let State::Finished(outcome) = self.machine.state() {
+            progress_bar.finish_and_clear();
+            return Ok(outcome);
+        }
+        self.play(alerts, Cue::SessionStart);
+
+        // Time is measured on the clock rather than by counting waits, so that commands received
+        // in the middle of a second don't make the timer drift.
+        let mut last_instant = Instant::now();
+        let mut last_wall = SystemTime::now();
+        let mut last_seconds_notified = self.duration < LAST_SECONDS_NOTICE;
+        let mut checkpoints = self.checkpoints_ahead(self.duration - *elapsed);
+        let mut interruptions = Interruptions::default();
+        // When the time left stops showing in the zen display.
+        let mut revealed_until: Option<Instant> = None;
+        // Whether a call paused the session, and whether it was resumed by hand during the call.
+        let (mut in_call, mut call_ignored) = (false, false);
+        // The stage of the break last announced.
+        let mut stage: Option<usize> = None;
+        let outcome = loop {
+            match self.machine.state() {
+                State::Finished(outcome) => break outcome,
+                State::Paused(_) => {
+                    let mut resume = false;
+                    match time::timeout(self.tick, commands.next()).await {
+                        // The projected ends move on while paused, and the call which paused the
+                        // session may be over.
+                        Err(_) => {
+                            progress_bar.set_message(self.message(self.duration - *elapsed));
+                            resume = in_call && !self.in_call();
                         }
-                        Command::Pause | Command::PauseResume => {
-                            self.is_paused = true;
+                        Ok(cmd) => match cmd.ok_or(AppError::Disconnected)? {
+                            cmd @ (Command::Extend(_) | Command::SetTask(_) | Command::Jump(_)) => {
+                                self.adjust(cmd, &progress_bar, *elapsed);
+                                last_seconds_notified &=
+                                    self.duration - *elapsed <= LAST_SECONDS_NOTICE;
+                                checkpoints = self.checkpoints_ahead(self.duration - *elapsed);
+                            }
+                            Command::Resume | Command::PauseResume => {
+                                // Resumed during the call, the session keeps running through it.
+                                call_ignored = in_call;
+                                in_call = false;
+                                resume = true;
+                            }
+                            Command::PauseReason(reason) => {
+                                self.events.emit(Event::PauseReason { reason });
+                            }
+                            Command::Intent(intent) => self.events.emit(Event::Intent { intent }),
+                            _ => {}
+                        },
+                    }
+                    if resume && self.fire(Trigger::Resume) {
+                        let paused = last_instant.elapsed();
+                        interruptions.paused += paused;
+                        last_instant = Instant::now();
+                        last_wall = SystemTime::now();
+                        progress_bar.reset_eta();
+                        self.events.emit(Event::Resumed {
+                            elapsed: *elapsed,
+                            paused,
+                        });
+                        if in_call {
+                            in_call = false;
+                            let summary = format!(
+                                "Call over after {}, back to work with {} left",
+                                time_left(paused),
+                                time_left(self.duration - *elapsed)
+                            );
+                            output::line(format!("note: {}", summary));
+                            self.notify(alerts, &summary).await;
                         }
-                        Command::Reset => {
-                            remaining_secs = self.duration.as_secs();
-                            progress_bar.set_position(0);
-                            progress_bar.reset_eta();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let remaining = self.duration - *elapsed;
+            if remaining.is_zero() {
+                self.fire(Trigger::Complete);
+                continue;
+            }
+            if let Some(away) = self
+                .auto_finish
+                .as_ref()
+                .and_then(|auto_finish| auto_finish.away(remaining, Instant::now()))
+                && self.fire(Trigger::FinishEarly)
+            {
+                output::line(format!(
+                    "note: away for {}m{:02}s, the work session finished early",
+                    away.as_secs() / 60,
+                    away.as_secs() % 60
+                ));
+                continue;
+            }
+            if self.auto_pause.is_some() {
+                let capturing = self.in_call();
+                call_ignored &= capturing;
+                if capturing && !call_ignored && self.fire(Trigger::Pause) {
+                    in_call = true;
+                    interruptions.pauses += 1;
+                    output::line(
+                        "note: the microphone or the camera is in use, paused for the call",
+                    );
+                    self.events.emit(Event::Paused { elapsed: *elapsed });
+                    self.events.emit(Event::PauseReason {
+                        reason: PauseReason::Call,
+                    });
+                    continue;
+                }
+            }
+            if checkpoints.first().is_some_and(|left| remaining <= *left) {
+                checkpoints.retain(|left| *left < remaining);
+                self.play(alerts, Cue::Checkpoint);
+                self.notify(
+                    alerts,
+                    &format!("{}: {} left", self.label, time_left(remaining)),
+                )
+                .await;
+            }
+            if let Some((index, left)) = self.stage(*elapsed)
+                && stage != Some(index)
+            {
+                stage = Some(index);
+                let stages = self.stages_ahead().count();
+                let (_, label) = &self.stages[index];
+                output::line(format!(
+                    "Stage {}/{}: {} for {}",
+                    index + 1,
+                    stages,
+                    label,
+                    time_left(left)
+                ));
+                // The first stage starts with the break, which has its own cue.
+                if index > 0 {
+                    self.play(alerts, Cue::Checkpoint);
+                    self.notify(
+                        alerts,
+                        &format!("{}: {} for {}", self.label, label, time_left(left)),
+                    )
+                    .await;
+                }
+            }
+            if !last_seconds_notified && remaining <= LAST_SECONDS_NOTICE {
+                last_seconds_notified = true;
+                self.play(alerts, Cue::TenSecondsLeft);
+                self.notify(alerts, &format!("{}: 00:10s left", self.label))
+                    .await;
+            }
+
+            // Only wake up when there is something to do: refreshing the progress bar on the next
+            // tick boundary, sending the notification or ending the session. Commands wake the
+            // timer up by themselves.
+            let into_tick = elapsed.as_nanos() % self.tick.as_nanos();
+            let to_next_tick = self.tick - Duration::from_nanos(into_tick as u64);
+            let mut timeout = to_next_tick.min(remaining);
+            if !last_seconds_notified {
+                timeout = timeout.min(remaining - LAST_SECONDS_NOTICE);
+            }
+            if let Some(left) = checkpoints.first() {
+                timeout = timeout.min(remaining - *left);
+            }
+            if let Some((_, left)) = self.stage(*elapsed) {
+                timeout = timeout.min(left);
+            }
+            if self.sound
+                && let Some(next) = alerts.next_repeat()
+            {
+                timeout = timeout.min(next.saturating_duration_since(Instant::now()));
+            }
+            if let Some(until) = revealed_until {
+                timeout = timeout.min(until.saturating_duration_since(Instant::now()));
+            }
+            let received = time::timeout(timeout, commands.next()).await;
+            let now = Instant::now();
+            let mut waited = now - last_instant;
+            let wall = SystemTime::now();
+            if self.suspend_aware {
+                // Android stops the timer's clock while asleep, not the wall clock.
+                let slept =
+                    platform::slept(wall.duration_since(last_wall).unwrap_or_default(), waited);
+                if !slept.is_zero() {
+                    output::line(format!(
+                        "note: the device slept for {}, the timer caught up",
+                        time_left(slept)
+                    ));
+                    waited += slept;
+                }
+            }
+            *elapsed = (*elapsed + waited).min(self.duration);
+            last_instant = now;
+            last_wall = wall;
+            if self.sound {
+                self.report(alerts.repeat());
+            }
+            match received {
+                Ok(Some(cmd)) => match cmd {
+                    Command::Acknowledge => {
+                        alerts.acknowledge();
+                        self.events.emit(Event::Acknowledged);
+                    }
+                    // Only breaks can be skipped.
+                    Command::Skip if self.fire(Trigger::Skip) => {}
+                    // Skipping is ignored during work, except right before the end where the user
+                    // most likely meant the upcoming break.
+                    Command::Skip
+                        if !self.skip_requested && self.duration - *elapsed <= SKIP_GRACE =>
+                    {
+                        self.skip_requested = true;
+                        self.note = Some("next break skipped".to_string());
+                        self.events.emit(Event::NextBreakSkipped);
+                    }
+                    // A count skips this break and the following ones, or from the next break
+                    // on during work.
+                    Command::SkipBreaks(count) if self.fire(Trigger::Skip) => {
+                        self.breaks_skipped = count.saturating_sub(1);
+                    }
+                    Command::SkipBreaks(count) if count > 0 && self.session.is_work() => {
+                        self.breaks_skipped = count;
+                        let skipped = match count {
+                            1 => "next break skipped".to_string(),
+                            _ => format!("next {} breaks skipped", count),
+                        };
+                        self.note = Some(skipped);
+                        self.events.emit(Event::NextBreakSkipped);
+                    }
+                    Command::Pause | Command::PauseResume if self.fire(Trigger::Pause) => {
+                        interruptions.pauses += 1;
+                        self.events.emit(Event::Paused { elapsed: *elapsed });
+                    }
+                    Command::Reset if self.fire(Trigger::Reset) => {
+                        interruptions.resets += 1;
+                        *elapsed = Duration::ZERO;
+                        stage = None;
+                        last_seconds_notified = self.duration < LAST_SECONDS_NOTICE;
+                        checkpoints = self.checkpoints_ahead(self.duration);
+                        progress_bar.reset_eta();
+                        if self.skip_requested {
+                            // The grace period starts over with the countdown.
+                            self.skip_requested = false;
+                            self.note = None;
                         }
-                        _ => {}
-                    },
-                    Err(RecvTimeoutError::Timeout) => {
-                        progress_bar.inc(1);
-                        remaining_secs -= 1;
+                        self.events.emit(Event::Reset);
+                    }
+                    Command::Extend(_) | Command::SetTask(_) | Command::Jump(_) => {
+                        self.adjust(cmd, &progress_bar, *elapsed);
+                        last_seconds_notified &= self.duration - *elapsed <= LAST_SECONDS_NOTICE;
+                        checkpoints = self.checkpoints_ahead(self.duration - *elapsed);
                     }
-                    Err(e) => {
-                        return Err(AppError::ChannelRecvTimeout(e)); // Command Dispatcher stopped
+                    Command::Intent(intent) => self.events.emit(Event::Intent { intent }),
+                    Command::Reveal if self.zen => {
+                        revealed_until = Some(Instant::now() + REVEAL);
+                        progress_bar.set_style(self.style(true)?);
+                    }
+                    _ => {}
+                },
+                Ok(None) => {
+                    return Err(AppError::Disconnected); // Command Dispatcher stopped
+                }
+                Err(_) => {
+                    self.events.emit(Event::Tick {
+                        elapsed: *elapsed,
+                        remaining: self.duration - *elapsed,
+                    });
+                }
+            }
+            if revealed_until.is_some_and(|until| until <= Instant::now()) {
+                revealed_until = None;
+                progress_bar.set_style(self.style(false)?);
+            }
+            progress_bar.set_message(self.message(self.duration - *elapsed));
+            progress_bar.set_position(elapsed.as_secs());
+        };
+        if outcome.is_done() {
+            match self.session.kind {
+                SessionKind::Work => {
+                    let score = interruptions.focus_score(outcome, *elapsed, self.duration);
+                    output::line(format!("Focus {}/100", score));
+                    self.play(alerts, Cue::WorkEnd);
+                    if self.reaches_goal {
+                        self.play(alerts, Cue::GoalReached);
                     }
                 }
+                _ => self.play(alerts, Cue::BreakEnd),
             }
         }
-        if remaining_secs == 0 && self.sound {
-            play_sound(&self.sink);
+        Ok(outcome)
+    }
+
+    /// The message of the progress bar, followed by the stage of the break with its own countdown,
+    /// the note and, with `remaining` left, the projected ends of the session and of the day plan.
+    /// The zen display leaves the cycle and the ends out, showing no number.
+    fn message(&self, remaining: Duration) -> String {
+        let mut message = self.label.clone();
+        if !self.zen {
+            message.push_str(&format!(" (#{}/{})", self.current_cycle, self.total_cycles));
+        }
+        if let Some((index, left)) = self.stage(self.duration.saturating_sub(remaining)) {
+            message.push_str(&format!(" › {} {}", self.stages[index].1, time_left(left)));
+        }
+        if let Some(note) = &self.note {
+            message.push_str(&format!(" - {}", note));
+        }
+        if !self.zen {
+            message.push_str(&format!(
+                " - {}",
+                plan::projection(Local::now(), remaining, self.plan)
+            ));
+        }
+        message
+    }
+
+    /// Style of the progress bar, the zen display filling without any number unless `revealed`.
+    fn style(&self, revealed: bool) -> Result<ProgressStyle, TemplateError> {
+        if self.zen && !revealed {
+            self.renderer.zen_style(self.session.color)
+        } else {
+            render::progress_style(render::columns(), self.session.color)
+        }
+    }
+
+    /// The stages starting before the end of the break, which the extensions may push back.
+    fn stages_ahead(&self) -> impl Iterator<Item = &(Duration, String)> {
+        self.stages
+            .iter()
+            .take_while(|(start, _)| *start < self.duration)
+    }
+
+    /// The stage running after `elapsed`, and the time left until the next one starts, the last
+    /// stage lasting until the end of the break.
+    fn stage(&self, elapsed: Duration) -> Option<(usize, Duration)> {
+        let index = self
+            .stages_ahead()
+            .take_while(|(start, _)| *start <= elapsed)
+            .count()
+            .checked_sub(1)?;
+        let end = self
+            .stages_ahead()
+            .nth(index + 1)
+            .map_or(self.duration, |(start, _)| *start);
+        Some((index, end.saturating_sub(elapsed)))
+    }
+
+    /// The times left at which the checkpoints still ahead with `remaining` left are announced,
+    /// the latest first. Those within the last seconds are left to their own notice.
+    fn checkpoints_ahead(&self, remaining: Duration) -> Vec<Duration> {
+        let mut ahead: Vec<Duration> = self
+            .checkpoints
+            .iter()
+            .map(|checkpoint| checkpoint.left(self.duration))
+            .filter(|left| *left < remaining && *left > LAST_SECONDS_NOTICE)
+            .collect();
+        ahead.sort_unstable_by(|a, b| b.cmp(a));
+        ahead.dedup();
+        ahead
+    }
+
+    /// Lengthens the session, labels the work sessions with another task, or leaves the session for
+    /// another one of the set, as `command` tells, `elapsed` into the session.
+    fn adjust(&mut self, command: Command, progress_bar: &ProgressBar, elapsed: Duration) {
+        match command {
+            Command::Extend(by) => {
+                self.duration += by;
+                progress_bar.set_length(self.duration.as_secs());
+                self.events.emit(Event::Extended { by });
+            }
+            Command::SetTask(task) => {
+                // Private mode keeps the task off the screen.
+                let shown = if self.private { PRIVATE_TASK } else { &task };
+                if self.session.is_work() {
+                    self.label = label(&self.session, Some(shown));
+                    progress_bar.set_message(self.message(self.duration - elapsed));
+                }
+                self.task = Some(task.clone());
+                self.events.emit(Event::TaskChanged { task });
+            }
+            Command::Jump(index) if self.fire(Trigger::Jump) => self.jumped_to = Some(index),
+            _ => {}
+        }
+    }
+
+    /// Plays the sound of `cue` if the sound is on, and its vibration either way. Once the run
+    /// started, the sound going away is reported and the timer keeps going.
+    fn play(&self, alerts: &mut Alerts, cue: Cue) {
+        alerts.vibrate(cue);
+        if !self.sound {
+            return;
+        }
+        self.report(alerts.play(cue));
+    }
+
+    /// Reports the result of playing a sound.
+    fn report(&self, result: Result<Option<Switch>, AppError>) {
+        match result {
+            Ok(Some(switch)) => output::line(format!("note: {}", switch)),
+            Ok(None) => {}
+            Err(e) => {
+                output::line(format!("warning: {}", e));
+                if let Some(hint) = e.hint() {
+                    output::line(format!("  hint: {}", hint));
+                }
+            }
+        }
+    }
+
+    async fn notify(&self, alerts: &mut Alerts, message: &str) {
+        if !self.notifications {
+            return;
+        }
+        // Keeping quiet, the terminal is the only place to show it.
+        if alerts.is_quiet() {
+            output::line(message);
+            return;
+        }
+        // Notifications are a convenience: report the failure and keep the timer going.
+        if let Err(e) = alerts.notify(message).await {
+            output::line(format!("warning: {}", e));
+            if let Some(hint) = e.hint() {
+                output::line(format!("  hint: {}", hint));
+            }
         }
-        Ok(())
     }
 }
 
-fn play_sound(sink: &rodio::Sink) {
-    let source = SineWave::new(440.0)
-        .take_duration(Duration::from_secs_f32(0.25))
-        .amplify(0.20);
+/// The session as the progress bar and the notifications name it, the work sessions with `task`.
+fn label(session: &SessionType, task: Option<&str>) -> String {
+    match (session.kind, task) {
+        (SessionKind::Work, Some(task)) => format!("{} - {}", session, task),
+        _ => session.to_string(),
+    }
+}
 
-    sink.append(source);
+/// `left` as announced at a checkpoint, e.g. `5m00s`, rounded up to the second.
+fn time_left(left: Duration) -> String {
+    let seconds = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+    format!("{}m{:02}s", seconds / 60, seconds % 60)
+}
 
-    // The sound plays in a separate thread. This call will block the current thread until the sink
-    // has finished playing all its queued sounds.
-    sink.sleep_until_end();
+/// Interval before the ring following the first `rings` ones of an alarm.
+fn alarm_interval(rings: u32) -> Duration {
+    ALARM_INTERVAL
+        .saturating_mul(2u32.saturating_pow(rings))
+        .min(MAX_ALARM_INTERVAL)
 }
 
-fn send_notification(message: &str) {
-    Notification::new()
-        .summary("Pomodoro Timer")
-        .body(message)
-        .icon("dialog-information")
-        .show()
-        .expect("Failed to send notification.");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        capture::{CaptureProbe, Device},
+        config::{AutoFinishConfig, AutoPauseConfig},
+        idle::IdleClock,
+        settings::{BreakStages, Stage},
+        testing::Script,
+    };
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    /// Runs a session driven by `script` and returns its result and how long it took.
+    async fn run(
+        script: Script,
+        duration: Duration,
+        session: SessionType,
+    ) -> (Result<SessionOutcome, AppError>, Duration) {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        };
+        let start = Instant::now();
+        let (result, _) = run_with(script, duration, session, &settings).await;
+        (result, start.elapsed())
+    }
+
+    /// Runs a session driven by `script` and returns its result and the number of ticks, i.e. of
+    /// times the timer woke up without a command.
+    async fn run_with(
+        script: Script,
+        duration: Duration,
+        session: SessionType,
+        settings: &Settings,
+    ) -> (Result<SessionOutcome, AppError>, usize) {
+        let events = Arc::new(EventBus::new());
+        let subscription = events.subscribe();
+        let mut timer = SessionTimer::new(Arc::clone(&events), duration, session, 1, settings);
+        let result = timer
+            .run(
+                &mut CommandQueue::new(script.spawn()),
+                &mut Alerts::new(settings).unwrap(),
+            )
+            .await;
+        let ticks = subscription
+            .try_iter()
+            .filter(|event| matches!(event, Event::Tick { .. }))
+            .count();
+        (result, ticks)
+    }
+
+    #[test]
+    fn alarm_rings_less_and_less_often() {
+        let intervals: Vec<u64> = (0..7)
+            .map(|rings| alarm_interval(rings).as_secs())
+            .collect();
+        assert_eq!(intervals, [15, 30, 60, 120, 240, 300, 300]);
+        assert_eq!(alarm_interval(u32::MAX), MAX_ALARM_INTERVAL);
+    }
+
+    #[test]
+    fn announces_the_checkpoints_still_ahead() {
+        let settings = Settings {
+            notify_at: ["50%", "5m", "1m", "10s", "5m"]
+                .iter()
+                .map(|checkpoint| checkpoint.parse().unwrap())
+                .collect(),
+            ..Settings::default()
+        };
+        let timer = SessionTimer::new(
+            Arc::new(EventBus::new()),
+            MINUTE * 20,
+            SessionType::work(),
+            1,
+            &settings,
+        );
+        assert_eq!(
+            timer.checkpoints_ahead(MINUTE * 20),
+            [MINUTE * 10, MINUTE * 5, MINUTE]
+        );
+        assert_eq!(timer.checkpoints_ahead(MINUTE * 5), [MINUTE]);
+        assert_eq!(time_left(MINUTE * 5 - SECOND / 2), "5m00s");
+    }
+
+    #[test]
+    fn labels_the_work_session_with_the_task_set() {
+        let settings = Settings {
+            task: Some("Review".to_string()),
+            ..Settings::default()
+        };
+        let bar = ProgressBar::hidden();
+        let mut timer = SessionTimer::new(
+            Arc::new(EventBus::new()),
+            MINUTE * 25,
+            SessionType::work(),
+            1,
+            &settings,
+        );
+        assert!(
+            timer
+                .message(MINUTE * 25)
+                .starts_with("Work session - Review (#1/4)")
+        );
+        timer.adjust(
+            Command::SetTask("Write the report".to_string()),
+            &bar,
+            MINUTE,
+        );
+        assert!(
+            bar.message()
+                .starts_with("Work session - Write the report (#1/4)")
+        );
+        assert_eq!(timer.task(), Some("Write the report"));
+
+        // Private mode keeps the task off the screen, not off the sessions after.
+        let private = Settings {
+            private: true,
+            ..settings
+        };
+        let mut timer = SessionTimer::new(
+            Arc::new(EventBus::new()),
+            MINUTE * 25,
+            SessionType::work(),
+            1,
+            &private,
+        );
+        timer.adjust(
+            Command::SetTask("Write the report".to_string()),
+            &bar,
+            MINUTE,
+        );
+        assert!(bar.message().starts_with("Work session - Focus (#1/4)"));
+        assert_eq!(timer.task(), Some("Write the report"));
+    }
+
+    #[test]
+    fn runs_the_break_through_its_stages() {
+        let stage = |label: &str, minutes| Stage {
+            label: label.to_string(),
+            minutes,
+        };
+        let settings = Settings {
+            stages: BreakStages {
+                short_break: vec![
+                    stage("Eyes closed", 1),
+                    stage("Walk", 3),
+                    stage("Plan", 1),
+                    stage("Stretch", 2),
+                ],
+                long_break: Vec::new(),
+            },
+            ..Settings::default()
+        };
+        let timer = |duration, session| {
+            SessionTimer::new(Arc::new(EventBus::new()), duration, session, 1, &settings)
+        };
+        let short_break = timer(MINUTE * 5, SessionType::short_break());
+        assert_eq!(short_break.stage(Duration::ZERO), Some((0, MINUTE)));
+        assert_eq!(short_break.stage(MINUTE * 2), Some((1, MINUTE * 2)));
+        // The last stage lasts until the end, those starting after it are left out.
+        assert_eq!(short_break.stage(MINUTE * 4), Some((2, MINUTE)));
+        assert_eq!(short_break.stages_ahead().count(), 3);
+        assert!(
+            short_break
+                .message(MINUTE * 2 + SECOND / 2)
+                .starts_with("Short break (#1/4) › Walk 1m01s - ends ")
+        );
+        assert_eq!(timer(MINUTE * 15, SessionType::work()).stage(MINUTE), None);
+        assert_eq!(
+            timer(MINUTE * 15, SessionType::long_break()).stage(MINUTE),
+            None
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn zen_display_shows_no_number_during_work() {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            display: DisplayMode::Zen,
+            ..Settings::default()
+        };
+        let timer =
+            |session| SessionTimer::new(Arc::new(EventBus::new()), MINUTE, session, 2, &settings);
+        let mut work = timer(SessionType::work());
+        work.note = Some("next break skipped".to_string());
+        assert_eq!(work.message(MINUTE), "Work session - next break skipped");
+        let message = timer(SessionType::short_break()).message(MINUTE);
+        assert!(message.starts_with("Short break (#2/4) - ends "));
+
+        let start = Instant::now();
+        let script = Script::new().at(SECOND * 30, Command::Reveal);
+        let (result, _) = run_with(script, MINUTE, SessionType::work(), &settings).await;
+        assert!(matches!(result, Ok(SessionOutcome::Completed)));
+        assert_eq!(start.elapsed(), MINUTE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn session_lasts_its_duration() {
+        let (result, now) = run(
+            Script::new(),
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(SessionOutcome::Completed)));
+        assert_eq!(now, MINUTE * 25);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_does_not_count_towards_the_session() {
+        let script = Script::new()
+            .at(SECOND * 10, Command::Pause)
+            .at(SECOND * 70, Command::Resume);
+        let (result, now) = run(
+            script,
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(now, MINUTE * 26);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_resume_toggles() {
+        let script = Script::new()
+            .at(SECOND * 10, Command::PauseResume)
+            .at(SECOND * 40, Command::PauseResume);
+        let (_, now) = run(
+            script,
+            MINUTE,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert_eq!(now, MINUTE + SECOND * 30);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn commands_within_a_second_do_not_drift() {
+        let script = Script::new()
+            .at(SECOND / 4, Command::Resume)
+            .at(SECOND / 2, Command::Resume)
+            .at(SECOND * 3 / 4, Command::Resume);
+        let (_, now) = run(
+            script,
+            SECOND * 5,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert_eq!(now, SECOND * 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_restarts_the_countdown() {
+        let script = Script::new().at(SECOND * 30, Command::Reset);
+        let (_, now) = run(
+            script,
+            MINUTE,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert_eq!(now, MINUTE + SECOND * 30);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_ends_a_break_only() {
+        let script = Script::new().at(SECOND * 30, Command::Skip);
+        let (result, now) = run(
+            script,
+            MINUTE * 5,
+            SessionType {
+                label: "Break".to_string(),
+                ..SessionType::short_break()
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(SessionOutcome::Skipped)));
+        assert_eq!(now, SECOND * 30);
+
+        let script = Script::new().at(SECOND * 30, Command::Skip);
+        let (_, now) = run(
+            script,
+            MINUTE * 5,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert_eq!(now, MINUTE * 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn late_skip_applies_to_the_next_break() {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        };
+        let events = Arc::new(EventBus::new());
+        let mut timer = SessionTimer::new(
+            events,
+            MINUTE,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            1,
+            &settings,
+        );
+        let script = Script::new().at(MINUTE - SECOND * 3, Command::Skip);
+        let start = Instant::now();
+        let result = timer
+            .run(
+                &mut CommandQueue::new(script.spawn()),
+                &mut Alerts::new(&settings).unwrap(),
+            )
+            .await;
+        assert!(matches!(result, Ok(SessionOutcome::Completed)));
+        assert_eq!(start.elapsed(), MINUTE);
+        assert!(timer.skips_next_break());
+
+        let script = Script::new().at(MINUTE - SECOND * 10, Command::Skip);
+        let mut timer = SessionTimer::new(
+            Arc::new(EventBus::new()),
+            MINUTE,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            1,
+            &settings,
+        );
+        timer
+            .run(
+                &mut CommandQueue::new(script.spawn()),
+                &mut Alerts::new(&settings).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(!timer.skips_next_break());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wakes_up_once_per_second() {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        };
+        let (result, ticks) = run_with(
+            Script::new(),
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            &settings,
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(ticks, 25 * 60);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn low_power_mode_wakes_up_less() {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            low_power: true,
+            ..Settings::default()
+        };
+        let (result, ticks) = run_with(
+            Script::new(),
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            &settings,
+        )
+        .await;
+        assert!(result.is_ok());
+        // One wake-up every 15 seconds, plus one to send the "10 seconds left" notification.
+        assert_eq!(ticks, 25 * 4 + 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_timer_does_not_wake_up() {
+        let script = Script::new()
+            .at(SECOND / 2, Command::Pause)
+            .at(MINUTE * 60, Command::Resume);
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        };
+        let (result, ticks) = run_with(
+            script,
+            SECOND,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            &settings,
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(ticks, 1);
+    }
+
+    /// Idle since the given instant.
+    struct LeftAt(Instant);
+
+    impl IdleClock for LeftAt {
+        fn idle(&self) -> Option<Duration> {
+            Some(Instant::now().saturating_duration_since(self.0))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn work_session_finishes_early_once_the_user_left() {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        };
+        let start = Instant::now();
+        let events = Arc::new(EventBus::new());
+        let mut timer = SessionTimer::new(
+            Arc::clone(&events),
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+            1,
+            &settings,
+        );
+        timer.auto_finish(Arc::new(AutoFinish::new(
+            AutoFinishConfig {
+                idle: 120,
+                within: 5,
+            },
+            LeftAt(start + MINUTE * 18),
+        )));
+        let result = timer
+            .run(
+                &mut CommandQueue::new(Script::new().spawn()),
+                &mut Alerts::new(&settings).unwrap(),
+            )
+            .await;
+        assert!(matches!(result, Ok(SessionOutcome::FinishedEarly)));
+        assert_eq!(start.elapsed(), MINUTE * 20);
+    }
+
+    /// In a call between the given instants.
+    struct CallBetween(Instant, Instant);
+
+    impl CaptureProbe for CallBetween {
+        fn capturing(&self, device: Device) -> Option<bool> {
+            let now = Instant::now();
+            Some(device == Device::Microphone && self.0 <= now && now < self.1)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn work_session_pauses_during_the_calls() {
+        let settings = Settings {
+            no_sound: true,
+            no_notify: true,
+            ..Settings::default()
+        };
+        for (script, ended_at) in [
+            (Script::new(), MINUTE * 35),
+            // Resumed by hand, the session runs through the call.
+            (Script::new().at(MINUTE * 7, Command::Resume), MINUTE * 27),
+        ] {
+            let start = Instant::now();
+            let events = Arc::new(EventBus::new());
+            let subscription = events.subscribe();
+            let mut timer = SessionTimer::new(
+                Arc::clone(&events),
+                MINUTE * 25,
+                SessionType::work(),
+                1,
+                &settings,
+            );
+            timer.auto_pause(Arc::new(AutoPause::new(
+                &AutoPauseConfig::default(),
+                CallBetween(start + MINUTE * 5, start + MINUTE * 15),
+            )));
+            let result = timer
+                .run(
+                    &mut CommandQueue::new(script.spawn()),
+                    &mut Alerts::new(&settings).unwrap(),
+                )
+                .await;
+            assert!(matches!(result, Ok(SessionOutcome::Completed)));
+            assert_eq!(start.elapsed(), ended_at);
+            let paused: Vec<Event> = subscription
+                .try_iter()
+                .filter(|event| matches!(event, Event::Paused { .. } | Event::PauseReason { .. }))
+                .collect();
+            assert_eq!(
+                paused,
+                [
+                    Event::Paused {
+                        elapsed: MINUTE * 5
+                    },
+                    Event::PauseReason {
+                        reason: PauseReason::Call
+                    },
+                ]
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quitting_interrupts_the_session() {
+        let script = Script::new().quit_at(MINUTE);
+        let (result, now) = run(
+            script,
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert!(result.is_err_and(|e| e.is_quit()));
+        assert_eq!(now, MINUTE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quitting_while_paused_interrupts_the_session() {
+        let script = Script::new()
+            .at(SECOND * 10, Command::Pause)
+            .quit_at(MINUTE);
+        let (result, now) = run(
+            script,
+            MINUTE * 25,
+            SessionType {
+                label: "Work".to_string(),
+                ..SessionType::work()
+            },
+        )
+        .await;
+        assert!(result.is_err_and(|e| e.is_quit()));
+        assert_eq!(now, MINUTE);
+    }
 }
@@ -0,0 +1,795 @@
+// filepath: src/settings.rs
+//! Module holding the run settings that can be adjusted while the Pomodoro timer is running.
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{
+    haptics::VibrationsConfig,
+    pace::TimeOfDay,
+    render::{Color, DisplayMode, Theme},
+    sounds::SoundsConfig,
+    types::SessionType,
+};
+
+/// Longest duration accepted for any session, in minutes (24 hours).
+pub const MAX_DURATION_MINUTES: u64 = 24 * 60;
+/// Highest number of cycles accepted for a run.
+pub const MAX_CYCLES: u64 = 100;
+/// Highest daily goal accepted, in pomodoros.
+pub const MAX_DAILY_GOAL: u64 = 100;
+/// Longest pause accepted between two sessions, in seconds.
+pub const MAX_TRANSITION_PAUSE: u64 = 60;
+/// Widest jitter accepted on the breaks, in seconds.
+pub const MAX_BREAK_JITTER: u64 = 60 * 60;
+/// Longest custom sequence accepted, in sessions.
+pub const MAX_SEQUENCE: usize = 200;
+/// Most stages of a break.
+pub const MAX_STAGES: usize = 10;
+/// Label shown in place of the task in private mode.
+pub const PRIVATE_TASK: &str = "Focus";
+/// Length of a minute of the sessions, outside of the tutorial.
+pub const MINUTE: Duration = Duration::from_secs(60);
+/// Shortest break a jitter can lead to.
+const MIN_JITTERED_BREAK: u64 = 60;
+
+/// How much the breaks vary around their duration, either way, e.g. `2m` or `90s`. A plain number
+/// is in minutes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Jitter {
+    pub seconds: u64,
+}
+
+impl Jitter {
+    /// A break of `duration` once jittered by `offset` seconds, from `-seconds` to `seconds`. A
+    /// break is never shortened below a minute.
+    pub fn apply(&self, duration: Duration, offset: i64) -> Duration {
+        let offset = offset.clamp(-(self.seconds as i64), self.seconds as i64);
+        let seconds = duration.as_secs().saturating_add_signed(offset);
+        Duration::from_secs(seconds.max(MIN_JITTERED_BREAK.min(duration.as_secs())))
+    }
+}
+
+impl FromStr for Jitter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = seconds(s)
+            .ok_or_else(|| format!("must be a duration such as 2m or 90s, got `{}`", s))?;
+        Ok(Jitter { seconds })
+    }
+}
+
+/// The seconds of a duration such as `2m` or `90s`, a plain number being in minutes.
+fn seconds(s: &str) -> Option<u64> {
+    let trimmed = s.trim();
+    let (number, unit) = match trimmed.strip_suffix('s') {
+        Some(number) => (number, 1),
+        None => (trimmed.strip_suffix('m').unwrap_or(trimmed), 60),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// Seconds written in minutes when they are a whole number of them, e.g. `2m`, or else as is.
+fn write_seconds(f: &mut fmt::Formatter<'_>, seconds: u64) -> fmt::Result {
+    if seconds.is_multiple_of(60) {
+        write!(f, "{}m", seconds / 60)
+    } else {
+        write!(f, "{}s", seconds)
+    }
+}
+
+impl TryFrom<String> for Jitter {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// The jitter in minutes when it is a whole number of them, e.g. `2m`, or else in seconds.
+impl fmt::Display for Jitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_seconds(f, self.seconds)
+    }
+}
+
+/// A length of time written as a duration, e.g. `10m` or `90s`. A plain number is in minutes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Span {
+    pub seconds: u64,
+}
+
+impl FromStr for Span {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = seconds(s)
+            .ok_or_else(|| format!("must be a duration such as 10m or 90s, got `{}`", s))?;
+        Ok(Span { seconds })
+    }
+}
+
+impl TryFrom<String> for Span {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_seconds(f, self.seconds)
+    }
+}
+
+/// A moment of the sessions at which the time left is announced: when a share of the session is
+/// left, e.g. `50%`, or a duration, e.g. `5m` or `30s`. A plain number is in minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Checkpoint {
+    Percent(u64),
+    Seconds(u64),
+}
+
+impl Checkpoint {
+    /// The time left at the checkpoint in a session of `duration`.
+    pub fn left(&self, duration: Duration) -> Duration {
+        match self {
+            Checkpoint::Percent(percent) => duration.mul_f64(*percent as f64 / 100.0),
+            Checkpoint::Seconds(seconds) => Duration::from_secs(*seconds),
+        }
+    }
+
+    /// What makes the checkpoint invalid, if anything.
+    pub fn problem(&self) -> Option<String> {
+        match self {
+            Checkpoint::Percent(percent) if !(1..=99).contains(percent) => Some(format!(
+                "shares of the session must be from 1% to 99%, got {}%",
+                percent
+            )),
+            Checkpoint::Seconds(seconds)
+                if *seconds == 0 || *seconds > MAX_DURATION_MINUTES * 60 =>
+            {
+                Some(format!(
+                    "durations must be from 1 second to {} minutes, got {}",
+                    MAX_DURATION_MINUTES, self
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "must be a share such as 50% or a duration such as 5m, got `{}`",
+                s
+            )
+        };
+        match s.trim().strip_suffix('%') {
+            Some(percent) => percent
+                .trim()
+                .parse()
+                .map(Checkpoint::Percent)
+                .map_err(|_| invalid()),
+            None => seconds(s).map(Checkpoint::Seconds).ok_or_else(invalid),
+        }
+    }
+}
+
+impl TryFrom<String> for Checkpoint {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Checkpoint::Percent(percent) => write!(f, "{}%", percent),
+            Checkpoint::Seconds(seconds) => write_seconds(f, *seconds),
+        }
+    }
+}
+
+/// Work durations growing with the cycles, in minutes, e.g. `[15, 20, 25, 30]` or
+/// `{ start = 15, step = 5, max = 40 }` in the configuration file, `15,20,25,30` or `15+5..40` on
+/// the command line.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Ramp {
+    /// The duration of each cycle, the last one going on for the next cycles.
+    List(Vec<u64>),
+    /// `start` minutes, `step` more each cycle, up to `max`.
+    Step { start: u64, step: u64, max: u64 },
+}
+
+impl Ramp {
+    /// The work duration of `cycle`, from 1.
+    pub fn minutes(&self, cycle: u64) -> u64 {
+        let index = cycle.saturating_sub(1);
+        match self {
+            Ramp::List(list) => usize::try_from(index)
+                .ok()
+                .and_then(|index| list.get(index))
+                .or(list.last())
+                .copied()
+                .unwrap_or(1),
+            Ramp::Step { start, step, max } => {
+                start.saturating_add(step.saturating_mul(index)).min(*max)
+            }
+        }
+    }
+
+    /// What makes the ramp invalid, if anything.
+    pub fn problem(&self) -> Option<String> {
+        let range = 1..=MAX_DURATION_MINUTES;
+        match self {
+            Ramp::List(list) if list.is_empty() => {
+                Some("must list at least a duration".to_string())
+            }
+            Ramp::List(list) => {
+                list.iter()
+                    .find(|minutes| !range.contains(minutes))
+                    .map(|minutes| {
+                        format!(
+                            "durations must be from 1 to {} minutes, got {}",
+                            MAX_DURATION_MINUTES, minutes
+                        )
+                    })
+            }
+            Ramp::Step { start, max, .. }
+                if !range.contains(start) || !range.contains(max) || max < start =>
+            {
+                Some(format!(
+                    "must go from 1 minute up to {} minutes at most, got {} to {}",
+                    MAX_DURATION_MINUTES, start, max
+                ))
+            }
+            Ramp::Step { .. } => None,
+        }
+    }
+}
+
+impl FromStr for Ramp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "must be durations such as 15,20,25 or 15+5..40, got `{}`",
+                s
+            )
+        };
+        let number = |part: &str| part.trim().parse::<u64>().map_err(|_| invalid());
+        if let Some((start, rest)) = s.split_once('+') {
+            let (step, max) = rest.split_once("..").ok_or_else(invalid)?;
+            return Ok(Ramp::Step {
+                start: number(start)?,
+                step: number(step)?,
+                max: number(max)?,
+            });
+        }
+        s.split(',')
+            .map(number)
+            .collect::<Result<_, _>>()
+            .map(Ramp::List)
+    }
+}
+
+/// The ramp in the syntax of the configuration file.
+impl fmt::Display for Ramp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ramp::List(list) => {
+                let list: Vec<String> = list.iter().map(u64::to_string).collect();
+                write!(f, "[{}]", list.join(", "))
+            }
+            Ramp::Step { start, step, max } => {
+                write!(f, "{{ start = {}, step = {}, max = {} }}", start, step, max)
+            }
+        }
+    }
+}
+
+/// A session of a custom sequence: a built-in one, or the name of a `[sessions.<name>]` table.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum Step {
+    Work,
+    Short,
+    Long,
+    Custom(String),
+}
+
+impl From<String> for Step {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "work" => Step::Work,
+            "short" => Step::Short,
+            "long" => Step::Long,
+            _ => Step::Custom(name),
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Work => write!(f, "work"),
+            Step::Short => write!(f, "short"),
+            Step::Long => write!(f, "long"),
+            Step::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// What makes the custom sequence `steps` invalid, if anything. Custom sessions may count as work,
+/// which only the effective configuration tells.
+pub fn sequence_problem(steps: &[Step]) -> Option<String> {
+    if !steps
+        .iter()
+        .any(|step| matches!(step, Step::Work | Step::Custom(_)))
+    {
+        Some("must hold at least a work session".to_string())
+    } else if steps.len() > MAX_SEQUENCE {
+        Some(format!(
+            "cannot exceed {} sessions, got {}",
+            MAX_SEQUENCE,
+            steps.len()
+        ))
+    } else {
+        None
+    }
+}
+
+/// A `[sessions.<name>]` table of the configuration file, a session of its own named in the
+/// sequences, e.g. a review.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// How the banner and the progress bar name the session.
+    pub label: String,
+    pub minutes: u64,
+    /// What the session counts as in the history and the statistics.
+    #[serde(default = "SessionConfig::default_counts_as")]
+    pub counts_as: SessionKind,
+    #[serde(default)]
+    pub color: Color,
+}
+
+impl SessionConfig {
+    fn default_counts_as() -> SessionKind {
+        SessionKind::Work
+    }
+}
+
+/// A stage of the breaks, e.g. a minute with the eyes closed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stage {
+    pub label: String,
+    pub minutes: u64,
+}
+
+/// The `[stages]` table of the configuration file: the stages the breaks go through, in order,
+/// the last one lasting until the end of the break.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct BreakStages {
+    pub short_break: Vec<Stage>,
+    pub long_break: Vec<Stage>,
+}
+
+impl BreakStages {
+    /// The stages of the sessions counting as `kind`, none for the work sessions.
+    pub fn of(&self, kind: SessionKind) -> &[Stage] {
+        match kind {
+            SessionKind::Work => &[],
+            SessionKind::ShortBreak => &self.short_break,
+            SessionKind::LongBreak => &self.long_break,
+        }
+    }
+}
+
+/// Settings shared between the controller loop and the command dispatcher.
+pub type SharedSettings = Arc<Mutex<Settings>>;
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub work_duration: u64,
+    /// Work durations of the cycles, replacing `work_duration`.
+    pub ramp: Option<Ramp>,
+    pub short_break: u64,
+    pub long_break: u64,
+    /// How much the breaks vary around their duration.
+    pub break_jitter: Jitter,
+    /// Moments of the sessions at which the time left is announced.
+    pub notify_at: Vec<Checkpoint>,
+    pub cycles: u64,
+    /// Sessions of a set, repeated, replacing the cycles followed by a long break.
+    pub sequence: Option<Vec<Step>>,
+    /// Custom sessions of the sequence, by name.
+    pub sessions: BTreeMap<String, SessionConfig>,
+    /// Stages the breaks go through.
+    pub stages: BreakStages,
+    pub no_sound: bool,
+    /// Name of the audio output device, the default one when `None`.
+    pub audio_device: Option<String>,
+    pub sounds: SoundsConfig,
+    /// Vibrations of the cues, alongside the sounds.
+    pub vibrations: Option<VibrationsConfig>,
+    pub no_notify: bool,
+    pub low_power: bool,
+    /// Whether the end of each session rings until acknowledged.
+    pub require_ack: bool,
+    /// Whether pausing with [p] asks for the reason of the pause.
+    pub ask_pause_reason: bool,
+    /// Whether each work session starts asking for its intent.
+    pub ask_intent: bool,
+    /// Whether the task is hidden behind a generic label, e.g. while screen-sharing.
+    pub private: bool,
+    /// What the work sessions are about, given with `--task`.
+    pub task: Option<String>,
+    pub theme: Theme,
+    /// What the progress bar of the work sessions shows.
+    pub display: DisplayMode,
+    /// Seconds to wait after announcing a session before starting it.
+    pub transition_pause: u64,
+    /// Length of a minute of the sessions, a second in the tutorial.
+    pub minute: Duration,
+    /// Pomodoros to complete in a day.
+    pub daily_goal: u64,
+    /// Window in which the daily goal is to be reached.
+    pub workday_start: TimeOfDay,
+    pub workday_end: TimeOfDay,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            work_duration: 25,
+            ramp: None,
+            short_break: 5,
+            long_break: 15,
+            break_jitter: Jitter::default(),
+            notify_at: Vec::new(),
+            cycles: 4,
+            sequence: None,
+            sessions: BTreeMap::new(),
+            stages: BreakStages::default(),
+            no_sound: false,
+            audio_device: None,
+            sounds: SoundsConfig::default(),
+            vibrations: None,
+            no_notify: false,
+            low_power: false,
+            require_ack: false,
+            ask_pause_reason: false,
+            ask_intent: false,
+            private: false,
+            task: None,
+            theme: Theme::default(),
+            display: DisplayMode::default(),
+            transition_pause: 0,
+            minute: MINUTE,
+            daily_goal: 8,
+            workday_start: TimeOfDay::new(9, 0),
+            workday_end: TimeOfDay::new(17, 0),
+        }
+    }
+}
+
+/// A single entry of the runtime settings menu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingField {
+    WorkDuration,
+    ShortBreak,
+    LongBreak,
+    Cycles,
+    Sound,
+    Notifications,
+    Private,
+}
+
+impl SettingField {
+    pub const ALL: [SettingField; 7] = [
+        SettingField::WorkDuration,
+        SettingField::ShortBreak,
+        SettingField::LongBreak,
+        SettingField::Cycles,
+        SettingField::Sound,
+        SettingField::Notifications,
+        SettingField::Private,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingField::WorkDuration => "Work duration",
+            SettingField::ShortBreak => "Short break",
+            SettingField::LongBreak => "Long break",
+            SettingField::Cycles => "Cycles",
+            SettingField::Sound => "Sound",
+            SettingField::Notifications => "Notifications",
+            SettingField::Private => "Private",
+        }
+    }
+}
+
+impl Settings {
+    pub fn into_shared(self) -> SharedSettings {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// The work duration of `cycle`, in minutes.
+    pub fn work_minutes(&self, cycle: u64) -> u64 {
+        self.ramp
+            .as_ref()
+            .map_or(self.work_duration, |ramp| ramp.minutes(cycle))
+    }
+
+    /// The sessions of a set: the custom sequence, or the cycles of work each followed by a short
+    /// break, the last one by a long break.
+    pub fn sequence(&self) -> Vec<Step> {
+        self.sequence.clone().unwrap_or_else(|| {
+            let mut steps: Vec<Step> = (1..self.cycles.max(1))
+                .flat_map(|_| [Step::Work, Step::Short])
+                .collect();
+            steps.extend([Step::Work, Step::Long]);
+            steps
+        })
+    }
+
+    /// The session `step` stands for, a custom one being a work session when it is not declared.
+    pub fn session_type(&self, step: &Step) -> SessionType {
+        match step {
+            Step::Work => SessionType::work(),
+            Step::Short => SessionType::short_break(),
+            Step::Long => SessionType::long_break(),
+            Step::Custom(name) => {
+                self.sessions
+                    .get(name)
+                    .map_or_else(SessionType::work, |session| {
+                        SessionType::custom(
+                            session.counts_as,
+                            &session.label,
+                            session.color,
+                            session.minutes,
+                        )
+                    })
+            }
+        }
+    }
+
+    /// The number of work sessions in a set, custom ones counting as work included.
+    pub fn cycles_per_set(&self) -> u64 {
+        match &self.sequence {
+            Some(steps) => steps
+                .iter()
+                .filter(|step| self.session_type(step).is_work())
+                .count() as u64,
+            None => self.cycles,
+        }
+    }
+
+    /// The task as the progress bar and the notifications show it: a generic label in private
+    /// mode, so that a shared screen does not give it away.
+    pub fn shown_task(&self) -> Option<&str> {
+        let task = self.task.as_deref()?;
+        Some(if self.private { PRIVATE_TASK } else { task })
+    }
+
+    /// The work duration as shown to the user, e.g. `25 min` or `15-30 min ramp`.
+    pub fn work_description(&self) -> String {
+        match &self.ramp {
+            None => format!("{} min", self.work_duration),
+            Some(ramp) => format!(
+                "{}-{} min ramp",
+                ramp.minutes(1),
+                ramp.minutes(self.cycles_per_set())
+            ),
+        }
+    }
+
+    /// Increases (`delta > 0`) or decreases (`delta < 0`) the given field. Durations and cycles stay
+    /// within the values accepted on the command line, the sound, notification and private settings are
+    /// toggled whatever the direction. Adjusting the work duration turns the ramp off, and adjusting the
+    /// cycles the custom sequence.
+    pub fn adjust(&mut self, field: SettingField, delta: i64) {
+        let step = |value: u64, max: u64| value.saturating_add_signed(delta).clamp(1, max);
+        match field {
+            SettingField::WorkDuration => {
+                self.ramp = None;
+                self.work_duration = step(self.work_duration, MAX_DURATION_MINUTES)
+            }
+            SettingField::ShortBreak => {
+                self.short_break = step(self.short_break, MAX_DURATION_MINUTES)
+            }
+            SettingField::LongBreak => {
+                self.long_break = step(self.long_break, MAX_DURATION_MINUTES)
+            }
+            SettingField::Cycles => {
+                self.cycles = step(self.cycles_per_set(), MAX_CYCLES);
+                self.sequence = None;
+            }
+            SettingField::Sound => self.no_sound = !self.no_sound,
+            SettingField::Notifications => self.no_notify = !self.no_notify,
+            SettingField::Private => self.private = !self.private,
+        }
+    }
+
+    pub fn value(&self, field: SettingField) -> String {
+        match field {
+            SettingField::WorkDuration => self.work_description(),
+            SettingField::ShortBreak => format!("{} min", self.short_break),
+            SettingField::LongBreak => format!("{} min", self.long_break),
+            SettingField::Cycles if self.sequence.is_some() => {
+                format!("{} (sequence)", self.cycles_per_set())
+            }
+            SettingField::Cycles => self.cycles.to_string(),
+            SettingField::Sound => (if self.no_sound { "off" } else { "on" }).to_string(),
+            SettingField::Notifications => (if self.no_notify { "off" } else { "on" }).to_string(),
+            SettingField::Private => (if self.private { "on" } else { "off" }).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramps_the_work_duration_up() {
+        let list: Ramp = "15, 20,25".parse().unwrap();
+        assert_eq!(list, Ramp::List(vec![15, 20, 25]));
+        let minutes: Vec<u64> = (1..=4).map(|cycle| list.minutes(cycle)).collect();
+        assert_eq!(minutes, [15, 20, 25, 25]);
+
+        let step: Ramp = "15+10..40".parse().unwrap();
+        assert_eq!(step.to_string(), "{ start = 15, step = 10, max = 40 }");
+        let minutes: Vec<u64> = (1..=5).map(|cycle| step.minutes(cycle)).collect();
+        assert_eq!(minutes, [15, 25, 35, 40, 40]);
+
+        assert!("15+5".parse::<Ramp>().is_err());
+        assert!("15,,20".parse::<Ramp>().is_err());
+        assert!(Ramp::List(vec![15, 0]).problem().is_some());
+        assert!("40+5..15".parse::<Ramp>().unwrap().problem().is_some());
+        assert_eq!(step.problem(), None);
+    }
+
+    #[test]
+    fn reads_the_checkpoints() {
+        let half: Checkpoint = " 50% ".parse().unwrap();
+        assert_eq!(
+            half.left(Duration::from_secs(1500)),
+            Duration::from_secs(750)
+        );
+        let checkpoints: Vec<String> = ["5m", "90s", "2"]
+            .iter()
+            .map(|checkpoint| checkpoint.parse::<Checkpoint>().unwrap().to_string())
+            .collect();
+        assert_eq!(checkpoints, ["5m", "90s", "2m"]);
+        assert!("half".parse::<Checkpoint>().is_err());
+        assert!("100%".parse::<Checkpoint>().unwrap().problem().is_some());
+        assert!("0s".parse::<Checkpoint>().unwrap().problem().is_some());
+        assert_eq!(half.problem(), None);
+    }
+
+    #[test]
+    fn custom_sessions_count_as_declared() {
+        let steps: Vec<Step> = toml::from_str::<BTreeMap<String, Vec<Step>>>(
+            "sequence = [\"work\", \"review\", \"long\"]",
+        )
+        .unwrap()
+        .remove("sequence")
+        .unwrap();
+        assert_eq!(
+            steps,
+            [Step::Work, Step::Custom("review".to_string()), Step::Long]
+        );
+        assert_eq!(steps[1].to_string(), "review");
+
+        let mut settings = Settings {
+            sequence: Some(steps),
+            ..Settings::default()
+        };
+        assert_eq!(settings.cycles_per_set(), 2);
+        settings.sessions.insert(
+            "review".to_string(),
+            SessionConfig {
+                label: "Review".to_string(),
+                minutes: 10,
+                counts_as: SessionKind::ShortBreak,
+                color: Color::Magenta,
+            },
+        );
+        assert_eq!(settings.cycles_per_set(), 1);
+        let review = settings.session_type(&Step::Custom("review".to_string()));
+        assert_eq!(
+            (review.label.as_str(), review.minutes),
+            ("Review", Some(10))
+        );
+        assert!(!review.is_work());
+    }
+
+    #[test]
+    fn adjusting_the_work_duration_turns_the_ramp_off() {
+        let mut settings = Settings {
+            ramp: Some(Ramp::List(vec![15, 20, 25, 30])),
+            ..Settings::default()
+        };
+        assert_eq!(settings.value(SettingField::WorkDuration), "15-30 min ramp");
+        assert_eq!(settings.work_minutes(2), 20);
+        settings.adjust(SettingField::WorkDuration, 1);
+        assert_eq!(settings.ramp, None);
+        assert_eq!(settings.work_minutes(2), 26);
+    }
+
+    #[test]
+    fn adjusting_the_cycles_turns_the_sequence_off() {
+        let mut settings = Settings {
+            cycles: 2,
+            ..Settings::default()
+        };
+        assert_eq!(
+            settings.sequence(),
+            [Step::Work, Step::Short, Step::Work, Step::Long]
+        );
+        let sequence = vec![Step::Work, Step::Work, Step::Work, Step::Long];
+        settings.sequence = Some(sequence.clone());
+        assert_eq!(settings.sequence(), sequence);
+        assert_eq!(settings.value(SettingField::Cycles), "3 (sequence)");
+        settings.adjust(SettingField::Cycles, 1);
+        assert_eq!((settings.cycles, settings.sequence), (4, None));
+
+        assert!(sequence_problem(&[Step::Short, Step::Long]).is_some());
+        assert_eq!(sequence_problem(&sequence), None);
+    }
+
+    #[test]
+    fn jitters_the_breaks_within_bounds() {
+        let jitter: Jitter = "2m".parse().unwrap();
+        assert_eq!(jitter, Jitter { seconds: 120 });
+        assert_eq!("90s".parse::<Jitter>().unwrap().to_string(), "90s");
+        assert_eq!("3".parse::<Jitter>().unwrap().to_string(), "3m");
+        assert!("2h".parse::<Jitter>().is_err());
+
+        let five_minutes = Duration::from_secs(5 * 60);
+        assert_eq!(jitter.apply(five_minutes, 72), Duration::from_secs(372));
+        assert_eq!(jitter.apply(five_minutes, -500), Duration::from_secs(180));
+        let long = Jitter { seconds: 600 };
+        assert_eq!(long.apply(five_minutes, -600), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn private_mode_hides_the_task() {
+        let mut settings = Settings {
+            task: Some("Acme merger memo".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(settings.shown_task(), Some("Acme merger memo"));
+        settings.adjust(SettingField::Private, 1);
+        assert_eq!(settings.value(SettingField::Private), "on");
+        assert_eq!(settings.shown_task(), Some(PRIVATE_TASK));
+        settings.task = None;
+        assert_eq!(settings.shown_task(), None);
+    }
+}
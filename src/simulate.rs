@@ -0,0 +1,290 @@
+// filepath: src/simulate.rs
+//! Module fast-forwarding a Pomodoro timer through workdays on a simulated clock, to check what a
+//! schedule configuration leads to before relying on it: `rustodoro simulate --days 5 --pattern
+//! config.toml` tells for each day how many sessions run, where the long breaks fall and which
+//! alarms the quiet hours hold back.
+//!
+//! Nobody presses a key during a simulated day: each session runs to its end, the next one starting
+//! right away, from the start of the workday to its end.
+use std::{io, path::Path, sync::Arc, time::Duration};
+
+use chrono::{Datelike, Local, NaiveDate, NaiveTime, TimeDelta};
+use rustodoro_client::protocol::SessionKind;
+use tokio::time::Instant;
+
+use crate::{
+    AppError, SessionType,
+    config::{self, Cli, ConfigError, QuietConfig, ScheduleConfig},
+    events::{Event, EventBus, SessionOutcome},
+    journal::Script,
+    pace::TimeOfDay,
+    quiet,
+    scheduler::Scheduler,
+    scripting::Schedule,
+    settings::Settings,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SimulateError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("cannot start the simulated clock: {0}")]
+    Runtime(#[from] io::Error),
+    #[error(transparent)]
+    App(#[from] AppError),
+}
+
+/// What happened during a simulated day.
+#[derive(Debug, Default, PartialEq)]
+struct Day {
+    pomodoros: u64,
+    work: Duration,
+    short_breaks: u64,
+    /// Start of each long break.
+    long_breaks: Vec<NaiveTime>,
+    goal_reached: Option<NaiveTime>,
+    /// Ends of the sessions whose alarm the quiet hours hold back.
+    held_back: Vec<NaiveTime>,
+}
+
+/// Length of the workday, which ends the next day when its end comes first.
+fn workday(settings: &Settings) -> Duration {
+    let length = settings.workday_end.0 - settings.workday_start.0;
+    let length = if length > TimeDelta::zero() {
+        length
+    } else {
+        length + TimeDelta::days(1)
+    };
+    length.to_std().unwrap_or_default()
+}
+
+/// Runs the engine through a workday on the paused clock, and returns the events with their time
+/// since the start of the day.
+async fn run_day(
+    settings: Settings,
+    schedule: Option<ScheduleConfig>,
+) -> Result<Vec<(Duration, Event)>, AppError> {
+    let script = Script::new().quit_at(workday(&settings));
+    let settings = Settings {
+        // Nobody is there to hear the alarms, nor to acknowledge them.
+        no_sound: true,
+        no_notify: true,
+        require_ack: false,
+        // Only the ticks are spared.
+        low_power: true,
+        ..settings
+    };
+    let start = Instant::now();
+    let events = Arc::new(EventBus::new());
+    let subscription = events.subscribe_timed();
+    let mut scheduler = Scheduler::new(script.spawn(), events, settings.into_shared())?;
+    if let Some(config) = schedule {
+        scheduler = scheduler.with_schedule(Schedule::new(config));
+    }
+    scheduler.run().await?;
+    if let Some(e) = scheduler.schedule_failure() {
+        eprintln!("warning: {}", e);
+    }
+    drop(scheduler);
+    Ok(subscription
+        .try_iter()
+        .filter(|(_, event)| !matches!(event, Event::Tick { .. }))
+        .map(|(at, event)| (at - start, event))
+        .collect())
+}
+
+/// Sums up the `events` of a day starting at `start`.
+fn tally(
+    events: &[(Duration, Event)],
+    start: NaiveTime,
+    daily_goal: u64,
+    quiet: Option<&QuietConfig>,
+) -> Day {
+    let time = |at: Duration| start + TimeDelta::from_std(at).unwrap_or_default();
+    let quiet_hours = quiet.and_then(|quiet| Some((quiet.start?, quiet.end?)));
+    let mut day = Day::default();
+    for (at, event) in events {
+        match event {
+            Event::SessionStarted {
+                session:
+                    SessionType {
+                        kind: SessionKind::LongBreak,
+                        ..
+                    },
+                ..
+            } => day.long_breaks.push(time(*at)),
+            Event::SessionEnded {
+                session,
+                elapsed,
+                outcome: SessionOutcome::Completed,
+            } => {
+                match session.kind {
+                    SessionKind::Work => {
+                        day.pomodoros += 1;
+                        day.work += *elapsed;
+                        if day.pomodoros == daily_goal {
+                            day.goal_reached = Some(time(*at));
+                        }
+                    }
+                    SessionKind::ShortBreak => day.short_breaks += 1,
+                    SessionKind::LongBreak => {}
+                }
+                if quiet_hours.is_some_and(|(start, end)| quiet::in_hours(start, end, time(*at))) {
+                    day.held_back.push(time(*at));
+                }
+            }
+            _ => {}
+        }
+    }
+    day
+}
+
+fn hours(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h{:02}m", minutes / 60, minutes % 60)
+}
+
+fn times(times: &[NaiveTime]) -> String {
+    times
+        .iter()
+        .map(|time| TimeOfDay(*time).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Describes `day`, a day of `date`, on a few lines.
+fn report(date: NaiveDate, day: &Day, daily_goal: u64) -> String {
+    let mut report = format!(
+        "{}: {} pomodoros ({} of work), {} short breaks, ",
+        date.format("%a %Y-%m-%d"),
+        day.pomodoros,
+        hours(day.work),
+        day.short_breaks
+    );
+    if day.long_breaks.is_empty() {
+        report.push_str("no long break\n");
+    } else {
+        report.push_str(&format!("long breaks at {}\n", times(&day.long_breaks)));
+    }
+    match day.goal_reached {
+        Some(time) => report.push_str(&format!(
+            "  daily goal of {} reached at {}\n",
+            daily_goal,
+            TimeOfDay(time)
+        )),
+        None if daily_goal > 0 => {
+            report.push_str(&format!("  daily goal of {} not reached\n", daily_goal))
+        }
+        None => {}
+    }
+    if !day.held_back.is_empty() {
+        report.push_str(&format!(
+            "  quiet hours hold back the alarms at {}\n",
+            times(&day.held_back)
+        ));
+    }
+    report
+}
+
+fn simulate(cli: &Cli, days: u64, pattern: Option<&Path>) -> Result<(), SimulateError> {
+    let file = match pattern {
+        Some(path) => Some((path.to_path_buf(), true)),
+        None => config::config_path(cli),
+    };
+    let today = Local::now().date_naive();
+    let effective = config::resolve_day(cli, file.clone(), today.weekday())?;
+    let settings = effective.settings();
+    let quiet = effective.quiet.value;
+    let schedule = effective.schedule.value;
+    println!(
+        "Simulating {} workdays from {} to {}.",
+        days, settings.workday_start, settings.workday_end
+    );
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()?;
+    let mut reports = Vec::new();
+    let (mut pomodoros, mut work) = (0, Duration::ZERO);
+    for (date, _) in today.iter_days().zip(0..days) {
+        // Each day follows its profile from the `[weekdays]` tables.
+        let settings = config::resolve_day(cli, file.clone(), date.weekday())?.settings();
+        let events = runtime.block_on(run_day(settings.clone(), schedule.clone()))?;
+        let day = tally(
+            &events,
+            settings.workday_start.0,
+            settings.daily_goal,
+            quiet.as_ref(),
+        );
+        pomodoros += day.pomodoros;
+        work += day.work;
+        reports.push(report(date, &day, settings.daily_goal));
+    }
+    // The banners of the sessions come first, the report standing out after them.
+    println!();
+    for report in reports {
+        print!("{}", report);
+    }
+    println!(
+        "Over {} days: {} pomodoros, {} of work.",
+        days,
+        pomodoros,
+        hours(work)
+    );
+    Ok(())
+}
+
+/// Runs the `simulate` subcommand and returns the process exit code.
+pub fn run_command(cli: &Cli, days: u64, pattern: Option<&Path>) -> i32 {
+    match simulate(cli, days, pattern) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn a_default_day_has_three_sets_and_a_half() {
+        let settings = Settings::default();
+        let events = run_day(settings.clone(), None).await.unwrap();
+        let day = tally(&events, settings.workday_start.0, settings.daily_goal, None);
+        // Sets of 4 × 25 min of work, 3 × 5 min short breaks and 15 min of long break, from 09:00
+        // to 17:00: 3 sets, then 3 pomodoros with their short breaks.
+        assert_eq!(day.pomodoros, 15);
+        assert_eq!(day.work, Duration::from_secs(15 * 25 * 60));
+        assert_eq!(day.short_breaks, 12);
+        assert_eq!(times(&day.long_breaks), "10:55, 13:05, 15:15");
+        assert_eq!(day.goal_reached, Some(TimeOfDay::new(13, 5).0));
+        assert!(day.held_back.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_quiet_hours_hold_back_the_late_alarms() {
+        let settings = Settings {
+            workday_start: TimeOfDay::new(21, 0),
+            workday_end: TimeOfDay::new(23, 0),
+            ..Settings::default()
+        };
+        let quiet = QuietConfig {
+            start: Some(TimeOfDay::new(22, 0)),
+            end: Some(TimeOfDay::new(8, 0)),
+            apps: Vec::new(),
+            fullscreen: false,
+        };
+        let events = run_day(settings.clone(), None).await.unwrap();
+        let day = tally(&events, settings.workday_start.0, 0, Some(&quiet));
+        assert_eq!(times(&day.held_back), "22:00, 22:25, 22:30, 22:55");
+        let report = report(NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(), &day, 0);
+        assert_eq!(
+            report,
+            "Mon 2026-10-19: 4 pomodoros (1h40m of work), 3 short breaks, long breaks at 22:55\n  \
+             quiet hours hold back the alarms at 22:00, 22:25, 22:30, 22:55\n"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// filepath: src/sounds.rs
+//! Module mapping the moments of a Pomodoro timer application to sounds: each cue of the `[sounds]`
+//! table plays a built-in tone, an audio file, or nothing. The sounds can fade in rather than
+//! start at full volume, and the end of the breaks can ring again, louder each time, until
+//! acknowledged.
+#[cfg(feature = "audio")]
+use std::fs::File;
+use std::{fmt, path::PathBuf, time::Duration};
+
+#[cfg(feature = "audio")]
+use rodio::{
+    Decoder, Sink,
+    source::{SineWave, Source},
+};
+use serde::Deserialize;
+use tokio::time::Instant;
+
+#[cfg(feature = "audio")]
+use crate::audio::AudioError;
+
+/// Longest fade-in accepted, in seconds.
+pub const MAX_FADE_IN: u64 = 10;
+/// Shortest and longest intervals accepted between two repeats, in seconds.
+pub const MIN_REPEAT: u64 = 10;
+pub const MAX_REPEAT: u64 = 60 * 60;
+/// Number of repeats played before giving up on the acknowledgment.
+const MAX_REPEATS: u32 = 5;
+/// Volume added by each repeat, up to twice the volume of the first sound.
+const REPEAT_STEP: f32 = 0.25;
+const MAX_REPEAT_VOLUME: f32 = 2.0;
+
+/// A moment of the run a sound can be played for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Cue {
+    SessionStart,
+    /// A checkpoint of `notify_at` was reached.
+    Checkpoint,
+    TenSecondsLeft,
+    WorkEnd,
+    BreakEnd,
+    /// The work session completing the daily goal ended.
+    GoalReached,
+}
+
+impl Cue {
+    pub const ALL: [Cue; 6] = [
+        Cue::SessionStart,
+        Cue::Checkpoint,
+        Cue::TenSecondsLeft,
+        Cue::WorkEnd,
+        Cue::BreakEnd,
+        Cue::GoalReached,
+    ];
+
+    /// Name of the cue in the configuration file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cue::SessionStart => "session_start",
+            Cue::Checkpoint => "checkpoint",
+            Cue::TenSecondsLeft => "ten_seconds_left",
+            Cue::WorkEnd => "work_end",
+            Cue::BreakEnd => "break_end",
+            Cue::GoalReached => "goal_reached",
+        }
+    }
+}
+
+/// A sound generated by the timer itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    /// The short beep ending the sessions.
+    Beep,
+    /// Two rising notes.
+    Chime,
+    /// A high note dying away.
+    Bell,
+}
+
+impl Tone {
+    const ALL: [Tone; 3] = [Tone::Beep, Tone::Chime, Tone::Bell];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tone::Beep => "beep",
+            Tone::Chime => "chime",
+            Tone::Bell => "bell",
+        }
+    }
+
+    /// The tone as a single source, so that it fades in as a whole.
+    #[cfg(feature = "audio")]
+    fn source(&self) -> Box<dyn Source + Send> {
+        let note = |frequency: f32, seconds: f32| {
+            SineWave::new(frequency)
+                .take_duration(Duration::from_secs_f32(seconds))
+                .amplify(0.20)
+        };
+        match self {
+            Tone::Beep => Box::new(note(440.0, 0.25)),
+            Tone::Chime => Box::new(
+                note(660.0, 0.15).mix(note(880.0, 0.30).delay(Duration::from_secs_f32(0.15))),
+            ),
+            Tone::Bell => Box::new(note(1320.0, 0.8).fade_out(Duration::from_secs_f32(0.8))),
+        }
+    }
+}
+
+/// What a cue plays, written `"none"`, the name of a tone, or the path to an audio file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "String")]
+pub enum Sound {
+    None,
+    Tone(Tone),
+    File(PathBuf),
+}
+
+impl From<String> for Sound {
+    fn from(value: String) -> Self {
+        let trimmed = value.trim();
+        if trimmed.is_empty() || trimmed == "none" {
+            return Sound::None;
+        }
+        Tone::ALL
+            .into_iter()
+            .find(|tone| tone.name() == trimmed)
+            .map_or_else(|| Sound::File(PathBuf::from(value)), Sound::Tone)
+    }
+}
+
+impl fmt::Display for Sound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sound::None => write!(f, "none"),
+            Sound::Tone(tone) => write!(f, "{}", tone.name()),
+            Sound::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Sound {
+    /// Starts playing the sound on `sink`, which plays it on its own thread, fading in over
+    /// `fade_in` and at `volume` times its own volume.
+    pub fn play(&self, sink: &Sink, fade_in: Duration, volume: f32) -> Result<(), AudioError> {
+        let source: Box<dyn Source + Send> = match self {
+            Sound::None => return Ok(()),
+            Sound::Tone(tone) => tone.source(),
+            Sound::File(path) => {
+                let failed = |message: String| AudioError::File {
+                    path: path.clone(),
+                    message,
+                };
+                let file = File::open(path).map_err(|e| failed(e.to_string()))?;
+                Box::new(Decoder::try_from(file).map_err(|e| failed(e.to_string()))?)
+            }
+        };
+        if fade_in.is_zero() {
+            sink.append(source.amplify(volume));
+        } else {
+            sink.append(source.fade_in(fade_in).amplify(volume));
+        }
+        Ok(())
+    }
+}
+
+/// The `[sounds]` table of the configuration file. Until configured, only the ends of the
+/// sessions beep, at full volume right away and once.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct SoundsConfig {
+    pub session_start: Sound,
+    pub checkpoint: Sound,
+    pub ten_seconds_left: Sound,
+    pub work_end: Sound,
+    pub break_end: Sound,
+    pub goal_reached: Sound,
+    /// Seconds over which the sounds rise to their volume, 0 to start at full volume.
+    pub fade_in: u64,
+    /// Seconds between the repeats of the end of the breaks until acknowledged, 0 to play it once.
+    pub repeat: u64,
+}
+
+impl Default for SoundsConfig {
+    fn default() -> Self {
+        SoundsConfig {
+            session_start: Sound::None,
+            checkpoint: Sound::None,
+            ten_seconds_left: Sound::None,
+            work_end: Sound::Tone(Tone::Beep),
+            break_end: Sound::Tone(Tone::Beep),
+            goal_reached: Sound::None,
+            fade_in: 0,
+            repeat: 0,
+        }
+    }
+}
+
+impl SoundsConfig {
+    /// The sound of `cue`.
+    pub fn sound(&self, cue: Cue) -> &Sound {
+        match cue {
+            Cue::SessionStart => &self.session_start,
+            Cue::Checkpoint => &self.checkpoint,
+            Cue::TenSecondsLeft => &self.ten_seconds_left,
+            Cue::WorkEnd => &self.work_end,
+            Cue::BreakEnd => &self.break_end,
+            Cue::GoalReached => &self.goal_reached,
+        }
+    }
+
+    pub fn fade_in(&self) -> Duration {
+        Duration::from_secs(self.fade_in)
+    }
+
+    /// The repeats following the sound of `cue` played at `now`, if it repeats.
+    pub fn repeats(&self, cue: Cue, now: Instant) -> Option<Repeats> {
+        (cue == Cue::BreakEnd && self.repeat > 0).then(|| Repeats {
+            cue,
+            every: Duration::from_secs(self.repeat),
+            next: now + Duration::from_secs(self.repeat),
+            played: 0,
+        })
+    }
+}
+
+/// The sound of a cue ringing again until acknowledged, a bit louder each time, and at most
+/// five times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repeats {
+    pub cue: Cue,
+    every: Duration,
+    next: Instant,
+    played: u32,
+}
+
+impl Repeats {
+    /// When the next repeat is due.
+    pub fn next(&self) -> Instant {
+        self.next
+    }
+
+    /// The volume of the repeat due at `now`, relative to the first sound, if one is due. Returns
+    /// `None` too once every repeat was played, which [`Repeats::is_over`] tells.
+    pub fn due(&mut self, now: Instant) -> Option<f32> {
+        if self.is_over() || now < self.next {
+            return None;
+        }
+        self.played += 1;
+        self.next = now + self.every;
+        Some((1.0 + REPEAT_STEP * self.played as f32).min(MAX_REPEAT_VOLUME))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.played >= MAX_REPEATS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_sounds() {
+        let sound = |value: &str| Sound::from(value.to_string());
+        assert_eq!(sound("none"), Sound::None);
+        assert_eq!(sound(" chime "), Sound::Tone(Tone::Chime));
+        assert_eq!(
+            sound("/home/me/gong.ogg"),
+            Sound::File(PathBuf::from("/home/me/gong.ogg"))
+        );
+        assert_eq!(sound("bell").to_string(), "bell");
+    }
+
+    #[test]
+    fn only_the_ends_of_the_sessions_beep_by_default() {
+        let config: SoundsConfig = toml::from_str("goal_reached = \"chime\"").unwrap();
+        let sounds: Vec<String> = Cue::ALL
+            .iter()
+            .map(|cue| format!("{} = {}", cue.name(), config.sound(*cue)))
+            .collect();
+        assert_eq!(
+            sounds,
+            [
+                "session_start = none",
+                "checkpoint = none",
+                "ten_seconds_left = none",
+                "work_end = beep",
+                "break_end = beep",
+                "goal_reached = chime",
+            ]
+        );
+        assert!(toml::from_str::<SoundsConfig>("work_start = \"beep\"").is_err());
+    }
+
+    #[test]
+    fn the_end_of_the_breaks_rings_louder_until_acknowledged() {
+        let config: SoundsConfig = toml::from_str("repeat = 30").unwrap();
+        let start = Instant::now();
+        assert_eq!(config.repeats(Cue::WorkEnd, start), None);
+        let mut repeats = config.repeats(Cue::BreakEnd, start).unwrap();
+        assert_eq!(repeats.due(start + Duration::from_secs(29)), None);
+        let volumes: Vec<Option<f32>> = (1..=6)
+            .map(|repeat| repeats.due(start + Duration::from_secs(30 * repeat)))
+            .collect();
+        assert_eq!(
+            volumes,
+            [
+                Some(1.25),
+                Some(1.5),
+                Some(1.75),
+                Some(2.0),
+                Some(2.0),
+                None
+            ]
+        );
+        assert!(repeats.is_over());
+        assert_eq!(SoundsConfig::default().repeats(Cue::BreakEnd, start), None);
+    }
+}
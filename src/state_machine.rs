@@ -0,0 +1,265 @@
+// filepath: src/state_machine.rs
+//! Module modelling a session of a Pomodoro timer application as an explicit state machine: the
+//! session is announced, runs, may be paused, and finishes with an outcome, after which it may ring
+//! until acknowledged. Each transition is guarded, so that e.g. a work session cannot be skipped
+//! nor a paused one reset.
+use std::fmt;
+
+use rustodoro_client::protocol::SessionKind;
+
+use crate::{SessionType, events::SessionOutcome};
+
+/// Whether a session is for working or resting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+impl From<&SessionType> for Phase {
+    fn from(session: &SessionType) -> Self {
+        match session.kind {
+            SessionKind::Work => Phase::Work,
+            SessionKind::ShortBreak | SessionKind::LongBreak => Phase::Break,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// No session yet.
+    Idle,
+    /// The session is announced, during the transition pause.
+    AwaitingStart(Phase),
+    Running(Phase),
+    Paused(Phase),
+    Finished(SessionOutcome),
+    /// The session completed and rings until acknowledged, the next one waiting for it.
+    Alarming(Phase),
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = |phase: &Phase| match phase {
+            Phase::Work => "work session",
+            Phase::Break => "break",
+        };
+        match self {
+            State::Idle => write!(f, "idle"),
+            State::AwaitingStart(p) => write!(f, "awaiting the start of a {}", phase(p)),
+            State::Running(p) => write!(f, "running a {}", phase(p)),
+            State::Paused(p) => write!(f, "in a paused {}", phase(p)),
+            State::Finished(outcome) => write!(f, "finished ({:?})", outcome),
+            State::Alarming(p) => write!(f, "ringing at the end of a {}", phase(p)),
+        }
+    }
+}
+
+/// What makes the session change state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Announce(Phase),
+    Start,
+    Pause,
+    Resume,
+    Reset,
+    Skip,
+    Complete,
+    /// The user left near the end of the work session.
+    FinishEarly,
+    /// The user moved to another session of the set.
+    Jump,
+    Alarm(Phase),
+    Acknowledge,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[error("cannot {trigger:?} while {state}")]
+pub struct InvalidTransition {
+    pub state: State,
+    pub trigger: Trigger,
+}
+
+/// A change of state, as emitted on the event bus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub from: State,
+    pub trigger: Trigger,
+    pub to: State,
+}
+
+/// The state `trigger` leads to from `state`, if it is allowed there.
+fn next(state: State, trigger: Trigger) -> Option<State> {
+    use State::*;
+
+    match (state, trigger) {
+        (Idle | Finished(_), Trigger::Announce(phase)) => Some(AwaitingStart(phase)),
+        (AwaitingStart(phase), Trigger::Start) => Some(Running(phase)),
+        // Only a break can be skipped, even before it starts.
+        (AwaitingStart(Phase::Break) | Running(Phase::Break), Trigger::Skip) => {
+            Some(Finished(SessionOutcome::Skipped))
+        }
+        // Unlike skipping, jumping leaves a work session too, paused or not.
+        (Running(_) | Paused(_), Trigger::Jump) => Some(Finished(SessionOutcome::Skipped)),
+        (Running(phase), Trigger::Pause) => Some(Paused(phase)),
+        (Paused(phase), Trigger::Resume) => Some(Running(phase)),
+        (Running(phase), Trigger::Reset) => Some(Running(phase)),
+        (Running(_), Trigger::Complete) => Some(Finished(SessionOutcome::Completed)),
+        (Running(Phase::Work), Trigger::FinishEarly) => {
+            Some(Finished(SessionOutcome::FinishedEarly))
+        }
+        (AwaitingStart(_) | Running(_) | Paused(_), Trigger::Quit) => {
+            Some(Finished(SessionOutcome::Interrupted))
+        }
+        (Finished(SessionOutcome::Completed), Trigger::Alarm(phase)) => Some(Alarming(phase)),
+        // The session completed before ringing, quitting doesn't take that back.
+        (Alarming(_), Trigger::Acknowledge | Trigger::Quit) => {
+            Some(Finished(SessionOutcome::Completed))
+        }
+        _ => None,
+    }
+}
+
+/// A session, from its announce to its outcome.
+#[derive(Debug)]
+pub struct Machine {
+    state: State,
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Machine { state: State::Idle }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Moves to the state `trigger` leads to, when its guard allows it.
+    pub fn fire(&mut self, trigger: Trigger) -> Result<Transition, InvalidTransition> {
+        let to = next(self.state, trigger).ok_or(InvalidTransition {
+            state: self.state,
+            trigger,
+        })?;
+        let transition = Transition {
+            from: self.state,
+            trigger,
+            to,
+        };
+        self.state = to;
+        Ok(transition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_a_session_to_its_outcome() {
+        let mut machine = Machine::new();
+        let states: Vec<State> = [
+            Trigger::Announce(Phase::Work),
+            Trigger::Start,
+            Trigger::Pause,
+            Trigger::Resume,
+            Trigger::Reset,
+            Trigger::Complete,
+        ]
+        .into_iter()
+        .map(|trigger| machine.fire(trigger).unwrap().to)
+        .collect();
+        assert_eq!(
+            states,
+            [
+                State::AwaitingStart(Phase::Work),
+                State::Running(Phase::Work),
+                State::Paused(Phase::Work),
+                State::Running(Phase::Work),
+                State::Running(Phase::Work),
+                State::Finished(SessionOutcome::Completed),
+            ]
+        );
+        assert_eq!(
+            machine.fire(Trigger::Announce(Phase::Break)).unwrap(),
+            Transition {
+                from: State::Finished(SessionOutcome::Completed),
+                trigger: Trigger::Announce(Phase::Break),
+                to: State::AwaitingStart(Phase::Break),
+            }
+        );
+        assert_eq!(
+            machine.fire(Trigger::Skip).unwrap().to,
+            State::Finished(SessionOutcome::Skipped)
+        );
+    }
+
+    #[test]
+    fn guards_the_transitions() {
+        let mut machine = Machine::new();
+        assert!(machine.fire(Trigger::Start).is_err());
+        machine.fire(Trigger::Announce(Phase::Work)).unwrap();
+        machine.fire(Trigger::Start).unwrap();
+        assert_eq!(
+            machine.fire(Trigger::Skip).unwrap_err().to_string(),
+            "cannot Skip while running a work session"
+        );
+        let mut resting = Machine::new();
+        resting.fire(Trigger::Announce(Phase::Break)).unwrap();
+        resting.fire(Trigger::Start).unwrap();
+        assert!(resting.fire(Trigger::FinishEarly).is_err());
+        machine.fire(Trigger::Pause).unwrap();
+        for trigger in [Trigger::Pause, Trigger::Reset, Trigger::Complete] {
+            assert!(machine.fire(trigger).is_err());
+        }
+        assert_eq!(machine.state(), State::Paused(Phase::Work));
+        assert_eq!(
+            machine.fire(Trigger::Quit).unwrap().to,
+            State::Finished(SessionOutcome::Interrupted)
+        );
+        assert!(machine.fire(Trigger::Quit).is_err());
+        assert!(machine.fire(Trigger::Alarm(Phase::Work)).is_err());
+    }
+
+    #[test]
+    fn jumps_away_from_work_too() {
+        let mut machine = Machine::new();
+        machine.fire(Trigger::Announce(Phase::Work)).unwrap();
+        assert!(machine.fire(Trigger::Jump).is_err());
+        machine.fire(Trigger::Start).unwrap();
+        machine.fire(Trigger::Pause).unwrap();
+        assert_eq!(
+            machine.fire(Trigger::Jump).unwrap().to,
+            State::Finished(SessionOutcome::Skipped)
+        );
+    }
+
+    #[test]
+    fn completed_sessions_ring_until_acknowledged() {
+        let mut machine = Machine::new();
+        for trigger in [
+            Trigger::Announce(Phase::Break),
+            Trigger::Start,
+            Trigger::Complete,
+        ] {
+            machine.fire(trigger).unwrap();
+        }
+        assert_eq!(
+            machine.fire(Trigger::Alarm(Phase::Break)).unwrap().to,
+            State::Alarming(Phase::Break)
+        );
+        for trigger in [
+            Trigger::Pause,
+            Trigger::Skip,
+            Trigger::Announce(Phase::Work),
+        ] {
+            assert!(machine.fire(trigger).is_err());
+        }
+        assert_eq!(
+            machine.fire(Trigger::Acknowledge).unwrap().to,
+            State::Finished(SessionOutcome::Completed)
+        );
+        assert!(machine.fire(Trigger::Acknowledge).is_err());
+    }
+}
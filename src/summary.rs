@@ -0,0 +1,379 @@
+// filepath: src/summary.rs
+//! Module computing the summary printed when quitting a Pomodoro timer application.
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{
+    compliance::{self, Taken},
+    events::SessionOutcome,
+    history::{SessionKind, SessionRecord},
+    scheduler::RunSummary,
+    types::AppError,
+};
+
+/// What a series of sessions amounts to.
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Completed work sessions.
+    pub pomodoros: u64,
+    /// Time counted by the work sessions, whatever their outcome.
+    pub focused: Duration,
+    /// Breaks which ran until their end.
+    pub breaks_taken: u64,
+    pub breaks_cut_short: u64,
+    pub breaks_skipped: u64,
+    /// Breaks taken, cut short or skipped, those the run ended in not counting.
+    pub breaks: u64,
+    /// Pauses and resets during work sessions.
+    pub interruptions: u64,
+    /// Completed work sessions without any interruption.
+    pub uninterrupted: u64,
+    /// Longest series of uninterrupted pomodoros, the breaks in between not counting.
+    pub longest_streak: u64,
+    /// Sum of the focus scores of the work sessions, and how many were scored.
+    pub focus_total: u64,
+    pub scored: u64,
+}
+
+impl Stats {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a SessionRecord>) -> Self {
+        let mut stats = Stats::default();
+        let mut streak = 0;
+        for record in records {
+            if record.kind != SessionKind::Work {
+                let Some(taken) = compliance::taken(record.outcome, record.elapsed()) else {
+                    continue;
+                };
+                stats.breaks += 1;
+                match taken {
+                    Taken::Full => stats.breaks_taken += 1,
+                    Taken::CutShort => stats.breaks_cut_short += 1,
+                    Taken::Skipped => stats.breaks_skipped += 1,
+                }
+                continue;
+            }
+            stats.focused += record.elapsed();
+            if let Some(focus) = record.focus {
+                stats.focus_total += focus;
+                stats.scored += 1;
+            }
+            let interruptions = u64::from(record.pauses + record.resets);
+            stats.interruptions += interruptions;
+            if record.outcome.is_done() {
+                stats.pomodoros += 1;
+            }
+            if record.outcome.is_done() && interruptions == 0 {
+                stats.uninterrupted += 1;
+                streak += 1;
+                stats.longest_streak = stats.longest_streak.max(streak);
+            } else if record.outcome != SessionOutcome::Interrupted || interruptions > 0 {
+                // Quitting in the middle of a session ends the run, not the streak.
+                streak = 0;
+            }
+        }
+        stats
+    }
+
+    /// Share of the breaks taken until their end, if any break was taken, cut short or skipped.
+    pub fn break_compliance(&self) -> Option<f64> {
+        (self.breaks > 0).then(|| self.breaks_taken as f64 / self.breaks as f64)
+    }
+
+    /// Average focus score of the work sessions, if any was scored.
+    pub fn focus(&self) -> Option<u64> {
+        (self.scored > 0).then(|| (self.focus_total as f64 / self.scored as f64).round() as u64)
+    }
+
+    /// A score out of 100: half of it for reaching the daily goal, a quarter for taking the breaks
+    /// and a quarter for working without interruptions.
+    pub fn score(&self, goal: u64) -> u64 {
+        let progress = (self.pomodoros as f64 / goal.max(1) as f64).min(1.0);
+        let compliance = self.break_compliance().unwrap_or(1.0);
+        let focus = if self.pomodoros == 0 {
+            0.0
+        } else {
+            self.uninterrupted as f64 / self.pomodoros as f64
+        };
+        (50.0 * progress + 25.0 * compliance + 25.0 * focus).round() as u64
+    }
+}
+
+/// The records of the sessions started on `day`.
+pub fn on_day(records: &[SessionRecord], day: NaiveDate) -> impl Iterator<Item = &SessionRecord> {
+    records
+        .iter()
+        .filter(move |record| record.started_at.date_naive() == day)
+}
+
+/// Renders the summary of the run, next to the one of the whole day when the history is available.
+pub fn render(run: &Stats, today: Option<&Stats>, goal: u64) -> String {
+    let columns: Vec<(&str, &Stats)> = [("This run", run)]
+        .into_iter()
+        .chain(today.map(|today| ("Today", today)))
+        .collect();
+    let mut output = format!("{:<18}", "");
+    for (title, _) in &columns {
+        output.push_str(&format!("{:<12}", title));
+    }
+    output = output.trim_end().to_string();
+    output.push('\n');
+    let cells: Vec<[String; 7]> = columns
+        .iter()
+        .map(|(_, stats)| cells(stats, goal))
+        .collect();
+    for (row, label) in ROWS.iter().enumerate() {
+        let mut line = format!("{:<18}", label);
+        for column in &cells {
+            line.push_str(&format!("{:<12}", column[row]));
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+const ROWS: [&str; 7] = [
+    "Pomodoros",
+    "Focused time",
+    "Break compliance",
+    "Interruptions",
+    "Longest streak",
+    "Focus score",
+    "Productivity",
+];
+
+/// The values of one column of the summary, in the order of [`ROWS`].
+fn cells(stats: &Stats, goal: u64) -> [String; 7] {
+    [
+        format!("{} / {}", stats.pomodoros, goal),
+        format_duration(stats.focused),
+        match stats.break_compliance() {
+            Some(compliance) => format!("{:.0}%", compliance * 100.0),
+            None => "-".to_string(),
+        },
+        stats.interruptions.to_string(),
+        stats.longest_streak.to_string(),
+        match stats.focus() {
+            Some(focus) => format!("{}/100", focus),
+            None => "-".to_string(),
+        },
+        format!("{}/100", stats.score(goal)),
+    ]
+}
+
+/// Formats `duration` as hours and minutes, e.g. "1h 05m".
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// Exit code of a run quit before all the cycles of a set were worked through.
+pub const EXIT_QUIT: i32 = 3;
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ending {
+    /// Quit after working through all the cycles at least once.
+    Completed,
+    /// Quit before the end of the first set of cycles.
+    Quit,
+    Error,
+}
+
+/// The summary of a run printed by `--summary-json`, for the wrappers and scripts.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RunReport {
+    pub ending: Ending,
+    pub exit_code: i32,
+    pub work_cycles: u64,
+    pub work_minutes: u64,
+    pub sets: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    pub fn new(result: &Result<RunSummary, AppError>) -> Self {
+        match result {
+            Ok(summary) => {
+                let (ending, exit_code) = if summary.sets > 0 {
+                    (Ending::Completed, 0)
+                } else {
+                    (Ending::Quit, EXIT_QUIT)
+                };
+                RunReport {
+                    ending,
+                    exit_code,
+                    work_cycles: summary.work_cycles,
+                    work_minutes: summary.work_minutes,
+                    sets: summary.sets,
+                    error: None,
+                }
+            }
+            Err(e) => RunReport {
+                ending: Ending::Error,
+                exit_code: e.exit_code(),
+                work_cycles: 0,
+                work_minutes: 0,
+                sets: 0,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+
+    fn record(
+        kind: SessionKind,
+        minutes: u64,
+        outcome: SessionOutcome,
+        pauses: u32,
+    ) -> SessionRecord {
+        SessionRecord {
+            id: String::new(),
+            started_at: Local.with_ymd_and_hms(2026, 3, 14, 9, 0, 0).unwrap(),
+            kind,
+            planned: 25 * 60,
+            elapsed: minutes * 60,
+            outcome,
+            pauses,
+            resets: 0,
+            paused: 0,
+            focus: None,
+            task: None,
+            pause_reasons: Vec::new(),
+            intent: None,
+            review: None,
+            tags: Vec::new(),
+            voided: false,
+            edits: Vec::new(),
+        }
+    }
+
+    fn work(outcome: SessionOutcome, pauses: u32) -> SessionRecord {
+        record(SessionKind::Work, 25, outcome, pauses)
+    }
+
+    fn short_break(outcome: SessionOutcome) -> SessionRecord {
+        record(SessionKind::ShortBreak, 5, outcome, 0)
+    }
+
+    #[test]
+    fn counts_pomodoros_breaks_and_interruptions() {
+        let records = [
+            work(SessionOutcome::Completed, 0),
+            short_break(SessionOutcome::Completed),
+            work(SessionOutcome::Completed, 2),
+            short_break(SessionOutcome::Skipped),
+            record(SessionKind::Work, 10, SessionOutcome::Interrupted, 0),
+        ];
+        let stats = Stats::from_records(&records);
+        assert_eq!(stats.pomodoros, 2);
+        assert_eq!(stats.focused, Duration::from_secs(60 * 60));
+        assert_eq!(stats.break_compliance(), Some(0.5));
+        assert_eq!((stats.breaks_cut_short, stats.breaks_skipped), (1, 0));
+        assert_eq!(stats.interruptions, 2);
+        assert_eq!(stats.uninterrupted, 1);
+        assert_eq!(stats.focus(), None);
+    }
+
+    #[test]
+    fn interruptions_break_the_streak() {
+        let records = [
+            work(SessionOutcome::Completed, 0),
+            work(SessionOutcome::Completed, 1),
+            work(SessionOutcome::Completed, 0),
+            work(SessionOutcome::Completed, 0),
+            work(SessionOutcome::Completed, 0),
+            work(SessionOutcome::Interrupted, 0),
+        ];
+        assert_eq!(Stats::from_records(&records).longest_streak, 3);
+    }
+
+    #[test]
+    fn score_rewards_goal_breaks_and_focus() {
+        let records = [
+            work(SessionOutcome::Completed, 0),
+            short_break(SessionOutcome::Completed),
+            work(SessionOutcome::Completed, 0),
+        ];
+        let stats = Stats::from_records(&records);
+        assert_eq!(stats.score(2), 100);
+        assert_eq!(stats.score(4), 75);
+        assert_eq!(Stats::default().score(8), 25);
+    }
+
+    #[test]
+    fn only_keeps_the_given_day() {
+        let mut yesterday = work(SessionOutcome::Completed, 0);
+        yesterday.started_at = Local.with_ymd_and_hms(2026, 3, 13, 22, 0, 0).unwrap();
+        let records = [yesterday, work(SessionOutcome::Completed, 0)];
+        let day = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap();
+        assert_eq!(on_day(&records, day).count(), 1);
+    }
+
+    #[test]
+    fn averages_the_focus_scores() {
+        let mut records = [
+            work(SessionOutcome::Completed, 0),
+            work(SessionOutcome::Completed, 1),
+            work(SessionOutcome::Completed, 0),
+        ];
+        records[1].focus = Some(90);
+        records[2].focus = Some(75);
+        // The records written before the focus scores are left out.
+        assert_eq!(Stats::from_records(&records).focus(), Some(83));
+    }
+
+    #[test]
+    fn renders_both_columns() {
+        let run = Stats::from_records(&[work(SessionOutcome::Completed, 0)]);
+        let today = Stats {
+            pomodoros: 5,
+            focused: Duration::from_secs(125 * 60),
+            ..Stats::default()
+        };
+        let summary = render(&run, Some(&today), 8);
+        assert!(summary.starts_with(&format!("{:<18}This run    Today\n", "")));
+        assert!(summary.contains("Pomodoros         1 / 8       5 / 8\n"));
+        assert!(summary.contains("Focused time      25m         2h 05m\n"));
+        assert!(summary.contains("Break compliance  -           -\n"));
+        assert!(summary.contains("Focus score       -           -\n"));
+    }
+
+    #[test]
+    fn reports_how_the_run_ended() {
+        let quit = RunSummary {
+            work_cycles: 2,
+            work_minutes: 50,
+            sets: 0,
+        };
+        assert_eq!(
+            serde_json::to_string(&RunReport::new(&Ok(quit))).unwrap(),
+            r#"{"ending":"quit","exit_code":3,"work_cycles":2,"work_minutes":50,"sets":0}"#
+        );
+        let completed = RunReport::new(&Ok(RunSummary {
+            sets: 1,
+            ..RunSummary::default()
+        }));
+        assert_eq!(
+            (completed.ending, completed.exit_code),
+            (Ending::Completed, 0)
+        );
+        let failed = RunReport::new(&Err(AppError::ThreadPanic));
+        assert_eq!((failed.ending, failed.exit_code), (Ending::Error, 1));
+        assert!(failed.error.is_some());
+    }
+}
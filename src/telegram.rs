@@ -0,0 +1,476 @@
+// filepath: src/telegram.rs
+//! Module running the built-in Telegram bot of a Pomodoro timer application: it reports the
+//! session transitions to a chat and forwards the commands sent from that chat to the timer. The
+//! chat can also receive the escalations of the notifications not acknowledged at the timer.
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        mpsc::{self, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustodoro_client::protocol::SessionKind;
+use rustodoro_client::protocol::{Action, Snapshot};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc::WeakUnboundedSender;
+use ureq::Agent;
+
+use crate::{
+    command_line::MAX_EXTEND,
+    config::TelegramConfig,
+    events::{Event, EventBus, SessionOutcome},
+    notifier::{Notification, Notifier, NotifierError},
+    remote::{self, SharedTracker},
+    settings::SharedSettings,
+    today::SharedToday,
+    types::Command,
+};
+
+const API: &str = "https://api.telegram.org";
+/// Seconds Telegram holds a request for updates open when there is none.
+const POLL_TIMEOUT: u64 = 30;
+/// Wait before polling again after a failure, e.g. while the network is down.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Telegram accepts about one message a second and twenty a minute in a chat.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+const MESSAGES_PER_MINUTE: usize = 20;
+const HELP: &str =
+    "Commands: /status, /pause, /resume, /skip, /reset, /extend <minutes>, /task <task>, /jump <n>";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TelegramError {
+    #[error("Telegram request failed: {0}")]
+    Http(#[from] ureq::Error),
+    #[error("Telegram refused the request: {0}")]
+    Api(String),
+}
+
+/// Envelope of every Telegram Bot API response.
+#[derive(Deserialize)]
+struct Reply<T> {
+    ok: bool,
+    #[serde(default)]
+    description: String,
+    result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+struct Bot {
+    agent: Agent,
+    /// API endpoint of the bot, containing its token.
+    base: String,
+    chat_id: i64,
+}
+
+impl Bot {
+    fn call<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        body: serde_json::Value,
+    ) -> Result<T, TelegramError> {
+        let reply: Reply<T> = self
+            .agent
+            .post(format!("{}/{}", self.base, method))
+            .send_json(body)?
+            .body_mut()
+            .read_json()?;
+        match reply.result {
+            Some(result) if reply.ok => Ok(result),
+            _ => Err(TelegramError::Api(reply.description)),
+        }
+    }
+
+    fn updates(&self, offset: i64, timeout: u64) -> Result<Vec<Update>, TelegramError> {
+        self.call(
+            "getUpdates",
+            json!({ "offset": offset, "timeout": timeout, "allowed_updates": ["message"] }),
+        )
+    }
+
+    fn send(&self, text: &str) -> Result<(), TelegramError> {
+        self.call::<serde_json::Value>(
+            "sendMessage",
+            json!({ "chat_id": self.chat_id, "text": text }),
+        )
+        .map(drop)
+    }
+}
+
+/// The chat of the bot, as a notification backend for the escalations.
+pub struct Escalations {
+    outbox: Sender<String>,
+}
+
+impl Notifier for Escalations {
+    fn send(&self, notification: &Notification) -> Result<(), NotifierError> {
+        self.outbox
+            .send(escalation(notification))
+            .map_err(|_| NotifierError {
+                backend: "Telegram",
+                message: "the bot stopped".to_string(),
+            })
+    }
+}
+
+/// The message sending `notification` to the chat again, as it was not acknowledged.
+fn escalation(notification: &Notification) -> String {
+    format!(
+        "🔔 {}, not acknowledged. {}",
+        notification.title, notification.message
+    )
+}
+
+/// Checks the token and starts the bot, which runs as long as the process, returning the
+/// escalations to its chat. The messages the chat sent while the timer was not running are
+/// ignored.
+pub fn start(
+    config: &TelegramConfig,
+    events: &EventBus,
+    commands: WeakUnboundedSender<Command>,
+    settings: SharedSettings,
+    today: SharedToday,
+) -> Result<Escalations, TelegramError> {
+    let agent: Agent = Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(POLL_TIMEOUT + 10)))
+        .build()
+        .into();
+    let bot = Arc::new(Bot {
+        agent,
+        base: format!("{}/bot{}", API, config.token),
+        chat_id: config.chat_id,
+    });
+    let missed = bot.updates(0, 0)?;
+    let offset = missed.last().map_or(0, |update| update.update_id + 1);
+
+    let (outbox, messages) = mpsc::channel::<String>();
+    let sender = Arc::clone(&bot);
+    thread::spawn(move || {
+        let mut limiter = RateLimiter::new(MESSAGES_PER_MINUTE, Duration::from_secs(60));
+        for message in messages {
+            let delay = limiter.delay(Instant::now());
+            thread::sleep(delay);
+            limiter.record(Instant::now());
+            // Failures were already reported by the first request, the timer goes on without
+            // the bot.
+            let _ = sender.send(&message);
+        }
+    });
+
+    let tracker = remote::follow(events, settings, today);
+    let events = events.subscribe();
+    let announcements = outbox.clone();
+    let escalations = Escalations {
+        outbox: outbox.clone(),
+    };
+    thread::spawn(move || {
+        for event in events {
+            if let Some(message) = announce(&event)
+                && announcements.send(message).is_err()
+            {
+                return;
+            }
+        }
+    });
+
+    thread::spawn(move || poll(&bot, offset, &tracker, &commands, &outbox));
+    Ok(escalations)
+}
+
+/// Long-polls the messages of the chat and answers them, until the token gets revoked.
+fn poll(
+    bot: &Bot,
+    mut offset: i64,
+    tracker: &SharedTracker,
+    commands: &WeakUnboundedSender<Command>,
+    outbox: &Sender<String>,
+) {
+    loop {
+        let updates = match bot.updates(offset, POLL_TIMEOUT) {
+            Ok(updates) => updates,
+            Err(TelegramError::Http(ureq::Error::StatusCode(401 | 404))) => return,
+            Err(_) => {
+                thread::sleep(RETRY_DELAY);
+                continue;
+            }
+        };
+        for update in updates {
+            offset = update.update_id + 1;
+            let Some(Message {
+                chat,
+                text: Some(text),
+            }) = update.message
+            else {
+                continue;
+            };
+            // Anybody can find the bot, only its owner's chat controls the timer.
+            if chat.id != bot.chat_id {
+                continue;
+            }
+            let status = remote::snapshot(tracker);
+            let answer = answer(&text, &status, |command| remote::send(commands, command));
+            if outbox.send(answer).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// The action asked by a bot command, e.g. `/skip`, `/skip@rustodoro_bot` in groups or
+/// `/extend 5`.
+fn parse(text: &str) -> Option<Action> {
+    let text = text.trim_start();
+    let word = text.split_whitespace().next()?;
+    let argument = text[word.len()..].trim();
+    let name = word.strip_prefix('/')?;
+    let name = name.split_once('@').map_or(name, |(name, _)| name);
+    match name.to_lowercase().as_str() {
+        "status" => Some(Action::Status),
+        "pause" => Some(Action::Pause),
+        "resume" => Some(Action::Resume),
+        "toggle" => Some(Action::Toggle),
+        "skip" => Some(Action::Skip),
+        "reset" => Some(Action::Reset),
+        "extend" => argument
+            .parse()
+            .ok()
+            .filter(|minutes| (1..=MAX_EXTEND).contains(minutes))
+            .map(|minutes| Action::Extend(minutes * 60)),
+        "task" if !argument.is_empty() => Some(Action::SetTask(argument.to_string())),
+        "jump" => argument.parse().ok().map(Action::Jump),
+        _ => None,
+    }
+}
+
+/// Carries out the command in `text`, sending timer commands with `send`, and returns the answer.
+fn answer(text: &str, status: &Snapshot, send: impl FnOnce(Command) -> bool) -> String {
+    let Some(action) = parse(text) else {
+        return HELP.to_string();
+    };
+    let Some(command) = remote::command(action) else {
+        return remote::describe(status);
+    };
+    let done = match command {
+        Command::Acknowledge => "Acknowledged.",
+        Command::Pause => "Pausing.",
+        Command::Resume => "Resuming.",
+        Command::PauseResume => "Toggling the pause.",
+        Command::Skip | Command::SkipBreaks(_) => "Skipping.",
+        Command::Reset => "Starting the session over.",
+        Command::Extend(_) => "Extending the session.",
+        Command::SetTask(_) => "Changing the task.",
+        Command::Jump(_) => "Moving to that session.",
+        Command::Reveal => "Showing the time left.",
+        Command::PauseReason(_) => "Noting the reason of the pause.",
+        Command::Intent(_) => "Noting the intent of the session.",
+    };
+    if send(command) {
+        done.to_string()
+    } else {
+        "The timer is stopping.".to_string()
+    }
+}
+
+/// The message reporting `event` to the chat, for the session transitions only.
+fn announce(event: &Event) -> Option<String> {
+    match event {
+        Event::SessionStarted {
+            session,
+            cycle,
+            duration,
+        } => {
+            let minutes = duration.as_secs().div_ceil(60);
+            Some(match session.kind {
+                SessionKind::Work => {
+                    format!("🍅 {} {} started, {} min", session, cycle, minutes)
+                }
+                SessionKind::ShortBreak => format!("☕ {} started, {} min", session, minutes),
+                SessionKind::LongBreak => format!("🌴 {} started, {} min", session, minutes),
+            })
+        }
+        Event::SessionEnded {
+            session, outcome, ..
+        } => Some(match outcome {
+            SessionOutcome::Completed => format!("✅ {} completed", session),
+            SessionOutcome::FinishedEarly => format!("✅ {} finished early", session),
+            SessionOutcome::Skipped => format!("⏭ {} skipped", session),
+            SessionOutcome::Interrupted => format!("⏹ {} interrupted", session),
+        }),
+        _ => None,
+    }
+}
+
+/// Spaces the messages out to stay within the limits of Telegram.
+struct RateLimiter {
+    max: usize,
+    period: Duration,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    fn new(max: usize, period: Duration) -> Self {
+        RateLimiter {
+            max,
+            period,
+            sent: VecDeque::new(),
+        }
+    }
+
+    /// How long to wait at `now` before sending the next message.
+    fn delay(&mut self, now: Instant) -> Duration {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= self.period)
+        {
+            self.sent.pop_front();
+        }
+        let spacing = self.sent.back().map_or(Duration::ZERO, |last| {
+            (*last + MIN_INTERVAL).saturating_duration_since(now)
+        });
+        let window = if self.sent.len() < self.max {
+            Duration::ZERO
+        } else {
+            (self.sent[0] + self.period).saturating_duration_since(now)
+        };
+        spacing.max(window)
+    }
+
+    fn record(&mut self, at: Instant) {
+        self.sent.push_back(at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustodoro_client::protocol::{Session, SessionKind, Totals};
+
+    use super::*;
+    use crate::{SessionType, notifier::Trigger};
+
+    #[test]
+    fn parses_bot_commands() {
+        assert_eq!(parse("/skip"), Some(Action::Skip));
+        assert_eq!(parse("/Status@rustodoro_bot please"), Some(Action::Status));
+        assert_eq!(parse("skip"), None);
+        assert_eq!(parse("/start"), None);
+        assert_eq!(parse("/extend 5"), Some(Action::Extend(300)));
+        assert_eq!(parse("/extend"), None);
+        assert_eq!(
+            parse("/task  Write the report"),
+            Some(Action::SetTask("Write the report".to_string()))
+        );
+        assert_eq!(parse("/jump 0"), None);
+    }
+
+    #[test]
+    fn answers_commands() {
+        let status = Snapshot {
+            name: None,
+            session: Some(Session {
+                kind: SessionKind::Work,
+                cycle: 2,
+                cycles: 4,
+                duration_secs: 1500,
+                remaining_secs: 723,
+                paused: true,
+                plan_secs: None,
+            }),
+            totals: Totals {
+                pomodoros: 1,
+                work_secs: 1500,
+                today: 1,
+            },
+        };
+        let reply = answer("/status", &status, |_| unreachable!());
+        assert!(reply.starts_with("Work session 2/4: 12:03 left, paused, ends "));
+        assert!(reply.ends_with(".\n1 pomodoro done so far."));
+        let mut sent = None;
+        let reply = answer("/skip", &status, |command| {
+            sent = Some(command);
+            true
+        });
+        assert_eq!(reply, "Skipping.");
+        assert!(matches!(sent, Some(Command::Skip)));
+        assert_eq!(
+            answer("/pause", &status, |_| false),
+            "The timer is stopping."
+        );
+        assert_eq!(answer("hello", &status, |_| unreachable!()), HELP);
+    }
+
+    #[test]
+    fn announces_session_transitions() {
+        let started = Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: Duration::from_secs(25 * 60),
+        };
+        assert_eq!(
+            announce(&started).as_deref(),
+            Some("🍅 Work session 1 started, 25 min")
+        );
+        let ended = Event::SessionEnded {
+            session: SessionType::short_break(),
+            elapsed: Duration::from_secs(60),
+            outcome: SessionOutcome::Skipped,
+        };
+        assert_eq!(announce(&ended).as_deref(), Some("⏭ Short break skipped"));
+        assert_eq!(announce(&Event::Reset), None);
+        let missed = Notification {
+            trigger: Trigger::BreakEnded,
+            title: "Short break is over".to_string(),
+            message: "Back to work.".to_string(),
+            escalated: true,
+        };
+        assert_eq!(
+            escalation(&missed),
+            "🔔 Short break is over, not acknowledged. Back to work."
+        );
+    }
+
+    #[test]
+    fn reads_updates() {
+        let reply: Reply<Vec<Update>> = serde_json::from_str(
+            r#"{"ok":true,"result":[{"update_id":7,"message":{"message_id":1,"chat":{"id":42,"type":"private"},"text":"/status"}},{"update_id":8,"edited_message":{}}]}"#,
+        )
+        .unwrap();
+        let updates = reply.result.unwrap();
+        assert_eq!(updates[0].message.as_ref().unwrap().chat.id, 42);
+        assert!(updates[1].message.is_none());
+    }
+
+    #[test]
+    fn spaces_messages_out() {
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        let mut limiter = RateLimiter::new(3, second * 60);
+        assert_eq!(limiter.delay(start), Duration::ZERO);
+        limiter.record(start);
+        assert_eq!(limiter.delay(start), second);
+        limiter.record(start + second);
+        limiter.record(start + second * 2);
+        // The window is full until the first message leaves it.
+        assert_eq!(limiter.delay(start + second * 10), second * 50);
+        assert_eq!(limiter.delay(start + second * 60), Duration::ZERO);
+    }
+}
@@ -0,0 +1,65 @@
+// filepath: src/template.rs
+//! Module filling the templates of the configuration file of a Pomodoro timer application, e.g.
+//! the lines of the daily notes or the texts of the notifications: the names between `{{` and
+//! `}}` are replaced by their values.
+
+/// The placeholders of `template`, the names between `{{` and `}}`.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    template
+        .split("{{")
+        .skip(1)
+        .filter_map(|part| part.split_once("}}"))
+        .map(|(name, _)| name.trim())
+        .collect()
+}
+
+/// The first placeholder of `template` which is not among the `known` ones, if any.
+pub fn unknown_placeholder<'a>(template: &'a str, known: &[&str]) -> Option<&'a str> {
+    placeholders(template)
+        .into_iter()
+        .find(|name| !known.contains(name))
+}
+
+/// `template` with its placeholders replaced by their `values`, unknown ones being left as they
+/// are.
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open..].find("}}") else {
+            break;
+        };
+        let name = rest[open + 2..open + close].trim();
+        filled.push_str(&rest[..open]);
+        match values.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) => filled.push_str(value),
+            None => filled.push_str(&rest[open..open + close + 2]),
+        }
+        rest = &rest[open + close + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_the_templates() {
+        let template = "- {{ start }}-{{end}} {{task}} {{unknown}} {{";
+        assert_eq!(placeholders(template), ["start", "end", "task", "unknown"]);
+        assert_eq!(
+            unknown_placeholder(template, &["start", "end", "task"]),
+            Some("unknown")
+        );
+        let values = [
+            ("start", "09:00".to_string()),
+            ("task", "Write".to_string()),
+        ];
+        assert_eq!(
+            fill(template, &values),
+            "- 09:00-{{end}} Write {{unknown}} {{"
+        );
+    }
+}
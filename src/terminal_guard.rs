@@ -0,0 +1,63 @@
+// filepath: src/terminal_guard.rs
+//! Module making sure the terminal is left usable, whether the application ends normally, returns
+//! an error or panics.
+use std::{
+    io::{self, IsTerminal},
+    panic,
+};
+
+use crossterm::{
+    cursor,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste},
+    execute, terminal,
+};
+
+use crate::AppError;
+
+/// Keeps the terminal in raw mode while alive, pasted text coming as a whole rather than as keys,
+/// and restores it when dropped, including during the unwinding of a panic.
+pub struct RawModeGuard {
+    _private: (),
+}
+
+impl RawModeGuard {
+    pub fn enable() -> Result<Self, AppError> {
+        terminal::enable_raw_mode()?;
+        if io::stdout().is_terminal() {
+            execute!(io::stdout(), EnableBracketedPaste)?;
+        }
+        Ok(RawModeGuard { _private: () })
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Restores the terminal to its normal state: raw mode off, cursor visible, no mouse capture nor
+/// bracketed paste and main screen. Failures are ignored as there is nothing more to do about them.
+pub fn restore() {
+    let _ = terminal::disable_raw_mode();
+    if !io::stdout().is_terminal() {
+        return;
+    }
+    let _ = execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        terminal::LeaveAlternateScreen,
+        cursor::Show
+    );
+}
+
+/// Restores the terminal before printing the panic message, so it is both readable and the shell
+/// remains usable whichever thread panicked.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,393 @@
+// filepath: src/testing.rs
+//! Module providing a headless harness for tests, running the whole Pomodoro engine on the paused
+//! Tokio clock and driven by key presses, and recording the emitted events.
+//!
+//! Tests using it run with `#[tokio::test(start_paused = true)]`: time only moves while every task
+//! waits, and jumps straight to the next timer instead of sleeping.
+use std::{sync::Arc, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tokio::time::Instant;
+
+pub use crate::journal::Script;
+use crate::{
+    SessionType,
+    events::{Event, EventBus, SessionOutcome},
+    keymap::{Count, KeyAction, Keymap},
+    scheduler::{RunSummary, Scheduler},
+    settings::Settings,
+    state_machine::State,
+};
+
+pub struct Harness {
+    settings: Settings,
+    script: Script,
+    keymap: Keymap,
+    count: Count,
+}
+
+/// What happened during a simulated run.
+pub struct Recording {
+    pub summary: RunSummary,
+    pub events: Vec<Event>,
+    /// Simulated time at which the run ended, pauses included.
+    pub ended_at: Duration,
+}
+
+impl Harness {
+    /// Prepares a run with the given settings, sound and notifications being always disabled.
+    pub fn new(settings: Settings) -> Self {
+        Harness {
+            settings: Settings {
+                no_sound: true,
+                no_notify: true,
+                ..settings
+            },
+            script: Script::new(),
+            keymap: Keymap::default(),
+            count: Count::default(),
+        }
+    }
+
+    /// Simulates pressing `key` at `time`, which must not be before the previous key press. Keys
+    /// which are neither commands, digits of a count nor quit keys are ignored.
+    pub fn press(mut self, time: Duration, key: KeyCode) -> Self {
+        let key_event = KeyEvent::new(key, KeyModifiers::NONE);
+        if let Some(digit) = self.keymap.digit(&key_event) {
+            self.count.push(digit);
+            return self;
+        }
+        let count = self.count.take();
+        if self.keymap.get(&key_event) == Some(&KeyAction::Quit) {
+            self.script = self.script.quit_at(time);
+        } else if let Some(command) = self.keymap.command(&key_event, count) {
+            self.script = self.script.at(time, command);
+        }
+        self
+    }
+
+    /// Runs the engine until a quit key is pressed.
+    pub async fn run(self) -> Recording {
+        let start = Instant::now();
+        let events = Arc::new(EventBus::new());
+        let subscription = events.subscribe();
+        let mut scheduler =
+            Scheduler::new(self.script.spawn(), events, self.settings.into_shared())
+                .expect("cannot create the scheduler");
+        let summary = scheduler.run().await.expect("simulated run failed");
+        Recording {
+            summary,
+            events: subscription.try_iter().collect(),
+            ended_at: start.elapsed(),
+        }
+    }
+}
+
+impl Recording {
+    /// The sessions which ended, with the time counted by their countdown and how they ended.
+    pub fn sessions(&self) -> Vec<(SessionType, Duration, SessionOutcome)> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::SessionEnded {
+                    session,
+                    elapsed,
+                    outcome,
+                } => Some((session.clone(), *elapsed, *outcome)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The events other than ticks, which are too many to be asserted on, and state transitions.
+    pub fn changes(&self) -> Vec<Event> {
+        self.events
+            .iter()
+            .filter(|event| !matches!(event, Event::Tick { .. } | Event::Transition(_)))
+            .cloned()
+            .collect()
+    }
+
+    /// The states the sessions went through, in order.
+    pub fn states(&self) -> Vec<State> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Transition(transition) => Some(transition.to),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Command;
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn settings() -> Settings {
+        Settings::default()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn script_sends_commands_at_their_time() {
+        let start = Instant::now();
+        let mut rx = Script::new()
+            .at(SECOND / 2, Command::Pause)
+            .at(SECOND * 90, Command::Resume)
+            .spawn();
+        assert!(matches!(rx.recv().await, Some(Command::Pause)));
+        assert_eq!(start.elapsed(), SECOND / 2);
+        assert!(matches!(rx.recv().await, Some(Command::Resume)));
+        assert_eq!(start.elapsed(), SECOND * 90);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn script_disconnects_at_quit_time() {
+        let start = Instant::now();
+        let mut rx = Script::new()
+            .at(SECOND * 10, Command::Pause)
+            .quit_at(SECOND * 5)
+            .spawn();
+        assert!(rx.recv().await.is_none());
+        assert_eq!(start.elapsed(), SECOND * 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_during_work_leaves_the_focused_time_unchanged() {
+        let recording = Harness::new(settings())
+            .press(MINUTE * 10, KeyCode::Char('p'))
+            .press(MINUTE * 12, KeyCode::Char('r'))
+            .press(MINUTE * 28, KeyCode::Char('q'))
+            .run()
+            .await;
+        assert_eq!(
+            recording.sessions(),
+            vec![
+                (SessionType::work(), MINUTE * 25, SessionOutcome::Completed),
+                (
+                    SessionType::short_break(),
+                    MINUTE,
+                    SessionOutcome::Interrupted
+                ),
+            ]
+        );
+        assert_eq!(recording.summary.work_minutes, 25);
+        assert_eq!(recording.ended_at, MINUTE * 28);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn emits_transitions_in_order() {
+        let recording = Harness::new(settings())
+            .press(MINUTE, KeyCode::Char(' '))
+            .press(MINUTE * 2, KeyCode::Char(' '))
+            .press(MINUTE * 3, KeyCode::Char('x'))
+            .press(MINUTE * 4, KeyCode::Esc)
+            .run()
+            .await;
+        assert_eq!(
+            recording.changes(),
+            vec![
+                Event::SessionStarted {
+                    session: SessionType::work(),
+                    cycle: 1,
+                    duration: MINUTE * 25,
+                },
+                // 7 more work sessions and their breaks to the daily goal of 8.
+                Event::Planned {
+                    ahead: MINUTE * 220
+                },
+                Event::Paused { elapsed: MINUTE },
+                Event::Resumed {
+                    elapsed: MINUTE,
+                    paused: MINUTE,
+                },
+                Event::Reset,
+                Event::SessionEnded {
+                    session: SessionType::work(),
+                    elapsed: MINUTE,
+                    outcome: SessionOutcome::Interrupted,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_key_ends_the_break_only() {
+        let recording = Harness::new(settings())
+            .press(MINUTE * 5, KeyCode::Char('s'))
+            .press(MINUTE * 26, KeyCode::Char('s'))
+            .press(MINUTE * 27, KeyCode::Char('q'))
+            .run()
+            .await;
+        assert_eq!(
+            recording.sessions(),
+            vec![
+                (SessionType::work(), MINUTE * 25, SessionOutcome::Completed),
+                (SessionType::short_break(), MINUTE, SessionOutcome::Skipped),
+                (SessionType::work(), MINUTE, SessionOutcome::Interrupted),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn late_skip_key_skips_the_upcoming_break() {
+        let recording = Harness::new(settings())
+            .press(MINUTE * 25 - SECOND * 2, KeyCode::Char('s'))
+            .press(MINUTE * 26, KeyCode::Char('q'))
+            .run()
+            .await;
+        assert_eq!(
+            recording.sessions(),
+            vec![
+                (SessionType::work(), MINUTE * 25, SessionOutcome::Completed),
+                (
+                    SessionType::short_break(),
+                    Duration::ZERO,
+                    SessionOutcome::Skipped
+                ),
+                (SessionType::work(), MINUTE, SessionOutcome::Interrupted),
+            ]
+        );
+        assert!(recording.changes().contains(&Event::NextBreakSkipped));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_key_after_a_count_skips_as_many_breaks() {
+        let recording = Harness::new(settings())
+            .press(MINUTE, KeyCode::Char('2'))
+            .press(MINUTE, KeyCode::Char('s'))
+            .press(MINUTE * 51, KeyCode::Char('3'))
+            .press(MINUTE * 51, KeyCode::Char('+'))
+            .press(MINUTE * 80, KeyCode::Char('q'))
+            .run()
+            .await;
+        assert_eq!(
+            recording.sessions(),
+            vec![
+                (SessionType::work(), MINUTE * 25, SessionOutcome::Completed),
+                (
+                    SessionType::short_break(),
+                    Duration::ZERO,
+                    SessionOutcome::Skipped
+                ),
+                (SessionType::work(), MINUTE * 25, SessionOutcome::Completed),
+                (
+                    SessionType::short_break(),
+                    Duration::ZERO,
+                    SessionOutcome::Skipped
+                ),
+                (SessionType::work(), MINUTE * 28, SessionOutcome::Completed),
+                (
+                    SessionType::short_break(),
+                    MINUTE * 2,
+                    SessionOutcome::Interrupted
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn records_the_states_of_the_sessions() {
+        use crate::state_machine::Phase;
+
+        let recording = Harness::new(settings())
+            .press(MINUTE, KeyCode::Char(' '))
+            // Ignored while paused.
+            .press(MINUTE * 2, KeyCode::Char('x'))
+            .press(MINUTE * 3, KeyCode::Char(' '))
+            .press(MINUTE * 28, KeyCode::Char('s'))
+            .press(MINUTE * 29, KeyCode::Char('q'))
+            .run()
+            .await;
+        assert_eq!(
+            recording.states(),
+            vec![
+                State::AwaitingStart(Phase::Work),
+                State::Running(Phase::Work),
+                State::Paused(Phase::Work),
+                State::Running(Phase::Work),
+                State::Finished(SessionOutcome::Completed),
+                State::AwaitingStart(Phase::Break),
+                State::Running(Phase::Break),
+                State::Finished(SessionOutcome::Skipped),
+                State::AwaitingStart(Phase::Work),
+                State::Running(Phase::Work),
+                State::Finished(SessionOutcome::Interrupted),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transition_pause_delays_each_session() {
+        // Work 0:05-25:05, short break 25:10-30:10, then work from 30:15.
+        let recording = Harness::new(Settings {
+            transition_pause: 5,
+            ..settings()
+        })
+        .press(MINUTE * 31, KeyCode::Char('q'))
+        .run()
+        .await;
+        assert_eq!(
+            recording.sessions(),
+            vec![
+                (SessionType::work(), MINUTE * 25, SessionOutcome::Completed),
+                (
+                    SessionType::short_break(),
+                    MINUTE * 5,
+                    SessionOutcome::Completed
+                ),
+                (
+                    SessionType::work(),
+                    SECOND * 45,
+                    SessionOutcome::Interrupted
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quitting_during_the_transition_pause_ends_the_run() {
+        let recording = Harness::new(Settings {
+            transition_pause: 30,
+            ..settings()
+        })
+        .press(SECOND * 10, KeyCode::Char('q'))
+        .run()
+        .await;
+        assert!(recording.sessions().is_empty());
+        assert_eq!(recording.ended_at, SECOND * 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ticks_report_the_remaining_time() {
+        let recording = Harness::new(Settings {
+            work_duration: 1,
+            ..settings()
+        })
+        .press(SECOND * 5 / 2, KeyCode::Char('q'))
+        .run()
+        .await;
+        let ticks: Vec<Event> = recording
+            .events
+            .into_iter()
+            .filter(|event| matches!(event, Event::Tick { .. }))
+            .collect();
+        assert_eq!(
+            ticks,
+            vec![
+                Event::Tick {
+                    elapsed: SECOND,
+                    remaining: SECOND * 59,
+                },
+                Event::Tick {
+                    elapsed: SECOND * 2,
+                    remaining: SECOND * 58,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+// filepath: src/today.rs
+//! Module counting the pomodoros of the day while a Pomodoro timer runs, for the daily goal and
+//! the status. A work session counts for the day it started on, as in the history, and the count
+//! starts over at local midnight: a run going on past midnight works towards the goal of the new
+//! day.
+use std::sync::{Arc, Mutex};
+
+use chrono::{Local, NaiveDate};
+
+use crate::garden::Plant;
+
+/// The pomodoros completed on a day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Today {
+    day: NaiveDate,
+    pomodoros: u64,
+    /// Work sessions abandoned on the day, before the run.
+    abandoned: u64,
+}
+
+/// The count shared between the scheduler, which counts the pomodoros, and the remote controls.
+pub type SharedToday = Arc<Mutex<Today>>;
+
+impl Today {
+    /// `pomodoros` were completed on `day` so far.
+    pub fn new(day: NaiveDate, pomodoros: u64) -> Self {
+        Today {
+            day,
+            pomodoros,
+            abandoned: 0,
+        }
+    }
+
+    /// `abandoned` work sessions were abandoned on the day so far.
+    pub fn with_abandoned(mut self, abandoned: u64) -> Self {
+        self.abandoned = abandoned;
+        self
+    }
+
+    /// No pomodoro completed yet on the current day.
+    pub fn empty() -> Self {
+        Today::new(Local::now().date_naive(), 0)
+    }
+
+    pub fn into_shared(self) -> SharedToday {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// Moves on to `day` when it comes after the day counted so far.
+    fn roll(&mut self, day: NaiveDate) {
+        if day > self.day {
+            self.day = day;
+            self.pomodoros = 0;
+            self.abandoned = 0;
+        }
+    }
+
+    /// The pomodoros completed on `day`, the current day.
+    pub fn on(&mut self, day: NaiveDate) -> u64 {
+        self.roll(day);
+        self.pomodoros
+    }
+
+    /// The plant of the garden grown on `day`, the current day.
+    pub fn plant(&mut self, day: NaiveDate) -> Plant {
+        self.roll(day);
+        Plant {
+            tomatoes: self.pomodoros,
+            wilted: self.abandoned,
+        }
+    }
+
+    /// Counts a pomodoro started on `started`, unless its day is over already.
+    pub fn count(&mut self, started: NaiveDate) {
+        self.roll(started);
+        if started == self.day {
+            self.pomodoros += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    #[test]
+    fn starts_over_at_midnight() {
+        let mut today = Today::new(day(14), 3);
+        today.count(day(14));
+        assert_eq!(today.on(day(14)), 4);
+        assert_eq!(today.on(day(15)), 0);
+        today.count(day(15));
+        assert_eq!(today.on(day(15)), 1);
+    }
+
+    #[test]
+    fn a_pomodoro_across_midnight_counts_for_the_day_it_started() {
+        let mut today = Today::new(day(14), 3);
+        // Started at 23:50, completed at 00:15.
+        today.count(day(14));
+        assert_eq!(today.on(day(15)), 0);
+        // Completed once the new day was counted already.
+        today.count(day(14));
+        assert_eq!(today.on(day(15)), 0);
+    }
+}
@@ -0,0 +1,288 @@
+// filepath: src/tutorial.rs
+//! Module teaching the keys of a Pomodoro timer application: `rustodoro tutorial` runs a demo cycle
+//! where a second stands for a minute, asks the user to try pause, resume, reset and skip in turn
+//! as the sessions go, and tells at the end which of them were tried.
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    thread,
+    time::Duration,
+};
+
+use rustodoro_client::protocol::SessionKind;
+use tokio::sync::mpsc;
+
+use crate::{
+    AppError, SessionType,
+    command_dispatcher::CommandDispatcher,
+    events::{Event, EventBus, SessionOutcome},
+    output,
+    scheduler::Scheduler,
+    settings::{Settings, Step},
+    terminal_guard,
+    types::Command,
+};
+
+/// Work session and break of the demo cycle, in minutes lasting a second.
+const WORK: u64 = 30;
+const BREAK: u64 = 10;
+
+/// A key to try.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lesson {
+    Pause,
+    Resume,
+    Reset,
+    Skip,
+}
+
+impl Lesson {
+    /// The lessons, in the order they are given.
+    const ALL: [Lesson; 4] = [Lesson::Pause, Lesson::Resume, Lesson::Reset, Lesson::Skip];
+
+    fn name(self) -> &'static str {
+        match self {
+            Lesson::Pause => "pause",
+            Lesson::Resume => "resume",
+            Lesson::Reset => "reset",
+            Lesson::Skip => "skip",
+        }
+    }
+
+    fn instruction(self) -> &'static str {
+        match self {
+            Lesson::Pause => "press [p] or [Space] to pause the countdown",
+            Lesson::Resume => "press [r] or [Space] to resume it",
+            Lesson::Reset => "press [x] to start the countdown of the session over",
+            Lesson::Skip => "press [s] during the break to skip it",
+        }
+    }
+
+    /// Whether `event` shows the key was tried.
+    fn tried(self, event: &Event) -> bool {
+        matches!(
+            (self, event),
+            (Lesson::Pause, Event::Paused { .. })
+                | (Lesson::Resume, Event::Resumed { .. })
+                | (Lesson::Reset, Event::Reset)
+                | (Lesson::Skip, Event::NextBreakSkipped)
+                | (
+                    Lesson::Skip,
+                    Event::SessionEnded {
+                        session: SessionType {
+                            kind: SessionKind::ShortBreak,
+                            ..
+                        } | SessionType {
+                            kind: SessionKind::LongBreak,
+                            ..
+                        },
+                        outcome: SessionOutcome::Skipped,
+                        ..
+                    },
+                )
+        )
+    }
+}
+
+/// The settings of the demo cycle, a work session and a break repeated until the user quits.
+fn settings() -> Settings {
+    Settings {
+        work_duration: WORK,
+        short_break: BREAK,
+        sequence: Some(vec![Step::Work, Step::Short]),
+        minute: Duration::from_secs(1),
+        // A desktop notification every half minute would be more noise than help.
+        no_notify: true,
+        ..Settings::default()
+    }
+}
+
+/// Follows the events of the demo cycle and tells the user what to try next.
+pub struct Coach {
+    tried: Vec<Lesson>,
+}
+
+impl Coach {
+    pub fn new() -> Self {
+        Coach { tried: Vec::new() }
+    }
+
+    /// The first lesson not tried yet, with its step number.
+    fn next(&self) -> Option<(usize, Lesson)> {
+        Lesson::ALL
+            .into_iter()
+            .enumerate()
+            .find(|(_, lesson)| !self.tried.contains(lesson))
+    }
+
+    fn step(&self) -> String {
+        match self.next() {
+            Some((index, lesson)) => format!(
+                "Step {}/{}: {}.",
+                index + 1,
+                Lesson::ALL.len(),
+                lesson.instruction()
+            ),
+            None => "All done! [o] opens the settings menu, [a] stops the ringing when the end of \
+                     the sessions must be acknowledged, and [q] ends the tutorial."
+                .to_string(),
+        }
+    }
+
+    /// The lessons not tried yet.
+    pub fn missing(&self) -> Vec<Lesson> {
+        Lesson::ALL
+            .into_iter()
+            .filter(|lesson| !self.tried.contains(lesson))
+            .collect()
+    }
+
+    /// Returns what to tell the user after `event`, if anything.
+    pub fn handle(&mut self, event: &Event) -> Option<String> {
+        if let Some(lesson) = self
+            .missing()
+            .into_iter()
+            .find(|lesson| lesson.tried(event))
+        {
+            self.tried.push(lesson);
+            return Some(format!("✓ {} tried. {}", lesson.name(), self.step()));
+        }
+        let Event::SessionStarted { session, .. } = event else {
+            return None;
+        };
+        match (self.next(), session.kind) {
+            (Some((_, Lesson::Skip)), SessionKind::Work) => Some(
+                "Step 4/4: let the work session run to its end, the break comes next (or press [s] \
+                 in its last seconds)."
+                    .to_string(),
+            ),
+            (Some((_, Lesson::Skip)), _) | (Some(_), SessionKind::Work) => Some(self.step()),
+            _ => None,
+        }
+    }
+
+    /// Handles the events until the event bus is dropped, returning the lessons not tried.
+    pub fn run(mut self, events: Receiver<Event>) -> Vec<Lesson> {
+        for event in events {
+            if let Some(message) = self.handle(&event) {
+                output::line(format!("\n{}", message));
+            }
+        }
+        self.missing()
+    }
+}
+
+fn run() -> Result<Vec<Lesson>, AppError> {
+    println!(
+        "Tutorial: a second stands for a minute, so the work session lasts {} seconds and the \
+         break {}. Follow the steps, [q] quits at any time.\n",
+        WORK, BREAK
+    );
+    let settings = settings().into_shared();
+    let (tx, rx) = mpsc::unbounded_channel::<Command>();
+    let events = Arc::new(EventBus::new());
+    let coach_events = events.subscribe();
+    let coach_thread = thread::spawn(move || Coach::new().run(coach_events));
+    let dispatcher_settings = Arc::clone(&settings);
+    let dispatcher_events = Arc::clone(&events);
+    let command_dispatcher_thread = thread::spawn(move || {
+        CommandDispatcher::new(tx, dispatcher_settings, dispatcher_events).run()
+    });
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+    let mut scheduler = Scheduler::new(rx, events, settings)?;
+    runtime.block_on(scheduler.run())?;
+    // Dropping the scheduler stops the command dispatcher, even if the run ends on its own, and
+    // lets the coach finish.
+    drop(scheduler);
+    command_dispatcher_thread
+        .join()
+        .map_err(|_| AppError::ThreadPanic)??;
+    let missing = coach_thread.join().map_err(|_| AppError::ThreadPanic)?;
+    Ok(missing)
+}
+
+/// Runs the `tutorial` subcommand and returns the process exit code.
+pub fn run_command() -> i32 {
+    match run() {
+        Ok(missing) if missing.is_empty() => {
+            println!("\nTutorial completed: pause, resume, reset and skip were all tried.");
+            0
+        }
+        Ok(missing) => {
+            let names: Vec<&str> = missing.iter().map(|lesson| lesson.name()).collect();
+            println!(
+                "\nNot tried yet: {}. Run `rustodoro tutorial` again to practise.",
+                names.join(", ")
+            );
+            0
+        }
+        Err(e) => {
+            // The command dispatcher may still hold the terminal in raw mode.
+            terminal_guard::restore();
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+
+    use super::*;
+    use crate::testing::Harness;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn gives_the_lessons_in_turn() {
+        let mut coach = Coach::new();
+        let work = Event::SessionStarted {
+            session: SessionType::work(),
+            cycle: 1,
+            duration: SECOND * 30,
+        };
+        assert_eq!(
+            coach.handle(&work).unwrap(),
+            "Step 1/4: press [p] or [Space] to pause the countdown."
+        );
+        assert_eq!(
+            coach.handle(&Event::Reset).unwrap(),
+            "✓ reset tried. Step 1/4: press [p] or [Space] to pause the countdown."
+        );
+        assert_eq!(
+            coach.handle(&Event::Paused { elapsed: SECOND }).unwrap(),
+            "✓ pause tried. Step 2/4: press [r] or [Space] to resume it."
+        );
+        let resumed = Event::Resumed {
+            elapsed: SECOND,
+            paused: SECOND,
+        };
+        assert!(coach.handle(&resumed).unwrap().contains("Step 4/4"));
+        assert_eq!(coach.handle(&Event::Reset), None);
+        assert_eq!(coach.missing(), [Lesson::Skip]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_second_stands_for_a_minute() {
+        let recording = Harness::new(settings())
+            .press(SECOND * 5, KeyCode::Char('p'))
+            .press(SECOND * 8, KeyCode::Char('r'))
+            .press(SECOND * 10, KeyCode::Char('x'))
+            // The countdown started over, the break starts 30 seconds later.
+            .press(SECOND * 42, KeyCode::Char('s'))
+            .press(SECOND * 45, KeyCode::Char('q'))
+            .run()
+            .await;
+        let sessions = recording.sessions();
+        assert_eq!(sessions[0].1, SECOND * 30);
+        assert_eq!(sessions[1].2, SessionOutcome::Skipped);
+        let mut coach = Coach::new();
+        for event in &recording.events {
+            coach.handle(event);
+        }
+        assert_eq!(coach.missing(), []);
+    }
+}
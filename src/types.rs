@@ -1,36 +1,220 @@
 // filepath: src/types.rs
 //! Module defining types and errors for a Pomodoro timer application.
-use std::{fmt, sync::mpsc};
+use std::{fmt, io, num::NonZeroUsize, time::Duration};
 
-#[derive(Debug, Clone)]
+use rustodoro_client::protocol::SessionKind;
+use tokio::sync::mpsc;
+
+use crate::{
+    audio::AudioError,
+    config::ConfigError,
+    credentials::CredentialError,
+    history::{HistoryError, PauseReason},
+    journal::JournalError,
+    render::Color,
+    run_queue::RunQueueError,
+};
+
+/// What the timer is told to do, by the keyboard, the command line, the control socket, the HTTP
+/// endpoint and the plugins alike. As JSON, commands without payload are their names, e.g.
+/// `"skip"`, and the others objects, e.g. `{"extend":300}` in seconds or `{"jump":3}`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Command {
+    /// Stops the sound ringing until acknowledged.
+    Acknowledge,
     Pause,
     PauseResume,
     Reset,
     Resume,
     Skip,
+    /// Skips as many breaks, from the running one or else the upcoming one.
+    SkipBreaks(u32),
+    /// Lengthens the running session.
+    Extend(#[serde(with = "seconds")] Duration),
+    /// Labels the work sessions with another task, from the running one on.
+    SetTask(String),
+    /// Leaves the running session for another session of the set.
+    Jump(SessionIndex),
+    /// Shows the time left for a few seconds in the zen display.
+    Reveal,
+    /// Tells why the timer is paused.
+    PauseReason(PauseReason),
+    /// Tells the one thing of the running work session.
+    Intent(String),
 }
 
-pub enum SessionType {
-    Work(&'static str),
-    ShortBreak(&'static str),
-    LongBreak(&'static str),
+/// Durations as whole seconds.
+mod seconds {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
 }
 
-impl fmt::Display for SessionType {
+/// Position of a session in the sequence of a set, from 1, e.g. 2 for the first break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct SessionIndex(NonZeroUsize);
+
+impl SessionIndex {
+    pub fn new(position: usize) -> Option<Self> {
+        NonZeroUsize::new(position).map(SessionIndex)
+    }
+
+    /// The step of the sequence, from 0.
+    pub fn step(self) -> usize {
+        self.0.get() - 1
+    }
+}
+
+impl From<NonZeroUsize> for SessionIndex {
+    fn from(position: NonZeroUsize) -> Self {
+        SessionIndex(position)
+    }
+}
+
+impl fmt::Display for SessionIndex {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SessionType::Work(msg) => write!(f, "{}", msg),
-            SessionType::ShortBreak(msg) => write!(f, "{}", msg),
-            SessionType::LongBreak(msg) => write!(f, "{}", msg),
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A session: what it counts as, how it is shown, and how long it lasts when it is a custom one,
+/// e.g. a review counting as work.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionType {
+    /// What the session counts as, in the history, the statistics and for the remote controls.
+    pub kind: SessionKind,
+    /// How the banner and the progress bar name the session, e.g. "Work session".
+    pub label: String,
+    /// Color of the progress bar.
+    pub color: Color,
+    /// Minutes the session lasts, the built-in sessions taking theirs from the settings.
+    pub minutes: Option<u64>,
+}
+
+impl SessionType {
+    pub fn work() -> Self {
+        SessionType::from(SessionKind::Work)
+    }
+
+    pub fn short_break() -> Self {
+        SessionType::from(SessionKind::ShortBreak)
+    }
+
+    pub fn long_break() -> Self {
+        SessionType::from(SessionKind::LongBreak)
+    }
+
+    /// A custom session, counting as `kind`.
+    pub fn custom(kind: SessionKind, label: &str, color: Color, minutes: u64) -> Self {
+        SessionType {
+            kind,
+            label: label.to_string(),
+            color,
+            minutes: Some(minutes),
         }
     }
+
+    pub fn is_work(&self) -> bool {
+        self.kind == SessionKind::Work
+    }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+/// The built-in session of `kind`.
+impl From<SessionKind> for SessionType {
+    fn from(kind: SessionKind) -> Self {
+        let label = match kind {
+            SessionKind::Work => "Work session",
+            SessionKind::ShortBreak => "Short break",
+            SessionKind::LongBreak => "Long break",
+        };
+        SessionType {
+            kind,
+            label: label.to_string(),
+            color: Color::default(),
+            minutes: None,
+        }
+    }
+}
+
+impl fmt::Display for SessionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
 pub enum AppError {
-    ChannelSend(mpsc::SendError<Command>),
-    ChannelRecv(mpsc::RecvError),
-    ChannelRecvTimeout(mpsc::RecvTimeoutError),
+    #[error("cannot open the audio output: {0}")]
+    AudioInit(#[from] AudioError),
+    #[error("cannot show the desktop notification: {0}")]
+    Notification(#[from] crate::desktop::Error),
+    #[error("terminal I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid progress bar template: {0}")]
+    Template(#[from] indicatif::style::TemplateError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Journal(#[from] JournalError),
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    Credential(#[from] CredentialError),
+    #[error(transparent)]
+    RunQueue(#[from] RunQueueError),
+    #[error("the timer stopped listening to commands")]
+    ChannelSend(#[from] mpsc::error::SendError<Command>),
+    #[error("the command dispatcher stopped")]
+    Disconnected,
+    #[error("a worker thread panicked")]
+    ThreadPanic,
+}
+
+impl AppError {
+    /// Suggests how the user can fix the error, if there is anything to do.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AppError::AudioInit(AudioError::NotFound(_)) => Some(
+                "run `rustodoro audio list` to see the devices, or leave --audio-device out for the default one",
+            ),
+            #[cfg(not(feature = "audio"))]
+            AppError::AudioInit(AudioError::Unsupported) => {
+                Some("run with --no-sound, or build rustodoro with the audio feature")
+            }
+            AppError::AudioInit(_) => {
+                Some("check that an audio output device is available, or run with --no-sound")
+            }
+            AppError::Notification(_) => Some(
+                "make sure a notification daemon is running (e.g. the one of your desktop, dunst or mako)",
+            ),
+            AppError::Io(_) => Some("make sure rustodoro runs in an interactive terminal"),
+            AppError::ThreadPanic => Some(
+                "this is a bug, please report it at https://github.com/jclegras/pomodoro/issues",
+            ),
+            _ => None,
+        }
+    }
+
+    /// Whether the error only means the user quit, stopping the command dispatcher.
+    pub fn is_quit(&self) -> bool {
+        matches!(self, AppError::Disconnected)
+    }
+
+    /// The process exit code reporting this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Config(_) => 2,
+            _ => 1,
+        }
+    }
 }
@@ -0,0 +1,166 @@
+// filepath: src/wait.rs
+//! Module blocking until a Pomodoro timer running in another process reaches a state, for the
+//! scripts reacting to it: `rustodoro wait --for break && xdg-open stretching.mp4`.
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use rustodoro_client::{Client, ClientError, SessionKind, Snapshot};
+
+use crate::{config::Cli, control};
+
+/// Exit code when the timeout elapsed first.
+pub const TIMED_OUT: i32 = 2;
+
+/// What to wait for.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Target {
+    /// The start of the next break, short or long.
+    Break,
+    /// The start of the next work session.
+    Work,
+    /// The timer stopping.
+    End,
+}
+
+/// Follows the snapshots of the timer to tell when the target is reached.
+struct Waiter {
+    target: Target,
+    /// Session of the last snapshot, by kind and cycle.
+    session: Option<(SessionKind, u64)>,
+}
+
+impl Waiter {
+    /// Starts waiting from `first`, the session running then not counting.
+    fn new(target: Target, first: &Snapshot) -> Self {
+        Waiter {
+            target,
+            session: first
+                .session
+                .as_ref()
+                .map(|session| (session.kind, session.cycle)),
+        }
+    }
+
+    /// Takes `snapshot` into account, and returns whether it starts the awaited session.
+    fn update(&mut self, snapshot: &Snapshot) -> bool {
+        let session = snapshot
+            .session
+            .as_ref()
+            .map(|session| (session.kind, session.cycle));
+        let started = session.filter(|_| session != self.session);
+        self.session = session;
+        let Some((kind, _)) = started else {
+            return false;
+        };
+        match self.target {
+            Target::Work => kind == SessionKind::Work,
+            Target::Break => kind != SessionKind::Work,
+            Target::End => false,
+        }
+    }
+}
+
+enum Outcome {
+    Reached,
+    TimedOut,
+}
+
+fn wait(cli: &Cli, target: Target, timeout: Option<Duration>) -> Result<Outcome, ClientError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut watch = Client::new(control::socket_path(cli)).watch()?;
+    let first = watch.next().transpose()?.unwrap_or_default();
+    let mut waiter = Waiter::new(target, &first);
+    loop {
+        if let Some(deadline) = deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(Outcome::TimedOut);
+            }
+            watch.set_timeout(Some(left))?;
+        }
+        match watch.next() {
+            // The connection closes when the timer stops.
+            None if target == Target::End => return Ok(Outcome::Reached),
+            None => return Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into())),
+            Some(Err(ClientError::Io(e)))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(Outcome::TimedOut);
+            }
+            Some(snapshot) => {
+                if waiter.update(&snapshot?) {
+                    return Ok(Outcome::Reached);
+                }
+            }
+        }
+    }
+}
+
+/// Runs the `wait` subcommand and returns the process exit code: 0 once the target is reached,
+/// [`TIMED_OUT`] when the timeout elapsed first, 1 on an error.
+pub fn run_command(cli: &Cli, target: Target, timeout: Option<u64>) -> i32 {
+    match wait(cli, target, timeout.map(Duration::from_secs)) {
+        Ok(Outcome::Reached) => 0,
+        Ok(Outcome::TimedOut) => {
+            eprintln!("error: timed out waiting for the timer");
+            TIMED_OUT
+        }
+        Err(ClientError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            eprintln!("error: the timer stopped first");
+            1
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustodoro_client::Session;
+
+    use super::*;
+
+    fn snapshot(kind: SessionKind, cycle: u64) -> Snapshot {
+        Snapshot {
+            session: Some(Session {
+                kind,
+                cycle,
+                cycles: 4,
+                duration_secs: 1500,
+                remaining_secs: 1500,
+                paused: false,
+                plan_secs: None,
+            }),
+            ..Snapshot::default()
+        }
+    }
+
+    #[test]
+    fn waits_for_the_next_session_of_a_kind() {
+        let work = snapshot(SessionKind::Work, 1);
+        let mut waiter = Waiter::new(Target::Work, &work);
+        // The session running when waiting starts does not count.
+        assert!(!waiter.update(&work));
+        assert!(!waiter.update(&Snapshot::default()));
+        assert!(!waiter.update(&snapshot(SessionKind::ShortBreak, 1)));
+        assert!(waiter.update(&snapshot(SessionKind::Work, 2)));
+
+        let mut waiter = Waiter::new(Target::Break, &Snapshot::default());
+        assert!(!waiter.update(&work));
+        assert!(waiter.update(&snapshot(SessionKind::LongBreak, 4)));
+    }
+
+    #[test]
+    fn the_end_is_not_a_session() {
+        let mut waiter = Waiter::new(Target::End, &Snapshot::default());
+        assert!(!waiter.update(&snapshot(SessionKind::Work, 1)));
+        assert!(!waiter.update(&snapshot(SessionKind::ShortBreak, 1)));
+    }
+}
@@ -0,0 +1,181 @@
+// filepath: src/wasm.rs
+//! Module loading the WASM plugins of a Pomodoro timer application. A WASM plugin is a WASI
+//! module described by a manifest in the plugin directory, and is run by the `wasmtime` runtime
+//! in its sandbox: it only sees the directories its manifest grants, has no network access, and
+//! does only what its capabilities allow.
+//!
+//! The module speaks the protocol of the other plugins. Its host API is the WASI standard input,
+//! where each event line is an `on_event` call, and its standard output, where each command line
+//! is a `suggest_command`, so the same module runs on any system the runtime supports.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::plugins::{Capabilities, PLUGIN_PROTOCOL_VERSION, Plugin, PluginError};
+
+/// The runtime running the modules, looked up in `PATH`.
+pub const RUNTIME: &str = "wasmtime";
+
+/// A manifest, e.g. `plugins/stretch.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// The module, relative to the manifest.
+    module: PathBuf,
+    #[serde(default)]
+    capabilities: Grants,
+}
+
+/// What the manifest allows the module to do, nothing by default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Grants {
+    /// Whether the commands of the module are run.
+    #[serde(default)]
+    commands: bool,
+    /// Whether the module receives the tick events.
+    #[serde(default)]
+    ticks: bool,
+    /// Absolute paths of the directories the module can read and write, under the same paths.
+    #[serde(default)]
+    dirs: Vec<PathBuf>,
+}
+
+/// The plugin described by the manifest at `path`.
+pub fn load(path: &Path) -> Result<Plugin, PluginError> {
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let invalid = |message: String| PluginError::Manifest {
+        name: name.clone(),
+        message,
+    };
+    let content = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    let manifest: Manifest =
+        toml::from_str(&content).map_err(|e| invalid(e.message().to_string()))?;
+    let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let module = dir.join(&manifest.module);
+    if module
+        .extension()
+        .is_none_or(|extension| extension != "wasm")
+    {
+        return Err(invalid(format!(
+            "the module {} is not a .wasm file",
+            manifest.module.display()
+        )));
+    }
+    if !module.is_file() {
+        return Err(invalid(format!(
+            "the module {} does not exist",
+            module.display()
+        )));
+    }
+    let mut args = vec![
+        "run".to_string(),
+        format!(
+            "--env=RUSTODORO_PLUGIN_PROTOCOL={}",
+            PLUGIN_PROTOCOL_VERSION
+        ),
+    ];
+    for granted in &manifest.capabilities.dirs {
+        if !granted.is_absolute() || !granted.is_dir() {
+            return Err(invalid(format!(
+                "{} is not an absolute path to a directory",
+                granted.display()
+            )));
+        }
+        args.push(format!("--dir={}", granted.display()));
+    }
+    args.push(module.display().to_string());
+    Ok(Plugin {
+        name,
+        program: PathBuf::from(RUNTIME),
+        args,
+        dir,
+        capabilities: Capabilities {
+            commands: manifest.capabilities.commands,
+            ticks: manifest.capabilities.ticks,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn loads_the_manifests() {
+        let dir = env::temp_dir().join(format!("rustodoro-wasm-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stretch.wasm"), b"\0asm").unwrap();
+        let manifest = |name: &str, content: &str| {
+            let path = dir.join(format!("{}.toml", name));
+            fs::write(&path, content).unwrap();
+            load(&path)
+        };
+
+        let plugin = manifest(
+            "stretch",
+            &format!(
+                "module = \"stretch.wasm\"\n[capabilities]\ncommands = true\ndirs = [\"{}\"]\n",
+                dir.display()
+            ),
+        )
+        .unwrap();
+        assert_eq!(plugin.name, "stretch");
+        assert_eq!(plugin.program, PathBuf::from("wasmtime"));
+        assert_eq!(
+            plugin.args,
+            [
+                "run".to_string(),
+                "--env=RUSTODORO_PLUGIN_PROTOCOL=1".to_string(),
+                format!("--dir={}", dir.display()),
+                dir.join("stretch.wasm").display().to_string(),
+            ]
+        );
+        assert_eq!(
+            plugin.capabilities,
+            Capabilities {
+                commands: true,
+                ticks: false
+            }
+        );
+
+        let errors: Vec<String> = [
+            manifest("missing", "module = \"missing.wasm\"\n"),
+            manifest("native", "module = \"native.so\"\n"),
+            manifest(
+                "relative",
+                "module = \"stretch.wasm\"\n[capabilities]\ndirs = [\"notes\"]\n",
+            ),
+            manifest("network", "module = \"stretch.wasm\"\nnetwork = true\n"),
+        ]
+        .into_iter()
+        .map(|result| result.unwrap_err().to_string())
+        .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            errors[0],
+            format!(
+                "invalid manifest for plugin missing: the module {} does not exist",
+                dir.join("missing.wasm").display()
+            )
+        );
+        assert_eq!(
+            errors[1],
+            "invalid manifest for plugin native: the module native.so is not a .wasm file"
+        );
+        assert_eq!(
+            errors[2],
+            "invalid manifest for plugin relative: notes is not an absolute path to a directory"
+        );
+        assert!(
+            errors[3].starts_with("invalid manifest for plugin network: unknown field `network`")
+        );
+    }
+}
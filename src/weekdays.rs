@@ -0,0 +1,177 @@
+// filepath: src/weekdays.rs
+//! Module following the days of the week while a Pomodoro timer runs, for the `[weekdays]` tables
+//! of the configuration file: from midnight on, the sessions follow the profile of the new day,
+//! e.g. shorter sessions on Fridays or no long break on Tuesdays.
+use std::{path::PathBuf, thread, time::Duration};
+
+use chrono::{Datelike, Local, Weekday};
+
+use crate::{
+    config::{self, Cli, ConfigError, WEEK},
+    pace::TimeOfDay,
+    settings::{Settings, SharedSettings, Step},
+};
+
+/// How often the date is looked at.
+const CHECK_EVERY: Duration = Duration::from_secs(30);
+
+/// The settings a profile of the configuration file sets.
+#[derive(Debug, Clone, PartialEq)]
+struct Profile {
+    work_duration: u64,
+    short_break: u64,
+    long_break: u64,
+    cycles: u64,
+    sequence: Option<Vec<Step>>,
+    daily_goal: u64,
+    workday_start: TimeOfDay,
+    workday_end: TimeOfDay,
+}
+
+impl Profile {
+    fn of(settings: &Settings) -> Self {
+        Profile {
+            work_duration: settings.work_duration,
+            short_break: settings.short_break,
+            long_break: settings.long_break,
+            cycles: settings.cycles,
+            sequence: settings.sequence.clone(),
+            daily_goal: settings.daily_goal,
+            workday_start: settings.workday_start,
+            workday_end: settings.workday_end,
+        }
+    }
+
+    /// Switches `settings` from the `previous` profile to this one. Only what differs between the
+    /// two is changed: a setting adjusted from the settings menu is kept otherwise.
+    fn apply(&self, previous: &Profile, settings: &mut Settings) {
+        if self.work_duration != previous.work_duration {
+            settings.work_duration = self.work_duration;
+        }
+        if self.short_break != previous.short_break {
+            settings.short_break = self.short_break;
+        }
+        if self.long_break != previous.long_break {
+            settings.long_break = self.long_break;
+        }
+        if self.cycles != previous.cycles {
+            settings.cycles = self.cycles;
+        }
+        if self.sequence != previous.sequence {
+            settings.sequence = self.sequence.clone();
+        }
+        if self.daily_goal != previous.daily_goal {
+            settings.daily_goal = self.daily_goal;
+        }
+        if self.workday_start != previous.workday_start {
+            settings.workday_start = self.workday_start;
+        }
+        if self.workday_end != previous.workday_end {
+            settings.workday_end = self.workday_end;
+        }
+    }
+}
+
+/// The profile of each day of the week, Monday first.
+pub struct Week {
+    profiles: Vec<Profile>,
+}
+
+impl Week {
+    /// Resolves the configuration for each day of the week, from the configuration file `file`.
+    /// Returns none when every day has the same profile: there is nothing to follow then.
+    pub fn resolve(cli: &Cli, file: Option<(PathBuf, bool)>) -> Result<Option<Self>, ConfigError> {
+        let profiles = WEEK
+            .iter()
+            .map(|day| {
+                config::resolve_day(cli, file.clone(), *day)
+                    .map(|effective| Profile::of(&effective.settings()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if profiles.iter().all(|profile| *profile == profiles[0]) {
+            return Ok(None);
+        }
+        Ok(Some(Week { profiles }))
+    }
+
+    fn profile(&self, day: Weekday) -> &Profile {
+        &self.profiles[day.num_days_from_monday() as usize]
+    }
+
+    /// Switches `settings` from the profile of `yesterday` to the one of `today`.
+    fn roll(&self, yesterday: Weekday, today: Weekday, settings: &mut Settings) {
+        self.profile(today).apply(self.profile(yesterday), settings);
+    }
+
+    /// Follows the days on a thread of its own, switching `settings` to the profile of each day at
+    /// its midnight. The session running then keeps its length, the next ones follow the new day.
+    pub fn follow(self, settings: SharedSettings) {
+        thread::spawn(move || {
+            let mut day = Local::now().date_naive();
+            loop {
+                thread::sleep(CHECK_EVERY);
+                let today = Local::now().date_naive();
+                if today != day {
+                    self.roll(
+                        day.weekday(),
+                        today.weekday(),
+                        &mut settings.lock().unwrap(),
+                    );
+                    day = today;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn week() -> Week {
+        let weekday = Profile::of(&Settings::default());
+        let friday = Profile {
+            work_duration: 20,
+            daily_goal: 6,
+            workday_end: TimeOfDay::new(15, 0),
+            ..weekday.clone()
+        };
+        let tuesday = Profile {
+            long_break: 5,
+            ..weekday.clone()
+        };
+        let mut profiles = vec![weekday; 7];
+        profiles[1] = tuesday;
+        profiles[4] = friday;
+        Week { profiles }
+    }
+
+    #[test]
+    fn switches_to_the_profile_of_the_new_day() {
+        let week = week();
+        let mut settings = Settings::default();
+        week.roll(Weekday::Thu, Weekday::Fri, &mut settings);
+        assert_eq!(settings.work_duration, 20);
+        assert_eq!(settings.daily_goal, 6);
+        assert_eq!(settings.workday_end, TimeOfDay::new(15, 0));
+        assert_eq!(settings.long_break, 15);
+        week.roll(Weekday::Fri, Weekday::Sat, &mut settings);
+        assert_eq!(Profile::of(&settings), Profile::of(&Settings::default()));
+        week.roll(Weekday::Mon, Weekday::Tue, &mut settings);
+        assert_eq!(settings.long_break, 5);
+    }
+
+    #[test]
+    fn keeps_what_the_menu_changed() {
+        let week = week();
+        let mut settings = Settings {
+            short_break: 10,
+            work_duration: 30,
+            ..Settings::default()
+        };
+        week.roll(Weekday::Thu, Weekday::Fri, &mut settings);
+        // The short breaks are the same on Thursdays and Fridays, the work sessions are not.
+        assert_eq!(settings.short_break, 10);
+        assert_eq!(settings.work_duration, 20);
+    }
+}
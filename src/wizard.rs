@@ -0,0 +1,157 @@
+// filepath: src/wizard.rs
+//! Module setting a Pomodoro timer application up on its first run: without a configuration file,
+//! a few questions about the sessions, the sounds, the notifications and the daily goal are asked
+//! in the terminal, and the configuration file is written from the answers. Cancelling writes the
+//! defaults, so that the questions are only asked once.
+use std::io::{self, IsTerminal};
+
+use crate::{
+    AppError,
+    config::{self, Cli},
+    prompt,
+    settings::{MAX_CYCLES, MAX_DAILY_GOAL, MAX_DURATION_MINUTES, Settings},
+};
+
+/// The answers to the questions of the wizard.
+#[derive(Debug, Clone, PartialEq)]
+struct Answers {
+    work: u64,
+    short_break: u64,
+    long_break: u64,
+    cycles: u64,
+    sound: bool,
+    notify: bool,
+    daily_goal: u64,
+}
+
+impl Answers {
+    fn defaults() -> Self {
+        let defaults = Settings::default();
+        Answers {
+            work: defaults.work_duration,
+            short_break: defaults.short_break,
+            long_break: defaults.long_break,
+            cycles: defaults.cycles,
+            sound: !defaults.no_sound,
+            notify: !defaults.no_notify,
+            daily_goal: defaults.daily_goal,
+        }
+    }
+
+    /// The settings of the configuration file the answers give.
+    fn values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("work", self.work.to_string()),
+            ("short_break", self.short_break.to_string()),
+            ("long_break", self.long_break.to_string()),
+            ("cycles", self.cycles.to_string()),
+            ("no_sound", (!self.sound).to_string()),
+            ("no_notify", (!self.notify).to_string()),
+            ("daily_goal", self.daily_goal.to_string()),
+        ]
+    }
+}
+
+/// Asks the questions, returning none when the user cancels.
+fn ask() -> io::Result<Option<Answers>> {
+    let defaults = Answers::defaults();
+    let durations = 1..=MAX_DURATION_MINUTES;
+    let Some(work) = prompt::number("Work session, in minutes", defaults.work, durations.clone())?
+    else {
+        return Ok(None);
+    };
+    let Some(short_break) = prompt::number(
+        "Short break, in minutes",
+        defaults.short_break,
+        durations.clone(),
+    )?
+    else {
+        return Ok(None);
+    };
+    let Some(long_break) =
+        prompt::number("Long break, in minutes", defaults.long_break, durations)?
+    else {
+        return Ok(None);
+    };
+    let Some(cycles) = prompt::number(
+        "Work sessions before a long break",
+        defaults.cycles,
+        1..=MAX_CYCLES,
+    )?
+    else {
+        return Ok(None);
+    };
+    let Some(sound) = prompt::yes_no("Ring at the end of the sessions?", defaults.sound)? else {
+        return Ok(None);
+    };
+    let Some(notify) = prompt::yes_no("Show desktop notifications?", defaults.notify)? else {
+        return Ok(None);
+    };
+    let Some(daily_goal) = prompt::number(
+        "Pomodoros to complete in a day",
+        defaults.daily_goal,
+        1..=MAX_DAILY_GOAL,
+    )?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(Answers {
+        work,
+        short_break,
+        long_break,
+        cycles,
+        sound,
+        notify,
+        daily_goal,
+    }))
+}
+
+/// Offers the wizard when there is no configuration file at its default location and the timer
+/// runs in a terminal. A configuration file given with `--config` or `RUSTODORO_CONFIG` is never
+/// created this way.
+pub fn offer(cli: &Cli) -> Result<(), AppError> {
+    let Some((path, false)) = config::config_path(cli) else {
+        return Ok(());
+    };
+    if path.exists() || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Ok(());
+    }
+    println!("No configuration file yet, let's set rustodoro up (Esc keeps the defaults).");
+    let answers = ask()?;
+    config::init_with(
+        &path,
+        false,
+        &answers.clone().unwrap_or_else(Answers::defaults).values(),
+    )?;
+    match answers {
+        Some(_) => println!("Configuration file written to {}.", path.display()),
+        None => println!("Defaults written to {}.", path.display()),
+    }
+    println!("Change it any time with `rustodoro config edit`.\n");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_answers_fill_the_configuration_file() {
+        let answers = Answers {
+            work: 50,
+            sound: false,
+            daily_goal: 6,
+            ..Answers::defaults()
+        };
+        let content = config::default_file_with(&answers.values());
+        let table: toml::Table = toml::from_str(&content).unwrap();
+        assert_eq!(table["work"].as_integer(), Some(50));
+        assert_eq!(table["short_break"].as_integer(), Some(5));
+        assert_eq!(table["no_sound"].as_bool(), Some(true));
+        assert_eq!(table["no_notify"].as_bool(), Some(false));
+        assert_eq!(table["daily_goal"].as_integer(), Some(6));
+        // The comments are kept, the settings left out too.
+        assert!(content.contains("# Work session duration in minutes"));
+        assert!(content.contains("\ntheme = \"emoji\"\n"));
+    }
+}